@@ -0,0 +1,232 @@
+use std::time::{Duration, Instant};
+
+// Onsets below this ratio to the rolling bass average are beats, not accents
+const STRONG_ONSET: f32 = 1.8;
+const MAX_ONSET: f32 = 3.5;
+const MIN_INTENSITY: f32 = 0.35;
+const RING_WIDTH: f32 = 4.0;
+const MAX_GLITCH_BANDS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccentKind {
+    Flash = 1,
+    Shockwave = 2,
+    Glitch = 3,
+}
+
+impl AccentKind {
+    // 0 turns the accent layer off
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Flash),
+            2 => Some(Self::Shockwave),
+            3 => Some(Self::Glitch),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flash => "flash",
+            Self::Shockwave => "shockwave",
+            Self::Glitch => "glitch",
+        }
+    }
+
+    fn duration(self) -> Duration {
+        match self {
+            Self::Flash => Duration::from_millis(150),
+            Self::Shockwave => Duration::from_millis(450),
+            Self::Glitch => Duration::from_millis(250),
+        }
+    }
+
+    // Minimum time between two accents so a busy drop doesn't turn into a strobe
+    fn cooldown(self) -> Duration {
+        match self {
+            Self::Flash => Duration::from_millis(400),
+            Self::Shockwave => Duration::from_millis(700),
+            Self::Glitch => Duration::from_millis(500),
+        }
+    }
+}
+
+// One-shot overlay fired by strong onsets, drawn over whatever the effect rendered
+pub struct AccentLayer {
+    kind: AccentKind,
+    // Venue cap on flash rate, over the kind's own cooldown
+    min_interval: Duration,
+    last_trigger: Option<Instant>,
+    // Start and intensity of the accent being drawn
+    active: Option<(Instant, f32)>,
+    seed: u32,
+}
+
+impl AccentLayer {
+    pub fn new(kind: AccentKind) -> Self {
+        Self {
+            kind,
+            min_interval: Duration::ZERO,
+            last_trigger: None,
+            active: None,
+            seed: 0x9E37_79B9,
+        }
+    }
+
+    pub fn kind(&self) -> AccentKind {
+        self.kind
+    }
+
+    pub fn set_min_interval(&mut self, min_interval: Duration) {
+        self.min_interval = min_interval;
+    }
+
+    // `strength` is the onset's bass level over the rolling average
+    pub fn trigger(&mut self, strength: f32, now: Instant) -> bool {
+        let cooldown = self.kind.cooldown().max(self.min_interval);
+        let cooled_down = self
+            .last_trigger
+            .is_none_or(|last| now.saturating_duration_since(last) >= cooldown);
+        if strength < STRONG_ONSET || !cooled_down {
+            return false;
+        }
+
+        let scale = ((strength - STRONG_ONSET) / (MAX_ONSET - STRONG_ONSET)).clamp(0.0, 1.0);
+        self.last_trigger = Some(now);
+        self.active = Some((now, MIN_INTENSITY + (1.0 - MIN_INTENSITY) * scale));
+        true
+    }
+
+    pub fn draw(&mut self, frame: &mut [u8], width: usize, height: usize, now: Instant) {
+        let Some((started, intensity)) = self.active else {
+            return;
+        };
+        let progress = now.saturating_duration_since(started).as_secs_f32()
+            / self.kind.duration().as_secs_f32();
+        if progress >= 1.0 {
+            self.active = None;
+            return;
+        }
+
+        match self.kind {
+            AccentKind::Flash => draw_flash(frame, intensity * (1.0 - progress)),
+            AccentKind::Shockwave => draw_ring(frame, width, height, progress, intensity),
+            AccentKind::Glitch => {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 17;
+                self.seed ^= self.seed << 5;
+                draw_glitch(frame, width, height, self.seed, intensity);
+            }
+        }
+    }
+}
+
+fn draw_flash(frame: &mut [u8], amount: f32) {
+    for value in frame.iter_mut() {
+        *value = (*value as f32 + (255.0 - *value as f32) * amount) as u8;
+    }
+}
+
+// White ring expanding from the center to the corners
+fn draw_ring(frame: &mut [u8], width: usize, height: usize, progress: f32, intensity: f32) {
+    let center = (width as f32 / 2.0, height as f32 / 2.0);
+    let radius = progress * center.0.hypot(center.1);
+    let peak = 255.0 * intensity * (1.0 - progress);
+
+    for y in 0..height {
+        for x in 0..width {
+            let distance = (x as f32 - center.0).hypot(y as f32 - center.1);
+            let falloff = 1.0 - (distance - radius).abs() / RING_WIDTH;
+            if falloff <= 0.0 {
+                continue;
+            }
+            let level = (peak * falloff) as u8;
+            let idx = (y * width + x) * 3;
+            if let Some(pixel) = frame.get_mut(idx..idx + 3) {
+                for value in pixel.iter_mut() {
+                    *value = (*value).max(level);
+                }
+            }
+        }
+    }
+}
+
+// Horizontal bands shifted sideways with their color channels rotated; a new pattern every frame
+fn draw_glitch(frame: &mut [u8], width: usize, height: usize, seed: u32, intensity: f32) {
+    let mut random = seed;
+    let mut next = |range: usize| {
+        random = random.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (random >> 16) as usize % range.max(1)
+    };
+
+    let bands = 1 + (MAX_GLITCH_BANDS as f32 * intensity) as usize;
+    for _ in 0..bands {
+        let top = next(height);
+        let rows = 2 + next(7);
+        let shift = 1 + next(24) % width.max(1);
+        for y in top..(top + rows).min(height) {
+            let Some(row) = frame.get_mut(y * width * 3..(y + 1) * width * 3) else {
+                break;
+            };
+            row.rotate_right(shift * 3);
+            for pixel in row.chunks_exact_mut(3) {
+                pixel.rotate_left(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strong_onsets_respect_cooldown() {
+        let mut layer = AccentLayer::new(AccentKind::Flash);
+        let start = Instant::now();
+
+        assert!(!layer.trigger(1.5, start));
+        assert!(layer.trigger(2.0, start));
+        assert!(!layer.trigger(5.0, start + Duration::from_millis(200)));
+        assert!(layer.trigger(5.0, start + Duration::from_millis(450)));
+        assert_eq!(layer.active.unwrap().1, 1.0);
+
+        // A 2 Hz venue cap holds the next one back past the flash's own cooldown
+        layer.set_min_interval(Duration::from_millis(500));
+        assert!(!layer.trigger(5.0, start + Duration::from_millis(900)));
+        assert!(layer.trigger(5.0, start + Duration::from_millis(950)));
+    }
+
+    #[test]
+    fn test_flash_fades_out() {
+        let mut layer = AccentLayer::new(AccentKind::Flash);
+        let start = Instant::now();
+        layer.trigger(MAX_ONSET, start);
+
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        layer.draw(&mut frame, 128, 128, start);
+        assert_eq!(frame[0], 255);
+
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        layer.draw(&mut frame, 128, 128, start + Duration::from_millis(75));
+        assert!(frame[0] > 100 && frame[0] < 150);
+
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        layer.draw(&mut frame, 128, 128, start + Duration::from_millis(200));
+        assert!(frame.iter().all(|&v| v == 0));
+        assert!(layer.active.is_none());
+    }
+
+    #[test]
+    fn test_shockwave_ring_leaves_center_dark() {
+        let mut layer = AccentLayer::new(AccentKind::Shockwave);
+        let start = Instant::now();
+        layer.trigger(MAX_ONSET, start);
+
+        let mut frame = vec![0u8; 96 * 64 * 3];
+        layer.draw(&mut frame, 96, 64, start + Duration::from_millis(225));
+        let center = (32 * 96 + 48) * 3;
+        assert_eq!(frame[center], 0);
+        assert!(frame.iter().any(|&v| v > 0));
+    }
+}
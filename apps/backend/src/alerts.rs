@@ -0,0 +1,387 @@
+use crate::config::{parse_time_of_day, AlertKind, AlertRule, AlertsConfig, MqttConfig, ShowHours};
+use crate::crash;
+use crate::messages::Message;
+use crate::schedule::local_time;
+use crate::AppState;
+use anyhow::{bail, Result};
+use rumqttc::{Client, Event, MqttOptions, Outgoing, QoS};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const EVALUATE_EVERY: Duration = Duration::from_secs(1);
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+const MQTT_KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Always true without show hours configured
+pub fn in_show_hours(hours: Option<&ShowHours>, minute: u32) -> bool {
+    let Some(hours) = hours else {
+        return true;
+    };
+    let (Some(start), Some(end)) = (
+        parse_time_of_day(&hours.start),
+        parse_time_of_day(&hours.end),
+    ) else {
+        return true;
+    };
+    if start <= end {
+        (start..end).contains(&minute)
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+// What the rules look at, fed by the LED and render threads between two evaluations
+#[derive(Default)]
+pub struct AlertInputs {
+    packets_sent: u64,
+    packets_failed: u64,
+    offline: Vec<String>,
+    audio_level: f32,
+}
+
+impl AlertInputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_output(&mut self, sent: u64, failed: u64, offline: Vec<String>) {
+        self.packets_sent += sent;
+        self.packets_failed += failed;
+        self.offline = offline;
+    }
+
+    // As last reported by the LED thread
+    pub fn offline_controllers(&self) -> &[String] {
+        &self.offline
+    }
+
+    pub fn record_audio(&mut self, spectrum: &[f32]) {
+        let loudest = spectrum.iter().copied().fold(0.0, f32::max);
+        self.audio_level = self.audio_level.max(loudest);
+    }
+
+    // Offline controllers stay as last reported, the rest starts again from zero
+    fn take(&mut self, fps: f32, sleeping: bool) -> AlertSample {
+        let attempted = self.packets_sent + self.packets_failed;
+        let sample = AlertSample {
+            fps,
+            sleeping,
+            packet_loss: if attempted == 0 {
+                0.0
+            } else {
+                self.packets_failed as f32 / attempted as f32
+            },
+            offline: self.offline.clone(),
+            audio_level: self.audio_level,
+        };
+        self.packets_sent = 0;
+        self.packets_failed = 0;
+        self.audio_level = 0.0;
+        sample
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AlertSample {
+    pub fps: f32,
+    // Energy saver asleep, the frame rate is low on purpose
+    pub sleeping: bool,
+    pub packet_loss: f32,
+    pub offline: Vec<String>,
+    pub audio_level: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub kind: AlertKind,
+    // False when the condition cleared
+    pub firing: bool,
+    #[serde(flatten)]
+    pub message: Message,
+    pub value: f32,
+    pub timestamp: u64,
+}
+
+// Value and description when the rule's condition holds
+fn condition(rule: &AlertRule, sample: &AlertSample) -> Option<(f32, Message)> {
+    match rule.kind {
+        AlertKind::ControllerOffline => {
+            let count = sample.offline.len() as f32;
+            (count > rule.threshold).then(|| {
+                let message = Message::new("controller_offline")
+                    .arg("controllers", sample.offline.join(", "));
+                (count, message)
+            })
+        }
+        AlertKind::LowFps => (!sample.sleeping && sample.fps < rule.threshold).then(|| {
+            let message = Message::new("low_fps").arg("fps", format!("{:.0}", sample.fps));
+            (sample.fps, message)
+        }),
+        AlertKind::PacketLoss => (sample.packet_loss > rule.threshold).then(|| {
+            let percent = format!("{:.1}", sample.packet_loss * 100.0);
+            (
+                sample.packet_loss,
+                Message::new("packet_loss").arg("percent", percent),
+            )
+        }),
+        AlertKind::AudioSilent => (sample.audio_level < rule.threshold)
+            .then(|| (sample.audio_level, Message::new("audio_silent"))),
+    }
+}
+
+#[derive(Default)]
+struct RuleState {
+    holding_since: Option<Instant>,
+    firing: bool,
+}
+
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    states: Vec<RuleState>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let states = rules.iter().map(|_| RuleState::default()).collect();
+        Self { rules, states }
+    }
+
+    // Events for the rules that started or stopped firing with this sample
+    pub fn evaluate(
+        &mut self,
+        sample: &AlertSample,
+        now: Instant,
+        show_hours: bool,
+    ) -> Vec<AlertEvent> {
+        let mut events = Vec::new();
+        for (rule, state) in self.rules.iter().zip(self.states.iter_mut()) {
+            let held = if rule.show_hours_only && !show_hours {
+                None
+            } else {
+                condition(rule, sample)
+            };
+
+            match held {
+                Some((value, message)) => {
+                    let since = *state.holding_since.get_or_insert(now);
+                    let held_for = now.saturating_duration_since(since).as_secs_f32();
+                    if !state.firing && held_for >= rule.for_secs {
+                        state.firing = true;
+                        events.push(AlertEvent {
+                            rule: rule.name.clone(),
+                            kind: rule.kind,
+                            firing: true,
+                            message,
+                            value,
+                            timestamp: timestamp(),
+                        });
+                    }
+                }
+                None => {
+                    state.holding_since = None;
+                    if state.firing {
+                        state.firing = false;
+                        events.push(AlertEvent {
+                            rule: rule.name.clone(),
+                            kind: rule.kind,
+                            firing: false,
+                            message: Message::new("alert_cleared").arg("rule", &rule.name),
+                            value: 0.0,
+                            timestamp: timestamp(),
+                        });
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+// "host:port", brackets allowed around an IPv6 host
+fn broker_address(broker: &str) -> Option<(&str, u16)> {
+    let (host, port) = broker.rsplit_once(':')?;
+    Some((host.trim_matches(['[', ']']), port.parse().ok()?))
+}
+
+// QoS 0, not retained; one connection per event, closed once the publish is out
+fn publish_mqtt(config: &MqttConfig, payload: Vec<u8>) -> Result<()> {
+    let Some((host, port)) = broker_address(&config.broker) else {
+        bail!("MQTT broker must be host:port, not {}", config.broker);
+    };
+    let mut options = MqttOptions::new(config.client_id.as_str(), host, port);
+    options.set_keep_alive(MQTT_KEEP_ALIVE);
+    let (client, mut connection) = Client::new(options, 2);
+    client.publish(config.topic.as_str(), QoS::AtMostOnce, false, payload)?;
+    client.disconnect()?;
+
+    loop {
+        match connection.recv_timeout(NOTIFY_TIMEOUT) {
+            Ok(Ok(Event::Outgoing(Outgoing::Disconnect))) => return Ok(()),
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => bail!("MQTT broker {} did not answer", config.broker),
+        }
+    }
+}
+
+// Webhooks and MQTT off the evaluation thread, a dead endpoint only costs its timeout
+fn notify(agent: &ureq::Agent, config: &AlertsConfig, event: &AlertEvent) {
+    if config.webhooks.is_empty() && config.mqtt.is_none() {
+        return;
+    }
+    let agent = agent.clone();
+    let webhooks = config.webhooks.clone();
+    let mqtt = config.mqtt.clone();
+    let event = event.clone();
+    std::thread::spawn(move || {
+        for url in &webhooks {
+            if let Err(e) = agent.post(url).send_json(&event) {
+                crash::log(format!("⚠️ Alert webhook {} failed: {}", url, e));
+            }
+        }
+        if let Some(mqtt) = &mqtt {
+            let payload = serde_json::to_vec(&event).unwrap_or_default();
+            if let Err(e) = publish_mqtt(mqtt, payload) {
+                crash::log(format!("⚠️ Alert MQTT publish failed: {}", e));
+            }
+        }
+    });
+}
+
+pub fn run(state: Arc<AppState>, config: AlertsConfig) {
+    let agent = ureq::AgentBuilder::new().timeout(NOTIFY_TIMEOUT).build();
+    let mut engine = AlertEngine::new(config.rules.clone());
+    loop {
+        std::thread::sleep(EVALUATE_EVERY);
+
+        let fps = state.render_health.lock().average_fps() as f32;
+        let sleeping = state.energy_saver.lock().is_sleeping();
+        let sample = state.alert_inputs.lock().take(fps, sleeping);
+        let show_hours = in_show_hours(config.show_hours.as_ref(), local_time().minute);
+
+        for event in engine.evaluate(&sample, Instant::now(), show_hours) {
+            let icon = if event.firing { "🔔" } else { "✅" };
+            crash::log(format!("{} {}: {}", icon, event.rule, event.message));
+            notify(&agent, &config, &event);
+            state.alert_events.lock().push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Language;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_rules_fire_after_holding_then_clear() {
+        let mut engine = AlertEngine::new(AlertsConfig::default().rules);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let healthy = AlertSample {
+            fps: 60.0,
+            audio_level: 0.5,
+            ..Default::default()
+        };
+        let offline = AlertSample {
+            offline: vec!["10.0.0.2".to_string()],
+            ..healthy.clone()
+        };
+
+        assert!(engine.evaluate(&offline, at(0), true).is_empty());
+        assert!(engine.evaluate(&offline, at(9), true).is_empty());
+        let events = engine.evaluate(&offline, at(10), true);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].firing);
+        assert_eq!(events[0].kind, AlertKind::ControllerOffline);
+        assert_eq!(events[0].message.code, "controller_offline");
+        assert_eq!(
+            events[0].message.text(Language::En),
+            "Controller offline: 10.0.0.2"
+        );
+        assert!(engine.evaluate(&offline, at(11), true).is_empty());
+
+        let events = engine.evaluate(&healthy, at(12), true);
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].firing);
+
+        // The hold restarts after clearing
+        assert!(engine.evaluate(&offline, at(13), true).is_empty());
+    }
+
+    #[test]
+    fn test_sleep_and_show_hours_suppress_rules() {
+        let mut engine = AlertEngine::new(AlertsConfig::default().rules);
+        let start = Instant::now();
+        let quiet = AlertSample {
+            fps: 10.0,
+            sleeping: true,
+            ..Default::default()
+        };
+        engine.evaluate(&quiet, start, false);
+        let later = start + Duration::from_secs(600);
+        assert!(engine.evaluate(&quiet, later, false).is_empty());
+
+        let events = engine.evaluate(&quiet, later, true);
+        assert!(events.is_empty());
+        let events = engine.evaluate(&quiet, later + Duration::from_secs(120), true);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, AlertKind::AudioSilent);
+    }
+
+    #[test]
+    fn test_show_hours_cross_midnight() {
+        let hours = ShowHours {
+            start: "22:00".to_string(),
+            end: "04:30".to_string(),
+        };
+        assert!(in_show_hours(Some(&hours), 23 * 60));
+        assert!(in_show_hours(Some(&hours), 60));
+        assert!(!in_show_hours(Some(&hours), 4 * 60 + 30));
+        assert!(!in_show_hours(Some(&hours), 12 * 60));
+        assert!(in_show_hours(None, 12 * 60));
+    }
+
+    #[test]
+    fn test_mqtt_publish() {
+        assert_eq!(broker_address("[::1]:1883"), Some(("::1", 1883)));
+        assert_eq!(broker_address("broker.local"), None);
+
+        // A broker that accepts the connection and keeps whatever it is sent
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = MqttConfig {
+            broker: listener.local_addr().unwrap().to_string(),
+            topic: "dj4led/alerts".to_string(),
+            client_id: "dj".to_string(),
+        };
+        let broker = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = vec![0; 64];
+            let read = stream.read(&mut received).unwrap();
+            stream.write_all(&[0x20, 2, 0, 0]).unwrap();
+            received.truncate(read);
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        publish_mqtt(&config, b"{}".to_vec()).unwrap();
+        let received = broker.join().unwrap();
+        // CONNECT, then PUBLISH on the topic with the payload, then DISCONNECT
+        assert_eq!(received[0], 0x10);
+        let publish = received.iter().position(|&b| b == 0x30).unwrap();
+        assert_eq!(&received[publish + 4..publish + 17], b"dj4led/alerts");
+        assert_eq!(&received[publish + 17..publish + 19], b"{}");
+        assert_eq!(received[received.len() - 2..], [0xE0, 0]);
+    }
+}
@@ -1,75 +1,195 @@
+use crate::config::AudioConfig;
+use crate::crash;
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleRate, StreamConfig};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+pub const SAMPLE_RATE: u32 = 48000;
+const DEFAULT_DEVICE: &str = "default";
+const DEFAULT_RETRY_SECS: f32 = 30.0;
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(500);
+
+type SampleCallback = Arc<Mutex<dyn FnMut(&[f32]) + Send>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActiveDevice {
+    pub name: String,
+    // Position in the priority chain, 0 = most preferred
+    pub rank: usize,
+    pub chain: Vec<String>,
+}
+
+// Walks an ordered chain of capture devices: opens the best one available, falls down
+// the chain when it fails and regularly tries to climb back to a preferred one
 pub struct AudioCapture {
-    stream: cpal::Stream,
+    host: cpal::Host,
+    chain: Vec<String>,
+    retry: Duration,
+    callback: SampleCallback,
+    failed: Arc<AtomicBool>,
+    current: Option<(usize, cpal::Stream)>,
+}
+
+// Configured devices, or the legacy single `device_name`, always ending on the system default
+pub fn device_chain(config: &AudioConfig) -> Vec<String> {
+    let mut chain: Vec<String> = if config.devices.is_empty() {
+        config.device_name.iter().cloned().collect()
+    } else {
+        config.devices.clone()
+    };
+    chain.retain(|name| !name.trim().is_empty());
+    if !chain
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(DEFAULT_DEVICE))
+    {
+        chain.push(DEFAULT_DEVICE.to_string());
+    }
+    chain
 }
 
 impl AudioCapture {
-    pub fn new<F>(mut callback: F) -> Result<Self>
+    pub fn new<F>(config: &AudioConfig, callback: F) -> Self
     where
         F: FnMut(&[f32]) + Send + 'static,
     {
-        let host = cpal::default_host();
-        let device = host.default_input_device().ok_or_else(|| anyhow::anyhow!("No default input device"))?;
+        let retry = config
+            .device_retry_secs
+            .unwrap_or(DEFAULT_RETRY_SECS)
+            .max(1.0);
+
+        Self {
+            host: cpal::default_host(),
+            chain: device_chain(config),
+            retry: Duration::from_secs_f32(retry),
+            callback: Arc::new(Mutex::new(callback)),
+            failed: Arc::new(AtomicBool::new(false)),
+            current: None,
+        }
+    }
 
-        for (idx, device) in host.input_devices()?.enumerate() {}
+    fn find_device(&self, entry: &str) -> Option<cpal::Device> {
+        if entry.eq_ignore_ascii_case(DEFAULT_DEVICE) {
+            return self.host.default_input_device();
+        }
+        let wanted = entry.to_lowercase();
+        self.host.input_devices().ok()?.find(|device| {
+            device
+                .name()
+                .is_ok_and(|name| name.to_lowercase().contains(&wanted))
+        })
+    }
 
-        let supported_configs = device.supported_input_configs()?;
-        for (idx, config) in supported_configs.enumerate() {}
+    fn open(&self, entry: &str) -> Result<(String, cpal::Stream)> {
+        let device = self
+            .find_device(entry)
+            .ok_or_else(|| anyhow::anyhow!("No input device matching {}", entry))?;
+        let name = device.name().unwrap_or_else(|_| entry.to_string());
 
         let config = StreamConfig {
             channels: 1,
-            sample_rate: SampleRate(48000),
+            sample_rate: SampleRate(SAMPLE_RATE),
             buffer_size: cpal::BufferSize::Fixed(64),
         };
 
-        let mut sample_counter = 0u64;
-        let mut last_log_time = std::time::Instant::now();
+        let callback = self.callback.clone();
+        let failed = self.failed.clone();
+        let stream = device
+            .build_input_stream(
+                &config,
+                move |data: &[f32], _: &_| (callback.lock())(data),
+                move |_| failed.store(true, Ordering::Relaxed),
+                None,
+            )
+            .map_err(|e| anyhow::anyhow!("Failed to create stream: {}", e))?;
 
-        let stream = device.build_input_stream(
-            &config,
-            move |data: &[f32], _: &_| {
-                sample_counter += data.len() as u64;
+        stream.play()?;
 
-                let max_level = data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-                let avg_level = data.iter().map(|&x| x.abs()).sum::<f32>() / data.len() as f32;
+        Ok((name, stream))
+    }
 
-                if last_log_time.elapsed().as_secs() >= 1 {
-                    sample_counter = 0;
-                    last_log_time = std::time::Instant::now();
+    // Opens the first device of the chain ranked above `limit`
+    fn open_best(&mut self, limit: usize) -> Option<ActiveDevice> {
+        for rank in 0..limit.min(self.chain.len()) {
+            let entry = self.chain[rank].clone();
+            match self.open(&entry) {
+                Ok((name, stream)) => {
+                    self.current = Some((rank, stream));
+                    return Some(ActiveDevice {
+                        name,
+                        rank,
+                        chain: self.chain.clone(),
+                    });
                 }
-
-                if avg_level > 0.002 || max_level > 0.01 {
-                    let filtered_data: Vec<f32> = data
-                        .iter()
-                        .map(|&x| {
-                            let abs_x = x.abs();
-                            if abs_x < 0.004 {
-                                0.0
-                            } else {
-                                x
-                            }
-                        })
-                        .collect();
-
-                    callback(&filtered_data);
-                } else {
-                    let silence = vec![0.0; data.len()];
-                    callback(&silence);
+                Err(e) if self.current.is_none() => {
+                    crash::log(format!("⚠️ Audio device {}: {}", entry, e))
                 }
-            },
-            |err| eprintln!(""),
-            None,
-        ).map_err(|e| anyhow::anyhow!("Failed to create stream: {}", e))?;
+                Err(_) => {}
+            }
+        }
+        None
+    }
 
-        stream.play()?;
+    pub fn run(&mut self, on_device: impl Fn(Option<ActiveDevice>)) {
+        let mut last_attempt: Option<Instant> = None;
+
+        loop {
+            if self.failed.swap(false, Ordering::Relaxed) && self.current.is_some() {
+                crash::log("❌ Audio device failed, falling back down the chain");
+                self.current = None;
+                last_attempt = None;
+                on_device(None);
+            }
 
-        Ok(Self { stream })
+            let rank = self
+                .current
+                .as_ref()
+                .map_or(self.chain.len(), |(rank, _)| *rank);
+            let due = last_attempt.is_none_or(|at| at.elapsed() >= self.retry);
+            if rank > 0 && due {
+                last_attempt = Some(Instant::now());
+                if let Some(device) = self.open_best(rank) {
+                    crash::log(format!(
+                        "🎤 Audio input: {} (priority {}/{})",
+                        device.name,
+                        device.rank + 1,
+                        self.chain.len()
+                    ));
+                    on_device(Some(device));
+                }
+            }
+
+            std::thread::sleep(SUPERVISE_INTERVAL);
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_device_chain_ends_on_default() {
+        let mut config = Config::default().audio;
+        assert_eq!(device_chain(&config), vec!["default"]);
+
+        config.device_name = Some("Scarlett".to_string());
+        assert_eq!(device_chain(&config), vec!["Scarlett", "default"]);
 
-    pub fn run(&self) {
-        std::thread::park();
+        config.devices = vec![
+            "CABLE Output".to_string(),
+            "".to_string(),
+            "Default".to_string(),
+            "Scarlett".to_string(),
+        ];
+        assert_eq!(
+            device_chain(&config),
+            vec!["CABLE Output", "Default", "Scarlett"]
+        );
     }
 }
@@ -0,0 +1,66 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Enough for 500 ms at well over the LED frame rate
+const MAX_QUEUED: usize = 256;
+
+// Delay line on the output path: frames go in as they are rendered and come out once
+// they are `delay` old. Raising the delay holds the last frame until the line catches up.
+pub struct DelayLine<T> {
+    delay: Duration,
+    queue: VecDeque<(Instant, T)>,
+    current: Option<T>,
+}
+
+impl<T> DelayLine<T> {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            queue: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    // The newest item at least `delay` old as of `now`, or the last one handed out
+    pub fn process(&mut self, now: Instant, item: T) -> Option<&T> {
+        if self.queue.len() >= MAX_QUEUED {
+            self.queue.pop_front();
+        }
+        self.queue.push_back((now, item));
+        while self
+            .queue
+            .front()
+            .is_some_and(|(queued, _)| now.duration_since(*queued) >= self.delay)
+        {
+            self.current = self.queue.pop_front().map(|(_, item)| item);
+        }
+        self.current.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_come_out_late() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut line = DelayLine::new(Duration::ZERO);
+        assert_eq!(line.process(at(0), 1), Some(&1));
+
+        line.set_delay(Duration::from_millis(100));
+        assert_eq!(line.process(at(50), 2), Some(&1));
+        assert_eq!(line.process(at(120), 3), Some(&1));
+        assert_eq!(line.process(at(160), 4), Some(&2));
+        assert_eq!(line.process(at(230), 5), Some(&3));
+
+        line.set_delay(Duration::ZERO);
+        assert_eq!(line.process(at(240), 6), Some(&6));
+    }
+}
@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const HISTORY_LEN: usize = 43;
+const BEAT_THRESHOLD: f32 = 1.4;
+const MIN_BASS_ENERGY: f32 = 0.1;
+const MIN_BEAT_INTERVAL: Duration = Duration::from_millis(250);
+const BEATS_PER_BAR: u64 = 4;
+const FLASH_DURATION: Duration = Duration::from_millis(120);
+const MARKER_SIZE: usize = 2;
+const BEAT_LEVEL: f32 = 96.0;
+const DOWNBEAT_LEVEL: f32 = 255.0;
+const BPM_WINDOW: usize = 8;
+const MAX_BEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beat {
+    pub downbeat: bool,
+}
+
+// Where the music is in the bar, extrapolated from the last beat at the current tempo
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BeatPhase {
+    pub bpm: f32,
+    // 0 on the beat, rising to 1 just before the next one
+    pub phase: f32,
+    // 0 on the downbeat
+    pub beat_in_bar: u8,
+}
+
+// Sent to clients on every detected beat
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatEvent {
+    pub downbeat: bool,
+    pub bpm: Option<f32>,
+    pub onset_strength: f32,
+}
+
+// Bass onsets against a rolling one-second average; every fourth beat counts as a downbeat
+pub struct BeatDetector {
+    history: VecDeque<f32>,
+    last_beat: Option<Instant>,
+    beats: u64,
+    intervals: VecDeque<Duration>,
+    onset_strength: f32,
+}
+
+impl Default for BeatDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BeatDetector {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            last_beat: None,
+            beats: 0,
+            intervals: VecDeque::with_capacity(BPM_WINDOW),
+            onset_strength: 0.0,
+        }
+    }
+
+    fn average_interval(&self, now: Instant) -> Option<Duration> {
+        let last = self.last_beat?;
+        if self.intervals.is_empty() || now.duration_since(last) > MAX_BEAT_INTERVAL {
+            return None;
+        }
+        Some(self.intervals.iter().sum::<Duration>() / self.intervals.len() as u32)
+    }
+
+    // Tempo from recent beat intervals; gaps longer than two seconds restart the estimate
+    pub fn bpm(&self) -> Option<f32> {
+        self.average_interval(Instant::now())
+            .map(|average| 60.0 / average.as_secs_f32())
+    }
+
+    // Keeps counting beats between detections, so a missed kick doesn't shift the bar
+    pub fn phase(&self, now: Instant) -> Option<BeatPhase> {
+        let average = self.average_interval(now)?.as_secs_f32();
+        let since = now.duration_since(self.last_beat?).as_secs_f32() / average;
+        let beat = self.beats - 1 + since as u64;
+        Some(BeatPhase {
+            bpm: 60.0 / average,
+            phase: since.fract(),
+            beat_in_bar: (beat % BEATS_PER_BAR) as u8,
+        })
+    }
+
+    // Picks up a bar another detector was counting, until the music confirms or corrects it
+    pub fn resume(&mut self, phase: BeatPhase, now: Instant) {
+        let Some(interval) = Duration::try_from_secs_f32(60.0 / phase.bpm)
+            .ok()
+            .filter(|interval| !interval.is_zero())
+        else {
+            return;
+        };
+        let Some(last_beat) = now.checked_sub(interval.mul_f32(phase.phase.clamp(0.0, 1.0))) else {
+            return;
+        };
+        self.last_beat = Some(last_beat);
+        self.intervals.clear();
+        self.intervals.push_back(interval);
+        self.beats = phase.beat_in_bar as u64 % BEATS_PER_BAR + 1;
+    }
+
+    // Bass level of the last beat over the rolling average before it
+    pub fn onset_strength(&self) -> f32 {
+        self.onset_strength
+    }
+
+    pub fn process(&mut self, spectrum: &[f32], now: Instant) -> Option<Beat> {
+        let bass = spectrum.iter().take(8).sum::<f32>() / 8.0;
+        let warmed_up = !self.history.is_empty();
+        let average = self.history.iter().sum::<f32>() / self.history.len().max(1) as f32;
+
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(bass);
+
+        let ready = self
+            .last_beat
+            .is_none_or(|last| now.duration_since(last) >= MIN_BEAT_INTERVAL);
+        if !warmed_up || !ready || bass < MIN_BASS_ENERGY || bass < average * BEAT_THRESHOLD {
+            return None;
+        }
+
+        if let Some(last) = self.last_beat {
+            let interval = now.duration_since(last);
+            if interval > MAX_BEAT_INTERVAL {
+                self.intervals.clear();
+            } else {
+                if self.intervals.len() == BPM_WINDOW {
+                    self.intervals.pop_front();
+                }
+                self.intervals.push_back(interval);
+            }
+        }
+        self.last_beat = Some(now);
+        self.onset_strength = bass / average.max(f32::EPSILON);
+        let downbeat = self.beats.is_multiple_of(BEATS_PER_BAR);
+        self.beats += 1;
+        Some(Beat { downbeat })
+    }
+}
+
+pub struct Metronome {
+    flash: Option<(Instant, Beat)>,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metronome {
+    pub fn new() -> Self {
+        Self { flash: None }
+    }
+
+    pub fn flash(&mut self, beat: Beat) {
+        self.flash = Some((Instant::now(), beat));
+    }
+
+    // Fading marker in the top-left corner, drawn over whatever the effect rendered
+    pub fn draw(&self, frame: &mut [u8], width: usize) {
+        let Some((started, beat)) = self.flash else {
+            return;
+        };
+        let age = started.elapsed();
+        if age >= FLASH_DURATION {
+            return;
+        }
+
+        let peak = if beat.downbeat {
+            DOWNBEAT_LEVEL
+        } else {
+            BEAT_LEVEL
+        };
+        let level = (peak * (1.0 - age.as_secs_f32() / FLASH_DURATION.as_secs_f32())) as u8;
+
+        for y in 0..MARKER_SIZE {
+            for x in 0..MARKER_SIZE {
+                let idx = (y * width + x) * 3;
+                if let Some(pixel) = frame.get_mut(idx..idx + 3) {
+                    for value in pixel.iter_mut() {
+                        *value = (*value).max(level);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_bass_onsets_and_downbeats() {
+        let mut detector = BeatDetector::new();
+        let quiet = vec![0.1; 64];
+        let kick = vec![0.9; 64];
+        let start = Instant::now();
+
+        for i in 0..20 {
+            assert!(detector
+                .process(&quiet, start + Duration::from_millis(i * 20))
+                .is_none());
+        }
+
+        let first = detector.process(&kick, start + Duration::from_millis(500));
+        assert_eq!(first, Some(Beat { downbeat: true }));
+        assert!(detector
+            .process(&kick, start + Duration::from_millis(520))
+            .is_none());
+
+        let second = detector.process(&kick, start + Duration::from_millis(1000));
+        assert_eq!(second, Some(Beat { downbeat: false }));
+        assert_eq!(detector.intervals, vec![Duration::from_millis(500)]);
+
+        let phase = detector.phase(start + Duration::from_millis(1250)).unwrap();
+        assert_eq!(phase.bpm, 120.0);
+        assert!((phase.phase - 0.5).abs() < 1e-3);
+        assert_eq!(phase.beat_in_bar, 1);
+        // Two beats later without a kick
+        let phase = detector.phase(start + Duration::from_millis(2000)).unwrap();
+        assert_eq!(phase.beat_in_bar, 3);
+        assert!(detector
+            .phase(start + Duration::from_millis(3500))
+            .is_none());
+    }
+
+    #[test]
+    fn test_resumed_phase_keeps_the_bar() {
+        let start = Instant::now() + Duration::from_secs(10);
+        let mut detector = BeatDetector::new();
+        detector.resume(
+            BeatPhase {
+                bpm: 120.0,
+                phase: 0.5,
+                beat_in_bar: 2,
+            },
+            start,
+        );
+
+        let phase = detector.phase(start).unwrap();
+        assert!((phase.bpm - 120.0).abs() < 1e-3);
+        assert!((phase.phase - 0.5).abs() < 1e-3);
+        assert_eq!(phase.beat_in_bar, 2);
+        // Counting on without a kick: the bar's last beat, then the downbeat
+        let phase = detector.phase(start + Duration::from_millis(500)).unwrap();
+        assert_eq!(phase.beat_in_bar, 3);
+        let phase = detector.phase(start + Duration::from_millis(750)).unwrap();
+        assert_eq!(phase.beat_in_bar, 0);
+
+        // Detected kicks carry on from the resumed bar
+        let quiet = vec![0.1; 64];
+        let kick = vec![0.9; 64];
+        detector.process(&quiet, start + Duration::from_millis(100));
+        let beat = detector.process(&kick, start + Duration::from_millis(250));
+        assert_eq!(beat, Some(Beat { downbeat: false }));
+        let beat = detector.process(&kick, start + Duration::from_millis(750));
+        assert_eq!(beat, Some(Beat { downbeat: true }));
+    }
+
+    #[test]
+    fn test_marker_drawn_in_corner() {
+        let mut metronome = Metronome::new();
+        metronome.flash(Beat { downbeat: true });
+
+        let mut frame = vec![0u8; 96 * 64 * 3];
+        metronome.draw(&mut frame, 96);
+
+        assert!(frame[0] > 200);
+        assert!(frame[(96 + 1) * 3] > 200);
+        assert_eq!(frame[2 * 3], 0);
+        assert_eq!(frame[2 * 96 * 3], 0);
+    }
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ResourceLimits {
+    pub max_particles: usize,
+    pub max_trail_points: usize,
+    pub max_clients: usize,
+    pub max_client_queue_bytes: usize,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_particles: 2000,
+            max_trail_points: 1000,
+            max_clients: 16,
+            max_client_queue_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ResourceUsage {
+    pub particles: usize,
+    pub trail_points: usize,
+    pub bytes: usize,
+}
+
+impl ResourceUsage {
+    pub fn of<T>(items: &Vec<T>) -> Self {
+        Self {
+            particles: items.len(),
+            trail_points: 0,
+            bytes: items.capacity() * std::mem::size_of::<T>(),
+        }
+    }
+
+    pub fn trails<T>(items: &Vec<T>) -> Self {
+        Self {
+            particles: 0,
+            trail_points: items.len(),
+            bytes: items.capacity() * std::mem::size_of::<T>(),
+        }
+    }
+}
+
+impl AddAssign for ResourceUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.particles += other.particles;
+        self.trail_points += other.trail_points;
+        self.bytes += other.bytes;
+    }
+}
+
+// Drops the oldest entries, which are at the front of every effect buffer
+pub fn trim_oldest<T>(items: &mut Vec<T>, max: usize) -> usize {
+    let excess = items.len().saturating_sub(max);
+    if excess > 0 {
+        items.drain(..excess);
+        items.shrink_to(max * 2);
+    }
+    excess
+}
+
+#[derive(Debug, Default)]
+pub struct ResourceBudget {
+    limits: ResourceLimits,
+    effects: ResourceUsage,
+    clients: usize,
+    client_queue_bytes: usize,
+    trimmed: u64,
+    rejected_clients: u64,
+}
+
+impl ResourceBudget {
+    pub fn new(limits: ResourceLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    pub fn record_effects(&mut self, usage: ResourceUsage, trimmed: usize) {
+        self.effects = usage;
+        self.trimmed += trimmed as u64;
+    }
+
+    pub fn allow_client(&mut self, connected: usize) -> bool {
+        let allowed = connected < self.limits.max_clients;
+        if !allowed {
+            self.rejected_clients += 1;
+        }
+        allowed
+    }
+
+    pub fn allow_client_bytes(&self, queued: usize) -> bool {
+        queued <= self.limits.max_client_queue_bytes
+    }
+
+    pub fn record_clients(&mut self, clients: usize, queued_bytes: usize) {
+        self.clients = clients;
+        self.client_queue_bytes = queued_bytes;
+    }
+
+    pub fn report(&self) -> serde_json::Value {
+        serde_json::json!({
+            "limits": self.limits,
+            "effects": self.effects,
+            "clients": self.clients,
+            "client_queue_bytes": self.client_queue_bytes,
+            "total_bytes": self.effects.bytes + self.client_queue_bytes,
+            "trimmed_items": self.trimmed,
+            "rejected_clients": self.rejected_clients,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_oldest() {
+        let mut items: Vec<u32> = (0..10).collect();
+        assert_eq!(trim_oldest(&mut items, 4), 6);
+        assert_eq!(items, vec![6, 7, 8, 9]);
+        assert_eq!(trim_oldest(&mut items, 4), 0);
+    }
+
+    #[test]
+    fn test_client_cap() {
+        let mut budget = ResourceBudget::new(ResourceLimits {
+            max_clients: 2,
+            ..Default::default()
+        });
+        assert!(budget.allow_client(1));
+        assert!(!budget.allow_client(2));
+        assert_eq!(budget.report()["rejected_clients"], 1);
+    }
+}
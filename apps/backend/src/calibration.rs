@@ -0,0 +1,178 @@
+use crate::config::{Config, SpectrumConfig};
+use crate::crash;
+use crate::AppState;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_CAPTURE: Duration = Duration::from_secs(10);
+pub const MAX_CAPTURE: Duration = Duration::from_secs(60);
+const MIN_GAIN: f32 = 0.25;
+const MAX_GAIN: f32 = 4.0;
+const MIN_BAND_LEVEL: f64 = 1e-4;
+
+struct Capture {
+    started: Instant,
+    duration: Duration,
+    sums: Vec<f64>,
+    frames: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureResult {
+    Learned(Vec<f32>),
+    NoSignal,
+}
+
+// Per-band gains learned from program audio so quiet bands of a given PA are lifted and hot ones tamed
+pub struct RoomCalibration {
+    curve: Vec<f32>,
+    capture: Option<Capture>,
+}
+
+impl RoomCalibration {
+    pub fn new(curve: Vec<f32>) -> Self {
+        Self {
+            curve,
+            capture: None,
+        }
+    }
+
+    pub fn curve(&self) -> &[f32] {
+        &self.curve
+    }
+
+    pub fn start_capture(&mut self, duration: Duration) {
+        self.capture = Some(Capture {
+            started: Instant::now(),
+            duration: duration.min(MAX_CAPTURE),
+            sums: Vec::new(),
+            frames: 0,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.curve.clear();
+        self.capture = None;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    pub fn progress(&self) -> Option<f32> {
+        self.capture.as_ref().map(|c| {
+            (c.started.elapsed().as_secs_f32() / c.duration.as_secs_f32().max(f32::EPSILON))
+                .min(1.0)
+        })
+    }
+
+    // Feeds the raw spectrum; returns the outcome once the capture window has elapsed
+    pub fn record(&mut self, spectrum: &[f32]) -> Option<CaptureResult> {
+        let capture = self.capture.as_mut()?;
+
+        if spectrum.iter().any(|&v| v > 0.0) {
+            if capture.sums.len() != spectrum.len() {
+                capture.sums = vec![0.0; spectrum.len()];
+                capture.frames = 0;
+            }
+            for (sum, &value) in capture.sums.iter_mut().zip(spectrum) {
+                *sum += value as f64;
+            }
+            capture.frames += 1;
+        }
+
+        if capture.started.elapsed() < capture.duration {
+            return None;
+        }
+
+        let capture = self.capture.take()?;
+        if capture.frames == 0 {
+            return Some(CaptureResult::NoSignal);
+        }
+
+        let means: Vec<f64> = capture
+            .sums
+            .iter()
+            .map(|sum| sum / capture.frames as f64)
+            .collect();
+        let overall = means.iter().sum::<f64>() / means.len() as f64;
+        if overall < MIN_BAND_LEVEL {
+            return Some(CaptureResult::NoSignal);
+        }
+
+        self.curve = means
+            .iter()
+            .map(|&mean| {
+                (overall / mean.max(MIN_BAND_LEVEL)).clamp(MIN_GAIN as f64, MAX_GAIN as f64) as f32
+            })
+            .collect();
+        Some(CaptureResult::Learned(self.curve.clone()))
+    }
+
+    pub fn apply(&self, spectrum: &mut [f32]) {
+        for (value, gain) in spectrum.iter_mut().zip(&self.curve) {
+            *value = (*value * gain).min(1.0);
+        }
+    }
+}
+
+// Runs on the audio path: feeds an active capture, persists a finished one and applies the curve
+pub fn process(state: &AppState, spectrum: &mut [f32]) {
+    let mut calibration = state.calibration.lock();
+
+    match calibration.record(spectrum) {
+        Some(CaptureResult::Learned(curve)) => {
+            crash::log(format!(
+                "🎧 Room calibration learned over {} bands",
+                curve.len()
+            ));
+            let config = SpectrumConfig {
+                normalization: curve,
+            };
+            if let Err(e) = Config::save_section("spectrum", &config) {
+                crash::log(format!("⚠️ Failed to save room calibration: {}", e));
+            }
+        }
+        Some(CaptureResult::NoSignal) => {
+            crash::log("⚠️ Room calibration heard no audio, keeping the previous curve");
+        }
+        None => {}
+    }
+
+    calibration.apply(spectrum);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learns_flattening_curve() {
+        let mut calibration = RoomCalibration::new(Vec::new());
+        calibration.start_capture(Duration::ZERO);
+
+        let result = calibration.record(&[0.2, 0.4, 0.6]).unwrap();
+        let CaptureResult::Learned(curve) = result else {
+            panic!("Expected a learned curve");
+        };
+        assert!((curve[0] - 2.0).abs() < 1e-4);
+        assert!((curve[1] - 1.0).abs() < 1e-4);
+        assert!(!calibration.is_capturing());
+
+        let mut spectrum = vec![0.2, 0.4, 0.6];
+        calibration.apply(&mut spectrum);
+        assert!(spectrum.iter().all(|&v| (v - 0.4).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_silence_keeps_previous_curve() {
+        let mut calibration = RoomCalibration::new(vec![1.5; 3]);
+        calibration.start_capture(Duration::ZERO);
+
+        assert_eq!(
+            calibration.record(&[0.0, 0.0, 0.0]),
+            Some(CaptureResult::NoSignal)
+        );
+        assert_eq!(calibration.curve(), &[1.5; 3]);
+        assert_eq!(calibration.record(&[0.5, 0.5, 0.5]), None);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::crash;
+use crate::led::{limit_power, OutputControl};
+use crate::output_profile;
+use anyhow::Result;
+use std::net::{SocketAddr, UdpSocket};
+
+// RGB pixels per Art-Net universe, 510 of the 512 DMX channels
+const PIXELS_PER_UNIVERSE: usize = 170;
+const DMX_CHANNELS: usize = 512;
+
+struct Route {
+    name: String,
+    target: SocketAddr,
+    universe: u16,
+    failing: bool,
+}
+
+// Sends frames other than the wall's (booth strip, edge strips...) to their own Art-Net
+// nodes, one route per frame in the order the routes were added
+pub struct CanvasOutput {
+    socket: UdpSocket,
+    routes: Vec<Route>,
+    scratch: Vec<u8>,
+}
+
+impl CanvasOutput {
+    pub fn new<'a>(routes: impl IntoIterator<Item = (&'a str, &'a str, u16)>) -> Result<Self> {
+        let mut parsed = Vec::new();
+        for (name, target, universe) in routes {
+            parsed.push(Route {
+                name: name.to_string(),
+                target: target.parse()?,
+                universe,
+                failing: false,
+            });
+        }
+        Ok(Self {
+            socket: UdpSocket::bind("0.0.0.0:0")?,
+            routes: parsed,
+            scratch: Vec::new(),
+        })
+    }
+
+    // None without any route; a failure is logged and leaves that output off
+    pub fn open<'a>(
+        kind: &str,
+        routes: impl IntoIterator<Item = (&'a str, &'a str, u16)>,
+    ) -> Option<Self> {
+        match Self::new(routes) {
+            Ok(output) if output.routes.is_empty() => None,
+            Ok(output) => Some(output),
+            Err(e) => {
+                crash::log(format!("❌ {} output disabled: {}", kind, e));
+                None
+            }
+        }
+    }
+
+    // Blackout, master brightness and the power limit apply as on the wall
+    pub fn send(&mut self, frames: &[Vec<u8>], control: &OutputControl, power_limit: Option<f32>) {
+        for (route, frame) in self.routes.iter_mut().zip(frames) {
+            self.scratch.clear();
+            self.scratch.extend_from_slice(frame);
+            if control.blackout {
+                self.scratch.fill(0);
+            } else {
+                output_profile::apply(control.profile, &mut self.scratch);
+                let brightness = control.effective_brightness();
+                if brightness < 1.0 {
+                    for value in self.scratch.iter_mut() {
+                        *value = (*value as f32 * brightness) as u8;
+                    }
+                }
+                if let Some(limit) = power_limit {
+                    limit_power(&mut self.scratch, limit);
+                }
+            }
+
+            let mut sent = true;
+            for packet in artnet_packets(&self.scratch, route.universe) {
+                if let Err(e) = self.socket.send_to(&packet, route.target) {
+                    if !route.failing {
+                        crash::log(format!(
+                            "❌ Error sending {} to {}: {}",
+                            route.name, route.target, e
+                        ));
+                    }
+                    sent = false;
+                    break;
+                }
+            }
+            route.failing = !sent;
+        }
+    }
+}
+
+// One ArtDmx packet per 170 pixels, universes numbered up from `first_universe`
+fn artnet_packets(frame: &[u8], first_universe: u16) -> Vec<Vec<u8>> {
+    frame
+        .chunks(PIXELS_PER_UNIVERSE * 3)
+        .enumerate()
+        .map(|(index, pixels)| {
+            let universe = first_universe.wrapping_add(index as u16);
+            let mut packet = vec![
+                b'A',
+                b'r',
+                b't',
+                b'-',
+                b'N',
+                b'e',
+                b't',
+                0,
+                0x00,
+                0x50,
+                0,
+                14,
+                0,
+                0,
+                (universe & 0xFF) as u8,
+                (universe >> 8) as u8,
+                0x02,
+                0x00,
+            ];
+            packet.extend_from_slice(pixels);
+            packet.resize(packet.len() + DMX_CHANNELS - pixels.len(), 0);
+            packet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_splits_into_universes() {
+        let frame: Vec<u8> = (0..300 * 3).map(|i| (i % 251) as u8).collect();
+        let packets = artnet_packets(&frame, 0x01FF);
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|p| p.len() == 18 + DMX_CHANNELS));
+
+        assert_eq!(&packets[0][14..16], &[0xFF, 0x01]);
+        assert_eq!(&packets[1][14..16], &[0x00, 0x02]);
+        assert_eq!(&packets[0][18..528], &frame[..510]);
+        assert_eq!(&packets[1][18..408], &frame[510..]);
+        assert!(packets[1][408..].iter().all(|&c| c == 0));
+    }
+}
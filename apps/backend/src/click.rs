@@ -0,0 +1,190 @@
+use crate::config::ClickConfig;
+use crate::crash;
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use crossbeam_queue::ArrayQueue;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+const QUEUE_CAPACITY: usize = 16;
+// Tones still ringing past this are cut short by newer ones
+const MAX_VOICES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    Beat,
+    Downbeat,
+    Warning,
+}
+
+impl Cue {
+    // (frequency in Hz, length in seconds)
+    fn tone(self) -> (f32, f32) {
+        match self {
+            Cue::Beat => (1000.0, 0.03),
+            Cue::Downbeat => (1600.0, 0.04),
+            Cue::Warning => (440.0, 0.25),
+        }
+    }
+}
+
+// Hand-off from the render and LED threads to the audio callback; cues that are off
+// in the config are dropped here
+pub struct ClickCues {
+    queue: ArrayQueue<Cue>,
+    beats: bool,
+    alerts: bool,
+}
+
+impl ClickCues {
+    pub fn new(config: &ClickConfig) -> Self {
+        Self {
+            queue: ArrayQueue::new(QUEUE_CAPACITY),
+            beats: config.beats,
+            alerts: config.alerts,
+        }
+    }
+
+    pub fn push(&self, cue: Cue) {
+        let wanted = match cue {
+            Cue::Beat | Cue::Downbeat => self.beats,
+            Cue::Warning => self.alerts,
+        };
+        if wanted {
+            self.queue.force_push(cue);
+        }
+    }
+}
+
+struct Voice {
+    step: f32,
+    phase: f32,
+    remaining: usize,
+    length: usize,
+}
+
+// Decaying sine bursts, mixed down to the same signal on every channel
+pub struct ClickSynth {
+    sample_rate: f32,
+    volume: f32,
+    voices: Vec<Voice>,
+}
+
+impl ClickSynth {
+    pub fn new(sample_rate: u32, volume: f32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            volume,
+            voices: Vec::with_capacity(MAX_VOICES),
+        }
+    }
+
+    pub fn trigger(&mut self, cue: Cue) {
+        let (frequency, seconds) = cue.tone();
+        let length = ((seconds * self.sample_rate) as usize).max(1);
+        if self.voices.len() == MAX_VOICES {
+            self.voices.remove(0);
+        }
+        self.voices.push(Voice {
+            step: frequency * TAU / self.sample_rate,
+            phase: 0.0,
+            remaining: length,
+            length,
+        });
+    }
+
+    pub fn fill(&mut self, output: &mut [f32], channels: usize) {
+        for frame in output.chunks_mut(channels.max(1)) {
+            let mut sample = 0.0;
+            for voice in self.voices.iter_mut() {
+                let envelope = voice.remaining as f32 / voice.length as f32;
+                sample += voice.phase.sin() * envelope * envelope;
+                voice.phase = (voice.phase + voice.step) % TAU;
+                voice.remaining -= 1;
+            }
+            self.voices.retain(|voice| voice.remaining > 0);
+            frame.fill((sample * self.volume).clamp(-1.0, 1.0));
+        }
+    }
+}
+
+fn find_device(host: &cpal::Host, name: Option<&str>) -> Option<cpal::Device> {
+    let Some(name) = name else {
+        return host.default_output_device();
+    };
+    let wanted = name.to_lowercase();
+    host.output_devices().ok()?.find(|device| {
+        device
+            .name()
+            .is_ok_and(|name| name.to_lowercase().contains(&wanted))
+    })
+}
+
+fn open(config: &ClickConfig, cues: Arc<ClickCues>) -> Result<(String, cpal::Stream)> {
+    let host = cpal::default_host();
+    let device = find_device(&host, config.device.as_deref())
+        .ok_or_else(|| anyhow::anyhow!("No output device matching {:?}", config.device))?;
+    let name = device.name().unwrap_or_default();
+    let stream_config: cpal::StreamConfig = device.default_output_config()?.into();
+    let channels = stream_config.channels as usize;
+
+    let mut synth = ClickSynth::new(stream_config.sample_rate.0, config.volume);
+    let stream = device.build_output_stream(
+        &stream_config,
+        move |output: &mut [f32], _: &_| {
+            while let Some(cue) = cues.queue.pop() {
+                synth.trigger(cue);
+            }
+            synth.fill(output, channels);
+        },
+        |e| crash::log(format!("⚠️ Click output error: {}", e)),
+        None,
+    )?;
+    stream.play()?;
+    Ok((name, stream))
+}
+
+// Runs the click output on its own thread, the stream lives as long as the process
+pub fn start(config: ClickConfig, cues: Arc<ClickCues>) {
+    std::thread::spawn(move || match open(&config, cues) {
+        Ok((name, _stream)) => {
+            crash::log(format!("🎧 Click track on {}", name));
+            loop {
+                std::thread::park();
+            }
+        }
+        Err(e) => crash::log(format!("❌ Click track disabled: {}", e)),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clicks_ring_then_stop() {
+        let mut synth = ClickSynth::new(48000, 1.0);
+        let mut output = vec![1.0; 4000];
+        synth.fill(&mut output, 2);
+        assert!(output.iter().all(|&s| s == 0.0));
+
+        synth.trigger(Cue::Beat);
+        synth.fill(&mut output, 2);
+        // 30 ms at 48 kHz, both channels carry the same sample
+        assert!(output[..2880].iter().any(|&s| s.abs() > 0.5));
+        assert!(output.chunks(2).all(|frame| frame[0] == frame[1]));
+        assert!(output[2880..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_disabled_cues_are_dropped() {
+        let cues = ClickCues::new(&ClickConfig {
+            alerts: false,
+            ..ClickConfig::default()
+        });
+        cues.push(Cue::Warning);
+        cues.push(Cue::Downbeat);
+        assert_eq!(cues.queue.pop(), Some(Cue::Downbeat));
+        assert_eq!(cues.queue.pop(), None);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::config::{ColorBalance, ColorCorrectionConfig, NEUTRAL_WHITE_POINT};
+use crate::led::{kelvin_to_rgb, MAX_COLOR_TEMP, MIN_COLOR_TEMP};
+use anyhow::{anyhow, bail, Result};
+
+pub const MIN_GAMMA: f32 = 0.5;
+pub const MAX_GAMMA: f32 = 4.0;
+
+// The balances a command changes: one controller's, or all of them for None
+pub fn balances(
+    config: &mut ColorCorrectionConfig,
+    controller: Option<usize>,
+) -> Result<&mut [ColorBalance]> {
+    match controller {
+        Some(index) => config
+            .controllers
+            .get_mut(index..=index)
+            .ok_or_else(|| anyhow!("No controller {}", index)),
+        None => Ok(&mut config.controllers),
+    }
+}
+
+pub fn check_gamma(gamma: f32) -> Result<()> {
+    if !(MIN_GAMMA..=MAX_GAMMA).contains(&gamma) {
+        bail!("Gamma must be between {} and {}", MIN_GAMMA, MAX_GAMMA);
+    }
+    Ok(())
+}
+
+pub fn check_balance(white_point: f32, gains: [f32; 3]) -> Result<()> {
+    if !(MIN_COLOR_TEMP..=MAX_COLOR_TEMP).contains(&white_point) {
+        bail!(
+            "White point must be between {} K and {} K",
+            MIN_COLOR_TEMP,
+            MAX_COLOR_TEMP
+        );
+    }
+    if gains.iter().any(|gain| !(0.0..=1.0).contains(gain)) {
+        bail!("Channel gains must be between 0 and 1");
+    }
+    Ok(())
+}
+
+// A controller's balance baked into one lookup table per channel, applied to its DMX data on
+// the way out. None leaves the values untouched.
+pub struct ColorCorrection {
+    tables: Option<Box<[[u8; 256]; 3]>>,
+}
+
+impl ColorCorrection {
+    pub fn new(balance: &ColorBalance) -> Self {
+        if balance.is_neutral() {
+            return Self { tables: None };
+        }
+
+        // Values from a hand-edited config are held to what the commands accept
+        let gamma = if balance.gamma.is_finite() {
+            balance.gamma.clamp(MIN_GAMMA, MAX_GAMMA)
+        } else {
+            1.0
+        };
+        let white_point = if balance.white_point.is_finite() {
+            balance.white_point.clamp(MIN_COLOR_TEMP, MAX_COLOR_TEMP)
+        } else {
+            NEUTRAL_WHITE_POINT
+        };
+        let white = white_gains(white_point);
+
+        let mut tables = Box::new([[0u8; 256]; 3]);
+        for (channel, table) in tables.iter_mut().enumerate() {
+            let gain = balance.gains[channel].clamp(0.0, 1.0) * white[channel];
+            for (value, entry) in table.iter_mut().enumerate() {
+                let level = (value as f32 / 255.0).powf(gamma) * gain;
+                *entry = (level * 255.0).round() as u8;
+            }
+        }
+        Self {
+            tables: Some(tables),
+        }
+    }
+
+    pub fn apply(&self, dmx_data: &mut [u8]) {
+        let Some(tables) = &self.tables else {
+            return;
+        };
+        for pixel in dmx_data.chunks_exact_mut(3) {
+            for (value, table) in pixel.iter_mut().zip(tables.iter()) {
+                *value = table[*value as usize];
+            }
+        }
+    }
+}
+
+// Channel gains that turn the neutral white into `kelvin`, the strongest channel left at full
+fn white_gains(kelvin: f32) -> [f32; 3] {
+    let (r, g, b) = kelvin_to_rgb(kelvin);
+    let (nr, ng, nb) = kelvin_to_rgb(NEUTRAL_WHITE_POINT);
+    let gains = [r / nr, g / ng, b / nb];
+    let strongest = gains.iter().copied().fold(f32::MIN, f32::max);
+    gains.map(|gain| gain / strongest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_follow_the_balance() {
+        let mut dmx = [0, 128, 255, 64, 64, 64];
+        ColorCorrection::new(&ColorBalance::default()).apply(&mut dmx);
+        assert_eq!(dmx, [0, 128, 255, 64, 64, 64]);
+
+        let gamma = ColorCorrection::new(&ColorBalance {
+            gamma: 2.2,
+            ..ColorBalance::default()
+        });
+        gamma.apply(&mut dmx);
+        assert_eq!(dmx[0], 0);
+        assert_eq!(dmx[2], 255);
+        assert!(dmx[1] < 64);
+
+        let warm = ColorCorrection::new(&ColorBalance {
+            white_point: 3000.0,
+            ..ColorBalance::default()
+        });
+        let mut white = [255; 3];
+        warm.apply(&mut white);
+        assert_eq!(white[0], 255);
+        assert!(white[2] < white[1] && white[1] < 255);
+
+        let dimmer_green = ColorCorrection::new(&ColorBalance {
+            gains: [1.0, 0.5, 1.0],
+            ..ColorBalance::default()
+        });
+        let mut white = [200; 3];
+        dimmer_green.apply(&mut white);
+        assert_eq!(white, [200, 100, 200]);
+    }
+
+    #[test]
+    fn test_commands_pick_their_controllers() {
+        let mut config = ColorCorrectionConfig::default();
+        balances(&mut config, Some(2)).unwrap()[0].gamma = 2.0;
+        assert_eq!(config.controllers[2].gamma, 2.0);
+        assert_eq!(config.controllers[1].gamma, 1.0);
+        assert_eq!(balances(&mut config, None).unwrap().len(), 4);
+        assert!(balances(&mut config, Some(4)).is_err());
+
+        assert!(check_gamma(2.2).is_ok());
+        assert!(check_gamma(f32::NAN).is_err());
+        assert!(check_balance(6500.0, [1.0, 0.9, 0.8]).is_ok());
+        assert!(check_balance(500.0, [1.0; 3]).is_err());
+        assert!(check_balance(6500.0, [1.2, 1.0, 1.0]).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::config::MatrixConfig;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const REPORT_DIR: &str = "commissioning_reports";
+const CONTROLLERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestColor {
+    Red,
+    Green,
+    Blue,
+    White,
+}
+
+impl TestColor {
+    const ALL: [Self; 4] = [Self::Red, Self::Green, Self::Blue, Self::White];
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Self::Red => [255, 0, 0],
+            Self::Green => [0, 255, 0],
+            Self::Blue => [0, 0, 255],
+            Self::White => [255, 255, 255],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::White => "white",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Check {
+    // Every strip of a controller in one color: dead channels and swapped color order
+    Color { controller: usize, color: TestColor },
+    // A single strip lit white: crossed cables and strips on the wrong universe
+    Strip { controller: usize, strip: usize },
+}
+
+impl Check {
+    pub fn describe(&self, matrix: &MatrixConfig) -> String {
+        match *self {
+            Self::Color { controller, color } => {
+                format!("Controller {}: all strips {}", controller + 1, color.name())
+            }
+            Self::Strip { controller, strip } => format!(
+                "Controller {}: strip {} only (band {}, {})",
+                controller + 1,
+                strip + 1,
+                strip / 2 + 1,
+                if matrix.strip_runs_up(strip % 2) {
+                    "up"
+                } else {
+                    "down"
+                }
+            ),
+        }
+    }
+
+    pub fn render(&self, matrix: &MatrixConfig) -> Vec<u8> {
+        let strips = strips_per_controller(matrix);
+        let (columns, rgb) = match *self {
+            Self::Color { controller, color } => {
+                let start = controller * strips;
+                (start..start + strips, color.rgb())
+            }
+            Self::Strip { controller, strip } => {
+                let col = controller * strips + strip;
+                (col..col + 1, TestColor::White.rgb())
+            }
+        };
+
+        let mut frame = vec![0u8; matrix.frame_len()];
+        for y in 0..matrix.height {
+            for x in columns.clone().filter(|&x| x < matrix.width) {
+                let idx = (y * matrix.width + x) * 3;
+                frame[idx..idx + 3].copy_from_slice(&rgb);
+            }
+        }
+        frame
+    }
+}
+
+fn strips_per_controller(matrix: &MatrixConfig) -> usize {
+    matrix.bands_per_controller() * 2
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Mark {
+    pub passed: bool,
+    pub note: String,
+}
+
+// Guided walk through every controller, color and strip; the operator marks each check
+// pass/fail from the control app while the wall shows the matching pattern
+pub struct Commissioning {
+    matrix: MatrixConfig,
+    started: u64,
+    checks: Vec<Check>,
+    marks: Vec<Option<Mark>>,
+    index: usize,
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Commissioning {
+    // Strips past the last column (a narrower wall's last controller) are skipped
+    pub fn new(matrix: &MatrixConfig) -> Self {
+        let strips = strips_per_controller(matrix);
+        let mut checks = Vec::new();
+        for controller in 0..CONTROLLERS {
+            for color in TestColor::ALL {
+                checks.push(Check::Color { controller, color });
+            }
+            for strip in (0..strips).filter(|strip| controller * strips + strip < matrix.width) {
+                checks.push(Check::Strip { controller, strip });
+            }
+        }
+
+        Self {
+            matrix: *matrix,
+            started: timestamp(),
+            marks: vec![None; checks.len()],
+            checks,
+            index: 0,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Check> {
+        self.checks.get(self.index)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.index >= self.checks.len()
+    }
+
+    pub fn frame(&self) -> Option<Vec<u8>> {
+        self.current().map(|check| check.render(&self.matrix))
+    }
+
+    // Records the outcome of the current check and moves on to the next one
+    pub fn mark(&mut self, passed: bool, note: String) {
+        if let Some(mark) = self.marks.get_mut(self.index) {
+            *mark = Some(Mark { passed, note });
+            self.index += 1;
+        }
+    }
+
+    pub fn back(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    fn count(&self, passed: bool) -> usize {
+        self.marks
+            .iter()
+            .flatten()
+            .filter(|mark| mark.passed == passed)
+            .count()
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        serde_json::json!({
+            "active": true,
+            "index": self.index,
+            "total": self.checks.len(),
+            "check": self.current(),
+            "description": self.current().map(|check| check.describe(&self.matrix)),
+            "passed": self.count(true),
+            "failed": self.count(false),
+            "complete": self.is_complete(),
+        })
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "DJ-4LED commissioning report");
+        let _ = writeln!(report, "Started: {} (unix)", self.started);
+        let _ = writeln!(report, "Finished: {} (unix)", timestamp());
+        let _ = writeln!(
+            report,
+            "Passed: {}  Failed: {}  Unchecked: {}",
+            self.count(true),
+            self.count(false),
+            self.marks.iter().filter(|mark| mark.is_none()).count()
+        );
+        let _ = writeln!(report);
+
+        for (check, mark) in self.checks.iter().zip(&self.marks) {
+            let status = match mark {
+                Some(mark) if mark.passed => "PASS",
+                Some(_) => "FAIL",
+                None => "----",
+            };
+            let _ = write!(report, "[{}] {}", status, check.describe(&self.matrix));
+            match mark {
+                Some(mark) if !mark.note.is_empty() => {
+                    let _ = writeln!(report, " - {}", mark.note);
+                }
+                _ => {
+                    let _ = writeln!(report);
+                }
+            }
+        }
+
+        report
+    }
+
+    pub fn write_report(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("commissioning-{}.txt", self.started));
+        fs::write(&path, self.report())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_check_lights_one_column() {
+        let matrix = MatrixConfig::default();
+        let frame = Check::Strip {
+            controller: 2,
+            strip: 5,
+        }
+        .render(&matrix);
+        let lit: Vec<usize> = (0..128)
+            .filter(|&x| frame[(10 * 128 + x) * 3] > 0)
+            .collect();
+        assert_eq!(lit, vec![69]);
+
+        let frame = Check::Color {
+            controller: 1,
+            color: TestColor::Blue,
+        }
+        .render(&matrix);
+        assert_eq!(&frame[32 * 3..32 * 3 + 3], &[0, 0, 255]);
+        assert_eq!(&frame[64 * 3..64 * 3 + 3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_marks_advance_and_fill_report() {
+        let mut session = Commissioning::new(&MatrixConfig::default());
+        assert_eq!(session.checks.len(), CONTROLLERS * (4 + 32));
+        let narrow = MatrixConfig {
+            width: 56,
+            ..MatrixConfig::default()
+        };
+        assert_eq!(
+            Commissioning::new(&narrow).checks.len(),
+            CONTROLLERS * 4 + 56
+        );
+
+        session.mark(true, String::new());
+        session.mark(false, "green missing on strip 3".to_string());
+        session.back();
+        assert_eq!(session.index, 1);
+        session.mark(false, "green missing on strip 3".to_string());
+
+        let status = session.status();
+        assert_eq!(status["passed"], 1);
+        assert_eq!(status["failed"], 1);
+        assert_eq!(status["index"], 2);
+
+        let report = session.report();
+        assert!(report.contains("[PASS] Controller 1: all strips red"));
+        assert!(report.contains("[FAIL] Controller 1: all strips green - green missing on strip 3"));
+        assert!(report.contains("Unchecked: 142"));
+
+        while !session.is_complete() {
+            session.mark(true, String::new());
+        }
+        assert!(session.frame().is_none());
+        session.mark(true, String::new());
+        assert_eq!(session.count(true), session.checks.len() - 1);
+    }
+}
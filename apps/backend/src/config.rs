@@ -1,6 +1,11 @@
+use crate::ihub::router::{route_issues, HubRoute};
+use crate::ihub::MatrixLayout;
+use crate::messages::Language;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -8,6 +13,70 @@ pub struct Config {
     pub led: LedConfig,
     pub effects: EffectsConfig,
     pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub stream: StreamConfig,
+    #[serde(default)]
+    pub startup: StartupConfig,
+    #[serde(default)]
+    pub trim: TrimConfig,
+    #[serde(default)]
+    pub color_correction: ColorCorrectionConfig,
+    #[serde(default)]
+    pub sensitivity: SensitivityConfig,
+    #[serde(default)]
+    pub spectrum: SpectrumConfig,
+    #[serde(default)]
+    pub smoothing: SmoothingConfig,
+    #[serde(default)]
+    pub band_weights: BandWeights,
+    #[serde(default)]
+    pub decibels: DecibelConfig,
+    #[serde(default)]
+    pub noise_gate: NoiseGateConfig,
+    #[serde(default)]
+    pub high_pass: HighPassConfig,
+    #[serde(default)]
+    pub pacing: PacingConfig,
+    #[serde(default)]
+    pub hub: HubConfig,
+    #[serde(default)]
+    pub spectrogram: SpectrogramConfig,
+    #[serde(default)]
+    pub energy_saver: EnergySaverConfig,
+    #[serde(default)]
+    pub av_delay: AvDelayConfig,
+    #[serde(default)]
+    pub click: ClickConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub splash: SplashConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default)]
+    pub triggers: TriggersConfig,
+    #[serde(default)]
+    pub playlist: PlaylistConfig,
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+    #[serde(default)]
+    pub osc: OscConfig,
+    #[serde(default)]
+    pub content_policy: ContentPolicyConfig,
+    #[serde(default)]
+    pub messages: MessagesConfig,
+    #[serde(default)]
+    pub effect_parameters: EffectParameters,
+    #[serde(default)]
+    pub canvases: Vec<CanvasConfig>,
+    #[serde(default)]
+    pub edge_strips: Vec<EdgeStripConfig>,
+    #[serde(default)]
+    pub matrix: MatrixConfig,
+    #[serde(default)]
+    pub output_profile: OutputProfileConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,8 +87,19 @@ pub struct AudioConfig {
     pub device_name: Option<String>,
     pub gain: f32,
     pub noise_floor: f32,
+    // Capture devices by priority (name substrings, "default" = system input)
+    #[serde(default)]
+    pub devices: Vec<String>,
+    // How often a fallback device gives the preferred ones another try
+    #[serde(default)]
+    pub device_retry_secs: Option<f32>,
 }
 
+// Art-Net nodes may drop their output after 4 s without data
+pub const MAX_KEEP_ALIVE_SECS: f32 = 4.0;
+// One Art-Net controller per quarter of the wall
+pub const OUTPUT_CONTROLLERS: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LedConfig {
     pub controllers: Vec<String>,
@@ -27,6 +107,13 @@ pub struct LedConfig {
     pub brightness: f32,
     pub gamma_correction: f32,
     pub color_temperature: f32,
+    #[serde(default)]
+    pub power_limit: Option<f32>,
+    #[serde(default)]
+    pub keep_alive_secs: Option<f32>,
+    // Named controller sets the output can be switched to live, e.g. show rig vs rehearsal panel
+    #[serde(default)]
+    pub target_sets: BTreeMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +124,9 @@ pub struct EffectsConfig {
     pub high_boost: f32,
     pub particle_limit: usize,
     pub wave_speed: f32,
+    // Seeds the effects' random generators: same seed and same audio, same frames
+    #[serde(default)]
+    pub seed: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +135,988 @@ pub struct PerformanceConfig {
     pub frame_skip: bool,
     pub adaptive_quality: bool,
     pub max_cpu_percent: f32,
+    #[serde(default)]
+    pub max_loaded_effects: Option<usize>,
+    // Cores for the render, LED output and audio threads; unset lets the OS schedule them
+    #[serde(default)]
+    pub render_core: Option<usize>,
+    #[serde(default)]
+    pub led_core: Option<usize>,
+    #[serde(default)]
+    pub audio_core: Option<usize>,
+    // Lowers every visualizer thread's priority so a DAW on the same machine goes first
+    #[serde(default)]
+    pub low_priority: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamConfig {
+    pub monitor_max_secs: u64,
+    pub display_max_secs: u64,
+    // Per-client preview bandwidth by role, in kbit/s (0 = unlimited)
+    #[serde(default)]
+    pub monitor_max_kbps: u32,
+    #[serde(default)]
+    pub display_max_kbps: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupConfig {
+    pub effect: Option<String>,
+    pub palette: Option<String>,
+    pub brightness: f32,
+    pub ramp_in_secs: f32,
+    pub start_blacked_out: bool,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            effect: None,
+            palette: None,
+            brightness: 1.0,
+            ramp_in_secs: 0.0,
+            start_blacked_out: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplashAnimation {
+    // `text` lit by a white band sweeping across
+    LogoSweep,
+    // Red, green, blue then white wiped across the wall: every channel gets lit once
+    ColorWipe,
+}
+
+// Played once when the output starts, before the startup effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplashConfig {
+    pub enabled: bool,
+    pub animation: SplashAnimation,
+    pub duration_secs: f32,
+    pub text: String,
+    // Directory of 128x128 PNG frames played in name order at `fps`, instead of `animation`
+    pub frames_dir: Option<String>,
+    pub fps: f32,
+}
+
+impl Default for SplashConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            animation: SplashAnimation::LogoSweep,
+            duration_secs: 3.0,
+            text: "DJ-4LED".to_string(),
+            frames_dir: None,
+            fps: 25.0,
+        }
+    }
+}
+
+// Art-Net carries at most 170 RGB pixels per universe
+pub const PIXELS_PER_UNIVERSE: usize = 170;
+pub const MAX_MATRIX_SIDE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Serpentine {
+    // First strip of each band runs bottom to top, the second top to bottom
+    UpFirst,
+    DownFirst,
+}
+
+// Geometry of the wall. Frames are `width` x `height`; every band of two columns is
+// wired as one strip up and one down, rows stretched over `leds_per_strip` LEDs each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatrixConfig {
+    pub width: usize,
+    pub height: usize,
+    pub serpentine: Serpentine,
+    // First then second strip of a band, in wiring order
+    pub leds_per_strip: [usize; 2],
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        Self {
+            width: 128,
+            height: 128,
+            serpentine: Serpentine::UpFirst,
+            leds_per_strip: [130, 129],
+        }
+    }
+}
+
+impl MatrixConfig {
+    // "96x64" from the command line, strips keeping their configured wiring
+    pub fn with_size(self, size: &str) -> Option<Self> {
+        let (width, height) = size.split_once(['x', 'X'])?;
+        Some(Self {
+            width: width.trim().parse().ok()?,
+            height: height.trim().parse().ok()?,
+            ..self
+        })
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.width * self.height * 3
+    }
+
+    pub fn bands(&self) -> usize {
+        self.width / 2
+    }
+
+    // Bands driven by each controller, the last one taking what is left
+    pub fn bands_per_controller(&self) -> usize {
+        self.bands().div_ceil(OUTPUT_CONTROLLERS)
+    }
+
+    pub fn band_leds(&self) -> usize {
+        self.leds_per_strip[0] + self.leds_per_strip[1]
+    }
+
+    pub fn universes_per_band(&self) -> usize {
+        self.band_leds().div_ceil(PIXELS_PER_UNIVERSE)
+    }
+
+    // Whether the given strip of a band (0 or 1) starts at the bottom row
+    pub fn strip_runs_up(&self, strip: usize) -> bool {
+        (strip == 0) == (self.serpentine == Serpentine::UpFirst)
+    }
+
+    // Frame pixel shown by `led` along one strip of a band, counted from its data input
+    pub fn strip_pixel(&self, band: usize, strip: usize, led: usize) -> (usize, usize) {
+        let row = (led * self.height / self.leds_per_strip[strip]).min(self.height - 1);
+        let y = if self.strip_runs_up(strip) {
+            self.height - 1 - row
+        } else {
+            row
+        };
+        (band * 2 + strip, y)
+    }
+
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.width == 0 || !self.width.is_multiple_of(2) || self.width > MAX_MATRIX_SIDE {
+            issues.push(format!(
+                "matrix.width must be even and at most {}: {}",
+                MAX_MATRIX_SIDE, self.width
+            ));
+        }
+        if self.height == 0 || self.height > MAX_MATRIX_SIDE {
+            issues.push(format!("matrix.height out of range: {}", self.height));
+        }
+        if self.leds_per_strip.contains(&0) {
+            issues.push("matrix.leds_per_strip must be non-zero".to_string());
+        }
+        issues
+    }
+}
+
+pub const TRIM_QUARTERS: usize = 4;
+pub const TRIM_STRIPS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrimConfig {
+    pub quarters: Vec<f32>,
+    pub strips: Vec<f32>,
+}
+
+impl Default for TrimConfig {
+    fn default() -> Self {
+        Self {
+            quarters: vec![1.0; TRIM_QUARTERS],
+            strips: vec![1.0; TRIM_STRIPS],
+        }
+    }
+}
+
+impl TrimConfig {
+    // Missing entries default to full brightness, extra ones are dropped
+    pub fn normalized(mut self) -> Self {
+        self.quarters.resize(TRIM_QUARTERS, 1.0);
+        self.strips.resize(TRIM_STRIPS, 1.0);
+        for trim in self.quarters.iter_mut().chain(self.strips.iter_mut()) {
+            *trim = trim.clamp(0.0, 1.0);
+        }
+        self
+    }
+}
+
+pub const NEUTRAL_WHITE_POINT: f32 = 6500.0;
+
+// How one controller's LEDs turn values into light: gamma for their response curve, a white
+// point in kelvin and per-channel gains to match batches that don't share the same white
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorBalance {
+    pub gamma: f32,
+    pub white_point: f32,
+    pub gains: [f32; 3],
+}
+
+impl Default for ColorBalance {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            white_point: NEUTRAL_WHITE_POINT,
+            gains: [1.0; 3],
+        }
+    }
+}
+
+impl ColorBalance {
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+// One balance per controller, in the same order as led.controllers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorCorrectionConfig {
+    pub controllers: Vec<ColorBalance>,
+}
+
+impl Default for ColorCorrectionConfig {
+    fn default() -> Self {
+        Self {
+            controllers: vec![ColorBalance::default(); OUTPUT_CONTROLLERS],
+        }
+    }
+}
+
+impl ColorCorrectionConfig {
+    // Missing controllers are left uncorrected, extra ones are dropped
+    pub fn normalized(mut self) -> Self {
+        self.controllers
+            .resize(OUTPUT_CONTROLLERS, ColorBalance::default());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpectrumConfig {
+    #[serde(default)]
+    pub normalization: Vec<f32>,
+}
+
+pub const MAX_INPUT_TRIM: f32 = 4.0;
+pub const MAX_SENSITIVITY: f32 = 20.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensitivityConfig {
+    pub input_trim: f32,
+    pub effects: BTreeMap<String, f32>,
+}
+
+impl Default for SensitivityConfig {
+    fn default() -> Self {
+        Self {
+            input_trim: 1.0,
+            effects: BTreeMap::new(),
+        }
+    }
+}
+
+impl SensitivityConfig {
+    pub fn normalized(mut self) -> Self {
+        self.input_trim = self.input_trim.clamp(0.0, MAX_INPUT_TRIM);
+        for sensitivity in self.effects.values_mut() {
+            *sensitivity = sensitivity.clamp(0.0, MAX_SENSITIVITY);
+        }
+        self
+    }
+
+    // Per-effect override (or the effect's own default) scaled by the global input trim
+    pub fn effective(&self, effect: &str, base: f32) -> f32 {
+        self.effects.get(effect).copied().unwrap_or(base) * self.input_trim
+    }
+}
+
+pub const MIN_DB: f32 = -120.0;
+pub const MIN_DB_RANGE: f32 = 6.0;
+
+// Optional dBFS mapping of the spectrum: min_db..max_db becomes 0..1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecibelConfig {
+    pub enabled: bool,
+    pub min_db: f32,
+    pub max_db: f32,
+    pub noise_floor_db: Option<f32>,
+}
+
+impl Default for DecibelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_db: -60.0,
+            max_db: 0.0,
+            noise_floor_db: None,
+        }
+    }
+}
+
+impl DecibelConfig {
+    pub fn normalized(mut self) -> Self {
+        self.max_db = self.max_db.clamp(MIN_DB + MIN_DB_RANGE, 0.0);
+        self.min_db = self.min_db.clamp(MIN_DB, self.max_db - MIN_DB_RANGE);
+        self.noise_floor_db = self.noise_floor_db.map(|floor| floor.clamp(MIN_DB, 0.0));
+        self
+    }
+}
+
+// Input gate in front of the FFT: opens above threshold_db (dBFS peak), stays open for
+// hold_ms after the signal drops, then fades out over release_ms
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseGateConfig {
+    pub enabled: bool,
+    pub threshold_db: f32,
+    pub hold_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for NoiseGateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_db: -50.0,
+            hold_ms: 250.0,
+            release_ms: 150.0,
+        }
+    }
+}
+
+impl NoiseGateConfig {
+    pub fn normalized(mut self) -> Self {
+        self.threshold_db = self.threshold_db.clamp(MIN_DB, 0.0);
+        self.hold_ms = self.hold_ms.clamp(0.0, 5000.0);
+        self.release_ms = self.release_ms.clamp(1.0, 5000.0);
+        self
+    }
+}
+
+// Removes stage rumble and handling noise below cutoff_hz before analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighPassConfig {
+    pub enabled: bool,
+    pub cutoff_hz: f32,
+}
+
+impl Default for HighPassConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cutoff_hz: 30.0,
+        }
+    }
+}
+
+impl HighPassConfig {
+    pub fn normalized(mut self) -> Self {
+        self.cutoff_hz = self.cutoff_hz.clamp(10.0, 500.0);
+        self
+    }
+}
+
+// ArtDMX pacing for one Art-Net node: `gap_us` pause after every `burst` packets
+// (0 = never pause) and at most `max_fps` frames per second (0 = unlimited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControllerPacing {
+    pub burst: usize,
+    pub gap_us: u64,
+    pub max_fps: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PacingConfig {
+    pub default: ControllerPacing,
+    // Overrides keyed by controller address, e.g. "192.168.1.47:6454"
+    pub controllers: BTreeMap<String, ControllerPacing>,
+    // Also cap each node at the refresh rate it reports in ArtPollReply
+    pub honor_reported_refresh: bool,
+}
+
+impl Default for PacingConfig {
+    fn default() -> Self {
+        Self {
+            default: ControllerPacing::default(),
+            controllers: BTreeMap::new(),
+            honor_reported_refresh: true,
+        }
+    }
+}
+
+impl PacingConfig {
+    pub fn for_controller(&self, address: &str) -> ControllerPacing {
+        self.controllers
+            .get(address)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+// eHuB routing table; no routes means no eHuB output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HubConfig {
+    pub routes: Vec<HubRoute>,
+}
+
+pub const MAX_SPECTROGRAM_MINUTES: f32 = 120.0;
+
+// Rolling history of the processed spectrum kept for post-show analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectrogramConfig {
+    pub minutes: f32,
+    pub columns_per_second: u32,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self {
+            minutes: 10.0,
+            columns_per_second: 10,
+        }
+    }
+}
+
+// Idle policy: no audio and no operator activity for `idle_minutes` lowers the output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnergySaverConfig {
+    pub enabled: bool,
+    pub idle_minutes: f32,
+    // Loudest band below this counts as silence
+    pub audio_threshold: f32,
+    pub fps: u32,
+    pub brightness: f32,
+    pub effect: Option<String>,
+}
+
+impl Default for EnergySaverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_minutes: 10.0,
+            audio_threshold: 0.05,
+            fps: 20,
+            brightness: 0.3,
+            effect: None,
+        }
+    }
+}
+
+// Largest extra canvas, in pixels; the wall itself is 128x128
+pub const MAX_CANVAS_PIXELS: usize = 128 * 128;
+
+// Output besides the wall, e.g. a pixel strip along the DJ booth, with its own effect.
+// Pixels go out as Art-Net in row order, 170 per universe starting at `universe`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasConfig {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub effect: String,
+    pub target: String,
+    #[serde(default)]
+    pub universe: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WallEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+// Strip framing the wall, lit from the wall's own edge instead of an effect. Top and
+// bottom strips run left to right, side strips top to bottom, unless `reversed`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeStripConfig {
+    pub name: String,
+    pub edge: WallEdge,
+    pub pixels: usize,
+    // Rows (or columns) averaged in from the edge
+    pub depth: usize,
+    // Box blur radius along the strip, in strip pixels
+    pub blur: usize,
+    pub gain: f32,
+    pub reversed: bool,
+    pub target: String,
+    pub universe: u16,
+}
+
+impl Default for EdgeStripConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            edge: WallEdge::Top,
+            pixels: 0,
+            depth: 8,
+            blur: 2,
+            gain: 1.0,
+            reversed: false,
+            target: String::new(),
+            universe: 0,
+        }
+    }
+}
+
+pub const MAX_AV_DELAY_MS: u32 = 500;
+
+// Holds the output back so light hits land with the PA rather than with the booth feed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvDelayConfig {
+    pub ms: u32,
+}
+
+// Palette constraint applied to everything sent to the LEDs, for cameras or color-blind viewers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputProfile {
+    #[default]
+    Standard,
+    // Deep saturated reds bloom into blobs on camera sensors
+    CameraSafe,
+    // Color-blind friendly: colors the viewer would confuse are pushed apart
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl OutputProfile {
+    pub const ALL: [OutputProfile; 5] = [
+        Self::Standard,
+        Self::CameraSafe,
+        Self::Deuteranopia,
+        Self::Protanopia,
+        Self::Tritanopia,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::CameraSafe => "camera_safe",
+            Self::Deuteranopia => "deuteranopia",
+            Self::Protanopia => "protanopia",
+            Self::Tritanopia => "tritanopia",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputProfileConfig {
+    pub profile: OutputProfile,
+}
+
+// Click track in the operator's headphones: a tick on every detected beat, a low tone
+// on output alerts, to check beat lock in a booth too loud to hear the wall's timing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClickConfig {
+    pub enabled: bool,
+    // Output device name substring, None = system default output
+    pub device: Option<String>,
+    pub volume: f32,
+    pub beats: bool,
+    pub alerts: bool,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: None,
+            volume: 0.5,
+            beats: true,
+            alerts: true,
+        }
+    }
+}
+
+// Per-minute output and audio aggregates kept in SQLite, to compare shows over months
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    pub enabled: bool,
+    pub path: String,
+    // Minutes older than this are deleted
+    pub retention_days: u32,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: "stats.db".to_string(),
+            retention_days: 365,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    // Art-Net sends to a controller keep failing
+    ControllerOffline,
+    // Render FPS under `threshold` (not checked while the energy saver is sleeping)
+    LowFps,
+    // Share of failed Art-Net packets over `threshold`, 0..1
+    PacketLoss,
+    // Loudest band under `threshold`
+    AudioSilent,
+}
+
+// Fires once its condition has held for `for_secs`, clears when it stops holding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub kind: AlertKind,
+    #[serde(default)]
+    pub threshold: f32,
+    #[serde(default)]
+    pub for_secs: f32,
+    // Only checked between show_hours.start and show_hours.end
+    #[serde(default)]
+    pub show_hours_only: bool,
+}
+
+impl AlertRule {
+    fn new(name: &str, kind: AlertKind, threshold: f32, for_secs: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            threshold,
+            for_secs,
+            show_hours_only: false,
+        }
+    }
+}
+
+// Local "HH:MM" times; an end before the start runs past midnight
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShowHours {
+    pub start: String,
+    pub end: String,
+}
+
+// Minutes since midnight of a "HH:MM" time
+pub fn parse_time_of_day(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttConfig {
+    // "host:port" of the broker
+    pub broker: String,
+    pub topic: String,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+}
+
+fn default_mqtt_client_id() -> String {
+    "dj4led".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub enabled: bool,
+    pub rules: Vec<AlertRule>,
+    // Every alert fired or cleared is POSTed as JSON to each of these URLs
+    pub webhooks: Vec<String>,
+    pub mqtt: Option<MqttConfig>,
+    pub show_hours: Option<ShowHours>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: vec![
+                AlertRule::new(
+                    "controller offline",
+                    AlertKind::ControllerOffline,
+                    0.0,
+                    10.0,
+                ),
+                AlertRule::new("low fps", AlertKind::LowFps, 50.0, 5.0),
+                AlertRule::new("packet loss", AlertKind::PacketLoss, 0.05, 10.0),
+                AlertRule {
+                    show_hours_only: true,
+                    ..AlertRule::new("audio silent", AlertKind::AudioSilent, 0.02, 120.0)
+                },
+            ],
+            webhooks: Vec::new(),
+            mqtt: None,
+            show_hours: None,
+        }
+    }
+}
+
+// "<days> <HH:MM> <action>" rules run by the backend, e.g. "daily 04:00 blackout"
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub rules: Vec<String>,
+}
+
+// How the wall goes from one playlist entry's effect to the next
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionStyle {
+    #[default]
+    Cut,
+    // Both effects render while the new one blends over the old
+    Crossfade,
+    // The old effect dims out, then the new one comes up
+    FadeThroughBlack,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    // Matched like `set_effect_by_name`
+    pub effect: String,
+    pub duration_secs: f32,
+    #[serde(default)]
+    pub transition: TransitionStyle,
+    #[serde(default = "default_transition_secs")]
+    pub transition_secs: f32,
+}
+
+fn default_transition_secs() -> f32 {
+    2.0
+}
+
+// Effects played in turn, each for its duration, then around again
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistConfig {
+    pub entries: Vec<PlaylistEntry>,
+    // A detected drop moves on to the next entry before its time is up
+    pub advance_on_drop: bool,
+}
+
+// Buttons at the booth wired to a serial or USB GPIO adapter that sends one line per press,
+// e.g. "B3". Bindings take the schedule's actions plus "preset <name>" and "panic".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggersConfig {
+    // e.g. "/dev/ttyUSB0"; unset leaves triggers off
+    pub device: Option<String>,
+    pub baud_rate: u32,
+    // Presses of the same input closer than this are contact bounce
+    pub debounce_ms: u64,
+    pub bindings: BTreeMap<String, String>,
+}
+
+impl Default for TriggersConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            baud_rate: 9600,
+            debounce_ms: 50,
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+// JSON control API for web dashboards and tablets: the UDP commands plus the preview stream
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebSocketConfig {
+    // e.g. "0.0.0.0:8082"; unset leaves the API off
+    pub address: Option<String>,
+}
+
+// OSC from DJ and lighting software (TouchOSC, Ableton), addresses under /dj4led
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscConfig {
+    // UDP port, e.g. 9000; unset leaves OSC off
+    pub port: Option<u16>,
+}
+
+// Content a venue doesn't allow, e.g. no strobe-like flashing for a photosensitive audience
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentPolicyConfig {
+    pub enabled: bool,
+    // Effect names, accent kinds ("flash", "shockwave", "glitch") and "strobe"
+    pub blacklist: Vec<String>,
+    // Flashes per second the strobe and the accents may reach; unset leaves their own rates
+    pub max_flash_hz: Option<f32>,
+}
+
+impl ContentPolicyConfig {
+    pub fn blocks(&self, name: &str) -> bool {
+        self.enabled
+            && self
+                .blacklist
+                .iter()
+                .any(|blocked| blocked.trim().eq_ignore_ascii_case(name))
+    }
+
+    // Shortest time allowed between two flashes, zero without a cap
+    pub fn min_flash_interval(&self) -> Duration {
+        match self.max_flash_hz {
+            Some(hz) if self.enabled && hz > 0.0 => {
+                Duration::try_from_secs_f32(1.0 / hz).unwrap_or(Duration::MAX)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+// Status and error texts sent to clients, which get a stable code alongside to translate them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessagesConfig {
+    pub language: Language,
+}
+
+// Knob values per effect name, e.g. [effect_parameters.flames] cooling = 0.04
+pub type EffectParameters = BTreeMap<String, BTreeMap<String, f32>>;
+
+pub const MIN_SMOOTHING: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmoothingProfile {
+    Punchy = 0,
+    Smooth = 1,
+    Broadcast = 2,
+    Custom = 3,
+}
+
+impl SmoothingProfile {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Punchy),
+            1 => Some(Self::Smooth),
+            2 => Some(Self::Broadcast),
+            3 => Some(Self::Custom),
+            _ => None,
+        }
+    }
+}
+
+// Fraction of the gap to the new value closed per frame, rising (attack) and falling (release)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BandSmoothing {
+    pub attack: f32,
+    pub release: f32,
+}
+
+impl BandSmoothing {
+    pub fn new(attack: f32, release: f32) -> Self {
+        Self {
+            attack: attack.clamp(MIN_SMOOTHING, 1.0),
+            release: release.clamp(MIN_SMOOTHING, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmoothingConfig {
+    pub profile: SmoothingProfile,
+    pub bass: BandSmoothing,
+    pub mid: BandSmoothing,
+    pub high: BandSmoothing,
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        Self::preset(SmoothingProfile::Punchy)
+    }
+}
+
+impl SmoothingConfig {
+    pub fn preset(profile: SmoothingProfile) -> Self {
+        let (bass, mid, high) = match profile {
+            SmoothingProfile::Punchy => ((1.0, 0.35), (1.0, 0.3), (1.0, 0.3)),
+            SmoothingProfile::Smooth => ((0.6, 0.15), (0.5, 0.12), (0.4, 0.1)),
+            SmoothingProfile::Broadcast => ((0.3, 0.05), (0.3, 0.05), (0.25, 0.05)),
+            SmoothingProfile::Custom => ((1.0, 1.0), (1.0, 1.0), (1.0, 1.0)),
+        };
+        Self {
+            profile,
+            bass: BandSmoothing::new(bass.0, bass.1),
+            mid: BandSmoothing::new(mid.0, mid.1),
+            high: BandSmoothing::new(high.0, high.1),
+        }
+    }
+
+    // Named profiles always use their preset values; only custom keeps the per-group numbers
+    pub fn normalized(self) -> Self {
+        if self.profile != SmoothingProfile::Custom {
+            return Self::preset(self.profile);
+        }
+        Self {
+            bass: BandSmoothing::new(self.bass.attack, self.bass.release),
+            mid: BandSmoothing::new(self.mid.attack, self.mid.release),
+            high: BandSmoothing::new(self.high.attack, self.high.release),
+            ..self
+        }
+    }
+}
+
+// Gain per band group applied to the scaled spectrum, e.g. to push the kick for techno
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandWeights {
+    pub bass: f32,
+    pub mid: f32,
+    pub high: f32,
+}
+
+impl Default for BandWeights {
+    fn default() -> Self {
+        Self {
+            bass: 1.0,
+            mid: 1.0,
+            high: 1.0,
+        }
+    }
+}
+
+impl BandWeights {
+    pub fn normalized(self) -> Self {
+        Self {
+            bass: self.bass.clamp(0.0, MAX_INPUT_TRIM),
+            mid: self.mid.clamp(0.0, MAX_INPUT_TRIM),
+            high: self.high.clamp(0.0, MAX_INPUT_TRIM),
+        }
+    }
 }
 
 impl Default for Config {
@@ -55,6 +1127,8 @@ impl Default for Config {
                 buffer_size: 64,
                 channels: 1,
                 device_name: None,
+                devices: Vec::new(),
+                device_retry_secs: None,
                 gain: 1.0,
                 noise_floor: 0.01,
             },
@@ -69,6 +1143,9 @@ impl Default for Config {
                 brightness: 1.0,
                 gamma_correction: 2.2,
                 color_temperature: 1.0,
+                power_limit: None,
+                keep_alive_secs: None,
+                target_sets: BTreeMap::new(),
             },
             effects: EffectsConfig {
                 smoothing_factor: 0.7,
@@ -77,13 +1154,51 @@ impl Default for Config {
                 high_boost: 1.0,
                 particle_limit: 2000,
                 wave_speed: 1.0,
+                seed: 0,
             },
             performance: PerformanceConfig {
                 thread_pool_size: 4,
                 frame_skip: false,
                 adaptive_quality: true,
                 max_cpu_percent: 80.0,
+                max_loaded_effects: None,
+                render_core: None,
+                led_core: None,
+                audio_core: None,
+                low_priority: false,
             },
+            stream: StreamConfig::default(),
+            trim: TrimConfig::default(),
+            color_correction: ColorCorrectionConfig::default(),
+            sensitivity: SensitivityConfig::default(),
+            spectrum: SpectrumConfig::default(),
+            smoothing: SmoothingConfig::default(),
+            band_weights: BandWeights::default(),
+            decibels: DecibelConfig::default(),
+            noise_gate: NoiseGateConfig::default(),
+            high_pass: HighPassConfig::default(),
+            pacing: PacingConfig::default(),
+            hub: HubConfig::default(),
+            spectrogram: SpectrogramConfig::default(),
+            energy_saver: EnergySaverConfig::default(),
+            av_delay: AvDelayConfig::default(),
+            click: ClickConfig::default(),
+            stats: StatsConfig::default(),
+            alerts: AlertsConfig::default(),
+            splash: SplashConfig::default(),
+            schedule: ScheduleConfig::default(),
+            triggers: TriggersConfig::default(),
+            playlist: PlaylistConfig::default(),
+            websocket: WebSocketConfig::default(),
+            osc: OscConfig::default(),
+            content_policy: ContentPolicyConfig::default(),
+            messages: MessagesConfig::default(),
+            effect_parameters: EffectParameters::new(),
+            matrix: MatrixConfig::default(),
+            output_profile: OutputProfileConfig::default(),
+            canvases: Vec::new(),
+            edge_strips: Vec::new(),
+            startup: StartupConfig::default(),
         }
     }
 }
@@ -117,6 +1232,34 @@ impl Config {
         Ok(())
     }
 
+    // Rewrites only the keys of one section so comments and other sections are kept
+    pub fn save_section<T: Serialize>(
+        key: &str,
+        value: &T,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::save_section_to(Path::new("config.toml"), key, value)
+    }
+
+    fn save_section_to<T: Serialize>(
+        path: &Path,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path).unwrap_or_default();
+        let mut document = contents.parse::<toml_edit::DocumentMut>()?;
+        let section = toml::to_string(value)?.parse::<toml_edit::DocumentMut>()?;
+
+        if !document.contains_table(key) {
+            document[key] = toml_edit::table();
+        }
+        for (name, item) in section.iter() {
+            document[key][name] = item.clone();
+        }
+
+        fs::write(path, document.to_string())?;
+        Ok(())
+    }
+
     pub fn production() -> Self {
         Self {
             audio: AudioConfig {
@@ -124,6 +1267,8 @@ impl Config {
                 buffer_size: 128,
                 channels: 1,
                 device_name: None,
+                devices: Vec::new(),
+                device_retry_secs: None,
                 gain: 1.2,
                 noise_floor: 0.02,
             },
@@ -138,6 +1283,9 @@ impl Config {
                 brightness: 0.9,
                 gamma_correction: 2.2,
                 color_temperature: 1.0,
+                power_limit: None,
+                keep_alive_secs: None,
+                target_sets: BTreeMap::new(),
             },
             effects: EffectsConfig {
                 smoothing_factor: 0.6,
@@ -146,12 +1294,55 @@ impl Config {
                 high_boost: 1.1,
                 particle_limit: 1500,
                 wave_speed: 1.2,
+                seed: 0,
             },
             performance: PerformanceConfig {
                 thread_pool_size: 6,
                 frame_skip: true,
                 adaptive_quality: true,
                 max_cpu_percent: 70.0,
+                max_loaded_effects: None,
+                render_core: None,
+                led_core: None,
+                audio_core: None,
+                low_priority: false,
+            },
+            stream: StreamConfig::default(),
+            trim: TrimConfig::default(),
+            color_correction: ColorCorrectionConfig::default(),
+            sensitivity: SensitivityConfig::default(),
+            spectrum: SpectrumConfig::default(),
+            smoothing: SmoothingConfig::default(),
+            band_weights: BandWeights::default(),
+            decibels: DecibelConfig::default(),
+            noise_gate: NoiseGateConfig::default(),
+            high_pass: HighPassConfig::default(),
+            pacing: PacingConfig::default(),
+            hub: HubConfig::default(),
+            spectrogram: SpectrogramConfig::default(),
+            energy_saver: EnergySaverConfig::default(),
+            av_delay: AvDelayConfig::default(),
+            click: ClickConfig::default(),
+            stats: StatsConfig::default(),
+            alerts: AlertsConfig::default(),
+            splash: SplashConfig::default(),
+            schedule: ScheduleConfig::default(),
+            triggers: TriggersConfig::default(),
+            playlist: PlaylistConfig::default(),
+            websocket: WebSocketConfig::default(),
+            osc: OscConfig::default(),
+            content_policy: ContentPolicyConfig::default(),
+            messages: MessagesConfig::default(),
+            effect_parameters: EffectParameters::new(),
+            matrix: MatrixConfig::default(),
+            output_profile: OutputProfileConfig::default(),
+            canvases: Vec::new(),
+            edge_strips: Vec::new(),
+            startup: StartupConfig {
+                brightness: 0.9,
+                ramp_in_secs: 5.0,
+                start_blacked_out: true,
+                ..StartupConfig::default()
             },
         }
     }
@@ -163,6 +1354,8 @@ impl Config {
                 buffer_size: 256,
                 channels: 1,
                 device_name: None,
+                devices: Vec::new(),
+                device_retry_secs: None,
                 gain: 1.0,
                 noise_floor: 0.03,
             },
@@ -177,6 +1370,9 @@ impl Config {
                 brightness: 0.8,
                 gamma_correction: 2.0,
                 color_temperature: 1.0,
+                power_limit: None,
+                keep_alive_secs: None,
+                target_sets: BTreeMap::new(),
             },
             effects: EffectsConfig {
                 smoothing_factor: 0.5,
@@ -185,12 +1381,54 @@ impl Config {
                 high_boost: 1.0,
                 particle_limit: 1000,
                 wave_speed: 1.0,
+                seed: 0,
             },
             performance: PerformanceConfig {
                 thread_pool_size: 4,
                 frame_skip: true,
                 adaptive_quality: true,
                 max_cpu_percent: 60.0,
+                max_loaded_effects: None,
+                render_core: None,
+                led_core: None,
+                audio_core: None,
+                low_priority: false,
+            },
+            stream: StreamConfig::default(),
+            trim: TrimConfig::default(),
+            color_correction: ColorCorrectionConfig::default(),
+            sensitivity: SensitivityConfig::default(),
+            spectrum: SpectrumConfig::default(),
+            smoothing: SmoothingConfig::default(),
+            band_weights: BandWeights::default(),
+            decibels: DecibelConfig::default(),
+            noise_gate: NoiseGateConfig::default(),
+            high_pass: HighPassConfig::default(),
+            pacing: PacingConfig::default(),
+            hub: HubConfig::default(),
+            spectrogram: SpectrogramConfig::default(),
+            energy_saver: EnergySaverConfig::default(),
+            av_delay: AvDelayConfig::default(),
+            click: ClickConfig::default(),
+            stats: StatsConfig::default(),
+            alerts: AlertsConfig::default(),
+            splash: SplashConfig::default(),
+            schedule: ScheduleConfig::default(),
+            triggers: TriggersConfig::default(),
+            playlist: PlaylistConfig::default(),
+            websocket: WebSocketConfig::default(),
+            osc: OscConfig::default(),
+            content_policy: ContentPolicyConfig::default(),
+            messages: MessagesConfig::default(),
+            effect_parameters: EffectParameters::new(),
+            matrix: MatrixConfig::default(),
+            output_profile: OutputProfileConfig::default(),
+            canvases: Vec::new(),
+            edge_strips: Vec::new(),
+            startup: StartupConfig {
+                brightness: 0.8,
+                ramp_in_secs: 2.0,
+                ..StartupConfig::default()
             },
         }
     }
@@ -220,6 +1458,203 @@ impl Config {
         }
     }
 
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+
+        if self.led.fps == 0 || self.led.fps > 240 {
+            issues.push(format!("led.fps out of range: {}", self.led.fps));
+        }
+        if !(0.0..=1.0).contains(&self.led.brightness) {
+            issues.push(format!(
+                "led.brightness out of range: {}",
+                self.led.brightness
+            ));
+        }
+        if let Some(limit) = self.led.power_limit {
+            if !(0.0..=1.0).contains(&limit) {
+                issues.push(format!("led.power_limit out of range: {}", limit));
+            }
+        }
+        if let Some(secs) = self.led.keep_alive_secs {
+            if !(secs > 0.0 && secs <= MAX_KEEP_ALIVE_SECS) {
+                issues.push(format!("led.keep_alive_secs out of range: {}", secs));
+            }
+        }
+        if self.led.gamma_correction <= 0.0 {
+            issues.push(format!(
+                "led.gamma_correction must be positive: {}",
+                self.led.gamma_correction
+            ));
+        }
+        for controller in &self.led.controllers {
+            if controller.parse::<std::net::SocketAddr>().is_err() {
+                issues.push(format!("invalid controller address: {}", controller));
+            }
+        }
+        for (name, controllers) in &self.led.target_sets {
+            if controllers.len() != OUTPUT_CONTROLLERS {
+                issues.push(format!(
+                    "led.target_sets.{} needs {} controllers, got {}",
+                    name,
+                    OUTPUT_CONTROLLERS,
+                    controllers.len()
+                ));
+            }
+            for controller in controllers {
+                if controller.parse::<std::net::SocketAddr>().is_err() {
+                    issues.push(format!(
+                        "invalid controller address in led.target_sets.{}: {}",
+                        name, controller
+                    ));
+                }
+            }
+        }
+        if !(0.0..=1.0).contains(&self.startup.brightness) {
+            issues.push(format!(
+                "startup.brightness out of range: {}",
+                self.startup.brightness
+            ));
+        }
+        if self.startup.ramp_in_secs < 0.0 {
+            issues.push(format!(
+                "startup.ramp_in_secs must not be negative: {}",
+                self.startup.ramp_in_secs
+            ));
+        }
+        if self.audio.sample_rate == 0 || self.audio.buffer_size == 0 {
+            issues.push("audio.sample_rate and audio.buffer_size must be non-zero".to_string());
+        }
+        issues.extend(route_issues(&self.hub.routes));
+        if !(self.spectrogram.minutes > 0.0 && self.spectrogram.minutes <= MAX_SPECTROGRAM_MINUTES)
+        {
+            issues.push(format!(
+                "spectrogram.minutes out of range: {}",
+                self.spectrogram.minutes
+            ));
+        }
+        if !(1..=60).contains(&self.spectrogram.columns_per_second) {
+            issues.push(format!(
+                "spectrogram.columns_per_second out of range: {}",
+                self.spectrogram.columns_per_second
+            ));
+        }
+        if self.energy_saver.idle_minutes <= 0.0 {
+            issues.push(format!(
+                "energy_saver.idle_minutes must be positive: {}",
+                self.energy_saver.idle_minutes
+            ));
+        }
+        if !(1..=60).contains(&self.energy_saver.fps) {
+            issues.push(format!(
+                "energy_saver.fps out of range: {}",
+                self.energy_saver.fps
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.energy_saver.brightness) {
+            issues.push(format!(
+                "energy_saver.brightness out of range: {}",
+                self.energy_saver.brightness
+            ));
+        }
+        if self.av_delay.ms > MAX_AV_DELAY_MS {
+            issues.push(format!("av_delay.ms out of range: {}", self.av_delay.ms));
+        }
+        if !(0.0..=1.0).contains(&self.click.volume) {
+            issues.push(format!("click.volume out of range: {}", self.click.volume));
+        }
+        if self.splash.duration_secs <= 0.0 {
+            issues.push(format!(
+                "splash.duration_secs must be positive: {}",
+                self.splash.duration_secs
+            ));
+        }
+        if self.splash.fps <= 0.0 {
+            issues.push(format!("splash.fps must be positive: {}", self.splash.fps));
+        }
+        if self.stats.retention_days == 0 {
+            issues.push("stats.retention_days must be at least 1".to_string());
+        }
+        for rule in &self.alerts.rules {
+            if rule.for_secs < 0.0 {
+                issues.push(format!(
+                    "alert {}: for_secs must not be negative: {}",
+                    rule.name, rule.for_secs
+                ));
+            }
+        }
+        for url in &self.alerts.webhooks {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                issues.push(format!("invalid alert webhook: {}", url));
+            }
+        }
+        if let Some(hours) = &self.alerts.show_hours {
+            for time in [&hours.start, &hours.end] {
+                if parse_time_of_day(time).is_none() {
+                    issues.push(format!("invalid alerts.show_hours time: {}", time));
+                }
+            }
+        }
+        for (index, canvas) in self.canvases.iter().enumerate() {
+            if canvas.name.is_empty() || canvas.name == "wall" {
+                issues.push(format!("canvas {}: invalid name {:?}", index, canvas.name));
+            }
+            if self.canvases[..index]
+                .iter()
+                .any(|other| other.name == canvas.name)
+            {
+                issues.push(format!("canvas {}: duplicate name {}", index, canvas.name));
+            }
+            let pixels = canvas.width.saturating_mul(canvas.height);
+            if pixels == 0 || pixels > MAX_CANVAS_PIXELS {
+                issues.push(format!(
+                    "canvas {}: size out of range: {}x{}",
+                    canvas.name, canvas.width, canvas.height
+                ));
+            }
+            if canvas.target.parse::<std::net::SocketAddr>().is_err() {
+                issues.push(format!(
+                    "canvas {}: invalid target {}",
+                    canvas.name, canvas.target
+                ));
+            }
+        }
+        issues.extend(self.matrix.issues());
+        if !self.hub.routes.is_empty() && !MatrixLayout::for_matrix(&self.matrix).fits() {
+            issues.push(format!(
+                "matrix {}x{} is too large for the eHuB entity numbering",
+                self.matrix.width, self.matrix.height
+            ));
+        }
+        for strip in &self.edge_strips {
+            if strip.pixels == 0 || strip.pixels > MAX_CANVAS_PIXELS {
+                issues.push(format!(
+                    "edge strip {}: pixels out of range: {}",
+                    strip.name, strip.pixels
+                ));
+            }
+            if !(1..=64).contains(&strip.depth) {
+                issues.push(format!(
+                    "edge strip {}: depth out of range: {}",
+                    strip.name, strip.depth
+                ));
+            }
+            if !(0.0..=4.0).contains(&strip.gain) {
+                issues.push(format!(
+                    "edge strip {}: gain out of range: {}",
+                    strip.name, strip.gain
+                ));
+            }
+            if strip.target.parse::<std::net::SocketAddr>().is_err() {
+                issues.push(format!(
+                    "edge strip {}: invalid target {}",
+                    strip.name, strip.target
+                ));
+            }
+        }
+
+        issues
+    }
+
     pub fn get_frame_delay_ms(&self) -> u64 {
         1000 / self.led.fps as u64
     }
@@ -228,3 +1663,84 @@ impl Config {
         self.performance.frame_skip && frame_time_ms > self.get_frame_delay_ms() as f32 * 1.5
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_section_keeps_other_sections() {
+        let path = std::env::temp_dir().join(format!("section_test_{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "# Réglages\n[led]\nfps = 60 # max\n[smoothing]\nbass = { attack = 1.0, release = 0.35 }\n",
+        )
+        .unwrap();
+
+        let mut trim = TrimConfig::default();
+        trim.quarters[2] = 0.85;
+        Config::save_section_to(&path, "trim", &trim).unwrap();
+
+        let mut sensitivity = SensitivityConfig::default();
+        sensitivity.effects.insert("rain".to_string(), 6.0);
+        Config::save_section_to(&path, "sensitivity", &sensitivity).unwrap();
+
+        let mut smoothing = SmoothingConfig::preset(SmoothingProfile::Custom);
+        smoothing.bass = BandSmoothing::new(0.8, 0.2);
+        Config::save_section_to(&path, "smoothing", &smoothing).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("fps = 60 # max"));
+
+        #[derive(Deserialize)]
+        struct Sections {
+            trim: TrimConfig,
+            sensitivity: SensitivityConfig,
+            smoothing: SmoothingConfig,
+        }
+        let reloaded: Sections = toml::from_str(&contents).unwrap();
+        assert_eq!(reloaded.trim, trim);
+        assert_eq!(reloaded.sensitivity, sensitivity);
+        assert_eq!(reloaded.smoothing, smoothing);
+        assert_eq!(reloaded.sensitivity.effective("rain", 4.0), 6.0);
+        assert_eq!(reloaded.sensitivity.effective("heartbeat", 5.0), 5.0);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_trim_normalized() {
+        let trim = TrimConfig {
+            quarters: vec![1.4, 0.5],
+            strips: vec![],
+        }
+        .normalized();
+        assert_eq!(trim.quarters, vec![1.0, 0.5, 1.0, 1.0]);
+        assert_eq!(trim.strips.len(), TRIM_STRIPS);
+    }
+
+    #[test]
+    fn test_matrix_geometry() {
+        let wall = MatrixConfig::default();
+        assert_eq!(wall.universes_per_band(), 2);
+        assert_eq!(wall.strip_pixel(3, 0, 0), (6, 127));
+        assert_eq!(wall.strip_pixel(3, 1, 128), (7, 127));
+
+        let screen = wall.with_size("96x40").unwrap();
+        assert_eq!((screen.width, screen.height), (96, 40));
+        assert_eq!(screen.bands_per_controller(), 12);
+        assert!(screen.issues().is_empty());
+
+        let flipped = MatrixConfig {
+            serpentine: Serpentine::DownFirst,
+            leds_per_strip: [40, 40],
+            ..screen
+        };
+        assert_eq!(flipped.universes_per_band(), 1);
+        assert_eq!(flipped.strip_pixel(0, 0, 0), (0, 0));
+        assert_eq!(flipped.strip_pixel(0, 1, 0), (1, 39));
+
+        assert!(wall.with_size("96").is_none());
+        assert_eq!(wall.with_size("95x0").unwrap().issues().len(), 2);
+    }
+}
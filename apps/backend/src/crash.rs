@@ -0,0 +1,267 @@
+use crate::AppState;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CRASH_DIR: &str = "crash_reports";
+const ARCHIVE_DIR: &str = "archived";
+const CONFIG_PATH: &str = "config.toml";
+const MAX_LOG_LINES: usize = 200;
+
+static LOG_BUFFER: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static APP_STATE: OnceLock<Arc<AppState>> = OnceLock::new();
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub timestamp: u64,
+    pub thread: String,
+    pub message: String,
+    pub location: String,
+    pub backtrace: String,
+    pub logs: Vec<String>,
+    pub config: Option<String>,
+    pub state: Option<StateSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSummary {
+    pub effect: Option<usize>,
+    pub color_mode: Option<String>,
+    pub blackout: Option<bool>,
+    pub strobe: Option<bool>,
+    pub brightness: Option<f32>,
+    pub render_fps: Option<f64>,
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub fn log(line: impl Into<String>) {
+    let line = line.into();
+    if !QUIET.load(Ordering::Relaxed) {
+        println!("{}", line);
+    }
+
+    let mut buffer = LOG_BUFFER.lock();
+    if buffer.len() == MAX_LOG_LINES {
+        buffer.pop_front();
+    }
+    buffer.push_back(format!("[{}] {}", timestamp(), line));
+}
+
+// While a full-screen UI owns the terminal, lines only go to the buffer
+#[cfg(feature = "tui")]
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+// The last `count` buffered lines, oldest first
+#[cfg(feature = "tui")]
+pub fn recent_logs(count: usize) -> Vec<String> {
+    let buffer = LOG_BUFFER.lock();
+    buffer
+        .iter()
+        .skip(buffer.len().saturating_sub(count))
+        .cloned()
+        .collect()
+}
+
+pub fn install(state: Arc<AppState>) {
+    let _ = APP_STATE.set(state);
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match write_report(Path::new(CRASH_DIR), &build_report(info)) {
+            Ok(path) => eprintln!("💥 Crash report written to {}", path.display()),
+            Err(e) => eprintln!("💥 Failed to write crash report: {}", e),
+        }
+        default_hook(info);
+    }));
+}
+
+fn build_report(info: &PanicHookInfo) -> CrashReport {
+    let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+
+    // The panicking thread may hold any lock, so never block while collecting
+    let logs = LOG_BUFFER
+        .try_lock()
+        .map(|buffer| buffer.iter().cloned().collect())
+        .unwrap_or_default();
+
+    CrashReport {
+        timestamp: timestamp(),
+        thread: std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string(),
+        message,
+        location: info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default(),
+        backtrace: Backtrace::force_capture().to_string(),
+        logs,
+        config: fs::read_to_string(CONFIG_PATH).ok(),
+        state: APP_STATE.get().map(|state| summarize_state(state)),
+    }
+}
+
+fn summarize_state(state: &AppState) -> StateSummary {
+    let engine = state.effect_engine.try_lock();
+    let output = state.output.try_lock();
+
+    StateSummary {
+        effect: engine.as_ref().map(|e| e.current_effect()),
+        color_mode: engine.as_ref().map(|e| e.color_mode().to_string()),
+        blackout: output.as_ref().map(|o| o.blackout),
+        strobe: output.as_ref().map(|o| o.strobe),
+        brightness: output.as_ref().map(|o| o.brightness),
+        render_fps: state.render_health.try_lock().map(|h| h.average_fps()),
+    }
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let content = serde_json::to_string_pretty(report)?;
+
+    // Timestamps only have one-second resolution, so never overwrite a report
+    // written in the same second and pick the next free suffix instead
+    let mut attempt = 0;
+    loop {
+        let name = match attempt {
+            0 => format!("crash-{}.json", report.timestamp),
+            n => format!("crash-{}-{}.json", report.timestamp, n),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn read_reports(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    reports.sort_by_key(|r| r.timestamp);
+    reports
+}
+
+fn archive_reports(dir: &Path) -> std::io::Result<usize> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(0);
+    };
+
+    let archive = dir.join(ARCHIVE_DIR);
+    let mut archived = 0;
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.extension().is_some_and(|ext| ext == "json") {
+            fs::create_dir_all(&archive)?;
+            if let Some(name) = path.file_name() {
+                fs::rename(&path, archive.join(name))?;
+                archived += 1;
+            }
+        }
+    }
+    Ok(archived)
+}
+
+pub fn pending_reports() -> Vec<CrashReport> {
+    read_reports(Path::new(CRASH_DIR))
+}
+
+pub fn dismiss_reports() -> std::io::Result<usize> {
+    archive_reports(Path::new(CRASH_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(timestamp: u64) -> CrashReport {
+        CrashReport {
+            timestamp,
+            thread: "main".to_string(),
+            message: "boom".to_string(),
+            location: "src/main.rs:1:1".to_string(),
+            backtrace: String::new(),
+            logs: vec!["line".to_string()],
+            config: None,
+            state: None,
+        }
+    }
+
+    #[test]
+    fn test_log_buffer_is_capped() {
+        for i in 0..MAX_LOG_LINES + 10 {
+            log(format!("line {}", i));
+        }
+        assert!(LOG_BUFFER.lock().len() <= MAX_LOG_LINES);
+    }
+
+    #[test]
+    fn test_report_roundtrip_and_dismiss() {
+        let dir = std::env::temp_dir().join(format!("crash_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        write_report(&dir, &report(2)).unwrap();
+        write_report(&dir, &report(1)).unwrap();
+
+        let reports = read_reports(&dir);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].timestamp, 1);
+
+        assert_eq!(archive_reports(&dir).unwrap(), 2);
+        assert!(read_reports(&dir).is_empty());
+        assert_eq!(read_reports(&dir.join(ARCHIVE_DIR)).len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reports_in_the_same_second_are_kept() {
+        let dir =
+            std::env::temp_dir().join(format!("crash_test_same_second_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let first = write_report(&dir, &report(5)).unwrap();
+        let second = write_report(&dir, &report(5)).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(read_reports(&dir).len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
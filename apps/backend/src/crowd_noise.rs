@@ -0,0 +1,211 @@
+use crate::audio::SAMPLE_RATE;
+use crate::effects::EffectRng;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+// Claps per second from a full house at the top of the envelope
+const MAX_CLAPS_PER_SEC: f32 = 600.0;
+// A hand clap is a few milliseconds of noise dying away
+const CLAP_DECAY_MS: (f32, f32) = (3.0, 12.0);
+// Cheering and shuffling under the claps, relative to the envelope level
+const BED_LEVEL: f32 = 0.08;
+
+// One simulated ovation: rises to `peak` (0-1) over the attack, holds, then dies away
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrowdEnvelope {
+    pub peak: f32,
+    pub attack_secs: f32,
+    pub hold_secs: f32,
+    pub release_secs: f32,
+}
+
+impl Default for CrowdEnvelope {
+    fn default() -> Self {
+        Self {
+            peak: 0.8,
+            attack_secs: 1.5,
+            hold_secs: 4.0,
+            release_secs: 3.0,
+        }
+    }
+}
+
+impl CrowdEnvelope {
+    fn seconds(&self) -> f32 {
+        self.attack_secs + self.hold_secs + self.release_secs
+    }
+
+    // 0-1 at `t` seconds into the ovation
+    fn level(&self, t: f32) -> f32 {
+        let shape = if t < self.attack_secs {
+            t / self.attack_secs
+        } else if t < self.attack_secs + self.hold_secs {
+            1.0
+        } else {
+            1.0 - (t - self.attack_secs - self.hold_secs) / self.release_secs
+        };
+        self.peak * shape.clamp(0.0, 1.0)
+    }
+}
+
+struct Clap {
+    gain: f32,
+    decay: f32,
+}
+
+// Applause-like test signal mixed into the capture, so the applaudimetre can be rehearsed
+// and its sensitivity set without a crowd in the room
+pub struct CrowdNoise {
+    envelope: CrowdEnvelope,
+    // Samples into the ovation, None when off
+    position: Option<usize>,
+    claps: Vec<Clap>,
+    rng: EffectRng,
+}
+
+impl Default for CrowdNoise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrowdNoise {
+    pub fn new() -> Self {
+        Self {
+            envelope: CrowdEnvelope::default(),
+            position: None,
+            claps: Vec::new(),
+            rng: EffectRng::new(0),
+        }
+    }
+
+    pub fn start(&mut self, envelope: CrowdEnvelope) -> Result<()> {
+        if !(0.0..=1.0).contains(&envelope.peak) {
+            bail!("Crowd noise peak must be between 0 and 1");
+        }
+        let times = [
+            envelope.attack_secs,
+            envelope.hold_secs,
+            envelope.release_secs,
+        ];
+        if times.iter().any(|secs| !secs.is_finite() || *secs < 0.0) {
+            bail!("Crowd noise times must be positive seconds");
+        }
+        self.envelope = envelope;
+        self.position = Some(0);
+        self.claps.clear();
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.position = None;
+        self.claps.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.position.is_some()
+    }
+
+    fn elapsed_secs(&self) -> Option<f32> {
+        self.position
+            .map(|position| position as f32 / SAMPLE_RATE as f32)
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        let elapsed = self.elapsed_secs();
+        serde_json::json!({
+            "active": elapsed.is_some(),
+            "elapsed_secs": elapsed,
+            "level": elapsed.map_or(0.0, |t| self.envelope.level(t)),
+            "envelope": self.envelope,
+        })
+    }
+
+    // The samples with the crowd added, None when the simulation is off. Turns itself off
+    // once the envelope is over.
+    pub fn mix(&mut self, samples: &[f32]) -> Option<Vec<f32>> {
+        let mut position = self.position?;
+        let end = (self.envelope.seconds() * SAMPLE_RATE as f32) as usize;
+
+        let mixed = samples
+            .iter()
+            .map(|&sample| {
+                let level = self.envelope.level(position as f32 / SAMPLE_RATE as f32);
+                position += 1;
+
+                if self.rng.next_f32() < MAX_CLAPS_PER_SEC * level / SAMPLE_RATE as f32 {
+                    let (shortest, longest) = CLAP_DECAY_MS;
+                    let decay_ms = shortest + self.rng.next_f32() * (longest - shortest);
+                    self.claps.push(Clap {
+                        gain: (0.3 + self.rng.next_f32() * 0.7) * level.sqrt(),
+                        decay: (-1000.0 / (decay_ms * SAMPLE_RATE as f32)).exp(),
+                    });
+                }
+
+                let mut crowd = (self.rng.next_f32() * 2.0 - 1.0) * level * BED_LEVEL;
+                for clap in &mut self.claps {
+                    crowd += (self.rng.next_f32() * 2.0 - 1.0) * clap.gain;
+                    clap.gain *= clap.decay;
+                }
+                self.claps.retain(|clap| clap.gain > 0.001);
+
+                (sample + crowd).clamp(-1.0, 1.0)
+            })
+            .collect();
+
+        self.position = (position < end).then_some(position);
+        if self.position.is_none() {
+            self.claps.clear();
+        }
+        Some(mixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_ovation_follows_its_envelope() {
+        let mut crowd = CrowdNoise::new();
+        let silence = vec![0.0; SAMPLE_RATE as usize / 4];
+        assert!(crowd.mix(&silence).is_none());
+
+        crowd
+            .start(CrowdEnvelope {
+                peak: 1.0,
+                attack_secs: 1.0,
+                hold_secs: 1.0,
+                release_secs: 1.0,
+            })
+            .unwrap();
+        // Quarter-second blocks through attack, hold and release
+        let loudness: Vec<f32> = (0..12)
+            .map(|_| rms(&crowd.mix(&silence).unwrap()))
+            .collect();
+        assert!(loudness[0] < loudness[3]);
+        assert!(loudness[5] > 0.1);
+        assert!(loudness[11] < loudness[8]);
+        assert!(!crowd.is_active());
+        assert!(crowd.mix(&silence).is_none());
+
+        let quiet = CrowdEnvelope {
+            peak: 0.2,
+            ..CrowdEnvelope::default()
+        };
+        crowd.start(quiet).unwrap();
+        let quiet_hold: Vec<f32> = (0..12).flat_map(|_| crowd.mix(&silence).unwrap()).collect();
+        assert!(rms(&quiet_hold[SAMPLE_RATE as usize * 2..]) < loudness[5]);
+        assert!(quiet_hold.iter().all(|s| (-1.0..=1.0).contains(s)));
+
+        let nonsense = CrowdEnvelope {
+            attack_secs: -1.0,
+            ..CrowdEnvelope::default()
+        };
+        assert!(crowd.start(nonsense).is_err());
+    }
+}
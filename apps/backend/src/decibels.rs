@@ -0,0 +1,177 @@
+use crate::config::{Config, DecibelConfig, MIN_DB, MIN_DB_RANGE};
+use crate::crash;
+use crate::fft;
+use crate::AppState;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_CAPTURE: Duration = Duration::from_secs(5);
+pub const MAX_CAPTURE: Duration = Duration::from_secs(60);
+const NOISE_MARGIN_DB: f32 = 3.0;
+
+struct Capture {
+    started: Instant,
+    duration: Duration,
+    sum: f64,
+    frames: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseResult {
+    Learned(f32),
+    NoSignal,
+}
+
+pub fn to_dbfs(level: f32) -> f32 {
+    if level > 0.0 {
+        (20.0 * level.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+// Turns raw band levels into 0..1, either adaptively or on a fixed dBFS window whose
+// bottom sits just above the measured room noise so quiet and loud venues read alike
+pub struct SpectrumScale {
+    config: DecibelConfig,
+    capture: Option<Capture>,
+}
+
+impl SpectrumScale {
+    pub fn new(config: DecibelConfig) -> Self {
+        Self {
+            config: config.normalized(),
+            capture: None,
+        }
+    }
+
+    pub fn config(&self) -> &DecibelConfig {
+        &self.config
+    }
+
+    pub fn set_window(&mut self, enabled: bool, min_db: f32, max_db: f32) {
+        self.config = DecibelConfig {
+            enabled,
+            min_db,
+            max_db,
+            ..self.config.clone()
+        }
+        .normalized();
+    }
+
+    pub fn start_noise_capture(&mut self, duration: Duration) {
+        self.capture = Some(Capture {
+            started: Instant::now(),
+            duration: duration.min(MAX_CAPTURE),
+            sum: 0.0,
+            frames: 0,
+        });
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    pub fn floor_db(&self) -> f32 {
+        let floor = self
+            .config
+            .noise_floor_db
+            .map_or(self.config.min_db, |noise| {
+                (noise + NOISE_MARGIN_DB).max(self.config.min_db)
+            });
+        floor.min(self.config.max_db - MIN_DB_RANGE)
+    }
+
+    // Feeds the room noise capture; returns the outcome once the window has elapsed
+    pub fn record(&mut self, levels: &[f32]) -> Option<NoiseResult> {
+        let capture = self.capture.as_mut()?;
+
+        if levels.iter().any(|&l| l > 0.0) {
+            capture.sum += levels.iter().map(|&l| l as f64).sum::<f64>() / levels.len() as f64;
+            capture.frames += 1;
+        }
+
+        if capture.started.elapsed() < capture.duration {
+            return None;
+        }
+
+        let capture = self.capture.take()?;
+        if capture.frames == 0 {
+            return Some(NoiseResult::NoSignal);
+        }
+
+        let noise = to_dbfs((capture.sum / capture.frames as f64) as f32);
+        self.config.noise_floor_db = Some(noise);
+        Some(NoiseResult::Learned(noise))
+    }
+
+    pub fn scale(&self, levels: &[f32]) -> Vec<f32> {
+        if !self.config.enabled {
+            return fft::normalize_linear(levels);
+        }
+
+        let floor = self.floor_db();
+        let range = self.config.max_db - floor;
+        levels
+            .iter()
+            .map(|&level| ((to_dbfs(level) - floor) / range).clamp(0.0, 1.0))
+            .collect()
+    }
+}
+
+// Runs on the render path: feeds an active noise capture, persists its result and scales the levels
+pub fn process(state: &AppState, levels: &[f32]) -> Vec<f32> {
+    let mut scale = state.decibels.lock();
+
+    match scale.record(levels) {
+        Some(NoiseResult::Learned(noise)) => {
+            crash::log(format!("🔇 Noise floor measured at {:.1} dBFS", noise));
+            if let Err(e) = Config::save_section("decibels", scale.config()) {
+                crash::log(format!("⚠️ Failed to save noise floor: {}", e));
+            }
+        }
+        Some(NoiseResult::NoSignal) => {
+            crash::log("🔇 Room is below the input gate, keeping the previous noise floor");
+        }
+        None => {}
+    }
+
+    scale.scale(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_db_window_maps_to_unit_range() {
+        let scale = SpectrumScale::new(DecibelConfig {
+            enabled: true,
+            ..DecibelConfig::default()
+        });
+
+        let half = 10f32.powf(-30.0 / 20.0);
+        let spectrum = scale.scale(&[1.0, half, 1e-4, 0.0]);
+        assert_eq!(spectrum[0], 1.0);
+        assert!((spectrum[1] - 0.5).abs() < 1e-4);
+        assert_eq!(spectrum[2], 0.0);
+        assert_eq!(spectrum[3], 0.0);
+    }
+
+    #[test]
+    fn test_noise_capture_raises_floor() {
+        let mut scale = SpectrumScale::new(DecibelConfig {
+            enabled: true,
+            ..DecibelConfig::default()
+        });
+        scale.start_noise_capture(Duration::ZERO);
+
+        let noise = 10f32.powf(-43.0 / 20.0);
+        let result = scale.record(&[noise; 4]).unwrap();
+        let NoiseResult::Learned(db) = result else {
+            panic!("Expected a learned noise floor");
+        };
+        assert!((db + 43.0).abs() < 1e-3);
+        assert!((scale.floor_db() + 40.0).abs() < 1e-3);
+        assert_eq!(scale.scale(&[noise])[0], 0.0);
+    }
+}
@@ -0,0 +1,608 @@
+use crate::config::Config;
+use crate::led::{create_artpoll_packet, is_artpoll_reply};
+use crate::messages::Message;
+use crate::udp::COMMAND_PORT;
+use crate::AppState;
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CONFIG_PATH: &str = "config.toml";
+const ARTPOLL_TIMEOUT: Duration = Duration::from_millis(500);
+const ARTNET_PORT: u16 = 6454;
+const MIN_FREE_DISK_WARN: u64 = 1024 * 1024 * 1024;
+const MIN_FREE_DISK_FAIL: u64 = 100 * 1024 * 1024;
+const FAILURES_BEFORE_RECOVERY: u32 = 256;
+const MAX_RECOVERY_ATTEMPTS: u32 = 5;
+const RECOVERY_BACKOFF_BASE: Duration = Duration::from_millis(500);
+const RECOVERY_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub status: CheckStatus,
+    pub generated_at: u64,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+pub struct RenderHealth {
+    last_frame: Option<Instant>,
+    frames: u64,
+    started: Instant,
+}
+
+impl RenderHealth {
+    pub fn new() -> Self {
+        Self {
+            last_frame: None,
+            frames: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn record_frame(&mut self) {
+        self.last_frame = Some(Instant::now());
+        self.frames += 1;
+    }
+
+    pub fn average_fps(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.frames as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputAlert {
+    pub controllers: Vec<String>,
+    pub recovery_attempts: u32,
+    #[serde(flatten)]
+    pub message: Message,
+}
+
+// Tracks consecutive Art-Net send failures per controller and paces socket rebuilds
+pub struct OutputHealth {
+    failures: HashMap<String, u32>,
+    recovery_attempts: u32,
+    next_recovery: Instant,
+    alerted: bool,
+    // Packets sent and failed since the last `take_counts`
+    sent: u64,
+    failed: u64,
+}
+
+impl OutputHealth {
+    pub fn new() -> Self {
+        Self {
+            failures: HashMap::new(),
+            recovery_attempts: 0,
+            next_recovery: Instant::now(),
+            alerted: false,
+            sent: 0,
+            failed: 0,
+        }
+    }
+
+    pub fn take_counts(&mut self) -> (u64, u64) {
+        (
+            std::mem::take(&mut self.sent),
+            std::mem::take(&mut self.failed),
+        )
+    }
+
+    // Returns the consecutive failure count for the controller
+    pub fn record_send(&mut self, controller: &str, ok: bool) -> u32 {
+        if ok {
+            self.sent += 1;
+        } else {
+            self.failed += 1;
+        }
+        let failures = match self.failures.get_mut(controller) {
+            Some(failures) => failures,
+            None => self.failures.entry(controller.to_string()).or_default(),
+        };
+        *failures = if ok { 0 } else { *failures + 1 };
+        *failures
+    }
+
+    // Controllers whose last send failed
+    pub fn offline_controllers(&self) -> Vec<String> {
+        let mut controllers: Vec<String> = self
+            .failures
+            .iter()
+            .filter(|(_, &failures)| failures > 0)
+            .map(|(controller, _)| controller.clone())
+            .collect();
+        controllers.sort();
+        controllers
+    }
+
+    fn failing_controllers(&self) -> Vec<String> {
+        let mut controllers: Vec<String> = self
+            .failures
+            .iter()
+            .filter(|(_, &failures)| failures >= FAILURES_BEFORE_RECOVERY)
+            .map(|(controller, _)| controller.clone())
+            .collect();
+        controllers.sort();
+        controllers
+    }
+
+    pub fn needs_recovery(&self) -> bool {
+        Instant::now() >= self.next_recovery && !self.failing_controllers().is_empty()
+    }
+
+    // Called after each socket rebuild; alerts once when recovery keeps failing
+    pub fn record_recovery(&mut self) -> Option<OutputAlert> {
+        let controllers = self.failing_controllers();
+        self.recovery_attempts += 1;
+        let backoff = RECOVERY_BACKOFF_BASE * 2u32.pow(self.recovery_attempts.min(6) - 1);
+        self.next_recovery = Instant::now() + backoff.min(RECOVERY_BACKOFF_MAX);
+        for controller in &controllers {
+            self.failures.insert(controller.clone(), 0);
+        }
+
+        if self.recovery_attempts < MAX_RECOVERY_ATTEMPTS || self.alerted {
+            return None;
+        }
+        self.alerted = true;
+        Some(OutputAlert {
+            message: Message::new("output_failing").arg("attempts", self.recovery_attempts),
+            controllers,
+            recovery_attempts: self.recovery_attempts,
+        })
+    }
+
+    // True once every controller sends cleanly again after at least one recovery
+    pub fn record_healthy(&mut self) -> bool {
+        if self.recovery_attempts == 0 || self.failures.values().any(|&f| f > 0) {
+            return false;
+        }
+        self.recovery_attempts = 0;
+        self.alerted = false;
+        self.next_recovery = Instant::now();
+        true
+    }
+}
+
+fn check(name: &str, status: CheckStatus, message: impl Into<String>) -> DiagnosticCheck {
+    DiagnosticCheck {
+        name: name.to_string(),
+        status,
+        message: message.into(),
+    }
+}
+
+// `listening` is where this backend's UDP server answers, if it runs
+pub fn run(state: &AppState, listening: Option<SocketAddr>) -> DiagnosticsReport {
+    let config = load_config();
+    let controllers = match &config {
+        Ok(Some(config)) => config.led.controllers.clone(),
+        _ => Config::default().led.controllers,
+    };
+
+    let checks = vec![
+        check_audio_device(state),
+        check_command_port(listening, COMMAND_PORT),
+        check_artnet_output(&controllers),
+        check_controllers(&controllers),
+        check_config(&config),
+        check_disk_space(Path::new(".")),
+        check_render_loop(&state.render_health.lock()),
+    ];
+
+    DiagnosticsReport {
+        status: checks
+            .iter()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(CheckStatus::Pass),
+        generated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        checks,
+    }
+}
+
+fn load_config() -> Result<Option<Config>, String> {
+    if !Path::new(CONFIG_PATH).exists() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(CONFIG_PATH).map_err(|e| e.to_string())?;
+    toml::from_str(&contents)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn check_audio_device(state: &AppState) -> DiagnosticCheck {
+    // The capture thread reports the device of the priority chain it is actually using
+    if let Some(device) = state.audio_device.lock().as_ref() {
+        let status = if device.rank == 0 {
+            CheckStatus::Pass
+        } else {
+            CheckStatus::Warn
+        };
+        return check(
+            "audio_device",
+            status,
+            format!(
+                "{} (priority {}/{}: {})",
+                device.name,
+                device.rank + 1,
+                device.chain.len(),
+                device.chain.join(" → ")
+            ),
+        );
+    }
+
+    let host = cpal::default_host();
+    match host.default_input_device() {
+        Some(device) => match device.default_input_config() {
+            Ok(config) => check(
+                "audio_device",
+                CheckStatus::Pass,
+                format!(
+                    "{} ({} Hz, {} ch)",
+                    device.name().unwrap_or_else(|_| "unknown".to_string()),
+                    config.sample_rate().0,
+                    config.channels()
+                ),
+            ),
+            Err(e) => check(
+                "audio_device",
+                CheckStatus::Warn,
+                format!("Input device found but not configurable: {}", e),
+            ),
+        },
+        None => check("audio_device", CheckStatus::Fail, "No input device found"),
+    }
+}
+
+// Held by this backend's server, or at least free for the next start
+fn check_command_port(listening: Option<SocketAddr>, port: u16) -> DiagnosticCheck {
+    if let Some(addr) = listening.filter(|addr| addr.port() == port) {
+        return check(
+            "command_port",
+            CheckStatus::Pass,
+            format!("UDP server listening on {}", addr),
+        );
+    }
+    match UdpSocket::bind(("0.0.0.0", port)) {
+        Ok(_) => check(
+            "command_port",
+            CheckStatus::Warn,
+            format!("Port {} is free but no UDP server is listening", port),
+        ),
+        Err(e) if e.kind() == ErrorKind::AddrInUse => check(
+            "command_port",
+            CheckStatus::Fail,
+            format!("Port {} is held by another process", port),
+        ),
+        Err(e) => check(
+            "command_port",
+            CheckStatus::Fail,
+            format!("Cannot bind port {}: {}", port, e),
+        ),
+    }
+}
+
+// Routes from an ephemeral socket like the LED sender's; connect only looks the route up,
+// nothing is sent
+fn check_artnet_output(controllers: &[String]) -> DiagnosticCheck {
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(e) => {
+            return check(
+                "artnet_output",
+                CheckStatus::Fail,
+                format!("Cannot bind the Art-Net output socket: {}", e),
+            )
+        }
+    };
+
+    let mut broken = Vec::new();
+    let mut off_port = Vec::new();
+    for controller in controllers {
+        match controller.parse::<SocketAddr>() {
+            Ok(addr) => match socket.connect(addr) {
+                Ok(()) if addr.port() != ARTNET_PORT => off_port.push(controller.as_str()),
+                Ok(()) => {}
+                Err(e) => broken.push(format!("{} ({})", controller, e)),
+            },
+            Err(_) => broken.push(format!("{} (not an address)", controller)),
+        }
+    }
+
+    if !broken.is_empty() {
+        check(
+            "artnet_output",
+            CheckStatus::Fail,
+            format!("No route to: {}", broken.join(", ")),
+        )
+    } else if !off_port.is_empty() {
+        check(
+            "artnet_output",
+            CheckStatus::Warn,
+            format!(
+                "Not on the Art-Net port {}: {}",
+                ARTNET_PORT,
+                off_port.join(", ")
+            ),
+        )
+    } else {
+        check(
+            "artnet_output",
+            CheckStatus::Pass,
+            format!(
+                "{} controllers routable on port {}",
+                controllers.len(),
+                ARTNET_PORT
+            ),
+        )
+    }
+}
+
+fn check_controllers(controllers: &[String]) -> DiagnosticCheck {
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(e) => return check("controllers", CheckStatus::Fail, e.to_string()),
+    };
+    let _ = socket.set_read_timeout(Some(Duration::from_millis(50)));
+
+    let mut targets: Vec<SocketAddr> = controllers.iter().filter_map(|c| c.parse().ok()).collect();
+    targets.sort();
+    targets.dedup();
+
+    let poll = create_artpoll_packet();
+    let mut unreachable = Vec::new();
+    for target in &targets {
+        if socket.send_to(&poll, target).is_err() {
+            unreachable.push(target.ip());
+        }
+    }
+
+    let mut replied = Vec::new();
+    let mut buf = [0u8; 1024];
+    let deadline = Instant::now() + ARTPOLL_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok((len, addr)) = socket.recv_from(&mut buf) {
+            if is_artpoll_reply(&buf[..len]) && !replied.contains(&addr.ip()) {
+                replied.push(addr.ip());
+            }
+        }
+    }
+
+    let silent: Vec<String> = targets
+        .iter()
+        .filter(|t| !replied.contains(&t.ip()) && !unreachable.contains(&t.ip()))
+        .map(|t| t.to_string())
+        .collect();
+
+    if !unreachable.is_empty() {
+        check(
+            "controllers",
+            CheckStatus::Fail,
+            format!("Send failed to: {:?}", unreachable),
+        )
+    } else if !silent.is_empty() {
+        check(
+            "controllers",
+            CheckStatus::Warn,
+            format!(
+                "{}/{} controllers answered ArtPoll, silent: {}",
+                replied.len(),
+                targets.len(),
+                silent.join(", ")
+            ),
+        )
+    } else {
+        check(
+            "controllers",
+            CheckStatus::Pass,
+            format!("{} controllers answered ArtPoll", replied.len()),
+        )
+    }
+}
+
+fn check_config(config: &Result<Option<Config>, String>) -> DiagnosticCheck {
+    match config {
+        Ok(Some(config)) => {
+            let issues = config.validate();
+            if issues.is_empty() {
+                check("config", CheckStatus::Pass, "config.toml is valid")
+            } else {
+                check("config", CheckStatus::Warn, issues.join("; "))
+            }
+        }
+        Ok(None) => check(
+            "config",
+            CheckStatus::Warn,
+            "config.toml not found, using defaults",
+        ),
+        Err(e) => check(
+            "config",
+            CheckStatus::Fail,
+            format!("config.toml is invalid: {}", e),
+        ),
+    }
+}
+
+#[cfg(unix)]
+fn free_disk_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_disk_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+fn check_disk_space(path: &Path) -> DiagnosticCheck {
+    match free_disk_space(path) {
+        Some(free) => {
+            let status = if free < MIN_FREE_DISK_FAIL {
+                CheckStatus::Fail
+            } else if free < MIN_FREE_DISK_WARN {
+                CheckStatus::Warn
+            } else {
+                CheckStatus::Pass
+            };
+            check(
+                "disk_space",
+                status,
+                format!("{} MB free for recordings", free / (1024 * 1024)),
+            )
+        }
+        None => check(
+            "disk_space",
+            CheckStatus::Warn,
+            "Free disk space unavailable",
+        ),
+    }
+}
+
+fn check_render_loop(health: &RenderHealth) -> DiagnosticCheck {
+    match health.last_frame {
+        Some(last_frame) => {
+            let age = last_frame.elapsed();
+            let status = if age < Duration::from_millis(200) {
+                CheckStatus::Pass
+            } else if age < Duration::from_secs(2) {
+                CheckStatus::Warn
+            } else {
+                CheckStatus::Fail
+            };
+            check(
+                "render_loop",
+                status,
+                format!(
+                    "Last frame {} ms ago, {:.1} FPS average",
+                    age.as_millis(),
+                    health.average_fps()
+                ),
+            )
+        }
+        None => check("render_loop", CheckStatus::Fail, "No frame rendered yet"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_checks() {
+        let held = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let addr = held.local_addr().unwrap();
+        assert_eq!(
+            check_command_port(Some(addr), addr.port()).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_command_port(None, addr.port()).status,
+            CheckStatus::Fail
+        );
+        drop(held);
+        assert_eq!(
+            check_command_port(None, addr.port()).status,
+            CheckStatus::Warn
+        );
+
+        let controllers = |list: &[&str]| list.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            check_artnet_output(&controllers(&["127.0.0.1:6454"])).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_artnet_output(&controllers(&["127.0.0.1:6454", "127.0.0.1:7000"])).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_artnet_output(&controllers(&["127.0.0.1:6454", "wall"])).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn test_render_loop_check() {
+        let mut health = RenderHealth::new();
+        assert_eq!(check_render_loop(&health).status, CheckStatus::Fail);
+
+        health.record_frame();
+        assert_eq!(check_render_loop(&health).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_output_health_recovery() {
+        let mut health = OutputHealth::new();
+        let fail = |health: &mut OutputHealth| {
+            for _ in 0..FAILURES_BEFORE_RECOVERY {
+                health.record_send("10.0.0.1:6454", false);
+            }
+            health.record_send("10.0.0.2:6454", true);
+        };
+
+        fail(&mut health);
+        assert!(health.needs_recovery());
+
+        for attempt in 1..MAX_RECOVERY_ATTEMPTS {
+            assert!(health.record_recovery().is_none(), "attempt {}", attempt);
+            fail(&mut health);
+            assert!(!health.needs_recovery());
+        }
+        let alert = health.record_recovery().unwrap();
+        assert_eq!(alert.controllers, vec!["10.0.0.1:6454".to_string()]);
+        assert!(health.record_recovery().is_none());
+
+        health.record_send("10.0.0.1:6454", true);
+        assert!(health.record_healthy());
+        assert!(!health.record_healthy());
+    }
+
+    #[test]
+    fn test_config_check() {
+        let mut config = Config::default();
+        assert_eq!(
+            check_config(&Ok(Some(config.clone()))).status,
+            CheckStatus::Pass
+        );
+
+        config.led.controllers.push("not-an-address".to_string());
+        assert_eq!(check_config(&Ok(Some(config))).status, CheckStatus::Warn);
+        assert_eq!(
+            check_config(&Err("bad".to_string())).status,
+            CheckStatus::Fail
+        );
+    }
+}
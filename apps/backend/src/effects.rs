@@ -1,12 +1,99 @@
+use crate::accent::{AccentKind, AccentLayer};
+use crate::beat::{Beat, BeatDetector, BeatEvent, BeatPhase, Metronome};
+use crate::budget::{trim_oldest, ResourceBudget, ResourceLimits, ResourceUsage};
+use crate::config::{
+    ContentPolicyConfig, EffectParameters, MatrixConfig, PlaylistConfig, SensitivityConfig,
+    TransitionStyle, MAX_SENSITIVITY,
+};
+use crate::hpss::TonalEstimator;
+use crate::messages::Message;
+use crate::sections::{Section, SectionDetector, SectionState};
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+mod canvas;
+mod parameters;
+mod particles;
+mod peak_hold;
+mod playlist;
+mod rng;
+mod tags;
+
+pub use canvas::{scale, Blend, Canvas};
+use parameters::{intensity_curve, DEFAULT_INTENSITY};
+pub use parameters::{EffectParameter, INTENSITY};
+use particles::add_dot;
+pub use particles::{Particle, ParticlePool, Physics};
+pub use peak_hold::{PeakDecay, PeakHold};
+pub use playlist::{EffectPlaylist, PlaylistStep};
+pub use rng::EffectRng;
+pub use tags::EffectTag;
 
 pub trait Effect: Send + Sync {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]);
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas);
     fn set_color_mode(&mut self, mode: &str);
     fn set_custom_color(&mut self, r: f32, g: f32, b: f32);
+
+    // Precomputes what rendering at this size needs, before the effect is first drawn
+    fn warm_up(&mut self, _width: usize, _height: usize) {}
+
+    fn resource_usage(&self) -> ResourceUsage {
+        ResourceUsage::default()
+    }
+
+    fn enforce_budget(&mut self, _limits: &ResourceLimits) -> usize {
+        0
+    }
+
+    fn base_sensitivity(&self) -> Option<f32> {
+        None
+    }
+
+    fn set_sensitivity(&mut self, _sensitivity: f32) {}
+
+    // Harmonic share of the music (0 = pure percussion, 1 = vocals/melody), every frame
+    fn set_tonalness(&mut self, _tonalness: f32) {}
+
+    // Position in the bar every frame, None until a tempo is found
+    fn set_beat(&mut self, _beat: Option<BeatPhase>) {}
+
+    // Effects drawing random numbers take them from their own seeded generator, so a seed
+    // and the same audio always replay the same frames
+    fn set_rng(&mut self, _rng: EffectRng) {}
+
+    // Knobs the effect can be tuned with at runtime, with their current values
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        Vec::new()
+    }
+
+    // Only called with a name from `get_parameters`, the value already clamped to its range
+    fn set_parameter(&mut self, _name: &str, _value: f32) {}
 }
 
+// Size of the stock wall, which effects size their state for before the first frame
+const FRAME_WIDTH: usize = 128;
+const FRAME_HEIGHT: usize = 128;
+const RAIN_SENSITIVITY: f32 = 4.0;
+const HEARTBEAT_SENSITIVITY: f32 = 5.0;
+const APPLAUDIMETRE_SENSITIVITY: f32 = 3.0;
+const FLAME_PARTICLES: f32 = 300.0;
+const FLAME_COOLING: f32 = 0.02;
+const RAIN_MAX_DROPS: f32 = 200.0;
+const STARFALL_MAX_STARS: f32 = 25.0;
+const BAR_PEAK_HOLD_SECS: f32 = 0.0;
+const BAR_PEAK_GRAVITY: f32 = 0.02;
+const APPLAUDIMETRE_PEAK_HOLD_SECS: f32 = 5.0;
+const APPLAUDIMETRE_PEAK_DECAY: f32 = 0.008;
+// Hue rotation of the bars when melody fully dominates
+const TONAL_HUE_SHIFT: f32 = 45.0;
+
+pub const COLOR_MODES: &[&str] = &["rainbow", "fire", "ocean", "sunset", "custom"];
+
 #[derive(Clone)]
 pub struct ColorConfig {
     pub mode: String,
@@ -27,11 +114,82 @@ impl Default for ColorConfig {
     }
 }
 
+struct EffectSlot {
+    name: &'static str,
+    tags: &'static [EffectTag],
+    factory: fn() -> Box<dyn Effect>,
+    instance: Option<Box<dyn Effect>>,
+    last_used: u64,
+}
+
+// The effect being left while a playlist transition plays
+struct EffectFade {
+    from: usize,
+    style: TransitionStyle,
+    started: Instant,
+    duration: Duration,
+}
+
+// Output besides the wall (e.g. a pixel strip along the DJ booth) with its own effect
+// instance, drawn on the same tick and from the same spectrum as the wall
+pub struct ExtraCanvas {
+    name: String,
+    width: usize,
+    height: usize,
+    slot: usize,
+    effect: Box<dyn Effect>,
+    frame: Vec<u8>,
+}
+
+impl ExtraCanvas {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    // Last rendered frame, RGB in row order
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+}
+
 pub struct EffectEngine {
-    effects: Vec<Box<dyn Effect>>,
+    slots: Vec<EffectSlot>,
     current: usize,
-    transition: f32,
+    playlist: EffectPlaylist,
+    fade: Option<EffectFade>,
+    // The outgoing effect's frame during a crossfade
+    fade_frame: Vec<u8>,
     color_config: ColorConfig,
+    max_loaded: Option<usize>,
+    use_counter: u64,
+    sensitivity: SensitivityConfig,
+    beats: BeatDetector,
+    metronome: Option<Metronome>,
+    accent: Option<AccentLayer>,
+    sections: SectionDetector,
+    tonal: TonalEstimator,
+    seed: u64,
+    // Spectrum after section staging, kept between frames so rendering doesn't allocate
+    staged: Vec<f32>,
+    canvases: Vec<ExtraCanvas>,
+    last_beat: Option<Beat>,
+    // Knob values per effect name, reapplied whenever an effect is instantiated
+    effect_parameters: EffectParameters,
+    // Effects and accents the venue doesn't allow are never selected
+    content_policy: ContentPolicyConfig,
+    // Size of the wall frames, from the matrix config
+    width: usize,
+    height: usize,
+}
+
+impl Default for EffectEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EffectEngine {
@@ -40,41 +198,756 @@ impl EffectEngine {
             GLOBAL_COLOR_CONFIG = ColorConfig::default();
         }
 
-        Self {
-            effects: vec![
-                Box::new(SpectrumBars::new()) as Box<dyn Effect>,
-                Box::new(CircularWave::new()) as Box<dyn Effect>,
-                Box::new(ParticleSystem::new()) as Box<dyn Effect>,
-                Box::new(Heartbeat::new()) as Box<dyn Effect>,
-                Box::new(Starfall::new()) as Box<dyn Effect>,
-                Box::new(Rain::new()) as Box<dyn Effect>,
-                Box::new(Flames::new()) as Box<dyn Effect>,
-                Box::new(Applaudimetre::new()) as Box<dyn Effect>,
-            ],
+        let mut engine = Self {
+            slots: Vec::new(),
             current: 0,
-            transition: 0.0,
+            playlist: EffectPlaylist::new(),
+            fade: None,
+            fade_frame: Vec::new(),
             color_config: ColorConfig::default(),
+            max_loaded: None,
+            use_counter: 0,
+            sensitivity: SensitivityConfig::default(),
+            beats: BeatDetector::new(),
+            metronome: None,
+            accent: None,
+            sections: SectionDetector::new(),
+            tonal: TonalEstimator::new(),
+            seed: 0,
+            staged: Vec::new(),
+            canvases: Vec::new(),
+            last_beat: None,
+            effect_parameters: EffectParameters::new(),
+            content_policy: ContentPolicyConfig::default(),
+            width: FRAME_WIDTH,
+            height: FRAME_HEIGHT,
+        };
+
+        engine.register(
+            "spectrum_bars",
+            &[EffectTag::Reactive, EffectTag::Party],
+            || Box::new(SpectrumBars::new()),
+        );
+        engine.register(
+            "circular_wave",
+            &[EffectTag::Reactive, EffectTag::Ambient],
+            || Box::new(CircularWave::new()),
+        );
+        engine.register(
+            "particle_system",
+            &[EffectTag::Reactive, EffectTag::Party],
+            || Box::new(ParticleSystem::new()),
+        );
+        engine.register(
+            "heartbeat",
+            &[EffectTag::Reactive, EffectTag::Party],
+            || Box::new(Heartbeat::new()),
+        );
+        engine.register("starfall", &[EffectTag::Ambient], || {
+            Box::new(Starfall::new())
+        });
+        engine.register("rain", &[EffectTag::Ambient], || Box::new(Rain::new()));
+        engine.register("flames", &[EffectTag::Ambient, EffectTag::Reactive], || {
+            Box::new(Flames::new())
+        });
+        engine.register("applaudimetre", &[EffectTag::Utility], || {
+            Box::new(Applaudimetre::new())
+        });
+
+        engine
+    }
+
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        tags: &'static [EffectTag],
+        factory: fn() -> Box<dyn Effect>,
+    ) {
+        self.slots.push(EffectSlot {
+            name,
+            tags,
+            factory,
+            instance: None,
+            last_used: 0,
+        });
+    }
+
+    pub fn effect_names(&self) -> Vec<&'static str> {
+        self.slots.iter().map(|slot| slot.name).collect()
+    }
+
+    // Registered effects in order, only those tagged `tag` when one is given; blacklisted ones
+    // are hidden while the content policy is on
+    pub fn effect_list(&self, tag: Option<EffectTag>) -> serde_json::Value {
+        let effects: Vec<_> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| tag.is_none_or(|tag| slot.tags.contains(&tag)))
+            .filter(|(_, slot)| !self.content_policy.blocks(slot.name))
+            .map(|(index, slot)| {
+                serde_json::json!({
+                    "index": index,
+                    "name": slot.name,
+                    "tags": slot.tags,
+                    "current": index == self.current,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "tag": tag,
+            "tags": EffectTag::ALL,
+            "effects": effects,
+        })
+    }
+
+    // A blacklisted current effect gives way to the first allowed one, a blacklisted accent
+    // is turned off
+    pub fn set_content_policy(&mut self, policy: ContentPolicyConfig) {
+        self.content_policy = policy;
+        if let Some(accent) = self.accent.as_mut() {
+            accent.set_min_interval(self.content_policy.min_flash_interval());
+        }
+        if self
+            .accent_kind()
+            .is_some_and(|kind| self.content_policy.blocks(kind.name()))
+        {
+            self.accent = None;
+        }
+        if !self.allows(self.current) {
+            if let Some(index) = (0..self.slots.len()).find(|&index| self.allows(index)) {
+                self.set_effect(index);
+            }
         }
     }
 
-    pub fn render(&mut self, spectrum: &[f32]) -> Vec<u8> {
-        let mut frame = vec![0u8; 128 * 128 * 3];
+    fn allows(&self, index: usize) -> bool {
+        self.slots
+            .get(index)
+            .is_none_or(|slot| !self.content_policy.blocks(slot.name))
+    }
 
-        if let Some(effect) = self.effects.get_mut(self.current) {
-            effect.render(spectrum, &mut frame);
-        } else {
+    pub fn check_effect(&self, index: usize) -> Result<()> {
+        if !self.allows(index) {
+            bail!(Message::new("blacklisted").arg("name", self.slots[index].name));
+        }
+        Ok(())
+    }
+
+    pub fn check_accent(&self, kind: AccentKind) -> Result<()> {
+        if self.content_policy.blocks(kind.name()) {
+            bail!(Message::new("blacklisted").arg("name", kind.name()));
+        }
+        Ok(())
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.instance.is_some())
+            .count()
+    }
+
+    pub fn set_max_loaded(&mut self, max_loaded: Option<usize>) {
+        self.max_loaded = max_loaded.map(|max| max.max(1));
+        self.unload_least_recently_used();
+    }
+
+    fn load(&mut self, index: usize) {
+        self.use_counter += 1;
+        self.slots[index].last_used = self.use_counter;
+
+        if self.slots[index].instance.is_none() {
+            let effect = self.instantiate(index, self.slots[index].name, self.width, self.height);
+            self.slots[index].instance = Some(effect);
+            self.unload_least_recently_used();
+        }
+    }
+
+    // A ready-to-render instance of the slot's effect at `width` x `height`, its random sequence
+    // keyed by `rng_key`
+    fn instantiate(
+        &self,
+        index: usize,
+        rng_key: &str,
+        width: usize,
+        height: usize,
+    ) -> Box<dyn Effect> {
+        let slot = &self.slots[index];
+        let mut effect = (slot.factory)();
+        let (r, g, b) = self.color_config.custom_color;
+        effect.set_custom_color(r, g, b);
+        effect.set_color_mode(&self.color_config.mode);
+        if let Some(base) = effect.base_sensitivity() {
+            effect.set_sensitivity(self.sensitivity.effective(slot.name, base));
+        }
+        effect.set_rng(EffectRng::for_effect(self.seed, rng_key));
+        parameters::apply_overrides(effect.as_mut(), self.effect_parameters.get(slot.name));
+        effect.warm_up(width, height);
+        effect
+    }
+
+    // Adds an output rendered alongside the wall; the effect name is matched like
+    // `set_effect_by_name`
+    pub fn add_canvas(
+        &mut self,
+        name: &str,
+        width: usize,
+        height: usize,
+        effect: &str,
+    ) -> Result<&'static str> {
+        if width == 0 || height == 0 {
+            anyhow::bail!("Canvas {} has no pixels", name);
+        }
+        if self.canvases.iter().any(|canvas| canvas.name == name) {
+            anyhow::bail!("Canvas {} already exists", name);
+        }
+        let slot = self
+            .find_effect(effect)
+            .ok_or_else(|| Message::new("unknown_effect").arg("name", effect))?;
+
+        self.canvases.push(ExtraCanvas {
+            name: name.to_string(),
+            width,
+            height,
+            slot,
+            effect: self.instantiate(slot, name, width, height),
+            frame: vec![0; width * height * 3],
+        });
+        Ok(self.slots[slot].name)
+    }
+
+    // Wall effects restart at the new size on their next frame
+    pub fn set_matrix(&mut self, matrix: &MatrixConfig) {
+        (self.width, self.height) = (matrix.width, matrix.height);
+        for slot in self.slots.iter_mut() {
+            slot.instance = None;
+        }
+    }
+
+    pub fn frame_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn canvases(&self) -> &[ExtraCanvas] {
+        &self.canvases
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Restarts every effect from the new seed; wall effects reload on their next frame
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        for slot in self.slots.iter_mut() {
+            slot.instance = None;
+        }
+        for index in 0..self.canvases.len() {
+            let canvas = &self.canvases[index];
+            let effect = self.instantiate(canvas.slot, &canvas.name, canvas.width, canvas.height);
+            self.canvases[index].effect = effect;
+        }
+    }
+
+    fn apply_sensitivity(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if let Some(effect) = slot.instance.as_mut() {
+                if let Some(base) = effect.base_sensitivity() {
+                    effect.set_sensitivity(self.sensitivity.effective(slot.name, base));
+                }
+            }
+        }
+        for canvas in self.canvases.iter_mut() {
+            if let Some(base) = canvas.effect.base_sensitivity() {
+                let name = self.slots[canvas.slot].name;
+                canvas
+                    .effect
+                    .set_sensitivity(self.sensitivity.effective(name, base));
+            }
+        }
+    }
+
+    pub fn sensitivity_config(&self) -> &SensitivityConfig {
+        &self.sensitivity
+    }
+
+    pub fn set_sensitivity_config(&mut self, config: SensitivityConfig) {
+        self.sensitivity = config.normalized();
+        self.apply_sensitivity();
+    }
+
+    pub fn set_input_trim(&mut self, input_trim: f32) {
+        let mut config = self.sensitivity.clone();
+        config.input_trim = input_trim;
+        self.set_sensitivity_config(config);
+    }
+
+    pub fn set_effect_sensitivity(&mut self, sensitivity: f32) -> Result<()> {
+        self.load(self.current);
+        let slot = &self.slots[self.current];
+        if slot
+            .instance
+            .as_ref()
+            .and_then(|e| e.base_sensitivity())
+            .is_none()
+        {
+            anyhow::bail!("Effect {} has no audio sensitivity", slot.name);
+        }
+
+        let mut config = self.sensitivity.clone();
+        config.effects.insert(
+            slot.name.to_string(),
+            sensitivity.clamp(0.0, MAX_SENSITIVITY),
+        );
+        self.set_sensitivity_config(config);
+        Ok(())
+    }
+
+    pub fn effect_parameters(&self) -> &EffectParameters {
+        &self.effect_parameters
+    }
+
+    pub fn set_effect_parameters(&mut self, effect_parameters: EffectParameters) {
+        self.effect_parameters = effect_parameters;
+        for slot in self.slots.iter_mut() {
+            if let Some(effect) = slot.instance.as_mut() {
+                parameters::apply_overrides(effect.as_mut(), self.effect_parameters.get(slot.name));
+            }
+        }
+        for canvas in self.canvases.iter_mut() {
+            let name = self.slots[canvas.slot].name;
+            parameters::apply_overrides(canvas.effect.as_mut(), self.effect_parameters.get(name));
+        }
+    }
+
+    // Tunes a knob of the current effect, canvases playing it included; returns the clamped value
+    pub fn set_effect_parameter(&mut self, name: &str, value: f32) -> Result<f32> {
+        let current = self.current;
+        self.load(current);
+        let slot = &mut self.slots[current];
+        let Some(effect) = slot.instance.as_mut() else {
+            anyhow::bail!("Effect {} is not loaded", slot.name);
+        };
+        let value = parameters::set_parameter(effect.as_mut(), name, value)?;
+        self.effect_parameters
+            .entry(slot.name.to_string())
+            .or_default()
+            .insert(name.to_string(), value);
+        for canvas in self.canvases.iter_mut().filter(|c| c.slot == current) {
+            canvas.effect.set_parameter(name, value);
+        }
+        Ok(value)
+    }
+
+    // Sets a knob of the current effect from a 0-1 position across its range
+    pub fn set_effect_parameter_fraction(&mut self, name: &str, fraction: f32) -> Result<f32> {
+        self.load(self.current);
+        let range = self.slots[self.current]
+            .instance
+            .as_ref()
+            .and_then(|effect| {
+                let parameters = effect.get_parameters();
+                let parameter = parameters.iter().find(|p| p.name == name)?;
+                Some((parameter.min, parameter.max))
+            });
+        // Unknown knobs go through set_effect_parameter for its error
+        let value = range.map_or(fraction, |(min, max)| {
+            min + (max - min) * fraction.clamp(0.0, 1.0)
+        });
+        self.set_effect_parameter(name, value)
+    }
+
+    pub fn parameters(&self) -> serde_json::Value {
+        let slot = &self.slots[self.current];
+        let base = slot.instance.as_ref().and_then(|e| e.base_sensitivity());
+        serde_json::json!({
+            "effect": slot.name,
+            "effect_parameters": slot.instance.as_ref().map_or(Vec::new(), |e| e.get_parameters()),
+            "sensitivity": base.map(|b| self.sensitivity.effective(slot.name, b)),
+            "default_sensitivity": base,
+            "override": self.sensitivity.effects.get(slot.name),
+            "input_trim": self.sensitivity.input_trim,
+            "metronome": self.metronome_enabled(),
+            "accent": self.accent_kind().map(AccentKind::name),
+            "seed": self.seed,
+        })
+    }
+
+    fn unload_least_recently_used(&mut self) {
+        let Some(max_loaded) = self.max_loaded else {
+            return;
+        };
+
+        while self.loaded_count() > max_loaded {
+            let oldest = self
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(i, slot)| *i != self.current && slot.instance.is_some())
+                .min_by_key(|(_, slot)| slot.last_used)
+                .map(|(i, _)| i);
+
+            match oldest {
+                Some(index) => self.slots[index].instance = None,
+                None => break,
+            }
         }
+    }
+
+    // Builds every effect once at the wall's and each canvas's size so the tables they
+    // precompute are cached before anything is selected: loading one later, under the engine
+    // lock, never stalls the render loop. Only the current effect stays loaded.
+    pub fn warm_up(&mut self) {
+        let sizes: Vec<_> = std::iter::once((self.width, self.height))
+            .chain(self.canvases.iter().map(|c| (c.width, c.height)))
+            .collect();
+        self.slots.par_iter().for_each(|slot| {
+            let mut effect = (slot.factory)();
+            for &(width, height) in &sizes {
+                effect.warm_up(width, height);
+            }
+        });
+        self.load(self.current);
+    }
 
+    // Allocates a fresh frame; render loops should keep a buffer and use `render_into`
+    pub fn render(&mut self, spectrum: &[f32]) -> Vec<u8> {
+        let mut frame = vec![0u8; self.width * self.height * 3];
+        self.render_into(spectrum, &mut frame);
         frame
     }
 
+    // Overwrites `frame`, which must hold `frame_size()` RGB pixels
+    pub fn render_into(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+        frame.fill(0);
+
+        if self.current < self.slots.len() {
+            self.load(self.current);
+        }
+
+        let now = Instant::now();
+        let entered = self.sections.process(spectrum, now);
+        if let Some(step) = self.playlist.tick(now, entered == Some(Section::Drop)) {
+            self.play_step(step, now);
+        }
+        let gain = self.sections.staging_gain(now);
+        self.staged.clear();
+        self.staged
+            .extend(spectrum.iter().map(|&v| (v * gain).min(1.0)));
+        let tonalness = self.tonal.process(spectrum);
+        let beat_phase = self.beats.phase(now);
+
+        let fade = self.fade.as_ref().map(|fade| {
+            let progress =
+                now.duration_since(fade.started).as_secs_f32() / fade.duration.as_secs_f32();
+            (fade.from, fade.style, progress.min(1.0))
+        });
+        let (width, height) = (self.width, self.height);
+        let mut draw = |slot: usize, frame: &mut [u8]| {
+            if let Some(effect) = self
+                .slots
+                .get_mut(slot)
+                .and_then(|slot| slot.instance.as_mut())
+            {
+                effect.set_tonalness(tonalness);
+                effect.set_beat(beat_phase);
+                effect.render(&self.staged, &mut Canvas::new(frame, width, height));
+            }
+        };
+
+        match fade {
+            Some((from, TransitionStyle::Crossfade, progress)) if progress < 1.0 => {
+                draw(self.current, frame);
+                // An outgoing effect unloaded by the budget fades from black
+                self.fade_frame.clear();
+                self.fade_frame.resize(frame.len(), 0);
+                draw(from, &mut self.fade_frame);
+                for (pixel, &old) in frame.iter_mut().zip(&self.fade_frame) {
+                    *pixel = (old as f32 + (*pixel as f32 - old as f32) * progress) as u8;
+                }
+            }
+            Some((from, TransitionStyle::FadeThroughBlack, progress)) if progress < 1.0 => {
+                let (slot, level) = if progress < 0.5 {
+                    (from, 1.0 - progress * 2.0)
+                } else {
+                    (self.current, progress * 2.0 - 1.0)
+                };
+                draw(slot, frame);
+                frame
+                    .iter_mut()
+                    .for_each(|pixel| *pixel = (*pixel as f32 * level) as u8);
+            }
+            _ => {
+                self.fade = None;
+                draw(self.current, frame);
+            }
+        }
+
+        for canvas in self.canvases.iter_mut() {
+            canvas.frame.fill(0);
+            canvas.effect.set_tonalness(tonalness);
+            canvas.effect.set_beat(beat_phase);
+            canvas.effect.render(
+                &self.staged,
+                &mut Canvas::new(&mut canvas.frame, canvas.width, canvas.height),
+            );
+        }
+
+        let beat = self.beats.process(spectrum, now);
+        self.last_beat = beat;
+        if let Some(accent) = self.accent.as_mut() {
+            if entered == Some(Section::Drop) {
+                // Drops always get a full-intensity accent
+                accent.trigger(f32::MAX, now);
+            } else if beat.is_some() {
+                accent.trigger(self.beats.onset_strength(), now);
+            }
+            accent.draw(frame, self.width, self.height, now);
+        }
+        if let Some(metronome) = self.metronome.as_mut() {
+            if let Some(beat) = beat {
+                metronome.flash(beat);
+            }
+            metronome.draw(frame, self.width);
+        }
+    }
+
+    // Entries naming an unknown or blacklisted effect or a negative time are refused; replacing the entries
+    // stops the playlist
+    pub fn set_playlist(&mut self, config: &PlaylistConfig) -> Result<()> {
+        let seconds = |secs: f32, what: &str, effect: &str| {
+            Duration::try_from_secs_f32(secs)
+                .map_err(|_| anyhow::anyhow!("Invalid {} for {}: {}", what, effect, secs))
+        };
+        let steps = config
+            .entries
+            .iter()
+            .map(|entry| {
+                let slot = self
+                    .find_effect(&entry.effect)
+                    .ok_or_else(|| Message::new("unknown_effect").arg("name", &entry.effect))?;
+                self.check_effect(slot)?;
+                Ok(PlaylistStep {
+                    slot,
+                    duration: seconds(entry.duration_secs, "duration", &entry.effect)?,
+                    transition: entry.transition,
+                    transition_time: seconds(entry.transition_secs, "transition", &entry.effect)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.playlist.set(config.clone(), steps);
+        Ok(())
+    }
+
+    pub fn start_playlist(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let step = self
+            .playlist
+            .start(now)
+            .ok_or_else(|| Message::new("playlist_empty"))?;
+        self.play_step(step, now);
+        Ok(())
+    }
+
+    pub fn stop_playlist(&mut self) {
+        self.playlist.stop();
+    }
+
+    pub fn playlist_config(&self) -> &PlaylistConfig {
+        self.playlist.config()
+    }
+
+    pub fn playlist_status(&self) -> serde_json::Value {
+        let position = self.playlist.position(Instant::now());
+        serde_json::json!({
+            "playing": position.is_some(),
+            "entry": position.map(|(index, _)| index),
+            "effect": position.map(|_| self.current_effect_name()),
+            "remaining_secs": position.and_then(|(_, remaining)| remaining),
+            "advance_on_drop": self.playlist.config().advance_on_drop,
+            "entries": self.playlist.config().entries,
+        })
+    }
+
+    fn play_step(&mut self, step: PlaylistStep, now: Instant) {
+        // Blacklisted since the playlist was set: the current effect plays on through the entry
+        if !self.allows(step.slot) {
+            return;
+        }
+        let from = self.current;
+        self.set_effect(step.slot);
+        self.fade = (step.transition != TransitionStyle::Cut
+            && !step.transition_time.is_zero()
+            && from != step.slot)
+            .then_some(EffectFade {
+                from,
+                style: step.transition,
+                started: now,
+                duration: step.transition_time,
+            });
+    }
+
+    pub fn set_metronome(&mut self, enabled: bool) {
+        if enabled != self.metronome.is_some() {
+            self.metronome = enabled.then(Metronome::new);
+        }
+    }
+
+    pub fn metronome_enabled(&self) -> bool {
+        self.metronome.is_some()
+    }
+
+    // Blacklisted accents are ignored
+    pub fn set_accent(&mut self, kind: Option<AccentKind>) {
+        if kind.is_some_and(|kind| self.check_accent(kind).is_err()) {
+            return;
+        }
+        if kind != self.accent_kind() {
+            self.accent = kind.map(|kind| {
+                let mut accent = AccentLayer::new(kind);
+                accent.set_min_interval(self.content_policy.min_flash_interval());
+                accent
+            });
+        }
+    }
+
+    pub fn accent_kind(&self) -> Option<AccentKind> {
+        self.accent.as_ref().map(AccentLayer::kind)
+    }
+
+    pub fn bpm(&self) -> Option<f32> {
+        self.beats.bpm()
+    }
+
+    // Beat detected on the last rendered frame, if any
+    pub fn last_beat(&self) -> Option<Beat> {
+        self.last_beat
+    }
+
+    pub fn beat_phase(&self) -> Option<BeatPhase> {
+        self.beats.phase(Instant::now())
+    }
+
+    pub fn resume_beat(&mut self, phase: BeatPhase) {
+        self.beats.resume(phase, Instant::now())
+    }
+
+    pub fn beat_event(&self) -> Option<BeatEvent> {
+        self.last_beat.map(|beat| BeatEvent {
+            downbeat: beat.downbeat,
+            bpm: self.beats.bpm(),
+            onset_strength: self.beats.onset_strength(),
+        })
+    }
+
+    pub fn section(&self) -> SectionState {
+        self.sections.state(Instant::now())
+    }
+
+    pub fn tonalness(&self) -> f32 {
+        self.tonal.tonalness()
+    }
+
+    // Blacklisted effects are ignored
     pub fn set_effect(&mut self, index: usize) {
-        if index < self.effects.len() {
+        if index < self.slots.len() && self.allows(index) {
             self.current = index;
+            self.load(index);
         } else {
         }
     }
 
+    pub fn set_effect_by_name(&mut self, name: &str) -> Result<&'static str> {
+        let index = self
+            .find_effect(name)
+            .ok_or_else(|| Message::new("unknown_effect").arg("name", name))?;
+        self.check_effect(index)?;
+        self.set_effect(index);
+        Ok(self.slots[index].name)
+    }
+
+    // Case, spaces and separators are ignored; falls back to a unique prefix, then the closest name
+    pub fn find_effect(&self, name: &str) -> Option<usize> {
+        let wanted = normalize_name(name);
+        if wanted.is_empty() {
+            return None;
+        }
+        let names: Vec<String> = self.slots.iter().map(|s| normalize_name(s.name)).collect();
+
+        if let Some(index) = names.iter().position(|n| *n == wanted) {
+            return Some(index);
+        }
+
+        let prefixed: Vec<usize> = (0..names.len())
+            .filter(|&i| names[i].starts_with(&wanted))
+            .collect();
+        if prefixed.len() == 1 {
+            return Some(prefixed[0]);
+        }
+
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (i, edit_distance(n, &wanted)))
+            .filter(|&(_, distance)| distance <= 2)
+            .min_by_key(|&(_, distance)| distance)
+            .map(|(i, _)| i)
+    }
+
+    // Stepping skips blacklisted effects
+    pub fn next_effect(&mut self) {
+        self.step_effect(1);
+    }
+
+    pub fn previous_effect(&mut self) {
+        self.step_effect(self.slots.len() - 1);
+    }
+
+    fn step_effect(&mut self, step: usize) {
+        let count = self.slots.len();
+        let next = (1..count)
+            .map(|i| (self.current + step * i) % count)
+            .find(|&index| self.allows(index));
+        if let Some(index) = next {
+            self.set_effect(index);
+        }
+    }
+
+    // Wall effects that are loaded, then the extra canvases' effects
+    fn loaded_effects(&mut self) -> impl Iterator<Item = &mut Box<dyn Effect>> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| slot.instance.as_mut())
+            .chain(self.canvases.iter_mut().map(|canvas| &mut canvas.effect))
+    }
+
+    pub fn enforce_budget(&mut self, budget: &mut ResourceBudget) {
+        let mut usage = ResourceUsage::default();
+        let mut trimmed = 0;
+        for effect in self.loaded_effects() {
+            trimmed += effect.enforce_budget(budget.limits());
+            usage += effect.resource_usage();
+        }
+        budget.record_effects(usage, trimmed);
+    }
+
+    pub fn current_effect(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_effect_name(&self) -> &'static str {
+        self.slots.get(self.current).map_or("", |slot| slot.name)
+    }
+
+    pub fn color_mode(&self) -> &str {
+        &self.color_config.mode
+    }
+
+    pub fn custom_color(&self) -> (f32, f32, f32) {
+        self.color_config.custom_color
+    }
+
     pub fn set_color_mode(&mut self, mode: &str) {
         self.color_config.mode = mode.to_string();
 
@@ -82,7 +955,7 @@ impl EffectEngine {
             GLOBAL_COLOR_CONFIG.mode = mode.to_string();
         }
 
-        for (i, effect) in self.effects.iter_mut().enumerate() {
+        for effect in self.loaded_effects() {
             effect.set_color_mode(mode);
         }
     }
@@ -94,7 +967,7 @@ impl EffectEngine {
             GLOBAL_COLOR_CONFIG.custom_color = (r, g, b);
         }
 
-        for (i, effect) in self.effects.iter_mut().enumerate() {
+        for effect in self.loaded_effects() {
             effect.set_custom_color(r, g, b);
         }
     }
@@ -102,24 +975,32 @@ impl EffectEngine {
 
 pub struct SpectrumBars {
     smoothed: Vec<f32>,
-    peak_hold: Vec<f32>,
-    peak_decay: Vec<f32>,
+    peaks: PeakHold,
+    tonalness: f32,
+    intensity: f32,
 }
 
 impl SpectrumBars {
     pub fn new() -> Self {
         Self {
             smoothed: vec![0.0; 64],
-            peak_hold: vec![0.0; 64],
-            peak_decay: vec![0.0; 64],
+            peaks: PeakHold::new(64, BAR_PEAK_HOLD_SECS, PeakDecay::Gravity(BAR_PEAK_GRAVITY)),
+            tonalness: 0.5,
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
+    // Melodic passages rotate the palette, percussion keeps it as configured
+    fn tonal_hue(&self, hue: f32) -> f32 {
+        let shift = ((self.tonalness - 0.5) * 2.0).clamp(0.0, 1.0) * TONAL_HUE_SHIFT;
+        (hue + shift) % 360.0
+    }
+
     fn get_color_for_bar(&self, bar: usize, brightness: f32) -> (f32, f32, f32) {
         let color_mode = unsafe { &GLOBAL_COLOR_CONFIG };
         match color_mode.mode.as_str() {
             "rainbow" => {
-                let hue = (bar as f32 / 64.0) * 360.0;
+                let hue = self.tonal_hue((bar as f32 / 64.0) * 360.0);
                 let saturation = 0.8
                     + if bar < self.smoothed.len() {
                         self.smoothed[bar] * 0.2
@@ -129,12 +1010,12 @@ impl SpectrumBars {
                 hsv_to_rgb(hue / 360.0, saturation.min(1.0), brightness)
             }
             "fire" => {
-                let hue = (bar as f32 / 64.0) * 60.0;
+                let hue = self.tonal_hue((bar as f32 / 64.0) * 60.0);
                 let saturation = 1.0;
                 hsv_to_rgb(hue / 360.0, saturation, brightness)
             }
             "ocean" => {
-                let hue = 180.0 + (bar as f32 / 64.0) * 60.0;
+                let hue = self.tonal_hue(180.0 + (bar as f32 / 64.0) * 60.0);
                 let saturation = 0.8
                     + if bar < self.smoothed.len() {
                         self.smoothed[bar] * 0.2
@@ -149,7 +1030,7 @@ impl SpectrumBars {
                 } else {
                     (bar as f32 - 32.0) / 32.0 * 60.0
                 };
-                hsv_to_rgb(hue / 360.0, 1.0, brightness)
+                hsv_to_rgb(self.tonal_hue(hue) / 360.0, 1.0, brightness)
             }
             "custom" => {
                 let (r, g, b) = color_mode.custom_color;
@@ -164,25 +1045,14 @@ impl SpectrumBars {
 }
 
 impl Effect for SpectrumBars {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
-        for i in 0..64 {
-            let target = spectrum[i];
-            let current = self.smoothed[i];
-
-            if target > current {
-                self.smoothed[i] = current * 0.4 + target * 0.6;
-            } else {
-                self.smoothed[i] = current * 0.85 + target * 0.15;
-            }
-
-            if self.smoothed[i] > self.peak_hold[i] {
-                self.peak_hold[i] = self.smoothed[i];
-                self.peak_decay[i] = 0.0;
-            } else {
-                self.peak_decay[i] += 0.02;
-                self.peak_hold[i] = (self.peak_hold[i] - self.peak_decay[i]).max(0.0);
-            }
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
+        // Intensity scales the bars' height and dims them when tamed
+        let gain = intensity_curve(self.intensity, 0.5, 1.6);
+        let dim = intensity_curve(self.intensity, 0.5, 1.0);
+        for (smoothed, &level) in self.smoothed.iter_mut().zip(spectrum) {
+            *smoothed = (level * gain).min(1.0);
         }
+        self.peaks.update(&self.smoothed);
 
         static mut DEBUG_COUNTER: u32 = 0;
         unsafe {
@@ -192,42 +1062,49 @@ impl Effect for SpectrumBars {
             }
         }
 
-        frame.fill(0);
+        canvas.clear();
 
-        frame.par_chunks_mut(3).enumerate().for_each(|(i, pixel)| {
-            let x = (i % 128) as f32;
-            let y = (i / 128) as f32;
+        let (width, bottom) = canvas.size();
+        let half = width / 2.0;
+        // Bars stop short of the top so the peak caps stay visible
+        let max_height = bottom - 8.0;
 
-            let bar = if x < 64.0 {
-                (x * 32.0 / 64.0) as usize
+        canvas.par_pixels_mut().for_each(|(x, y, pixel)| {
+            let x = x as f32;
+            let y = y as f32;
+
+            // Bass at both edges, mirrored towards the middle
+            let bar_pos = if x < half {
+                x * 32.0 / half
             } else {
-                (31.0 - (x - 64.0) * 32.0 / 64.0) as usize
+                31.0 - (x - half) * 32.0 / half
             };
+            let bar = bar_pos as usize;
 
             if bar < 32 {
                 let value = self.smoothed[bar.min(31)];
                 let curved_value = if value > 0.0 { value.powf(0.6) } else { 0.0 };
 
-                let height = curved_value * 120.0;
-                let peak_height = self.peak_hold[bar] * 120.0;
+                let height = curved_value * max_height;
+                let peak_height = self.peaks.peak(bar) * max_height;
 
-                let bar_bottom = 128.0 - height;
+                let bar_bottom = bottom - height;
                 let distance_from_bottom = (y - bar_bottom).max(0.0);
-                let gradient_factor = if y >= bar_bottom && y < 128.0 {
+                let gradient_factor = if y >= bar_bottom && y < bottom {
                     1.0 - (distance_from_bottom / height).min(1.0) * 0.3
                 } else {
                     0.0
                 };
 
-                if y >= bar_bottom && y < 128.0 {
-                    let brightness = gradient_factor;
+                if y >= bar_bottom && y < bottom {
+                    let brightness = gradient_factor * dim;
                     let (r, g, b) = self.get_color_for_bar(bar, brightness);
                     pixel[0] = (r * 255.0) as u8;
                     pixel[1] = (g * 255.0) as u8;
                     pixel[2] = (b * 255.0) as u8;
                 }
 
-                let peak_y = 128.0 - peak_height;
+                let peak_y = bottom - peak_height;
                 if (y - peak_y).abs() < 1.0 && peak_height > 5.0 {
                     let (r, g, b) = self.get_color_for_bar(bar, 0.8);
                     pixel[0] = (r * 255.0 * 0.8) as u8;
@@ -235,19 +1112,14 @@ impl Effect for SpectrumBars {
                     pixel[2] = (b * 255.0 * 0.8) as u8;
                 }
 
-                let bar_pos = if x < 64.0 {
-                    x * 32.0 / 64.0
-                } else {
-                    31.0 - (x - 64.0) * 32.0 / 64.0
-                };
-                let bar_boundary = (bar_pos - bar as f32).abs() * 64.0 / 32.0;
-                if bar_boundary > 1.8 && y >= bar_bottom && y < 128.0 {
+                let bar_boundary = (bar_pos - bar as f32).abs() * half / 32.0;
+                if bar_boundary > 1.8 && y >= bar_bottom && y < bottom {
                     pixel[0] = (pixel[0] as f32 * 0.7) as u8;
                     pixel[1] = (pixel[1] as f32 * 0.7) as u8;
                     pixel[2] = (pixel[2] as f32 * 0.7) as u8;
                 }
 
-                if (x - 64.0).abs() < 0.5 && y >= bar_bottom && y < 128.0 {
+                if (x - half).abs() < 0.5 && y >= bar_bottom && y < bottom {
                     let (r, g, b) = self.get_color_for_bar(bar, 0.3);
                     pixel[0] = (r * 255.0) as u8;
                     pixel[1] = (g * 255.0) as u8;
@@ -260,15 +1132,37 @@ impl Effect for SpectrumBars {
     fn set_color_mode(&mut self, mode: &str) {}
 
     fn set_custom_color(&mut self, r: f32, g: f32, b: f32) {}
+
+    fn set_tonalness(&mut self, tonalness: f32) {
+        self.tonalness = tonalness;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![EffectParameter::intensity(self.intensity)]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == INTENSITY {
+            self.intensity = value;
+        }
+    }
 }
 
+type PolarMap = Arc<Vec<(f32, f32)>>;
+
 pub struct CircularWave {
     time: f32,
+    polar: PolarMap,
+    intensity: f32,
 }
 
 impl CircularWave {
     pub fn new() -> Self {
-        Self { time: 0.0 }
+        Self {
+            time: 0.0,
+            polar: Arc::default(),
+            intensity: DEFAULT_INTENSITY,
+        }
     }
 
     fn get_color_for_wave(
@@ -320,23 +1214,48 @@ impl CircularWave {
             _ => hsv_to_rgb(0.5, 1.0, brightness),
         }
     }
+
+    // Distance from the center in safe radii and angle of every pixel, computed once per size
+    // and shared so an unloaded instance comes back without recomputing it
+    fn polar_map(width: usize, height: usize) -> PolarMap {
+        static MAPS: OnceLock<Mutex<HashMap<(usize, usize), PolarMap>>> = OnceLock::new();
+        let mut maps = MAPS.get_or_init(Default::default).lock();
+        let map = maps.entry((width, height)).or_insert_with(|| {
+            let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+            let radius = width.min(height) as f32 / 2.0;
+            Arc::new(
+                (0..width * height)
+                    .map(|i| {
+                        let x = ((i % width) as f32 - center_x) / radius;
+                        let y = ((i / width) as f32 - center_y) / radius;
+                        ((x * x + y * y).sqrt(), y.atan2(x))
+                    })
+                    .collect(),
+            )
+        });
+        map.clone()
+    }
 }
 
 impl Effect for CircularWave {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let total_energy = spectrum.iter().sum::<f32>() / spectrum.len() as f32;
-        self.time += 0.05 + total_energy * 0.2;
+        // Intensity speeds the waves up and strengthens the music's share of their brightness
+        let speed = intensity_curve(self.intensity, 0.4, 1.8);
+        let boost = intensity_curve(self.intensity, 0.3, 1.6);
+        self.time += (0.05 + total_energy * 0.2) * speed;
 
         let bass_energy = spectrum[..8].iter().sum::<f32>() / 8.0;
         let mid_energy = spectrum[8..24].iter().sum::<f32>() / 16.0;
         let high_energy = spectrum[24..].iter().sum::<f32>() / 40.0;
 
-        frame.par_chunks_mut(3).enumerate().for_each(|(i, pixel)| {
-            let x = ((i % 128) as f32 - 64.0) / 64.0;
-            let y = ((i / 128) as f32 - 64.0) / 64.0;
+        if self.polar.len() != canvas.width() * canvas.height() {
+            self.polar = Self::polar_map(canvas.width(), canvas.height());
+        }
 
-            let dist = (x * x + y * y).sqrt();
-            let angle = y.atan2(x);
+        let width = canvas.width();
+        canvas.par_pixels_mut().for_each(|(x, y, pixel)| {
+            let (dist, angle) = self.polar[y * width + x];
 
             let speed_mod = 1.0 + bass_energy * 3.0;
 
@@ -346,7 +1265,8 @@ impl Effect for CircularWave {
 
             let base_intensity = 0.3;
             let audio_intensity =
-                wave1 * bass_energy * 2.0 + wave2 * mid_energy * 1.5 + wave3 * high_energy;
+                (wave1 * bass_energy * 2.0 + wave2 * mid_energy * 1.5 + wave3 * high_energy)
+                    * boost;
 
             let intensity = (base_intensity + audio_intensity).min(1.0);
 
@@ -365,30 +1285,39 @@ impl Effect for CircularWave {
     fn set_color_mode(&mut self, mode: &str) {}
 
     fn set_custom_color(&mut self, r: f32, g: f32, b: f32) {}
-}
 
-pub struct ParticleSystem {
-    particles: Vec<Particle>,
+    fn warm_up(&mut self, width: usize, height: usize) {
+        self.polar = Self::polar_map(width, height);
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![EffectParameter::intensity(self.intensity)]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == INTENSITY {
+            self.intensity = value;
+        }
+    }
 }
 
-struct Particle {
-    x: f32,
-    y: f32,
-    vx: f32,
-    vy: f32,
-    life: f32,
-    color: (f32, f32, f32),
+pub struct ParticleSystem {
+    particles: ParticlePool<(f32, f32, f32)>,
+    rng: EffectRng,
+    intensity: f32,
 }
 
 impl ParticleSystem {
     pub fn new() -> Self {
         Self {
-            particles: Vec::with_capacity(1000),
+            particles: ParticlePool::new(2000),
+            rng: EffectRng::default(),
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
     fn get_particle_color(
-        &self,
+        &mut self,
         particle_index: usize,
         base_particles: usize,
         _bass_energy: f32,
@@ -399,182 +1328,204 @@ impl ParticleSystem {
         match color_mode.mode.as_str() {
             "rainbow" => {
                 let hue = if particle_index < base_particles {
-                    rand()
+                    self.rng.next_f32()
                 } else if particle_index % 3 == 0 {
-                    rand() * 0.1 // Rouge
+                    self.rng.next_f32() * 0.1 // Rouge
                 } else if particle_index % 3 == 1 {
-                    0.3 + rand() * 0.3
+                    0.3 + self.rng.next_f32() * 0.3
                 } else {
-                    0.7 + rand() * 0.3
+                    0.7 + self.rng.next_f32() * 0.3
                 };
                 hsv_to_rgb(hue, 1.0, 1.0)
             }
             "fire" => {
-                let hue = rand() * 0.15;
-                let saturation = 0.8 + rand() * 0.2;
-                let brightness = 0.7 + rand() * 0.3;
+                let hue = self.rng.next_f32() * 0.15;
+                let saturation = 0.8 + self.rng.next_f32() * 0.2;
+                let brightness = 0.7 + self.rng.next_f32() * 0.3;
                 hsv_to_rgb(hue, saturation, brightness)
             }
             "ocean" => {
-                let hue = 0.5 + rand() * 0.17;
-                let saturation = 0.6 + rand() * 0.4;
-                let brightness = 0.6 + rand() * 0.4;
+                let hue = 0.5 + self.rng.next_f32() * 0.17;
+                let saturation = 0.6 + self.rng.next_f32() * 0.4;
+                let brightness = 0.6 + self.rng.next_f32() * 0.4;
                 hsv_to_rgb(hue, saturation, brightness)
             }
             "sunset" => {
-                let hue = if rand() > 0.5 {
-                    0.833 + rand() * 0.167
+                let hue = if self.rng.next_f32() > 0.5 {
+                    0.833 + self.rng.next_f32() * 0.167
                 } else {
-                    rand() * 0.167
+                    self.rng.next_f32() * 0.167
                 };
                 hsv_to_rgb(hue, 1.0, 1.0)
             }
             "custom" => {
                 let (r, g, b) = color_mode.custom_color;
-                let variation = 0.8 + rand() * 0.4;
+                let variation = 0.8 + self.rng.next_f32() * 0.4;
                 (
                     (r * variation).min(1.0),
                     (g * variation).min(1.0),
                     (b * variation).min(1.0),
                 )
             }
-            _ => hsv_to_rgb(rand(), 1.0, 1.0),
+            _ => hsv_to_rgb(self.rng.next_f32(), 1.0, 1.0),
         }
     }
 }
 
 impl Effect for ParticleSystem {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let bass_energy = spectrum[..8].iter().sum::<f32>() / 8.0;
         let mid_energy = spectrum[8..24].iter().sum::<f32>() / 16.0;
         let high_energy = spectrum[24..].iter().sum::<f32>() / 40.0;
         let total_energy = (bass_energy + mid_energy + high_energy) / 3.0;
 
+        // Intensity scales how many particles the music spawns and how fast they fly
+        let count = intensity_curve(self.intensity, 0.2, 2.5);
+        let speed = intensity_curve(self.intensity, 0.6, 1.5);
+
         let base_particles = if self.particles.len() < 100 { 2 } else { 0 };
-        let audio_particles = if total_energy > 0.05 && self.particles.len() < 2000 {
-            ((bass_energy * 50.0).min(20.0)
+        let audio_particles = if total_energy > 0.05 && !self.particles.is_full() {
+            (((bass_energy * 50.0).min(20.0)
                 + (mid_energy * 30.0).min(10.0)
-                + (high_energy * 20.0).min(5.0)) as usize
+                + (high_energy * 20.0).min(5.0))
+                * count) as usize
         } else {
             0
         };
 
         let num_particles = base_particles + audio_particles;
+        let (width, height) = canvas.size();
+        let (_, center_y) = canvas.center();
 
         for i in 0..num_particles {
+            // Bass rises from the floor, mids blow in from the sides, highs appear anywhere
             let (spawn_x, spawn_y) = if i < base_particles {
-                (rand() * 128.0, 100.0 + rand() * 28.0)
+                (
+                    self.rng.next_f32() * width,
+                    height - 28.0 + self.rng.next_f32() * 28.0,
+                )
             } else if i % 3 == 0 && bass_energy > 0.1 {
-                (rand() * 128.0, 120.0 + rand() * 8.0)
+                (
+                    self.rng.next_f32() * width,
+                    height - 8.0 + self.rng.next_f32() * 8.0,
+                )
             } else if i % 3 == 1 && mid_energy > 0.1 {
-                if rand() > 0.5 {
-                    (0.0 + rand() * 8.0, 64.0 + (rand() - 0.5) * 64.0)
+                if self.rng.next_f32() > 0.5 {
+                    (
+                        self.rng.next_f32() * 8.0,
+                        center_y + (self.rng.next_f32() - 0.5) * center_y,
+                    )
                 } else {
-                    (120.0 + rand() * 8.0, 64.0 + (rand() - 0.5) * 64.0)
+                    (
+                        width - 8.0 + self.rng.next_f32() * 8.0,
+                        center_y + (self.rng.next_f32() - 0.5) * center_y,
+                    )
                 }
             } else {
-                (rand() * 128.0, rand() * 128.0)
+                (self.rng.next_f32() * width, self.rng.next_f32() * height)
             };
 
             let (vx, vy) = if i < base_particles {
-                ((rand() - 0.5) * 5.0, -rand() * 8.0 - 2.0)
+                (
+                    (self.rng.next_f32() - 0.5) * 5.0,
+                    -self.rng.next_f32() * 8.0 - 2.0,
+                )
             } else if i % 3 == 0 {
                 (
-                    (rand() - 0.5) * bass_energy * 10.0,
-                    -bass_energy * 15.0 - rand() * 5.0,
+                    (self.rng.next_f32() - 0.5) * bass_energy * 10.0,
+                    -bass_energy * 15.0 - self.rng.next_f32() * 5.0,
                 )
             } else if i % 3 == 1 {
                 (
-                    (rand() - 0.5) * mid_energy * 15.0,
-                    (rand() - 0.5) * mid_energy * 10.0,
+                    (self.rng.next_f32() - 0.5) * mid_energy * 15.0,
+                    (self.rng.next_f32() - 0.5) * mid_energy * 10.0,
                 )
             } else {
                 (
-                    (rand() - 0.5) * high_energy * 20.0,
-                    (rand() - 0.5) * high_energy * 20.0,
+                    (self.rng.next_f32() - 0.5) * high_energy * 20.0,
+                    (self.rng.next_f32() - 0.5) * high_energy * 20.0,
                 )
             };
 
             let color =
                 self.get_particle_color(i, base_particles, bass_energy, mid_energy, high_energy);
 
-            self.particles.push(Particle {
-                x: spawn_x,
-                y: spawn_y,
-                vx,
-                vy,
-                life: 0.5 + total_energy * 0.5,
+            self.particles.spawn(Particle::new(
+                spawn_x,
+                spawn_y,
+                vx * speed,
+                vy * speed,
+                0.5 + total_energy * 0.5,
                 color,
-            });
+            ));
         }
 
-        self.particles.retain_mut(|p| {
-            p.x += p.vx;
-            p.y += p.vy;
-
-            p.vy += 0.3 - total_energy * 0.2;
-
-            let friction = 0.97 - total_energy * 0.02;
-            p.vx *= friction;
-            p.vy *= friction;
-
-            p.life -= 0.02 - total_energy * 0.01;
-
-            p.life > 0.0 && p.x >= -5.0 && p.x < 133.0 && p.y >= -5.0 && p.y < 133.0
+        let friction = 0.97 - total_energy * 0.02;
+        self.particles.step(&Physics {
+            gravity: 0.3 - total_energy * 0.2,
+            drag_x: friction,
+            drag_y: friction,
+            fade: 0.02 - total_energy * 0.01,
+            margin: 5.0,
+            bounds: (width, height),
         });
 
-        frame.fill(0);
-
-        for particle in &self.particles {
-            let x = particle.x as i32;
-            let y = particle.y as i32;
+        canvas.clear();
 
+        for particle in self.particles.iter() {
             let size = if particle.life > 0.7 { 2 } else { 1 };
-
-            for dy in -size..=size {
-                for dx in -size..=size {
-                    let px = x + dx;
-                    let py = y + dy;
-
-                    if px >= 0 && px < 128 && py >= 0 && py < 128 {
-                        let idx = (py as usize * 128 + px as usize) * 3;
-                        if idx + 2 < frame.len() {
-                            let factor = if dx == 0 && dy == 0 { 1.0 } else { 0.5 };
-                            frame[idx] = ((particle.color.0 * particle.life * 255.0 * factor)
-                                as u8)
-                                .saturating_add(frame[idx]);
-                            frame[idx + 1] = ((particle.color.1 * particle.life * 255.0 * factor)
-                                as u8)
-                                .saturating_add(frame[idx + 1]);
-                            frame[idx + 2] = ((particle.color.2 * particle.life * 255.0 * factor)
-                                as u8)
-                                .saturating_add(frame[idx + 2]);
-                        }
-                    }
-                }
-            }
+            add_dot(
+                canvas,
+                particle.x as i32,
+                particle.y as i32,
+                size,
+                particle.data,
+                particle.life,
+            );
         }
     }
 
     fn set_color_mode(&mut self, mode: &str) {}
 
     fn set_custom_color(&mut self, r: f32, g: f32, b: f32) {}
+
+    fn resource_usage(&self) -> ResourceUsage {
+        self.particles.usage()
+    }
+
+    fn enforce_budget(&mut self, limits: &ResourceLimits) -> usize {
+        self.particles.trim(limits.max_particles)
+    }
+
+    fn set_rng(&mut self, rng: EffectRng) {
+        self.rng = rng;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![EffectParameter::intensity(self.intensity)]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == INTENSITY {
+            self.intensity = value;
+        }
+    }
 }
 
 pub struct Flames {
-    particles: Vec<FlameParticle>,
+    particles: ParticlePool<FlameParticle>,
     heat_sources: Vec<f32>,
     time: f32,
     sound_history: Vec<f32>,
     base_temperature: f32,
+    rng: EffectRng,
+    // Particles kept in silence, up to 5/3 of it at full volume
+    particle_count: f32,
+    cooling: f32,
+    intensity: f32,
 }
 
-#[derive(Clone)]
 struct FlameParticle {
-    x: f32,
-    y: f32,
-    velocity_x: f32,
-    velocity_y: f32,
     temperature: f32,
     age: f32,
     max_age: f32,
@@ -583,60 +1534,61 @@ struct FlameParticle {
 }
 
 impl FlameParticle {
-    fn new(x: f32, y: f32, temperature: f32) -> Self {
-        Self {
+    fn spawn(rng: &mut EffectRng, x: f32, y: f32, temperature: f32) -> Particle<Self> {
+        Particle::new(
             x,
             y,
-            velocity_x: (rand() - 0.5),
-            velocity_y: -rand() * 1.5 - 0.5, // Toujours vers le haut
-            temperature,
-            age: 0.0,
-            max_age: 15.0 + rand() * 30.0,
-            size: 0.5 + rand() * 1.5,
-            turbulence_offset: rand() * 2.0 * PI,
-        }
+            rng.next_f32() - 0.5,
+            -rng.next_f32() * 1.5 - 0.5, // Toujours vers le haut
+            1.0,
+            Self {
+                temperature,
+                age: 0.0,
+                max_age: 15.0 + rng.next_f32() * 30.0,
+                size: 0.5 + rng.next_f32() * 1.5,
+                turbulence_offset: rng.next_f32() * 2.0 * PI,
+            },
+        )
     }
 
-    fn update(&mut self, time: f32, wind_force: f32, sound_intensity: f32) {
-        self.age += 1.0;
-
-        let turbulence_x = (time * 0.1 + self.turbulence_offset).sin() * 0.3;
-        let _turbulence_y = (time * 0.08 + self.turbulence_offset * 1.3).cos() * 0.2;
+    fn update(
+        particle: &mut Particle<Self>,
+        physics: &Physics,
+        time: f32,
+        wind_force: f32,
+        sound_intensity: f32,
+        cooling: f32,
+    ) -> bool {
+        let flame = &mut particle.data;
+        flame.age += 1.0;
 
-        self.velocity_y -= 0.15 + sound_intensity * 0.1;
-        self.velocity_x += turbulence_x + wind_force;
+        let turbulence_x = (time * 0.1 + flame.turbulence_offset).sin() * 0.3;
+        particle.vx += turbulence_x + wind_force;
 
-        self.velocity_x *= 0.98;
-        self.velocity_y *= 0.995;
+        let cooling_rate = cooling + (flame.age / flame.max_age) * 0.08;
+        flame.temperature *= 1.0 - cooling_rate;
 
-        self.x += self.velocity_x;
-        self.y += self.velocity_y;
-
-        let cooling_rate = 0.02 + (self.age / self.max_age) * 0.08;
-        self.temperature *= 1.0 - cooling_rate;
-
-        let age_factor = 1.0 - (self.age / self.max_age);
+        let age_factor = 1.0 - (flame.age / flame.max_age);
         let sound_boost = 1.0 + sound_intensity * 0.5;
-        self.size = self.size * 0.999 * age_factor * sound_boost;
-    }
+        flame.size = flame.size * 0.999 * age_factor * sound_boost;
 
-    fn is_alive(&self) -> bool {
-        self.age < self.max_age
-            && self.temperature > 0.05
-            && self.y > -10.0
-            && self.x > -10.0
-            && self.x < 138.0
+        let alive = flame.age < flame.max_age && flame.temperature > 0.05;
+        particle.advance(physics) && alive
     }
 }
 
 impl Flames {
     pub fn new() -> Self {
         Self {
-            particles: Vec::new(),
+            particles: ParticlePool::new(usize::MAX),
             heat_sources: vec![0.0; 128],
             time: 0.0,
             sound_history: vec![0.0; 10],
             base_temperature: 0.0,
+            rng: EffectRng::default(),
+            particle_count: FLAME_PARTICLES,
+            cooling: FLAME_COOLING,
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
@@ -709,52 +1661,52 @@ impl Flames {
         }
     }
 
-    fn create_flame_base(&mut self, sound_intensity: f32) {
+    fn create_flame_base(&mut self, sound_intensity: f32, (width, height): (f32, f32)) {
         let base_particles = 2;
         let sound_particles = (sound_intensity * 15.0) as usize;
         let total_new_particles = base_particles + sound_particles;
 
         let base_width = 8.0 + sound_intensity * 20.0;
-        let base_center = 64.0;
+        let base_center = width / 2.0;
 
         for _ in 0..total_new_particles {
-            let x_offset = (rand() - 0.5) * base_width * 2.0;
+            let x_offset = (self.rng.next_f32() - 0.5) * base_width * 2.0;
             let x = base_center + x_offset;
-            let y = 127.0 + (rand() - 0.5) * 4.0;
+            let y = height - 1.0 + (self.rng.next_f32() - 0.5) * 4.0;
 
             let distance_from_center = (x - base_center).abs() / base_width;
             let center_boost = 1.0 - distance_from_center.clamp(0.0, 1.0);
             let temperature = 0.7 + sound_intensity * 0.3 + center_boost * 0.2;
 
-            if x >= 0.0 && x < 128.0 {
-                let particle = FlameParticle::new(x, y, temperature);
-                self.particles.push(particle);
+            if x >= 0.0 && x < width {
+                self.particles
+                    .spawn(FlameParticle::spawn(&mut self.rng, x, y, temperature));
             }
         }
     }
 
-    fn add_sparks(&mut self, sound_intensity: f32) {
-        if sound_intensity > 0.3 && rand() < sound_intensity * 0.5 {
+    fn add_sparks(&mut self, sound_intensity: f32, (width, height): (f32, f32)) {
+        if sound_intensity > 0.3 && self.rng.next_f32() < sound_intensity * 0.5 {
             let spark_count = (sound_intensity * 5.0) as usize;
 
             for _ in 0..spark_count {
-                let x = 64.0 + (rand() - 0.5) * 60.0;
-                let y = 127.0 - rand() * 40.0;
+                let x = width / 2.0 + (self.rng.next_f32() - 0.5) * 60.0;
+                let y = height - 1.0 - self.rng.next_f32() * 40.0;
 
-                let mut spark = FlameParticle::new(x, y, 0.9);
-                spark.velocity_x = (rand() - 0.5) * 6.0;
-                spark.velocity_y = -rand() * 4.0 - 1.0;
-                spark.max_age = 8.0 + rand() * 12.0;
-                spark.size = 0.3 + rand() * 0.7;
+                let mut spark = FlameParticle::spawn(&mut self.rng, x, y, 0.9);
+                spark.vx = (self.rng.next_f32() - 0.5) * 6.0;
+                spark.vy = -self.rng.next_f32() * 4.0 - 1.0;
+                spark.data.max_age = 8.0 + self.rng.next_f32() * 12.0;
+                spark.data.size = 0.3 + self.rng.next_f32() * 0.7;
 
-                self.particles.push(spark);
+                self.particles.spawn(spark);
             }
         }
     }
 }
 
 impl Effect for Flames {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let bass_energy = (spectrum[..8].iter().sum::<f32>() / 8.0) * 3.0;
         let mid_energy = (spectrum[8..24].iter().sum::<f32>() / 16.0) * 2.0;
         let high_energy = (spectrum[24..].iter().sum::<f32>() / 40.0) * 1.5;
@@ -769,35 +1721,43 @@ impl Effect for Flames {
 
         self.time += 1.0 + sound_intensity * 2.0;
 
-        self.create_flame_base(smoothed_intensity);
+        self.create_flame_base(smoothed_intensity, canvas.size());
 
-        self.add_sparks(sound_intensity);
+        self.add_sparks(sound_intensity, canvas.size());
 
         let wind_force = (high_energy - 0.1).max(0.0) * 0.3 * (self.time * 0.05).sin();
 
-        for particle in &mut self.particles {
-            particle.update(self.time, wind_force, sound_intensity);
-        }
-
-        self.particles.retain(|p| p.is_alive());
+        // Intensity makes the fire denser and its flames rise faster
+        let density = intensity_curve(self.intensity, 0.3, 1.8);
+        let lift = intensity_curve(self.intensity, 0.7, 1.4);
+
+        let (time, cooling) = (self.time, self.cooling);
+        let physics = Physics {
+            gravity: -(0.15 + sound_intensity * 0.1) * lift,
+            drag_x: 0.98,
+            drag_y: 0.995,
+            margin: 10.0,
+            bounds: canvas.size(),
+            ..Default::default()
+        };
+        self.particles.update(|p| {
+            FlameParticle::update(p, &physics, time, wind_force, sound_intensity, cooling)
+        });
 
-        let max_particles = 300 + (sound_intensity * 200.0) as usize;
-        if self.particles.len() > max_particles {
-            self.particles
-                .drain(0..self.particles.len() - max_particles);
-        }
+        let max_particles =
+            (self.particle_count * density * (1.0 + sound_intensity * 2.0 / 3.0)) as usize;
+        self.particles.trim(max_particles);
 
-        for pixel in frame.iter_mut() {
-            *pixel = 0;
-        }
+        canvas.clear();
 
-        let mut temperature_buffer = vec![0.0f32; 128 * 128];
+        let (width, height) = (canvas.width(), canvas.height());
+        let mut temperature_buffer = vec![0.0f32; width * height];
 
-        for particle in &self.particles {
+        for particle in self.particles.iter() {
             let px = particle.x as i32;
             let py = particle.y as i32;
 
-            let radius = particle.size.max(1.0);
+            let radius = particle.data.size.max(1.0);
             let radius_sq = radius * radius;
 
             for dy in -(radius as i32)..=(radius as i32) {
@@ -805,14 +1765,14 @@ impl Effect for Flames {
                     let x = px + dx;
                     let y = py + dy;
 
-                    if x >= 0 && x < 128 && y >= 0 && y < 128 {
+                    if canvas.contains(x, y) {
                         let dist_sq = (dx * dx + dy * dy) as f32;
 
                         if dist_sq <= radius_sq {
                             let attenuation = (1.0 - dist_sq / radius_sq).max(0.0);
-                            let contrib = particle.temperature * attenuation;
+                            let contrib = particle.data.temperature * attenuation;
 
-                            let idx = (y * 128 + x) as usize;
+                            let idx = y as usize * width + x as usize;
                             temperature_buffer[idx] = temperature_buffer[idx].max(contrib);
                         }
                     }
@@ -820,52 +1780,93 @@ impl Effect for Flames {
             }
         }
 
-        for y in 0..128 {
-            for x in 0..128 {
-                let idx = y * 128 + x;
-                let temperature = temperature_buffer[idx];
+        for y in 0..height {
+            for x in 0..width {
+                let temperature = temperature_buffer[y * width + x];
 
                 if temperature > 0.01 {
-                    let age_factor = 1.0 - (y as f32 / 128.0);
-                    let (r, g, b) = self.get_flame_color(temperature, age_factor);
-
-                    let frame_idx = idx * 3;
-                    frame[frame_idx] = (r * 255.0).clamp(0.0, 255.0) as u8;
-                    frame[frame_idx + 1] = (g * 255.0).clamp(0.0, 255.0) as u8;
-                    frame[frame_idx + 2] = (b * 255.0).clamp(0.0, 255.0) as u8;
+                    let age_factor = 1.0 - (y as f32 / height as f32);
+                    let color = self.get_flame_color(temperature, age_factor);
+                    canvas.put_pixel(x as i32, y as i32, color, Blend::Replace);
                 }
             }
         }
     }
+
+    fn resource_usage(&self) -> ResourceUsage {
+        self.particles.usage()
+    }
+
+    fn enforce_budget(&mut self, limits: &ResourceLimits) -> usize {
+        self.particles.trim(limits.max_particles)
+    }
+
+    fn set_rng(&mut self, rng: EffectRng) {
+        self.rng = rng;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![
+            EffectParameter::intensity(self.intensity),
+            EffectParameter::new(
+                "particle_count",
+                self.particle_count,
+                50.0,
+                2000.0,
+                FLAME_PARTICLES,
+            ),
+            EffectParameter::new("cooling", self.cooling, 0.005, 0.1, FLAME_COOLING),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        match name {
+            INTENSITY => self.intensity = value,
+            "particle_count" => self.particle_count = value.round(),
+            "cooling" => self.cooling = value,
+            _ => {}
+        }
+    }
 }
 
 struct Rain {
-    drops: Vec<RainDrop>,
+    drops: ParticlePool<RainDrop>,
     animation_counter: f32,
     color_mode: String,
     custom_color: (f32, f32, f32),
+    sensitivity: f32,
+    rng: EffectRng,
+    // Multipliers on the drops spawned per frame and on their falling speed
+    density: f32,
+    speed: f32,
+    intensity: f32,
 }
 
 struct RainDrop {
-    x: f32,
-    y: f32,
     length: f32,
-    speed: f32,
     brightness: f32,
 }
 
+impl RainDrop {
+    // Drops fall at a constant speed and never fade, they die below the frame
+    fn spawn(x: f32, y: f32, length: f32, speed: f32, brightness: f32) -> Particle<Self> {
+        Particle::new(x, y, 0.0, speed, 1.0, Self { length, brightness })
+    }
+}
+
 impl Rain {
     fn new() -> Self {
-        let mut drops = Vec::with_capacity(100);
+        let mut drops = ParticlePool::new(RAIN_MAX_DROPS as usize);
+        let mut rng = EffectRng::default();
 
         for _ in 0..50 {
-            drops.push(RainDrop {
-                x: rand() * 128.0,
-                y: rand() * 128.0,
-                length: 3.0 + rand() * 10.0,
-                speed: 1.0 + rand() * 3.0,
-                brightness: 0.3 + rand() * 0.7,
-            });
+            drops.spawn(RainDrop::spawn(
+                rng.next_f32() * FRAME_WIDTH as f32,
+                rng.next_f32() * FRAME_HEIGHT as f32,
+                3.0 + rng.next_f32() * 10.0,
+                1.0 + rng.next_f32() * 3.0,
+                0.3 + rng.next_f32() * 0.7,
+            ));
         }
 
         Self {
@@ -873,9 +1874,15 @@ impl Rain {
             animation_counter: 0.0,
             color_mode: "ocean".to_string(),
             custom_color: (0.0, 0.5, 1.0),
+            sensitivity: RAIN_SENSITIVITY,
+            rng,
+            density: 1.0,
+            speed: 1.0,
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
+    // `y_pos` runs 0..1 from the top of the canvas
     fn get_rain_color(&self, brightness: f32, y_pos: f32) -> (f32, f32, f32) {
         match self.color_mode.as_str() {
             "custom" => {
@@ -883,7 +1890,7 @@ impl Rain {
                 (r * brightness, g * brightness, b * brightness)
             }
             "rainbow" => {
-                let hue = (y_pos / 128.0) * 360.0;
+                let hue = y_pos * 360.0;
                 let (r, g, b) = hsv_to_rgb(hue, 0.7, brightness);
                 (r, g, b)
             }
@@ -923,107 +1930,75 @@ impl Rain {
 }
 
 impl Effect for Rain {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let bass_energy = spectrum[..8].iter().sum::<f32>() / 8.0;
         let mid_energy = spectrum[8..24].iter().sum::<f32>() / 16.0;
         let high_energy = spectrum[24..].iter().sum::<f32>() / 40.0;
 
-        let sensitivity = 4.0;
-        let bass_energy = (bass_energy * sensitivity).min(1.0);
-        let mid_energy = (mid_energy * sensitivity).min(1.0);
-        let high_energy = (high_energy * sensitivity).min(1.0);
+        let bass_energy = (bass_energy * self.sensitivity).min(1.0);
+        let mid_energy = (mid_energy * self.sensitivity).min(1.0);
+        let high_energy = (high_energy * self.sensitivity).min(1.0);
 
         let total_energy = (bass_energy * 0.5 + mid_energy * 0.3 + high_energy * 0.2).min(1.0);
 
-        for pixel in frame.chunks_exact_mut(3) {
-            pixel[0] = 0;
-            pixel[1] = 0;
-            pixel[2] = 0;
-        }
+        canvas.clear();
+        let (width, height) = canvas.size();
 
         self.animation_counter += 0.1;
 
+        // Intensity thickens the shower and speeds the drops up, on top of their own knobs
+        let density = self.density * intensity_curve(self.intensity, 0.2, 2.5);
+        let speed = self.speed * intensity_curve(self.intensity, 0.6, 1.6);
+
         let drop_chance = 0.2 + total_energy * 0.5;
-        if rand() < drop_chance {
-            let num_new_drops = (1.0 + total_energy * 5.0) as usize;
+        if self.rng.next_f32() < drop_chance {
+            let num_new_drops = ((1.0 + total_energy * 5.0) * density) as usize;
 
             for _ in 0..num_new_drops {
-                if self.drops.len() < 200 {
-                    self.drops.push(RainDrop {
-                        x: rand() * 128.0,
-                        y: -10.0 - rand() * 10.0,
-                        length: 3.0 + rand() * 12.0 + total_energy * 10.0,
-                        speed: 1.0 + rand() * 2.0 + total_energy * 3.0,
-                        brightness: 0.3 + rand() * 0.5 + total_energy * 0.2,
-                    });
-                }
+                self.drops.spawn(RainDrop::spawn(
+                    self.rng.next_f32() * width,
+                    -10.0 - self.rng.next_f32() * 10.0,
+                    3.0 + self.rng.next_f32() * 12.0 + total_energy * 10.0,
+                    (1.0 + self.rng.next_f32() * 2.0 + total_energy * 3.0) * speed,
+                    0.3 + self.rng.next_f32() * 0.5 + total_energy * 0.2,
+                ));
             }
         }
 
-        let mut i = 0;
-        while i < self.drops.len() {
-            {
-                let drop = &mut self.drops[i];
-                drop.y += drop.speed;
-
-                let wind_effect = (self.animation_counter * 0.05).sin() * mid_energy * 0.5;
-                drop.x += wind_effect;
-
-                if drop.x < 0.0 {
-                    drop.x = 0.0;
-                } else if drop.x >= 128.0 {
-                    drop.x = 127.9;
-                }
-            }
+        let wind_effect = (self.animation_counter * 0.05).sin() * mid_energy * 0.5;
+        self.drops.update(|drop| {
+            drop.x = (drop.x + wind_effect).clamp(0.0, width - 0.1);
+            drop.advance(&Physics::default()) && drop.y - drop.data.length <= height
+        });
 
-            let drop = &self.drops[i];
-            let x = drop.x as usize;
-            let start_y = (drop.y - drop.length).max(0.0) as usize;
-            let end_y = drop.y.min(127.0) as usize;
+        for drop in self.drops.iter() {
+            let x = drop.x as i32;
+            let start_y = (drop.y - drop.data.length).max(0.0) as i32;
+            let end_y = drop.y.min(height - 1.0) as i32;
 
             for y in start_y..=end_y {
-                if y < 128 {
-                    let relative_pos =
-                        (y as f32 - start_y as f32) / (end_y as f32 - start_y as f32 + 1.0);
-                    let brightness_factor = drop.brightness * (0.5 + relative_pos * 0.5);
-
-                    let (r, g, b) = self.get_rain_color(brightness_factor, y as f32);
-
-                    let idx = (y * 128 + x) * 3;
-                    if idx + 2 < frame.len() {
-                        frame[idx] = (r * 255.0) as u8;
-                        frame[idx + 1] = (g * 255.0) as u8;
-                        frame[idx + 2] = (b * 255.0) as u8;
-                    }
-                }
-            }
+                let relative_pos =
+                    (y as f32 - start_y as f32) / (end_y as f32 - start_y as f32 + 1.0);
+                let brightness_factor = drop.data.brightness * (0.5 + relative_pos * 0.5);
 
-            if drop.y - drop.length > 128.0 {
-                self.drops.swap_remove(i);
-            } else {
-                i += 1;
+                let color = self.get_rain_color(brightness_factor, y as f32 / height);
+                canvas.put_pixel(x, y, color, Blend::Replace);
             }
         }
 
         if total_energy > 0.3 {
             for _i in 0..5 {
-                let splash_x = rand() * 128.0;
-                let splash_y = 127.0 - rand() * 3.0;
-                let splash_size = 1.0 + rand() * 2.0 + total_energy * 3.0;
+                let splash_x = self.rng.next_f32() * width;
+                let splash_y = height - 1.0 - self.rng.next_f32() * 3.0;
+                let splash_size = 1.0 + self.rng.next_f32() * 2.0 + total_energy * 3.0;
 
                 for dx in -splash_size as i32..=splash_size as i32 {
-                    let x = (splash_x as i32 + dx).max(0).min(127) as usize;
-                    let y = splash_y as usize;
+                    let x = (splash_x as i32 + dx).clamp(0, canvas.width() as i32 - 1);
+                    let y = splash_y as i32;
 
                     let brightness = (1.0 - (dx.abs() as f32 / splash_size)) * total_energy;
-                    let (r, g, b) = self.get_rain_color(brightness, y as f32);
-
-                    let idx = (y * 128 + x) * 3;
-                    if idx + 2 < frame.len() {
-                        frame[idx] = (r * 255.0) as u8;
-                        frame[idx + 1] = (g * 255.0) as u8;
-                        frame[idx + 2] = (b * 255.0) as u8;
-                    }
+                    let color = self.get_rain_color(brightness, y as f32 / height);
+                    canvas.put_pixel(x, y, color, Blend::Replace);
                 }
             }
         }
@@ -1037,44 +2012,104 @@ impl Effect for Rain {
         self.custom_color = (r, g, b);
         self.color_mode = "custom".to_string();
     }
+
+    fn resource_usage(&self) -> ResourceUsage {
+        self.drops.usage()
+    }
+
+    fn enforce_budget(&mut self, limits: &ResourceLimits) -> usize {
+        self.drops.trim(limits.max_particles)
+    }
+
+    fn base_sensitivity(&self) -> Option<f32> {
+        Some(RAIN_SENSITIVITY)
+    }
+
+    fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity;
+    }
+
+    fn set_rng(&mut self, rng: EffectRng) {
+        self.rng = rng;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![
+            EffectParameter::intensity(self.intensity),
+            EffectParameter::new("density", self.density, 0.0, 4.0, 1.0),
+            EffectParameter::new("speed", self.speed, 0.25, 4.0, 1.0),
+            EffectParameter::new(
+                "max_drops",
+                self.drops.capacity() as f32,
+                10.0,
+                1000.0,
+                RAIN_MAX_DROPS,
+            ),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        match name {
+            INTENSITY => self.intensity = value,
+            "density" => self.density = value,
+            "speed" => self.speed = value,
+            "max_drops" => {
+                self.drops.set_capacity(value.round() as usize);
+            }
+            _ => {}
+        }
+    }
 }
 
 pub struct Applaudimetre {
     current_level: f32,
-    max_level: f32,
-    max_hold_time: f32,
+    max_peak: PeakHold,
     smoothed_level: f32,
     peak_history: Vec<f32>,
     animation_time: f32,
     level_history: Vec<f32>,
-    peak_sparkles: Vec<PeakSparkle>,
+    peak_sparkles: ParticlePool<PeakSparkle>,
     sensitivity: f32,
     auto_gain: f32,
     background_pulse: f32,
+    rng: EffectRng,
+    intensity: f32,
 }
 
 struct PeakSparkle {
-    x: f32,
-    y: f32,
-    life: f32,
     brightness: f32,
     color: (f32, f32, f32),
 }
 
+// Sparkles float up from the peak marker and fade out
+const SPARKLE_PHYSICS: Physics = Physics {
+    gravity: 0.0,
+    drag_x: 1.0,
+    drag_y: 1.0,
+    fade: 0.03,
+    margin: f32::INFINITY,
+    bounds: (0.0, 0.0),
+};
+
 impl Applaudimetre {
     pub fn new() -> Self {
         Self {
             current_level: 0.0,
-            max_level: 0.0,
-            max_hold_time: 0.0,
+            max_peak: PeakHold::new(
+                1,
+                APPLAUDIMETRE_PEAK_HOLD_SECS,
+                PeakDecay::Linear(APPLAUDIMETRE_PEAK_DECAY),
+            ),
             smoothed_level: 0.0,
             peak_history: vec![0.0; 30],
             animation_time: 0.0,
             level_history: vec![0.0; 128],
-            peak_sparkles: Vec::new(),
-            sensitivity: 3.0,
+            peak_sparkles: ParticlePool::new(usize::MAX),
+            sensitivity: APPLAUDIMETRE_SENSITIVITY,
             auto_gain: 1.0,
             background_pulse: 0.0,
+            rng: EffectRng::default(),
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
@@ -1180,33 +2215,36 @@ impl Applaudimetre {
         final_level.min(1.0)
     }
 
-    fn update_sparkles(&mut self) {
-        self.peak_sparkles.retain_mut(|sparkle| {
-            sparkle.life -= 0.03;
-            sparkle.y -= 0.5;
-            sparkle.x += (rand() - 0.5) * 0.3;
-            sparkle.life > 0.0
+    fn update_sparkles(&mut self, (width, height): (f32, f32)) {
+        self.peak_sparkles.update(|sparkle| {
+            sparkle.x += (self.rng.next_f32() - 0.5) * 0.3;
+            sparkle.advance(&SPARKLE_PHYSICS)
         });
 
-        if self.current_level > 0.3 && rand() < 0.4 {
-            let bar_center = 64.0;
-            let max_y = 127.0 - self.max_level * 127.0;
-
-            for _ in 0..(1 + (self.current_level * 3.0) as usize) {
-                self.peak_sparkles.push(PeakSparkle {
-                    x: bar_center + (rand() - 0.5) * 50.0,
-                    y: max_y + (rand() - 0.5) * 10.0,
-                    life: 0.5 + rand() * 0.5,
-                    brightness: 0.6 + rand() * 0.4,
-                    color: self.get_color_for_level(self.current_level, false),
-                });
+        if self.current_level > 0.3 && self.rng.next_f32() < 0.4 {
+            let bar_center = width / 2.0;
+            let max_y = (height - 1.0) * (1.0 - self.max_peak.peak(0));
+
+            let sparkles = intensity_curve(self.intensity, 0.0, 2.5);
+            for _ in 0..((1.0 + self.current_level * 3.0) * sparkles) as usize {
+                self.peak_sparkles.spawn(Particle::new(
+                    bar_center + (self.rng.next_f32() - 0.5) * 50.0,
+                    max_y + (self.rng.next_f32() - 0.5) * 10.0,
+                    0.0,
+                    -0.5,
+                    0.5 + self.rng.next_f32() * 0.5,
+                    PeakSparkle {
+                        brightness: 0.6 + self.rng.next_f32() * 0.4,
+                        color: self.get_color_for_level(self.current_level, false),
+                    },
+                ));
             }
         }
     }
 }
 
 impl Effect for Applaudimetre {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let raw_level = self.calculate_audio_level(spectrum);
 
         let smoothing = if raw_level > self.smoothed_level {
@@ -1225,37 +2263,29 @@ impl Effect for Applaudimetre {
             self.peak_history.push(self.current_level);
         }
 
+        // The maximum never falls below the loudest of the last frames
         let recent_max = self.peak_history.iter().cloned().fold(0.0f32, f32::max);
-
-        if self.current_level > self.max_level {
-            self.max_level = self.current_level;
-            self.max_hold_time = 0.0;
-        } else {
-            self.max_hold_time += 1.0 / 60.0;
-            if self.max_hold_time >= 5.0 {
-                let decay_rate = 0.008;
-                self.max_level = (self.max_level - decay_rate).max(recent_max);
-                if self.max_level <= recent_max {
-                    self.max_hold_time = 0.0;
-                }
-            }
-        }
+        self.max_peak.update(&[recent_max]);
 
         self.animation_time += 1.0 + self.current_level * 2.0;
         self.background_pulse = (self.animation_time * 0.05).sin() * 0.1 + 0.9;
-        self.update_sparkles();
+        // The level stays a measurement: intensity only scales the pulse and sparkles around it
+        let pulse_depth = 0.2 * intensity_curve(self.intensity, 0.0, 2.0);
+        self.update_sparkles(canvas.size());
+        let max_level = self.max_peak.peak(0);
+        let max_hold_time = self.max_peak.held_secs(0);
 
-        frame.fill(0);
+        canvas.clear();
 
-        frame.par_chunks_mut(3).enumerate().for_each(|(i, pixel)| {
-            let x = i % 128;
-            let y = i / 128;
-            let bar_left = 40;
-            let bar_right = 88;
-            let bar_center = (bar_left + bar_right) / 2;
+        // Kept off the left edge so the frame around the bar fits on narrow strips
+        let bottom = (canvas.height() - 1).max(1);
+        let bar_center = canvas.width() / 2;
+        let bar_left = (bar_center - canvas.width() * 3 / 16).max(1);
+        let bar_right = (bar_center + canvas.width() * 3 / 16).max(bar_left);
 
+        canvas.par_pixels_mut().for_each(|(x, y, pixel)| {
             if x >= bar_left && x < bar_right {
-                let y_pos = (127 - y) as f32 / 127.0;
+                let y_pos = (bottom - y) as f32 / bottom as f32;
                 let bar_height = self.current_level.min(1.0);
 
                 let history_index = ((x - bar_left) as f32 / (bar_right - bar_left) as f32
@@ -1276,33 +2306,33 @@ impl Effect for Applaudimetre {
                 if y_pos <= bar_height && bar_height > 0.0 {
                     let level_factor = y_pos / bar_height.max(0.01);
 
-                    let distance_from_center =
-                        ((x - bar_center) as f32 / ((bar_right - bar_left) / 2) as f32).abs();
+                    let distance_from_center = ((x as f32 - bar_center as f32)
+                        / ((bar_right - bar_left) / 2) as f32)
+                        .abs();
                     let center_glow = (1.0 - distance_from_center).max(0.0);
 
                     let brightness = 0.7 + level_factor * 0.3 + center_glow * 0.3;
                     let (r, g, b) = self.get_color_for_level(level_factor, false);
 
-                    let pulse = 1.0 + self.current_level * (self.animation_time * 0.1).sin() * 0.2;
+                    let pulse =
+                        1.0 + self.current_level * (self.animation_time * 0.1).sin() * pulse_depth;
 
                     pixel[0] = (r * brightness * pulse * 255.0).min(255.0) as u8;
                     pixel[1] = (g * brightness * pulse * 255.0).min(255.0) as u8;
                     pixel[2] = (b * brightness * pulse * 255.0).min(255.0) as u8;
                 }
 
-                let max_y = (127.0 - self.max_level * 127.0) as usize;
-                if y >= max_y.saturating_sub(2)
-                    && y <= max_y.saturating_add(2)
-                    && self.max_level > 0.05
+                let max_y = (bottom as f32 * (1.0 - max_level)) as usize;
+                if y >= max_y.saturating_sub(2) && y <= max_y.saturating_add(2) && max_level > 0.05
                 {
-                    let (r, g, b) = self.get_color_for_level(self.max_level, true);
+                    let (r, g, b) = self.get_color_for_level(max_level, true);
 
-                    let blink_factor = if self.max_hold_time < 5.0 {
-                        let base_blink = 0.8 + 0.2 * (self.max_hold_time * 8.0).sin();
+                    let blink_factor = if max_hold_time < APPLAUDIMETRE_PEAK_HOLD_SECS {
+                        let base_blink = 0.8 + 0.2 * (max_hold_time * 8.0).sin();
                         let pulse_blink = 1.0 + 0.3 * (self.animation_time * 0.15).sin();
                         base_blink * pulse_blink
                     } else {
-                        0.6 + 0.4 * (self.max_hold_time * 2.0).sin().abs()
+                        0.6 + 0.4 * (max_hold_time * 2.0).sin().abs()
                     };
 
                     let width_factor = if (y as i32 - max_y as i32).abs() <= 1 {
@@ -1318,7 +2348,7 @@ impl Effect for Applaudimetre {
 
                 if x == bar_left + 1 || x == bar_right - 2 {
                     for grad in 0..11 {
-                        let grad_y = (127.0 - (grad as f32 * 127.0 / 10.0)) as usize;
+                        let grad_y = (bottom as f32 * (1.0 - grad as f32 / 10.0)) as usize;
                         if y >= grad_y.saturating_sub(1) && y <= grad_y.saturating_add(1) {
                             let intensity = if grad == 10 {
                                 0.9
@@ -1337,8 +2367,8 @@ impl Effect for Applaudimetre {
                 }
             }
 
-            let is_frame = ((x == bar_left - 1 || x == bar_right) && y < 128)
-                || ((y == 0 || y == 127) && x >= bar_left - 1 && x <= bar_right);
+            let is_frame = (x == bar_left - 1 || x == bar_right)
+                || ((y == 0 || y == bottom) && x >= bar_left - 1 && x <= bar_right);
 
             if is_frame {
                 let glow_intensity = 80.0 + self.current_level * 50.0;
@@ -1348,39 +2378,59 @@ impl Effect for Applaudimetre {
             }
         });
 
-        for sparkle in &self.peak_sparkles {
-            let x = sparkle.x as usize;
-            let y = sparkle.y as usize;
-
-            if x < 128 && y < 128 {
-                let idx = (y * 128 + x) * 3;
-                if idx + 2 < frame.len() {
-                    let intensity = sparkle.brightness * sparkle.life;
-
-                    frame[idx] =
-                        ((sparkle.color.0 * intensity * 255.0) as u8).saturating_add(frame[idx]);
-                    frame[idx + 1] = ((sparkle.color.1 * intensity * 255.0) as u8)
-                        .saturating_add(frame[idx + 1]);
-                    frame[idx + 2] = ((sparkle.color.2 * intensity * 255.0) as u8)
-                        .saturating_add(frame[idx + 2]);
-                }
-            }
+        for sparkle in self.peak_sparkles.iter() {
+            canvas.put_pixel(
+                sparkle.x as i32,
+                sparkle.y as i32,
+                scale(sparkle.data.color, sparkle.data.brightness * sparkle.life),
+                Blend::Add,
+            );
+        }
+    }
+
+    fn resource_usage(&self) -> ResourceUsage {
+        self.peak_sparkles.usage()
+    }
+
+    fn enforce_budget(&mut self, limits: &ResourceLimits) -> usize {
+        self.peak_sparkles.trim(limits.max_particles)
+    }
+
+    fn base_sensitivity(&self) -> Option<f32> {
+        Some(APPLAUDIMETRE_SENSITIVITY)
+    }
+
+    fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity;
+    }
+
+    fn set_rng(&mut self, rng: EffectRng) {
+        self.rng = rng;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![EffectParameter::intensity(self.intensity)]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == INTENSITY {
+            self.intensity = value;
         }
     }
 }
 
 pub struct Starfall {
-    shooting_stars: Vec<ShootingStar>,
+    shooting_stars: ParticlePool<ShootingStar>,
     animation_time: f32,
     spawn_timer: f32,
+    rng: EffectRng,
+    max_stars: f32,
+    // Multiplier on how often stars spawn
+    spawn_rate: f32,
+    intensity: f32,
 }
 
 struct ShootingStar {
-    x: f32,
-    y: f32,
-    velocity_x: f32,
-    velocity_y: f32,
-
     brightness: f32,
     size: f32,
     color: (f32, f32, f32),
@@ -1404,32 +2454,37 @@ struct TrailPoint {
 }
 
 impl ShootingStar {
-    fn new(spawn_side: SpawnSide, sound_intensity: f32) -> Self {
+    fn spawn(
+        rng: &mut EffectRng,
+        spawn_side: SpawnSide,
+        sound_intensity: f32,
+        width: f32,
+    ) -> Particle<Self> {
         let (start_x, start_y, vel_x, vel_y) = match spawn_side {
             SpawnSide::TopLeft => {
-                let x = -30.0 + rand() * 20.0;
-                let y = -30.0 + rand() * 80.0;
-                let vx = 2.0 + rand() * 3.0 + sound_intensity * 3.0;
-                let vy = 1.5 + rand() * 2.5 + sound_intensity * 2.0;
+                let x = -30.0 + rng.next_f32() * 20.0;
+                let y = -30.0 + rng.next_f32() * 80.0;
+                let vx = 2.0 + rng.next_f32() * 3.0 + sound_intensity * 3.0;
+                let vy = 1.5 + rng.next_f32() * 2.5 + sound_intensity * 2.0;
                 (x, y, vx, vy)
             }
             SpawnSide::TopRight => {
-                let x = 138.0 + rand() * 20.0;
-                let y = -30.0 + rand() * 80.0;
-                let vx = -2.0 - rand() * 3.0 - sound_intensity * 3.0;
-                let vy = 1.5 + rand() * 2.5 + sound_intensity * 2.0;
+                let x = width + 10.0 + rng.next_f32() * 20.0;
+                let y = -30.0 + rng.next_f32() * 80.0;
+                let vx = -2.0 - rng.next_f32() * 3.0 - sound_intensity * 3.0;
+                let vy = 1.5 + rng.next_f32() * 2.5 + sound_intensity * 2.0;
                 (x, y, vx, vy)
             }
             SpawnSide::Top => {
-                let x = 20.0 + rand() * 88.0;
-                let y = -40.0 + rand() * 30.0;
-                let vx = (rand() - 0.5) * 4.0;
-                let vy = 3.0 + rand() * 3.0 + sound_intensity * 3.0;
+                let x = 20.0 + rng.next_f32() * (width - 40.0);
+                let y = -40.0 + rng.next_f32() * 30.0;
+                let vx = (rng.next_f32() - 0.5) * 4.0;
+                let vy = 3.0 + rng.next_f32() * 3.0 + sound_intensity * 3.0;
                 (x, y, vx, vy)
             }
         };
 
-        let star_temp = rand();
+        let star_temp = rng.next_f32();
         let base_color = if star_temp < 0.1 {
             (0.7, 0.8, 1.0)
         } else if star_temp < 0.3 {
@@ -1442,64 +2497,61 @@ impl ShootingStar {
             (1.0, 0.6, 0.4)
         };
 
-        Self {
-            x: start_x,
-            y: start_y,
-            velocity_x: vel_x,
-            velocity_y: vel_y,
-            brightness: 0.6 + rand() * 0.4 + sound_intensity * 0.3,
-            size: 1.0 + rand() * 2.0 + sound_intensity * 1.5,
-            color: base_color,
-            trail_points: Vec::new(),
-            max_trail_length: (15 + (sound_intensity * 25.0) as usize).min(40),
-            age: 0.0,
-            max_age: 120.0 + rand() * 60.0,
-            twinkle_phase: rand() * 2.0 * PI,
-            twinkle_speed: 0.1 + rand() * 0.2,
-        }
+        Particle::new(
+            start_x,
+            start_y,
+            vel_x,
+            vel_y,
+            1.0,
+            Self {
+                brightness: 0.6 + rng.next_f32() * 0.4 + sound_intensity * 0.3,
+                size: 1.0 + rng.next_f32() * 2.0 + sound_intensity * 1.5,
+                color: base_color,
+                trail_points: Vec::new(),
+                max_trail_length: (15 + (sound_intensity * 25.0) as usize).min(40),
+                age: 0.0,
+                max_age: 120.0 + rng.next_f32() * 60.0,
+                twinkle_phase: rng.next_f32() * 2.0 * PI,
+                twinkle_speed: 0.1 + rng.next_f32() * 0.2,
+            },
+        )
     }
 
-    fn update(&mut self, time: f32) {
-        self.age += 1.0;
-        self.twinkle_phase += self.twinkle_speed;
+    fn update(particle: &mut Particle<Self>, time: f32, bounds: (f32, f32)) -> bool {
+        let star = &mut particle.data;
+        star.age += 1.0;
+        star.twinkle_phase += star.twinkle_speed;
 
-        self.trail_points.push(TrailPoint {
-            x: self.x,
-            y: self.y,
-            intensity: self.brightness,
+        star.trail_points.push(TrailPoint {
+            x: particle.x,
+            y: particle.y,
+            intensity: star.brightness,
             age: 0.0,
         });
 
-        if self.trail_points.len() > self.max_trail_length {
-            self.trail_points.remove(0);
+        if star.trail_points.len() > star.max_trail_length {
+            star.trail_points.remove(0);
         }
 
-        for point in &mut self.trail_points {
+        for point in &mut star.trail_points {
             point.age += 1.0;
             point.intensity *= 0.95;
         }
 
-        self.x += self.velocity_x;
-        self.y += self.velocity_y;
-
-        self.velocity_y += 0.05;
+        particle.vx += (time * 0.1 + star.twinkle_phase).sin() * 0.1;
+        particle.vy += (time * 0.08 + star.twinkle_phase * 1.3).cos() * 0.05;
 
-        self.velocity_x += (time * 0.1 + self.twinkle_phase).sin() * 0.1;
-        self.velocity_y += (time * 0.08 + self.twinkle_phase * 1.3).cos() * 0.05;
-
-        if self.age > self.max_age * 0.7 {
-            let fade_factor = 1.0 - (self.age - self.max_age * 0.7) / (self.max_age * 0.3);
-            self.brightness *= fade_factor.max(0.0);
+        if star.age > star.max_age * 0.7 {
+            let fade_factor = 1.0 - (star.age - star.max_age * 0.7) / (star.max_age * 0.3);
+            star.brightness *= fade_factor.max(0.0);
         }
-    }
 
-    fn is_alive(&self) -> bool {
-        self.age < self.max_age
-            && self.brightness > 0.01
-            && self.x > -50.0
-            && self.x < 178.0
-            && self.y > -50.0
-            && self.y < 178.0
+        let alive = star.age < star.max_age && star.brightness > 0.01;
+        alive
+            && particle.advance(&Physics {
+                bounds,
+                ..STAR_PHYSICS
+            })
     }
 
     fn get_twinkle_factor(&self) -> f32 {
@@ -1507,6 +2559,15 @@ impl ShootingStar {
     }
 }
 
+const STAR_PHYSICS: Physics = Physics {
+    gravity: 0.05,
+    drag_x: 1.0,
+    drag_y: 1.0,
+    fade: 0.0,
+    margin: 50.0,
+    bounds: (0.0, 0.0),
+};
+
 #[derive(Clone)]
 enum SpawnSide {
     TopLeft,
@@ -1517,9 +2578,13 @@ enum SpawnSide {
 impl Starfall {
     pub fn new() -> Self {
         Self {
-            shooting_stars: Vec::new(),
+            shooting_stars: ParticlePool::new(usize::MAX),
             animation_time: 0.0,
             spawn_timer: 0.0,
+            rng: EffectRng::default(),
+            max_stars: STARFALL_MAX_STARS,
+            spawn_rate: 1.0,
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
@@ -1558,35 +2623,39 @@ impl Starfall {
         }
     }
 
-    fn spawn_shooting_star(&mut self, sound_intensity: f32) {
-        let spawn_side = match (rand() * 3.0) as usize {
+    fn spawn_shooting_star(&mut self, sound_intensity: f32, width: f32) {
+        let spawn_side = match (self.rng.next_f32() * 3.0) as usize {
             0 => SpawnSide::TopLeft,
             1 => SpawnSide::TopRight,
             _ => SpawnSide::Top,
         };
 
-        let star = ShootingStar::new(spawn_side, sound_intensity);
-        self.shooting_stars.push(star);
+        self.shooting_stars.spawn(ShootingStar::spawn(
+            &mut self.rng,
+            spawn_side,
+            sound_intensity,
+            width,
+        ));
     }
 
-    fn create_meteor_shower(&mut self, intensity: f32) {
+    fn create_meteor_shower(&mut self, intensity: f32, width: f32) {
         let meteor_count = (intensity * 8.0) as usize;
         for _ in 0..meteor_count {
             let spawn_side = SpawnSide::TopLeft;
-            let mut star = ShootingStar::new(spawn_side, intensity);
+            let mut star = ShootingStar::spawn(&mut self.rng, spawn_side, intensity, width);
 
-            star.velocity_x *= 1.5;
-            star.velocity_y *= 1.5;
-            star.brightness *= 1.3;
-            star.max_trail_length *= 2;
+            star.vx *= 1.5;
+            star.vy *= 1.5;
+            star.data.brightness *= 1.3;
+            star.data.max_trail_length *= 2;
 
-            self.shooting_stars.push(star);
+            self.shooting_stars.spawn(star);
         }
     }
 }
 
 impl Effect for Starfall {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let bass_energy = (spectrum[..8].iter().sum::<f32>() / 8.0) * 4.0;
         let mid_energy = (spectrum[8..24].iter().sum::<f32>() / 16.0) * 3.0;
         let high_energy = (spectrum[24..].iter().sum::<f32>() / 40.0) * 2.0;
@@ -1600,8 +2669,13 @@ impl Effect for Starfall {
         let base_spawn_interval = 45.0;
         let min_spawn_interval = 8.0;
 
-        let spawn_interval =
-            base_spawn_interval - (total_energy * (base_spawn_interval - min_spawn_interval));
+        // Intensity brings stars more often and lets more of them fly at once
+        let spawn_rate = self.spawn_rate * intensity_curve(self.intensity, 0.3, 2.0);
+        let max_stars = self.max_stars * intensity_curve(self.intensity, 0.4, 1.8);
+
+        let spawn_interval = (base_spawn_interval
+            - (total_energy * (base_spawn_interval - min_spawn_interval)))
+            / spawn_rate;
 
         if self.spawn_timer >= spawn_interval {
             self.spawn_timer = 0.0;
@@ -1615,25 +2689,25 @@ impl Effect for Starfall {
             };
 
             for _ in 0..star_count {
-                if self.shooting_stars.len() < 25 {
-                    self.spawn_shooting_star(total_energy);
+                if self.shooting_stars.len() < max_stars as usize {
+                    self.spawn_shooting_star(total_energy, canvas.size().0);
                 }
             }
         }
 
-        if total_energy > 0.85 && rand() < 0.1 {
-            self.create_meteor_shower(total_energy);
-        }
-
-        for star in &mut self.shooting_stars {
-            star.update(self.animation_time);
+        if total_energy > 0.85 && self.rng.next_f32() < 0.1 {
+            self.create_meteor_shower(total_energy, canvas.size().0);
         }
 
-        self.shooting_stars.retain(|star| star.is_alive());
+        let time = self.animation_time;
+        let bounds = canvas.size();
+        self.shooting_stars
+            .update(|star| ShootingStar::update(star, time, bounds));
 
-        frame.fill(0);
+        canvas.clear();
 
-        for star in &self.shooting_stars {
+        for particle in self.shooting_stars.iter() {
+            let star = &particle.data;
             let twinkle = star.get_twinkle_factor();
 
             for (i, trail_point) in star.trail_points.iter().enumerate() {
@@ -1641,56 +2715,42 @@ impl Effect for Starfall {
                     let trail_age_factor = 1.0 - (i as f32 / star.trail_points.len() as f32);
                     let trail_brightness = trail_point.intensity * trail_age_factor * 0.6;
 
-                    let px = trail_point.x as i32;
-                    let py = trail_point.y as i32;
-
-                    if px >= 0 && px < 128 && py >= 0 && py < 128 {
-                        let idx = (py as usize * 128 + px as usize) * 3;
-                        if idx + 2 < frame.len() {
-                            let (r, g, b) = self.get_star_color(star.color, trail_brightness);
-
-                            frame[idx] = ((r * 255.0) as u8).saturating_add(frame[idx]);
-                            frame[idx + 1] = ((g * 255.0) as u8).saturating_add(frame[idx + 1]);
-                            frame[idx + 2] = ((b * 255.0) as u8).saturating_add(frame[idx + 2]);
-                        }
-                    }
+                    canvas.put_pixel(
+                        trail_point.x as i32,
+                        trail_point.y as i32,
+                        self.get_star_color(star.color, trail_brightness),
+                        Blend::Add,
+                    );
                 }
             }
 
             let star_brightness = star.brightness * twinkle;
             let star_size = star.size;
 
-            let center_x = star.x as i32;
-            let center_y = star.y as i32;
+            let center_x = particle.x as i32;
+            let center_y = particle.y as i32;
 
             for dy in -(star_size as i32 + 1)..=(star_size as i32 + 1) {
                 for dx in -(star_size as i32 + 1)..=(star_size as i32 + 1) {
-                    let x = center_x + dx;
-                    let y = center_y + dy;
+                    let distance = ((dx * dx + dy * dy) as f32).sqrt();
 
-                    if x >= 0 && x < 128 && y >= 0 && y < 128 {
-                        let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                    let mut intensity = 0.0;
 
-                        let mut intensity = 0.0;
-
-                        if distance <= star_size {
-                            let core_falloff = (1.0 - distance / star_size).max(0.0);
-                            intensity = star_brightness * core_falloff;
-                        } else if distance <= star_size + 1.0 {
-                            let halo_falloff = (1.0 - (distance - star_size)).max(0.0);
-                            intensity = star_brightness * halo_falloff * 0.3;
-                        }
-
-                        if intensity > 0.01 {
-                            let idx = (y as usize * 128 + x as usize) * 3;
-                            if idx + 2 < frame.len() {
-                                let (r, g, b) = self.get_star_color(star.color, intensity);
+                    if distance <= star_size {
+                        let core_falloff = (1.0 - distance / star_size).max(0.0);
+                        intensity = star_brightness * core_falloff;
+                    } else if distance <= star_size + 1.0 {
+                        let halo_falloff = (1.0 - (distance - star_size)).max(0.0);
+                        intensity = star_brightness * halo_falloff * 0.3;
+                    }
 
-                                frame[idx] = ((r * 255.0) as u8).saturating_add(frame[idx]);
-                                frame[idx + 1] = ((g * 255.0) as u8).saturating_add(frame[idx + 1]);
-                                frame[idx + 2] = ((b * 255.0) as u8).saturating_add(frame[idx + 2]);
-                            }
-                        }
+                    if intensity > 0.01 {
+                        canvas.put_pixel(
+                            center_x + dx,
+                            center_y + dy,
+                            self.get_star_color(star.color, intensity),
+                            Blend::Add,
+                        );
                     }
                 }
             }
@@ -1700,41 +2760,55 @@ impl Effect for Starfall {
                 let ray_brightness = star_brightness * 0.4;
 
                 for offset in -ray_length..=ray_length {
-                    let hx = center_x + offset;
-                    let hy = center_y;
-                    if hx >= 0 && hx < 128 && hy >= 0 && hy < 128 {
-                        let idx = (hy as usize * 128 + hx as usize) * 3;
-                        if idx + 2 < frame.len() {
-                            let ray_intensity =
-                                ray_brightness * (1.0 - offset.abs() as f32 / ray_length as f32);
-                            let (r, g, b) = self.get_star_color(star.color, ray_intensity);
-
-                            frame[idx] = ((r * 128.0) as u8).saturating_add(frame[idx]);
-                            frame[idx + 1] = ((g * 128.0) as u8).saturating_add(frame[idx + 1]);
-                            frame[idx + 2] = ((b * 128.0) as u8).saturating_add(frame[idx + 2]);
-                        }
-                    }
+                    let ray_intensity =
+                        ray_brightness * (1.0 - offset.abs() as f32 / ray_length as f32);
+                    let color = scale(self.get_star_color(star.color, ray_intensity), 0.5);
 
-                    let vx = center_x;
-                    let vy = center_y + offset;
-                    if vx >= 0 && vx < 128 && vy >= 0 && vy < 128 {
-                        let idx = (vy as usize * 128 + vx as usize) * 3;
-                        if idx + 2 < frame.len() {
-                            let ray_intensity =
-                                ray_brightness * (1.0 - offset.abs() as f32 / ray_length as f32);
-                            let (r, g, b) = self.get_star_color(star.color, ray_intensity);
-
-                            frame[idx] = ((r * 128.0) as u8).saturating_add(frame[idx]);
-                            frame[idx + 1] = ((g * 128.0) as u8).saturating_add(frame[idx + 1]);
-                            frame[idx + 2] = ((b * 128.0) as u8).saturating_add(frame[idx + 2]);
-                        }
-                    }
+                    canvas.put_pixel(center_x + offset, center_y, color, Blend::Add);
+                    canvas.put_pixel(center_x, center_y + offset, color, Blend::Add);
                 }
             }
         }
     }
-}
 
+    fn resource_usage(&self) -> ResourceUsage {
+        let mut usage = self.shooting_stars.usage();
+        for star in self.shooting_stars.iter() {
+            usage += ResourceUsage::trails(&star.data.trail_points);
+        }
+        usage
+    }
+
+    fn enforce_budget(&mut self, limits: &ResourceLimits) -> usize {
+        let mut trimmed = self.shooting_stars.trim(limits.max_particles);
+        let per_star = limits.max_trail_points / self.shooting_stars.len().max(1);
+        for star in self.shooting_stars.iter_mut() {
+            trimmed += trim_oldest(&mut star.data.trail_points, per_star);
+        }
+        trimmed
+    }
+
+    fn set_rng(&mut self, rng: EffectRng) {
+        self.rng = rng;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![
+            EffectParameter::intensity(self.intensity),
+            EffectParameter::new("max_stars", self.max_stars, 1.0, 100.0, STARFALL_MAX_STARS),
+            EffectParameter::new("spawn_rate", self.spawn_rate, 0.1, 5.0, 1.0),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        match name {
+            INTENSITY => self.intensity = value,
+            "max_stars" => self.max_stars = value.round(),
+            "spawn_rate" => self.spawn_rate = value,
+            _ => {}
+        }
+    }
+}
 pub struct Heartbeat {
     beat_phase: f32,
     beat_intensity: f32,
@@ -1743,6 +2817,11 @@ pub struct Heartbeat {
     animation_time: f32,
     last_beat_time: f32,
     beat_frequency: f32,
+    sensitivity: f32,
+    rng: EffectRng,
+    beat: Option<BeatPhase>,
+    last_music_phase: f32,
+    intensity: f32,
 }
 
 struct PulseRing {
@@ -1762,6 +2841,11 @@ impl Heartbeat {
             animation_time: 0.0,
             last_beat_time: 0.0,
             beat_frequency: 60.0,
+            sensitivity: HEARTBEAT_SENSITIVITY,
+            rng: EffectRng::default(),
+            beat: None,
+            last_music_phase: 0.0,
+            intensity: DEFAULT_INTENSITY,
         }
     }
 
@@ -1808,24 +2892,34 @@ impl Heartbeat {
 }
 
 impl Effect for Heartbeat {
-    fn render(&mut self, spectrum: &[f32], frame: &mut [u8]) {
+    fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
         let bass_energy = spectrum[..8].iter().sum::<f32>() / 8.0;
         let mid_energy = spectrum[8..24].iter().sum::<f32>() / 16.0;
         let high_energy = spectrum[24..].iter().sum::<f32>() / 40.0;
 
-        let sensitivity = 5.0;
-        let bass = (bass_energy * sensitivity).min(1.0);
-        let mid = (mid_energy * sensitivity).min(1.0);
-        let high = (high_energy * sensitivity).min(1.0);
+        let bass = (bass_energy * self.sensitivity).min(1.0);
+        let mid = (mid_energy * self.sensitivity).min(1.0);
+        let high = (high_energy * self.sensitivity).min(1.0);
 
         let total_energy = (bass * 0.6 + mid * 0.3 + high * 0.1).min(1.0);
 
-        self.beat_frequency = 40.0 + total_energy * 100.0;
-        let beat_interval = 60.0 / self.beat_frequency;
-
         self.animation_time += 1.0 / 60.0;
 
-        if self.animation_time - self.last_beat_time >= beat_interval {
+        // Locked to the music once a tempo is found, free-running on the energy otherwise
+        let pulse = match self.beat {
+            Some(beat) => {
+                self.beat_frequency = beat.bpm;
+                let wrapped = beat.phase < self.last_music_phase;
+                self.last_music_phase = beat.phase;
+                wrapped
+            }
+            None => {
+                self.beat_frequency = 40.0 + total_energy * 100.0;
+                self.animation_time - self.last_beat_time >= 60.0 / self.beat_frequency
+            }
+        };
+
+        if pulse {
             self.last_beat_time = self.animation_time;
             self.beat_phase = 0.0;
 
@@ -1849,20 +2943,24 @@ impl Effect for Heartbeat {
 
         self.beat_intensity = 0.4 + double_beat * (0.3 + total_energy * 0.3);
 
-        let base_size = 15.0 + total_energy * 25.0;
+        // Intensity widens the heart's swing, speeds up its rings and adds sparkles
+        let swing = intensity_curve(self.intensity, 0.3, 1.5);
+        let ring_speed = intensity_curve(self.intensity, 0.6, 1.5);
+        let sparkles = intensity_curve(self.intensity, 0.0, 2.0);
+
+        let base_size = 15.0 + total_energy * 25.0 * swing;
         self.heart_size = base_size * (0.8 + self.beat_intensity * 0.4);
 
         self.pulse_rings.retain_mut(|ring| {
-            ring.radius += 2.0 + total_energy * 3.0;
+            ring.radius += (2.0 + total_energy * 3.0) * ring_speed;
             ring.life -= 0.02;
             ring.intensity *= 0.98;
             ring.life > 0.0 && ring.radius < 100.0
         });
 
-        frame.fill(0);
+        canvas.clear();
 
-        let center_x = 64.0;
-        let center_y = 64.0;
+        let (center_x, center_y) = canvas.center();
 
         for ring in &self.pulse_rings {
             for angle_step in 0..64 {
@@ -1870,106 +2968,133 @@ impl Effect for Heartbeat {
                 let x = center_x + angle.cos() * ring.radius;
                 let y = center_y + angle.sin() * ring.radius;
 
-                if x >= 0.0 && x < 128.0 && y >= 0.0 && y < 128.0 {
-                    let px = x as usize;
-                    let py = y as usize;
-                    let idx = (py * 128 + px) * 3;
-
-                    if idx + 2 < frame.len() {
-                        let ring_intensity = ring.intensity * ring.life * 0.3;
-
-                        frame[idx] = ((ring.color.0 * ring_intensity * 255.0) as u8)
-                            .saturating_add(frame[idx]);
-                        frame[idx + 1] = ((ring.color.1 * ring_intensity * 255.0) as u8)
-                            .saturating_add(frame[idx + 1]);
-                        frame[idx + 2] = ((ring.color.2 * ring_intensity * 255.0) as u8)
-                            .saturating_add(frame[idx + 2]);
-                    }
+                if x >= 0.0 && y >= 0.0 {
+                    let ring_intensity = ring.intensity * ring.life * 0.3;
+                    canvas.put_pixel(
+                        x as i32,
+                        y as i32,
+                        scale(ring.color, ring_intensity),
+                        Blend::Add,
+                    );
                 }
             }
         }
 
-        for y in 0..128 {
-            for x in 0..128 {
+        for y in 0..canvas.height() as i32 {
+            for x in 0..canvas.width() as i32 {
                 let heart_intensity =
                     self.is_inside_heart(x as f32, y as f32, center_x, center_y, self.heart_size);
 
                 if heart_intensity > 0.0 {
-                    let idx = (y * 128 + x) * 3;
-                    if idx + 2 < frame.len() {
-                        let final_intensity = heart_intensity * self.beat_intensity;
+                    let final_intensity = heart_intensity * self.beat_intensity;
 
-                        let distance_from_center =
-                            ((x as f32 - center_x).powi(2) + (y as f32 - center_y).powi(2)).sqrt();
-                        let center_glow =
-                            (1.0 - (distance_from_center / self.heart_size).min(1.0)) * 0.3 + 0.7;
+                    let distance_from_center =
+                        ((x as f32 - center_x).powi(2) + (y as f32 - center_y).powi(2)).sqrt();
+                    let center_glow =
+                        (1.0 - (distance_from_center / self.heart_size).min(1.0)) * 0.3 + 0.7;
 
-                        let (r, g, b) = self.get_heart_color(final_intensity * center_glow);
+                    let color = self.get_heart_color(final_intensity * center_glow);
 
-                        let pulse_glow = 1.0 + (self.beat_phase * 12.56).sin() * total_energy * 0.3;
+                    let pulse_glow = 1.0 + (self.beat_phase * 12.56).sin() * total_energy * 0.3;
 
-                        frame[idx] = (r * pulse_glow * 255.0).min(255.0) as u8;
-                        frame[idx + 1] = (g * pulse_glow * 255.0).min(255.0) as u8;
-                        frame[idx + 2] = (b * pulse_glow * 255.0).min(255.0) as u8;
-                    }
+                    canvas.put_pixel(x, y, scale(color, pulse_glow), Blend::Replace);
                 }
             }
         }
 
         if self.beat_intensity > 0.8 && total_energy > 0.5 {
-            let sparkle_count = (total_energy * 15.0) as usize;
+            let sparkle_count = (total_energy * 15.0 * sparkles) as usize;
 
             for _ in 0..sparkle_count {
-                let angle = rand() * 6.28;
-                let distance = self.heart_size + 5.0 + rand() * 15.0;
+                let angle = self.rng.next_f32() * 6.28;
+                let distance = self.heart_size + 5.0 + self.rng.next_f32() * 15.0;
 
                 let sparkle_x = center_x + angle.cos() * distance;
                 let sparkle_y = center_y + angle.sin() * distance;
 
-                if sparkle_x >= 0.0 && sparkle_x < 128.0 && sparkle_y >= 0.0 && sparkle_y < 128.0 {
-                    let px = sparkle_x as usize;
-                    let py = sparkle_y as usize;
-                    let idx = (py * 128 + px) * 3;
-
-                    if idx + 2 < frame.len() {
-                        let sparkle_intensity = 0.5 + rand() * 0.5;
-                        let (r, g, b) = self.get_heart_color(sparkle_intensity);
-
-                        frame[idx] = ((r * 200.0) as u8).saturating_add(frame[idx]);
-                        frame[idx + 1] = ((g * 200.0) as u8).saturating_add(frame[idx + 1]);
-                        frame[idx + 2] = ((b * 200.0) as u8).saturating_add(frame[idx + 2]);
-                    }
+                if sparkle_x >= 0.0 && sparkle_y >= 0.0 {
+                    let sparkle_intensity = 0.5 + self.rng.next_f32() * 0.5;
+                    let color = self.get_heart_color(sparkle_intensity);
+                    canvas.put_pixel(
+                        sparkle_x as i32,
+                        sparkle_y as i32,
+                        scale(color, 200.0 / 255.0),
+                        Blend::Add,
+                    );
                 }
             }
         }
 
         if self.beat_intensity > 0.9 && total_energy > 0.7 {
             let flash_intensity = (self.beat_intensity - 0.9) * 10.0 * total_energy;
-            let (flash_r, flash_g, flash_b) = self.get_heart_color(1.0);
+            let flash_color = self.get_heart_color(1.0);
 
-            for y in (center_y as usize).saturating_sub(50)..((center_y as usize + 50).min(128)) {
-                for x in (center_x as usize).saturating_sub(50)..((center_x as usize + 50).min(128))
-                {
-                    let idx = (y * 128 + x) * 3;
-                    if idx + 2 < frame.len() {
-                        let distance =
-                            ((x as f32 - center_x).powi(2) + (y as f32 - center_y).powi(2)).sqrt();
-                        let flash_falloff = (1.0 - distance / 50.0).max(0.0);
-                        let flash_add = flash_intensity * flash_falloff * 50.0;
-
-                        frame[idx] = ((flash_r * flash_add) as u8).saturating_add(frame[idx]);
-                        frame[idx + 1] =
-                            ((flash_g * flash_add) as u8).saturating_add(frame[idx + 1]);
-                        frame[idx + 2] =
-                            ((flash_b * flash_add) as u8).saturating_add(frame[idx + 2]);
-                    }
+            for y in (center_y - 50.0) as i32..(center_y + 50.0) as i32 {
+                for x in (center_x - 50.0) as i32..(center_x + 50.0) as i32 {
+                    let distance =
+                        ((x as f32 - center_x).powi(2) + (y as f32 - center_y).powi(2)).sqrt();
+                    let flash_falloff = (1.0 - distance / 50.0).max(0.0);
+                    let flash_add = flash_intensity * flash_falloff * 50.0 / 255.0;
+
+                    canvas.put_pixel(x, y, scale(flash_color, flash_add), Blend::Add);
                 }
             }
         }
     }
+
+    fn base_sensitivity(&self) -> Option<f32> {
+        Some(HEARTBEAT_SENSITIVITY)
+    }
+
+    fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity;
+    }
+
+    fn set_rng(&mut self, rng: EffectRng) {
+        self.rng = rng;
+    }
+
+    fn set_beat(&mut self, beat: Option<BeatPhase>) {
+        self.beat = beat;
+    }
+
+    fn get_parameters(&self) -> Vec<EffectParameter> {
+        vec![EffectParameter::intensity(self.intensity)]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == INTENSITY {
+            self.intensity = value;
+        }
+    }
 }
 
-fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
     let c = v * s;
     let x = c * (1.0 - ((h * 6.0) % 2.0 - 1.0).abs());
     let m = v - c;
@@ -1986,18 +3111,52 @@ fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
     (r + m, g + m, b + m)
 }
 
-fn rand() -> f32 {
-    use std::cell::Cell;
-    thread_local! {
-        static SEED: Cell<u32> = Cell::new(0x12345678);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_loading_with_unloading() {
+        let mut engine = EffectEngine::new();
+        engine.set_max_loaded(Some(2));
+        engine.warm_up();
+        assert_eq!(engine.loaded_count(), 1);
+
+        for index in 0..4 {
+            engine.set_effect(index);
+            assert!(engine.loaded_count() <= 2);
+        }
+        assert_eq!(engine.current_effect_name(), "heartbeat");
+
+        // Both were unloaded on the way and come back on selection
+        engine.set_effect(0);
+        assert_eq!(engine.current_effect_name(), "spectrum_bars");
+        assert_eq!(
+            engine.set_effect_by_name("circular wave").unwrap(),
+            "circular_wave"
+        );
+        assert_eq!(engine.current_effect_name(), "circular_wave");
+        assert_eq!(engine.loaded_count(), 2);
+        assert_eq!(
+            engine.render(&[0.5; 64]).len(),
+            FRAME_WIDTH * FRAME_HEIGHT * 3
+        );
     }
 
-    SEED.with(|seed| {
-        let mut s = seed.get();
-        s ^= s << 13;
-        s ^= s >> 17;
-        s ^= s << 5;
-        seed.set(s);
-        (s as f32) / (u32::MAX as f32)
-    })
+    #[test]
+    fn test_render_reuses_the_warmed_polar_map() {
+        let (width, height) = (48, 20);
+        let mut effect = CircularWave::new();
+        effect.warm_up(width, height);
+        let warmed = effect.polar.clone();
+
+        let mut pixels = vec![0; width * height * 3];
+        effect.render(&[0.5; 64], &mut Canvas::new(&mut pixels, width, height));
+        assert!(Arc::ptr_eq(&effect.polar, &warmed));
+        // Another instance, as after an unload, gets the same table
+        assert!(Arc::ptr_eq(
+            &CircularWave::polar_map(width, height),
+            &warmed
+        ));
+    }
 }
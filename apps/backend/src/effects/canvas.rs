@@ -0,0 +1,151 @@
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blend {
+    Replace,
+    // Saturating sum, overlapping light brightens
+    Add,
+    // Brightest channel wins, overlaps never blow out to white
+    Max,
+}
+
+pub fn scale(color: (f32, f32, f32), factor: f32) -> (f32, f32, f32) {
+    (color.0 * factor, color.1 * factor, color.2 * factor)
+}
+
+// RGB frame an effect draws into, so effects place things relative to its size instead of
+// assuming a 128x128 wall
+pub struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(pixels: &'a mut [u8], width: usize, height: usize) -> Self {
+        assert_eq!(pixels.len(), width * height * 3, "canvas size mismatch");
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        (self.width as f32, self.height as f32)
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.width as f32 / 2.0, self.height as f32 / 2.0)
+    }
+
+    // Radius of the largest circle around the center that stays on the wall, so round
+    // shapes stay round and whole whatever the aspect ratio
+    pub fn safe_radius(&self) -> f32 {
+        self.width.min(self.height) as f32 / 2.0
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    // Pixel coordinates to 0..1 along each axis
+    pub fn normalize(&self, x: f32, y: f32) -> (f32, f32) {
+        (x / self.width as f32, y / self.height as f32)
+    }
+
+    pub fn denormalize(&self, nx: f32, ny: f32) -> (f32, f32) {
+        (nx * self.width as f32, ny * self.height as f32)
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    // Colors are 0..1 per channel; anything off the canvas is ignored
+    pub fn put_pixel(&mut self, x: i32, y: i32, color: (f32, f32, f32), blend: Blend) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let idx = (y as usize * self.width + x as usize) * 3;
+        let pixel = &mut self.pixels[idx..idx + 3];
+        for (value, channel) in pixel.iter_mut().zip([color.0, color.1, color.2]) {
+            let level = (channel * 255.0) as u8;
+            *value = match blend {
+                Blend::Replace => level,
+                Blend::Add => value.saturating_add(level),
+                Blend::Max => (*value).max(level),
+            };
+        }
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Option<[u8; 3]> {
+        if !self.contains(x, y) {
+            return None;
+        }
+        let idx = (y as usize * self.width + x as usize) * 3;
+        Some([self.pixels[idx], self.pixels[idx + 1], self.pixels[idx + 2]])
+    }
+
+    // Every pixel with its coordinates, for effects that shade the whole frame in parallel
+    pub fn par_pixels_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = (usize, usize, &mut [u8])> {
+        let width = self.width;
+        self.pixels
+            .par_chunks_mut(3)
+            .enumerate()
+            .map(move |(i, pixel)| (i % width, i / width, pixel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_modes_and_bounds() {
+        let mut frame = vec![0u8; 4 * 2 * 3];
+        let mut canvas = Canvas::new(&mut frame, 4, 2);
+        assert_eq!(canvas.center(), (2.0, 1.0));
+        assert_eq!(canvas.safe_radius(), 1.0);
+
+        canvas.put_pixel(1, 0, (1.0, 0.5, 0.0), Blend::Replace);
+        canvas.put_pixel(1, 0, (0.6, 0.2, 0.6), Blend::Add);
+        assert_eq!(canvas.pixel(1, 0), Some([255, 178, 153]));
+
+        canvas.put_pixel(1, 0, (0.2, 1.0, 0.2), Blend::Max);
+        assert_eq!(canvas.pixel(1, 0), Some([255, 255, 153]));
+
+        canvas.put_pixel(-1, 0, (1.0, 1.0, 1.0), Blend::Replace);
+        canvas.put_pixel(4, 1, (1.0, 1.0, 1.0), Blend::Replace);
+        canvas.put_pixel(0, 2, (1.0, 1.0, 1.0), Blend::Replace);
+        assert_eq!(canvas.pixel(4, 1), None);
+        assert_eq!(frame.iter().filter(|&&v| v > 0).count(), 3);
+    }
+
+    #[test]
+    fn test_parallel_pixels_know_their_coordinates() {
+        let mut frame = vec![0u8; 5 * 3 * 3];
+        let mut canvas = Canvas::new(&mut frame, 5, 3);
+        canvas.par_pixels_mut().for_each(|(x, y, pixel)| {
+            pixel[0] = x as u8;
+            pixel[1] = y as u8;
+        });
+        assert_eq!(canvas.pixel(4, 2), Some([4, 2, 0]));
+        assert_eq!(canvas.normalize(2.5, 1.5), (0.5, 0.5));
+        assert_eq!(canvas.denormalize(1.0, 0.0), (5.0, 0.0));
+    }
+}
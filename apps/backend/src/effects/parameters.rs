@@ -0,0 +1,146 @@
+use super::Effect;
+use crate::messages::Message;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Macro knob every effect declares, scaling its reactive parameters together
+pub const INTENSITY: &str = "intensity";
+// Where every effect plays as designed
+pub const DEFAULT_INTENSITY: f32 = 0.5;
+
+// A knob an effect declares for runtime tuning
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EffectParameter {
+    pub name: &'static str,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+impl EffectParameter {
+    pub fn new(name: &'static str, value: f32, min: f32, max: f32, default: f32) -> Self {
+        Self {
+            name,
+            value,
+            min,
+            max,
+            default,
+        }
+    }
+
+    pub fn intensity(value: f32) -> Self {
+        Self::new(INTENSITY, value, 0.0, 1.0, DEFAULT_INTENSITY)
+    }
+}
+
+// Multiplier on one parameter under the intensity macro: `low` fully tamed, 1 at the
+// default and `high` fully pushed, linear in between
+pub fn intensity_curve(intensity: f32, low: f32, high: f32) -> f32 {
+    if intensity < DEFAULT_INTENSITY {
+        low + (1.0 - low) * intensity / DEFAULT_INTENSITY
+    } else {
+        1.0 + (high - 1.0) * (intensity - DEFAULT_INTENSITY) / (1.0 - DEFAULT_INTENSITY)
+    }
+}
+
+// Checks `name` against the knobs the effect declares; returns the value clamped to its range
+pub fn set_parameter(effect: &mut dyn Effect, name: &str, value: f32) -> Result<f32> {
+    let parameters = effect.get_parameters();
+    let Some(parameter) = parameters.iter().find(|p| p.name == name) else {
+        let names: Vec<&str> = parameters.iter().map(|p| p.name).collect();
+        if names.is_empty() {
+            bail!("Unknown parameter: {}", name);
+        }
+        bail!(Message::new("unknown_parameter")
+            .arg("name", name)
+            .arg("options", names.join(", ")));
+    };
+    if !value.is_finite() {
+        bail!(Message::new("invalid_value").arg("name", name));
+    }
+    let value = value.clamp(parameter.min, parameter.max);
+    effect.set_parameter(name, value);
+    Ok(value)
+}
+
+// Saved values of knobs the effect no longer declares are skipped
+pub fn apply_overrides(effect: &mut dyn Effect, overrides: Option<&BTreeMap<String, f32>>) {
+    for (name, &value) in overrides.into_iter().flatten() {
+        let _ = set_parameter(effect, name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::EffectEngine;
+    use super::*;
+    use crate::config::EffectParameters;
+
+    fn knob(engine: &EffectEngine, name: &str) -> f32 {
+        let parameters = engine.parameters();
+        let knob = parameters["effect_parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|knob| knob["name"] == name)
+            .unwrap();
+        knob["value"].as_f64().unwrap() as f32
+    }
+
+    #[test]
+    fn test_knobs_are_clamped_and_survive_reloads() {
+        let mut engine = EffectEngine::new();
+        engine.set_effect_by_name("flames").unwrap();
+        assert_eq!(engine.set_effect_parameter("cooling", 1.0).unwrap(), 0.1);
+        assert!(engine.set_effect_parameter("density", 2.0).is_err());
+        assert!(engine.set_effect_parameter("cooling", f32::NAN).is_err());
+
+        // A new seed drops every loaded instance
+        engine.set_seed(7);
+        engine.warm_up();
+        assert_eq!(knob(&engine, "cooling"), 0.1);
+        assert_eq!(engine.effect_parameters()["flames"]["cooling"], 0.1);
+
+        let mut saved = EffectParameters::new();
+        saved.insert(
+            "flames".to_string(),
+            BTreeMap::from([
+                ("particle_count".to_string(), 5000.0),
+                ("gone".to_string(), 1.0),
+            ]),
+        );
+        engine.set_effect_parameters(saved);
+        assert_eq!(knob(&engine, "particle_count"), 2000.0);
+    }
+
+    #[test]
+    fn test_every_effect_has_an_intensity_macro() {
+        assert_eq!(intensity_curve(0.0, 0.2, 2.5), 0.2);
+        assert_eq!(intensity_curve(DEFAULT_INTENSITY, 0.2, 2.5), 1.0);
+        assert_eq!(intensity_curve(1.0, 0.2, 2.5), 2.5);
+
+        let mut engine = EffectEngine::new();
+        for name in engine.effect_names() {
+            engine.set_effect_by_name(name).unwrap();
+            engine.warm_up();
+            assert_eq!(knob(&engine, INTENSITY), DEFAULT_INTENSITY, "{}", name);
+            assert_eq!(engine.set_effect_parameter(INTENSITY, 2.0).unwrap(), 1.0);
+        }
+        assert_eq!(engine.effect_parameters()["heartbeat"][INTENSITY], 1.0);
+
+        // The same music lights more of the wall when pushed than when tamed
+        let lit = |intensity: f32| {
+            let mut engine = EffectEngine::new();
+            engine.set_effect_by_name("particle_system").unwrap();
+            engine.set_effect_parameter(INTENSITY, intensity).unwrap();
+            let mut frame = Vec::new();
+            for _ in 0..30 {
+                frame = engine.render(&[0.6; 64]);
+            }
+            frame.chunks(3).filter(|p| p.iter().any(|&c| c > 0)).count()
+        };
+        assert!(lit(1.0) > lit(0.0) * 2);
+    }
+}
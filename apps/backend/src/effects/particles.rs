@@ -0,0 +1,220 @@
+use super::canvas::{scale, Blend, Canvas};
+use crate::budget::{trim_oldest, ResourceUsage};
+
+pub struct Particle<T> {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    // The particle dies once this runs out
+    pub life: f32,
+    pub data: T,
+}
+
+// Per-frame forces shared by every particle of a pool
+#[derive(Debug, Clone, Copy)]
+pub struct Physics {
+    pub gravity: f32,
+    // Velocity multipliers applied every frame, 1.0 keeps the speed
+    pub drag_x: f32,
+    pub drag_y: f32,
+    pub fade: f32,
+    // How far off the canvas a particle may drift before it dies
+    pub margin: f32,
+    // Canvas size the margin is measured from
+    pub bounds: (f32, f32),
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            gravity: 0.0,
+            drag_x: 1.0,
+            drag_y: 1.0,
+            fade: 0.0,
+            margin: f32::INFINITY,
+            bounds: (0.0, 0.0),
+        }
+    }
+}
+
+impl<T> Particle<T> {
+    pub fn new(x: f32, y: f32, vx: f32, vy: f32, life: f32, data: T) -> Self {
+        Self {
+            x,
+            y,
+            vx,
+            vy,
+            life,
+            data,
+        }
+    }
+
+    // Integrates one frame and returns whether the particle is still alive
+    pub fn advance(&mut self, physics: &Physics) -> bool {
+        self.vy += physics.gravity;
+        self.vx *= physics.drag_x;
+        self.vy *= physics.drag_y;
+        self.x += self.vx;
+        self.y += self.vy;
+        self.life -= physics.fade;
+
+        self.life > 0.0 && self.on_screen(physics.bounds, physics.margin)
+    }
+
+    pub fn on_screen(&self, (width, height): (f32, f32), margin: f32) -> bool {
+        self.x >= -margin
+            && self.x < width + margin
+            && self.y >= -margin
+            && self.y < height + margin
+    }
+}
+
+// Particles in spawn order, oldest first, so budget trims drop the ones closest to dying
+pub struct ParticlePool<T> {
+    particles: Vec<Particle<T>>,
+    capacity: usize,
+}
+
+impl<T> ParticlePool<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            particles: Vec::new(),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.particles.len() >= self.capacity
+    }
+
+    // Refused once the pool holds `capacity` particles
+    pub fn spawn(&mut self, particle: Particle<T>) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.particles.push(particle);
+        true
+    }
+
+    pub fn step(&mut self, physics: &Physics) {
+        self.particles.retain_mut(|p| p.advance(physics));
+    }
+
+    // Custom per-particle update for effects with their own forces; false kills the particle
+    pub fn update(&mut self, f: impl FnMut(&mut Particle<T>) -> bool) {
+        self.particles.retain_mut(f);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle<T>> {
+        self.particles.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Particle<T>> {
+        self.particles.iter_mut()
+    }
+
+    pub fn trim(&mut self, max: usize) -> usize {
+        trim_oldest(&mut self.particles, max)
+    }
+
+    // Lowering the capacity drops the oldest particles over it
+    pub fn set_capacity(&mut self, capacity: usize) -> usize {
+        self.capacity = capacity;
+        self.trim(capacity)
+    }
+
+    pub fn usage(&self) -> ResourceUsage {
+        ResourceUsage::of(&self.particles)
+    }
+}
+
+// Full-strength center with a half-strength square around it, blended additively
+pub fn add_dot(
+    canvas: &mut Canvas,
+    x: i32,
+    y: i32,
+    radius: i32,
+    color: (f32, f32, f32),
+    intensity: f32,
+) {
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let factor = if dx == 0 && dy == 0 { 1.0 } else { 0.5 };
+            canvas.put_pixel(x + dx, y + dy, scale(color, intensity * factor), Blend::Add);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_respects_capacity_and_trims_oldest() {
+        let mut pool = ParticlePool::new(3);
+        for i in 0..5 {
+            pool.spawn(Particle::new(0.0, 0.0, 0.0, 0.0, 1.0, i));
+        }
+        assert_eq!(pool.len(), 3);
+        assert!(pool.is_full());
+
+        assert_eq!(pool.trim(1), 2);
+        assert_eq!(pool.iter().map(|p| p.data).collect::<Vec<_>>(), vec![2]);
+
+        assert_eq!(pool.set_capacity(2), 0);
+        assert!(pool.spawn(Particle::new(0.0, 0.0, 0.0, 0.0, 1.0, 5)));
+        assert!(!pool.spawn(Particle::new(0.0, 0.0, 0.0, 0.0, 1.0, 6)));
+        assert_eq!(pool.set_capacity(1), 1);
+        assert_eq!(pool.iter().map(|p| p.data).collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn test_physics_kills_faded_and_off_screen_particles() {
+        let mut pool = ParticlePool::new(10);
+        pool.spawn(Particle::new(64.0, 64.0, 0.0, 0.0, 0.15, "fading"));
+        pool.spawn(Particle::new(64.0, 125.0, 0.0, 0.0, 1.0, "falling"));
+        pool.spawn(Particle::new(64.0, 64.0, 1.0, 0.0, 1.0, "drifting"));
+
+        let physics = Physics {
+            gravity: 1.0,
+            drag_x: 0.5,
+            fade: 0.1,
+            margin: 5.0,
+            bounds: (128.0, 128.0),
+            ..Default::default()
+        };
+        for _ in 0..4 {
+            pool.step(&physics);
+        }
+
+        let survivors: Vec<_> = pool.iter().map(|p| p.data).collect();
+        assert_eq!(survivors, vec!["drifting"]);
+        let drifting = pool.iter().next().unwrap();
+        assert!((drifting.vx - 0.0625).abs() < 1e-6);
+        assert!((drifting.y - 74.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_dot_adds_up() {
+        let mut frame = vec![0u8; 8 * 8 * 3];
+        let mut canvas = Canvas::new(&mut frame, 8, 8);
+        add_dot(&mut canvas, 0, 0, 1, (1.0, 0.5, 0.0), 0.6);
+        add_dot(&mut canvas, 0, 0, 1, (1.0, 0.5, 0.0), 0.6);
+        assert_eq!(canvas.pixel(0, 0), Some([255, 152, 0]));
+        assert_eq!(canvas.pixel(1, 1), Some([152, 76, 0]));
+        assert_eq!(canvas.pixel(2, 2), Some([0, 0, 0]));
+    }
+}
@@ -0,0 +1,108 @@
+// Effects render once per frame at this nominal rate
+const FRAME_RATE: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeakDecay {
+    // Fixed drop per frame
+    Linear(f32),
+    // Falling speed grows by this much every frame, like a cap dropping under gravity
+    Gravity(f32),
+}
+
+// Peak markers per band: a new maximum is held for `hold_secs`, then falls with the decay
+// curve until it meets the level again
+pub struct PeakHold {
+    hold_frames: u32,
+    decay: PeakDecay,
+    peaks: Vec<f32>,
+    held: Vec<u32>,
+    speed: Vec<f32>,
+}
+
+impl PeakHold {
+    pub fn new(bands: usize, hold_secs: f32, decay: PeakDecay) -> Self {
+        Self {
+            hold_frames: (hold_secs.max(0.0) * FRAME_RATE) as u32,
+            decay,
+            peaks: vec![0.0; bands],
+            held: vec![0; bands],
+            speed: vec![0.0; bands],
+        }
+    }
+
+    pub fn update(&mut self, levels: &[f32]) {
+        for (band, &level) in levels.iter().enumerate().take(self.peaks.len()) {
+            if level >= self.peaks[band] {
+                self.peaks[band] = level;
+                self.held[band] = 0;
+                self.speed[band] = 0.0;
+                continue;
+            }
+
+            self.held[band] = self.held[band].saturating_add(1);
+            if self.held[band] <= self.hold_frames {
+                continue;
+            }
+
+            let fall = match self.decay {
+                PeakDecay::Linear(rate) => rate,
+                PeakDecay::Gravity(acceleration) => {
+                    self.speed[band] += acceleration;
+                    self.speed[band]
+                }
+            };
+            self.peaks[band] = (self.peaks[band] - fall).max(level);
+            if self.peaks[band] <= level {
+                self.held[band] = 0;
+                self.speed[band] = 0.0;
+            }
+        }
+    }
+
+    pub fn peak(&self, band: usize) -> f32 {
+        self.peaks.get(band).copied().unwrap_or(0.0)
+    }
+
+    // Time since the band's peak was last set, decay included
+    pub fn held_secs(&self, band: usize) -> f32 {
+        self.held
+            .get(band)
+            .map_or(0.0, |&frames| frames as f32 / FRAME_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gravity_decay_accelerates_down_to_level() {
+        let mut peaks = PeakHold::new(1, 0.0, PeakDecay::Gravity(0.02));
+        peaks.update(&[0.8]);
+        peaks.update(&[0.0]);
+        assert!((peaks.peak(0) - 0.78).abs() < 1e-6);
+        peaks.update(&[0.0]);
+        assert!((peaks.peak(0) - 0.74).abs() < 1e-6);
+
+        for _ in 0..20 {
+            peaks.update(&[0.3]);
+        }
+        assert_eq!(peaks.peak(0), 0.3);
+    }
+
+    #[test]
+    fn test_hold_then_linear_decay() {
+        let mut peaks = PeakHold::new(2, 1.0, PeakDecay::Linear(0.01));
+        peaks.update(&[0.5, 0.2]);
+        for _ in 0..60 {
+            peaks.update(&[0.1, 0.2]);
+        }
+        assert_eq!(peaks.peak(0), 0.5);
+
+        peaks.update(&[0.1, 0.2]);
+        assert!((peaks.peak(0) - 0.49).abs() < 1e-6);
+        assert!((peaks.held_secs(0) - 61.0 / 60.0).abs() < 1e-6);
+        assert_eq!(peaks.peak(1), 0.2);
+        assert_eq!(peaks.peak(2), 0.0);
+    }
+}
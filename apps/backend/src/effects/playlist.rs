@@ -0,0 +1,230 @@
+use crate::config::{PlaylistConfig, TransitionStyle};
+use std::time::{Duration, Instant};
+
+// A playlist entry with its effect resolved to an engine slot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaylistStep {
+    pub slot: usize,
+    // Zero keeps the entry until a drop moves it on
+    pub duration: Duration,
+    pub transition: TransitionStyle,
+    pub transition_time: Duration,
+}
+
+pub struct EffectPlaylist {
+    config: PlaylistConfig,
+    steps: Vec<PlaylistStep>,
+    // Entry playing and when it started; None while stopped
+    playing: Option<(usize, Instant)>,
+}
+
+impl Default for EffectPlaylist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EffectPlaylist {
+    pub fn new() -> Self {
+        Self {
+            config: PlaylistConfig::default(),
+            steps: Vec::new(),
+            playing: None,
+        }
+    }
+
+    // Replaces the entries; a new list starts from its first entry, so this stops the playlist
+    pub fn set(&mut self, config: PlaylistConfig, steps: Vec<PlaylistStep>) {
+        self.config = config;
+        self.steps = steps;
+        self.playing = None;
+    }
+
+    pub fn config(&self) -> &PlaylistConfig {
+        &self.config
+    }
+
+    // The first entry, None when there is nothing to play
+    pub fn start(&mut self, now: Instant) -> Option<PlaylistStep> {
+        let step = *self.steps.first()?;
+        self.playing = Some((0, now));
+        Some(step)
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = None;
+    }
+
+    // The entry to switch to when the one playing is over
+    pub fn tick(&mut self, now: Instant, drop: bool) -> Option<PlaylistStep> {
+        let (index, started) = self.playing?;
+        let step = self.steps[index];
+        let elapsed = now.duration_since(started);
+        let timed_out = !step.duration.is_zero() && elapsed >= step.duration;
+        // A drop during the entry's own transition would cut it short
+        let dropped = drop && self.config.advance_on_drop && elapsed >= step.transition_time;
+        if !timed_out && !dropped {
+            return None;
+        }
+
+        let next = (index + 1) % self.steps.len();
+        self.playing = Some((next, now));
+        Some(self.steps[next])
+    }
+
+    // Entry playing and the seconds left on it, None for an entry waiting for a drop
+    pub fn position(&self, now: Instant) -> Option<(usize, Option<f32>)> {
+        let (index, started) = self.playing?;
+        let duration = self.steps[index].duration;
+        let remaining = (!duration.is_zero()).then(|| {
+            duration
+                .saturating_sub(now.duration_since(started))
+                .as_secs_f32()
+        });
+        Some((index, remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::EffectEngine;
+    use super::*;
+    use crate::accent::AccentKind;
+    use crate::config::{ContentPolicyConfig, PlaylistEntry};
+
+    fn step(slot: usize, secs: u64) -> PlaylistStep {
+        PlaylistStep {
+            slot,
+            duration: Duration::from_secs(secs),
+            transition: TransitionStyle::Crossfade,
+            transition_time: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_entries_cycle_on_time_and_on_drops() {
+        let mut playlist = EffectPlaylist::new();
+        let config = PlaylistConfig {
+            advance_on_drop: true,
+            ..PlaylistConfig::default()
+        };
+        playlist.set(config, vec![step(4, 10), step(2, 0), step(7, 5)]);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(playlist.tick(at(0), true), None);
+        assert_eq!(playlist.start(at(0)).map(|s| s.slot), Some(4));
+        // Drops inside the transition are ignored
+        assert_eq!(playlist.tick(at(0), true), None);
+        assert_eq!(playlist.position(at(3)), Some((0, Some(7.0))));
+        assert_eq!(playlist.tick(at(10), false).map(|s| s.slot), Some(2));
+        // No duration: only a drop moves it on
+        assert_eq!(playlist.tick(at(500), false), None);
+        assert_eq!(playlist.position(at(500)), Some((1, None)));
+        assert_eq!(playlist.tick(at(500), true).map(|s| s.slot), Some(7));
+        assert_eq!(playlist.tick(at(505), false).map(|s| s.slot), Some(4));
+
+        playlist.stop();
+        assert_eq!(playlist.tick(at(600), true), None);
+        assert_eq!(playlist.position(at(600)), None);
+    }
+
+    #[test]
+    fn test_engine_resolves_and_plays_entries() {
+        let mut engine = EffectEngine::new();
+        let entry = |effect: &str| PlaylistEntry {
+            effect: effect.to_string(),
+            duration_secs: 30.0,
+            transition: TransitionStyle::FadeThroughBlack,
+            transition_secs: 2.0,
+        };
+
+        let unknown = PlaylistConfig {
+            entries: vec![entry("rain"), entry("fireworks")],
+            advance_on_drop: false,
+        };
+        assert!(engine.set_playlist(&unknown).is_err());
+        assert!(engine.start_playlist().is_err());
+
+        let config = PlaylistConfig {
+            entries: vec![entry("Star fall"), entry("rain")],
+            advance_on_drop: false,
+        };
+        engine.set_playlist(&config).unwrap();
+        engine.start_playlist().unwrap();
+        assert_eq!(engine.current_effect_name(), "starfall");
+        let status = engine.playlist_status();
+        assert_eq!(status["playing"], true);
+        assert_eq!(status["effect"], "starfall");
+
+        // The fade renders both sides without touching the frame size
+        let (width, height) = engine.frame_size();
+        assert_eq!(engine.render(&[0.5; 64]).len(), width * height * 3);
+        engine.stop_playlist();
+        assert_eq!(engine.playlist_status()["playing"], false);
+    }
+
+    #[test]
+    fn test_content_policy_keeps_blacklisted_content_off_the_wall() {
+        let mut engine = EffectEngine::new();
+        engine.set_effect_by_name("rain").unwrap();
+        engine.set_accent(Some(AccentKind::Flash));
+        let policy = ContentPolicyConfig {
+            enabled: true,
+            blacklist: vec![
+                "rain".to_string(),
+                "Heartbeat".to_string(),
+                "flash".to_string(),
+            ],
+            max_flash_hz: Some(3.0),
+        };
+        engine.set_content_policy(policy.clone());
+        assert_eq!(engine.current_effect_name(), "spectrum_bars");
+        assert_eq!(engine.accent_kind(), None);
+
+        assert!(engine.set_effect_by_name("rain").is_err());
+        engine.set_accent(Some(AccentKind::Flash));
+        assert_eq!(engine.accent_kind(), None);
+        engine.set_accent(Some(AccentKind::Shockwave));
+        assert_eq!(engine.accent_kind(), Some(AccentKind::Shockwave));
+
+        // Stepping goes around the blacklisted ones
+        engine.set_effect_by_name("particle_system").unwrap();
+        engine.next_effect();
+        assert_eq!(engine.current_effect_name(), "starfall");
+        engine.next_effect();
+        assert_eq!(engine.current_effect_name(), "flames");
+        engine.previous_effect();
+        engine.previous_effect();
+        assert_eq!(engine.current_effect_name(), "particle_system");
+
+        let list = engine.effect_list(None);
+        let effects = list["effects"].as_array().unwrap();
+        assert_eq!(effects.len(), 6);
+        assert!(!effects.iter().any(|effect| effect["name"] == "rain"));
+
+        let playlist = PlaylistConfig {
+            entries: vec![PlaylistEntry {
+                effect: "rain".to_string(),
+                duration_secs: 30.0,
+                transition: TransitionStyle::Cut,
+                transition_secs: 0.0,
+            }],
+            advance_on_drop: false,
+        };
+        assert!(engine.set_playlist(&playlist).is_err());
+
+        engine.set_content_policy(ContentPolicyConfig {
+            enabled: false,
+            ..policy
+        });
+        assert_eq!(engine.set_effect_by_name("rain").unwrap(), "rain");
+        assert_eq!(
+            engine.effect_list(None)["effects"]
+                .as_array()
+                .unwrap()
+                .len(),
+            8
+        );
+    }
+}
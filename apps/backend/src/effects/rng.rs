@@ -0,0 +1,64 @@
+// Xorshift generator owned by one effect, so its frames only depend on the seed and the
+// audio, not on which thread rendered them or what other effects drew before
+#[derive(Debug, Clone)]
+pub struct EffectRng {
+    state: u32,
+}
+
+impl Default for EffectRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl EffectRng {
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 spreads nearby seeds apart; xorshift must never start from zero
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self {
+            state: (z as u32 ^ (z >> 32) as u32).max(1),
+        }
+    }
+
+    // Seed for one effect, so effects sharing the show seed don't share a sequence
+    pub fn for_effect(seed: u64, name: &str) -> Self {
+        let hash = name.bytes().fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+        });
+        Self::new(seed ^ hash)
+    }
+
+    // Uniform in 0..=1
+    pub fn next_f32(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f32 / u32::MAX as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = EffectRng::new(42);
+        let mut b = EffectRng::new(42);
+        let mut other = EffectRng::new(43);
+
+        let first: Vec<f32> = (0..100).map(|_| a.next_f32()).collect();
+        let second: Vec<f32> = (0..100).map(|_| b.next_f32()).collect();
+        let third: Vec<f32> = (0..100).map(|_| other.next_f32()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+        assert!(first.iter().all(|v| (0.0..=1.0).contains(v)));
+
+        let mut rain = EffectRng::for_effect(42, "rain");
+        let mut flames = EffectRng::for_effect(42, "flames");
+        assert_ne!(rain.next_f32(), flames.next_f32());
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Serialize;
+
+// What an effect is good for, so UIs and automation can pick from a subset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectTag {
+    // Slow, calm looks for warm-up and breaks
+    Ambient,
+    // Follows the music closely
+    Reactive,
+    // Peak-time looks
+    Party,
+    // Not a show look (meters, test patterns)
+    Utility,
+}
+
+impl EffectTag {
+    pub const ALL: [EffectTag; 4] = [Self::Ambient, Self::Reactive, Self::Party, Self::Utility];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ambient => "ambient",
+            Self::Reactive => "reactive",
+            Self::Party => "party",
+            Self::Utility => "utility",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|tag| tag.name().eq_ignore_ascii_case(name))
+    }
+}
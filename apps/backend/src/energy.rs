@@ -0,0 +1,169 @@
+use crate::config::EnergySaverConfig;
+use crate::crash;
+use crate::AppState;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    Sleep,
+    Wake,
+}
+
+// Idle policy: without audio above the threshold and without operator moves for a while,
+// the output slows down and dims until the next sign of life
+pub struct EnergySaver {
+    config: EnergySaverConfig,
+    last_activity: Instant,
+    sleeping_since: Option<Instant>,
+    // (effect before sleeping, sleep effect): restored on wake unless someone picked another one
+    restore_effect: Option<(&'static str, &'static str)>,
+}
+
+impl EnergySaver {
+    pub fn new(config: EnergySaverConfig) -> Self {
+        Self {
+            config,
+            last_activity: Instant::now(),
+            sleeping_since: None,
+            restore_effect: None,
+        }
+    }
+
+    pub fn record_audio(&mut self, spectrum: &[f32]) {
+        if spectrum
+            .iter()
+            .any(|&level| level > self.config.audio_threshold)
+        {
+            self.record_activity();
+        }
+    }
+
+    pub fn record_activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn update(&mut self) -> Option<Transition> {
+        self.update_at(Instant::now())
+    }
+
+    fn update_at(&mut self, now: Instant) -> Option<Transition> {
+        let idle_for = Duration::from_secs_f32(self.config.idle_minutes.max(0.0) * 60.0);
+        let idle =
+            self.config.enabled && now.saturating_duration_since(self.last_activity) >= idle_for;
+
+        match (idle, self.sleeping_since) {
+            (true, None) => {
+                self.sleeping_since = Some(now);
+                Some(Transition::Sleep)
+            }
+            (false, Some(_)) => {
+                self.sleeping_since = None;
+                Some(Transition::Wake)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping_since.is_some()
+    }
+
+    // Output brightness multiplier
+    pub fn brightness(&self) -> f32 {
+        if self.is_sleeping() {
+            self.config.brightness.clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    // Minimum time between two output frames while sleeping
+    pub fn frame_interval(&self) -> Option<Duration> {
+        self.is_sleeping()
+            .then(|| Duration::from_secs_f64(1.0 / self.config.fps.max(1) as f64))
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        serde_json::json!({
+            "enabled": self.config.enabled,
+            "sleeping": self.is_sleeping(),
+            "idle_secs": self.last_activity.elapsed().as_secs(),
+            "sleeping_secs": self.sleeping_since.map(|since| since.elapsed().as_secs()),
+            "idle_minutes": self.config.idle_minutes,
+            "fps": self.config.fps,
+            "brightness": self.config.brightness,
+            "effect": self.config.effect,
+        })
+    }
+}
+
+// Swaps in the configured low-power effect on sleep and puts the show back on wake
+pub fn apply(state: &AppState, transition: Transition) {
+    let mut saver = state.energy_saver.lock();
+    let mut engine = state.effect_engine.lock();
+
+    match transition {
+        Transition::Sleep => {
+            crash::log("🌙 Energy saver: idle, output slowed down and dimmed");
+            let previous = engine.current_effect_name();
+            if let Some(effect) = saver.config.effect.as_deref() {
+                match engine.set_effect_by_name(effect) {
+                    Ok(sleep_effect) => saver.restore_effect = Some((previous, sleep_effect)),
+                    Err(e) => crash::log(format!("⚠️ Energy saver effect ignored: {}", e)),
+                }
+            }
+        }
+        Transition::Wake => {
+            crash::log("☀️ Energy saver: activity, output restored");
+            if let Some((previous, sleep_effect)) = saver.restore_effect.take() {
+                if engine.current_effect_name() == sleep_effect {
+                    let _ = engine.set_effect_by_name(previous);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saver(enabled: bool) -> EnergySaver {
+        EnergySaver::new(EnergySaverConfig {
+            enabled,
+            idle_minutes: 1.0,
+            ..EnergySaverConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_sleeps_when_idle_and_wakes_on_activity() {
+        let mut saver = saver(true);
+        let start = saver.last_activity;
+
+        saver.record_audio(&[0.01; 64]);
+        assert_eq!(saver.update_at(start + Duration::from_secs(30)), None);
+        assert_eq!(
+            saver.update_at(start + Duration::from_secs(61)),
+            Some(Transition::Sleep)
+        );
+        assert_eq!(saver.update_at(start + Duration::from_secs(90)), None);
+        assert_eq!(saver.brightness(), 0.3);
+        assert_eq!(saver.frame_interval(), Some(Duration::from_millis(50)));
+
+        let mut spectrum = [0.0; 64];
+        spectrum[2] = 0.4;
+        saver.record_audio(&spectrum);
+        assert_eq!(saver.update(), Some(Transition::Wake));
+        assert_eq!(saver.brightness(), 1.0);
+        assert_eq!(saver.frame_interval(), None);
+    }
+
+    #[test]
+    fn test_disabled_never_sleeps() {
+        let mut saver = saver(false);
+        let later = saver.last_activity + Duration::from_secs(3600);
+        assert_eq!(saver.update_at(later), None);
+        assert!(!saver.is_sleeping());
+    }
+}
@@ -3,11 +3,60 @@ use num_complex::Complex;
 use rustfft::FftPlanner;
 
 const FFT_SIZE: usize = 1024;
+const HOP_SIZE: usize = FFT_SIZE / 2;
+// Peak bin magnitude of a full-scale sine through the Hann window
+const FULL_SCALE: f32 = FFT_SIZE as f32 / 4.0;
 const SPECTRUM_SIZE: usize = 64;
 const NOISE_FLOOR: f32 = 0.001;
 const MIN_THRESHOLD: f32 = 0.05;
 
-pub fn compute_spectrum(audio: &[f32]) -> Vec<f32> {
+// Sample ring between capture and FFT: every HOP_SIZE samples yields a full, half-overlapping
+// window no matter how the driver chunks its callbacks
+pub struct SampleWindow {
+    samples: Vec<f32>,
+    window: Vec<f32>,
+    position: usize,
+    filled: usize,
+    since_hop: usize,
+}
+
+impl Default for SampleWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SampleWindow {
+    pub fn new() -> Self {
+        Self {
+            samples: vec![0.0; FFT_SIZE],
+            window: vec![0.0; FFT_SIZE],
+            position: 0,
+            filled: 0,
+            since_hop: 0,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[f32], mut on_window: impl FnMut(&[f32])) {
+        for &sample in chunk {
+            self.samples[self.position] = sample;
+            self.position = (self.position + 1) % FFT_SIZE;
+            self.filled = (self.filled + 1).min(FFT_SIZE);
+            self.since_hop += 1;
+
+            if self.filled == FFT_SIZE && self.since_hop >= HOP_SIZE {
+                self.since_hop = 0;
+                let (newest, oldest) = self.samples.split_at(self.position);
+                self.window[..oldest.len()].copy_from_slice(oldest);
+                self.window[oldest.len()..].copy_from_slice(newest);
+                on_window(&self.window);
+            }
+        }
+    }
+}
+
+// Mean magnitude per band relative to a full-scale sine, so 1.0 reads as 0 dBFS
+pub fn band_levels(audio: &[f32]) -> Vec<f32> {
     let mut planner = FftPlanner::new();
     let fft = planner.plan_fft_forward(FFT_SIZE);
 
@@ -28,7 +77,7 @@ pub fn compute_spectrum(audio: &[f32]) -> Vec<f32> {
 
     fft.process(&mut input);
 
-    let mut spectrum = vec![0.0; SPECTRUM_SIZE];
+    let mut levels = vec![0.0; SPECTRUM_SIZE];
 
     let useful_bins = FFT_SIZE / 4;
 
@@ -49,11 +98,22 @@ pub fn compute_spectrum(audio: &[f32]) -> Vec<f32> {
             }
 
             if count > 0 {
-                spectrum[i] = (sum / count as f32).sqrt() * 0.25;
+                levels[i] = sum / count as f32 / FULL_SCALE;
             }
         }
     }
 
+    levels
+}
+
+// Adaptive mapping where the loudest band sets the scale, so any input level fills the range
+pub fn normalize_linear(levels: &[f32]) -> Vec<f32> {
+    let mut spectrum: Vec<f32> = levels
+        .iter()
+        .map(|&level| (level * FULL_SCALE).sqrt() * 0.25)
+        .collect();
+    spectrum.resize(SPECTRUM_SIZE, 0.0);
+
     for i in 0..SPECTRUM_SIZE {
         let freq_factor = if i < 8 {
             1.5
@@ -114,3 +174,29 @@ pub fn compute_spectrum(audio: &[f32]) -> Vec<f32> {
 
     smoothed
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_independent_of_chunk_size() {
+        let samples: Vec<f32> = (0..4096).map(|i| i as f32).collect();
+
+        let collect = |chunk_size: usize| {
+            let mut window = SampleWindow::new();
+            let mut windows = Vec::new();
+            for chunk in samples.chunks(chunk_size) {
+                window.push(chunk, |w| windows.push(w.to_vec()));
+            }
+            windows
+        };
+
+        let small = collect(64);
+        let odd = collect(333);
+        assert_eq!(small.len(), (4096 - FFT_SIZE) / HOP_SIZE + 1);
+        assert_eq!(small, odd);
+        assert_eq!(small[1][0], HOP_SIZE as f32);
+        assert_eq!(small[1][FFT_SIZE - 1], (HOP_SIZE + FFT_SIZE - 1) as f32);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::config::{BandWeights, Config, SmoothingProfile};
+use crate::crash;
+use crate::AppState;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenrePreset {
+    Techno,
+    Rock,
+    HipHop,
+    LiveBand,
+}
+
+pub const GENRE_PRESETS: [GenrePreset; 4] = [
+    GenrePreset::Techno,
+    GenrePreset::Rock,
+    GenrePreset::HipHop,
+    GenrePreset::LiveBand,
+];
+
+impl GenrePreset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Techno => "techno",
+            Self::Rock => "rock",
+            Self::HipHop => "hip-hop",
+            Self::LiveBand => "live band",
+        }
+    }
+
+    // Case, dashes and underscores don't matter: "Hip_Hop" and "live-band" both match
+    pub fn from_name(name: &str) -> Option<Self> {
+        let wanted = name.trim().to_lowercase().replace(['-', '_'], " ");
+        GENRE_PRESETS
+            .into_iter()
+            .find(|preset| preset.name().replace('-', " ") == wanted)
+    }
+
+    pub fn band_weights(self) -> BandWeights {
+        let (bass, mid, high) = match self {
+            Self::Techno => (1.3, 0.9, 1.0),
+            Self::Rock => (1.0, 1.2, 1.1),
+            Self::HipHop => (1.4, 1.0, 0.8),
+            Self::LiveBand => (0.9, 1.2, 1.0),
+        };
+        BandWeights { bass, mid, high }
+    }
+
+    pub fn smoothing(self) -> SmoothingProfile {
+        match self {
+            Self::Techno | Self::Rock => SmoothingProfile::Punchy,
+            Self::HipHop => SmoothingProfile::Smooth,
+            Self::LiveBand => SmoothingProfile::Broadcast,
+        }
+    }
+
+    // None keeps the adaptive gain; a fixed dBFS window lets quiet passages stay quiet
+    pub fn db_window(self) -> Option<(f32, f32)> {
+        match self {
+            Self::Techno | Self::HipHop => None,
+            Self::Rock => Some((-50.0, -6.0)),
+            Self::LiveBand => Some((-55.0, -10.0)),
+        }
+    }
+}
+
+fn save<T: Serialize>(key: &str, value: &T) {
+    if let Err(e) = Config::save_section(key, value) {
+        crash::log(format!("⚠️ Failed to save {}: {}", key, e));
+    }
+}
+
+// Band weighting, gain mode and smoothing in one go, each saved like its own command would
+pub fn apply(state: &AppState, preset: GenrePreset) -> serde_json::Value {
+    let weights = preset.band_weights();
+    *state.band_weights.lock() = weights;
+
+    let smoothing = {
+        let mut smoother = state.smoothing.lock();
+        smoother.set_profile(preset.smoothing());
+        smoother.config().clone()
+    };
+
+    let decibels = {
+        let mut scale = state.decibels.lock();
+        let (min_db, max_db) = preset
+            .db_window()
+            .unwrap_or((scale.config().min_db, scale.config().max_db));
+        scale.set_window(preset.db_window().is_some(), min_db, max_db);
+        scale.config().clone()
+    };
+
+    save("band_weights", &weights);
+    save("smoothing", &smoothing);
+    save("decibels", &decibels);
+    crash::log(format!("🎛️ Genre preset: {}", preset.name()));
+
+    serde_json::json!({
+        "preset": preset.name(),
+        "band_weights": weights,
+        "smoothing": smoothing,
+        "decibels": decibels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_names_round_trip() {
+        for preset in GENRE_PRESETS {
+            assert_eq!(GenrePreset::from_name(preset.name()), Some(preset));
+        }
+        assert_eq!(
+            GenrePreset::from_name(" Hip_Hop "),
+            Some(GenrePreset::HipHop)
+        );
+        assert_eq!(
+            GenrePreset::from_name("live-band"),
+            Some(GenrePreset::LiveBand)
+        );
+        assert_eq!(GenrePreset::from_name("jazz"), None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::beat::BeatPhase;
+use crate::crash;
+use crate::messages;
+use crate::presets::Preset;
+use crate::show_report;
+use crate::udp::{PacketType, UdpCommand, UdpPacket};
+use crate::AppState;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_FADE_SECS: f32 = 2.0;
+const RUNNING_SERVER: &str = "127.0.0.1:8081";
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+// Past the fade, the time the old instance gets to write its show report and let go of the port
+const RELEASE_GRACE: Duration = Duration::from_secs(5);
+
+// What a new instance needs to carry the show on where the running one is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoverState {
+    pub look: Preset,
+    pub blackout: bool,
+    pub beat: Option<BeatPhase>,
+}
+
+impl HandoverState {
+    pub fn capture(state: &AppState) -> Self {
+        let engine = state.effect_engine.lock();
+        let output = state.output.lock();
+        Self {
+            look: Preset::capture("handover", &engine, &output),
+            blackout: output.blackout,
+            beat: engine.beat_phase(),
+        }
+    }
+
+    // Same look and bar as the old instance, brought up from black as it fades out
+    pub fn resume(&self, state: &AppState, fade_secs: f32) -> Result<()> {
+        let mut engine = state.effect_engine.lock();
+        let mut output = state.output.lock();
+        self.look.apply(&mut engine, &mut output)?;
+        if let Some(beat) = self.beat {
+            engine.resume_beat(beat);
+        }
+        output.set_blackout(self.blackout);
+        output.set_brightness(0.0);
+        output.fade_brightness(self.look.brightness, fade_secs);
+        Ok(())
+    }
+}
+
+// Old instance: fades to black, then exits through the usual shutdown so the show report is kept
+pub fn release(state: &AppState, fade_secs: f32) {
+    state.output.lock().fade_to_black(fade_secs);
+    crash::log(format!(
+        "🤝 Handing over to a new instance in {:.1} s",
+        fade_secs
+    ));
+    let fade = Duration::try_from_secs_f32(fade_secs).unwrap_or_default();
+    thread::spawn(move || {
+        thread::sleep(fade);
+        show_report::request_exit();
+    });
+}
+
+// New instance: asks the running one for its state and tells it to fade out
+pub fn request(fade_secs: f32) -> Result<HandoverState> {
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    socket.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    let command = UdpPacket::new(
+        PacketType::Command,
+        1,
+        UdpCommand::Handover(fade_secs).to_payload(),
+    );
+    socket.send_to(&command.to_bytes()?, RUNNING_SERVER)?;
+
+    let mut buf = [0u8; 4096];
+    loop {
+        let len = socket
+            .recv(&mut buf)
+            .map_err(|e| anyhow!("No running backend answered on {}: {}", RUNNING_SERVER, e))?;
+        let Ok(reply) = UdpPacket::from_bytes(&buf[..len]) else {
+            continue;
+        };
+        match reply.packet_type {
+            PacketType::Response => return Ok(serde_json::from_slice(&reply.payload)?),
+            PacketType::Nack => bail!(
+                "Handover refused: {}",
+                messages::parse_nack(&reply.payload).1
+            ),
+            _ => {}
+        }
+    }
+}
+
+// The old instance holds the server port until its fade is over
+pub fn wait_for_port(address: &str, fade_secs: f32) -> Result<()> {
+    let fade = Duration::try_from_secs_f32(fade_secs).unwrap_or_default();
+    let deadline = Instant::now() + fade + RELEASE_GRACE;
+    loop {
+        match UdpSocket::bind(address) {
+            Ok(_) => return Ok(()),
+            Err(e) if Instant::now() >= deadline => {
+                bail!("{} still in use after the handover: {}", address, e)
+            }
+            Err(_) => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_survives_the_wire() {
+        let state = HandoverState {
+            look: Preset {
+                name: "handover".to_string(),
+                effect: "starfall".to_string(),
+                color_mode: "ocean".to_string(),
+                custom_color: (0.2, 0.4, 0.6),
+                brightness: 0.7,
+                effect_parameters: Default::default(),
+            },
+            blackout: false,
+            beat: Some(BeatPhase {
+                bpm: 128.0,
+                phase: 0.25,
+                beat_in_bar: 3,
+            }),
+        };
+        let json = serde_json::to_vec(&state).unwrap();
+        let decoded: HandoverState = serde_json::from_slice(&json).unwrap();
+        assert_eq!(decoded.look, state.look);
+        assert_eq!(decoded.beat, state.beat);
+    }
+
+    #[test]
+    fn test_waits_for_the_old_instance_to_let_go() {
+        let old = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = old.local_addr().unwrap().to_string();
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            drop(old);
+        });
+
+        let start = Instant::now();
+        wait_for_port(&address, 0.0).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        release.join().unwrap();
+    }
+}
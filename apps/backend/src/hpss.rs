@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+// Spectrogram columns for the harmonic (time) median and bands for the percussive one
+const HISTORY_LEN: usize = 17;
+const BAND_SPAN: usize = 4;
+const SMOOTHING: f32 = 0.05;
+const SILENCE: f32 = 1e-4;
+
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(f32::total_cmp);
+    values[values.len() / 2]
+}
+
+// Median-filter harmonic/percussive split of the spectrogram: sustained lines (vocals,
+// pads, melody) survive a median across time, hits spread across bands survive one across
+// frequency. Tonalness is the harmonic share of the energy, 0 = pure percussion
+pub struct TonalEstimator {
+    history: VecDeque<Vec<f32>>,
+    tonalness: f32,
+    // Median scratch space, kept so estimating doesn't allocate every frame
+    column: Vec<f32>,
+    neighbors: Vec<f32>,
+}
+
+impl Default for TonalEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TonalEstimator {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            tonalness: 0.5,
+            column: Vec::with_capacity(HISTORY_LEN),
+            neighbors: Vec::with_capacity(2 * BAND_SPAN + 1),
+        }
+    }
+
+    pub fn tonalness(&self) -> f32 {
+        self.tonalness
+    }
+
+    pub fn process(&mut self, spectrum: &[f32]) -> f32 {
+        if self
+            .history
+            .front()
+            .is_some_and(|f| f.len() != spectrum.len())
+        {
+            self.history.clear();
+        }
+        // The oldest frame's buffer is recycled for the newest one
+        let mut frame = if self.history.len() == HISTORY_LEN {
+            self.history.pop_front().unwrap_or_default()
+        } else {
+            Vec::with_capacity(spectrum.len())
+        };
+        frame.clear();
+        frame.extend_from_slice(spectrum);
+        self.history.push_back(frame);
+
+        let (column, neighbors) = (&mut self.column, &mut self.neighbors);
+        let (mut harmonic, mut percussive) = (0.0, 0.0);
+
+        for band in 0..spectrum.len() {
+            column.clear();
+            column.extend(self.history.iter().map(|frame| frame[band]));
+            let h = median(column);
+
+            neighbors.clear();
+            let low = band.saturating_sub(BAND_SPAN);
+            let high = (band + BAND_SPAN + 1).min(spectrum.len());
+            neighbors.extend_from_slice(&spectrum[low..high]);
+            let p = median(neighbors);
+
+            harmonic += h * h;
+            percussive += p * p;
+        }
+
+        if harmonic + percussive > SILENCE {
+            let share = harmonic / (harmonic + percussive);
+            self.tonalness += (share - self.tonalness) * SMOOTHING;
+        }
+        self.tonalness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sustained_lines_are_tonal() {
+        let mut estimator = TonalEstimator::new();
+        let mut spectrum = vec![0.0; 64];
+        spectrum[10] = 0.8;
+        spectrum[20] = 0.6;
+        spectrum[21] = 0.5;
+
+        for _ in 0..200 {
+            estimator.process(&spectrum);
+        }
+        assert!(estimator.tonalness() > 0.9);
+    }
+
+    #[test]
+    fn test_broadband_hits_are_percussive() {
+        let mut estimator = TonalEstimator::new();
+        let hit = vec![0.8; 64];
+        let rest = vec![0.0; 64];
+
+        for frame in 0..200u32 {
+            estimator.process(if frame.is_multiple_of(4) { &hit } else { &rest });
+        }
+        assert!(estimator.tonalness() < 0.1);
+    }
+}
@@ -0,0 +1,156 @@
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+pub const CHAR_ADVANCE: usize = GLYPH_WIDTH + 1;
+const LINE_ADVANCE: usize = GLYPH_HEIGHT + 1;
+const MARGIN: usize = 1;
+const TEXT_LEVEL: u8 = 160;
+
+pub struct HudStats {
+    pub fps: f64,
+    pub effect: &'static str,
+    pub bpm: Option<f32>,
+    pub brightness: f32,
+}
+
+impl HudStats {
+    fn lines(&self) -> Vec<String> {
+        vec![
+            format!("FPS {:.0}", self.fps),
+            self.effect.to_uppercase(),
+            self.bpm
+                .map_or("BPM --".to_string(), |bpm| format!("BPM {:.0}", bpm)),
+            format!("BRI {:.0}%", self.brightness * 100.0),
+        ]
+    }
+}
+
+// Rows top to bottom, three bits each with the left column as the high bit
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+fn set_pixel(frame: &mut [u8], frame_width: usize, x: usize, y: usize, level: u8) {
+    if x >= frame_width {
+        return;
+    }
+    let idx = (y * frame_width + x) * 3;
+    if let Some(pixel) = frame.get_mut(idx..idx + 3) {
+        pixel.fill(level);
+    }
+}
+
+// Bottom-left block on a blacked-out background so it stays readable over any effect
+pub fn draw(frame: &mut [u8], frame_width: usize, frame_height: usize, stats: &HudStats) {
+    let lines = stats.lines();
+    let max_chars = frame_width.saturating_sub(2 * MARGIN) / CHAR_ADVANCE;
+    let width = lines
+        .iter()
+        .map(|line| line.chars().count().min(max_chars))
+        .max()
+        .unwrap_or(0)
+        * CHAR_ADVANCE
+        + MARGIN;
+    let height = lines.len() * LINE_ADVANCE + MARGIN;
+    let top = frame_height.saturating_sub(height);
+
+    for y in top..frame_height {
+        for x in 0..width.min(frame_width) {
+            set_pixel(frame, frame_width, x, y, 0);
+        }
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        let y0 = top + MARGIN + row * LINE_ADVANCE;
+        for (col, c) in line.chars().take(max_chars).enumerate() {
+            let x0 = MARGIN + col * CHAR_ADVANCE;
+            for (dy, bits) in glyph(c).iter().enumerate() {
+                for dx in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> dx) != 0 {
+                        set_pixel(frame, frame_width, x0 + dx, y0 + dy, TEXT_LEVEL);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_WIDTH: usize = 128;
+    const FRAME_HEIGHT: usize = 128;
+
+    #[test]
+    fn test_hud_drawn_in_bottom_left() {
+        let stats = HudStats {
+            fps: 60.0,
+            effect: "spectrum_bars",
+            bpm: None,
+            brightness: 1.0,
+        };
+        let mut frame = vec![255u8; FRAME_WIDTH * FRAME_HEIGHT * 3];
+        draw(&mut frame, FRAME_WIDTH, FRAME_HEIGHT, &stats);
+
+        let pixel = |x: usize, y: usize| frame[(y * FRAME_WIDTH + x) * 3];
+        let top = FRAME_HEIGHT - 4 * LINE_ADVANCE - MARGIN;
+
+        // "F" of the FPS line: full top row, then only the left column
+        assert_eq!(pixel(1, top + 1), TEXT_LEVEL);
+        assert_eq!(pixel(3, top + 1), TEXT_LEVEL);
+        assert_eq!(pixel(3, top + 2), 0);
+        assert_eq!(pixel(0, FRAME_HEIGHT - 1), 0);
+        assert_eq!(pixel(0, top - 1), 255);
+        assert_eq!(pixel(FRAME_WIDTH - 1, FRAME_HEIGHT - 1), 255);
+    }
+
+    #[test]
+    fn test_effect_names_have_glyphs() {
+        for c in "SPECTRUM_BARS CIRCULAR_WAVE APPLAUDIMETRE".chars() {
+            assert!(c == ' ' || glyph(c) != [0; GLYPH_HEIGHT], "{}", c);
+        }
+    }
+}
@@ -9,7 +9,14 @@ use std::time::{Duration, Instant};
 pub mod protocol;
 pub mod router;
 
+use crate::config::MatrixConfig;
 use protocol::{Entity, EntityRange};
+use serde::{Deserialize, Serialize};
+
+pub const QUARTER_BASES: [u16; 4] = [100, 5100, 10100, 15100];
+pub const BANDS_PER_QUARTER: u16 = 16;
+pub const BAND_STRIDE: u16 = 300;
+pub const STRIP_LENGTH: u16 = 128;
 
 pub struct IHubController {
     socket: UdpSocket,
@@ -173,78 +180,157 @@ impl IHubController {
     pub fn set_differential_updates(&mut self, enabled: bool) {
         self.use_differential_updates = enabled;
     }
+
+    pub fn configure_layout(&mut self, layout: &MatrixLayout) {
+        self.configure_entities(layout.entity_ranges());
+    }
+}
+
+// Entity numbering of the wall: each quarter starts at its base and every band of two
+// strips takes `band_stride` ids, of which only the first `band_entities()` are used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatrixLayout {
+    pub quarter_bases: Vec<u16>,
+    pub bands_per_quarter: u16,
+    pub band_stride: u16,
+    pub strip_length: u16,
+}
+
+impl Default for MatrixLayout {
+    fn default() -> Self {
+        Self {
+            quarter_bases: QUARTER_BASES.to_vec(),
+            bands_per_quarter: BANDS_PER_QUARTER,
+            band_stride: BAND_STRIDE,
+            strip_length: STRIP_LENGTH,
+        }
+    }
+}
+
+impl MatrixLayout {
+    // Numbering for a wall of any size: strips as long as its columns, bands spread wider
+    // when they no longer fit the stock stride
+    pub fn for_matrix(matrix: &MatrixConfig) -> Self {
+        let strip_length = matrix.height as u16;
+        Self {
+            bands_per_quarter: matrix.bands_per_controller() as u16,
+            band_stride: BAND_STRIDE.max(strip_length * 2 + 3),
+            strip_length,
+            ..Self::default()
+        }
+    }
+
+    // Whether each quarter's ids end before the next quarter's base
+    pub fn fits(&self) -> bool {
+        self.quarter_bases.windows(2).all(|bases| {
+            bases[0] as usize + self.bands_per_quarter as usize * self.band_stride as usize
+                <= bases[1] as usize
+        }) && self.quarter_bases.last().is_some_and(|&base| {
+            base as usize + self.bands_per_quarter as usize * self.band_stride as usize
+                <= u16::MAX as usize
+        })
+    }
+
+    // Leading marker, up strip, middle marker, down strip, closing marker
+    pub fn band_entities(&self) -> u16 {
+        self.strip_length * 2 + 3
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.quarter_bases.len() * self.bands_per_quarter as usize * self.band_entities() as usize
+    }
+
+    // Config ranges in id order, sextets numbered the way a full update packs them;
+    // bands whose ids touch are merged into one range
+    pub fn entity_ranges(&self) -> Vec<EntityRange> {
+        let mut bands: Vec<u16> = self
+            .quarter_bases
+            .iter()
+            .flat_map(|&base| {
+                (0..self.bands_per_quarter).map(move |band| base + band * self.band_stride)
+            })
+            .collect();
+        bands.sort_unstable();
+
+        let count = self.band_entities();
+        let mut ranges: Vec<EntityRange> = Vec::with_capacity(bands.len());
+        let mut sextet = 0u16;
+        for start in bands {
+            let end = start + count - 1;
+            match ranges.last_mut() {
+                Some(range) if range.entity_end + 1 == start => {
+                    range.entity_end = end;
+                    range.sextet_end = sextet + count - 1;
+                }
+                _ => ranges.push(EntityRange {
+                    sextet_start: sextet,
+                    entity_start: start,
+                    sextet_end: sextet + count - 1,
+                    entity_end: end,
+                }),
+            }
+            sextet += count;
+        }
+
+        ranges
+    }
 }
 
 pub fn frame_to_entities_optimized(
     frame: &[u8],
-    width: usize,
-    height: usize,
+    matrix: &MatrixConfig,
     output: &mut Vec<(u16, u8, u8, u8, u8)>,
 ) {
+    let layout = MatrixLayout::for_matrix(matrix);
+    let (width, height) = (matrix.width, matrix.height);
     output.clear();
-    output.reserve(64 * 259);
+    output.reserve(matrix.bands() * layout.band_entities() as usize);
 
-    const QUARTER_BASES: [u16; 4] = [100, 5100, 10100, 15100];
+    for (quarter, &base_entity) in layout.quarter_bases.iter().enumerate() {
+        for band_in_quarter in 0..layout.bands_per_quarter {
+            let band = quarter * layout.bands_per_quarter as usize + band_in_quarter as usize;
+            let entity_base = base_entity + band_in_quarter * layout.band_stride;
 
-    for quarter in 0..4 {
-        let base_entity = QUARTER_BASES[quarter];
-        let quarter_x_offset = quarter * 32;
-
-        for band in 0..16 {
-            let col_start = quarter_x_offset + band * 2;
-            let entity_base = base_entity + (band as u16) * 300;
-
-            if col_start + 1 >= width {
+            if band >= matrix.bands() {
                 continue;
             }
 
+            // Leading marker, then each strip followed by its own marker
             output.push((entity_base, 0, 0, 0, 0));
 
-            let col1 = col_start;
-            for i in 0..128 {
-                let y = height.saturating_sub(1 + i);
-                let pixel_idx = (y * width + col1) * 3;
-
-                if pixel_idx + 2 < frame.len() {
-                    unsafe {
-                        output.push((
-                            entity_base + 1 + (i as u16),
-                            *frame.get_unchecked(pixel_idx),
-                            *frame.get_unchecked(pixel_idx + 1),
-                            *frame.get_unchecked(pixel_idx + 2),
-                            0,
-                        ));
+            for strip in 0..2 {
+                let col = band * 2 + strip;
+                let first_entity = entity_base + 1 + strip as u16 * (layout.strip_length + 1);
+                for i in 0..height {
+                    let y = if matrix.strip_runs_up(strip) {
+                        height - 1 - i
+                    } else {
+                        i
+                    };
+                    let pixel_idx = (y * width + col) * 3;
+
+                    if pixel_idx + 2 < frame.len() {
+                        unsafe {
+                            output.push((
+                                first_entity + (i as u16),
+                                *frame.get_unchecked(pixel_idx),
+                                *frame.get_unchecked(pixel_idx + 1),
+                                *frame.get_unchecked(pixel_idx + 2),
+                                0,
+                            ));
+                        }
                     }
                 }
-            }
 
-            output.push((entity_base + 129, 0, 0, 0, 0));
-
-            let col2 = col_start + 1;
-            for i in 0..128 {
-                let pixel_idx = (i * width + col2) * 3;
-
-                if pixel_idx + 2 < frame.len() {
-                    unsafe {
-                        output.push((
-                            entity_base + 130 + (i as u16),
-                            *frame.get_unchecked(pixel_idx),
-                            *frame.get_unchecked(pixel_idx + 1),
-                            *frame.get_unchecked(pixel_idx + 2),
-                            0,
-                        ));
-                    }
-                }
+                output.push((first_entity + layout.strip_length, 0, 0, 0, 0));
             }
-
-            output.push((entity_base + 258, 0, 0, 0, 0));
         }
     }
 }
 
-pub fn frame_to_entities(frame: &[u8], width: usize, height: usize) -> Vec<(u16, u8, u8, u8, u8)> {
-    let mut entities = Vec::with_capacity(64 * 259);
-    frame_to_entities_optimized(frame, width, height, &mut entities);
+pub fn frame_to_entities(frame: &[u8], matrix: &MatrixConfig) -> Vec<(u16, u8, u8, u8, u8)> {
+    let mut entities = Vec::new();
+    frame_to_entities_optimized(frame, matrix, &mut entities);
     entities
 }
 
@@ -266,6 +352,132 @@ mod tests {
         assert_eq!(controller.dirty_entities[0], 2);
     }
 
+    const LIT: (u8, u8, u8) = (10, 20, 30);
+
+    fn lit_entities(x: usize, y: usize) -> Vec<u16> {
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        let idx = (y * 128 + x) * 3;
+        frame[idx..idx + 3].copy_from_slice(&[LIT.0, LIT.1, LIT.2]);
+
+        let mut output = Vec::new();
+        frame_to_entities_optimized(&frame, &MatrixConfig::default(), &mut output);
+        assert_eq!(output.len(), 64 * 259);
+
+        output
+            .iter()
+            .filter(|&&(_, r, g, b, w)| r > 0 || g > 0 || b > 0 || w > 0)
+            .map(|&(id, r, g, b, w)| {
+                assert_eq!((r, g, b, w), (LIT.0, LIT.1, LIT.2, 0));
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_entity_mapping_single_pixel() {
+        // Even columns run bottom to top after the band's leading marker
+        assert_eq!(lit_entities(0, 127), vec![101]);
+        assert_eq!(lit_entities(0, 0), vec![228]);
+        assert_eq!(lit_entities(70, 10), vec![11118]);
+
+        // Odd columns run top to bottom after the middle marker
+        assert_eq!(lit_entities(1, 0), vec![230]);
+        assert_eq!(lit_entities(1, 127), vec![357]);
+        assert_eq!(lit_entities(127, 5), vec![19735]);
+    }
+
+    #[test]
+    fn test_entity_mapping_is_one_to_one() {
+        // Each pixel carries its own coordinates so a single frame checks the whole wall
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        for y in 0..128 {
+            for x in 0..128 {
+                let idx = (y * 128 + x) * 3;
+                frame[idx..idx + 3].copy_from_slice(&[x as u8, y as u8, 1]);
+            }
+        }
+
+        let mut output = Vec::new();
+        frame_to_entities_optimized(&frame, &MatrixConfig::default(), &mut output);
+
+        let mut pixels = std::collections::HashSet::new();
+        let mut ids = std::collections::HashSet::new();
+        for &(id, x, y, b, _) in &output {
+            if b == 1 {
+                assert!(pixels.insert((x, y)));
+                assert!(ids.insert(id));
+            }
+        }
+        assert_eq!(pixels.len(), 128 * 128);
+    }
+
+    #[test]
+    fn test_layout_ranges_match_frame_entities() {
+        let layout = MatrixLayout::default();
+        let ranges = layout.entity_ranges();
+        assert_eq!(ranges.len(), 64);
+        assert_eq!((ranges[0].entity_start, ranges[0].entity_end), (100, 358));
+        assert_eq!(ranges[63].entity_end, 19858);
+
+        let mut output = Vec::new();
+        frame_to_entities_optimized(
+            &vec![0u8; 128 * 128 * 3],
+            &MatrixConfig::default(),
+            &mut output,
+        );
+        let mut ids: Vec<u16> = output.iter().map(|&(id, ..)| id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids.len(), layout.entity_count());
+
+        let expanded: Vec<(u16, u16)> = ranges
+            .iter()
+            .flat_map(|range| {
+                (range.entity_start..=range.entity_end).zip(range.sextet_start..=range.sextet_end)
+            })
+            .collect();
+        let expected: Vec<(u16, u16)> = ids.into_iter().zip(0..).collect();
+        assert_eq!(expanded, expected);
+
+        // A stride equal to the band size packs each quarter into a single range
+        let packed = MatrixLayout {
+            band_stride: 259,
+            ..MatrixLayout::default()
+        };
+        assert_eq!(packed.entity_ranges().len(), 4);
+    }
+
+    #[test]
+    fn test_entities_follow_matrix_config() {
+        let matrix = MatrixConfig {
+            width: 8,
+            height: 200,
+            serpentine: crate::config::Serpentine::DownFirst,
+            ..MatrixConfig::default()
+        };
+        let layout = MatrixLayout::for_matrix(&matrix);
+        assert_eq!((layout.bands_per_quarter, layout.band_stride), (1, 403));
+        assert!(layout.fits());
+
+        let mut frame = vec![0u8; matrix.frame_len()];
+        frame[..3].copy_from_slice(&[LIT.0, LIT.1, LIT.2]);
+        let entities = frame_to_entities(&frame, &matrix);
+        assert_eq!(entities.len(), 4 * layout.band_entities() as usize);
+        // The first strip now starts at the top row
+        let lit: Vec<u16> = entities
+            .iter()
+            .filter(|&&(_, r, ..)| r > 0)
+            .map(|&(id, ..)| id)
+            .collect();
+        assert_eq!(lit, vec![101]);
+
+        let huge = MatrixConfig {
+            width: 1024,
+            height: 1024,
+            ..MatrixConfig::default()
+        };
+        assert!(!MatrixLayout::for_matrix(&huge).fits());
+    }
+
     #[test]
     fn test_frame_conversion_performance() {
         let frame = vec![0u8; 128 * 128 * 3];
@@ -273,7 +485,7 @@ mod tests {
 
         let start = Instant::now();
         for _ in 0..100 {
-            frame_to_entities_optimized(&frame, 128, 128, &mut output);
+            frame_to_entities_optimized(&frame, &MatrixConfig::default(), &mut output);
         }
         let duration = start.elapsed();
 
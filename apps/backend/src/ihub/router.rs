@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::HashMap;
-use std::net::UdpSocket;
+use std::net::{SocketAddr, UdpSocket};
 
-use super::protocol::{Entity, UniverseConfig};
+use super::protocol::{Entity, EntityRange, UniverseConfig};
+use super::{frame_to_entities_optimized, IHubController, MatrixLayout};
+use crate::config::MatrixConfig;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ControllerConfig {
@@ -146,8 +148,8 @@ impl IHubRouter {
         Ok(())
     }
 
-    pub fn route_frame(&mut self, frame: &[u8], width: usize, height: usize) -> Result<()> {
-        let entities = super::frame_to_entities(frame, width, height);
+    pub fn route_frame(&mut self, frame: &[u8], matrix: &MatrixConfig) -> Result<()> {
+        let entities = super::frame_to_entities(frame, matrix);
 
         let entities: Vec<Entity> = entities
             .into_iter()
@@ -200,6 +202,183 @@ impl InstallationConfig {
     }
 }
 
+// One entry of the eHuB routing table: entities entity_start..=entity_end go to `target`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HubRoute {
+    pub entity_start: u16,
+    pub entity_end: u16,
+    pub target: String,
+    pub universe: u8,
+}
+
+impl HubRoute {
+    fn contains(&self, id: u16) -> bool {
+        (self.entity_start..=self.entity_end).contains(&id)
+    }
+}
+
+pub fn route_issues(routes: &[HubRoute]) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    for (index, route) in routes.iter().enumerate() {
+        if route.entity_start > route.entity_end {
+            issues.push(format!(
+                "hub route {}: entity_start {} is after entity_end {}",
+                index, route.entity_start, route.entity_end
+            ));
+        }
+        if route.target.parse::<SocketAddr>().is_err() {
+            issues.push(format!(
+                "hub route {}: invalid target {}",
+                index, route.target
+            ));
+        }
+        if let Some(other) = routes[..index].iter().position(|other| {
+            other.entity_start <= route.entity_end && route.entity_start <= other.entity_end
+        }) {
+            issues.push(format!("hub route {} overlaps route {}", index, other));
+        }
+    }
+
+    issues
+}
+
+// Contiguous runs of sorted ids, numbered in the order a full update packs their sextets
+fn entity_ranges(ids: &[u16]) -> Vec<EntityRange> {
+    let mut ranges: Vec<EntityRange> = Vec::new();
+
+    for (sextet, &id) in ids.iter().enumerate() {
+        let sextet = sextet as u16;
+        match ranges.last_mut() {
+            Some(range) if range.entity_end.checked_add(1) == Some(id) => {
+                range.entity_end = id;
+                range.sextet_end = sextet;
+            }
+            _ => ranges.push(EntityRange {
+                sextet_start: sextet,
+                entity_start: id,
+                sextet_end: sextet,
+                entity_end: id,
+            }),
+        }
+    }
+
+    ranges
+}
+
+struct HubTarget {
+    controller: IHubController,
+    batch: Vec<(u16, u8, u8, u8, u8)>,
+}
+
+// Splits the wall's entities across several eHuB receivers following the routing table.
+// Routes sharing a target and universe feed the same controller; unrouted entities are dropped.
+pub struct HubDistributor {
+    routes: Vec<HubRoute>,
+    route_targets: Vec<usize>,
+    targets: Vec<HubTarget>,
+    entities: Vec<(u16, u8, u8, u8, u8)>,
+    matrix: MatrixConfig,
+}
+
+impl HubDistributor {
+    pub fn new(routes: Vec<HubRoute>, matrix: MatrixConfig) -> Result<Self> {
+        let issues = route_issues(&routes);
+        if !issues.is_empty() {
+            return Err(anyhow!(issues.join("; ")));
+        }
+        if !MatrixLayout::for_matrix(&matrix).fits() {
+            return Err(anyhow!(
+                "A {}x{} matrix does not fit the entity numbering",
+                matrix.width,
+                matrix.height
+            ));
+        }
+
+        let mut keys: Vec<(&str, u8)> = Vec::new();
+        let mut route_targets = Vec::with_capacity(routes.len());
+        for route in &routes {
+            let key = (route.target.as_str(), route.universe);
+            let index = match keys.iter().position(|&existing| existing == key) {
+                Some(index) => index,
+                None => {
+                    keys.push(key);
+                    keys.len() - 1
+                }
+            };
+            route_targets.push(index);
+        }
+
+        let targets = keys
+            .iter()
+            .map(|&(target, universe)| {
+                Ok(HubTarget {
+                    controller: IHubController::new(target, universe)?,
+                    batch: Vec::with_capacity(matrix.width * matrix.height),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut distributor = Self {
+            routes,
+            route_targets,
+            targets,
+            entities: Vec::with_capacity(matrix.width * matrix.height + 512),
+            matrix,
+        };
+        distributor.configure();
+        Ok(distributor)
+    }
+
+    fn target_of(&self, id: u16) -> Option<usize> {
+        self.routes
+            .iter()
+            .position(|route| route.contains(id))
+            .map(|route| self.route_targets[route])
+    }
+
+    fn configure(&mut self) {
+        let blank = vec![0u8; self.matrix.frame_len()];
+        frame_to_entities_optimized(&blank, &self.matrix, &mut self.entities);
+
+        let mut ids = vec![Vec::new(); self.targets.len()];
+        for &(id, ..) in &self.entities {
+            if let Some(target) = self.target_of(id) {
+                ids[target].push(id);
+            }
+        }
+
+        for (target, mut ids) in self.targets.iter_mut().zip(ids) {
+            ids.sort_unstable();
+            ids.dedup();
+            target.controller.configure_entities(entity_ranges(&ids));
+        }
+    }
+
+    pub fn send_frame(&mut self, frame: &[u8]) {
+        frame_to_entities_optimized(frame, &self.matrix, &mut self.entities);
+
+        for target in &mut self.targets {
+            target.batch.clear();
+        }
+        for index in 0..self.entities.len() {
+            let entity = self.entities[index];
+            if let Some(target) = self.target_of(entity.0) {
+                self.targets[target].batch.push(entity);
+            }
+        }
+
+        for target in &mut self.targets {
+            target.controller.update_entities(&target.batch);
+            target.controller.tick();
+        }
+    }
+
+    pub fn target_count(&self) -> usize {
+        self.targets.len()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,4 +399,66 @@ mod tests {
         assert!(stats.entity_count > 0);
         assert_eq!(stats.controller_count, 1);
     }
+
+    fn route(entity_start: u16, entity_end: u16, target: &str, universe: u8) -> HubRoute {
+        HubRoute {
+            entity_start,
+            entity_end,
+            target: target.to_string(),
+            universe,
+        }
+    }
+
+    #[test]
+    fn test_route_issues() {
+        let routes = vec![
+            route(100, 9858, "127.0.0.1:8765", 0),
+            route(9000, 9999, "127.0.0.1:8765", 1),
+            route(12000, 11000, "hub.local", 0),
+        ];
+        let issues = route_issues(&routes);
+        assert_eq!(issues.len(), 3);
+        assert!(issues[0].contains("overlaps route 0"));
+        assert!(HubDistributor::new(routes, MatrixConfig::default()).is_err());
+
+        let ranges = entity_ranges(&[100, 101, 102, 400, 401]);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!((ranges[1].entity_start, ranges[1].sextet_start), (400, 3));
+        assert_eq!((ranges[1].entity_end, ranges[1].sextet_end), (401, 4));
+    }
+
+    #[test]
+    fn test_distributor_splits_entity_ranges() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(std::time::Duration::from_secs(1)))
+            .unwrap();
+        let target = receiver.local_addr().unwrap().to_string();
+
+        let mut distributor = HubDistributor::new(
+            vec![
+                route(100, 9858, &target, 0),
+                route(10100, 14858, &target, 1),
+                route(15100, 19858, &target, 1),
+            ],
+            MatrixConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(distributor.target_count(), 2);
+        distributor.send_frame(&vec![60u8; 128 * 128 * 3]);
+
+        // (message type, universe) -> range or entity count from the header
+        let mut received = HashMap::new();
+        let mut buf = vec![0u8; 65536];
+        for _ in 0..4 {
+            let len = receiver.recv(&mut buf).unwrap();
+            assert!(len > 10 && &buf[..4] == b"iHuB");
+            received.insert((buf[4], buf[5]), u16::from_le_bytes([buf[6], buf[7]]));
+        }
+
+        assert_eq!(received[&(1, 0)], 32);
+        assert_eq!(received[&(1, 1)], 32);
+        assert_eq!(received[&(2, 0)], 32 * 259);
+        assert_eq!(received[&(2, 1)], 32 * 259);
+    }
 }
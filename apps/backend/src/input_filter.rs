@@ -0,0 +1,223 @@
+use crate::audio::SAMPLE_RATE;
+use crate::config::{HighPassConfig, NoiseGateConfig};
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+// Decay of the peak follower driving the gate
+const ENVELOPE_MS: f32 = 10.0;
+
+fn ms_to_samples(ms: f32) -> f32 {
+    ms * SAMPLE_RATE as f32 / 1000.0
+}
+
+fn from_dbfs(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// Second-order Butterworth section (RBJ cookbook coefficients)
+#[derive(Debug, Clone, Default)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    fn high_pass(cutoff_hz: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / SAMPLE_RATE as f32;
+        let cos = w0.cos();
+        let alpha = w0.sin() * FRAC_1_SQRT_2;
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 + cos) / 2.0 / a0,
+            b1: -(1.0 + cos) / a0,
+            b2: (1.0 + cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            ..Self::default()
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+// High-pass then noise gate on the raw capture, so rumble and hiss between tracks never
+// reach the FFT. Only the analysis sees this signal: the gate opens instantly without
+// worrying about clicks
+pub struct InputFilter {
+    gate: NoiseGateConfig,
+    high_pass: HighPassConfig,
+    biquad: Biquad,
+    threshold: f32,
+    envelope_decay: f32,
+    hold_samples: usize,
+    release_step: f32,
+    envelope: f32,
+    hold_left: usize,
+    gain: f32,
+}
+
+impl InputFilter {
+    pub fn new(gate: NoiseGateConfig, high_pass: HighPassConfig) -> Self {
+        let mut filter = Self {
+            gate: NoiseGateConfig::default(),
+            high_pass: HighPassConfig::default(),
+            biquad: Biquad::default(),
+            threshold: 0.0,
+            envelope_decay: (-1.0 / ms_to_samples(ENVELOPE_MS)).exp(),
+            hold_samples: 0,
+            release_step: 1.0,
+            envelope: 0.0,
+            hold_left: 0,
+            gain: 1.0,
+        };
+        filter.set_gate(gate);
+        filter.set_high_pass(high_pass);
+        filter
+    }
+
+    pub fn gate(&self) -> &NoiseGateConfig {
+        &self.gate
+    }
+
+    pub fn high_pass(&self) -> &HighPassConfig {
+        &self.high_pass
+    }
+
+    pub fn set_gate(&mut self, gate: NoiseGateConfig) {
+        self.gate = gate.normalized();
+        self.threshold = from_dbfs(self.gate.threshold_db);
+        self.hold_samples = ms_to_samples(self.gate.hold_ms) as usize;
+        self.release_step = 1.0 / ms_to_samples(self.gate.release_ms).max(1.0);
+        if !self.gate.enabled {
+            self.gain = 1.0;
+        }
+    }
+
+    pub fn set_high_pass(&mut self, high_pass: HighPassConfig) {
+        self.high_pass = high_pass.normalized();
+        self.biquad = Biquad::high_pass(self.high_pass.cutoff_hz);
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.gate.enabled || self.gain > 0.0
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        samples
+            .iter()
+            .map(|&sample| {
+                let sample = if self.high_pass.enabled {
+                    self.biquad.process(sample)
+                } else {
+                    sample
+                };
+                if !self.gate.enabled {
+                    return sample;
+                }
+
+                self.envelope = sample.abs().max(self.envelope * self.envelope_decay);
+                if self.envelope >= self.threshold {
+                    self.hold_left = self.hold_samples;
+                    self.gain = 1.0;
+                } else if self.hold_left > 0 {
+                    self.hold_left -= 1;
+                } else {
+                    self.gain = (self.gain - self.release_step).max(0.0);
+                }
+                sample * self.gain
+            })
+            .collect()
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        serde_json::json!({
+            "noise_gate": self.gate,
+            "high_pass": self.high_pass,
+            "open": self.is_open(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+        (0..ms_to_samples(seconds * 1000.0) as usize)
+            .map(|i| amplitude * (2.0 * PI * freq * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |peak, &x| peak.max(x.abs()))
+    }
+
+    #[test]
+    fn test_high_pass_removes_rumble() {
+        let gate = NoiseGateConfig {
+            enabled: false,
+            ..NoiseGateConfig::default()
+        };
+        let high_pass = HighPassConfig {
+            enabled: true,
+            cutoff_hz: 40.0,
+        };
+
+        let mut filter = InputFilter::new(gate.clone(), high_pass.clone());
+        let rumble = filter.process(&sine(8.0, 0.5, 1.0));
+        assert!(peak(&rumble[24000..]) < 0.05 * 0.5);
+
+        let mut filter = InputFilter::new(gate, high_pass);
+        let kick = filter.process(&sine(1000.0, 0.5, 0.2));
+        assert!(peak(&kick[4800..]) > 0.95 * 0.5);
+    }
+
+    #[test]
+    fn test_gate_holds_then_releases() {
+        let mut filter = InputFilter::new(
+            NoiseGateConfig {
+                enabled: true,
+                threshold_db: -40.0,
+                hold_ms: 100.0,
+                release_ms: 50.0,
+            },
+            HighPassConfig {
+                enabled: false,
+                cutoff_hz: 30.0,
+            },
+        );
+
+        let loud = filter.process(&sine(440.0, 0.5, 0.1));
+        assert_eq!(peak(&loud), peak(&sine(440.0, 0.5, 0.1)));
+        assert!(filter.is_open());
+
+        // Hiss at -60 dBFS: passes during the hold, then fades to silence
+        let hiss = sine(5000.0, 0.001, 0.5);
+        let gated = filter.process(&hiss);
+        assert!(peak(&gated[..4000]) > 0.0009);
+        assert_eq!(peak(&gated[ms_to_samples(200.0) as usize..]), 0.0);
+        assert!(!filter.is_open());
+
+        filter.set_gate(NoiseGateConfig {
+            enabled: false,
+            ..filter.gate().clone()
+        });
+        assert_eq!(filter.process(&hiss), hiss);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::effects::COLOR_MODES;
+use crate::AppState;
+use anyhow::Result;
+use eframe::egui;
+use std::sync::Arc;
+use std::time::Duration;
+
+const REPAINT_INTERVAL: Duration = Duration::from_millis(16);
+
+struct KioskApp {
+    state: Arc<AppState>,
+    preview: Option<egui::TextureHandle>,
+}
+
+impl KioskApp {
+    fn new(state: Arc<AppState>) -> Self {
+        Self {
+            state,
+            preview: None,
+        }
+    }
+
+    fn update_preview(&mut self, ctx: &egui::Context) -> egui::TextureId {
+        let frame = self.state.led_frame.lock().pixels.clone();
+        let matrix = self.state.matrix;
+        let image = egui::ColorImage::from_rgb([matrix.width, matrix.height], &frame);
+
+        match &mut self.preview {
+            Some(texture) => {
+                texture.set(image, egui::TextureOptions::NEAREST);
+                texture.id()
+            }
+            None => {
+                let texture = ctx.load_texture("preview", image, egui::TextureOptions::NEAREST);
+                let id = texture.id();
+                self.preview = Some(texture);
+                id
+            }
+        }
+    }
+
+    fn controls(&self, ui: &mut egui::Ui) {
+        let (names, current, color_mode) = {
+            let engine = self.state.effect_engine.lock();
+            // Blacklisted effects aren't offered
+            let names: Vec<_> = engine
+                .effect_names()
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| engine.check_effect(*index).is_ok())
+                .collect();
+            (
+                names,
+                engine.current_effect(),
+                engine.color_mode().to_string(),
+            )
+        };
+
+        ui.heading("Effect");
+        for (index, name) in names {
+            if ui.selectable_label(index == current, name).clicked() {
+                self.state.effect_engine.lock().set_effect(index);
+            }
+        }
+
+        ui.separator();
+        ui.heading("Colors");
+        for mode in COLOR_MODES {
+            if ui.selectable_label(color_mode == *mode, *mode).clicked() {
+                self.state.effect_engine.lock().set_color_mode(mode);
+            }
+        }
+
+        ui.separator();
+        ui.heading("Output");
+        let mut output = self.state.output.lock();
+        let mut brightness = output.brightness;
+        if ui
+            .add(egui::Slider::new(&mut brightness, 0.0..=1.0).text("Brightness"))
+            .changed()
+        {
+            output.set_brightness(brightness);
+        }
+        let mut blackout = output.blackout;
+        if ui.checkbox(&mut blackout, "Blackout").changed() {
+            output.set_blackout(blackout);
+        }
+        ui.add_enabled(
+            !output.strobe_blocked,
+            egui::Checkbox::new(&mut output.strobe, "Strobe"),
+        );
+        drop(output);
+
+        ui.separator();
+        let fps = self.state.render_health.lock().average_fps();
+        ui.label(format!("Render: {:.1} FPS", fps));
+    }
+}
+
+impl eframe::App for KioskApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let texture = self.update_preview(ctx);
+
+        egui::SidePanel::right("controls")
+            .resizable(false)
+            .show(ctx, |ui| self.controls(ui));
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Largest size keeping the wall's aspect ratio
+            let (width, height) = (
+                self.state.matrix.width as f32,
+                self.state.matrix.height as f32,
+            );
+            let scale = (ui.available_width() / width).min(ui.available_height() / height);
+            ui.centered_and_justified(|ui| {
+                ui.image((texture, egui::vec2(width * scale, height * scale)));
+            });
+        });
+
+        ctx.request_repaint_after(REPAINT_INTERVAL);
+    }
+}
+
+pub fn run(state: Arc<AppState>) -> Result<()> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("DJ-4LED")
+            .with_inner_size([900.0, 640.0]),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "DJ-4LED",
+        options,
+        Box::new(move |_cc| Ok(Box::new(KioskApp::new(state)))),
+    )
+    .map_err(|e| anyhow::anyhow!("Kiosk window failed: {}", e))
+}
@@ -1,15 +1,512 @@
-use anyhow::Result;
+use crate::color_correction::{self, ColorCorrection};
+use crate::config::{
+    ColorBalance, ColorCorrectionConfig, ContentPolicyConfig, MatrixConfig, OutputProfile,
+    PacingConfig, StartupConfig, TrimConfig, MAX_KEEP_ALIVE_SECS, OUTPUT_CONTROLLERS,
+    PIXELS_PER_UNIVERSE, TRIM_STRIPS,
+};
+use crate::crash;
+use crate::diagnostics::{OutputAlert, OutputHealth};
+use crate::output_profile;
+use crate::pacing::Pacing;
+use crate::priority::SendContention;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
 use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const STROBE_PERIOD_MS: u128 = 100;
+const STROBE_FLASH_MS: u128 = 30;
+const MAX_FADE_SECS: f32 = 600.0;
+pub const MIN_COLOR_TEMP: f32 = 1800.0;
+pub const MAX_COLOR_TEMP: f32 = 10000.0;
+// The simulator takes each column as universes of 64 pixels
+pub const SIMULATOR_COLUMN_PIXELS: usize = 64;
+const SIMULATOR_ADDRESS: &str = "127.0.0.1:6454";
+const CONTROLLERS: usize = OUTPUT_CONTROLLERS;
+const IDENTIFY_DIM: f32 = 0.1;
+const IDENTIFY_RGB: [u8; 3] = [255, 255, 255];
+const ARTPOLL_REFRESH_OFFSET: usize = 226;
+const DMX_REFRESH_HZ: u32 = 44;
 
 pub enum LedMode {
     Simulator,
     Production,
 }
 
+#[derive(Debug, Clone)]
+pub struct BrightnessFade {
+    from: f32,
+    started: Option<Instant>,
+    duration: Duration,
+}
+
+impl BrightnessFade {
+    fn progress(&self) -> f32 {
+        match self.started {
+            Some(started) if !self.duration.is_zero() => {
+                (started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+            }
+            Some(_) => 1.0,
+            None => 0.0,
+        }
+    }
+
+    fn level(&self, target: f32) -> f32 {
+        let t = self.progress();
+        let eased = t * t * (3.0 - 2.0 * t);
+        self.from + (target - self.from) * eased
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HouseLight {
+    pub level: f32,
+    pub color_temp: f32,
+}
+
+impl HouseLight {
+    pub fn new(level: f32, color_temp: f32) -> Self {
+        Self {
+            level: level.clamp(0.0, 1.0),
+            color_temp: color_temp.clamp(MIN_COLOR_TEMP, MAX_COLOR_TEMP),
+        }
+    }
+
+    pub fn rgb(&self) -> [u8; 3] {
+        let (r, g, b) = kelvin_to_rgb(self.color_temp);
+        [
+            (r * self.level * 255.0) as u8,
+            (g * self.level * 255.0) as u8,
+            (b * self.level * 255.0) as u8,
+        ]
+    }
+}
+
+// Tanner Helland's blackbody approximation, normalized to 0..1
+pub fn kelvin_to_rgb(kelvin: f32) -> (f32, f32, f32) {
+    let temp = kelvin / 100.0;
+
+    let r = if temp <= 66.0 {
+        255.0
+    } else {
+        329.699 * (temp - 60.0).powf(-0.133_205)
+    };
+    let g = if temp <= 66.0 {
+        99.470_8 * temp.ln() - 161.119_57
+    } else {
+        288.122_16 * (temp - 60.0).powf(-0.075_514_85)
+    };
+    let b = if temp >= 66.0 {
+        255.0
+    } else if temp <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (temp - 10.0).ln() - 305.044_8
+    };
+
+    (
+        (r / 255.0).clamp(0.0, 1.0),
+        (g / 255.0).clamp(0.0, 1.0),
+        (b / 255.0).clamp(0.0, 1.0),
+    )
+}
+
+// Scales the whole frame down when its average channel level exceeds the limit
+pub fn limit_power(frame: &mut [u8], max_ratio: f32) {
+    if frame.is_empty() {
+        return;
+    }
+
+    let total: u64 = frame.iter().map(|&v| v as u64).sum();
+    let ratio = total as f32 / (frame.len() as f32 * 255.0);
+    if ratio <= max_ratio {
+        return;
+    }
+
+    let scale = max_ratio.max(0.0) / ratio;
+    for value in frame.iter_mut() {
+        *value = (*value as f32 * scale) as u8;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimRegion {
+    Quarter = 0,
+    Strip = 1,
+}
+
+impl TrimRegion {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Quarter),
+            1 => Some(Self::Strip),
+            _ => None,
+        }
+    }
+}
+
+// Trim strips are spread evenly over the columns (two each on a 128 wide wall) and each
+// quarter (one controller) takes sixteen of them
+fn column_trims(trim: &TrimConfig, width: usize) -> Vec<f32> {
+    let strips_per_quarter = TRIM_STRIPS / trim.quarters.len().max(1);
+    let mut trims = vec![1.0; width];
+    for (col, value) in trims.iter_mut().enumerate() {
+        let strip = col * TRIM_STRIPS / width;
+        let quarter = trim.quarters.get(strip / strips_per_quarter).copied();
+        *value = quarter.unwrap_or(1.0) * trim.strips.get(strip).copied().unwrap_or(1.0);
+    }
+    trims
+}
+
+pub fn apply_region_trim(frame: &mut [u8], width: usize, trim: &TrimConfig) {
+    let trims = column_trims(trim, width);
+    if trims.iter().all(|&t| t >= 1.0) {
+        return;
+    }
+
+    for (index, pixel) in frame.chunks_exact_mut(3).enumerate() {
+        let factor = trims[index % width];
+        for value in pixel.iter_mut() {
+            *value = (*value as f32 * factor) as u8;
+        }
+    }
+}
+
+// Wiring trace target: strips count per controller like the frame columns (two per band,
+// running as the matrix serpentine says) and pixels along the strip from its data input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Identify {
+    pub controller: usize,
+    pub strip: usize,
+    pub pixel: Option<usize>,
+}
+
+impl Identify {
+    pub fn new(
+        matrix: &MatrixConfig,
+        controller: usize,
+        strip: usize,
+        pixel: Option<usize>,
+    ) -> Result<Self> {
+        if controller >= CONTROLLERS {
+            anyhow::bail!("No controller {}", controller);
+        }
+        let identify = Self {
+            controller,
+            strip,
+            pixel: None,
+        };
+        if strip >= matrix.bands_per_controller() * 2 || identify.column(matrix) >= matrix.width {
+            anyhow::bail!("No strip {} on controller {}", strip, controller);
+        }
+        if let Some(pixel) = pixel {
+            if pixel >= identify.strip_len(matrix) {
+                anyhow::bail!("Strip {} has {} pixels", strip, identify.strip_len(matrix));
+            }
+        }
+        Ok(Self { pixel, ..identify })
+    }
+
+    fn column(&self, matrix: &MatrixConfig) -> usize {
+        self.controller * matrix.bands_per_controller() * 2 + self.strip
+    }
+
+    fn strip_len(&self, matrix: &MatrixConfig) -> usize {
+        matrix.leds_per_strip[self.strip % 2]
+    }
+
+    // (universe, led) pairs to light, numbered the way each mode sends them
+    fn leds(&self, mode: &LedMode, matrix: &MatrixConfig) -> Vec<(usize, usize)> {
+        let pixels = match self.pixel {
+            Some(pixel) => pixel..pixel + 1,
+            None => match mode {
+                LedMode::Simulator => 0..matrix.height,
+                LedMode::Production => 0..self.strip_len(matrix),
+            },
+        };
+        let col = self.column(matrix);
+
+        match mode {
+            LedMode::Simulator => {
+                let universes = matrix.height.div_ceil(SIMULATOR_COLUMN_PIXELS);
+                pixels
+                    .filter(|&pixel| pixel < matrix.height)
+                    .map(|pixel| {
+                        (
+                            col * universes + pixel / SIMULATOR_COLUMN_PIXELS,
+                            pixel % SIMULATOR_COLUMN_PIXELS,
+                        )
+                    })
+                    .collect()
+            }
+            LedMode::Production => {
+                let first = col / 2 * matrix.universes_per_band() * PIXELS_PER_UNIVERSE;
+                let offset = if self.strip.is_multiple_of(2) {
+                    0
+                } else {
+                    matrix.leds_per_strip[0]
+                };
+                pixels
+                    .map(|pixel| {
+                        let led = first + offset + pixel;
+                        (led / PIXELS_PER_UNIVERSE, led % PIXELS_PER_UNIVERSE)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputControl {
+    pub blackout: bool,
+    pub strobe: bool,
+    pub brightness: f32,
+    pub fade: Option<BrightnessFade>,
+    pub house_light: Option<HouseLight>,
+    pub trim: TrimConfig,
+    pub color_correction: ColorCorrectionConfig,
+    pub hud: bool,
+    pub identify: Option<Identify>,
+    pub profile: OutputProfile,
+    // From the venue content policy
+    pub strobe_blocked: bool,
+    pub min_flash_interval: Duration,
+}
+
+impl Default for OutputControl {
+    fn default() -> Self {
+        Self {
+            blackout: false,
+            strobe: false,
+            brightness: 1.0,
+            fade: None,
+            house_light: None,
+            trim: TrimConfig::default(),
+            color_correction: ColorCorrectionConfig::default(),
+            hud: false,
+            identify: None,
+            profile: OutputProfile::default(),
+            strobe_blocked: false,
+            min_flash_interval: Duration::ZERO,
+        }
+    }
+}
+
+impl OutputControl {
+    pub fn startup(config: &StartupConfig) -> Self {
+        let fade = (config.ramp_in_secs > 0.0).then(|| BrightnessFade {
+            from: 0.0,
+            started: (!config.start_blacked_out).then(Instant::now),
+            duration: Duration::from_secs_f32(config.ramp_in_secs),
+        });
+
+        Self {
+            blackout: config.start_blacked_out,
+            brightness: config.brightness.clamp(0.0, 1.0),
+            fade,
+            ..Self::default()
+        }
+    }
+
+    // A ramp held back by a startup blackout begins when the output is unlocked
+    pub fn set_blackout(&mut self, enabled: bool) {
+        self.blackout = enabled;
+        if !enabled {
+            if let Some(fade) = self.fade.as_mut().filter(|f| f.started.is_none()) {
+                fade.started = Some(Instant::now());
+            }
+        }
+    }
+
+    // A blacklisted strobe is switched off; the flash cap slows it down otherwise
+    pub fn set_content_policy(&mut self, policy: &ContentPolicyConfig) {
+        self.strobe_blocked = policy.blocks("strobe");
+        self.min_flash_interval = policy.min_flash_interval();
+        if self.strobe_blocked {
+            self.strobe = false;
+        }
+    }
+
+    pub fn set_brightness(&mut self, brightness: f32) {
+        self.brightness = brightness.clamp(0.0, 1.0);
+        self.fade = self.fade.take().filter(|f| f.started.is_none());
+    }
+
+    // Starts from the level currently on the output so a fade can interrupt another
+    pub fn fade_brightness(&mut self, target: f32, seconds: f32) {
+        let from = self.effective_brightness();
+        self.brightness = target.clamp(0.0, 1.0);
+        self.fade = Some(BrightnessFade {
+            from,
+            started: Some(Instant::now()),
+            duration: Duration::try_from_secs_f32(seconds.clamp(0.0, MAX_FADE_SECS))
+                .unwrap_or_default(),
+        });
+    }
+
+    pub fn fade_to_black(&mut self, seconds: f32) {
+        self.fade_brightness(0.0, seconds);
+    }
+
+    // A zero level leaves house light mode and hands the wall back to the effects
+    pub fn set_house_light(&mut self, level: f32, color_temp: f32) {
+        self.house_light = (level > 0.0).then(|| HouseLight::new(level, color_temp));
+    }
+
+    pub fn set_region_trim(&mut self, region: TrimRegion, index: usize, trim: f32) -> Result<()> {
+        let trims = match region {
+            TrimRegion::Quarter => &mut self.trim.quarters,
+            TrimRegion::Strip => &mut self.trim.strips,
+        };
+        let slot = trims
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("No {:?} region {}", region, index))?;
+        *slot = trim.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    // None sets every controller
+    pub fn set_gamma(&mut self, controller: Option<usize>, gamma: f32) -> Result<()> {
+        color_correction::check_gamma(gamma)?;
+        for balance in color_correction::balances(&mut self.color_correction, controller)? {
+            balance.gamma = gamma;
+        }
+        Ok(())
+    }
+
+    pub fn set_color_balance(
+        &mut self,
+        controller: Option<usize>,
+        white_point: f32,
+        gains: [f32; 3],
+    ) -> Result<()> {
+        color_correction::check_balance(white_point, gains)?;
+        for balance in color_correction::balances(&mut self.color_correction, controller)? {
+            balance.white_point = white_point;
+            balance.gains = gains;
+        }
+        Ok(())
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.as_ref().is_some_and(|f| f.progress() < 1.0)
+    }
+
+    pub fn effective_brightness(&self) -> f32 {
+        let brightness = self.brightness.max(0.0);
+        self.fade
+            .as_ref()
+            .map_or(brightness, |f| f.level(brightness))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetSet {
+    pub name: String,
+    pub controllers: Vec<String>,
+}
+
+// Named controller sets from config; the LED thread picks up the active one before each frame
+pub struct OutputTargets {
+    sets: BTreeMap<String, Vec<String>>,
+    active: Option<Arc<TargetSet>>,
+}
+
+impl OutputTargets {
+    pub fn new(sets: BTreeMap<String, Vec<String>>) -> Self {
+        Self { sets, active: None }
+    }
+
+    // An empty name goes back to the built-in controllers
+    pub fn switch(&mut self, name: &str) -> Result<()> {
+        if name.is_empty() {
+            self.active = None;
+            return Ok(());
+        }
+
+        let controllers = self
+            .sets
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown target set: {}", name))?;
+        if controllers.len() != CONTROLLERS
+            || controllers
+                .iter()
+                .any(|c| c.parse::<std::net::SocketAddr>().is_err())
+        {
+            return Err(anyhow!(
+                "Target set {} needs {} valid controller addresses",
+                name,
+                CONTROLLERS
+            ));
+        }
+
+        self.active = Some(Arc::new(TargetSet {
+            name: name.to_string(),
+            controllers: controllers.clone(),
+        }));
+        Ok(())
+    }
+
+    pub fn active(&self) -> Option<Arc<TargetSet>> {
+        self.active.clone()
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        serde_json::json!({
+            "active": self.active.as_ref().map(|set| &set.name),
+            "controllers": self.active.as_ref().map(|set| &set.controllers),
+            "sets": self.sets,
+        })
+    }
+}
+
+pub fn create_artpoll_packet() -> Vec<u8> {
+    vec![
+        b'A', b'r', b't', b'-', b'N', b'e', b't', 0, 0x00, 0x20, 0, 14, 0x00, 0x00,
+    ]
+}
+
+pub fn is_artpoll_reply(data: &[u8]) -> bool {
+    data.len() >= 10 && &data[..8] == b"Art-Net\0" && data[8] == 0x00 && data[9] == 0x21
+}
+
+// Art-Net 4 RefreshRate field; 1-44 all mean plain DMX512 timing, zero is left by older nodes
+pub fn artpoll_refresh_rate(data: &[u8]) -> Option<u32> {
+    if !is_artpoll_reply(data) || data.len() < ARTPOLL_REFRESH_OFFSET + 2 {
+        return None;
+    }
+    match u16::from_be_bytes([
+        data[ARTPOLL_REFRESH_OFFSET],
+        data[ARTPOLL_REFRESH_OFFSET + 1],
+    ]) {
+        0 => None,
+        rate => Some((rate as u32).max(DMX_REFRESH_HZ)),
+    }
+}
+
 pub struct LedController {
     socket: UdpSocket,
     controllers: Vec<String>,
+    default_controllers: Vec<String>,
+    target_set: Option<Arc<TargetSet>>,
     mode: LedMode,
+    strobe_clock: Instant,
+    power_limit: Option<f32>,
+    health: OutputHealth,
+    pending_alert: Option<OutputAlert>,
+    identify_leds: Vec<(usize, usize)>,
+    pacing: Pacing,
+    keep_alive: Option<Duration>,
+    sent_universes: HashMap<usize, (Vec<u8>, Instant)>,
+    contention: SendContention,
+    matrix: MatrixConfig,
+    color_balance: Vec<ColorBalance>,
+    color_correction: Vec<ColorCorrection>,
 }
 
 impl LedController {
@@ -37,29 +534,233 @@ impl LedController {
 
         Ok(Self {
             socket,
+            default_controllers: controllers.clone(),
+            target_set: None,
             controllers,
             mode,
+            strobe_clock: Instant::now(),
+            power_limit: None,
+            health: OutputHealth::new(),
+            pending_alert: None,
+            identify_leds: Vec::new(),
+            pacing: Pacing::new(PacingConfig::default()),
+            keep_alive: None,
+            sent_universes: HashMap::new(),
+            contention: SendContention::default(),
+            matrix: MatrixConfig::default(),
+            color_balance: Vec::new(),
+            color_correction: Vec::new(),
         })
     }
 
-    pub fn send_frame(&mut self, frame: &[u8]) {
+    pub fn restart_connections(&mut self) -> Result<()> {
+        self.socket = UdpSocket::bind("0.0.0.0:0")?;
+        self.sent_universes.clear();
+        Ok(())
+    }
+
+    pub fn take_alert(&mut self) -> Option<OutputAlert> {
+        self.pending_alert.take()
+    }
+
+    // (sent, failed) Art-Net packets since the last call
+    pub fn take_send_counts(&mut self) -> (u64, u64) {
+        self.health.take_counts()
+    }
+
+    // Backpressure met since the last call
+    pub fn take_contention(&mut self) -> SendContention {
+        std::mem::take(&mut self.contention)
+    }
+
+    fn send_artnet(&mut self, packet: &[u8], addr: &str) -> io::Result<usize> {
+        let started = Instant::now();
+        let result = self.socket.send_to(packet, addr);
+        self.contention.record(started.elapsed(), &result);
+        result
+    }
+
+    pub fn offline_controllers(&self) -> Vec<String> {
+        self.health.offline_controllers()
+    }
+
+    fn check_health(&mut self) {
+        if self.health.needs_recovery() {
+            match self.restart_connections() {
+                Ok(()) => crash::log("🔄 Art-Net socket rebuilt after repeated send failures"),
+                Err(e) => crash::log(format!("❌ Failed to rebuild Art-Net socket: {}", e)),
+            }
+            if let Some(alert) = self.health.record_recovery() {
+                self.pending_alert = Some(alert);
+            }
+        } else if self.health.record_healthy() {
+            crash::log("✅ Art-Net output recovered");
+        }
+    }
+
+    pub fn set_power_limit(&mut self, power_limit: Option<f32>) {
+        self.power_limit = power_limit.map(|limit| limit.clamp(0.0, 1.0));
+    }
+
+    // With a keep-alive, unchanged universes are only resent once it has elapsed
+    pub fn set_keep_alive(&mut self, secs: Option<f32>) {
+        self.keep_alive = secs
+            .filter(|secs| secs.is_finite() && *secs > 0.0)
+            .map(|secs| Duration::from_secs_f32(secs.min(MAX_KEEP_ALIVE_SECS)));
+        self.sent_universes.clear();
+    }
+
+    fn universe_changed(&self, universe: usize, dmx_data: &[u8]) -> bool {
+        let Some(keep_alive) = self.keep_alive else {
+            return true;
+        };
+        match self.sent_universes.get(&universe) {
+            Some((sent, at)) => sent.as_slice() != dmx_data || at.elapsed() >= keep_alive,
+            None => true,
+        }
+    }
+
+    fn record_universe(&mut self, universe: usize, dmx_data: &[u8]) {
+        if self.keep_alive.is_none() {
+            return;
+        }
+        match self.sent_universes.get_mut(&universe) {
+            Some((sent, at)) => {
+                sent.clear();
+                sent.extend_from_slice(dmx_data);
+                *at = Instant::now();
+            }
+            None => {
+                self.sent_universes
+                    .insert(universe, (dmx_data.to_vec(), Instant::now()));
+            }
+        }
+    }
+
+    // Swapped between two frames, so every universe of the next one goes to the new set
+    pub fn set_target_set(&mut self, target_set: Option<Arc<TargetSet>>) {
+        if self.target_set == target_set {
+            return;
+        }
+
+        self.controllers = match &target_set {
+            Some(set) => set.controllers.clone(),
+            None => self.default_controllers.clone(),
+        };
+        if matches!(self.mode, LedMode::Simulator) {
+            crash::log("⚠️ Target sets only apply to production output");
+        }
+        crash::log(format!(
+            "🔀 Output switched to {} ({})",
+            target_set
+                .as_ref()
+                .map_or("default controllers", |set| set.name.as_str()),
+            self.controllers.join(", ")
+        ));
+        self.target_set = target_set;
+        self.sent_universes.clear();
+    }
+
+    pub fn set_matrix(&mut self, matrix: MatrixConfig) {
+        self.matrix = matrix;
+        self.sent_universes.clear();
+    }
+
+    // Tables are only rebuilt when a balance has changed
+    pub fn set_color_correction(&mut self, config: &ColorCorrectionConfig) {
+        if self.color_balance == config.controllers {
+            return;
+        }
+        self.color_correction = config
+            .controllers
+            .iter()
+            .map(ColorCorrection::new)
+            .collect();
+        self.color_balance = config.controllers.clone();
+    }
+
+    pub fn set_pacing(&mut self, config: PacingConfig) {
+        self.pacing = Pacing::new(config);
+    }
+
+    pub fn apply_output_control(&mut self, frame: &mut [u8], control: &OutputControl) {
+        self.identify_leds.clear();
+        if control.blackout {
+            frame.fill(0);
+            return;
+        }
+
+        if let Some(house_light) = &control.house_light {
+            let rgb = house_light.rgb();
+            for pixel in frame.chunks_exact_mut(3) {
+                pixel.copy_from_slice(&rgb);
+            }
+        } else {
+            if control.strobe && !control.strobe_blocked {
+                let period = STROBE_PERIOD_MS.max(control.min_flash_interval.as_millis());
+                let phase = self.strobe_clock.elapsed().as_millis() % period;
+                frame.fill(if phase < STROBE_FLASH_MS { 255 } else { 0 });
+            } else {
+                self.strobe_clock = Instant::now();
+            }
+
+            let brightness = control.effective_brightness();
+            if brightness < 1.0 {
+                for value in frame.iter_mut() {
+                    *value = (*value as f32 * brightness) as u8;
+                }
+            }
+        }
+
+        output_profile::apply(control.profile, frame);
+        apply_region_trim(frame, self.matrix.width, &control.trim);
+
+        // The traced LEDs are lit at full level when the universes are built
+        if let Some(identify) = &control.identify {
+            for value in frame.iter_mut() {
+                *value = (*value as f32 * IDENTIFY_DIM) as u8;
+            }
+            self.identify_leds = identify.leds(&self.mode, &self.matrix);
+        }
+
+        if let Some(limit) = self.power_limit {
+            limit_power(frame, limit);
+        }
+    }
+
+    fn highlight_identified(&self, dmx_data: &mut [u8], universe: usize) {
+        for &(_, led) in self.identify_leds.iter().filter(|(u, _)| *u == universe) {
+            dmx_data[led * 3..led * 3 + 3].copy_from_slice(&IDENTIFY_RGB);
+        }
+    }
+
+    pub fn send_frame(&mut self, frame: &[u8]) -> usize {
         let avg_brightness =
             frame.iter().map(|&b| b as u32).sum::<u32>() as f32 / frame.len() as f32;
         if avg_brightness > 1.0 {
+            // Per frame: stdout only, it would flush everything else out of the crash log
             println!("📡 Sending frame - avg brightness: {:.1}", avg_brightness);
         }
 
-        match self.mode {
+        self.pacing.poll_nodes(&self.controllers);
+        let packets_sent = match self.mode {
             LedMode::Simulator => self.send_frame_simulator(frame),
             LedMode::Production => self.send_frame_production(frame),
-        }
+        };
+        self.check_health();
+        packets_sent
     }
 
-    fn send_frame_simulator(&mut self, frame: &[u8]) {
+    fn send_frame_simulator(&mut self, frame: &[u8]) -> usize {
         let mut universe = 0;
+        let mut packets_sent = 0;
+        if !self.pacing.frame_due(SIMULATOR_ADDRESS) {
+            return packets_sent;
+        }
 
-        for col in 0..128 {
-            for uni_in_col in 0..2 {
+        let universes_per_col = self.matrix.height.div_ceil(SIMULATOR_COLUMN_PIXELS);
+        for col in 0..self.matrix.width {
+            for uni_in_col in 0..universes_per_col {
                 let mut artnet_packet = vec![
                     b'A',
                     b'r',
@@ -81,78 +782,78 @@ impl LedController {
                     0x00,
                 ];
 
-                let mut dmx_data = vec![0u8; 512];
-
-                if col % 2 == 0 {
-                    let start_pixel = uni_in_col * 64;
-                    let end_pixel = ((uni_in_col + 1) * 64).min(128);
-
-                    for pixel in start_pixel..end_pixel {
-                        let led_idx = pixel - start_pixel;
-                        let y = 127 - pixel;
-                        let pixel_idx = (y * 128 + col) * 3;
-
-                        if pixel_idx + 2 < frame.len() && led_idx * 3 + 2 < 512 {
-                            dmx_data[led_idx * 3] = frame[pixel_idx];
-                            dmx_data[led_idx * 3 + 1] = frame[pixel_idx + 1];
-                            dmx_data[led_idx * 3 + 2] = frame[pixel_idx + 2];
-                        }
-                    }
-                } else {
-                    let start_pixel = uni_in_col * 64;
-                    let end_pixel = ((uni_in_col + 1) * 64).min(128);
-
-                    for pixel in start_pixel..end_pixel {
-                        let led_idx = pixel - start_pixel;
-                        let y = pixel;
-                        let pixel_idx = (y * 128 + col) * 3;
-
-                        if pixel_idx + 2 < frame.len() && led_idx * 3 + 2 < 512 {
-                            dmx_data[led_idx * 3] = frame[pixel_idx];
-                            dmx_data[led_idx * 3 + 1] = frame[pixel_idx + 1];
-                            dmx_data[led_idx * 3 + 2] = frame[pixel_idx + 2];
-                        }
-                    }
+                let mut dmx_data = Self::simulator_universe(&self.matrix, frame, col, uni_in_col);
+                self.highlight_identified(&mut dmx_data, universe);
+                if !self.universe_changed(universe, &dmx_data) {
+                    universe += 1;
+                    continue;
                 }
 
                 artnet_packet.extend_from_slice(&dmx_data);
 
-                let _ = self.socket.send_to(&artnet_packet, "127.0.0.1:6454");
+                let sent = self.send_artnet(&artnet_packet, SIMULATOR_ADDRESS).is_ok();
+                self.health.record_send(SIMULATOR_ADDRESS, sent);
+                if sent {
+                    self.record_universe(universe, &dmx_data);
+                    packets_sent += 1;
+                }
+                self.pacing.packet_sent(SIMULATOR_ADDRESS);
 
                 universe += 1;
             }
         }
+
+        packets_sent
     }
 
-    fn send_frame_production(&mut self, frame: &[u8]) {
+    fn send_frame_production(&mut self, frame: &[u8]) -> usize {
         let mut packets_sent = 0;
+        let bands_per_controller = self.matrix.bands_per_controller();
+        let universes_per_band = self.matrix.universes_per_band();
 
-        for quarter in 0..4 {
-            let controller_ip = &self.controllers[quarter];
-            let base_universe = quarter * 32;
-
-            for band_in_quarter in 0..16 {
-                let physical_band = quarter * 16 + band_in_quarter;
-
-                let col_up = physical_band * 2;
-                let col_down = physical_band * 2 + 1;
+        for quarter in 0..CONTROLLERS {
+            let controller_ip = self.controllers[quarter].clone();
+            let controller_ip = controller_ip.as_str();
+            if !self.pacing.frame_due(controller_ip) {
+                continue;
+            }
 
-                for uni_in_band in 0..2 {
-                    let universe = base_universe + band_in_quarter * 2 + uni_in_band;
+            let first_band = quarter * bands_per_controller;
+            let last_band = (first_band + bands_per_controller).min(self.matrix.bands());
+            for band in first_band..last_band {
+                for uni_in_band in 0..universes_per_band {
+                    let universe = band * universes_per_band + uni_in_band;
                     let mut artnet_packet = self.create_artnet_header(universe);
                     let mut dmx_data = vec![0u8; 512];
 
-                    self.map_pixels_to_band(&mut dmx_data, frame, col_up, col_down, uni_in_band);
+                    Self::map_pixels_to_band(&self.matrix, &mut dmx_data, frame, band, uni_in_band);
+                    if let Some(correction) = self.color_correction.get(quarter) {
+                        correction.apply(&mut dmx_data);
+                    }
+                    self.highlight_identified(&mut dmx_data, universe);
+                    if !self.universe_changed(universe, &dmx_data) {
+                        continue;
+                    }
 
                     artnet_packet.extend_from_slice(&dmx_data);
-                    if let Err(e) = self.socket.send_to(&artnet_packet, controller_ip) {
-                        println!("❌ Error sending to {}: {}", controller_ip, e);
-                    } else {
-                        packets_sent += 1;
+                    match self.send_artnet(&artnet_packet, controller_ip) {
+                        Ok(_) => {
+                            self.health.record_send(controller_ip, true);
+                            self.record_universe(universe, &dmx_data);
+                            packets_sent += 1;
+                        }
+                        Err(e) => {
+                            if self.health.record_send(controller_ip, false) == 1 {
+                                crash::log(format!("❌ Error sending to {}: {}", controller_ip, e));
+                            }
+                        }
                     }
+                    self.pacing.packet_sent(controller_ip);
                 }
             }
         }
+
+        packets_sent
     }
 
     fn create_artnet_header(&self, universe: usize) -> Vec<u8> {
@@ -178,67 +879,353 @@ impl LedController {
         ]
     }
 
-    fn map_pixels_to_band(
-        &self,
+    // One simulator universe: part of a column, columns alternating like the wall's strips
+    pub fn simulator_universe(
+        matrix: &MatrixConfig,
+        frame: &[u8],
+        col: usize,
+        uni_in_col: usize,
+    ) -> Vec<u8> {
+        let mut dmx_data = vec![0u8; 512];
+        let start_pixel = uni_in_col * SIMULATOR_COLUMN_PIXELS;
+        let end_pixel = (start_pixel + SIMULATOR_COLUMN_PIXELS).min(matrix.height);
+
+        for pixel in start_pixel..end_pixel {
+            let led_idx = pixel - start_pixel;
+            let y = if matrix.strip_runs_up(col % 2) {
+                matrix.height - 1 - pixel
+            } else {
+                pixel
+            };
+            let pixel_idx = (y * matrix.width + col) * 3;
+
+            if pixel_idx + 2 < frame.len() && led_idx * 3 + 2 < 512 {
+                dmx_data[led_idx * 3] = frame[pixel_idx];
+                dmx_data[led_idx * 3 + 1] = frame[pixel_idx + 1];
+                dmx_data[led_idx * 3 + 2] = frame[pixel_idx + 2];
+            }
+        }
+
+        dmx_data
+    }
+
+    // One universe of a band: its LEDs in wiring order, first strip then second, each
+    // universe taking the next PIXELS_PER_UNIVERSE of them
+    pub fn map_pixels_to_band(
+        matrix: &MatrixConfig,
         dmx_data: &mut [u8],
         frame: &[u8],
-        col_up: usize,
-        col_down: usize,
+        band: usize,
         uni_in_band: usize,
     ) {
-        if col_up >= 128 || col_down >= 128 {
+        if band >= matrix.bands() {
             return;
         }
 
-        if uni_in_band == 0 {
-            let mut dmx_offset = 0;
+        let first = uni_in_band * PIXELS_PER_UNIVERSE;
+        let last = (first + PIXELS_PER_UNIVERSE).min(matrix.band_leds());
+        for (dmx_led, band_led) in (first..last).enumerate() {
+            let (strip, led) = match band_led.checked_sub(matrix.leds_per_strip[0]) {
+                Some(led) => (1, led),
+                None => (0, band_led),
+            };
+            let (x, y) = matrix.strip_pixel(band, strip, led);
+            let pixel_idx = (y * matrix.width + x) * 3;
 
-            for led in 0..130 {
-                if dmx_offset + 2 < 510 {
-                    let y = 127 - (led * 128 / 130);
-                    let y = y.min(127);
-
-                    let pixel_idx = (y * 128 + col_up) * 3;
-                    if pixel_idx + 2 < frame.len() {
-                        dmx_data[dmx_offset] = frame[pixel_idx];
-                        dmx_data[dmx_offset + 1] = frame[pixel_idx + 1];
-                        dmx_data[dmx_offset + 2] = frame[pixel_idx + 2];
-                    }
-                    dmx_offset += 3;
-                }
+            if let (Some(pixel), Some(dmx)) = (
+                frame.get(pixel_idx..pixel_idx + 3),
+                dmx_data.get_mut(dmx_led * 3..dmx_led * 3 + 3),
+            ) {
+                dmx.copy_from_slice(pixel);
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Serpentine;
+
+    #[test]
+    fn test_startup_ramp_waits_for_unlock() {
+        let config = StartupConfig {
+            brightness: 0.5,
+            ramp_in_secs: 0.05,
+            start_blacked_out: true,
+            ..StartupConfig::default()
+        };
+        let mut control = OutputControl::startup(&config);
+        assert!(control.blackout);
+        assert_eq!(control.effective_brightness(), 0.0);
+
+        control.set_blackout(false);
+        assert!(control.effective_brightness() < 0.5);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(control.effective_brightness(), 0.5);
+
+        let control = OutputControl::startup(&StartupConfig::default());
+        assert!(!control.blackout);
+        assert_eq!(control.effective_brightness(), 1.0);
+    }
+
+    #[test]
+    fn test_fade_interpolates_from_current_level() {
+        let mut control = OutputControl::default();
+        control.fade_to_black(0.05);
+        assert!(control.is_fading());
+        assert!(control.effective_brightness() > 0.9);
 
-            for led in 0..40 {
-                if dmx_offset + 2 < 510 {
-                    let y = led * 128 / 129;
-                    let y = y.min(127);
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!control.is_fading());
+        assert_eq!(control.effective_brightness(), 0.0);
 
-                    let pixel_idx = (y * 128 + col_down) * 3;
-                    if pixel_idx + 2 < frame.len() {
-                        dmx_data[dmx_offset] = frame[pixel_idx];
-                        dmx_data[dmx_offset + 1] = frame[pixel_idx + 1];
-                        dmx_data[dmx_offset + 2] = frame[pixel_idx + 2];
+        control.fade_brightness(0.8, 10.0);
+        control.set_brightness(0.3);
+        assert!(control.fade.is_none());
+        assert_eq!(control.effective_brightness(), 0.3);
+    }
+
+    #[test]
+    fn test_house_light_respects_power_limit() {
+        let warm = HouseLight::new(1.0, 2700.0).rgb();
+        let cool = HouseLight::new(1.0, 6500.0).rgb();
+        assert_eq!(warm[0], 255);
+        assert!(warm[2] < cool[2]);
+
+        let mut control = OutputControl::default();
+        control.set_house_light(0.0, 4000.0);
+        assert!(control.house_light.is_none());
+
+        let mut frame = vec![255u8; 30];
+        limit_power(&mut frame, 0.5);
+        assert!(frame.iter().all(|&v| v == 127));
+
+        let mut frame = vec![50u8; 30];
+        limit_power(&mut frame, 0.5);
+        assert!(frame.iter().all(|&v| v == 50));
+    }
+
+    const LIT: [u8; 3] = [10, 20, 30];
+
+    fn lit_frame(x: usize, y: usize) -> Vec<u8> {
+        lit_matrix_frame(&MatrixConfig::default(), x, y)
+    }
+
+    fn lit_matrix_frame(matrix: &MatrixConfig, x: usize, y: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; matrix.frame_len()];
+        let idx = (y * matrix.width + x) * 3;
+        frame[idx..idx + 3].copy_from_slice(&LIT);
+        frame
+    }
+
+    // (universe, led) pairs lit by the production mapping, universes numbered as on the wire
+    fn production_leds(frame: &[u8]) -> Vec<(usize, usize)> {
+        matrix_leds(&MatrixConfig::default(), frame)
+    }
+
+    fn matrix_leds(matrix: &MatrixConfig, frame: &[u8]) -> Vec<(usize, usize)> {
+        let mut lit = Vec::new();
+        let universes = matrix.universes_per_band();
+
+        for band in 0..matrix.bands() {
+            for uni_in_band in 0..universes {
+                let mut dmx_data = vec![0u8; 512];
+                LedController::map_pixels_to_band(matrix, &mut dmx_data, frame, band, uni_in_band);
+
+                for (led, rgb) in dmx_data.chunks(3).enumerate() {
+                    if rgb.iter().any(|&c| c > 0) {
+                        assert_eq!(rgb, LIT);
+                        lit.push((band * universes + uni_in_band, led));
                     }
-                    dmx_offset += 3;
                 }
             }
-        } else {
-            let mut dmx_offset = 0;
-
-            for led in 40..129 {
-                if dmx_offset + 2 < 267 {
-                    let y = led * 128 / 129;
-                    let y = y.min(127);
-
-                    let pixel_idx = (y * 128 + col_down) * 3;
-                    if pixel_idx + 2 < frame.len() {
-                        dmx_data[dmx_offset] = frame[pixel_idx];
-                        dmx_data[dmx_offset + 1] = frame[pixel_idx + 1];
-                        dmx_data[dmx_offset + 2] = frame[pixel_idx + 2];
-                    }
-                    dmx_offset += 3;
-                }
+        }
+
+        lit
+    }
+
+    #[test]
+    fn test_production_mapping_single_pixel() {
+        // Up strip starts at the bottom and stretches 128 rows over 130 LEDs
+        assert_eq!(production_leds(&lit_frame(0, 127)), vec![(0, 0), (0, 1)]);
+        assert_eq!(production_leds(&lit_frame(0, 0)), vec![(0, 129)]);
+        assert_eq!(production_leds(&lit_frame(70, 10)), vec![(70, 119)]);
+
+        // Down strip continues on the same universe, then spills into the second one
+        assert_eq!(production_leds(&lit_frame(1, 0)), vec![(0, 130), (0, 131)]);
+        assert_eq!(production_leds(&lit_frame(1, 127)), vec![(1, 88)]);
+        assert_eq!(production_leds(&lit_frame(127, 64)), vec![(127, 25)]);
+    }
+
+    #[test]
+    fn test_production_mapping_reaches_every_row() {
+        for x in [0, 1, 64, 127] {
+            for y in 0..128 {
+                let leds = production_leds(&lit_frame(x, y));
+                assert!(!leds.is_empty(), "pixel ({}, {}) is not mapped", x, y);
+                assert!(leds.iter().all(|&(universe, _)| universe / 2 == x / 2));
             }
         }
     }
+
+    #[test]
+    fn test_mapping_follows_matrix_config() {
+        // 3 bands of 8 + 8 LEDs over 4 rows, first strip running down
+        let matrix = MatrixConfig {
+            width: 6,
+            height: 4,
+            serpentine: Serpentine::DownFirst,
+            leds_per_strip: [8, 8],
+        };
+        assert_eq!(
+            matrix_leds(&matrix, &lit_matrix_frame(&matrix, 0, 0)),
+            vec![(0, 0), (0, 1)]
+        );
+        assert_eq!(
+            matrix_leds(&matrix, &lit_matrix_frame(&matrix, 1, 3)),
+            vec![(0, 8), (0, 9)]
+        );
+        assert_eq!(
+            matrix_leds(&matrix, &lit_matrix_frame(&matrix, 5, 0)),
+            vec![(2, 14), (2, 15)]
+        );
+
+        // Taller strips need a third universe per band
+        let tall = MatrixConfig {
+            width: 2,
+            height: 200,
+            leds_per_strip: [200, 200],
+            ..MatrixConfig::default()
+        };
+        assert_eq!(tall.universes_per_band(), 3);
+        assert_eq!(
+            matrix_leds(&tall, &lit_matrix_frame(&tall, 1, 199)),
+            vec![(2, 59)]
+        );
+        assert_eq!(
+            LedController::simulator_universe(&tall, &lit_matrix_frame(&tall, 0, 0), 0, 3)[21..24],
+            LIT
+        );
+
+        let identify = Identify::new(&tall, 0, 1, Some(199)).unwrap();
+        assert_eq!(identify.leds(&LedMode::Production, &tall), vec![(2, 59)]);
+        assert!(Identify::new(&tall, 0, 2, None).is_err());
+    }
+
+    #[test]
+    fn test_keep_alive_suppresses_static_universes() {
+        let mut led = LedController::new().unwrap();
+        let dmx_data = vec![40u8; 384];
+        assert!(led.universe_changed(3, &dmx_data));
+        led.record_universe(3, &dmx_data);
+        assert!(led.universe_changed(3, &dmx_data));
+
+        led.set_keep_alive(Some(1.0));
+        led.record_universe(3, &dmx_data);
+        assert!(!led.universe_changed(3, &dmx_data));
+        assert!(led.universe_changed(3, &[41u8; 384]));
+        assert!(led.universe_changed(4, &dmx_data));
+
+        led.set_keep_alive(Some(0.01));
+        led.record_universe(3, &dmx_data);
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(led.universe_changed(3, &dmx_data));
+    }
+
+    #[test]
+    fn test_artpoll_refresh_rate() {
+        let mut reply = vec![0u8; 239];
+        reply[..10].copy_from_slice(b"Art-Net\0\x00\x21");
+        assert_eq!(artpoll_refresh_rate(&reply), None);
+
+        reply[226..228].copy_from_slice(&40u16.to_be_bytes());
+        assert_eq!(artpoll_refresh_rate(&reply), Some(44));
+        reply[226..228].copy_from_slice(&300u16.to_be_bytes());
+        assert_eq!(artpoll_refresh_rate(&reply), Some(300));
+        assert_eq!(artpoll_refresh_rate(&reply[..200]), None);
+        assert_eq!(artpoll_refresh_rate(&create_artpoll_packet()), None);
+    }
+
+    #[test]
+    fn test_identify_targets_physical_leds() {
+        let matrix = MatrixConfig::default();
+        assert!(Identify::new(&matrix, 4, 0, None).is_err());
+        assert!(Identify::new(&matrix, 0, 32, None).is_err());
+        assert!(Identify::new(&matrix, 0, 1, Some(129)).is_err());
+        assert!(Identify::new(&matrix, 0, 0, Some(129)).is_ok());
+
+        let identify = Identify::new(&matrix, 1, 3, Some(45)).unwrap();
+        assert_eq!(identify.leds(&LedMode::Production, &matrix), vec![(35, 5)]);
+        assert_eq!(identify.leds(&LedMode::Simulator, &matrix), vec![(70, 45)]);
+
+        let strip = Identify::new(&matrix, 0, 1, None)
+            .unwrap()
+            .leds(&LedMode::Production, &matrix);
+        assert_eq!(strip.len(), 129);
+        assert_eq!(strip[0], (0, 130));
+        assert_eq!(strip[128], (1, 88));
+
+        let mut led = LedController::new_with_mode(LedMode::Production).unwrap();
+        let control = OutputControl {
+            identify: Some(identify),
+            ..OutputControl::default()
+        };
+        let mut frame = vec![200u8; matrix.frame_len()];
+        led.apply_output_control(&mut frame, &control);
+        assert_eq!(frame[0], 20);
+
+        let mut dmx_data = vec![0u8; 512];
+        led.highlight_identified(&mut dmx_data, 35);
+        assert_eq!(&dmx_data[15..18], &IDENTIFY_RGB);
+        assert_eq!(dmx_data.iter().filter(|&&v| v > 0).count(), 3);
+    }
+
+    #[test]
+    fn test_region_trim_by_column() {
+        let mut control = OutputControl::default();
+        control
+            .set_region_trim(TrimRegion::Quarter, 1, 0.5)
+            .unwrap();
+        control.set_region_trim(TrimRegion::Strip, 0, 0.5).unwrap();
+        assert!(control.set_region_trim(TrimRegion::Strip, 64, 0.5).is_err());
+
+        let mut frame = vec![200u8; 128 * 2 * 3];
+        apply_region_trim(&mut frame, 128, &control.trim);
+
+        let pixel = |col: usize| frame[(128 + col) * 3];
+        assert_eq!(pixel(0), 100);
+        assert_eq!(pixel(1), 100);
+        assert_eq!(pixel(2), 200);
+        assert_eq!(pixel(32), 100);
+        assert_eq!(pixel(64), 200);
+    }
+
+    #[test]
+    fn test_target_set_switch() {
+        let rehearsal: Vec<String> = (0..CONTROLLERS)
+            .map(|i| format!("10.0.0.{}:6454", i + 10))
+            .collect();
+        let mut sets = BTreeMap::new();
+        sets.insert("rehearsal panel".to_string(), rehearsal.clone());
+        sets.insert("broken".to_string(), vec!["10.0.0.1:6454".to_string()]);
+        let mut targets = OutputTargets::new(sets);
+
+        assert!(targets.switch("missing").is_err());
+        assert!(targets.switch("broken").is_err());
+        targets.switch("rehearsal panel").unwrap();
+        assert_eq!(targets.status()["active"], "rehearsal panel");
+
+        let mut led = LedController::new_with_mode(LedMode::Production).unwrap();
+        led.set_keep_alive(Some(1.0));
+        led.record_universe(0, &[1, 2, 3]);
+        led.set_target_set(targets.active());
+        assert_eq!(led.controllers, rehearsal);
+        assert!(led.sent_universes.is_empty());
+
+        targets.switch("").unwrap();
+        led.set_target_set(targets.active());
+        assert_eq!(led.controllers[0], "192.168.1.45:6454");
+    }
 }
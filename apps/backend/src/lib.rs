@@ -0,0 +1,21 @@
+//! Audio-reactive effect engine behind the DJ-4LED wall, usable on its own: feed it a
+//! spectrum or raw audio and pull 128×128 RGB frames, no server or LED hardware needed.
+//!
+//! [`Renderer`] is the entry point. The lower-level modules are public for tools that
+//! need more control, e.g. [`ihub`] for mapping frames onto eHuB entities.
+
+pub mod accent;
+pub mod beat;
+pub mod budget;
+pub mod config;
+pub mod effects;
+pub mod fft;
+pub mod hpss;
+pub mod ihub;
+pub mod messages;
+pub mod mip;
+pub mod renderer;
+pub mod sections;
+pub mod smoothing;
+
+pub use renderer::{Renderer, RendererConfig, FRAME_HEIGHT, FRAME_WIDTH, SPECTRUM_BANDS};
@@ -2,88 +2,588 @@ use anyhow::Result;
 use parking_lot::Mutex;
 use std::sync::Arc;
 
+mod alerts;
 mod audio;
-mod effects;
-mod fft;
-mod ihub;
+mod av_delay;
+mod calibration;
+mod canvas_output;
+mod click;
+mod color_correction;
+mod commissioning;
+mod crash;
+mod crowd_noise;
+mod decibels;
+mod diagnostics;
+mod energy;
+mod genre;
+mod handover;
+mod hud;
+mod input_filter;
+#[cfg(feature = "kiosk")]
+mod kiosk;
 mod led;
+mod mapping_diff;
+mod midi;
+mod osc;
+mod output_profile;
+mod pacing;
+mod palettes;
+mod presets;
+mod priority;
+mod render;
+mod schedule;
+mod show_report;
+mod spectrogram;
+mod spill;
+mod splash;
+mod stats;
+mod threads;
+mod trace;
+mod triggers;
+#[cfg(feature = "tui")]
+mod tui;
 mod udp;
+mod watch;
+mod websocket;
+mod xy_pad;
 
-use audio::AudioCapture;
-use effects::EffectEngine;
-use led::{LedController, LedMode};
+use led_visualizer::{accent, beat, budget, config, effects, fft, ihub, messages, mip, smoothing};
+
+use alerts::{AlertEvent, AlertInputs};
+use audio::{ActiveDevice, AudioCapture};
+use av_delay::DelayLine;
+use beat::BeatEvent;
+use budget::{ResourceBudget, ResourceLimits};
+use calibration::RoomCalibration;
+use canvas_output::CanvasOutput;
+use click::{ClickCues, Cue};
+use commissioning::Commissioning;
+use config::{AvDelayConfig, BandWeights, Config, MatrixConfig};
+use crowd_noise::CrowdNoise;
+use decibels::SpectrumScale;
+use diagnostics::{OutputAlert, RenderHealth};
+use effects::{EffectEngine, COLOR_MODES};
+use energy::EnergySaver;
+use fft::SampleWindow;
+use ihub::router::HubDistributor;
+use input_filter::InputFilter;
+use led::{LedController, LedMode, OutputControl, OutputTargets};
+use midi::MidiRouter;
+use mip::FrameMips;
+use palettes::PaletteStore;
+use presets::PresetStore;
+use priority::OutputPriority;
+use render::{LedFrame, SpectrumRing};
+use schedule::{Schedule, Scheduler};
+use show_report::ShowRecorder;
+use smoothing::SpectrumSmoother;
+use spectrogram::Spectrogram;
+use spill::EdgeSpill;
+use splash::Splash;
+use stats::{StatsDb, StatsRecorder};
 use std::env;
-use udp::UdpServer;
+use std::time::Instant;
+use trace::FrameTracer;
+use udp::{PacketCapture, UdpCommand, UdpServer};
+use watch::ReloadEvent;
+use xy_pad::XyPad;
 
 pub struct AppState {
     pub spectrum: Mutex<Vec<f32>>,
     pub effect_engine: Mutex<EffectEngine>,
-    pub led_frame: Mutex<Vec<u8>>,
+    // Wall geometry, fixed for the whole run
+    pub matrix: MatrixConfig,
+    pub led_frame: Mutex<LedFrame>,
+    // Extra canvases' latest frames, in the engine's canvas order
+    pub canvas_frames: Mutex<Vec<Vec<u8>>>,
+    pub frame_mips: Mutex<Arc<FrameMips>>,
+    pub output: Mutex<OutputControl>,
+    pub output_targets: Mutex<OutputTargets>,
+    pub xy_pad: Mutex<XyPad>,
+    pub render_health: Mutex<RenderHealth>,
+    pub resource_budget: Mutex<ResourceBudget>,
+    pub frame_tracer: Mutex<FrameTracer>,
+    pub palettes: Mutex<PaletteStore>,
+    pub presets: Mutex<PresetStore>,
+    pub midi: Mutex<MidiRouter>,
+    pub output_alerts: Mutex<Vec<OutputAlert>>,
+    pub calibration: Mutex<RoomCalibration>,
+    pub smoothing: Mutex<SpectrumSmoother>,
+    pub band_weights: Mutex<BandWeights>,
+    pub decibels: Mutex<SpectrumScale>,
+    pub input_filter: Mutex<InputFilter>,
+    // Simulated applause added to the capture for applaudimetre rehearsals
+    pub crowd_noise: Mutex<CrowdNoise>,
+    pub commissioning: Mutex<Option<Commissioning>>,
+    pub spectrogram: Mutex<Spectrogram>,
+    pub show: Mutex<ShowRecorder>,
+    pub energy_saver: Mutex<EnergySaver>,
+    pub audio_device: Mutex<Option<ActiveDevice>>,
+    pub reload_events: Mutex<Vec<ReloadEvent>>,
+    pub av_delay: Mutex<AvDelayConfig>,
+    // Only set when the click track is enabled in config
+    pub click: Option<Arc<ClickCues>>,
+    pub stats: Mutex<StatsRecorder>,
+    pub stats_db: Mutex<Option<StatsDb>>,
+    pub alert_inputs: Mutex<AlertInputs>,
+    pub alert_events: Mutex<Vec<AlertEvent>>,
+    pub scheduler: Mutex<Scheduler>,
+    // Commands fired by the booth's physical buttons, OSC and MIDI controllers, run by the UDP server
+    pub triggered: Mutex<Vec<UdpCommand>>,
+    pub beat_events: Mutex<Vec<BeatEvent>>,
+    pub output_priority: Mutex<OutputPriority>,
 }
 
 fn main() -> Result<()> {
     let test_mode = env::args().any(|arg| arg == "--test");
     let production_mode = env::args().any(|arg| arg == "--production");
+    let kiosk_mode = env::args().any(|arg| arg == "--kiosk");
+    // --takeover, or --takeover=SECS for the crossfade length
+    let takeover = env::args().find_map(|arg| match arg.strip_prefix("--takeover") {
+        Some("") => Some(handover::DEFAULT_FADE_SECS),
+        Some(secs) => secs.strip_prefix('=')?.parse::<f32>().ok(),
+        None => None,
+    });
+    let tui_mode = env::args().any(|arg| arg == "--tui");
+    let watch_mode = env::args().any(|arg| arg == "--watch");
+    let accept_config_push = env::args().any(|arg| arg == "--accept-config-push");
+    let trace_every = env::args()
+        .find_map(|arg| arg.strip_prefix("--trace-every=")?.parse::<u64>().ok())
+        .unwrap_or(0);
+    let capture_path = env::args().find_map(|arg| arg.strip_prefix("--capture=").map(String::from));
+    let capture_frames = env::args().any(|arg| arg == "--capture-frames");
+
+    if let Some(path) =
+        env::args().find_map(|arg| arg.strip_prefix("--print-capture=").map(String::from))
+    {
+        return udp::print_capture(std::path::Path::new(&path));
+    }
+
+    if let Some(arg) = env::args().find(|arg| arg.starts_with("--mapping-diff")) {
+        let effect = arg.strip_prefix("--mapping-diff=");
+        mapping_diff::run(effect)?;
+        return Ok(());
+    }
+
+    let warm_up_start = std::time::Instant::now();
+    let config = Config::load();
+    messages::set_language(config.messages.language);
+    if let Err(e) = threads::init_pool(&config.performance) {
+        crash::log(format!("⚠️ Render thread pool not configured: {}", e));
+    }
+    // --matrix=WxH overrides the configured size for this run
+    let matrix = env::args()
+        .find_map(|arg| config.matrix.with_size(arg.strip_prefix("--matrix=")?))
+        .unwrap_or(config.matrix);
+    let matrix = if matrix.issues().is_empty() {
+        matrix
+    } else {
+        crash::log(format!(
+            "⚠️ Invalid LED matrix ({}), using the stock 128x128 wall",
+            matrix.issues().join(", ")
+        ));
+        MatrixConfig::default()
+    };
+    if matrix != MatrixConfig::default() {
+        crash::log(format!("📐 LED matrix {}x{}", matrix.width, matrix.height));
+    }
+    let mut effect_engine = EffectEngine::new();
+    effect_engine.set_matrix(&matrix);
+    effect_engine.set_seed(config.effects.seed);
+    effect_engine.set_max_loaded(config.performance.max_loaded_effects);
+    effect_engine.set_sensitivity_config(config.sensitivity.clone());
+    effect_engine.set_effect_parameters(config.effect_parameters.clone());
+    effect_engine.set_content_policy(config.content_policy.clone());
+    if let Err(e) = effect_engine.set_playlist(&config.playlist) {
+        crash::log(format!("⚠️ Playlist ignored: {}", e));
+    }
+    if let Some(effect) = &config.startup.effect {
+        if let Err(e) = effect_engine.set_effect_by_name(effect) {
+            crash::log(format!("⚠️ Startup effect ignored: {}", e));
+        }
+    }
+    if let Some(palette) = &config.startup.palette {
+        if COLOR_MODES.contains(&palette.as_str()) {
+            effect_engine.set_color_mode(palette);
+        } else {
+            crash::log(format!("⚠️ Unknown startup palette: {}", palette));
+        }
+    }
+    let mut canvases = Vec::new();
+    for canvas in &config.canvases {
+        match effect_engine.add_canvas(&canvas.name, canvas.width, canvas.height, &canvas.effect) {
+            Ok(effect) => {
+                crash::log(format!(
+                    "🖼️ Canvas {} ({}x{}) playing {} to {}",
+                    canvas.name, canvas.width, canvas.height, effect, canvas.target
+                ));
+                canvases.push(canvas.clone());
+            }
+            Err(e) => crash::log(format!("⚠️ Canvas {} disabled: {}", canvas.name, e)),
+        }
+    }
+    let mut schedules = Vec::new();
+    for rule in &config.schedule.rules {
+        match Schedule::parse(rule) {
+            Ok(schedule) => schedules.push(schedule),
+            Err(e) => crash::log(format!("⚠️ Schedule ignored: {}", e)),
+        }
+    }
+    effect_engine.warm_up();
+    let warm_up_ms = warm_up_start.elapsed().as_millis();
+
+    let click = config.click.enabled.then(|| {
+        let cues = Arc::new(ClickCues::new(&config.click));
+        click::start(config.click.clone(), cues.clone());
+        cues
+    });
 
     let state = Arc::new(AppState {
         spectrum: Mutex::new(vec![0.0; 64]),
-        effect_engine: Mutex::new(EffectEngine::new()),
-        led_frame: Mutex::new(vec![0; 128 * 128 * 3]),
+        effect_engine: Mutex::new(effect_engine),
+        matrix,
+        led_frame: Mutex::new(LedFrame {
+            id: 0,
+            pixels: vec![0; matrix.frame_len()],
+        }),
+        canvas_frames: Mutex::new(Vec::new()),
+        frame_mips: Mutex::new(Arc::new(FrameMips::default())),
+        output: Mutex::new(OutputControl {
+            trim: config.trim.clone().normalized(),
+            color_correction: config.color_correction.clone().normalized(),
+            profile: config.output_profile.profile,
+            ..OutputControl::startup(&config.startup)
+        }),
+        output_targets: Mutex::new(OutputTargets::new(config.led.target_sets.clone())),
+        xy_pad: Mutex::new(XyPad::new()),
+        render_health: Mutex::new(RenderHealth::new()),
+        resource_budget: Mutex::new(ResourceBudget::new(ResourceLimits::default())),
+        frame_tracer: Mutex::new(FrameTracer::new()),
+        palettes: Mutex::new(PaletteStore::load()),
+        presets: Mutex::new(PresetStore::load()),
+        midi: Mutex::new(MidiRouter::load()),
+        output_alerts: Mutex::new(Vec::new()),
+        calibration: Mutex::new(RoomCalibration::new(config.spectrum.normalization.clone())),
+        smoothing: Mutex::new(SpectrumSmoother::new(config.smoothing.clone())),
+        band_weights: Mutex::new(config.band_weights.normalized()),
+        decibels: Mutex::new(SpectrumScale::new(config.decibels.clone())),
+        input_filter: Mutex::new(InputFilter::new(
+            config.noise_gate.clone(),
+            config.high_pass.clone(),
+        )),
+        crowd_noise: Mutex::new(CrowdNoise::new()),
+        commissioning: Mutex::new(None),
+        spectrogram: Mutex::new(Spectrogram::new(&config.spectrogram)),
+        show: Mutex::new(ShowRecorder::new()),
+        energy_saver: Mutex::new(EnergySaver::new(config.energy_saver.clone())),
+        audio_device: Mutex::new(None),
+        reload_events: Mutex::new(Vec::new()),
+        av_delay: Mutex::new(config.av_delay),
+        click,
+        stats: Mutex::new(StatsRecorder::new()),
+        stats_db: Mutex::new(None),
+        alert_inputs: Mutex::new(AlertInputs::new()),
+        alert_events: Mutex::new(Vec::new()),
+        scheduler: Mutex::new(Scheduler::new(schedules)),
+        triggered: Mutex::new(Vec::new()),
+        beat_events: Mutex::new(Vec::new()),
+        output_priority: Mutex::new(OutputPriority::new()),
+    });
+    state.frame_tracer.lock().set_interval(trace_every);
+    state
+        .output
+        .lock()
+        .set_content_policy(&config.content_policy);
+
+    let mut took_over = false;
+    if let Some(fade_secs) = takeover {
+        match handover::request(fade_secs) {
+            Ok(handover) => {
+                // The old instance is fading out either way, so the port is waited for even
+                // when its look can't be carried over
+                took_over = true;
+                match handover.resume(&state, fade_secs) {
+                    Ok(()) => crash::log(format!(
+                        "🤝 Taking over the running backend, {:.1} s crossfade",
+                        fade_secs
+                    )),
+                    Err(e) => crash::log(format!("⚠️ Taking over without the running look: {}", e)),
+                }
+            }
+            Err(e) => crash::log(format!("⚠️ Takeover failed, starting fresh: {}", e)),
+        }
+    }
+
+    crash::install(state.clone());
+    show_report::install_shutdown(state.clone());
+    crash::log(format!("🔥 Effects warmed up in {} ms", warm_up_ms));
+    if config.startup.start_blacked_out {
+        crash::log("🔒 Output blacked out until unlocked");
+    }
+    let pending_crashes = crash::pending_reports().len();
+    if pending_crashes > 0 {
+        crash::log(format!(
+            "💥 {} crash report(s) from previous runs in crash_reports/",
+            pending_crashes
+        ));
+    }
+
+    if config.stats.enabled {
+        let stats_state = state.clone();
+        let stats_config = config.stats.clone();
+        std::thread::spawn(move || stats::run(stats_state, stats_config));
+    }
+    if config.alerts.enabled {
+        let alerts_state = state.clone();
+        let alerts_config = config.alerts.clone();
+        std::thread::spawn(move || alerts::run(alerts_state, alerts_config));
+    }
+    if config.triggers.device.is_some() {
+        let triggers_state = state.clone();
+        let triggers_config = config.triggers.clone();
+        std::thread::spawn(move || triggers::run(triggers_state, triggers_config));
+    }
+    // Always running, since a mapping can pick a controller at any time
+    let midi_state = state.clone();
+    std::thread::spawn(move || midi::run(midi_state));
+    if config.websocket.address.is_some() {
+        let websocket_config = config.websocket.clone();
+        std::thread::spawn(move || websocket::run(websocket_config));
+    }
+    if config.osc.port.is_some() {
+        let osc_state = state.clone();
+        let osc_config = config.osc.clone();
+        std::thread::spawn(move || osc::run(osc_state, osc_config));
+    }
+    if watch_mode {
+        let watch_state = state.clone();
+        let watch_config = config.clone();
+        std::thread::spawn(move || watch::run(watch_state, watch_config));
+        crash::log("♻️ Watching config.toml and palettes.json for changes");
+    }
+
+    let ring = Arc::new(SpectrumRing::new());
+    let render_state = state.clone();
+    let render_ring = ring.clone();
+    let render_performance = config.performance.clone();
+    std::thread::spawn(move || {
+        threads::setup(threads::Role::Render, &render_performance);
+        render::run(render_state, render_ring)
     });
 
     let audio_state = state.clone();
+    let audio_config = config.audio.clone();
+    let audio_performance = config.performance.clone();
     std::thread::spawn(move || {
         if test_mode {
+            threads::setup(threads::Role::Audio, &audio_performance);
             let mut time = 0.0f32;
+            let mut window = SampleWindow::new();
+            let silence = vec![0.0; audio::SAMPLE_RATE as usize / 50];
             loop {
+                // A crowd simulation replaces the test pattern while it plays
+                if let Some(crowd) = audio_state.crowd_noise.lock().mix(&silence) {
+                    window.push(&crowd, |samples| ring.push(fft::band_levels(samples)));
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    continue;
+                }
                 let mut spectrum = vec![0.0; 64];
                 for i in 0..64 {
                     spectrum[i] = ((time * (i as f32 + 1.0) * 0.1).sin() + 1.0)
                         * 0.5
                         * if i < 8 { 1.0 } else { 0.5 };
                 }
-                *audio_state.spectrum.lock() = spectrum.clone();
-
-                let mut engine = audio_state.effect_engine.lock();
-                let frame = engine.render(&spectrum);
-                *audio_state.led_frame.lock() = frame;
+                ring.push(spectrum);
 
                 time += 0.05;
                 std::thread::sleep(std::time::Duration::from_millis(20));
             }
         } else {
-            match AudioCapture::new(move |data| {
-                let spectrum = fft::compute_spectrum(data);
-                *audio_state.spectrum.lock() = spectrum;
-
-                let mut engine = audio_state.effect_engine.lock();
-                let frame = engine.render(&audio_state.spectrum.lock());
-                *audio_state.led_frame.lock() = frame;
-            }) {
-                Ok(audio) => {
-                    audio.run();
+            let mut window = SampleWindow::new();
+            let filter_state = audio_state.clone();
+            // Samples arrive on the audio backend's own thread, set up on its first callback
+            let mut thread_ready = false;
+            let mut audio = AudioCapture::new(&audio_config, move |data| {
+                if !thread_ready {
+                    threads::setup(threads::Role::Audio, &audio_performance);
+                    thread_ready = true;
                 }
-                Err(e) => {}
-            }
+                let crowd = filter_state.crowd_noise.lock().mix(data);
+                let filtered = filter_state
+                    .input_filter
+                    .lock()
+                    .process(crowd.as_deref().unwrap_or(data));
+                window.push(&filtered, |samples| ring.push(fft::band_levels(samples)));
+            });
+            audio.run(|device| *audio_state.audio_device.lock() = device);
         }
     });
 
     let led_state = state.clone();
     let production = production_mode;
+    let power_limit = config.led.power_limit;
+    let pacing = config.pacing.clone();
+    let keep_alive_secs = config.led.keep_alive_secs;
+    let hub_routes = config.hub.routes.clone();
+    let led_performance = config.performance.clone();
+    let edge_strips = config.edge_strips.clone();
+    let mut splash_config = config.splash.clone();
+    // Mid-show, the wall goes straight to the handed over look
+    splash_config.enabled &= !took_over;
     std::thread::spawn(move || {
+        threads::setup(threads::Role::Led, &led_performance);
         let mode = if production {
             LedMode::Production
         } else {
             LedMode::Simulator
         };
         let mut led = LedController::new_with_mode(mode).expect("Failed to init LED");
+        led.set_matrix(matrix);
+        led.set_power_limit(power_limit);
+        led.set_pacing(pacing);
+        led.set_keep_alive(keep_alive_secs);
+        let mut hub = if hub_routes.is_empty() {
+            None
+        } else {
+            match HubDistributor::new(hub_routes, matrix) {
+                Ok(hub) => {
+                    crash::log(format!("🛰️ eHuB output to {} targets", hub.target_count()));
+                    Some(hub)
+                }
+                Err(e) => {
+                    crash::log(format!("❌ eHuB routing disabled: {}", e));
+                    None
+                }
+            }
+        };
+
+        let mut canvas_output = CanvasOutput::open(
+            "Canvas",
+            canvases.iter().map(|canvas| {
+                (
+                    canvas.name.as_str(),
+                    canvas.target.as_str(),
+                    canvas.universe,
+                )
+            }),
+        );
+        let mut spill = EdgeSpill::new(edge_strips, matrix.width, matrix.height);
+        let mut spill_output = CanvasOutput::open(
+            "Edge strip",
+            spill
+                .strips()
+                .iter()
+                .map(|strip| (strip.name.as_str(), strip.target.as_str(), strip.universe)),
+        );
+
+        let mut delay_line = DelayLine::new(std::time::Duration::ZERO);
+        let mut splash = Splash::load(&splash_config, matrix.width, matrix.height);
 
         let mut frame_count = 0u64;
         let start_time = std::time::Instant::now();
 
         loop {
-            let frame = led_state.led_frame.lock().clone();
-            led.send_frame(&frame);
+            // A commissioning pattern replaces the effect but still goes through the output limits
+            let pattern = led_state
+                .commissioning
+                .lock()
+                .as_ref()
+                .and_then(Commissioning::frame);
+            let commissioning = pattern.is_some();
+            // Wall and canvases are delayed together; commissioning patterns go out right away
+            let av_delay = led_state.av_delay.lock().ms;
+            delay_line.set_delay(std::time::Duration::from_millis(av_delay as u64));
+            let rendered = (
+                led_state.led_frame.lock().clone(),
+                led_state.canvas_frames.lock().clone(),
+            );
+            let (delayed, canvas_frames) = delay_line
+                .process(Instant::now(), rendered)
+                .cloned()
+                .unwrap_or_else(|| {
+                    let blank = LedFrame {
+                        id: 0,
+                        pixels: vec![0; matrix.frame_len()],
+                    };
+                    (blank, Vec::new())
+                });
+            // The splash plays once over the startup effect, without the AV delay
+            let splash_frame = splash
+                .as_mut()
+                .and_then(|splash| splash.frame(Instant::now()));
+            if splash_frame.is_none() {
+                splash = None;
+            }
+            // Only the effect's own frames are traced
+            let frame_id = (pattern.is_none() && splash_frame.is_none()).then_some(delayed.id);
+            let mut frame = pattern.or(splash_frame).unwrap_or(delayed.pixels);
+            // Sampled before trims and the HUD, the strips only take the effect's colors
+            let spill_frames = spill_output.as_ref().map(|_| spill.render(&frame));
+            let transition = led_state.energy_saver.lock().update();
+            if let Some(transition) = transition {
+                energy::apply(&led_state, transition);
+            }
+            let mut control = led_state.output.lock().clone();
+            control.brightness *= led_state.energy_saver.lock().brightness();
+            let output_start = Instant::now();
+            led.apply_output_control(&mut frame, &control);
+            let (effect, bpm) = {
+                let engine = led_state.effect_engine.lock();
+                (engine.current_effect_name(), engine.bpm())
+            };
+            if control.hud && !control.blackout && !commissioning {
+                let stats = hud::HudStats {
+                    fps: led_state.render_health.lock().average_fps(),
+                    effect,
+                    bpm,
+                    brightness: control.effective_brightness(),
+                };
+                hud::draw(&mut frame, matrix.width, matrix.height, &stats);
+            }
+            led.set_target_set(led_state.output_targets.lock().active());
+            led.set_color_correction(&control.color_correction);
+            let send_start = Instant::now();
+            let packets = led.send_frame(&frame);
+            if let Some(hub) = hub.as_mut() {
+                hub.send_frame(&frame);
+            }
+            if let Some(output) = canvas_output.as_mut() {
+                output.send(&canvas_frames, &control, power_limit);
+            }
+            if let (Some(output), Some(frames)) = (spill_output.as_mut(), spill_frames) {
+                output.send(frames, &control, power_limit);
+            }
+            if let Some(frame_id) = frame_id {
+                led_state.frame_tracer.lock().record_output(
+                    frame_id,
+                    send_start - output_start,
+                    send_start.elapsed(),
+                    packets,
+                );
+            }
+            led_state.show.lock().record_frame(effect, &frame, packets);
+            led_state
+                .output_priority
+                .lock()
+                .record_led(led.take_contention(), Instant::now());
+            let (sent, failed) = led.take_send_counts();
+            led_state.stats.lock().record_frame(&frame, sent, failed);
+            led_state
+                .alert_inputs
+                .lock()
+                .record_output(sent, failed, led.offline_controllers());
+            if let Some(alert) = led.take_alert() {
+                crash::log(format!("🚨 {}", alert.message));
+                if let Some(cues) = &led_state.click {
+                    cues.push(Cue::Warning);
+                }
+                led_state.stats.lock().record_error();
+                led_state
+                    .show
+                    .lock()
+                    .record_error(alert.message.to_string());
+                led_state.output_alerts.lock().push(alert);
+            }
 
             frame_count += 1;
             if frame_count % 100 == 0 {
@@ -91,11 +591,68 @@ fn main() -> Result<()> {
                 let fps = frame_count as f64 / elapsed;
             }
 
-            std::thread::sleep(std::time::Duration::from_millis(13));
+            let frame_delay = led_state
+                .energy_saver
+                .lock()
+                .frame_interval()
+                .unwrap_or(std::time::Duration::from_millis(13));
+            std::thread::sleep(frame_delay);
         }
     });
 
-    let server = UdpServer::new(state)?;
+    let capture = capture_path.and_then(|path| {
+        match PacketCapture::create(std::path::Path::new(&path), capture_frames) {
+            Ok(capture) => {
+                crash::log(format!("📼 Capturing protocol packets to {}", path));
+                Some(capture)
+            }
+            Err(e) => {
+                crash::log(format!("❌ Protocol capture disabled: {}", e));
+                None
+            }
+        }
+    });
+    if let Some(fade_secs) = takeover.filter(|_| took_over) {
+        handover::wait_for_port("0.0.0.0:8081", fade_secs)?;
+    }
+    let server = UdpServer::new(
+        state.clone(),
+        config.stream.clone(),
+        accept_config_push,
+        capture,
+    )?;
+
+    if kiosk_mode {
+        #[cfg(feature = "kiosk")]
+        {
+            std::thread::spawn(move || {
+                if let Err(e) = server.run() {
+                    crash::log(format!("❌ UDP server stopped: {}", e));
+                }
+            });
+            return kiosk::run(state);
+        }
+
+        #[cfg(not(feature = "kiosk"))]
+        crash::log("⚠️ --kiosk needs a build with `--features kiosk`, running headless");
+    }
+
+    if tui_mode {
+        #[cfg(feature = "tui")]
+        {
+            let clients = server.client_list();
+            std::thread::spawn(move || {
+                if let Err(e) = server.run() {
+                    crash::log(format!("❌ UDP server stopped: {}", e));
+                }
+            });
+            return tui::run(state, clients);
+        }
+
+        #[cfg(not(feature = "tui"))]
+        crash::log("⚠️ --tui needs a build with `--features tui`, running headless");
+    }
+
     server.run()?;
 
     Ok(())
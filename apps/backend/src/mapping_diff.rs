@@ -0,0 +1,206 @@
+use crate::config::MatrixConfig;
+use crate::crash;
+use crate::effects::EffectEngine;
+use crate::led::{LedController, SIMULATOR_COLUMN_PIXELS};
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+// Compared on the stock 128x128 wall
+const SIZE: usize = 128;
+const OUTPUT_DIR: &str = "mapping_diff";
+const WARM_UP_FRAMES: usize = 30;
+const REPORTED_COLUMNS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappingDiff {
+    pub differing: usize,
+    pub max_delta: u8,
+    // Columns with the most differing pixels, worst first
+    pub columns: Vec<(usize, usize)>,
+}
+
+fn set_pixel(image: &mut [u8], x: usize, y: usize, rgb: &[u8]) {
+    let idx = (y * SIZE + x) * 3;
+    image[idx..idx + 3].copy_from_slice(&rgb[..3]);
+}
+
+// What the simulator shows: its universes per column laid back out as pixels
+pub fn simulator_view(frame: &[u8]) -> Vec<u8> {
+    let matrix = MatrixConfig::default();
+    let mut image = vec![0u8; SIZE * SIZE * 3];
+
+    for col in 0..SIZE {
+        for uni_in_col in 0..SIZE / SIMULATOR_COLUMN_PIXELS {
+            let dmx_data = LedController::simulator_universe(&matrix, frame, col, uni_in_col);
+            for led in 0..SIMULATOR_COLUMN_PIXELS {
+                let pixel = uni_in_col * SIMULATOR_COLUMN_PIXELS + led;
+                let y = if col.is_multiple_of(2) {
+                    127 - pixel
+                } else {
+                    pixel
+                };
+                set_pixel(&mut image, col, y, &dmx_data[led * 3..]);
+            }
+        }
+    }
+
+    image
+}
+
+// What the wall shows: each strip's LEDs sampled back at the height of every pixel row
+pub fn production_view(frame: &[u8]) -> Vec<u8> {
+    let matrix = MatrixConfig::default();
+    let [up_leds, down_leds] = matrix.leds_per_strip;
+    let mut image = vec![0u8; SIZE * SIZE * 3];
+
+    for band in 0..SIZE / 2 {
+        let (col_up, col_down) = (band * 2, band * 2 + 1);
+        let mut leds = Vec::new();
+        for uni_in_band in 0..matrix.universes_per_band() {
+            let mut dmx_data = vec![0u8; 512];
+            LedController::map_pixels_to_band(&matrix, &mut dmx_data, frame, band, uni_in_band);
+            leds.extend_from_slice(&dmx_data[..510]);
+        }
+        let (up, down) = leds.split_at(up_leds * 3);
+
+        for row in 0..SIZE {
+            let led = (row * 2 + 1) * up_leds / (SIZE * 2);
+            set_pixel(&mut image, col_up, 127 - row, &up[led * 3..]);
+
+            let led = (row * 2 + 1) * down_leds / (SIZE * 2);
+            set_pixel(&mut image, col_down, row, &down[led * 3..]);
+        }
+    }
+
+    image
+}
+
+pub fn compare(simulator: &[u8], production: &[u8]) -> MappingDiff {
+    let mut per_column = vec![0usize; SIZE];
+    let mut differing = 0;
+    let mut max_delta = 0;
+
+    for (index, (a, b)) in simulator.chunks(3).zip(production.chunks(3)).enumerate() {
+        let delta = a
+            .iter()
+            .zip(b)
+            .map(|(&a, &b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0);
+        if delta > 0 {
+            differing += 1;
+            per_column[index % SIZE] += 1;
+            max_delta = max_delta.max(delta);
+        }
+    }
+
+    let mut columns: Vec<(usize, usize)> = per_column
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    columns.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    columns.truncate(REPORTED_COLUMNS);
+
+    MappingDiff {
+        differing,
+        max_delta,
+        columns,
+    }
+}
+
+// Horizontal gradient with a green line every 4 rows, so any row the strips skip or repeat stands out
+pub fn test_card() -> Vec<u8> {
+    let mut frame = vec![0u8; SIZE * SIZE * 3];
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let green = if y.is_multiple_of(4) { 255 } else { 0 };
+            set_pixel(&mut frame, x, y, &[(x * 2) as u8, green, (y * 2) as u8]);
+        }
+    }
+    frame
+}
+
+fn effect_frame(name: &str) -> Result<Vec<u8>> {
+    let mut engine = EffectEngine::new();
+    engine.set_effect_by_name(name)?;
+
+    let mut frame = Vec::new();
+    for step in 0..WARM_UP_FRAMES {
+        let time = step as f32 * 0.05;
+        let spectrum: Vec<f32> = (0..64)
+            .map(|i| ((time * (i as f32 + 1.0) * 0.1).sin() + 1.0) * 0.5)
+            .collect();
+        frame = engine.render(&spectrum);
+    }
+    Ok(frame)
+}
+
+fn write_ppm(path: &Path, image: &[u8]) -> Result<()> {
+    let mut data = format!("P6\n{} {}\n255\n", SIZE, SIZE).into_bytes();
+    data.extend_from_slice(image);
+    fs::write(path, data)?;
+    Ok(())
+}
+
+// Renders the test card, or a few frames of `effect`, through both mappings and writes
+// simulator.ppm, production.ppm and diff.ppm side by side for rehearsal checks
+pub fn run(effect: Option<&str>) -> Result<MappingDiff> {
+    let frame = match effect {
+        Some(name) => effect_frame(name)?,
+        None => test_card(),
+    };
+
+    let simulator = simulator_view(&frame);
+    let production = production_view(&frame);
+    let delta: Vec<u8> = simulator
+        .iter()
+        .zip(&production)
+        .map(|(&a, &b)| a.abs_diff(b))
+        .collect();
+
+    let dir = Path::new(OUTPUT_DIR);
+    fs::create_dir_all(dir)?;
+    write_ppm(&dir.join("simulator.ppm"), &simulator)?;
+    write_ppm(&dir.join("production.ppm"), &production)?;
+    write_ppm(&dir.join("diff.ppm"), &delta)?;
+
+    let diff = compare(&simulator, &production);
+    crash::log(format!(
+        "🔍 Mapping diff: {} / {} pixels differ (max delta {}), images in {}/",
+        diff.differing,
+        SIZE * SIZE,
+        diff.max_delta,
+        OUTPUT_DIR
+    ));
+    for (col, count) in &diff.columns {
+        crash::log(format!("   column {:3}: {} pixels", col, count));
+    }
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_frame_matches() {
+        let frame = vec![90u8; SIZE * SIZE * 3];
+        assert_eq!(simulator_view(&frame), frame);
+
+        let diff = compare(&simulator_view(&frame), &production_view(&frame));
+        assert_eq!(diff.differing, 0);
+        assert!(diff.columns.is_empty());
+    }
+
+    #[test]
+    fn test_row_lines_expose_strip_resampling() {
+        let frame = test_card();
+        let diff = compare(&simulator_view(&frame), &production_view(&frame));
+        assert!(diff.differing > 0);
+        assert_eq!(diff.max_delta, 255);
+        assert!(diff.columns.len() <= REPORTED_COLUMNS);
+    }
+}
@@ -0,0 +1,315 @@
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Language of the human text sent with every code
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Language {
+    pub const ALL: &'static [Language] = &[Language::En, Language::Fr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Fr => "fr",
+        }
+    }
+}
+
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::Fr,
+        _ => Language::En,
+    }
+}
+
+// Code, English, French; `{name}` is filled from the message argument of that name.
+// Codes are stable: clients branch on them and translate them, so never rename one.
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("error", "{detail}", "{detail}"),
+    ("server_full", "Server full", "Serveur plein"),
+    (
+        "invalid_value",
+        "Invalid value for {name}",
+        "Valeur invalide pour {name}",
+    ),
+    (
+        "unknown_parameter",
+        "Unknown parameter: {name} (try {options})",
+        "Réglage inconnu : {name} (essayez {options})",
+    ),
+    (
+        "unknown_effect",
+        "Unknown effect: {name}",
+        "Effet inconnu : {name}",
+    ),
+    (
+        "unknown_effect_tag",
+        "Unknown effect tag: {name} ({options})",
+        "Tag d'effet inconnu : {name} ({options})",
+    ),
+    (
+        "blacklisted",
+        "{name} is blacklisted by the venue content policy",
+        "{name} est interdit par la politique de contenu de la salle",
+    ),
+    (
+        "playlist_empty",
+        "The playlist is empty",
+        "La playlist est vide",
+    ),
+    (
+        "unknown_preset",
+        "Unknown preset: {name}",
+        "Preset inconnu : {name}",
+    ),
+    (
+        "unknown_palette",
+        "Unknown palette: {name}",
+        "Palette inconnue : {name}",
+    ),
+    (
+        "unknown_output_profile",
+        "Unknown output profile: {name} ({options})",
+        "Profil de sortie inconnu : {name} ({options})",
+    ),
+    (
+        "unknown_genre_preset",
+        "Unknown genre preset {name} (try {options})",
+        "Preset de genre inconnu {name} (essayez {options})",
+    ),
+    (
+        "handover_localhost_only",
+        "Handover is only accepted from localhost",
+        "La relève n'est acceptée que depuis localhost",
+    ),
+    (
+        "not_streaming_client",
+        "Not a streaming client",
+        "Ce client ne reçoit pas le flux",
+    ),
+    (
+        "invalid_thumbnail_size",
+        "Thumbnail size must be 0, 16 or 32",
+        "La vignette fait 0, 16 ou 32 pixels",
+    ),
+    (
+        "missing_png_path",
+        "Missing PNG path",
+        "Chemin PNG manquant",
+    ),
+    (
+        "spectrogram_export_failed",
+        "Failed to export spectrogram: {detail}",
+        "Échec de l'export du spectrogramme : {detail}",
+    ),
+    (
+        "no_commissioning",
+        "No commissioning in progress",
+        "Aucune mise en service en cours",
+    ),
+    (
+        "report_write_failed",
+        "Failed to write report: {detail}",
+        "Échec de l'écriture du rapport : {detail}",
+    ),
+    (
+        "no_show_report",
+        "No show report yet",
+        "Pas encore de rapport de soirée",
+    ),
+    (
+        "tape_save_failed",
+        "Failed to save tape: {detail}",
+        "Échec de l'enregistrement de la bande : {detail}",
+    ),
+    (
+        "av_delay_out_of_range",
+        "AV delay out of range (0-{max} ms)",
+        "Délai audio-vidéo hors limites (0-{max} ms)",
+    ),
+    (
+        "output_failing",
+        "Art-Net output still failing after {attempts} socket restarts",
+        "La sortie Art-Net échoue encore après {attempts} redémarrages du socket",
+    ),
+    (
+        "controller_offline",
+        "Controller offline: {controllers}",
+        "Contrôleur hors ligne : {controllers}",
+    ),
+    ("low_fps", "Render at {fps} FPS", "Rendu à {fps} FPS"),
+    (
+        "packet_loss",
+        "{percent}% of Art-Net packets lost",
+        "{percent} % des paquets Art-Net perdus",
+    ),
+    ("audio_silent", "No audio input", "Aucune entrée audio"),
+    ("alert_cleared", "{rule} cleared", "{rule} terminée"),
+];
+
+// A user-facing status or error: a stable code plus the values its text is built from.
+// Raised as an error (`bail!(Message::new(..))`) it keeps its code up to the NACK.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub code: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    pub fn new(code: &'static str) -> Self {
+        Self {
+            code,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+
+    // Errors raised without a code keep their own text under "error"
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        error
+            .downcast_ref::<Message>()
+            .cloned()
+            .unwrap_or_else(|| Message::new("error").arg("detail", error))
+    }
+
+    pub fn text(&self, language: Language) -> String {
+        let Some(&(_, en, fr)) = CATALOG.iter().find(|(code, ..)| *code == self.code) else {
+            return self.code.to_string();
+        };
+        let template = match language {
+            Language::En => en,
+            Language::Fr => fr,
+        };
+        self.args
+            .iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text(language()))
+    }
+}
+
+impl std::error::Error for Message {}
+
+struct Args<'a>(&'a [(&'static str, String)]);
+
+impl Serialize for Args<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+// {"code", "message", "args"}, the text in the configured language
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut message = serializer.serialize_struct("Message", 3)?;
+        message.serialize_field("code", self.code)?;
+        message.serialize_field("message", &self.to_string())?;
+        message.serialize_field("args", &Args(&self.args))?;
+        message.end()
+    }
+}
+
+// Every text of every code, for clients translating on their side
+pub fn catalog() -> serde_json::Value {
+    let messages: serde_json::Map<_, _> = CATALOG
+        .iter()
+        .map(|&(code, en, fr)| (code.to_string(), serde_json::json!({ "en": en, "fr": fr })))
+        .collect();
+    serde_json::json!({
+        "language": language().name(),
+        "languages": Language::ALL.iter().map(|l| l.name()).collect::<Vec<_>>(),
+        "messages": messages,
+    })
+}
+
+// Code and text of a NACK payload; older backends sent the bare text
+pub fn parse_nack(payload: &[u8]) -> (String, String) {
+    let text = || String::from_utf8_lossy(payload).to_string();
+    match serde_json::from_slice::<serde_json::Value>(payload) {
+        Ok(nack) if nack["code"].is_string() => (
+            nack["code"].as_str().unwrap_or_default().to_string(),
+            nack["message"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(text),
+        ),
+        _ => ("error".to_string(), text()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<_> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_catalog_is_consistent() {
+        for (index, (code, en, fr)) in CATALOG.iter().enumerate() {
+            assert!(
+                CATALOG[index + 1..].iter().all(|(other, ..)| other != code),
+                "{} listed twice",
+                code
+            );
+            assert_eq!(placeholders(en), placeholders(fr), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_message_rendering_and_nack() {
+        let message = Message::new("unknown_preset").arg("name", "drop");
+        assert_eq!(message.text(Language::En), "Unknown preset: drop");
+        assert_eq!(message.text(Language::Fr), "Preset inconnu : drop");
+
+        let error = anyhow::Error::from(message.clone());
+        assert_eq!(Message::from_error(&error), message);
+        let plain = Message::from_error(&anyhow::anyhow!("Disk full"));
+        assert_eq!(
+            (plain.code, plain.text(Language::Fr).as_str()),
+            ("error", "Disk full")
+        );
+
+        let nack = serde_json::to_vec(&message).unwrap();
+        assert_eq!(parse_nack(&nack).0, "unknown_preset");
+        assert_eq!(
+            parse_nack(b"Server full"),
+            ("error".to_string(), "Server full".to_string())
+        );
+    }
+}
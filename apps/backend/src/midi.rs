@@ -0,0 +1,525 @@
+use crate::crash;
+use crate::effects::hsv_to_rgb;
+use crate::schedule::parse_action;
+use crate::udp::UdpCommand;
+use crate::AppState;
+use anyhow::{anyhow, bail, Result};
+use midir::{MidiInput, MidiInputConnection, MidiInputPort};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const MIDI_MAPPING_PATH: &str = "midi.json";
+const CLIENT_NAME: &str = "dj4led";
+// How often the mapping's device and the ports are checked: an unplugged controller is
+// dropped, then looked for again
+const CHECK_EVERY: Duration = Duration::from_millis(500);
+// Knobs past half way count as a press for actions that aren't continuous
+const PRESS_THRESHOLD: u8 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiControl {
+    Cc,
+    Note,
+}
+
+// A note off is a note with a value of 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MidiEvent {
+    pub control: MidiControl,
+    // 1 to 16
+    pub channel: u8,
+    pub number: u8,
+    pub value: u8,
+}
+
+// Decodes a MIDI byte stream: running status, clock bytes in the middle of a message,
+// and SysEx or other system messages skipped
+#[derive(Debug, Default)]
+pub struct MidiParser {
+    status: u8,
+    data: [u8; 2],
+    len: usize,
+}
+
+impl MidiParser {
+    pub fn push(&mut self, byte: u8) -> Option<MidiEvent> {
+        match byte {
+            0xF8..=0xFF => return None,
+            0x80..=0xEF => {
+                self.status = byte;
+                self.len = 0;
+                return None;
+            }
+            // Their data bytes are ignored until the next channel message
+            0xF0..=0xF7 => {
+                self.status = 0;
+                return None;
+            }
+            _ if self.status == 0 => return None,
+            _ => {}
+        }
+
+        self.data[self.len] = byte;
+        self.len += 1;
+        let needed = match self.status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            _ => 2,
+        };
+        if self.len < needed {
+            return None;
+        }
+        self.len = 0;
+
+        let channel = (self.status & 0x0F) + 1;
+        let [number, value] = self.data;
+        let (control, value) = match self.status & 0xF0 {
+            0x80 => (MidiControl::Note, 0),
+            0x90 => (MidiControl::Note, value),
+            0xB0 => (MidiControl::Cc, value),
+            _ => return None,
+        };
+        Some(MidiEvent {
+            control,
+            channel,
+            number,
+            value,
+        })
+    }
+}
+
+fn default_max() -> f32 {
+    1.0
+}
+
+// e.g. {"control": "cc", "channel": 1, "number": 7, "action": "brightness"}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidiBinding {
+    pub control: MidiControl,
+    // 1 to 16; unset listens on every channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+    pub number: u8,
+    // "brightness", "red", "green", "blue", "hue" or "parameter <name>" follow a knob; anything
+    // else ("next_effect", "previous_effect" or a schedule action like "effect rain") fires
+    // on a note or a knob going past half way
+    pub action: String,
+    // What a knob sweeps for "parameter <name>"
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default = "default_max")]
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiMapping {
+    // Name of the MIDI input port, or part of it, e.g. "nanoKONTROL2"; unset leaves MIDI off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub bindings: Vec<MidiBinding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorChannel {
+    Red,
+    Green,
+    Blue,
+    Hue,
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    Brightness,
+    Color(ColorChannel),
+    Parameter(String),
+    Command(UdpCommand),
+}
+
+fn parse_binding(binding: &MidiBinding) -> Result<Action> {
+    if binding
+        .channel
+        .is_some_and(|channel| !(1..=16).contains(&channel))
+    {
+        bail!("MIDI channels go from 1 to 16");
+    }
+    if binding.number > 127 {
+        bail!("MIDI numbers go from 0 to 127");
+    }
+    if !binding.min.is_finite() || !binding.max.is_finite() {
+        bail!("Invalid range for {}", binding.action);
+    }
+
+    Ok(match binding.action.trim() {
+        "brightness" => Action::Brightness,
+        "red" => Action::Color(ColorChannel::Red),
+        "green" => Action::Color(ColorChannel::Green),
+        "blue" => Action::Color(ColorChannel::Blue),
+        "hue" => Action::Color(ColorChannel::Hue),
+        "next_effect" => Action::Command(UdpCommand::NextEffect),
+        "previous_effect" => Action::Command(UdpCommand::PreviousEffect),
+        action => match action.strip_prefix("parameter ") {
+            Some(name) if !name.trim().is_empty() => Action::Parameter(name.trim().to_string()),
+            _ => Action::Command(parse_action(action)?),
+        },
+    })
+}
+
+// Turns a controller's messages into commands through the mapping
+#[derive(Default)]
+pub struct MidiRouter {
+    mapping: MidiMapping,
+    actions: Vec<Action>,
+    // Last custom color sent, so knobs for red, green and blue each move their own part
+    color: Option<(f32, f32, f32)>,
+    // Knobs past half way, which fire again only once they've come back down
+    held: HashSet<(u8, u8)>,
+}
+
+impl MidiRouter {
+    pub fn new(mapping: MidiMapping) -> Result<Self> {
+        let actions = mapping
+            .bindings
+            .iter()
+            .enumerate()
+            .map(|(i, binding)| {
+                parse_binding(binding).map_err(|e| anyhow::anyhow!("Binding {}: {}", i + 1, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            mapping,
+            actions,
+            color: None,
+            held: HashSet::new(),
+        })
+    }
+
+    pub fn load() -> Self {
+        let mapping = match fs::read_to_string(MIDI_MAPPING_PATH) {
+            Ok(content) => serde_json::from_str(&content).map_err(anyhow::Error::from),
+            Err(_) => Ok(MidiMapping::default()),
+        };
+        match mapping.and_then(Self::new) {
+            Ok(router) => router,
+            Err(e) => {
+                crash::log(format!("⚠️ MIDI mapping ignored: {}", e));
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(
+            MIDI_MAPPING_PATH,
+            serde_json::to_string_pretty(&self.mapping)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn mapping(&self) -> &MidiMapping {
+        &self.mapping
+    }
+
+    pub fn device(&self) -> Option<&str> {
+        self.mapping.device.as_deref()
+    }
+
+    // `current_color` gives the engine's custom color the first time a color knob moves
+    pub fn handle(
+        &mut self,
+        event: MidiEvent,
+        current_color: impl FnOnce() -> (f32, f32, f32),
+    ) -> Vec<UdpCommand> {
+        let pressed = match event.control {
+            MidiControl::Note => event.value > 0,
+            MidiControl::Cc if event.value >= PRESS_THRESHOLD => {
+                self.held.insert((event.channel, event.number))
+            }
+            MidiControl::Cc => {
+                self.held.remove(&(event.channel, event.number));
+                false
+            }
+        };
+        let level = event.value as f32 / 127.0;
+        let mut current_color = Some(current_color);
+
+        let mut commands = Vec::new();
+        for (binding, action) in self.mapping.bindings.iter().zip(&self.actions) {
+            if binding.control != event.control
+                || binding.number != event.number
+                || binding
+                    .channel
+                    .is_some_and(|channel| channel != event.channel)
+            {
+                continue;
+            }
+            // Note offs only matter to buttons, and buttons ignore them
+            if event.control == MidiControl::Note && event.value == 0 {
+                continue;
+            }
+
+            let command = match action {
+                Action::Brightness => UdpCommand::SetBrightness(level),
+                Action::Color(channel) => {
+                    let (r, g, b) = self
+                        .color
+                        .or_else(|| current_color.take().map(|color| color()))
+                        .unwrap_or((1.0, 1.0, 1.0));
+                    let color = match channel {
+                        ColorChannel::Red => (level, g, b),
+                        ColorChannel::Green => (r, level, b),
+                        ColorChannel::Blue => (r, g, level),
+                        // The top of the knob comes back round to red
+                        ColorChannel::Hue => hsv_to_rgb(level.min(0.999), 1.0, 1.0),
+                    };
+                    self.color = Some(color);
+                    UdpCommand::SetCustomColor(color.0, color.1, color.2)
+                }
+                Action::Parameter(name) => {
+                    let value = binding.min + (binding.max - binding.min) * level;
+                    UdpCommand::SetParameter(name.clone(), value.to_string())
+                }
+                Action::Command(command) if pressed => command.clone(),
+                Action::Command(_) => continue,
+            };
+            commands.push(command);
+        }
+        commands
+    }
+}
+
+// MIDI input ports seen by the system (ALSA, CoreMIDI or WinMM), by name
+pub fn list_devices() -> Vec<serde_json::Value> {
+    let Ok(input) = MidiInput::new(CLIENT_NAME) else {
+        return Vec::new();
+    };
+    let mut names: Vec<_> = input
+        .ports()
+        .iter()
+        .filter_map(|port| input.port_name(port).ok())
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| serde_json::json!({ "device": name, "name": name }))
+        .collect()
+}
+
+// The port named `device`, or else the first whose name contains it: ALSA adds client
+// numbers that change when controllers are plugged in another order
+fn find_port(input: &MidiInput, device: &str) -> Option<MidiInputPort> {
+    let named: Vec<_> = input
+        .ports()
+        .into_iter()
+        .filter_map(|port| Some((input.port_name(&port).ok()?, port)))
+        .collect();
+    named
+        .iter()
+        .position(|(name, _)| name == device)
+        .or_else(|| named.iter().position(|(name, _)| name.contains(device)))
+        .map(|index| named[index].1.clone())
+}
+
+fn handle_message(state: &AppState, parser: &mut MidiParser, message: &[u8]) {
+    let mut commands = Vec::new();
+    {
+        let mut router = state.midi.lock();
+        for &byte in message {
+            if let Some(event) = parser.push(byte) {
+                let color = || state.effect_engine.lock().custom_color();
+                commands.extend(router.handle(event, color));
+            }
+        }
+    }
+    if !commands.is_empty() {
+        state.triggered.lock().extend(commands);
+    }
+}
+
+// Messages arrive on midir's thread for as long as the connection is kept
+fn connect(state: &Arc<AppState>, device: &str) -> Result<MidiInputConnection<MidiParser>> {
+    let input = MidiInput::new(CLIENT_NAME)?;
+    let Some(port) = find_port(&input, device) else {
+        bail!("no such MIDI input");
+    };
+    let state = state.clone();
+    input
+        .connect(
+            &port,
+            CLIENT_NAME,
+            move |_, message, parser| handle_message(&state, parser, message),
+            MidiParser::default(),
+        )
+        .map_err(|e| anyhow!("{}", e))
+}
+
+fn port_present(device: &str) -> bool {
+    MidiInput::new(CLIENT_NAME).is_ok_and(|input| find_port(&input, device).is_some())
+}
+
+// Like the booth's buttons, commands are handed to the UDP server, which applies them between
+// two frames like any client's
+pub fn run(state: Arc<AppState>) {
+    let mut connection: Option<(String, MidiInputConnection<MidiParser>)> = None;
+    let mut last_error = String::new();
+    loop {
+        let device = state.midi.lock().device().map(str::to_string);
+        if let Some((open, _)) = &connection {
+            if device.as_deref() != Some(open.as_str()) {
+                connection = None;
+            } else if !port_present(open) {
+                crash::log(format!("⚠️ MIDI controller {} disconnected", open));
+                connection = None;
+            }
+        }
+
+        if let (None, Some(device)) = (&connection, device) {
+            match connect(&state, &device) {
+                Ok(port) => {
+                    crash::log(format!("🎹 MIDI controller on {}", device));
+                    last_error.clear();
+                    connection = Some((device, port));
+                }
+                Err(e) => {
+                    // Logged once, not every retry while the controller stays unplugged
+                    let error = e.to_string();
+                    if error != last_error {
+                        crash::log(format!("⚠️ MIDI controller {}: {}", device, error));
+                        last_error = error;
+                    }
+                }
+            }
+        }
+        std::thread::sleep(CHECK_EVERY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(bytes: &[u8]) -> Vec<MidiEvent> {
+        let mut parser = MidiParser::default();
+        bytes.iter().filter_map(|&byte| parser.push(byte)).collect()
+    }
+
+    fn binding(control: MidiControl, number: u8, action: &str) -> MidiBinding {
+        MidiBinding {
+            control,
+            channel: None,
+            number,
+            action: action.to_string(),
+            min: 0.0,
+            max: 1.0,
+        }
+    }
+
+    fn cc(number: u8, value: u8) -> MidiEvent {
+        MidiEvent {
+            control: MidiControl::Cc,
+            channel: 1,
+            number,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_parser_handles_running_status_and_clock() {
+        // CC 7 on channel 2, a clock tick mid-message, then running status; SysEx skipped
+        let parsed = events(&[
+            0xB1, 7, 0xF8, 100, 7, 20, 0xF0, 0x7E, 0x01, 0xF7, 0x90, 36, 90, 36, 0,
+        ]);
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(
+            parsed[0],
+            MidiEvent {
+                control: MidiControl::Cc,
+                channel: 2,
+                number: 7,
+                value: 100
+            }
+        );
+        assert_eq!(parsed[1].value, 20);
+        assert_eq!(
+            parsed[2],
+            MidiEvent {
+                control: MidiControl::Note,
+                channel: 1,
+                number: 36,
+                value: 90
+            }
+        );
+        // Note on at velocity 0 is a note off, like 0x80
+        assert_eq!(parsed[3].value, 0);
+        assert_eq!(events(&[0x80, 36, 64])[0].value, 0);
+        // Program changes take one data byte and aren't mapped
+        assert_eq!(events(&[0xC0, 5, 0xB0, 1, 2]).len(), 1);
+    }
+
+    #[test]
+    fn test_router_maps_knobs_and_buttons() {
+        let mut parameter = binding(MidiControl::Cc, 20, "parameter cooling");
+        parameter.max = 0.5;
+        let mapping = MidiMapping {
+            device: None,
+            bindings: vec![
+                binding(MidiControl::Cc, 7, "brightness"),
+                binding(MidiControl::Cc, 21, "green"),
+                binding(MidiControl::Cc, 22, "red"),
+                parameter,
+                binding(MidiControl::Note, 36, "effect rain"),
+                binding(MidiControl::Cc, 40, "next_effect"),
+            ],
+        };
+        let mut router = MidiRouter::new(mapping).unwrap();
+        let white = || (1.0, 1.0, 1.0);
+
+        assert!(matches!(
+            router.handle(cc(7, 127), white)[..],
+            [UdpCommand::SetBrightness(level)] if level == 1.0
+        ));
+        assert!(matches!(
+            router.handle(cc(21, 0), || (0.5, 0.5, 0.5))[..],
+            [UdpCommand::SetCustomColor(r, g, _)] if r == 0.5 && g == 0.0
+        ));
+        // The second knob builds on the first, not on the engine's stale color
+        assert!(matches!(
+            router.handle(cc(22, 127), white)[..],
+            [UdpCommand::SetCustomColor(r, g, b)] if r == 1.0 && g == 0.0 && b == 0.5
+        ));
+        assert!(matches!(
+            &router.handle(cc(20, 127), white)[..],
+            [UdpCommand::SetParameter(name, value)] if name == "cooling" && value == "0.5"
+        ));
+
+        let pad = MidiEvent {
+            control: MidiControl::Note,
+            channel: 10,
+            number: 36,
+            value: 100,
+        };
+        assert!(matches!(
+            &router.handle(pad, white)[..],
+            [UdpCommand::SetEffectByName(name)] if name == "rain"
+        ));
+        assert!(router
+            .handle(MidiEvent { value: 0, ..pad }, white)
+            .is_empty());
+
+        // A CC button fires once per press
+        assert_eq!(router.handle(cc(40, 127), white).len(), 1);
+        assert!(router.handle(cc(40, 127), white).is_empty());
+        assert!(router.handle(cc(40, 0), white).is_empty());
+        assert_eq!(router.handle(cc(40, 127), white).len(), 1);
+        assert!(router.handle(cc(99, 127), white).is_empty());
+
+        let invalid = MidiMapping {
+            device: None,
+            bindings: vec![binding(MidiControl::Note, 1, "fireworks")],
+        };
+        assert!(MidiRouter::new(invalid).is_err());
+    }
+}
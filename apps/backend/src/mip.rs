@@ -0,0 +1,203 @@
+use crate::renderer::{FRAME_HEIGHT, FRAME_WIDTH};
+use serde::Serialize;
+
+pub const MIP_SIZES: [usize; 3] = [64, 32, 16];
+const DOMINANT_COLORS: usize = 3;
+// Pixels whose channels sum below this are background, not a color of the show
+const DARK_THRESHOLD: u16 = 48;
+// Dominant colors are bucketed on the top 3 bits of each channel
+const BUCKET_SHIFT: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DominantColor {
+    pub rgb: [u8; 3],
+    // Fraction of all pixels, so a mostly dark frame has small shares
+    pub share: f32,
+}
+
+// Downscaled copies and color summary of one wall frame, built once per rendered frame
+// and shared by every consumer (stream preview, thumbnails, color sync). Levels are
+// square whatever the wall's shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameMips {
+    levels: Vec<Vec<u8>>,
+    pub average: [u8; 3],
+    pub dominant: Vec<DominantColor>,
+}
+
+impl Default for FrameMips {
+    fn default() -> Self {
+        Self::new(
+            &[0; FRAME_WIDTH * FRAME_HEIGHT * 3],
+            FRAME_WIDTH,
+            FRAME_HEIGHT,
+        )
+    }
+}
+
+impl FrameMips {
+    pub fn new(frame: &[u8], width: usize, height: usize) -> Self {
+        let mut levels = Vec::with_capacity(MIP_SIZES.len());
+        let mut source = (frame, width, height);
+        for size in MIP_SIZES {
+            levels.push(shrink(source.0, source.1, source.2, size));
+            source = (levels.last().map_or(&[][..], Vec::as_slice), size, size);
+        }
+
+        let smallest = levels.last().map_or(&[][..], Vec::as_slice);
+        Self {
+            average: average_color(smallest),
+            dominant: dominant_colors(&levels[1]),
+            levels,
+        }
+    }
+
+    // Row-major RGB of the `size`² level, one of MIP_SIZES
+    pub fn level(&self, size: usize) -> Option<&[u8]> {
+        MIP_SIZES
+            .iter()
+            .position(|&s| s == size)
+            .map(|index| self.levels[index].as_slice())
+    }
+}
+
+// Box filter down to `size`², each destination pixel averaging the source block it
+// covers (2×2 from a 128×128 wall); missing source pixels count as black
+fn shrink(src: &[u8], src_width: usize, src_height: usize, size: usize) -> Vec<u8> {
+    let span = |i: usize, src_len: usize| {
+        let start = i * src_len / size;
+        start..((i + 1) * src_len / size).max(start + 1)
+    };
+
+    let mut dst = Vec::with_capacity(size * size * 3);
+    for y in 0..size {
+        for x in 0..size {
+            let mut sum = [0u32; 3];
+            let mut count = 0;
+            for sy in span(y, src_height) {
+                for sx in span(x, src_width) {
+                    let idx = (sy * src_width + sx) * 3;
+                    for (c, sum) in sum.iter_mut().enumerate() {
+                        *sum += src.get(idx + c).copied().unwrap_or(0) as u32;
+                    }
+                    count += 1;
+                }
+            }
+            dst.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+        }
+    }
+    dst
+}
+
+fn average_color(pixels: &[u8]) -> [u8; 3] {
+    let count = (pixels.len() / 3).max(1) as u32;
+    let mut sum = [0u32; 3];
+    for pixel in pixels.chunks_exact(3) {
+        for (sum, &c) in sum.iter_mut().zip(pixel) {
+            *sum += c as u32;
+        }
+    }
+    sum.map(|total| (total / count) as u8)
+}
+
+fn dominant_colors(pixels: &[u8]) -> Vec<DominantColor> {
+    let total = (pixels.len() / 3).max(1) as f32;
+    // Per bucket: pixel count and channel sums, so each color is the mean of its bucket
+    let mut buckets = vec![(0u32, [0u32; 3]); 1 << (3 * (8 - BUCKET_SHIFT))];
+
+    for pixel in pixels.chunks_exact(3) {
+        if pixel.iter().map(|&c| c as u16).sum::<u16>() < DARK_THRESHOLD {
+            continue;
+        }
+        let index = pixel.iter().fold(0usize, |index, &c| {
+            (index << (8 - BUCKET_SHIFT)) | (c >> BUCKET_SHIFT) as usize
+        });
+        let bucket = &mut buckets[index];
+        bucket.0 += 1;
+        for (sum, &c) in bucket.1.iter_mut().zip(pixel) {
+            *sum += c as u32;
+        }
+    }
+
+    let mut colors: Vec<(u32, [u32; 3])> = buckets.into_iter().filter(|b| b.0 > 0).collect();
+    colors.sort_by_key(|&(count, _)| std::cmp::Reverse(count));
+    colors
+        .into_iter()
+        .take(DOMINANT_COLORS)
+        .map(|(count, sum)| DominantColor {
+            rgb: sum.map(|total| (total / count) as u8),
+            share: count as f32 / total,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_average_blocks() {
+        let mut frame = vec![0u8; FRAME_WIDTH * FRAME_HEIGHT * 3];
+        for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+            let (x, y) = (i % FRAME_WIDTH, i / FRAME_WIDTH);
+            pixel.copy_from_slice(&[
+                if x < 64 { 200 } else { 0 },
+                if (x + y).is_multiple_of(2) { 100 } else { 0 },
+                0,
+            ]);
+        }
+
+        let mips = FrameMips::new(&frame, FRAME_WIDTH, FRAME_HEIGHT);
+        for size in MIP_SIZES {
+            let level = mips.level(size).unwrap();
+            assert_eq!(level.len(), size * size * 3);
+            assert_eq!(&level[..3], &[200, 50, 0]);
+            assert_eq!(&level[level.len() - 3..], &[0, 50, 0]);
+        }
+        assert!(mips.level(8).is_none());
+        assert_eq!(mips.average, [100, 50, 0]);
+    }
+
+    #[test]
+    fn test_dominant_colors_ignore_background() {
+        let mut frame = vec![0u8; FRAME_WIDTH * FRAME_HEIGHT * 3];
+        for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+            let x = i % FRAME_WIDTH;
+            if x < 64 {
+                pixel.copy_from_slice(&[250, 10, 10]);
+            } else if x < 80 {
+                pixel.copy_from_slice(&[10, 10, 250]);
+            }
+        }
+
+        let mips = FrameMips::new(&frame, FRAME_WIDTH, FRAME_HEIGHT);
+        assert_eq!(mips.dominant.len(), 2);
+        assert_eq!(mips.dominant[0].rgb, [250, 10, 10]);
+        assert_eq!(mips.dominant[0].share, 0.5);
+        assert_eq!(mips.dominant[1].rgb, [10, 10, 250]);
+        assert_eq!(mips.dominant[1].share, 0.125);
+
+        assert!(FrameMips::default().dominant.is_empty());
+    }
+
+    #[test]
+    fn test_other_wall_sizes_fill_every_level() {
+        // Left half red on a wide, short wall, smaller than the first level vertically
+        let (width, height) = (192, 48);
+        let mut frame = vec![0u8; width * height * 3];
+        for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+            if i % width < width / 2 {
+                pixel.copy_from_slice(&[240, 0, 0]);
+            }
+        }
+
+        let mips = FrameMips::new(&frame, width, height);
+        for size in MIP_SIZES {
+            let level = mips.level(size).unwrap();
+            assert_eq!(level.len(), size * size * 3);
+            assert_eq!(&level[..3], &[240, 0, 0]);
+            assert_eq!(&level[level.len() - 3..], &[0, 0, 0]);
+        }
+        assert_eq!(mips.average, [120, 0, 0]);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::accent::AccentKind;
+use crate::config::OscConfig;
+use crate::crash;
+use crate::udp::UdpCommand;
+use crate::AppState;
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashSet;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::Duration;
+
+const ADDRESS_PREFIX: &str = "/dj4led";
+// After a handover the old instance holds the port until its fade is over
+const BIND_ATTEMPTS: u32 = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Argument {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Argument {
+    fn number(&self) -> Option<f32> {
+        match self {
+            Self::Int(value) => Some(*value as f32),
+            Self::Float(value) => Some(*value as f32),
+            Self::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            Self::Str(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Message {
+    address: String,
+    arguments: Vec<Argument>,
+}
+
+// OSC 1.0 strings are NUL-terminated and padded to 4 bytes
+fn read_string(data: &[u8], pos: &mut usize) -> Result<String> {
+    let rest = data.get(*pos..).unwrap_or_default();
+    let len = rest
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| anyhow!("Unterminated OSC string"))?;
+    let string = std::str::from_utf8(&rest[..len])?.to_string();
+    *pos += (len + 4) & !3;
+    Ok(string)
+}
+
+fn read_bytes<const N: usize>(data: &[u8], pos: &mut usize) -> Result<[u8; N]> {
+    let bytes = data
+        .get(*pos..*pos + N)
+        .ok_or_else(|| anyhow!("OSC argument cut short"))?;
+    *pos += N;
+    Ok(bytes.try_into()?)
+}
+
+fn decode_message(data: &[u8]) -> Result<Message> {
+    let mut pos = 0;
+    let address = read_string(data, &mut pos)?;
+    if !address.starts_with('/') {
+        bail!("Not an OSC address: {}", address);
+    }
+    // Very old senders leave the type tags out, and with them the arguments
+    if pos >= data.len() {
+        return Ok(Message {
+            address,
+            arguments: Vec::new(),
+        });
+    }
+    let tags = read_string(data, &mut pos)?;
+    let Some(tags) = tags.strip_prefix(',') else {
+        bail!("Missing OSC type tags");
+    };
+
+    let mut arguments = Vec::new();
+    for tag in tags.chars() {
+        arguments.push(match tag {
+            'i' => Argument::Int(i32::from_be_bytes(read_bytes(data, &mut pos)?) as i64),
+            'h' => Argument::Int(i64::from_be_bytes(read_bytes(data, &mut pos)?)),
+            'f' => Argument::Float(f32::from_be_bytes(read_bytes(data, &mut pos)?) as f64),
+            'd' => Argument::Float(f64::from_be_bytes(read_bytes(data, &mut pos)?)),
+            's' | 'S' => Argument::Str(read_string(data, &mut pos)?),
+            'T' => Argument::Bool(true),
+            'F' => Argument::Bool(false),
+            _ => bail!("Unsupported OSC type tag {}", tag),
+        });
+    }
+    Ok(Message { address, arguments })
+}
+
+// Messages of a packet in order, bundles unpacked; their time tags are ignored and everything
+// is applied on arrival
+fn decode_packet(data: &[u8], messages: &mut Vec<Message>) -> Result<()> {
+    let Some(mut elements) = data.strip_prefix(b"#bundle\0") else {
+        messages.push(decode_message(data)?);
+        return Ok(());
+    };
+    elements = elements
+        .get(8..)
+        .ok_or_else(|| anyhow!("OSC bundle without a time tag"))?;
+    while !elements.is_empty() {
+        let mut pos = 0;
+        let size = i32::from_be_bytes(read_bytes(elements, &mut pos)?);
+        let element = usize::try_from(size)
+            .ok()
+            .and_then(|size| elements.get(4..4 + size))
+            .ok_or_else(|| anyhow!("OSC bundle element cut short"))?;
+        decode_packet(element, messages)?;
+        elements = &elements[4 + element.len()..];
+    }
+    Ok(())
+}
+
+// The command an OSC message stands for; None for a button release, buttons sending 1 when
+// pressed and 0 when let go
+fn to_command(message: &Message) -> Result<Option<UdpCommand>> {
+    let path = message
+        .address
+        .strip_prefix(ADDRESS_PREFIX)
+        .filter(|path| path.starts_with('/'))
+        .ok_or_else(|| anyhow!("Not under {}", ADDRESS_PREFIX))?;
+    let arguments = &message.arguments;
+    let number = |i: usize| {
+        arguments
+            .get(i)
+            .and_then(Argument::number)
+            .ok_or_else(|| anyhow!("{} needs a number", path))
+    };
+    let text = || match arguments.first() {
+        Some(Argument::Str(text)) => Ok(text.clone()),
+        _ => Err(anyhow!("{} needs a name", path)),
+    };
+    let pressed = arguments.first().and_then(Argument::number).unwrap_or(1.0) > 0.0;
+
+    if let Some(name) = path.strip_prefix("/parameter/") {
+        return Ok(Some(UdpCommand::SetParameter(
+            name.to_string(),
+            number(0)?.to_string(),
+        )));
+    }
+    let command = match path {
+        "/effect" => match arguments.first() {
+            Some(Argument::Str(name)) => UdpCommand::SetEffectByName(name.clone()),
+            _ => UdpCommand::SetEffect(number(0)?.max(0.0) as usize),
+        },
+        "/effect/next" if pressed => UdpCommand::NextEffect,
+        "/effect/previous" if pressed => UdpCommand::PreviousEffect,
+        "/effect/next" | "/effect/previous" => return Ok(None),
+        // A palette name, or red, green and blue from 0 to 1 for a custom color
+        "/color" => match arguments.first() {
+            Some(Argument::Str(mode)) => UdpCommand::SetColorMode(mode.clone()),
+            _ => UdpCommand::SetCustomColor(
+                number(0)?.clamp(0.0, 1.0),
+                number(1)?.clamp(0.0, 1.0),
+                number(2)?.clamp(0.0, 1.0),
+            ),
+        },
+        "/brightness" => UdpCommand::SetBrightness(number(0)?.clamp(0.0, 1.0)),
+        "/fade_to_black" => UdpCommand::FadeToBlack(number(0)?.max(0.0)),
+        "/blackout" => UdpCommand::SetBlackout(pressed),
+        "/strobe" => UdpCommand::SetStrobe(pressed),
+        "/xy" => UdpCommand::SetXy(number(0)?.clamp(0.0, 1.0), number(1)?.clamp(0.0, 1.0)),
+        "/accent" => {
+            let name = text()?;
+            let kind = [AccentKind::Flash, AccentKind::Shockwave, AccentKind::Glitch]
+                .into_iter()
+                .find(|kind| kind.name() == name);
+            match kind {
+                Some(kind) => UdpCommand::SetAccent(kind as u8),
+                None if name == "off" => UdpCommand::SetAccent(0),
+                None => bail!("Unknown accent {}", name),
+            }
+        }
+        "/preset" => UdpCommand::LoadPreset(text()?),
+        _ => bail!("Unknown address"),
+    };
+    Ok(Some(command))
+}
+
+// Controllers like TouchOSC or a Max for Live device in Ableton send to this port; commands
+// are handed to the UDP server, which applies them between two frames like any client's
+pub fn run(state: Arc<AppState>, config: OscConfig) {
+    let Some(port) = config.port else {
+        return;
+    };
+    let mut attempts = 0;
+    let socket = loop {
+        match UdpSocket::bind(("0.0.0.0", port)) {
+            Ok(socket) => break socket,
+            Err(e) if attempts < BIND_ATTEMPTS => {
+                attempts += 1;
+                if attempts == 1 {
+                    crash::log(format!("⏳ OSC port {} busy ({}), retrying", port, e));
+                }
+                std::thread::sleep(Duration::from_secs(1));
+            }
+            Err(e) => {
+                crash::log(format!(
+                    "❌ OSC disabled, can't listen on port {}: {}",
+                    port, e
+                ));
+                return;
+            }
+        }
+    };
+    crash::log(format!(
+        "🎛️ OSC on UDP port {} ({}/...)",
+        port, ADDRESS_PREFIX
+    ));
+
+    let mut buf = vec![0u8; 65536];
+    // Faders send dozens of messages a second, so each bad address is only logged once
+    let mut reported = HashSet::new();
+    loop {
+        let Ok((len, from)) = socket.recv_from(&mut buf) else {
+            continue;
+        };
+        let mut messages = Vec::new();
+        if let Err(e) = decode_packet(&buf[..len], &mut messages) {
+            if reported.insert(from.to_string()) {
+                crash::log(format!("⚠️ Malformed OSC packet from {}: {}", from, e));
+            }
+        }
+
+        let mut commands = Vec::new();
+        for message in messages {
+            match to_command(&message) {
+                Ok(command) => commands.extend(command),
+                Err(e) => {
+                    if reported.insert(message.address.clone()) {
+                        crash::log(format!("⚠️ OSC {} ignored: {}", message.address, e));
+                    }
+                }
+            }
+        }
+        if !commands.is_empty() {
+            state.triggered.lock().extend(commands);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn padded(text: &str) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize((text.len() + 4) & !3, 0);
+        bytes
+    }
+
+    fn message(address: &str, tags: &str, arguments: &[u8]) -> Vec<u8> {
+        let mut packet = padded(address);
+        packet.extend(padded(tags));
+        packet.extend_from_slice(arguments);
+        packet
+    }
+
+    fn command(address: &str, arguments: Vec<Argument>) -> Result<Option<UdpCommand>> {
+        to_command(&Message {
+            address: address.to_string(),
+            arguments,
+        })
+    }
+
+    #[test]
+    fn test_messages_and_bundles_decode() {
+        let mut color = Vec::new();
+        for value in [1.0f32, 0.5, 0.0] {
+            color.extend(value.to_be_bytes());
+        }
+        let color = message("/dj4led/color", ",fff", &color);
+        let mut messages = Vec::new();
+        decode_packet(&color, &mut messages).unwrap();
+        assert_eq!(messages[0].address, "/dj4led/color");
+        assert_eq!(
+            messages[0].arguments,
+            [1.0, 0.5, 0.0].map(Argument::Float).to_vec()
+        );
+
+        let mut arguments = padded("rain");
+        arguments.extend(7i32.to_be_bytes());
+        let effect = message("/dj4led/effect", ",siT", &arguments);
+        let mut bundle = padded("#bundle");
+        bundle.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        for element in [&color, &effect] {
+            bundle.extend((element.len() as i32).to_be_bytes());
+            bundle.extend_from_slice(element);
+        }
+        messages.clear();
+        decode_packet(&bundle, &mut messages).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[1].arguments,
+            vec![
+                Argument::Str("rain".to_string()),
+                Argument::Int(7),
+                Argument::Bool(true)
+            ]
+        );
+
+        // A float promised but not sent
+        assert!(decode_packet(&message("/dj4led/brightness", ",f", &[]), &mut messages).is_err());
+        bundle.truncate(bundle.len() - 4);
+        assert!(decode_packet(&bundle, &mut messages).is_err());
+    }
+
+    #[test]
+    fn test_addresses_map_to_commands() {
+        assert!(matches!(
+            command("/dj4led/effect", vec![Argument::Str("rain".to_string())]),
+            Ok(Some(UdpCommand::SetEffectByName(name))) if name == "rain"
+        ));
+        assert!(matches!(
+            command("/dj4led/effect", vec![Argument::Int(3)]),
+            Ok(Some(UdpCommand::SetEffect(3)))
+        ));
+        // TouchOSC buttons: 1 on press, 0 on release
+        assert!(matches!(
+            command("/dj4led/effect/next", vec![Argument::Float(1.0)]),
+            Ok(Some(UdpCommand::NextEffect))
+        ));
+        assert!(matches!(
+            command("/dj4led/effect/next", vec![Argument::Float(0.0)]),
+            Ok(None)
+        ));
+        assert!(matches!(
+            command("/dj4led/color", [0.2, 1.5, 0.0].map(Argument::Float).to_vec()),
+            Ok(Some(UdpCommand::SetCustomColor(r, 1.0, 0.0))) if r == 0.2
+        ));
+        assert!(matches!(
+            command("/dj4led/brightness", vec![Argument::Float(0.25)]),
+            Ok(Some(UdpCommand::SetBrightness(level))) if level == 0.25
+        ));
+        assert!(matches!(
+            command("/dj4led/blackout", vec![Argument::Bool(false)]),
+            Ok(Some(UdpCommand::SetBlackout(false)))
+        ));
+        assert!(matches!(
+            command("/dj4led/parameter/cooling", vec![Argument::Float(0.5)]),
+            Ok(Some(UdpCommand::SetParameter(name, value))) if name == "cooling" && value == "0.5"
+        ));
+        assert!(matches!(
+            command("/dj4led/accent", vec![Argument::Str("glitch".to_string())]),
+            Ok(Some(UdpCommand::SetAccent(3)))
+        ));
+
+        assert!(command("/dj4led/brightness", vec![]).is_err());
+        assert!(command("/dj4led/lasers", vec![Argument::Int(1)]).is_err());
+        assert!(command("/dj4ledx/effect", vec![Argument::Int(1)]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::config::OutputProfile;
+
+type Matrix = [[f32; 3]; 3];
+
+// Cameras clip saturated red first: it is held below full scale and never left alone
+const CAMERA_RED_MAX: f32 = 220.0;
+// Share of red's lead over the other channels lifted into green and blue
+const CAMERA_RED_SPREAD: f32 = 0.18;
+
+// How RGB looks to each kind of color blindness (Viénot, Brettel & Mollon)
+const PROTANOPIA: Matrix = [
+    [0.567, 0.433, 0.0],
+    [0.558, 0.442, 0.0],
+    [0.0, 0.242, 0.758],
+];
+const DEUTERANOPIA: Matrix = [[0.625, 0.375, 0.0], [0.7, 0.3, 0.0], [0.0, 0.3, 0.7]];
+const TRITANOPIA: Matrix = [[0.95, 0.05, 0.0], [0.0, 0.433, 0.567], [0.0, 0.475, 0.525]];
+// Where the difference the viewer misses is moved: green and blue for red-green blindness,
+// red and green for blue-yellow
+const RED_GREEN_SHIFT: Matrix = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+const BLUE_YELLOW_SHIFT: Matrix = [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]];
+
+pub fn apply(profile: OutputProfile, frame: &mut [u8]) {
+    match profile {
+        OutputProfile::Standard => {}
+        OutputProfile::CameraSafe => frame.chunks_exact_mut(3).for_each(camera_safe),
+        OutputProfile::Deuteranopia => daltonize(frame, &DEUTERANOPIA, &RED_GREEN_SHIFT),
+        OutputProfile::Protanopia => daltonize(frame, &PROTANOPIA, &RED_GREEN_SHIFT),
+        OutputProfile::Tritanopia => daltonize(frame, &TRITANOPIA, &BLUE_YELLOW_SHIFT),
+    }
+}
+
+fn camera_safe(pixel: &mut [u8]) {
+    let (r, g, b) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+    let lead = r - g.max(b);
+    if lead > 0.0 {
+        pixel[1] = (g + lead * CAMERA_RED_SPREAD) as u8;
+        pixel[2] = (b + lead * CAMERA_RED_SPREAD) as u8;
+    }
+    pixel[0] = r.min(CAMERA_RED_MAX) as u8;
+}
+
+// Greys are left alone; colors the viewer would confuse differ in channels they still see
+fn daltonize(frame: &mut [u8], simulation: &Matrix, shift: &Matrix) {
+    let matrix = daltonize_matrix(simulation, shift);
+    for pixel in frame.chunks_exact_mut(3) {
+        let rgb = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+        for (value, row) in pixel.iter_mut().zip(&matrix) {
+            let mixed = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+            *value = mixed.clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// rgb + shift * (rgb - simulated rgb), folded into a single matrix
+fn daltonize_matrix(simulation: &Matrix, shift: &Matrix) -> Matrix {
+    let identity = |i: usize, j: usize| if i == j { 1.0 } else { 0.0 };
+    let mut matrix = [[0.0; 3]; 3];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            let error: f32 = (0..3)
+                .map(|k| shift[i][k] * (identity(k, j) - simulation[k][j]))
+                .sum();
+            *value = identity(i, j) + error;
+        }
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiled(profile: OutputProfile, rgb: [u8; 3]) -> [u8; 3] {
+        let mut pixel = rgb;
+        apply(profile, &mut pixel);
+        pixel
+    }
+
+    #[test]
+    fn test_camera_safe_tames_deep_red() {
+        let red = profiled(OutputProfile::CameraSafe, [255, 0, 0]);
+        assert_eq!(red[0], CAMERA_RED_MAX as u8);
+        assert!(red[1] > 0 && red[2] > 0);
+        // Colors without a red lead keep their other channels
+        assert_eq!(
+            profiled(OutputProfile::CameraSafe, [0, 200, 90]),
+            [0, 200, 90]
+        );
+        assert_eq!(profiled(OutputProfile::Standard, [255, 0, 0]), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_color_blind_profiles_keep_greys_and_split_red_from_green() {
+        for profile in [
+            OutputProfile::Deuteranopia,
+            OutputProfile::Protanopia,
+            OutputProfile::Tritanopia,
+        ] {
+            for level in [0, 128, 255] {
+                let grey = profiled(profile, [level; 3]);
+                assert!(
+                    grey.iter().all(|&v| v.abs_diff(level) <= 1),
+                    "{:?}",
+                    profile
+                );
+            }
+        }
+
+        // Red and green look alike to a deuteranope; after the profile they differ in blue
+        let red = profiled(OutputProfile::Deuteranopia, [200, 40, 0]);
+        let green = profiled(OutputProfile::Deuteranopia, [40, 200, 0]);
+        assert!(red[2] > green[2] + 20);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::config::{ControllerPacing, PacingConfig};
+use crate::crash;
+use crate::led::{artpoll_refresh_rate, create_artpoll_packet};
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+// Render loop jitter must not make a node that keeps up skip every other frame
+const FRAME_SLACK: Duration = Duration::from_millis(2);
+
+struct NodePacer {
+    pacing: ControllerPacing,
+    reported_fps: Option<u32>,
+    next_due: Option<Instant>,
+    in_burst: usize,
+}
+
+impl NodePacer {
+    fn new(pacing: ControllerPacing) -> Self {
+        Self {
+            pacing,
+            reported_fps: None,
+            next_due: None,
+            in_burst: 0,
+        }
+    }
+
+    fn max_fps(&self, honor_reported: bool) -> Option<u32> {
+        let configured = (self.pacing.max_fps > 0).then_some(self.pacing.max_fps);
+        let reported = self.reported_fps.filter(|_| honor_reported);
+        match (configured, reported) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (limit, None) | (None, limit) => limit,
+        }
+    }
+
+    // A node that is not due keeps showing its previous frame
+    fn frame_due(&mut self, now: Instant, honor_reported: bool) -> bool {
+        self.in_burst = 0;
+        let Some(fps) = self.max_fps(honor_reported) else {
+            return true;
+        };
+        let interval = Duration::from_secs_f64(1.0 / fps as f64);
+
+        match self.next_due {
+            Some(due) if now + FRAME_SLACK < due => false,
+            Some(due) if now < due + interval => {
+                self.next_due = Some(due + interval);
+                true
+            }
+            _ => {
+                self.next_due = Some(now + interval);
+                true
+            }
+        }
+    }
+
+    // Returns the pause owed once a full burst went out back to back
+    fn packet_sent(&mut self) -> Option<Duration> {
+        if self.pacing.burst == 0 || self.pacing.gap_us == 0 {
+            return None;
+        }
+        self.in_burst += 1;
+        if self.in_burst < self.pacing.burst {
+            return None;
+        }
+        self.in_burst = 0;
+        Some(Duration::from_micros(self.pacing.gap_us))
+    }
+}
+
+// Per-controller ArtDMX pacing, so cheaper nodes are never sent more than they can take
+pub struct Pacing {
+    config: PacingConfig,
+    nodes: HashMap<String, NodePacer>,
+    poll_socket: Option<UdpSocket>,
+    last_poll: Option<Instant>,
+}
+
+impl Pacing {
+    pub fn new(config: PacingConfig) -> Self {
+        let poll_socket = if config.honor_reported_refresh {
+            match UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+                socket.set_nonblocking(true)?;
+                Ok(socket)
+            }) {
+                Ok(socket) => Some(socket),
+                Err(e) => {
+                    crash::log(format!(
+                        "⚠️ ArtPoll socket unavailable, refresh limits not read: {}",
+                        e
+                    ));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Self {
+            config,
+            nodes: HashMap::new(),
+            poll_socket,
+            last_poll: None,
+        }
+    }
+
+    fn node(&mut self, address: &str) -> &mut NodePacer {
+        let config = &self.config;
+        self.nodes
+            .entry(address.to_string())
+            .or_insert_with(|| NodePacer::new(config.for_controller(address)))
+    }
+
+    pub fn frame_due(&mut self, address: &str) -> bool {
+        let honor_reported = self.config.honor_reported_refresh;
+        self.node(address).frame_due(Instant::now(), honor_reported)
+    }
+
+    pub fn packet_sent(&mut self, address: &str) {
+        if let Some(gap) = self.node(address).packet_sent() {
+            thread::sleep(gap);
+        }
+    }
+
+    // Polls the nodes every POLL_INTERVAL and picks up the refresh rate their replies report
+    pub fn poll_nodes(&mut self, addresses: &[String]) {
+        let Some(socket) = &self.poll_socket else {
+            return;
+        };
+
+        let mut buf = [0u8; 1024];
+        let mut reports = Vec::new();
+        while let Ok((len, from)) = socket.recv_from(&mut buf) {
+            if let Some(rate) = artpoll_refresh_rate(&buf[..len]) {
+                reports.push((from, rate));
+            }
+        }
+
+        if self
+            .last_poll
+            .is_none_or(|last| last.elapsed() >= POLL_INTERVAL)
+        {
+            let poll = create_artpoll_packet();
+            for address in addresses {
+                let _ = socket.send_to(&poll, address);
+            }
+            self.last_poll = Some(Instant::now());
+        }
+
+        for (from, rate) in reports {
+            self.record_reported(addresses, from, rate);
+        }
+    }
+
+    fn record_reported(&mut self, addresses: &[String], from: SocketAddr, rate: u32) {
+        let matching = addresses.iter().filter(|address| {
+            address
+                .parse::<SocketAddr>()
+                .is_ok_and(|addr| addr.ip() == from.ip())
+        });
+        for address in matching {
+            let node = self.node(address);
+            if node.reported_fps != Some(rate) {
+                node.reported_fps = Some(rate);
+                crash::log(format!(
+                    "⏱️ {} reports a max refresh of {} Hz",
+                    address, rate
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_limit_skips_frames() {
+        let mut node = NodePacer::new(ControllerPacing {
+            max_fps: 30,
+            ..ControllerPacing::default()
+        });
+        let start = Instant::now();
+        let frame = Duration::from_micros(16_667);
+
+        let sent = (0..60)
+            .filter(|&i| node.frame_due(start + frame * i, true))
+            .count();
+        assert_eq!(sent, 30);
+
+        node.reported_fps = Some(20);
+        assert_eq!(node.max_fps(true), Some(20));
+        assert_eq!(node.max_fps(false), Some(30));
+
+        let mut unlimited = NodePacer::new(ControllerPacing::default());
+        assert!((0..10).all(|i| unlimited.frame_due(start + frame * i, true)));
+    }
+
+    #[test]
+    fn test_burst_pause() {
+        let mut node = NodePacer::new(ControllerPacing {
+            burst: 3,
+            gap_us: 400,
+            max_fps: 0,
+        });
+        let gaps: Vec<bool> = (0..7).map(|_| node.packet_sent().is_some()).collect();
+        assert_eq!(gaps, vec![false, false, true, false, false, true, false]);
+
+        node.frame_due(Instant::now(), true);
+        assert_eq!(node.in_burst, 0);
+    }
+
+    #[test]
+    fn test_controller_overrides() {
+        let config: PacingConfig = toml::from_str(
+            "default = { burst = 32, gap_us = 100 }\n[controllers.\"192.168.1.47:6454\"]\nmax_fps = 40\n",
+        )
+        .unwrap();
+        assert!(config.honor_reported_refresh);
+        assert_eq!(config.for_controller("192.168.1.45:6454").burst, 32);
+        assert_eq!(config.for_controller("192.168.1.47:6454").max_fps, 40);
+        assert_eq!(config.for_controller("192.168.1.47:6454").burst, 0);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::effects::COLOR_MODES;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub const PALETTES_PATH: &str = "palettes.json";
+const MAX_PALETTE_COLORS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorPalette {
+    pub name: String,
+    pub colors: Vec<(f32, f32, f32)>,
+}
+
+pub struct PaletteStore {
+    path: PathBuf,
+    palettes: Vec<ColorPalette>,
+    revision: u32,
+}
+
+impl PaletteStore {
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(PALETTES_PATH))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let palettes = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            palettes,
+            revision: 0,
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.palettes)?)?;
+        Ok(())
+    }
+
+    pub fn upsert(&mut self, palette: ColorPalette) -> Result<()> {
+        let palette = normalize(palette)?;
+        match self.palettes.iter_mut().find(|p| p.name == palette.name) {
+            Some(existing) => *existing = palette,
+            None => self.palettes.push(palette),
+        }
+        self.revision = self.revision.wrapping_add(1);
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        let before = self.palettes.len();
+        self.palettes.retain(|p| p.name != name);
+        if self.palettes.len() == before {
+            return Ok(false);
+        }
+
+        self.revision = self.revision.wrapping_add(1);
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn palettes(&self) -> &[ColorPalette] {
+        &self.palettes
+    }
+
+    // Takes over another instance's palettes wholesale (config push)
+    pub fn replace(&mut self, palettes: Vec<ColorPalette>) -> Result<()> {
+        self.palettes = palettes;
+        self.revision = self.revision.wrapping_add(1);
+        self.save()
+    }
+
+    // Picks up edits made to the file by hand; returns whether the palettes changed
+    pub fn reload(&mut self) -> Result<bool> {
+        let palettes: Vec<ColorPalette> = serde_json::from_str(&fs::read_to_string(&self.path)?)?;
+        let palettes = palettes
+            .into_iter()
+            .map(normalize)
+            .collect::<Result<Vec<_>>>()?;
+        if palettes == self.palettes {
+            return Ok(false);
+        }
+
+        self.palettes = palettes;
+        self.revision = self.revision.wrapping_add(1);
+        Ok(true)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "revision": self.revision,
+            "modes": COLOR_MODES,
+            "palettes": self.palettes,
+        })
+    }
+}
+
+fn normalize(mut palette: ColorPalette) -> Result<ColorPalette> {
+    if palette.name.trim().is_empty() || COLOR_MODES.contains(&palette.name.as_str()) {
+        anyhow::bail!("Invalid palette name: {}", palette.name);
+    }
+    if palette.colors.is_empty() {
+        anyhow::bail!("Palette {} has no colors", palette.name);
+    }
+
+    palette.colors.truncate(MAX_PALETTE_COLORS);
+    for color in palette.colors.iter_mut() {
+        color.0 = color.0.clamp(0.0, 1.0);
+        color.1 = color.1.clamp(0.0, 1.0);
+        color.2 = color.2.clamp(0.0, 1.0);
+    }
+    Ok(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_upsert_and_remove() {
+        let path = std::env::temp_dir().join(format!("palettes_test_{}.json", std::process::id()));
+        let mut store = PaletteStore::load_from(path.clone());
+
+        let palette = ColorPalette {
+            name: "club".to_string(),
+            colors: vec![(1.5, 0.0, 0.5)],
+        };
+        store.upsert(palette.clone()).unwrap();
+        store.upsert(palette).unwrap();
+
+        let json = store.to_json();
+        assert_eq!(json["revision"], 2);
+        assert_eq!(json["palettes"].as_array().unwrap().len(), 1);
+        assert_eq!(json["palettes"][0]["colors"][0][0], 1.0);
+
+        assert!(store
+            .upsert(ColorPalette {
+                name: "rainbow".to_string(),
+                colors: vec![(0.0, 0.0, 0.0)],
+            })
+            .is_err());
+
+        let reloaded = PaletteStore::load_from(path.clone());
+        assert_eq!(reloaded.palettes.len(), 1);
+
+        assert!(store.remove("club").unwrap());
+        assert!(!store.remove("club").unwrap());
+
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::EffectParameters;
+use crate::effects::EffectEngine;
+use crate::led::OutputControl;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub const PRESETS_PATH: &str = "presets.json";
+
+// A look the DJ can snap back to: effect, colors, brightness and every effect's knobs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub effect: String,
+    pub color_mode: String,
+    pub custom_color: (f32, f32, f32),
+    pub brightness: f32,
+    #[serde(default)]
+    pub effect_parameters: EffectParameters,
+}
+
+impl Preset {
+    pub fn capture(name: &str, engine: &EffectEngine, output: &OutputControl) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            effect: engine.current_effect_name().to_string(),
+            color_mode: engine.color_mode().to_string(),
+            custom_color: engine.custom_color(),
+            brightness: output.brightness,
+            effect_parameters: engine.effect_parameters().clone(),
+        }
+    }
+
+    // Knobs are applied before switching so the effect comes in already tuned
+    pub fn apply(&self, engine: &mut EffectEngine, output: &mut OutputControl) -> Result<()> {
+        let Some(index) = engine.find_effect(&self.effect) else {
+            anyhow::bail!("Preset {} uses unknown effect {}", self.name, self.effect);
+        };
+        engine.check_effect(index)?;
+
+        engine.set_effect_parameters(self.effect_parameters.clone());
+        engine.set_effect(index);
+        engine.set_color_mode(&self.color_mode);
+        let (r, g, b) = self.custom_color;
+        engine.set_custom_color(r, g, b);
+        output.set_brightness(self.brightness);
+        Ok(())
+    }
+}
+
+pub struct PresetStore {
+    path: PathBuf,
+    presets: Vec<Preset>,
+}
+
+impl PresetStore {
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(PRESETS_PATH))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let presets = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self { path, presets }
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.presets)?)?;
+        Ok(())
+    }
+
+    pub fn upsert(&mut self, preset: Preset) -> Result<()> {
+        let preset = normalize(preset)?;
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        let before = self.presets.len();
+        self.presets.retain(|p| p.name != name);
+        if self.presets.len() == before {
+            return Ok(false);
+        }
+
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "presets": self.presets })
+    }
+}
+
+fn normalize(mut preset: Preset) -> Result<Preset> {
+    if preset.name.is_empty() {
+        anyhow::bail!("Preset name is empty");
+    }
+
+    let (r, g, b) = preset.custom_color;
+    preset.custom_color = (r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0));
+    preset.brightness = preset.brightness.clamp(0.0, 1.0);
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_upsert_and_remove() {
+        let path = std::env::temp_dir().join(format!("presets_test_{}.json", std::process::id()));
+        let mut store = PresetStore::load_from(path.clone());
+
+        let preset = Preset {
+            name: "drop".to_string(),
+            effect: "flames".to_string(),
+            color_mode: "fire".to_string(),
+            custom_color: (1.5, 0.0, 0.5),
+            brightness: 0.8,
+            effect_parameters: EffectParameters::from([(
+                "flames".to_string(),
+                [("cooling".to_string(), 0.05)].into(),
+            )]),
+        };
+        store.upsert(preset.clone()).unwrap();
+        store.upsert(preset).unwrap();
+
+        let json = store.to_json();
+        assert_eq!(json["presets"].as_array().unwrap().len(), 1);
+        assert_eq!(json["presets"][0]["custom_color"][0], 1.0);
+
+        assert!(store
+            .upsert(Preset {
+                name: String::new(),
+                ..store.get("drop").unwrap().clone()
+            })
+            .is_err());
+
+        let reloaded = PresetStore::load_from(path.clone());
+        assert_eq!(reloaded.get("drop"), store.get("drop"));
+        assert_eq!(
+            reloaded.get("drop").unwrap().effect_parameters["flames"]["cooling"],
+            0.05
+        );
+
+        assert!(store.remove("drop").unwrap());
+        assert!(!store.remove("drop").unwrap());
+
+        let _ = fs::remove_file(path);
+    }
+}
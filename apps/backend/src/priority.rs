@@ -0,0 +1,138 @@
+use serde::Serialize;
+use std::io;
+use std::time::{Duration, Instant};
+
+// An Art-Net send taking longer than this waited for room in a full socket buffer
+const SLOW_SEND: Duration = Duration::from_millis(2);
+// Preview frames stay held back this long after the LED output last met contention
+const YIELD_FOR: Duration = Duration::from_millis(500);
+
+pub fn is_backpressure(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock || error.raw_os_error() == Some(libc::ENOBUFS)
+}
+
+// Signs of a saturated NIC seen by the Art-Net sender
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SendContention {
+    pub slow_sends: u64,
+    pub buffer_errors: u64,
+}
+
+impl SendContention {
+    pub fn record<T>(&mut self, elapsed: Duration, result: &io::Result<T>) {
+        if elapsed > SLOW_SEND {
+            self.slow_sends += 1;
+        }
+        if result.as_ref().is_err_and(is_backpressure) {
+            self.buffer_errors += 1;
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.slow_sends > 0 || self.buffer_errors > 0
+    }
+}
+
+// LED output first: the preview sender skips its frames while the Art-Net sender meets backpressure
+pub struct OutputPriority {
+    contended_until: Option<Instant>,
+    led: SendContention,
+    contended_frames: u64,
+    yielded_frames: u64,
+    // Preview sends refused by the kernel
+    preview_blocked: u64,
+}
+
+impl Default for OutputPriority {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputPriority {
+    pub fn new() -> Self {
+        Self {
+            contended_until: None,
+            led: SendContention::default(),
+            contended_frames: 0,
+            yielded_frames: 0,
+            preview_blocked: 0,
+        }
+    }
+
+    // Contention met while sending one LED frame
+    pub fn record_led(&mut self, contention: SendContention, now: Instant) {
+        if contention.any() {
+            self.contended_frames += 1;
+            self.contended_until = Some(now + YIELD_FOR);
+        }
+        self.led.slow_sends += contention.slow_sends;
+        self.led.buffer_errors += contention.buffer_errors;
+    }
+
+    pub fn record_preview_blocked(&mut self) {
+        self.preview_blocked += 1;
+    }
+
+    // Whether the preview sender holds back the frame it is about to send
+    pub fn preview_yields(&mut self, now: Instant) -> bool {
+        let yields = self.contended_until.is_some_and(|until| now < until);
+        if yields {
+            self.yielded_frames += 1;
+        }
+        yields
+    }
+
+    pub fn report(&self) -> serde_json::Value {
+        serde_json::json!({
+            "led": {
+                "contended_frames": self.contended_frames,
+                "slow_sends": self.led.slow_sends,
+                "buffer_errors": self.led.buffer_errors,
+            },
+            "preview": {
+                "yielding": self.contended_until.is_some_and(|until| Instant::now() < until),
+                "yielded_frames": self.yielded_frames,
+                "blocked_sends": self.preview_blocked,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_yields_after_led_contention() {
+        let mut contention = SendContention::default();
+        contention.record(Duration::from_micros(50), &Ok(530));
+        assert!(!contention.any());
+        contention.record(
+            Duration::from_micros(50),
+            &Err::<usize, _>(io::Error::from_raw_os_error(libc::ENOBUFS)),
+        );
+        contention.record(Duration::from_millis(5), &Ok(530));
+        assert_eq!(
+            contention,
+            SendContention {
+                slow_sends: 1,
+                buffer_errors: 1
+            }
+        );
+
+        let mut priority = OutputPriority::new();
+        let start = Instant::now();
+        priority.record_led(SendContention::default(), start);
+        assert!(!priority.preview_yields(start));
+
+        priority.record_led(contention, start);
+        assert!(priority.preview_yields(start + Duration::from_millis(100)));
+        assert!(!priority.preview_yields(start + YIELD_FOR));
+
+        let report = priority.report();
+        assert_eq!(report["led"]["contended_frames"], 1);
+        assert_eq!(report["led"]["buffer_errors"], 1);
+        assert_eq!(report["preview"]["yielded_frames"], 1);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::calibration;
+use crate::click::Cue;
+use crate::decibels;
+use crate::mip::FrameMips;
+use crate::smoothing;
+use crate::AppState;
+use crossbeam_queue::ArrayQueue;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const RING_CAPACITY: usize = 8;
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+// Lock-free hand-off of raw band levels from the audio callback; when the renderer
+// falls behind the oldest entries are dropped
+pub struct SpectrumRing {
+    queue: ArrayQueue<Vec<f32>>,
+}
+
+impl SpectrumRing {
+    pub fn new() -> Self {
+        Self {
+            queue: ArrayQueue::new(RING_CAPACITY),
+        }
+    }
+
+    pub fn push(&self, spectrum: Vec<f32>) {
+        self.queue.force_push(spectrum);
+    }
+
+    pub fn latest(&self) -> Option<Vec<f32>> {
+        let mut latest = None;
+        while let Some(spectrum) = self.queue.pop() {
+            latest = Some(spectrum);
+        }
+        latest
+    }
+}
+
+// The latest rendered frame, numbered in render order so the output and network stages can
+// tell which frame they handled
+#[derive(Debug, Clone, Default)]
+pub struct LedFrame {
+    pub id: u64,
+    pub pixels: Vec<u8>,
+}
+
+// Dedicated render thread so a slow effect never stalls the audio callback
+pub fn run(state: Arc<AppState>, ring: Arc<SpectrumRing>) {
+    // Double buffer: frames render into `back`, which then swaps with the published frame
+    let mut back = state.led_frame.lock().pixels.clone();
+    let mut frame_id = 0;
+    loop {
+        let Some(levels) = ring.latest() else {
+            std::thread::sleep(IDLE_WAIT);
+            continue;
+        };
+
+        let mut spectrum = decibels::process(&state, &levels);
+        calibration::process(&state, &mut spectrum);
+        smoothing::weigh_bands(&state.band_weights.lock(), &mut spectrum);
+        state.smoothing.lock().process(&mut spectrum);
+        state.spectrogram.lock().record(&spectrum);
+        state.show.lock().record_spectrum(&spectrum);
+        state.stats.lock().record_audio(&spectrum);
+        state.alert_inputs.lock().record_audio(&spectrum);
+        state.energy_saver.lock().record_audio(&spectrum);
+        *state.spectrum.lock() = spectrum.clone();
+
+        frame_id += 1;
+        let render_start = Instant::now();
+        let mut engine = state.effect_engine.lock();
+        engine.render_into(&spectrum, &mut back);
+        if let (Some(cues), Some(beat)) = (&state.click, engine.last_beat()) {
+            cues.push(if beat.downbeat {
+                Cue::Downbeat
+            } else {
+                Cue::Beat
+            });
+        }
+        if let Some(event) = engine.beat_event() {
+            state.beat_events.lock().push(event);
+        }
+        engine.enforce_budget(&mut state.resource_budget.lock());
+        let mut canvas_frames = state.canvas_frames.lock();
+        canvas_frames.resize_with(engine.canvases().len(), Vec::new);
+        for (published, canvas) in canvas_frames.iter_mut().zip(engine.canvases()) {
+            published.clear();
+            published.extend_from_slice(canvas.frame());
+        }
+        drop(canvas_frames);
+        drop(engine);
+        let render_time = render_start.elapsed();
+        // Downscaled once here so stream, thumbnails and color summaries share the work
+        let mips = Arc::new(FrameMips::new(
+            &back,
+            state.matrix.width,
+            state.matrix.height,
+        ));
+        {
+            let mut published = state.led_frame.lock();
+            std::mem::swap(&mut published.pixels, &mut back);
+            published.id = frame_id;
+        }
+        *state.frame_mips.lock() = mips;
+        state.render_health.lock().record_frame();
+        state
+            .frame_tracer
+            .lock()
+            .record_render(frame_id, render_start, render_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_keeps_newest_spectrum() {
+        let ring = SpectrumRing::new();
+        assert!(ring.latest().is_none());
+
+        for i in 0..RING_CAPACITY + 3 {
+            ring.push(vec![i as f32; 4]);
+        }
+        assert_eq!(ring.latest(), Some(vec![(RING_CAPACITY + 2) as f32; 4]));
+        assert!(ring.latest().is_none());
+    }
+}
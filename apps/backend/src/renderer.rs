@@ -0,0 +1,295 @@
+use crate::config::{SensitivityConfig, SmoothingConfig};
+use crate::effects::{EffectEngine, COLOR_MODES};
+use crate::fft::{self, SampleWindow};
+use crate::smoothing::SpectrumSmoother;
+use anyhow::Result;
+
+pub const FRAME_WIDTH: usize = 128;
+pub const FRAME_HEIGHT: usize = 128;
+pub const SPECTRUM_BANDS: usize = 64;
+/// Sample rate expected by [`Renderer::push_samples`].
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// Settings for a [`Renderer`]; the defaults match a fresh server.
+#[derive(Debug, Clone, Default)]
+pub struct RendererConfig {
+    /// Effect to start on, matched loosely like the server does (`"spectrum bars"` works).
+    pub effect: Option<String>,
+    /// One of [`COLOR_MODES`].
+    pub color_mode: Option<String>,
+    pub smoothing: SmoothingConfig,
+    pub sensitivity: SensitivityConfig,
+    /// Seeds the effects' random generators; renderers with the same seed, config and
+    /// input produce the same frames.
+    pub seed: u64,
+}
+
+/// Headless render pipeline: spectrum or PCM in, RGB frames out.
+///
+/// ```
+/// use led_visualizer::{Renderer, RendererConfig, FRAME_HEIGHT, FRAME_WIDTH};
+///
+/// let mut renderer = Renderer::new(RendererConfig {
+///     effect: Some("spectrum bars".to_string()),
+///     ..RendererConfig::default()
+/// })?;
+/// renderer.push_spectrum(&[0.8; 64]);
+///
+/// let frame = renderer.next_frame();
+/// assert_eq!(frame.len(), FRAME_WIDTH * FRAME_HEIGHT * 3);
+/// assert!(frame.iter().any(|&c| c > 0));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Renderer {
+    engine: EffectEngine,
+    smoother: SpectrumSmoother,
+    window: SampleWindow,
+    spectrum: Vec<f32>,
+    frame: Vec<u8>,
+}
+
+impl Renderer {
+    /// Fails when the configured effect or color mode is unknown.
+    pub fn new(config: RendererConfig) -> Result<Self> {
+        let mut engine = EffectEngine::new();
+        engine.set_seed(config.seed);
+        engine.set_sensitivity_config(config.sensitivity);
+
+        let mut renderer = Self {
+            engine,
+            smoother: SpectrumSmoother::new(config.smoothing),
+            window: SampleWindow::new(),
+            spectrum: vec![0.0; SPECTRUM_BANDS],
+            frame: vec![0; FRAME_WIDTH * FRAME_HEIGHT * 3],
+        };
+        if let Some(effect) = &config.effect {
+            renderer.set_effect(effect)?;
+        }
+        if let Some(mode) = &config.color_mode {
+            renderer.set_color_mode(mode)?;
+        }
+        renderer.engine.warm_up();
+        Ok(renderer)
+    }
+
+    /// Feeds [`SPECTRUM_BANDS`] values in 0..1, bass first. Missing bands read as silent.
+    pub fn push_spectrum(&mut self, spectrum: &[f32]) {
+        for (band, value) in self.spectrum.iter_mut().enumerate() {
+            *value = spectrum.get(band).map_or(0.0, |v| v.clamp(0.0, 1.0));
+        }
+        self.smoother.process(&mut self.spectrum);
+    }
+
+    /// Feeds mono PCM at [`SAMPLE_RATE`] in chunks of any size; the spectrum updates
+    /// every half FFT window (512 samples).
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        let mut latest = None;
+        self.window
+            .push(samples, |window| latest = Some(fft::band_levels(window)));
+        if let Some(levels) = latest {
+            self.push_spectrum(&fft::normalize_linear(&levels));
+        }
+    }
+
+    /// Renders the current effect from the latest spectrum. Effects animate per call, so
+    /// call this at the output frame rate. The frame buffer is reused between calls.
+    pub fn next_frame(&mut self) -> &[u8] {
+        self.engine.render_into(&self.spectrum, &mut self.frame);
+        &self.frame
+    }
+
+    /// The smoothed spectrum the next frame will be rendered from.
+    pub fn spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    pub fn effect_names(&self) -> Vec<&'static str> {
+        self.engine.effect_names()
+    }
+
+    pub fn current_effect(&self) -> &'static str {
+        self.engine.current_effect_name()
+    }
+
+    /// Returns the registered name the query resolved to.
+    pub fn set_effect(&mut self, name: &str) -> Result<&'static str> {
+        self.engine.set_effect_by_name(name)
+    }
+
+    /// Color modes are shared by every renderer in the process.
+    pub fn set_color_mode(&mut self, mode: &str) -> Result<()> {
+        if !COLOR_MODES.contains(&mode) {
+            anyhow::bail!("Unknown color mode: {}", mode);
+        }
+        self.engine.set_color_mode(mode);
+        Ok(())
+    }
+
+    /// Full engine access for what the renderer does not wrap (sensitivity, metronome...).
+    pub fn engine_mut(&mut self) -> &mut EffectEngine {
+        &mut self.engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::{Blend, Canvas, Effect, EffectTag};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Counts the allocations of the thread that enabled counting, so tests running in
+    // parallel don't add to each other's totals
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get().map(|n| n + 1)));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count_allocations(f: impl FnOnce()) -> usize {
+        ALLOCATIONS.with(|count| count.set(Some(0)));
+        f();
+        ALLOCATIONS.with(|count| count.take().unwrap())
+    }
+
+    // Draws without allocating, so only the engine's own allocations get counted
+    struct Gradient;
+
+    impl Effect for Gradient {
+        fn render(&mut self, spectrum: &[f32], canvas: &mut Canvas) {
+            for x in 0..canvas.width() as i32 {
+                let level = spectrum[x as usize % spectrum.len()];
+                canvas.put_pixel(x, 0, (level, 0.0, 1.0 - level), Blend::Replace);
+            }
+        }
+        fn set_color_mode(&mut self, _mode: &str) {}
+        fn set_custom_color(&mut self, _r: f32, _g: f32, _b: f32) {}
+    }
+
+    #[test]
+    fn test_samples_drive_spectrum() {
+        let mut renderer = Renderer::new(RendererConfig::default()).unwrap();
+        let tone: Vec<f32> = (0..4096)
+            .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin() * 0.5)
+            .collect();
+
+        for chunk in tone.chunks(100) {
+            renderer.push_samples(chunk);
+        }
+        assert!(renderer.spectrum().iter().any(|&v| v > 0.1));
+        assert!(renderer.set_effect("no such effect").is_err());
+        assert!(renderer.set_color_mode("plaid").is_err());
+    }
+
+    #[test]
+    fn test_same_seed_renders_same_frames() {
+        let render = |seed| {
+            let mut renderer = Renderer::new(RendererConfig {
+                effect: Some("starfall".to_string()),
+                seed,
+                ..RendererConfig::default()
+            })
+            .unwrap();
+            let mut frames = Vec::new();
+            for step in 0..120 {
+                let level = if step % 10 < 5 { 0.9 } else { 0.2 };
+                renderer.push_spectrum(&[level; SPECTRUM_BANDS]);
+                frames.push(renderer.next_frame().to_vec());
+            }
+            frames
+        };
+
+        assert_eq!(render(7), render(7));
+        assert_ne!(render(7), render(8));
+    }
+
+    #[test]
+    fn test_rendering_reuses_frame_buffers() {
+        let mut renderer = Renderer::new(RendererConfig::default()).unwrap();
+        renderer
+            .engine_mut()
+            .register("gradient", &[EffectTag::Utility], || Box::new(Gradient));
+        renderer.set_effect("gradient").unwrap();
+        // Long enough for the beat, section and tonalness histories to fill up
+        for _ in 0..300 {
+            renderer.push_spectrum(&[0.5; SPECTRUM_BANDS]);
+            renderer.next_frame();
+        }
+
+        let allocations = count_allocations(|| {
+            for _ in 0..100 {
+                renderer.push_spectrum(&[0.7; SPECTRUM_BANDS]);
+                assert_eq!(renderer.next_frame()[0], 178);
+            }
+        });
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn test_effects_are_listed_by_tag() {
+        let mut renderer = Renderer::new(RendererConfig::default()).unwrap();
+        let engine = renderer.engine_mut();
+        let names = |tag| -> Vec<String> {
+            engine.effect_list(tag)["effects"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|effect| effect["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(names(None).len(), engine.effect_names().len());
+        assert_eq!(names(Some(EffectTag::Utility)), ["applaudimetre"]);
+        let party = names(Some(EffectTag::Party));
+        assert!(party.contains(&"spectrum_bars".to_string()));
+        assert!(!party.contains(&"starfall".to_string()));
+
+        assert_eq!(EffectTag::from_name(" Party"), Some(EffectTag::Party));
+        assert_eq!(EffectTag::from_name("chill"), None);
+    }
+
+    #[test]
+    fn test_extra_canvases_render_every_effect() {
+        let mut renderer = Renderer::new(RendererConfig::default()).unwrap();
+        let engine = renderer.engine_mut();
+        for name in engine.effect_names() {
+            for (width, height) in [(300, 1), (1, 300), (7, 3)] {
+                let canvas = format!("{}_{}x{}", name, width, height);
+                engine.add_canvas(&canvas, width, height, name).unwrap();
+            }
+        }
+        assert!(engine.add_canvas("booth", 0, 10, "rain").is_err());
+        assert!(engine.add_canvas("booth", 10, 1, "no such effect").is_err());
+
+        for step in 0..60 {
+            let level = if step % 10 < 5 { 0.9 } else { 0.3 };
+            renderer.push_spectrum(&[level; SPECTRUM_BANDS]);
+            renderer.next_frame();
+        }
+
+        let canvases = renderer.engine_mut().canvases();
+        for canvas in canvases {
+            let (width, height) = canvas.size();
+            assert_eq!(canvas.frame().len(), width * height * 3);
+        }
+        let strip = canvases
+            .iter()
+            .find(|canvas| canvas.name() == "circular_wave_300x1")
+            .unwrap();
+        assert!(strip.frame().iter().any(|&c| c > 0));
+    }
+}
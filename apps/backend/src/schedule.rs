@@ -0,0 +1,320 @@
+use crate::config::{parse_time_of_day, SmoothingProfile};
+use crate::genre::GenrePreset;
+use crate::udp::UdpCommand;
+use anyhow::{anyhow, bail, Result};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Indexed like tm_wday
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const EVERY_DAY: u8 = 0b111_1111;
+const WEEKDAYS: u8 = 0b011_1110;
+const WEEKENDS: u8 = 0b100_0001;
+const ACTIONS: &str =
+    "blackout, effect, palette, brightness, fade_to_black, genre, targets, profile, preset or replay";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    // 0 = Sunday
+    pub weekday: u8,
+    // Minutes since local midnight
+    pub minute: u32,
+}
+
+impl LocalTime {
+    fn describe(self) -> String {
+        format!(
+            "{} {:02}:{:02}",
+            DAY_NAMES[self.weekday as usize],
+            self.minute / 60,
+            self.minute % 60
+        )
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn local_time() -> LocalTime {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as libc::time_t;
+    let mut local: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut local) }.is_null() {
+        return LocalTime {
+            weekday: 0,
+            minute: 0,
+        };
+    }
+    LocalTime {
+        weekday: local.tm_wday as u8,
+        minute: (local.tm_hour * 60 + local.tm_min) as u32,
+    }
+}
+
+// No time zone database to read here: schedules run on UTC
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn local_time() -> LocalTime {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let days = now / 86_400;
+    LocalTime {
+        // 1970-01-01 was a Thursday
+        weekday: ((days + 4) % 7) as u8,
+        minute: (now % 86_400 / 60) as u32,
+    }
+}
+
+fn parse_day(name: &str) -> Result<u8> {
+    DAY_NAMES
+        .iter()
+        .position(|day| day.eq_ignore_ascii_case(name))
+        .map(|day| day as u8)
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown day {} (use MON..SUN, daily, weekdays or weekends)",
+                name
+            )
+        })
+}
+
+// "daily", "*", "weekdays", "weekends", "FRI", "MON,WED" or "FRI-SUN" as a bit per tm_wday
+fn parse_days(spec: &str) -> Result<u8> {
+    match spec.to_lowercase().as_str() {
+        "daily" | "*" => return Ok(EVERY_DAY),
+        "weekdays" => return Ok(WEEKDAYS),
+        "weekends" => return Ok(WEEKENDS),
+        _ => {}
+    }
+    let mut days = 0;
+    for part in spec.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (mut day, last) = (parse_day(first)?, parse_day(last)?);
+                days |= 1 << day;
+                while day != last {
+                    day = (day + 1) % 7;
+                    days |= 1 << day;
+                }
+            }
+            None => days |= 1 << parse_day(part)?,
+        }
+    }
+    Ok(days)
+}
+
+fn parse_profile(name: &str) -> Result<SmoothingProfile> {
+    match name.to_lowercase().as_str() {
+        "punchy" => Ok(SmoothingProfile::Punchy),
+        "smooth" => Ok(SmoothingProfile::Smooth),
+        "broadcast" => Ok(SmoothingProfile::Broadcast),
+        _ => bail!("Unknown profile {} (try punchy, smooth or broadcast)", name),
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|seconds| *seconds >= 0.0)
+        .ok_or_else(|| anyhow!("Invalid duration {}", value))
+}
+
+// "key value", or "set key=value"
+pub fn parse_action(action: &str) -> Result<UdpCommand> {
+    let (key, value) = match action.strip_prefix("set ") {
+        Some(setting) => setting
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected set key=value"))?,
+        None => action.split_once(' ').unwrap_or((action, "")),
+    };
+    let (key, value) = (key.trim().to_lowercase(), value.trim());
+    let required = |what: &str| {
+        if value.is_empty() {
+            Err(anyhow!("{} needs a {}", key, what))
+        } else {
+            Ok(value.to_string())
+        }
+    };
+
+    Ok(match key.as_str() {
+        "blackout" => match value.to_lowercase().as_str() {
+            "" | "on" => UdpCommand::SetBlackout(true),
+            "off" => UdpCommand::SetBlackout(false),
+            _ => bail!("blackout takes on or off"),
+        },
+        "effect" => UdpCommand::SetEffectByName(required("name")?),
+        "palette" => UdpCommand::SetColorMode(required("name")?),
+        "brightness" => match value.parse::<f32>() {
+            Ok(brightness) if (0.0..=1.0).contains(&brightness) => {
+                UdpCommand::SetBrightness(brightness)
+            }
+            _ => bail!("brightness takes a level from 0 to 1"),
+        },
+        "fade_to_black" => UdpCommand::FadeToBlack(parse_seconds(value)?),
+        "genre" => {
+            let name = required("preset")?;
+            if GenrePreset::from_name(&name).is_none() {
+                bail!("Unknown genre preset {}", name);
+            }
+            UdpCommand::SetGenrePreset(name)
+        }
+        "targets" => UdpCommand::SwitchTargetSet(value.to_string()),
+        "profile" => UdpCommand::SetSmoothingProfile(parse_profile(value)?),
+        "preset" => UdpCommand::LoadPreset(required("name")?),
+        "replay" => UdpCommand::ReplayTape(required("tape name")?),
+        _ => bail!("Unknown action {} (use {})", key, ACTIONS),
+    })
+}
+
+// "<days> <HH:MM> <action>", e.g. "FRI 23:00 set profile=smooth" or "daily 04:00 blackout"
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub rule: String,
+    days: u8,
+    minute: u32,
+    command: UdpCommand,
+}
+
+impl Schedule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        let mut parts = rule.splitn(3, char::is_whitespace);
+        let (Some(days), Some(time), Some(action)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("Expected <days> <HH:MM> <action>: {}", rule);
+        };
+        Ok(Self {
+            rule: rule.to_string(),
+            days: parse_days(days)?,
+            minute: parse_time_of_day(time).ok_or_else(|| anyhow!("Invalid time {}", time))?,
+            command: parse_action(action.trim())?,
+        })
+    }
+
+    fn matches(&self, time: LocalTime) -> bool {
+        self.days & (1 << time.weekday) != 0 && self.minute == time.minute
+    }
+}
+
+// Fires each rule once as the local clock enters its minute
+pub struct Scheduler {
+    schedules: Vec<Schedule>,
+    last: Option<LocalTime>,
+}
+
+impl Scheduler {
+    pub fn new(schedules: Vec<Schedule>) -> Self {
+        Self {
+            schedules,
+            last: None,
+        }
+    }
+
+    pub fn add(&mut self, rule: &str) -> Result<()> {
+        self.schedules.push(Schedule::parse(rule)?);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<Schedule> {
+        if index >= self.schedules.len() {
+            bail!("No schedule {}", index);
+        }
+        Ok(self.schedules.remove(index))
+    }
+
+    pub fn rules(&self) -> Vec<String> {
+        self.schedules.iter().map(|s| s.rule.clone()).collect()
+    }
+
+    // Rules due at `now`, once per minute; the minute running at startup never fires
+    pub fn due(&mut self, now: LocalTime) -> Vec<(String, UdpCommand)> {
+        let last = self.last.replace(now);
+        if last.is_none() || last == Some(now) {
+            return Vec::new();
+        }
+        self.schedules
+            .iter()
+            .filter(|schedule| schedule.matches(now))
+            .map(|schedule| (schedule.rule.clone(), schedule.command.clone()))
+            .collect()
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        json!({
+            "local_time": local_time().describe(),
+            "schedules": self.schedules.iter().enumerate().map(|(index, schedule)| json!({
+                "index": index,
+                "rule": schedule.rule,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(weekday: u8, time: &str) -> LocalTime {
+        LocalTime {
+            weekday,
+            minute: parse_time_of_day(time).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let schedule = Schedule::parse("FRI 23:00 set profile=smooth").unwrap();
+        assert_eq!(schedule.days, 1 << 5);
+        assert_eq!(schedule.minute, 23 * 60);
+        assert!(matches!(
+            schedule.command,
+            UdpCommand::SetSmoothingProfile(SmoothingProfile::Smooth)
+        ));
+
+        let schedule = Schedule::parse("daily 04:00 blackout").unwrap();
+        assert_eq!(schedule.days, EVERY_DAY);
+        assert!(matches!(schedule.command, UdpCommand::SetBlackout(true)));
+
+        assert_eq!(parse_days("fri-mon").unwrap(), 0b110_0011);
+        assert_eq!(parse_days("MON,wed").unwrap(), 0b000_1010);
+        assert!(matches!(
+            Schedule::parse("weekdays 18:30 targets main wall").unwrap().command,
+            UdpCommand::SwitchTargetSet(name) if name == "main wall"
+        ));
+
+        assert!(matches!(
+            Schedule::parse("SAT 01:30 preset closing").unwrap().command,
+            UdpCommand::LoadPreset(name) if name == "closing"
+        ));
+
+        assert!(Schedule::parse("FRI 23:00 set profile=show").is_err());
+        assert!(Schedule::parse("FRI 25:00 blackout").is_err());
+        assert!(Schedule::parse("FUN 23:00 blackout").is_err());
+        assert!(Schedule::parse("daily 04:00 brightness 2").is_err());
+        assert!(Schedule::parse("daily 04:00").is_err());
+    }
+
+    #[test]
+    fn test_rules_fire_once_per_minute() {
+        let mut scheduler = Scheduler::new(vec![
+            Schedule::parse("FRI 23:00 effect starfall").unwrap(),
+            Schedule::parse("daily 04:00 blackout").unwrap(),
+        ]);
+        // Started during a scheduled minute: nothing until the next one
+        assert!(scheduler.due(at(5, "23:00")).is_empty());
+        assert!(scheduler.due(at(5, "23:01")).is_empty());
+
+        assert!(scheduler.due(at(6, "03:59")).is_empty());
+        let due = scheduler.due(at(6, "04:00"));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "daily 04:00 blackout");
+        assert!(scheduler.due(at(6, "04:00")).is_empty());
+
+        scheduler.remove(1).unwrap();
+        assert!(scheduler.remove(1).is_err());
+        assert!(scheduler.due(at(4, "23:00")).is_empty());
+        assert_eq!(scheduler.due(at(5, "23:00")).len(), 1);
+    }
+}
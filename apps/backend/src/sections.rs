@@ -0,0 +1,309 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const BASS_BANDS: usize = 8;
+const SHORT_TAU: f32 = 1.0;
+const LONG_TAU: f32 = 16.0;
+const CONFIDENCE_TAU: f32 = 2.0;
+const TREND_WINDOW: Duration = Duration::from_secs(4);
+// Below this mean level the room is between tracks
+const QUIET: f32 = 0.02;
+const BUILD_TREND: f32 = 1.2;
+const DROP_BASS_RATIO: f32 = 1.5;
+const MIN_DROP_BASS: f32 = 0.15;
+const BREAKDOWN_RATIO: f32 = 0.7;
+// A candidate has to last this long before the section changes, drops react faster
+const SWITCH_HOLD: Duration = Duration::from_millis(1500);
+const DROP_HOLD: Duration = Duration::from_millis(300);
+const MIN_DWELL: Duration = Duration::from_secs(3);
+const BUILD_RAMP_SECS: f32 = 8.0;
+const DROP_PUNCH_SECS: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    Intro,
+    Build,
+    Drop,
+    Breakdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionState {
+    pub section: Section,
+    pub confidence: f32,
+    pub section_secs: f32,
+    pub energy: f32,
+    pub long_energy: f32,
+    pub bass: f32,
+    pub flux: f32,
+    pub trend: f32,
+}
+
+fn ema(value: &mut f32, target: f32, dt: f32, tau: f32) {
+    *value += (target - *value) * (1.0 - (-dt / tau).exp());
+}
+
+// Classifies the running track from rolling energy statistics: rising energy without the
+// kick is a build, a bass surge is a drop, a fall well below the recent average a breakdown
+pub struct SectionDetector {
+    section: Section,
+    since: Option<Instant>,
+    candidate: Option<(Section, Instant)>,
+    last: Option<Instant>,
+    previous: Vec<f32>,
+    energy: f32,
+    long_energy: f32,
+    bass: f32,
+    long_bass: f32,
+    flux: f32,
+    history: VecDeque<(Instant, f32)>,
+    confidence: f32,
+}
+
+impl Default for SectionDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SectionDetector {
+    pub fn new() -> Self {
+        Self {
+            section: Section::Intro,
+            since: None,
+            candidate: None,
+            last: None,
+            previous: Vec::new(),
+            energy: 0.0,
+            long_energy: 0.0,
+            bass: 0.0,
+            long_bass: 0.0,
+            flux: 0.0,
+            history: VecDeque::new(),
+            confidence: 0.0,
+        }
+    }
+
+    pub fn section(&self) -> Section {
+        self.section
+    }
+
+    fn trend(&self) -> f32 {
+        match self.history.front() {
+            Some(&(_, oldest)) if oldest > QUIET => self.energy / oldest,
+            _ => 1.0,
+        }
+    }
+
+    fn classify(&self) -> Option<Section> {
+        if self.energy < QUIET {
+            Some(Section::Intro)
+        } else if self.bass > MIN_DROP_BASS && self.bass > self.long_bass * DROP_BASS_RATIO {
+            Some(Section::Drop)
+        } else if self.trend() > BUILD_TREND {
+            Some(Section::Build)
+        } else if self.long_energy > QUIET * 3.0 && self.energy < self.long_energy * BREAKDOWN_RATIO
+        {
+            Some(Section::Breakdown)
+        } else {
+            None
+        }
+    }
+
+    // Returns the new section when it changes
+    pub fn process(&mut self, spectrum: &[f32], now: Instant) -> Option<Section> {
+        if spectrum.is_empty() {
+            return None;
+        }
+        let energy = spectrum.iter().sum::<f32>() / spectrum.len() as f32;
+        let bass_bands = spectrum.len().min(BASS_BANDS);
+        let bass = spectrum[..bass_bands].iter().sum::<f32>() / bass_bands as f32;
+        let flux = if self.previous.len() == spectrum.len() {
+            spectrum
+                .iter()
+                .zip(&self.previous)
+                .map(|(value, previous)| (value - previous).max(0.0))
+                .sum::<f32>()
+                / spectrum.len() as f32
+        } else {
+            0.0
+        };
+        self.previous.clear();
+        self.previous.extend_from_slice(spectrum);
+
+        let Some(last) = self.last.replace(now) else {
+            self.energy = energy;
+            self.long_energy = energy;
+            self.bass = bass;
+            self.long_bass = bass;
+            self.since = Some(now);
+            return None;
+        };
+        let dt = now.saturating_duration_since(last).as_secs_f32();
+        ema(&mut self.energy, energy, dt, SHORT_TAU);
+        ema(&mut self.long_energy, energy, dt, LONG_TAU);
+        ema(&mut self.bass, bass, dt, SHORT_TAU);
+        ema(&mut self.long_bass, bass, dt, LONG_TAU);
+        ema(&mut self.flux, flux, dt, SHORT_TAU);
+
+        self.history.push_back((now, self.energy));
+        while self
+            .history
+            .front()
+            .is_some_and(|&(at, _)| now.saturating_duration_since(at) > TREND_WINDOW)
+        {
+            self.history.pop_front();
+        }
+
+        let candidate = self.classify();
+        let agreement = match candidate {
+            Some(section) if section == self.section => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        ema(&mut self.confidence, agreement, dt, CONFIDENCE_TAU);
+
+        let Some(section) = candidate.filter(|&section| section != self.section) else {
+            self.candidate = None;
+            return None;
+        };
+        let started = match self.candidate {
+            Some((pending, started)) if pending == section => started,
+            _ => {
+                self.candidate = Some((section, now));
+                now
+            }
+        };
+
+        let hold = if section == Section::Drop {
+            DROP_HOLD
+        } else {
+            SWITCH_HOLD
+        };
+        let dwelled = self
+            .since
+            .is_none_or(|since| now.saturating_duration_since(since) >= MIN_DWELL);
+        if now.saturating_duration_since(started) < hold || !(dwelled || section == Section::Drop) {
+            return None;
+        }
+
+        self.section = section;
+        self.since = Some(now);
+        self.candidate = None;
+        self.confidence = 0.5;
+        Some(section)
+    }
+
+    fn section_secs(&self, now: Instant) -> f32 {
+        self.since.map_or(0.0, |since| {
+            now.saturating_duration_since(since).as_secs_f32()
+        })
+    }
+
+    // Spectrum gain staging the show: builds climb, drops hit hard then settle, breakdowns
+    // back off. Scaled by confidence so a doubtful guess barely moves anything
+    pub fn staging_gain(&self, now: Instant) -> f32 {
+        let secs = self.section_secs(now);
+        let gain = match self.section {
+            Section::Intro => 1.0,
+            Section::Build => 1.0 + 0.25 * (secs / BUILD_RAMP_SECS).min(1.0),
+            Section::Drop => 1.15 + 0.2 * (1.0 - secs / DROP_PUNCH_SECS).max(0.0),
+            Section::Breakdown => 0.85,
+        };
+        1.0 + (gain - 1.0) * self.confidence.clamp(0.0, 1.0)
+    }
+
+    pub fn state(&self, now: Instant) -> SectionState {
+        SectionState {
+            section: self.section,
+            confidence: self.confidence.clamp(0.0, 1.0),
+            section_secs: self.section_secs(now),
+            energy: self.energy,
+            long_energy: self.long_energy,
+            bass: self.bass,
+            flux: self.flux,
+            trend: self.trend(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(20);
+
+    fn spectrum(bass: f32, rest: f32) -> Vec<f32> {
+        (0..64)
+            .map(|band| if band < BASS_BANDS { bass } else { rest })
+            .collect()
+    }
+
+    // Feeds `secs` of audio and returns the section at the end
+    fn play(
+        detector: &mut SectionDetector,
+        now: &mut Instant,
+        secs: u64,
+        levels: impl Fn(f32) -> (f32, f32),
+    ) -> Section {
+        let steps = secs * 1000 / STEP.as_millis() as u64;
+        for step in 0..steps {
+            let (bass, rest) = levels(step as f32 / steps as f32);
+            detector.process(&spectrum(bass, rest), *now);
+            *now += STEP;
+        }
+        detector.section()
+    }
+
+    #[test]
+    fn test_intro_build_drop_breakdown() {
+        let mut detector = SectionDetector::new();
+        let mut now = Instant::now();
+
+        assert_eq!(
+            play(&mut detector, &mut now, 10, |_| (0.1, 0.1)),
+            Section::Intro
+        );
+        assert_eq!(
+            play(&mut detector, &mut now, 10, |t| (0.1, 0.1 + 0.4 * t)),
+            Section::Build
+        );
+        assert!(detector.staging_gain(now) > 1.0);
+
+        assert_eq!(
+            play(&mut detector, &mut now, 2, |_| (0.8, 0.5)),
+            Section::Drop
+        );
+        assert_eq!(
+            play(&mut detector, &mut now, 18, |_| (0.8, 0.5)),
+            Section::Drop
+        );
+        assert!(detector.state(now).confidence > 0.3);
+
+        assert_eq!(
+            play(&mut detector, &mut now, 10, |_| (0.05, 0.1)),
+            Section::Breakdown
+        );
+        assert!(detector.staging_gain(now) < 1.0);
+    }
+
+    #[test]
+    fn test_steady_groove_keeps_section() {
+        let mut detector = SectionDetector::new();
+        let mut now = Instant::now();
+
+        // Short fluctuations never outlast the hold
+        let section = play(&mut detector, &mut now, 30, |t| {
+            let wobble = if ((t * 100.0) as u32).is_multiple_of(2) {
+                0.3
+            } else {
+                0.2
+            };
+            (wobble, 0.25)
+        });
+        assert_eq!(section, Section::Intro);
+        assert_eq!(detector.staging_gain(now), 1.0);
+    }
+}
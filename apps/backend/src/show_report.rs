@@ -0,0 +1,434 @@
+use crate::crash;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const REPORT_DIR: &str = "show_reports";
+const MAX_ERRORS: usize = 50;
+const APPLAUSE_FIRST_BAND: usize = 32;
+// Per spectrum update; applause has to last about a second to register fully
+const APPLAUSE_SMOOTHING: f32 = 0.05;
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectTime {
+    pub name: String,
+    pub seconds: f64,
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShowReport {
+    pub started: u64,
+    pub ended: u64,
+    pub reason: String,
+    pub duration_secs: f64,
+    // Most played first
+    pub effects: Vec<EffectTime>,
+    // Mean output level of the frames actually sent, 0..1
+    pub average_brightness: f32,
+    pub max_brightness: f32,
+    pub frames: u64,
+    pub led_packets: u64,
+    pub stream_packets: u64,
+    pub stream_bytes: u64,
+    pub errors: Vec<String>,
+    pub peak_applause: f32,
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Noise-like energy in the upper half of the spectrum: loud and flat, like a clapping crowd,
+// rather than the tonal peaks of music. 0..1.
+pub fn applause_level(spectrum: &[f32]) -> f32 {
+    let high = spectrum.get(APPLAUSE_FIRST_BAND..).unwrap_or_default();
+    if high.is_empty() {
+        return 0.0;
+    }
+
+    let count = high.len() as f32;
+    let mean = high.iter().map(|v| v.max(0.0)).sum::<f32>() / count;
+    if mean <= f32::EPSILON {
+        return 0.0;
+    }
+    let geometric = (high.iter().map(|v| v.max(1e-6).ln()).sum::<f32>() / count).exp();
+    (mean * (geometric / mean).min(1.0)).clamp(0.0, 1.0)
+}
+
+// Running totals for the current show; `finish` turns them into a report and starts over
+pub struct ShowRecorder {
+    started: u64,
+    since: Instant,
+    effect: Option<(&'static str, Instant)>,
+    effect_time: BTreeMap<&'static str, Duration>,
+    brightness_sum: f64,
+    max_brightness: f32,
+    frames: u64,
+    led_packets: u64,
+    stream_packets: u64,
+    stream_bytes: u64,
+    errors: Vec<String>,
+    applause: f32,
+    peak_applause: f32,
+}
+
+impl Default for ShowRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShowRecorder {
+    pub fn new() -> Self {
+        Self {
+            started: timestamp(),
+            since: Instant::now(),
+            effect: None,
+            effect_time: BTreeMap::new(),
+            brightness_sum: 0.0,
+            max_brightness: 0.0,
+            frames: 0,
+            led_packets: 0,
+            stream_packets: 0,
+            stream_bytes: 0,
+            errors: Vec::new(),
+            applause: 0.0,
+            peak_applause: 0.0,
+        }
+    }
+
+    pub fn record_spectrum(&mut self, spectrum: &[f32]) {
+        self.applause += (applause_level(spectrum) - self.applause) * APPLAUSE_SMOOTHING;
+        self.peak_applause = self.peak_applause.max(self.applause);
+    }
+
+    // One output frame after brightness, blackout and trims were applied
+    pub fn record_frame(&mut self, effect: &'static str, frame: &[u8], packets: usize) {
+        self.record_frame_at(effect, frame, packets, Instant::now());
+    }
+
+    fn record_frame_at(
+        &mut self,
+        effect: &'static str,
+        frame: &[u8],
+        packets: usize,
+        now: Instant,
+    ) {
+        self.charge_effect(now);
+        self.effect = Some((effect, now));
+
+        let level = if frame.is_empty() {
+            0.0
+        } else {
+            frame.iter().map(|&v| v as u64).sum::<u64>() as f32 / (frame.len() as f32 * 255.0)
+        };
+        self.brightness_sum += level as f64;
+        self.max_brightness = self.max_brightness.max(level);
+        self.frames += 1;
+        self.led_packets += packets as u64;
+    }
+
+    pub fn record_stream(&mut self, packets: usize, bytes: usize) {
+        self.stream_packets += packets as u64;
+        self.stream_bytes += bytes as u64;
+    }
+
+    pub fn record_error(&mut self, message: impl Into<String>) {
+        if self.errors.len() < MAX_ERRORS {
+            self.errors
+                .push(format!("[{}] {}", timestamp(), message.into()));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    fn charge_effect(&mut self, now: Instant) {
+        if let Some((name, at)) = self.effect {
+            *self.effect_time.entry(name).or_default() += now.saturating_duration_since(at);
+        }
+    }
+
+    pub fn finish(&mut self, reason: &str) -> ShowReport {
+        let report = self.report_at(reason, Instant::now());
+        *self = Self::new();
+        report
+    }
+
+    fn report_at(&mut self, reason: &str, now: Instant) -> ShowReport {
+        self.charge_effect(now);
+        self.effect = None;
+
+        let played: f64 = self.effect_time.values().map(Duration::as_secs_f64).sum();
+        let mut effects: Vec<EffectTime> = self
+            .effect_time
+            .iter()
+            .map(|(name, time)| EffectTime {
+                name: name.to_string(),
+                seconds: time.as_secs_f64(),
+                share: if played > 0.0 {
+                    time.as_secs_f64() / played
+                } else {
+                    0.0
+                },
+            })
+            .collect();
+        effects.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+
+        ShowReport {
+            started: self.started,
+            ended: timestamp(),
+            reason: reason.to_string(),
+            duration_secs: now.saturating_duration_since(self.since).as_secs_f64(),
+            effects,
+            average_brightness: if self.frames > 0 {
+                (self.brightness_sum / self.frames as f64) as f32
+            } else {
+                0.0
+            },
+            max_brightness: self.max_brightness,
+            frames: self.frames,
+            led_packets: self.led_packets,
+            stream_packets: self.stream_packets,
+            stream_bytes: self.stream_bytes,
+            errors: self.errors.clone(),
+            peak_applause: self.peak_applause,
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn render_html(report: &ShowReport) -> String {
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>DJ-4LED show report</title>\n\
+         <style>body{{font-family:sans-serif;background:#111;color:#eee}}td,th{{padding:2px 12px;text-align:left}}</style>\n\
+         </head><body>\n<h1>DJ-4LED show report</h1>"
+    );
+    let _ = writeln!(html, "<table>");
+    for (label, value) in [
+        ("Started (unix)", report.started.to_string()),
+        ("Ended (unix)", report.ended.to_string()),
+        ("Ended by", escape(&report.reason)),
+        ("Duration", format!("{:.0} s", report.duration_secs)),
+        (
+            "Brightness",
+            format!(
+                "{:.0}% average, {:.0}% max",
+                report.average_brightness * 100.0,
+                report.max_brightness * 100.0
+            ),
+        ),
+        (
+            "Packets",
+            format!(
+                "{} frames, {} Art-Net packets, {} stream packets ({} bytes)",
+                report.frames, report.led_packets, report.stream_packets, report.stream_bytes
+            ),
+        ),
+        (
+            "Peak applause",
+            format!("{:.0}%", report.peak_applause * 100.0),
+        ),
+    ] {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, value);
+    }
+    let _ = writeln!(html, "</table>\n<h2>Effects</h2>\n<table>");
+    for effect in &report.effects {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{:.0} s</td><td>{:.0}%</td></tr>",
+            escape(&effect.name),
+            effect.seconds,
+            effect.share * 100.0
+        );
+    }
+    let _ = writeln!(
+        html,
+        "</table>\n<h2>Errors ({})</h2>\n<ul>",
+        report.errors.len()
+    );
+    for error in &report.errors {
+        let _ = writeln!(html, "<li>{}</li>", escape(error));
+    }
+    let _ = writeln!(html, "</ul>\n</body></html>");
+    html
+}
+
+// Writes show-<started>-<ended>.json and a matching .html; returns the JSON path
+pub fn write_report(dir: &Path, report: &ShowReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("show-{}-{}.json", report.started, report.ended));
+    fs::write(&path, serde_json::to_string_pretty(report)?)?;
+    fs::write(path.with_extension("html"), render_html(report))?;
+    Ok(path)
+}
+
+fn read_last_report(dir: &Path) -> Option<(ShowReport, PathBuf)> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let report = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some((report, path))
+        })
+        .max_by_key(|(report, _): &(ShowReport, PathBuf)| (report.ended, report.started))
+}
+
+pub fn last_report() -> Option<(ShowReport, PathBuf)> {
+    read_last_report(Path::new(REPORT_DIR))
+}
+
+// Closes the current show and writes its report; a show with no output frames is dropped
+pub fn finish(state: &AppState, reason: &str) -> Option<PathBuf> {
+    let report = {
+        let mut show = state.show.lock();
+        if show.is_empty() {
+            return None;
+        }
+        show.finish(reason)
+    };
+
+    match write_report(Path::new(REPORT_DIR), &report) {
+        Ok(path) => {
+            crash::log(format!(
+                "📝 Show report ({:.0} s, {}) written to {}",
+                report.duration_secs,
+                reason,
+                path.display()
+            ));
+            Some(path)
+        }
+        Err(e) => {
+            crash::log(format!("⚠️ Failed to write show report: {}", e));
+            None
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+extern "C" fn request_shutdown(_: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn catch_signals() {
+    let handler = request_shutdown as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn catch_signals() {
+    crash::log(
+        "⚠️ Signals are not caught here: the show report is only written on an exit request",
+    );
+}
+
+// Same exit as SIGTERM, for the process to end itself
+pub fn request_exit() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+// SIGINT/SIGTERM (Linux and macOS) write the show report before the process exits
+pub fn install_shutdown(state: Arc<AppState>) {
+    catch_signals();
+
+    thread::spawn(move || {
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        finish(&state, "shutdown");
+        std::process::exit(0);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applause_prefers_flat_noise() {
+        let mut noise = vec![0.0; 64];
+        noise[APPLAUSE_FIRST_BAND..].fill(0.6);
+        let mut tone = vec![0.0; 64];
+        tone[40] = 1.0;
+
+        assert!((applause_level(&noise) - 0.6).abs() < 1e-4);
+        assert!(applause_level(&tone) < 0.01);
+        assert_eq!(applause_level(&[0.5; 8]), 0.0);
+    }
+
+    #[test]
+    fn test_report_splits_effect_time() {
+        let mut show = ShowRecorder::new();
+        let start = Instant::now();
+        let frame = Duration::from_millis(100);
+
+        for i in 0..30u32 {
+            let effect = if i < 20 { "rain" } else { "starfall" };
+            let level = if i == 5 { 255 } else { 51 };
+            show.record_frame_at(effect, &[level; 12], 4, start + frame * i);
+        }
+        show.record_stream(3, 3000);
+        show.record_error("Art-Net output still failing");
+
+        let report = show.report_at("stream_stopped", start + frame * 30);
+        assert_eq!(report.effects[0].name, "rain");
+        assert!((report.effects[0].seconds - 2.0).abs() < 1e-6);
+        assert!((report.effects[1].share - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(report.max_brightness, 1.0);
+        assert!((report.average_brightness - (29.0 * 0.2 + 1.0) / 30.0).abs() < 1e-5);
+        assert_eq!((report.frames, report.led_packets), (30, 120));
+        assert_eq!((report.stream_packets, report.stream_bytes), (3, 3000));
+        assert_eq!(report.errors.len(), 1);
+
+        let html = render_html(&report);
+        assert!(html.contains("<td>rain</td><td>2 s</td><td>67%</td>"));
+    }
+
+    #[test]
+    fn test_last_report_is_newest() {
+        let dir = std::env::temp_dir().join(format!("show_report_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(read_last_report(&dir).is_none());
+
+        let mut report = ShowRecorder::new().finish("shutdown");
+        report.ended = 10;
+        write_report(&dir, &report).unwrap();
+        report.ended = 20;
+        report.reason = "stream_stopped".to_string();
+        let path = write_report(&dir, &report).unwrap();
+
+        let (last, last_path) = read_last_report(&dir).unwrap();
+        assert_eq!(last, report);
+        assert_eq!(last_path, path);
+        assert!(path.with_extension("html").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
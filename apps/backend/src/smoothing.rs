@@ -0,0 +1,150 @@
+use crate::config::{BandSmoothing, BandWeights, SmoothingConfig, SmoothingProfile};
+use serde::{Deserialize, Serialize};
+
+const BASS_BANDS: usize = 8;
+const MID_BANDS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BandGroup {
+    Bass = 0,
+    Mid = 1,
+    High = 2,
+}
+
+impl BandGroup {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Bass),
+            1 => Some(Self::Mid),
+            2 => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    fn of_band(band: usize) -> Self {
+        if band < BASS_BANDS {
+            Self::Bass
+        } else if band < MID_BANDS {
+            Self::Mid
+        } else {
+            Self::High
+        }
+    }
+}
+
+fn band(config: &SmoothingConfig, group: BandGroup) -> BandSmoothing {
+    match group {
+        BandGroup::Bass => config.bass,
+        BandGroup::Mid => config.mid,
+        BandGroup::High => config.high,
+    }
+}
+
+// Scales each band by its group weight, capped at full scale
+pub fn weigh_bands(weights: &BandWeights, spectrum: &mut [f32]) {
+    for (index, value) in spectrum.iter_mut().enumerate() {
+        let weight = match BandGroup::of_band(index) {
+            BandGroup::Bass => weights.bass,
+            BandGroup::Mid => weights.mid,
+            BandGroup::High => weights.high,
+        };
+        *value = (*value * weight).min(1.0);
+    }
+}
+
+// Shared attack/release envelope applied to the spectrum before any effect sees it
+pub struct SpectrumSmoother {
+    config: SmoothingConfig,
+    levels: Vec<f32>,
+}
+
+impl SpectrumSmoother {
+    pub fn new(config: SmoothingConfig) -> Self {
+        Self {
+            config: config.normalized(),
+            levels: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SmoothingConfig {
+        &self.config
+    }
+
+    pub fn set_profile(&mut self, profile: SmoothingProfile) {
+        if profile == SmoothingProfile::Custom {
+            self.config.profile = profile;
+        } else {
+            self.config = SmoothingConfig::preset(profile);
+        }
+    }
+
+    // Tweaking a single group switches to the custom profile, starting from the current values
+    pub fn set_band(&mut self, group: BandGroup, attack: f32, release: f32) {
+        self.config.profile = SmoothingProfile::Custom;
+        *self.band_mut(group) = BandSmoothing::new(attack, release);
+    }
+
+    fn band_mut(&mut self, group: BandGroup) -> &mut BandSmoothing {
+        match group {
+            BandGroup::Bass => &mut self.config.bass,
+            BandGroup::Mid => &mut self.config.mid,
+            BandGroup::High => &mut self.config.high,
+        }
+    }
+
+    pub fn process(&mut self, spectrum: &mut [f32]) {
+        if self.levels.len() != spectrum.len() {
+            self.levels = spectrum.to_vec();
+            return;
+        }
+
+        for (index, (level, value)) in self.levels.iter_mut().zip(spectrum.iter_mut()).enumerate() {
+            let smoothing = band(&self.config, BandGroup::of_band(index));
+            let rate = if *value > *level {
+                smoothing.attack
+            } else {
+                smoothing.release
+            };
+            *level += (*value - *level) * rate;
+            *value = *level;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attack_and_release_per_group() {
+        let mut smoother = SpectrumSmoother::new(SmoothingConfig::default());
+        smoother.set_band(BandGroup::Bass, 1.0, 0.5);
+        smoother.set_band(BandGroup::High, 0.5, 1.0);
+        assert_eq!(smoother.config().profile, SmoothingProfile::Custom);
+
+        let mut spectrum = vec![0.0; 64];
+        smoother.process(&mut spectrum);
+
+        let mut spectrum = vec![1.0; 64];
+        smoother.process(&mut spectrum);
+        assert_eq!(spectrum[0], 1.0);
+        assert_eq!(spectrum[63], 0.5);
+
+        let mut spectrum = vec![0.0; 64];
+        smoother.process(&mut spectrum);
+        assert_eq!(spectrum[0], 0.5);
+        assert_eq!(spectrum[63], 0.0);
+    }
+
+    #[test]
+    fn test_named_profile_ignores_stored_values() {
+        let mut config = SmoothingConfig::preset(SmoothingProfile::Smooth);
+        config.bass = BandSmoothing::new(0.9, 0.9);
+        let smoother = SpectrumSmoother::new(config);
+        assert_eq!(
+            smoother.config(),
+            &SmoothingConfig::preset(SmoothingProfile::Smooth)
+        );
+    }
+}
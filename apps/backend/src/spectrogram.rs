@@ -0,0 +1,246 @@
+use crate::config::SpectrogramConfig;
+use anyhow::{anyhow, Result};
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const BANDS: usize = 64;
+// Queries answer at most this many columns so the reply fits one datagram
+pub const MAX_QUERY_COLUMNS: usize = 128;
+
+// Rolling history of the processed spectrum, one column of 64 band levels per tick.
+// Each column keeps the peak of every band since the previous one so short hits survive.
+pub struct Spectrogram {
+    columns: VecDeque<[u8; BANDS]>,
+    capacity: usize,
+    columns_per_second: u32,
+    interval: Duration,
+    pending: Option<[u8; BANDS]>,
+    next_column: Option<Instant>,
+}
+
+impl Spectrogram {
+    pub fn new(config: &SpectrogramConfig) -> Self {
+        let columns_per_second = config.columns_per_second.max(1);
+        let capacity = (config.minutes.max(0.0) * 60.0 * columns_per_second as f32) as usize;
+
+        Self {
+            columns: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1),
+            columns_per_second,
+            interval: Duration::from_secs_f64(1.0 / columns_per_second as f64),
+            pending: None,
+            next_column: None,
+        }
+    }
+
+    pub fn record(&mut self, spectrum: &[f32]) {
+        self.record_at(spectrum, Instant::now());
+    }
+
+    fn record_at(&mut self, spectrum: &[f32], now: Instant) {
+        let pending = self.pending.get_or_insert([0; BANDS]);
+        for (level, &value) in pending.iter_mut().zip(spectrum) {
+            *level = (*level).max((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+
+        let due = self.next_column.unwrap_or(now);
+        if now < due {
+            return;
+        }
+        // Catch up from the schedule, unless the renderer stalled for more than a column
+        self.next_column = Some(if now - due < self.interval {
+            due + self.interval
+        } else {
+            now + self.interval
+        });
+
+        if self.columns.len() == self.capacity {
+            self.columns.pop_front();
+        }
+        self.columns.extend(self.pending.take());
+    }
+
+    pub fn recorded_secs(&self) -> f32 {
+        self.columns.len() as f32 / self.columns_per_second as f32
+    }
+
+    // Last `seconds` of history (0 = all of it) max-pooled down to at most `width` columns
+    pub fn query(&self, seconds: u32, width: usize) -> serde_json::Value {
+        let wanted = match seconds {
+            0 => self.columns.len(),
+            seconds => {
+                (seconds as usize * self.columns_per_second as usize).min(self.columns.len())
+            }
+        };
+        let recent: Vec<&[u8; BANDS]> = self
+            .columns
+            .iter()
+            .skip(self.columns.len() - wanted)
+            .collect();
+        let width = width.clamp(1, MAX_QUERY_COLUMNS).min(recent.len());
+
+        let columns: Vec<Vec<u8>> = (0..width)
+            .map(|i| {
+                let span = &recent[i * recent.len() / width..(i + 1) * recent.len() / width];
+                (0..BANDS)
+                    .map(|band| span.iter().map(|column| column[band]).max().unwrap_or(0))
+                    .collect()
+            })
+            .collect();
+
+        serde_json::json!({
+            "recorded_secs": self.recorded_secs(),
+            "span_secs": wanted as f32 / self.columns_per_second as f32,
+            "columns_per_second": self.columns_per_second,
+            "bands": BANDS,
+            "columns": columns,
+        })
+    }
+
+    // Time runs left to right, lowest band at the bottom
+    pub fn export_png(&self, path: &Path) -> Result<()> {
+        if self.columns.is_empty() {
+            return Err(anyhow!("Spectrogram is empty"));
+        }
+
+        let width = self.columns.len();
+        let mut rgb = Vec::with_capacity(width * BANDS * 3);
+        for band in (0..BANDS).rev() {
+            for column in &self.columns {
+                rgb.extend_from_slice(&heat(column[band]));
+            }
+        }
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, encode_png(width as u32, BANDS as u32, &rgb)?)?;
+        Ok(())
+    }
+}
+
+// Black → red → yellow → white
+fn heat(level: u8) -> [u8; 3] {
+    let level = level as u16 * 3;
+    [
+        level.min(255) as u8,
+        level.saturating_sub(255).min(255) as u8,
+        level.saturating_sub(510) as u8,
+    ]
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    out.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+// Minimal 8-bit RGB PNG, unfiltered rows
+pub fn encode_png(width: u32, height: u32, rgb: &[u8]) -> Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in rgb.chunks(width as usize * 3) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let pixels = encoder.finish()?;
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut png, b"IHDR", &header);
+    png_chunk(&mut png, b"IDAT", &pixels);
+    png_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(minutes: f32) -> SpectrogramConfig {
+        SpectrogramConfig {
+            minutes,
+            columns_per_second: 10,
+        }
+    }
+
+    #[test]
+    fn test_columns_keep_peaks_and_roll() {
+        let mut spectrogram = Spectrogram::new(&config(0.1));
+        let start = Instant::now();
+        let frame = Duration::from_millis(25);
+
+        for i in 0..400u32 {
+            let level = if i % 4 == 1 { 1.0 } else { 0.2 };
+            spectrogram.record_at(&[level; BANDS], start + frame * i);
+        }
+
+        assert_eq!(spectrogram.columns.len(), 60);
+        assert!(spectrogram.columns.iter().all(|column| column[0] == 255));
+        assert_eq!(spectrogram.recorded_secs(), 6.0);
+    }
+
+    #[test]
+    fn test_query_pools_recent_columns() {
+        let mut spectrogram = Spectrogram::new(&config(1.0));
+        let start = Instant::now();
+        for i in 0..100u32 {
+            let mut spectrum = [0.0; BANDS];
+            spectrum[3] = i as f32 / 100.0;
+            spectrogram.record_at(&spectrum, start + Duration::from_millis(100) * i);
+        }
+
+        let summary = spectrogram.query(5, 10);
+        assert_eq!(summary["span_secs"], 5.0);
+        let columns = summary["columns"].as_array().unwrap();
+        assert_eq!(columns.len(), 10);
+        assert_eq!(columns[9][3], 252);
+        assert_eq!(columns[0][0], 0);
+
+        assert_eq!(
+            spectrogram.query(0, 500)["columns"]
+                .as_array()
+                .unwrap()
+                .len(),
+            100
+        );
+        assert_eq!(
+            Spectrogram::new(&config(1.0)).query(0, 10)["columns"],
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn test_export_png() {
+        let path = std::env::temp_dir().join(format!("spectrogram_{}.png", std::process::id()));
+        let mut spectrogram = Spectrogram::new(&config(1.0));
+        assert!(spectrogram.export_png(&path).is_err());
+
+        spectrogram.record(&[0.5; BANDS]);
+        spectrogram.export_png(&path).unwrap();
+        let png = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 1);
+        assert_eq!(
+            u32::from_be_bytes(png[20..24].try_into().unwrap()),
+            BANDS as u32
+        );
+        assert!(png.ends_with(&[0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82]));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::config::{EdgeStripConfig, WallEdge};
+
+// Ambilight-style surround: every edge strip shows a blurred, averaged copy of the wall
+// edge it frames, so it follows whatever effect is playing
+pub struct EdgeSpill {
+    strips: Vec<EdgeStripConfig>,
+    // Size of the wall frames sampled
+    width: usize,
+    height: usize,
+    frames: Vec<Vec<u8>>,
+    // Unblurred strip colors, reused between frames
+    sampled: Vec<[f32; 3]>,
+}
+
+impl EdgeSpill {
+    pub fn new(strips: Vec<EdgeStripConfig>, width: usize, height: usize) -> Self {
+        let frames = strips
+            .iter()
+            .map(|strip| vec![0; strip.pixels * 3])
+            .collect();
+        Self {
+            strips,
+            width,
+            height,
+            frames,
+            sampled: Vec::new(),
+        }
+    }
+
+    pub fn strips(&self) -> &[EdgeStripConfig] {
+        &self.strips
+    }
+
+    // Strip frames for the given wall frame, in the order of the config
+    pub fn render(&mut self, wall: &[u8]) -> &[Vec<u8>] {
+        for (strip, frame) in self.strips.iter().zip(self.frames.iter_mut()) {
+            sample_edge(wall, (self.width, self.height), strip, &mut self.sampled);
+            blur(&self.sampled, strip.blur, strip.gain, frame);
+        }
+        &self.frames
+    }
+}
+
+// Average color of the block of wall pixels behind each strip pixel
+fn sample_edge(
+    wall: &[u8],
+    (width, height): (usize, usize),
+    strip: &EdgeStripConfig,
+    sampled: &mut Vec<[f32; 3]>,
+) {
+    let horizontal = matches!(strip.edge, WallEdge::Top | WallEdge::Bottom);
+    let (along, across) = if horizontal {
+        (width, height)
+    } else {
+        (height, width)
+    };
+    let depth = strip.depth.clamp(1, across);
+
+    sampled.clear();
+    for i in 0..strip.pixels {
+        let index = if strip.reversed {
+            strip.pixels - 1 - i
+        } else {
+            i
+        };
+        let start = index * along / strip.pixels;
+        let end = ((index + 1) * along / strip.pixels).max(start + 1);
+
+        let mut sum = [0.0f32; 3];
+        for a in start..end {
+            for d in 0..depth {
+                let (x, y) = match strip.edge {
+                    WallEdge::Top => (a, d),
+                    WallEdge::Bottom => (a, height - 1 - d),
+                    WallEdge::Left => (d, a),
+                    WallEdge::Right => (width - 1 - d, a),
+                };
+                let offset = (y * width + x) * 3;
+                for c in 0..3 {
+                    sum[c] += wall[offset + c] as f32;
+                }
+            }
+        }
+        let count = ((end - start) * depth) as f32;
+        sampled.push(sum.map(|c| c / count));
+    }
+}
+
+fn blur(sampled: &[[f32; 3]], radius: usize, gain: f32, frame: &mut [u8]) {
+    for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+        let start = i.saturating_sub(radius);
+        let end = (i + radius + 1).min(sampled.len());
+        let mut sum = [0.0f32; 3];
+        for color in &sampled[start..end] {
+            for c in 0..3 {
+                sum[c] += color[c];
+            }
+        }
+        let count = (end - start) as f32;
+        for c in 0..3 {
+            pixel[c] = (sum[c] / count * gain).min(255.0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use led_visualizer::{FRAME_HEIGHT, FRAME_WIDTH};
+
+    fn strip(edge: WallEdge, pixels: usize) -> EdgeStripConfig {
+        EdgeStripConfig {
+            name: "test".to_string(),
+            edge,
+            pixels,
+            blur: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_strips_follow_their_edge() {
+        // Red left half, blue bottom rows
+        let mut wall = vec![0u8; FRAME_WIDTH * FRAME_HEIGHT * 3];
+        for y in 0..FRAME_HEIGHT {
+            for x in 0..FRAME_WIDTH {
+                let offset = (y * FRAME_WIDTH + x) * 3;
+                if x < FRAME_WIDTH / 2 {
+                    wall[offset] = 200;
+                }
+                if y >= FRAME_HEIGHT - 8 {
+                    wall[offset + 2] = 100;
+                }
+            }
+        }
+
+        let mut spill = EdgeSpill::new(
+            vec![
+                strip(WallEdge::Top, 4),
+                EdgeStripConfig {
+                    reversed: true,
+                    gain: 2.0,
+                    ..strip(WallEdge::Bottom, 4)
+                },
+                strip(WallEdge::Right, 2),
+                EdgeStripConfig {
+                    blur: 1,
+                    ..strip(WallEdge::Top, 4)
+                },
+            ],
+            FRAME_WIDTH,
+            FRAME_HEIGHT,
+        );
+        let frames = spill.render(&wall);
+
+        assert_eq!(frames[0], vec![200, 0, 0, 200, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            frames[1],
+            vec![0, 0, 200, 0, 0, 200, 255, 0, 200, 255, 0, 200]
+        );
+        assert_eq!(frames[2], vec![0, 0, 0, 0, 0, 12]);
+        assert_eq!(&frames[3][3..9], &[133, 0, 0, 66, 0, 0]);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::config::{SplashAnimation, SplashConfig};
+use crate::crash;
+use crate::hud::{glyph, CHAR_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use anyhow::{anyhow, bail, Result};
+use flate2::read::ZlibDecoder;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const TEXT_LEVEL: f32 = 60.0;
+// Half width of the sweeping band, in pixels
+const SWEEP_WIDTH: f32 = 16.0;
+// Share of the animation spent fading out to the program
+const FADE_OUT: f32 = 0.2;
+const MAX_TEXT_SCALE: usize = 4;
+const WIPE_COLORS: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+
+enum Source {
+    Animation(SplashAnimation),
+    Frames(Vec<Vec<u8>>),
+}
+
+// Boot animation shown once the LED output is up, so the crew sees the wall is alive
+pub struct Splash {
+    source: Source,
+    // Size of the wall frames played
+    width: usize,
+    height: usize,
+    text: String,
+    duration: Duration,
+    started: Option<Instant>,
+}
+
+impl Splash {
+    // None when disabled; unreadable PNG frames fall back to the built-in animation
+    pub fn load(config: &SplashConfig, width: usize, height: usize) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let mut source = Source::Animation(config.animation);
+        let mut duration = Duration::from_secs_f32(config.duration_secs.max(0.0));
+        if let Some(dir) = &config.frames_dir {
+            match load_frames(Path::new(dir), width, height) {
+                Ok(frames) => {
+                    duration = Duration::from_secs_f32(frames.len() as f32 / config.fps.max(1.0));
+                    source = Source::Frames(frames);
+                }
+                Err(e) => crash::log(format!("⚠️ Splash frames not loaded from {}: {}", dir, e)),
+            }
+        }
+        Some(Self {
+            source,
+            width,
+            height,
+            text: config.text.to_uppercase(),
+            duration,
+            started: None,
+        })
+    }
+
+    // The splash frame for `now`, None once it has played; the clock starts on the first call
+    pub fn frame(&mut self, now: Instant) -> Option<Vec<u8>> {
+        let started = *self.started.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(started);
+        if elapsed >= self.duration {
+            return None;
+        }
+        let progress = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        Some(match &self.source {
+            Source::Animation(SplashAnimation::LogoSweep) => {
+                logo_sweep(&self.text, progress, self.width, self.height)
+            }
+            Source::Animation(SplashAnimation::ColorWipe) => {
+                color_wipe(progress, self.width, self.height)
+            }
+            Source::Frames(frames) => {
+                frames[((progress * frames.len() as f32) as usize).min(frames.len() - 1)].clone()
+            }
+        })
+    }
+}
+
+fn logo_sweep(text: &str, progress: f32, frame_width: usize, frame_height: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; frame_width * frame_height * 3];
+    let chars = text.chars().count().max(1);
+    let scale = (frame_width.saturating_sub(8) / (chars * CHAR_ADVANCE)).clamp(1, MAX_TEXT_SCALE);
+    let width = (chars * CHAR_ADVANCE - 1) * scale;
+    let left = frame_width.saturating_sub(width) / 2;
+    let top = frame_height.saturating_sub(GLYPH_HEIGHT * scale) / 2;
+
+    let sweep_x = -SWEEP_WIDTH + progress * (frame_width as f32 + 2.0 * SWEEP_WIDTH);
+    let fade = ((1.0 - progress) / FADE_OUT).min(1.0);
+
+    for (col, c) in text.chars().enumerate() {
+        for (dy, bits) in glyph(c).iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> dx) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        let x = left + (col * CHAR_ADVANCE + dx) * scale + sx;
+                        let y = top + dy * scale + sy;
+                        if x >= frame_width || y >= frame_height {
+                            continue;
+                        }
+                        let band = (1.0 - (x as f32 - sweep_x).abs() / SWEEP_WIDTH).max(0.0);
+                        let level = (TEXT_LEVEL + (255.0 - TEXT_LEVEL) * band) * fade;
+                        let idx = (y * frame_width + x) * 3;
+                        frame[idx..idx + 3].fill(level as u8);
+                    }
+                }
+            }
+        }
+    }
+    frame
+}
+
+// Each color wipes in from the left over the previous one
+fn color_wipe(progress: f32, width: usize, height: usize) -> Vec<u8> {
+    let stages = WIPE_COLORS.len();
+    let stage = ((progress * stages as f32) as usize).min(stages - 1);
+    let edge = ((progress * stages as f32 - stage as f32) * width as f32) as usize;
+    let under = stage
+        .checked_sub(1)
+        .map_or([0; 3], |previous| WIPE_COLORS[previous]);
+
+    let mut frame = Vec::with_capacity(width * height * 3);
+    for _ in 0..height {
+        for x in 0..width {
+            frame.extend_from_slice(if x < edge {
+                &WIPE_COLORS[stage]
+            } else {
+                &under
+            });
+        }
+    }
+    frame
+}
+
+fn load_frames(dir: &Path, frame_width: usize, frame_height: usize) -> Result<Vec<Vec<u8>>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        bail!("No PNG frames");
+    }
+
+    paths
+        .iter()
+        .map(|path| {
+            let (width, height, rgb) = decode_png(&fs::read(path)?)?;
+            if (width, height) != (frame_width, frame_height) {
+                bail!(
+                    "{} is {}x{}, frames must be {}x{}",
+                    path.display(),
+                    width,
+                    height,
+                    frame_width,
+                    frame_height
+                );
+            }
+            Ok(rgb)
+        })
+        .collect()
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// Undoes one scanline's filter in place, `bpp` bytes per pixel
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], bpp: usize) -> Result<()> {
+    for i in 0..row.len() {
+        let left = if i >= bpp { row[i - bpp] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= bpp { previous[i - bpp] } else { 0 };
+        row[i] = row[i].wrapping_add(match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => bail!("Unknown PNG filter {}", filter),
+        });
+    }
+    Ok(())
+}
+
+// 8-bit RGB or RGBA, non-interlaced; alpha is applied over black
+fn decode_png(data: &[u8]) -> Result<(usize, usize, Vec<u8>)> {
+    let mut rest = data
+        .strip_prefix(b"\x89PNG\r\n\x1a\n")
+        .ok_or_else(|| anyhow!("Not a PNG file"))?;
+    let mut header = None;
+    let mut compressed = Vec::new();
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        let kind = &rest[4..8];
+        let chunk = rest
+            .get(8..8 + length)
+            .ok_or_else(|| anyhow!("Truncated PNG chunk"))?;
+        match kind {
+            b"IHDR" if length == 13 => header = Some(chunk.to_vec()),
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = &rest[(12 + length).min(rest.len())..];
+    }
+
+    let header = header.ok_or_else(|| anyhow!("Missing PNG header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into()?) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into()?) as usize;
+    let bpp = match (header[8], header[9], header[12]) {
+        (8, 2, 0) => 3,
+        (8, 6, 0) => 4,
+        _ => bail!("Only 8-bit RGB or RGBA non-interlaced PNG frames are supported"),
+    };
+
+    let stride = width * bpp;
+    let mut raw = Vec::new();
+    ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut raw)?;
+    if raw.len() < (stride + 1) * height {
+        bail!("Truncated PNG image data");
+    }
+
+    let mut pixels = vec![0u8; stride * height];
+    let mut previous = vec![0u8; stride];
+    for (y, line) in raw.chunks_exact(stride + 1).take(height).enumerate() {
+        let row = &mut pixels[y * stride..(y + 1) * stride];
+        row.copy_from_slice(&line[1..]);
+        unfilter(line[0], row, &previous, bpp)?;
+        previous.copy_from_slice(row);
+    }
+
+    let rgb = pixels
+        .chunks_exact(bpp)
+        .flat_map(|pixel| {
+            let alpha = if bpp == 4 { pixel[3] as u16 } else { 255 };
+            [0, 1, 2].map(|c| (pixel[c] as u16 * alpha / 255) as u8)
+        })
+        .collect();
+    Ok((width, height, rgb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrogram::encode_png;
+    use led_visualizer::{FRAME_HEIGHT, FRAME_WIDTH};
+
+    #[test]
+    fn test_splash_plays_once() {
+        let mut splash = Splash::load(
+            &SplashConfig {
+                animation: SplashAnimation::ColorWipe,
+                duration_secs: 4.0,
+                ..SplashConfig::default()
+            },
+            FRAME_WIDTH,
+            FRAME_HEIGHT,
+        )
+        .unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let pixel = |frame: &[u8], x: usize| [frame[x * 3], frame[x * 3 + 1], frame[x * 3 + 2]];
+
+        let frame = splash.frame(at(0)).unwrap();
+        assert_eq!(pixel(&frame, 0), [0, 0, 0]);
+        // Halfway through green, over the red
+        let frame = splash.frame(at(1500)).unwrap();
+        assert_eq!(pixel(&frame, 10), [0, 255, 0]);
+        assert_eq!(pixel(&frame, 100), [255, 0, 0]);
+        assert_eq!(pixel(&splash.frame(at(3999)).unwrap(), 0), [255; 3]);
+        assert!(splash.frame(at(4000)).is_none());
+
+        let disabled = SplashConfig {
+            enabled: false,
+            ..SplashConfig::default()
+        };
+        assert!(Splash::load(&disabled, FRAME_WIDTH, FRAME_HEIGHT).is_none());
+    }
+
+    #[test]
+    fn test_logo_is_centered_and_fades() {
+        let lit = |frame: &[u8]| frame.iter().filter(|&&v| v > 0).count();
+        let start = logo_sweep("DJ-4LED", 0.0, FRAME_WIDTH, FRAME_HEIGHT);
+        assert!(lit(&start) > 0);
+        // Nothing in the top and bottom rows
+        assert_eq!(lit(&start[..FRAME_WIDTH * 3 * 40]), 0);
+        assert_eq!(lit(&start[FRAME_WIDTH * 3 * 90..]), 0);
+        assert_eq!(
+            lit(&logo_sweep("DJ-4LED", 1.0, FRAME_WIDTH, FRAME_HEIGHT)),
+            0
+        );
+        // A short screen still gets the logo, cut rather than out of bounds
+        assert!(lit(&logo_sweep("DJ-4LED", 0.3, 32, 4)) > 0);
+    }
+
+    #[test]
+    fn test_png_frames_decode() {
+        let rgb: Vec<u8> = (0..FRAME_WIDTH * FRAME_HEIGHT * 3)
+            .map(|i| (i % 253) as u8)
+            .collect();
+        let png = encode_png(FRAME_WIDTH as u32, FRAME_HEIGHT as u32, &rgb).unwrap();
+        assert_eq!(decode_png(&png).unwrap(), (FRAME_WIDTH, FRAME_HEIGHT, rgb));
+
+        // Sub then Up filtered rows of a 2x2 RGB image
+        let mut row = vec![10, 20, 30, 5, 5, 5];
+        unfilter(1, &mut row, &[0; 6], 3).unwrap();
+        assert_eq!(row, [10, 20, 30, 15, 25, 35]);
+        let mut below = vec![1, 1, 1, 1, 1, 1];
+        unfilter(2, &mut below, &row, 3).unwrap();
+        assert_eq!(below, [11, 21, 31, 16, 26, 36]);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::config::StatsConfig;
+use crate::crash;
+use crate::AppState;
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MINUTE_SECS: u64 = 60;
+const DAY_SECS: u64 = 24 * 60 * 60;
+// Keeps a query response within one UDP packet
+pub const MAX_POINTS: u16 = 300;
+const PRUNE_EVERY_MINUTES: u64 = 60;
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// One row of the database, or several merged by a query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinuteStats {
+    // Unix time the minute (or the query bucket) starts at
+    pub minute: u64,
+    pub fps: f32,
+    pub packets_sent: u64,
+    pub packets_failed: u64,
+    // Failed over attempted packets, 0..1
+    pub packet_loss: f32,
+    // Mean and peak output level of the frames sent, 0..1
+    pub brightness: f32,
+    pub max_brightness: f32,
+    pub audio_level: f32,
+    pub errors: u64,
+}
+
+fn packet_loss(sent: u64, failed: u64) -> f32 {
+    if sent + failed == 0 {
+        0.0
+    } else {
+        failed as f32 / (sent + failed) as f32
+    }
+}
+
+// Totals for the minute in progress, fed by the LED and render threads
+#[derive(Default)]
+pub struct StatsRecorder {
+    frames: u64,
+    brightness_sum: f64,
+    max_brightness: f32,
+    packets_sent: u64,
+    packets_failed: u64,
+    audio_sum: f64,
+    audio_updates: u64,
+    errors: u64,
+}
+
+impl StatsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // One output frame after brightness and blackout, with the packets it took
+    pub fn record_frame(&mut self, frame: &[u8], sent: u64, failed: u64) {
+        let level = if frame.is_empty() {
+            0.0
+        } else {
+            frame.iter().map(|&v| v as u64).sum::<u64>() as f32 / (frame.len() as f32 * 255.0)
+        };
+        self.frames += 1;
+        self.brightness_sum += level as f64;
+        self.max_brightness = self.max_brightness.max(level);
+        self.packets_sent += sent;
+        self.packets_failed += failed;
+    }
+
+    pub fn record_audio(&mut self, spectrum: &[f32]) {
+        if spectrum.is_empty() {
+            return;
+        }
+        self.audio_sum += (spectrum.iter().sum::<f32>() / spectrum.len() as f32) as f64;
+        self.audio_updates += 1;
+    }
+
+    pub fn record_error(&mut self) {
+        self.errors += 1;
+    }
+
+    // Closes the minute starting at `minute` and starts the next one from zero
+    pub fn take_minute(&mut self, minute: u64, elapsed: Duration) -> MinuteStats {
+        let totals = std::mem::take(self);
+        MinuteStats {
+            minute,
+            fps: totals.frames as f32 / elapsed.as_secs_f32().max(1.0),
+            packets_sent: totals.packets_sent,
+            packets_failed: totals.packets_failed,
+            packet_loss: packet_loss(totals.packets_sent, totals.packets_failed),
+            brightness: (totals.brightness_sum / totals.frames.max(1) as f64) as f32,
+            max_brightness: totals.max_brightness,
+            audio_level: (totals.audio_sum / totals.audio_updates.max(1) as f64) as f32,
+            errors: totals.errors,
+        }
+    }
+}
+
+pub struct StatsDb {
+    connection: Connection,
+}
+
+impl StatsDb {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS minutes (
+                minute INTEGER PRIMARY KEY,
+                fps REAL NOT NULL,
+                packets_sent INTEGER NOT NULL,
+                packets_failed INTEGER NOT NULL,
+                brightness REAL NOT NULL,
+                max_brightness REAL NOT NULL,
+                audio_level REAL NOT NULL,
+                errors INTEGER NOT NULL
+            );",
+        )?;
+        Ok(Self { connection })
+    }
+
+    pub fn insert(&self, stats: &MinuteStats) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO minutes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                stats.minute as i64,
+                stats.fps,
+                stats.packets_sent as i64,
+                stats.packets_failed as i64,
+                stats.brightness,
+                stats.max_brightness,
+                stats.audio_level,
+                stats.errors as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Deletes the minutes before `before`, returns how many went
+    pub fn prune(&self, before: u64) -> Result<usize> {
+        Ok(self
+            .connection
+            .execute("DELETE FROM minutes WHERE minute < ?1", [before as i64])?)
+    }
+
+    // Minutes in from..to merged into at most `points` buckets of whole minutes
+    pub fn query(&self, from: u64, to: u64, points: u16) -> Result<Vec<MinuteStats>> {
+        let points = points.clamp(1, MAX_POINTS) as u64;
+        let span = to.saturating_sub(from);
+        let bucket = span.div_ceil(points).div_ceil(MINUTE_SECS).max(1) * MINUTE_SECS;
+
+        let mut statement = self.connection.prepare(
+            "SELECT MIN(minute), AVG(fps), SUM(packets_sent), SUM(packets_failed),
+                    AVG(brightness), MAX(max_brightness), AVG(audio_level), SUM(errors)
+             FROM minutes WHERE minute >= ?1 AND minute < ?2
+             GROUP BY (minute - ?1) / ?3 ORDER BY 1",
+        )?;
+        let rows = statement.query_map(params![from as i64, to as i64, bucket as i64], |row| {
+            let packets_sent = row.get::<_, i64>(2)? as u64;
+            let packets_failed = row.get::<_, i64>(3)? as u64;
+            Ok(MinuteStats {
+                minute: row.get::<_, i64>(0)? as u64,
+                fps: row.get::<_, f64>(1)? as f32,
+                packets_sent,
+                packets_failed,
+                packet_loss: packet_loss(packets_sent, packets_failed),
+                brightness: row.get::<_, f64>(4)? as f32,
+                max_brightness: row.get::<_, f64>(5)? as f32,
+                audio_level: row.get::<_, f64>(6)? as f32,
+                errors: row.get::<_, i64>(7)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+// Writes the recorder out at every minute boundary and applies the retention policy
+pub fn run(state: Arc<AppState>, config: StatsConfig) {
+    match StatsDb::open(&config.path) {
+        Ok(db) => *state.stats_db.lock() = Some(db),
+        Err(e) => {
+            crash::log(format!("❌ Statistics database disabled: {}", e));
+            return;
+        }
+    }
+    let retention = config.retention_days as u64 * DAY_SECS;
+
+    let mut minute = timestamp() / MINUTE_SECS * MINUTE_SECS;
+    let mut started = Instant::now();
+    for written in 0u64.. {
+        let next = minute + MINUTE_SECS;
+        std::thread::sleep(Duration::from_secs(next.saturating_sub(timestamp())));
+
+        let stats = state.stats.lock().take_minute(minute, started.elapsed());
+        started = Instant::now();
+        minute = timestamp() / MINUTE_SECS * MINUTE_SECS;
+
+        let db = state.stats_db.lock();
+        let Some(db) = db.as_ref() else {
+            return;
+        };
+        if let Err(e) = db.insert(&stats) {
+            crash::log(format!("⚠️ Failed to save statistics: {}", e));
+        }
+        if written % PRUNE_EVERY_MINUTES == 0 {
+            if let Err(e) = db.prune(timestamp().saturating_sub(retention)) {
+                crash::log(format!("⚠️ Failed to prune statistics: {}", e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_closes_minutes() {
+        let mut recorder = StatsRecorder::new();
+        recorder.record_frame(&[255, 255, 0, 0], 30, 10);
+        recorder.record_frame(&[0, 0, 0, 0], 40, 0);
+        recorder.record_audio(&[0.2, 0.4]);
+        recorder.record_error();
+
+        let stats = recorder.take_minute(120, Duration::from_secs(2));
+        assert_eq!(stats.fps, 1.0);
+        assert_eq!((stats.packets_sent, stats.packets_failed), (70, 10));
+        assert!((stats.packet_loss - 0.125).abs() < 1e-6);
+        assert!((stats.brightness - 0.25).abs() < 1e-6);
+        assert_eq!(stats.max_brightness, 0.5);
+        assert!((stats.audio_level - 0.3).abs() < 1e-6);
+        assert_eq!(stats.errors, 1);
+
+        assert_eq!(recorder.take_minute(180, Duration::from_secs(60)).errors, 0);
+    }
+
+    #[test]
+    fn test_query_merges_minutes_into_buckets() {
+        let db = StatsDb::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        for i in 0..10u64 {
+            let mut recorder = StatsRecorder::new();
+            recorder.record_frame(&[0], 100, i);
+            let mut stats = recorder.take_minute(6000 + i * 60, Duration::from_secs(60));
+            stats.fps = i as f32;
+            db.insert(&stats).unwrap();
+        }
+
+        let all = db.query(6000, 6600, 300).unwrap();
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[3].minute, 6180);
+
+        let merged = db.query(6000, 6600, 2).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[1].minute, 6300);
+        assert_eq!(merged[1].fps, 7.0);
+        assert_eq!(
+            (merged[1].packets_sent, merged[1].packets_failed),
+            (500, 35)
+        );
+
+        assert_eq!(db.prune(6300).unwrap(), 5);
+        assert_eq!(db.query(6000, 6600, 300).unwrap().len(), 5);
+    }
+}
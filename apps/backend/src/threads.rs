@@ -0,0 +1,123 @@
+use crate::config::PerformanceConfig;
+use crate::crash;
+use anyhow::Result;
+
+// Nice value of low-priority threads, high enough that a DAW's audio threads win
+const LOW_PRIORITY_NICE: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Render,
+    Led,
+    Audio,
+}
+
+impl Role {
+    fn name(self) -> &'static str {
+        match self {
+            Role::Render => "render",
+            Role::Led => "LED",
+            Role::Audio => "audio",
+        }
+    }
+
+    fn core(self, config: &PerformanceConfig) -> Option<usize> {
+        match self {
+            Role::Render => config.render_core,
+            Role::Led => config.led_core,
+            Role::Audio => config.audio_core,
+        }
+    }
+}
+
+// Sizes rayon's global pool, 0 meaning one worker per core. Must run before anything
+// touches rayon, the pool can only be built once.
+pub fn init_pool(config: &PerformanceConfig) -> Result<()> {
+    let low_priority = config.low_priority;
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.thread_pool_size)
+        .thread_name(|index| format!("render-pool-{}", index))
+        .start_handler(move |_| {
+            if low_priority {
+                let _ = lower_priority();
+            }
+        })
+        .build_global()?;
+    Ok(())
+}
+
+// Applies the configured pinning and priority to the calling thread. Failures are
+// logged, a thread that can't be pinned still runs.
+pub fn setup(role: Role, config: &PerformanceConfig) {
+    if let Some(core) = role.core(config) {
+        match pin_to_core(core) {
+            Ok(()) => crash::log(format!("📌 {} thread pinned to core {}", role.name(), core)),
+            Err(e) => crash::log(format!(
+                "⚠️ Could not pin {} thread to core {}: {}",
+                role.name(),
+                core,
+                e
+            )),
+        }
+    }
+    if config.low_priority {
+        if let Err(e) = lower_priority() {
+            crash::log(format!(
+                "⚠️ Could not lower {} thread priority: {}",
+                role.name(),
+                e
+            ));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_core(core: usize) -> Result<()> {
+    if core >= libc::CPU_SETSIZE as usize {
+        anyhow::bail!("no such core");
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_core(_core: usize) -> Result<()> {
+    anyhow::bail!("core pinning is only supported on Linux")
+}
+
+// On Linux this only affects the calling thread, elsewhere the whole process
+#[cfg(unix)]
+fn lower_priority() -> Result<()> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY_NICE) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lower_priority() -> Result<()> {
+    anyhow::bail!("low priority mode is only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_role_uses_its_core() {
+        let mut config = crate::config::Config::default().performance;
+        config.render_core = Some(1);
+        config.audio_core = Some(3);
+
+        assert_eq!(Role::Render.core(&config), Some(1));
+        assert_eq!(Role::Led.core(&config), None);
+        assert_eq!(Role::Audio.core(&config), Some(3));
+        assert!(pin_to_core(usize::MAX).is_err());
+    }
+}
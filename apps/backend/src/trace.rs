@@ -0,0 +1,172 @@
+use crate::crash;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FrameTrace {
+    pub frame: u64,
+    pub render_us: u64,
+    pub output_us: Option<u64>,
+    pub send_us: Option<u64>,
+    pub artnet_packets: Option<usize>,
+    pub udp_clients: Option<usize>,
+    pub udp_packets: Option<usize>,
+    pub udp_send_us: Option<u64>,
+    pub acks: u32,
+    pub total_us: u64,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl FrameTrace {
+    fn is_complete(&self) -> bool {
+        self.send_us.is_some() && self.udp_send_us.is_some()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FrameTracer {
+    every_n: u64,
+    active: Option<FrameTrace>,
+}
+
+impl FrameTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_interval(&mut self, every_n: u64) {
+        self.every_n = every_n;
+        self.active = None;
+    }
+
+    // `frame` is the id the render loop gave the frame; the later stages pass it back
+    pub fn record_render(&mut self, frame: u64, started: Instant, render_time: Duration) {
+        if self.every_n == 0 || self.active.is_some() || !frame.is_multiple_of(self.every_n) {
+            return;
+        }
+
+        self.active = Some(FrameTrace {
+            frame,
+            render_us: render_time.as_micros() as u64,
+            started: Some(started),
+            ..Default::default()
+        });
+    }
+
+    pub fn record_output(
+        &mut self,
+        frame: u64,
+        output_time: Duration,
+        send_time: Duration,
+        packets: usize,
+    ) {
+        self.drop_if_skipped(frame, |t| t.send_us.is_none());
+        if let Some(trace) = self
+            .active
+            .as_mut()
+            .filter(|t| t.frame == frame && t.send_us.is_none())
+        {
+            trace.output_us = Some(output_time.as_micros() as u64);
+            trace.send_us = Some(send_time.as_micros() as u64);
+            trace.artnet_packets = Some(packets);
+        }
+        self.finish_if_complete();
+    }
+
+    pub fn record_network(
+        &mut self,
+        frame: u64,
+        clients: usize,
+        packets: usize,
+        send_time: Duration,
+    ) {
+        self.drop_if_skipped(frame, |t| t.udp_send_us.is_none());
+        if let Some(trace) = self
+            .active
+            .as_mut()
+            .filter(|t| t.frame == frame && t.udp_send_us.is_none())
+        {
+            trace.udp_clients = Some(clients);
+            trace.udp_packets = Some(packets);
+            trace.udp_send_us = Some(send_time.as_micros() as u64);
+        }
+        self.finish_if_complete();
+    }
+
+    pub fn record_ack(&mut self) {
+        if let Some(trace) = self.active.as_mut() {
+            trace.acks += 1;
+        }
+    }
+
+    // A stage that moved past the traced frame without handling it never will: the trace is
+    // dropped so a later frame can be traced
+    fn drop_if_skipped(&mut self, frame: u64, pending: impl Fn(&FrameTrace) -> bool) {
+        if self
+            .active
+            .as_ref()
+            .is_some_and(|t| frame > t.frame && pending(t))
+        {
+            self.active = None;
+        }
+    }
+
+    fn finish_if_complete(&mut self) {
+        if !self.active.as_ref().is_some_and(|t| t.is_complete()) {
+            return;
+        }
+
+        if let Some(mut trace) = self.active.take() {
+            trace.total_us = trace
+                .started
+                .map(|s| s.elapsed().as_micros() as u64)
+                .unwrap_or_default();
+            crash::log(format!(
+                "🔎 Frame trace: {}",
+                serde_json::to_string(&trace).unwrap_or_default()
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_every_nth_frame() {
+        let mut tracer = FrameTracer::new();
+        tracer.record_render(1, Instant::now(), Duration::from_micros(10));
+        assert!(tracer.active.is_none());
+
+        tracer.set_interval(2);
+        tracer.record_render(1, Instant::now(), Duration::from_micros(10));
+        assert!(tracer.active.is_none());
+        tracer.record_render(2, Instant::now(), Duration::from_micros(10));
+        assert!(tracer.active.is_some());
+        assert_eq!(tracer.active.as_ref().unwrap().frame, 2);
+
+        // Earlier frames still in flight are not the traced one
+        tracer.record_ack();
+        tracer.record_output(1, Duration::from_micros(5), Duration::from_micros(50), 256);
+        assert_eq!(tracer.active.as_ref().unwrap().send_us, None);
+        tracer.record_output(2, Duration::from_micros(5), Duration::from_micros(50), 256);
+        assert_eq!(tracer.active.as_ref().unwrap().send_us, Some(50));
+
+        tracer.record_network(2, 1, 12, Duration::from_micros(30));
+        assert!(tracer.active.is_none());
+    }
+
+    #[test]
+    fn test_skipped_frame_drops_the_trace() {
+        let mut tracer = FrameTracer::new();
+        tracer.set_interval(2);
+        tracer.record_render(2, Instant::now(), Duration::from_micros(10));
+        tracer.record_network(3, 1, 12, Duration::from_micros(30));
+        assert!(tracer.active.is_none());
+
+        tracer.record_render(4, Instant::now(), Duration::from_micros(10));
+        assert_eq!(tracer.active.as_ref().unwrap().frame, 4);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::config::TriggersConfig;
+use crate::crash;
+use crate::schedule::parse_action;
+use crate::udp::UdpCommand;
+use crate::AppState;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// An unplugged adapter is looked for again this often
+const REOPEN_EVERY: Duration = Duration::from_secs(2);
+
+// "panic" kills the wall outright, blackout first so no strobe flash slips through;
+// anything else is a schedule action
+fn parse_binding(action: &str) -> Result<Vec<UdpCommand>> {
+    match action.trim() {
+        "panic" => Ok(vec![
+            UdpCommand::SetBlackout(true),
+            UdpCommand::SetStrobe(false),
+        ]),
+        action => Ok(vec![parse_action(action)?]),
+    }
+}
+
+pub struct Triggers {
+    bindings: HashMap<String, Vec<UdpCommand>>,
+    debounce: Duration,
+    last_press: HashMap<String, Instant>,
+}
+
+impl Triggers {
+    pub fn new(config: &TriggersConfig) -> Self {
+        let mut bindings = HashMap::new();
+        for (input, action) in &config.bindings {
+            match parse_binding(action) {
+                Ok(commands) => {
+                    bindings.insert(input.clone(), commands);
+                }
+                Err(e) => crash::log(format!("⚠️ Trigger {} ignored: {}", input, e)),
+            }
+        }
+        Self {
+            bindings,
+            debounce: Duration::from_millis(config.debounce_ms),
+            last_press: HashMap::new(),
+        }
+    }
+
+    // Commands bound to the input, none when it is unbound or bouncing
+    pub fn press(&mut self, input: &str, now: Instant) -> &[UdpCommand] {
+        let Some(commands) = self.bindings.get(input) else {
+            return &[];
+        };
+        // Bounces count as presses, so a chattering contact stays quiet until it settles
+        let last = self.last_press.insert(input.to_string(), now);
+        if last.is_some_and(|last| now.duration_since(last) < self.debounce) {
+            return &[];
+        }
+        commands
+    }
+}
+
+// Raw 8N1 at the given speed; reads block until a byte arrives
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn open_serial(device: &str, baud_rate: u32) -> Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    let speed = match baud_rate {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        _ => bail!(
+            "Unsupported baud rate {} (use 9600, 19200, 38400, 57600 or 115200)",
+            baud_rate
+        ),
+    };
+    let port = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(device)?;
+
+    let fd = port.as_raw_fd();
+    let mut tty: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut tty) } != 0 {
+        bail!("Not a serial port: {}", std::io::Error::last_os_error());
+    }
+    unsafe {
+        libc::cfmakeraw(&mut tty);
+        libc::cfsetspeed(&mut tty, speed);
+    }
+    tty.c_cflag |= libc::CLOCAL | libc::CREAD;
+    tty.c_cc[libc::VMIN] = 1;
+    tty.c_cc[libc::VTIME] = 0;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &tty) } != 0 {
+        bail!(
+            "Could not configure the port: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(port)
+}
+
+// The port is read as it is: its speed has to be set beforehand with the system's tools
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn open_serial(device: &str, _baud_rate: u32) -> Result<File> {
+    Ok(OpenOptions::new().read(true).write(true).open(device)?)
+}
+
+// Runs on the backend alone, so the buttons work with the network down; commands are
+// handed to the UDP server, which applies them between two frames like any client's
+pub fn run(state: Arc<AppState>, config: TriggersConfig) {
+    let Some(device) = config.device.clone() else {
+        return;
+    };
+    let mut triggers = Triggers::new(&config);
+    let mut last_error = String::new();
+
+    loop {
+        match open_serial(&device, config.baud_rate) {
+            Ok(port) => {
+                crash::log(format!("🔘 Trigger adapter on {}", device));
+                last_error.clear();
+
+                let mut reader = BufReader::new(port);
+                let mut line = Vec::new();
+                while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+                    let input = String::from_utf8_lossy(&line).trim().to_string();
+                    line.clear();
+                    let commands = triggers.press(&input, Instant::now());
+                    if !commands.is_empty() {
+                        crash::log(format!("🔘 Trigger {}: {}", input, config.bindings[&input]));
+                        let mut queue = state.triggered.lock();
+                        queue.extend(commands.iter().cloned());
+                    }
+                }
+                crash::log(format!("⚠️ Trigger adapter {} disconnected", device));
+            }
+            Err(e) => {
+                // Logged once, not every retry while the adapter stays unplugged
+                let error = e.to_string();
+                if error != last_error {
+                    crash::log(format!("⚠️ Trigger adapter {}: {}", device, error));
+                    last_error = error;
+                }
+            }
+        }
+        std::thread::sleep(REOPEN_EVERY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_and_debounce() {
+        let config = TriggersConfig {
+            bindings: [
+                ("B1".to_string(), "preset drop".to_string()),
+                ("B2".to_string(), "blackout off".to_string()),
+                ("B8".to_string(), "panic".to_string()),
+                ("B9".to_string(), "fireworks".to_string()),
+            ]
+            .into(),
+            ..TriggersConfig::default()
+        };
+        let mut triggers = Triggers::new(&config);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert!(matches!(
+            triggers.press("B1", at(0)),
+            [UdpCommand::LoadPreset(name)] if name == "drop"
+        ));
+        // Contact bounce, then a real second press
+        assert!(triggers.press("B1", at(20)).is_empty());
+        assert!(triggers.press("B1", at(60)).is_empty());
+        assert_eq!(triggers.press("B1", at(200)).len(), 1);
+        // Inputs debounce separately
+        assert!(matches!(
+            triggers.press("B2", at(200)),
+            [UdpCommand::SetBlackout(false)]
+        ));
+
+        assert!(matches!(
+            triggers.press("B8", at(0)),
+            [UdpCommand::SetBlackout(true), UdpCommand::SetStrobe(false)]
+        ));
+        assert!(triggers.press("B9", at(0)).is_empty());
+        assert!(triggers.press("B5", at(0)).is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::crash;
+use crate::effects::COLOR_MODES;
+use crate::udp::{ClientList, ClientSummary};
+use crate::AppState;
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::Arc;
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+const BRIGHTNESS_STEP: f32 = 0.05;
+const LOG_LINES: usize = 6;
+
+// One bar per terminal column, each taking the band under it
+fn spectrum_bars(spectrum: &[f32], width: usize) -> Vec<u64> {
+    if spectrum.is_empty() {
+        return Vec::new();
+    }
+    (0..width)
+        .map(|column| {
+            let band = column * spectrum.len() / width;
+            (spectrum[band].clamp(0.0, 1.0) * 100.0) as u64
+        })
+        .collect()
+}
+
+struct Dashboard {
+    state: Arc<AppState>,
+    clients: ClientList,
+}
+
+impl Dashboard {
+    // False once the operator quits
+    fn handle_key(&self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Left | KeyCode::Right => {
+                let mut engine = self.state.effect_engine.lock();
+                if code == KeyCode::Right {
+                    engine.next_effect();
+                } else {
+                    engine.previous_effect();
+                }
+            }
+            KeyCode::Up | KeyCode::Char('+') | KeyCode::Down | KeyCode::Char('-') => {
+                let step = if matches!(code, KeyCode::Up | KeyCode::Char('+')) {
+                    BRIGHTNESS_STEP
+                } else {
+                    -BRIGHTNESS_STEP
+                };
+                let mut output = self.state.output.lock();
+                let brightness = (output.brightness + step).clamp(0.0, 1.0);
+                output.set_brightness(brightness);
+            }
+            KeyCode::Char('c') => {
+                let mut engine = self.state.effect_engine.lock();
+                let current = COLOR_MODES
+                    .iter()
+                    .position(|mode| *mode == engine.color_mode())
+                    .unwrap_or(0);
+                engine.set_color_mode(COLOR_MODES[(current + 1) % COLOR_MODES.len()]);
+            }
+            KeyCode::Char('b') => {
+                let mut output = self.state.output.lock();
+                let blackout = !output.blackout;
+                output.set_blackout(blackout);
+            }
+            _ => return true,
+        }
+        self.state.energy_saver.lock().record_activity();
+        true
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let (effect, color_mode, bpm) = {
+            let engine = self.state.effect_engine.lock();
+            (
+                engine.current_effect_name(),
+                engine.color_mode().to_string(),
+                engine.bpm(),
+            )
+        };
+        let (brightness, blackout) = {
+            let output = self.state.output.lock();
+            (output.brightness, output.blackout)
+        };
+        let fps = self.state.render_health.lock().average_fps();
+        let sleeping = self.state.energy_saver.lock().is_sleeping();
+
+        let mut spans = vec![
+            Span::styled(effect, Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("  colors {}", color_mode)),
+            Span::raw(format!("  {:.1} FPS", fps)),
+            Span::raw(bpm.map_or("  BPM --".to_string(), |bpm| format!("  BPM {:.0}", bpm))),
+            Span::raw(format!("  brightness {:.0}%", brightness * 100.0)),
+        ];
+        if blackout {
+            spans.push(Span::styled("  BLACKOUT", Style::default().fg(Color::Red)));
+        }
+        if sleeping {
+            spans.push(Span::styled(
+                "  sleeping",
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn clients_table(clients: &[ClientSummary]) -> Table<'static> {
+        let rows = clients.iter().map(|client| {
+            Row::new(vec![
+                client.addr.clone(),
+                format!("{:?}", client.role).to_lowercase(),
+                client
+                    .rtt_ms
+                    .map_or("--".to_string(), |rtt| format!("{:.1} ms", rtt)),
+                format!("{:.0} kbit/s", client.kbps),
+                format!("{} s", client.session_secs),
+            ])
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Min(21),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(13),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new(vec!["Address", "Role", "RTT", "Rate", "Session"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(format!(" Clients ({}) ", clients.len())))
+    }
+
+    fn controllers(&self) -> Paragraph<'static> {
+        let offline = self
+            .state
+            .alert_inputs
+            .lock()
+            .offline_controllers()
+            .to_vec();
+        let target_set = self
+            .state
+            .output_targets
+            .lock()
+            .active()
+            .map_or("default controllers".to_string(), |set| set.name.clone());
+
+        let mut lines = vec![Line::raw(format!("Target set: {}", target_set))];
+        if offline.is_empty() {
+            lines.push(Line::styled(
+                "All controllers sending",
+                Style::default().fg(Color::Green),
+            ));
+        } else {
+            lines.extend(offline.into_iter().map(|controller| {
+                Line::styled(
+                    format!("{} offline", controller),
+                    Style::default().fg(Color::Red),
+                )
+            }));
+        }
+        Paragraph::new(lines).block(Block::bordered().title(" Controllers "))
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [status, spectrum, middle, log, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(6),
+            Constraint::Length(8),
+            Constraint::Length(LOG_LINES as u16 + 2),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [clients, controllers] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(middle);
+
+        frame.render_widget(
+            Paragraph::new(self.status_line()).block(Block::bordered().title(" DJ-4LED ")),
+            status,
+        );
+
+        let bars = spectrum_bars(&self.state.spectrum.lock(), inner_width(spectrum));
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(" Spectrum "))
+                .data(&bars)
+                .max(100)
+                .style(Style::default().fg(Color::Cyan)),
+            spectrum,
+        );
+
+        frame.render_widget(Self::clients_table(&self.clients.summaries()), clients);
+        frame.render_widget(self.controllers(), controllers);
+
+        let lines: Vec<Line> = crash::recent_logs(LOG_LINES)
+            .into_iter()
+            .map(Line::raw)
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Log ")),
+            log,
+        );
+        frame.render_widget(
+            Paragraph::new("←/→ effect  ↑/↓ brightness  c colors  b blackout  q quit")
+                .style(Style::default().fg(Color::DarkGray)),
+            help,
+        );
+    }
+}
+
+fn inner_width(area: Rect) -> usize {
+    area.width.saturating_sub(2) as usize
+}
+
+fn run_loop(terminal: &mut DefaultTerminal, dashboard: &Dashboard) -> Result<()> {
+    loop {
+        terminal.draw(|frame| dashboard.draw(frame))?;
+        if !event::poll(REFRESH_INTERVAL)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !dashboard.handle_key(key.code, key.modifiers) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Takes over the terminal until the operator quits; log lines stay in the log panel meanwhile
+pub fn run(state: Arc<AppState>, clients: ClientList) -> Result<()> {
+    let dashboard = Dashboard { state, clients };
+    crash::set_quiet(true);
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal, &dashboard);
+    ratatui::restore();
+    crash::set_quiet(false);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spectrum_fills_the_width() {
+        let spectrum = [0.0, 0.5, 1.0, 2.0];
+        assert_eq!(
+            spectrum_bars(&spectrum, 8),
+            [0, 0, 50, 50, 100, 100, 100, 100]
+        );
+        assert_eq!(spectrum_bars(&spectrum, 2), [0, 100]);
+        assert!(spectrum_bars(&[], 10).is_empty());
+    }
+}
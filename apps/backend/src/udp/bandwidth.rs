@@ -0,0 +1,165 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamMode {
+    Full,
+    // Some frames held back to stay under the cap
+    ReducedFps,
+    // Not even one frame fits the cap, only the spectrum goes out
+    SpectrumOnly,
+}
+
+// Bytes sent to one client, and the cap its preview frames are held to
+#[derive(Debug, Clone)]
+pub struct ClientBandwidth {
+    // Bytes per second, None = unlimited
+    cap: Option<u64>,
+    // Token bucket holding at most one second of the cap; spectrum packets can take it below zero
+    tokens: i64,
+    refilled: Instant,
+    total_bytes: u64,
+    frames_dropped: u64,
+    window_start: Instant,
+    window_bytes: u64,
+    window_frames: (u64, u64),
+    // Bytes per second and (sent, dropped) frames over the last full window
+    rate: f32,
+    last_frames: (u64, u64),
+}
+
+impl ClientBandwidth {
+    // 0 = unlimited
+    pub fn new(cap_kbps: u32) -> Self {
+        let cap = (cap_kbps > 0).then(|| cap_kbps as u64 * 1000 / 8);
+        let now = Instant::now();
+        Self {
+            cap,
+            tokens: cap.unwrap_or(0) as i64,
+            refilled: now,
+            total_bytes: 0,
+            frames_dropped: 0,
+            window_start: now,
+            window_bytes: 0,
+            window_frames: (0, 0),
+            rate: 0.0,
+            last_frames: (0, 0),
+        }
+    }
+
+    fn roll_window(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+        self.rate = self.window_bytes as f32 / elapsed.as_secs_f32();
+        self.last_frames = self.window_frames;
+        self.window_start = now;
+        self.window_bytes = 0;
+        self.window_frames = (0, 0);
+    }
+
+    // Whether a frame of `bytes` goes out now; counted as dropped otherwise
+    pub fn allow_frame(&mut self, bytes: usize, now: Instant) -> bool {
+        self.roll_window(now);
+        let allowed = match self.cap {
+            None => true,
+            Some(cap) => {
+                let refill = cap as f64 * now.duration_since(self.refilled).as_secs_f64();
+                self.tokens = (self.tokens + refill as i64).min(cap as i64);
+                self.refilled = now;
+                self.tokens >= bytes as i64
+            }
+        };
+        if allowed {
+            self.window_frames.0 += 1;
+        } else {
+            self.window_frames.1 += 1;
+            self.frames_dropped += 1;
+        }
+        allowed
+    }
+
+    pub fn record(&mut self, bytes: usize, now: Instant) {
+        self.roll_window(now);
+        self.total_bytes += bytes as u64;
+        self.window_bytes += bytes as u64;
+        if self.cap.is_some() {
+            self.tokens -= bytes as i64;
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    pub fn frames_dropped(&self) -> u64 {
+        self.frames_dropped
+    }
+
+    pub fn kbps(&self) -> f32 {
+        self.rate * 8.0 / 1000.0
+    }
+
+    pub fn cap_kbps(&self) -> Option<u32> {
+        self.cap.map(|cap| (cap * 8 / 1000) as u32)
+    }
+
+    pub fn mode(&self) -> StreamMode {
+        match self.last_frames {
+            (_, 0) => StreamMode::Full,
+            (0, _) => StreamMode::SpectrumOnly,
+            _ => StreamMode::ReducedFps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: usize = 12_000;
+
+    // 60 frames of FRAME bytes over one second, with the stream mode reached at the end of it
+    fn stream_second(bandwidth: &mut ClientBandwidth, start: Instant) -> u64 {
+        let mut sent = 0;
+        for tick in 0..=60 {
+            let now = start + Duration::from_micros(tick * 16_667);
+            if bandwidth.allow_frame(FRAME, now) {
+                bandwidth.record(FRAME, now);
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn test_unlimited_clients_get_every_frame() {
+        let mut bandwidth = ClientBandwidth::new(0);
+        assert_eq!(stream_second(&mut bandwidth, Instant::now()), 61);
+        assert_eq!(bandwidth.cap_kbps(), None);
+        assert_eq!(bandwidth.total_bytes(), 61 * FRAME as u64);
+        assert_eq!(bandwidth.mode(), StreamMode::Full);
+        assert!((bandwidth.kbps() - 60.0 * FRAME as f32 * 8.0 / 1000.0).abs() < 50.0);
+    }
+
+    #[test]
+    fn test_caps_lower_the_frame_rate() {
+        // 1.2 Mbit/s = 150 kB/s, about 12 frames per second after the first second's burst
+        let mut bandwidth = ClientBandwidth::new(1200);
+        let start = Instant::now();
+        stream_second(&mut bandwidth, start);
+        let sent = stream_second(&mut bandwidth, start + Duration::from_secs(1));
+        assert!((11..=14).contains(&sent), "{} frames", sent);
+        assert_eq!(bandwidth.mode(), StreamMode::ReducedFps);
+        assert!(bandwidth.frames_dropped() > 0);
+
+        // Less than one frame per second: spectrum only
+        let mut bandwidth = ClientBandwidth::new(80);
+        assert_eq!(stream_second(&mut bandwidth, start), 0);
+        assert_eq!(bandwidth.mode(), StreamMode::SpectrumOnly);
+    }
+}
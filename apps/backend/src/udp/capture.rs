@@ -0,0 +1,333 @@
+use super::protocol::{PacketType, UdpCommand, UdpPacket};
+use crate::crash;
+use anyhow::{anyhow, bail, Result};
+use parking_lot::Mutex;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 6] = b"DJ4CAP";
+// Bumped whenever the record layout changes
+pub const FORMAT_VERSION: u16 = 1;
+const DETAIL_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Received = 0,
+    Sent = 1,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureHeader {
+    pub format_version: u16,
+    // Backend version that wrote the capture
+    pub server_version: String,
+    pub started_micros: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRecord {
+    // Since the start of the capture
+    pub micros: u64,
+    pub direction: Direction,
+    pub addr: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+// Capture file: header, then one record per datagram
+//   header: "DJ4CAP", format version (u16), start time in Unix µs (u64), server version (u8 length + UTF-8)
+//   record: µs since start (u64), direction (u8), IP version (u8, 4 or 6), address (4 or 16 bytes),
+//           port (u16), length (u32), datagram
+// All integers little endian, like the protocol itself
+pub struct PacketCapture {
+    out: BufWriter<File>,
+    started: Instant,
+    // Frame and spectrum packets make up nearly all the traffic, they are left out unless asked for
+    frames: bool,
+    failed: bool,
+}
+
+impl PacketCapture {
+    pub fn create(path: &Path, frames: bool) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out = BufWriter::new(File::create(path)?);
+        let version = env!("CARGO_PKG_VERSION");
+        let started_micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        out.write_all(&started_micros.to_le_bytes())?;
+        out.write_all(&[version.len() as u8])?;
+        out.write_all(version.as_bytes())?;
+        out.flush()?;
+        Ok(Self {
+            out,
+            started: Instant::now(),
+            frames,
+            failed: false,
+        })
+    }
+
+    fn write_record(&mut self, direction: Direction, addr: SocketAddr, data: &[u8]) -> Result<()> {
+        let micros = self.started.elapsed().as_micros() as u64;
+        self.out.write_all(&micros.to_le_bytes())?;
+        self.out.write_all(&[direction as u8])?;
+        match addr.ip() {
+            IpAddr::V4(ip) => {
+                self.out.write_all(&[4])?;
+                self.out.write_all(&ip.octets())?;
+            }
+            IpAddr::V6(ip) => {
+                self.out.write_all(&[6])?;
+                self.out.write_all(&ip.octets())?;
+            }
+        }
+        self.out.write_all(&addr.port().to_le_bytes())?;
+        self.out.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.out.write_all(data)?;
+        // Flushed every time so a crash keeps everything up to it
+        self.out.flush()?;
+        Ok(())
+    }
+
+    // Gives up with a single log line once the file can't be written
+    fn record(&mut self, direction: Direction, addr: SocketAddr, data: &[u8]) {
+        let streamed = matches!(
+            data.first(),
+            Some(&t) if t == PacketType::FrameData as u8
+                || t == PacketType::FrameDataCompressed as u8
+                || t == PacketType::SpectrumData as u8
+        );
+        if self.failed || (streamed && !self.frames) {
+            return;
+        }
+        if let Err(e) = self.write_record(direction, addr, data) {
+            crash::log(format!("❌ Protocol capture stopped: {}", e));
+            self.failed = true;
+        }
+    }
+}
+
+// The server socket, recording every datagram when a capture is running
+pub struct CaptureSocket {
+    socket: UdpSocket,
+    capture: Option<Arc<Mutex<PacketCapture>>>,
+}
+
+impl CaptureSocket {
+    pub fn new(socket: UdpSocket, capture: Option<PacketCapture>) -> Self {
+        Self {
+            socket,
+            capture: capture.map(|capture| Arc::new(Mutex::new(capture))),
+        }
+    }
+
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            socket: self.socket.try_clone()?,
+            capture: self.capture.clone(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn send_to(&self, data: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let sent = self.socket.send_to(data, addr)?;
+        if let Some(capture) = &self.capture {
+            capture.lock().record(Direction::Sent, addr, data);
+        }
+        Ok(sent)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let (len, addr) = self.socket.recv_from(buf)?;
+        if let Some(capture) = &self.capture {
+            capture
+                .lock()
+                .record(Direction::Received, addr, &buf[..len]);
+        }
+        Ok((len, addr))
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        bail!("Truncated capture");
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Ok(head)
+}
+
+pub fn read_capture(mut data: &[u8]) -> Result<(CaptureHeader, Vec<CaptureRecord>)> {
+    if take(&mut data, MAGIC.len())? != MAGIC {
+        bail!("Not a protocol capture");
+    }
+    let format_version = u16::from_le_bytes(take(&mut data, 2)?.try_into()?);
+    if format_version != FORMAT_VERSION {
+        bail!(
+            "Capture format version {} (this build reads {})",
+            format_version,
+            FORMAT_VERSION
+        );
+    }
+    let started_micros = u64::from_le_bytes(take(&mut data, 8)?.try_into()?);
+    let version_len = take(&mut data, 1)?[0] as usize;
+    let server_version = String::from_utf8_lossy(take(&mut data, version_len)?).into_owned();
+
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let micros = u64::from_le_bytes(take(&mut data, 8)?.try_into()?);
+        let direction = match take(&mut data, 1)?[0] {
+            0 => Direction::Received,
+            1 => Direction::Sent,
+            other => bail!("Unknown direction {}", other),
+        };
+        let ip = match take(&mut data, 1)?[0] {
+            4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(take(&mut data, 4)?)?)),
+            6 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(take(&mut data, 16)?)?)),
+            other => bail!("Unknown IP version {}", other),
+        };
+        let port = u16::from_le_bytes(take(&mut data, 2)?.try_into()?);
+        let len = u32::from_le_bytes(take(&mut data, 4)?.try_into()?) as usize;
+        records.push(CaptureRecord {
+            micros,
+            direction,
+            addr: SocketAddr::new(ip, port),
+            data: take(&mut data, len)?.to_vec(),
+        });
+    }
+    Ok((
+        CaptureHeader {
+            format_version,
+            server_version,
+            started_micros,
+        },
+        records,
+    ))
+}
+
+fn shorten(mut text: String) -> String {
+    if let Some((cut, _)) = text.char_indices().nth(DETAIL_CHARS) {
+        text.truncate(cut);
+        text.push('…');
+    }
+    text
+}
+
+// One line per datagram: packet header, then the command or JSON body when there is one
+pub fn describe(data: &[u8]) -> String {
+    let packet = match UdpPacket::from_bytes(data) {
+        Ok(packet) => packet,
+        Err(e) => {
+            let hex: Vec<String> = data.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+            return format!("undecodable ({}): {}", e, hex.join(" "));
+        }
+    };
+    let mut line = format!(
+        "{:?} seq={} flags={:#04x} fragment={}/{} payload={}B",
+        packet.packet_type,
+        packet.sequence,
+        packet.flags.bits(),
+        packet.fragment_id + 1,
+        packet.fragment_count,
+        packet.payload.len()
+    );
+    let detail = match packet.packet_type {
+        PacketType::Command => Some(match UdpCommand::from_payload(&packet.payload) {
+            Some(command) => format!("{:?}", command),
+            None => format!(
+                "unknown command {:#04x}",
+                packet.payload.first().unwrap_or(&0)
+            ),
+        }),
+        PacketType::Response
+        | PacketType::Nack
+        | PacketType::ColorModes
+        | PacketType::OutputAlert
+        | PacketType::ConfigReload
+        | PacketType::AlertEvent
+        | PacketType::BeatEvent => Some(String::from_utf8_lossy(&packet.payload).into_owned()),
+        _ => None,
+    };
+    if let Some(detail) = detail.filter(|detail| !detail.is_empty()) {
+        line.push_str("  ");
+        line.push_str(&shorten(detail));
+    }
+    line
+}
+
+pub fn print_capture(path: &Path) -> Result<()> {
+    let data = fs::read(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    let (header, records) = read_capture(&data)?;
+    println!(
+        "{}: format v{}, backend {}, started at {} (Unix µs), {} packets",
+        path.display(),
+        header.format_version,
+        header.server_version,
+        header.started_micros,
+        records.len()
+    );
+    for record in &records {
+        let arrow = match record.direction {
+            Direction::Received => "<-",
+            Direction::Sent => "->",
+        };
+        println!(
+            "{:>12.6}s {} {:<21} {}",
+            record.micros as f64 / 1_000_000.0,
+            arrow,
+            record.addr,
+            describe(&record.data)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_round_trip() {
+        let path = std::env::temp_dir().join(format!("capture_{}.dj4cap", std::process::id()));
+        let client: SocketAddr = "192.168.1.20:5000".parse().unwrap();
+        let command = UdpPacket::new(PacketType::Command, 7, UdpCommand::StopTape.to_payload())
+            .to_bytes()
+            .unwrap();
+        let frame = UdpPacket::new(PacketType::FrameData, 8, vec![0; 32])
+            .to_bytes()
+            .unwrap();
+
+        let mut capture = PacketCapture::create(&path, false).unwrap();
+        capture.record(Direction::Received, client, &command);
+        capture.record(Direction::Sent, client, &frame);
+        capture.record(Direction::Sent, "[::1]:8081".parse().unwrap(), &[1, 2]);
+        drop(capture);
+
+        let (header, records) = read_capture(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(header.format_version, FORMAT_VERSION);
+        assert_eq!(header.server_version, env!("CARGO_PKG_VERSION"));
+        // The frame packet is left out
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, Direction::Received);
+        assert_eq!(records[0].addr, client);
+        assert_eq!(records[0].data, command);
+        assert_eq!(records[1].addr.port(), 8081);
+
+        assert!(describe(&records[0].data).starts_with("Command seq=7"));
+        assert!(describe(&records[0].data).ends_with("StopTape"));
+        assert!(describe(&records[1].data).starts_with("undecodable"));
+        assert!(read_capture(&fs::read("Cargo.toml").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+const COMMAND_WINDOW: usize = 64;
+const COMMAND_TTL: Duration = Duration::from_secs(30);
+
+struct CommandEntry {
+    sequence: u32,
+    reply: Option<Vec<u8>>,
+    received: Instant,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CommandStatus {
+    New,
+    Duplicate(Option<Vec<u8>>),
+}
+
+pub struct CommandLog {
+    entries: HashMap<SocketAddr, VecDeque<CommandEntry>>,
+    duplicates: u64,
+}
+
+impl CommandLog {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            duplicates: 0,
+        }
+    }
+
+    // Registers the command when it has not been seen yet from this address
+    pub fn check(&mut self, addr: SocketAddr, sequence: u32) -> CommandStatus {
+        self.prune();
+
+        let history = self.entries.entry(addr).or_default();
+        if let Some(entry) = history.iter().find(|e| e.sequence == sequence) {
+            self.duplicates += 1;
+            return CommandStatus::Duplicate(entry.reply.clone());
+        }
+
+        if history.len() == COMMAND_WINDOW {
+            history.pop_front();
+        }
+        history.push_back(CommandEntry {
+            sequence,
+            reply: None,
+            received: Instant::now(),
+        });
+        CommandStatus::New
+    }
+
+    pub fn record_reply(&mut self, addr: SocketAddr, sequence: u32, reply: &[u8]) {
+        if let Some(entry) = self
+            .entries
+            .get_mut(&addr)
+            .and_then(|history| history.iter_mut().find(|e| e.sequence == sequence))
+        {
+            entry.reply = Some(reply.to_vec());
+        }
+    }
+
+    pub fn has_reply(&self, addr: SocketAddr, sequence: u32) -> bool {
+        self.entries.get(&addr).is_some_and(|history| {
+            history
+                .iter()
+                .any(|e| e.sequence == sequence && e.reply.is_some())
+        })
+    }
+
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    fn prune(&mut self) {
+        self.entries.retain(|_, history| {
+            while history
+                .front()
+                .is_some_and(|e| e.received.elapsed() > COMMAND_TTL)
+            {
+                history.pop_front();
+            }
+            !history.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_commands_return_cached_reply() {
+        let mut log = CommandLog::new();
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let other: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+
+        assert_eq!(log.check(addr, 7), CommandStatus::New);
+        assert_eq!(log.check(addr, 7), CommandStatus::Duplicate(None));
+        assert_eq!(log.check(other, 7), CommandStatus::New);
+
+        log.record_reply(addr, 7, &[0x05]);
+        assert!(log.has_reply(addr, 7));
+        assert_eq!(
+            log.check(addr, 7),
+            CommandStatus::Duplicate(Some(vec![0x05]))
+        );
+        assert_eq!(log.duplicates(), 2);
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let mut log = CommandLog::new();
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+
+        for sequence in 0..(COMMAND_WINDOW as u32 + 1) {
+            log.check(addr, sequence);
+        }
+        assert_eq!(log.check(addr, 0), CommandStatus::New);
+    }
+}
@@ -0,0 +1,310 @@
+use super::protocol::{PacketFlags, PacketType, UdpPacket};
+use crate::config::Config;
+use crate::messages;
+use crate::palettes::ColorPalette;
+use anyhow::{anyhow, Result};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CONFIG_PATH: &str = "config.toml";
+const DEFAULT_PORT: u16 = 8081;
+// The receiver reads datagrams into a 1024-byte buffer, header included
+const FRAGMENT_SIZE: usize = 1000;
+const MAX_FRAGMENTS: usize = 256;
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(10);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+const PUSH_ATTEMPTS: usize = 2;
+
+// Everything that makes one instance behave like another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub config: String,
+    pub palettes: Vec<ColorPalette>,
+}
+
+impl ConfigBundle {
+    // The config file is sent as text so its comments survive the trip
+    pub fn export(palettes: &[ColorPalette]) -> Result<Self> {
+        Ok(Self {
+            config: fs::read_to_string(CONFIG_PATH)?,
+            palettes: palettes.to_vec(),
+        })
+    }
+
+    pub fn check(&self) -> Result<()> {
+        let config: Config = toml::from_str(&self.config)?;
+        let issues = config.validate();
+        if !issues.is_empty() {
+            return Err(anyhow!("Invalid config: {}", issues.join(", ")));
+        }
+        Ok(())
+    }
+
+    pub fn install_config(&self) -> Result<()> {
+        self.install_config_to(Path::new(CONFIG_PATH))
+    }
+
+    // Written next to the live file first so a crash never leaves half a config
+    fn install_config_to(&self, path: &Path) -> Result<()> {
+        let staged = path.with_extension("toml.incoming");
+        fs::write(&staged, &self.config)?;
+        fs::rename(&staged, path)?;
+        Ok(())
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&serde_json::to_vec(self)?)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let mut json = Vec::new();
+        ZlibDecoder::new(data).read_to_end(&mut json)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+// CONFIG_TRANSFER packets share the transfer id as sequence and carry one chunk each
+fn fragments(transfer_id: u32, data: &[u8]) -> Result<Vec<UdpPacket>> {
+    let chunks: Vec<&[u8]> = data.chunks(FRAGMENT_SIZE).collect();
+    if chunks.len() > MAX_FRAGMENTS {
+        return Err(anyhow!("Config bundle too large ({} bytes)", data.len()));
+    }
+
+    let count = chunks.len() as u16;
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut packet =
+                UdpPacket::new(PacketType::ConfigTransfer, transfer_id, chunk.to_vec());
+            packet.flags = PacketFlags::COMPRESSED | PacketFlags::FRAGMENTED;
+            if i as u16 == count - 1 {
+                packet.flags |= PacketFlags::LAST_FRAGMENT;
+            }
+            packet.fragment_id = i as u16;
+            packet.fragment_count = count;
+            packet
+        })
+        .collect())
+}
+
+fn resolve(target: &str) -> Result<SocketAddr> {
+    let target = target.trim();
+    let with_port = if target.contains(':') {
+        target.to_string()
+    } else {
+        format!("{}:{}", target, DEFAULT_PORT)
+    };
+    with_port
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("Unknown host: {}", target))
+}
+
+// Sends the bundle to another backend and waits for it to accept or refuse it
+pub fn push(bundle: &ConfigBundle, target: &str) -> Result<SocketAddr> {
+    let addr = resolve(target)?;
+    let transfer_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let packets = fragments(transfer_id, &bundle.encode()?)?
+        .iter()
+        .map(UdpPacket::to_bytes)
+        .collect::<Result<Vec<_>>>()?;
+
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    let mut buf = [0u8; 1024];
+
+    for _ in 0..PUSH_ATTEMPTS {
+        for (i, packet) in packets.iter().enumerate() {
+            socket.send_to(packet, addr)?;
+            // Small bursts so the receiver's socket buffer never overflows
+            if i % 32 == 31 {
+                std::thread::sleep(Duration::from_millis(2));
+            }
+        }
+
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        while Instant::now() < deadline {
+            let Ok((len, from)) = socket.recv_from(&mut buf) else {
+                break;
+            };
+            let Ok(reply) = UdpPacket::from_bytes(&buf[..len]) else {
+                continue;
+            };
+            if from != addr || reply.sequence != transfer_id {
+                continue;
+            }
+            match reply.packet_type {
+                PacketType::Ack => return Ok(addr),
+                PacketType::Nack => {
+                    return Err(anyhow!(
+                        "{} refused the config: {}",
+                        addr,
+                        messages::parse_nack(&reply.payload).1
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Err(anyhow!("No answer from {}", addr))
+}
+
+struct PartialTransfer {
+    started: Instant,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+// Reassembles CONFIG_TRANSFER fragments per sender; incomplete transfers expire
+pub struct ConfigTransfers {
+    pending: HashMap<(SocketAddr, u32), PartialTransfer>,
+}
+
+impl ConfigTransfers {
+    pub fn new() -> Self {
+        Self {
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn receive(
+        &mut self,
+        addr: SocketAddr,
+        packet: &UdpPacket,
+    ) -> Option<Result<ConfigBundle>> {
+        self.receive_at(addr, packet, Instant::now())
+    }
+
+    fn receive_at(
+        &mut self,
+        addr: SocketAddr,
+        packet: &UdpPacket,
+        now: Instant,
+    ) -> Option<Result<ConfigBundle>> {
+        self.pending.retain(|_, transfer| {
+            now.saturating_duration_since(transfer.started) < TRANSFER_TIMEOUT
+        });
+
+        let count = packet.fragment_count as usize;
+        if count == 0 || count > MAX_FRAGMENTS || packet.fragment_id as usize >= count {
+            return Some(Err(anyhow!("Invalid config fragment")));
+        }
+
+        let key = (addr, packet.sequence);
+        let transfer = self.pending.entry(key).or_insert_with(|| PartialTransfer {
+            started: now,
+            chunks: vec![None; count],
+        });
+        if transfer.chunks.len() != count {
+            self.pending.remove(&key);
+            return Some(Err(anyhow!("Inconsistent config fragments")));
+        }
+
+        transfer.chunks[packet.fragment_id as usize] = Some(packet.payload.clone());
+        if transfer.chunks.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let transfer = self.pending.remove(&key)?;
+        let data: Vec<u8> = transfer.chunks.into_iter().flatten().flatten().collect();
+        Some(ConfigBundle::decode(&data).and_then(|bundle| bundle.check().map(|_| bundle)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> ConfigBundle {
+        ConfigBundle {
+            config: toml::to_string_pretty(&Config::default()).unwrap(),
+            palettes: vec![ColorPalette {
+                name: "club".to_string(),
+                colors: vec![(1.0, 0.0, 0.5)],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_fragments_reassemble_in_any_order() {
+        let bundle = bundle();
+        // Incompressible filler forces several fragments
+        let mut noisy = bundle.clone();
+        noisy.config.push_str(
+            &(0..4000u32)
+                .map(|i| format!("# {:x}\n", i.wrapping_mul(2654435761)))
+                .collect::<String>(),
+        );
+        let packets = fragments(7, &noisy.encode().unwrap()).unwrap();
+        assert!(packets.len() > 2);
+        assert!(packets.iter().all(|p| p.to_bytes().unwrap().len() <= 1024));
+        assert!(packets
+            .last()
+            .unwrap()
+            .flags
+            .contains(PacketFlags::LAST_FRAGMENT));
+
+        let addr: SocketAddr = "10.0.0.2:8081".parse().unwrap();
+        let mut transfers = ConfigTransfers::new();
+        let mut result = None;
+        for packet in packets.iter().rev() {
+            assert!(result.is_none());
+            result = transfers.receive(addr, packet);
+        }
+        assert_eq!(result.unwrap().unwrap(), noisy);
+        assert!(transfers.pending.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_invalid_config_and_expires() {
+        let addr: SocketAddr = "10.0.0.2:8081".parse().unwrap();
+        let mut transfers = ConfigTransfers::new();
+
+        let mut broken = bundle();
+        broken.config = "[led]\nfps = \"fast\"\n".to_string();
+        let packets = fragments(1, &broken.encode().unwrap()).unwrap();
+        assert!(transfers.receive(addr, &packets[0]).unwrap().is_err());
+
+        let mut partial = fragments(2, &[0; 3000]).unwrap();
+        let now = Instant::now();
+        assert!(transfers.receive_at(addr, &partial[0], now).is_none());
+        assert_eq!(transfers.pending.len(), 1);
+        partial[1].fragment_count = 9;
+        assert!(transfers
+            .receive_at(addr, &partial[1], now)
+            .unwrap()
+            .is_err());
+        assert!(transfers.receive_at(addr, &partial[0], now).is_none());
+        let other = fragments(3, &[0; 3000]).unwrap();
+        assert!(transfers
+            .receive_at(addr, &other[0], now + TRANSFER_TIMEOUT)
+            .is_none());
+        assert!(transfers.pending.contains_key(&(addr, 3)));
+        assert!(!transfers.pending.contains_key(&(addr, 2)));
+    }
+
+    #[test]
+    fn test_install_config_and_resolve() {
+        let path = std::env::temp_dir().join(format!("pushed_{}.toml", std::process::id()));
+        bundle().install_config_to(&path).unwrap();
+        assert!(toml::from_str::<Config>(&fs::read_to_string(&path).unwrap()).is_ok());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(resolve("127.0.0.1").unwrap().port(), DEFAULT_PORT);
+        assert_eq!(resolve("127.0.0.1:9000").unwrap().port(), 9000);
+    }
+}
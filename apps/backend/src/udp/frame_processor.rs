@@ -1,30 +1,99 @@
 use super::protocol::*;
+use crate::config::MatrixConfig;
+use crate::mip::FrameMips;
+use anyhow::{anyhow, Result};
+use dj4led_protocol::HEADER_SIZE;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use serde::Serialize;
 use std::io::Write;
 
+const OVERVIEW_SIZE: usize = 64;
+// Largest payload that still fits one datagram on a 1500 byte MTU
+const FRAGMENT_PAYLOAD: usize = MAX_PACKET_SIZE - HEADER_SIZE;
+
+// Part of the frame a client streams at full resolution instead of the downscaled overview
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct FrameRegion {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+impl FrameRegion {
+    pub fn new(x: u8, y: u8, width: u8, height: u8, matrix: &MatrixConfig) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(anyhow!("Region must not be empty"));
+        }
+        if x as usize + width as usize > matrix.width
+            || y as usize + height as usize > matrix.height
+        {
+            return Err(anyhow!(
+                "Region {}×{} at ({}, {}) exceeds the {}×{} frame",
+                width,
+                height,
+                x,
+                y,
+                matrix.width,
+                matrix.height
+            ));
+        }
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
 pub struct UdpFrameProcessor {
+    region: Option<FrameRegion>,
+    // Width of the wall frames regions are cropped from
+    source_width: usize,
     frame_buffer: Vec<u8>,
     compression_buffer: Vec<u8>,
     last_frame_hash: u64,
     last_spectrum_hash: u64,
     frame_counter: u32,
+    encoded: Vec<Vec<u8>>,
 }
 
 impl UdpFrameProcessor {
-    pub fn new() -> Self {
+    pub fn with_region(region: Option<FrameRegion>, source_width: usize) -> Self {
         Self {
-            frame_buffer: Vec::with_capacity(128 * 128 * 3),
+            region,
+            source_width,
+            frame_buffer: Vec::with_capacity(OVERVIEW_SIZE * OVERVIEW_SIZE * 3),
             compression_buffer: Vec::with_capacity(64 * 1024),
             last_frame_hash: 0,
             last_spectrum_hash: 0,
             frame_counter: 0,
+            encoded: Vec::new(),
         }
     }
 
+    // Serialized once per encoding group; senders stamp each client's sequence into a copy
+    pub fn encode_packets(
+        &mut self,
+        frame: &[u8],
+        mips: &FrameMips,
+        spectrum: &[f32],
+        use_compression: bool,
+    ) -> &[Vec<u8>] {
+        let packets = self.prepare_packets(frame, mips, spectrum, 0, use_compression);
+
+        self.encoded.clear();
+        self.encoded
+            .extend(packets.iter().filter_map(|packet| packet.to_bytes().ok()));
+        &self.encoded
+    }
+
     pub fn prepare_packets(
         &mut self,
         frame: &[u8],
+        mips: &FrameMips,
         spectrum: &[f32],
         sequence_base: u32,
         use_compression: bool,
@@ -36,11 +105,22 @@ impl UdpFrameProcessor {
         if frame_hash != self.last_frame_hash || self.frame_counter % 60 == 0 {
             self.last_frame_hash = frame_hash;
 
-            self.downscale_frame(frame, 128, 64, 64);
+            let (width, height) = match self.region {
+                Some(region) => {
+                    self.crop_frame(frame, self.source_width, region);
+                    (region.width as u16, region.height as u16)
+                }
+                None => {
+                    self.frame_buffer.clear();
+                    self.frame_buffer
+                        .extend_from_slice(mips.level(OVERVIEW_SIZE).unwrap_or_default());
+                    (OVERVIEW_SIZE as u16, OVERVIEW_SIZE as u16)
+                }
+            };
 
             let frame_data = FrameData {
-                width: 64,
-                height: 64,
+                width,
+                height,
                 format: FrameFormat::RGB,
                 data: self.frame_buffer.clone(),
             };
@@ -61,28 +141,9 @@ impl UdpFrameProcessor {
                 (payload, PacketType::FrameData)
             };
 
-            if final_payload.len() <= MAX_PACKET_SIZE - 12 {
-                packets.push(UdpPacket::new(packet_type, current_sequence, final_payload));
+            for packet in Self::fragment(packet_type, current_sequence, final_payload) {
+                packets.push(packet);
                 current_sequence = current_sequence.wrapping_add(1);
-            } else {
-                let chunk_size = MAX_PACKET_SIZE - 12;
-                let chunks: Vec<_> = final_payload.chunks(chunk_size).collect();
-                let fragment_count = chunks.len() as u16;
-
-                for (i, chunk) in chunks.iter().enumerate() {
-                    let mut packet = UdpPacket::new(packet_type, current_sequence, chunk.to_vec());
-
-                    packet.flags |= PacketFlags::FRAGMENTED;
-                    packet.fragment_id = i as u16;
-                    packet.fragment_count = fragment_count;
-
-                    if i == chunks.len() - 1 {
-                        packet.flags |= PacketFlags::LAST_FRAGMENT;
-                    }
-
-                    packets.push(packet);
-                    current_sequence = current_sequence.wrapping_add(1);
-                }
             }
         }
 
@@ -108,6 +169,31 @@ impl UdpFrameProcessor {
         packets
     }
 
+    // Payloads past one datagram are split on consecutive sequences; clients put them back
+    // together by `sequence - fragment_id` and check the frame checksum on the whole
+    fn fragment(packet_type: PacketType, sequence: u32, payload: Vec<u8>) -> Vec<UdpPacket> {
+        if payload.len() <= FRAGMENT_PAYLOAD {
+            return vec![UdpPacket::new(packet_type, sequence, payload)];
+        }
+
+        let fragment_count = payload.len().div_ceil(FRAGMENT_PAYLOAD) as u16;
+        payload
+            .chunks(FRAGMENT_PAYLOAD)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut packet =
+                    UdpPacket::new(packet_type, sequence.wrapping_add(i as u32), chunk.to_vec());
+                packet.flags |= PacketFlags::FRAGMENTED;
+                packet.fragment_id = i as u16;
+                packet.fragment_count = fragment_count;
+                if i as u16 == fragment_count - 1 {
+                    packet.flags |= PacketFlags::LAST_FRAGMENT;
+                }
+                packet
+            })
+            .collect()
+    }
+
     fn fast_hash(data: &[u8]) -> u64 {
         data.chunks(8).enumerate().fold(0u64, |acc, (i, chunk)| {
             let mut bytes = [0u8; 8];
@@ -123,34 +209,17 @@ impl UdpFrameProcessor {
         })
     }
 
-    fn downscale_frame(
-        &mut self,
-        src: &[u8],
-        src_width: usize,
-        dst_width: usize,
-        dst_height: usize,
-    ) {
+    fn crop_frame(&mut self, src: &[u8], src_width: usize, region: FrameRegion) {
         self.frame_buffer.clear();
-        let scale = src_width / dst_width;
-
-        self.frame_buffer.reserve_exact(dst_width * dst_height * 3);
-
-        for y in 0..dst_height {
-            let src_y = y * scale;
-            for x in 0..dst_width {
-                let src_x = x * scale;
-                let src_idx = (src_y * src_width + src_x) * 3;
-
-                if src_idx + 2 < src.len() {
-                    unsafe {
-                        let ptr = src.as_ptr().add(src_idx);
-                        self.frame_buffer.push(*ptr);
-                        self.frame_buffer.push(*ptr.add(1));
-                        self.frame_buffer.push(*ptr.add(2));
-                    }
-                } else {
-                    self.frame_buffer.extend_from_slice(&[0, 0, 0]);
-                }
+        let row_len = region.width as usize * 3;
+
+        for y in region.y as usize..region.y as usize + region.height as usize {
+            let start = (y * src_width + region.x as usize) * 3;
+            match src.get(start..start + row_len) {
+                Some(row) => self.frame_buffer.extend_from_slice(row),
+                None => self
+                    .frame_buffer
+                    .resize(self.frame_buffer.len() + row_len, 0),
             }
         }
     }
@@ -196,16 +265,84 @@ impl UdpFrameProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use dj4led_protocol::{stamp_sequence, FrameReassembly, PacketHeader};
+    use std::time::Duration;
+
+    #[test]
+    fn test_overview_uses_mip_level() {
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+            pixel.fill(if i % 2 == 0 { 255 } else { 0 });
+        }
+        let mips = FrameMips::new(&frame, 128, 128);
+
+        let mut processor = UdpFrameProcessor::with_region(None, 128);
+        let packets = processor.prepare_packets(&frame, &mips, &[], 0, false);
+        let payload: Vec<u8> = packets.iter().flat_map(|p| p.payload.clone()).collect();
+
+        let data = FrameData::from_payload(&payload).unwrap();
+        assert_eq!((data.width, data.height), (64, 64));
+        assert_eq!(data.data, mips.level(64).unwrap());
+        assert!(data.data.iter().all(|&x| x == 128));
+    }
 
     #[test]
-    fn test_downscale() {
-        let mut processor = UdpFrameProcessor::new();
-        let src = vec![255u8; 128 * 128 * 3];
+    fn test_region_crop() {
+        let mut frame = vec![0u8; 128 * 128 * 3];
+        for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+            pixel.copy_from_slice(&[(i % 128) as u8, (i / 128) as u8, 7]);
+        }
+
+        let matrix = MatrixConfig::default();
+        let region = FrameRegion::new(32, 100, 32, 28, &matrix).unwrap();
+        let mut processor = UdpFrameProcessor::with_region(Some(region), matrix.width);
+        let packets = processor.prepare_packets(&frame, &FrameMips::default(), &[], 0, false);
+        let payload: Vec<u8> = packets
+            .iter()
+            .filter(|p| p.packet_type == PacketType::FrameData)
+            .flat_map(|p| p.payload.clone())
+            .collect();
+
+        let data = FrameData::from_payload(&payload).unwrap();
+        assert_eq!((data.width, data.height), (32, 28));
+        assert_eq!(data.data.len(), 32 * 28 * 3);
+        assert_eq!(&data.data[..3], &[32, 100, 7]);
+        assert_eq!(&data.data[data.data.len() - 3..], &[63, 127, 7]);
+
+        assert!(FrameRegion::new(100, 0, 29, 10, &matrix).is_err());
+        assert!(FrameRegion::new(0, 0, 0, 10, &matrix).is_err());
+        let wide = matrix.with_size("256x64").unwrap();
+        assert!(FrameRegion::new(200, 0, 50, 64, &wide).is_ok());
+        assert!(FrameRegion::new(0, 40, 10, 30, &wide).is_err());
+    }
 
-        processor.downscale_frame(&src, 128, 64, 64);
+    #[test]
+    fn test_full_region_is_fragmented_and_reassembles() {
+        let frame: Vec<u8> = (0..128 * 128 * 3).map(|i| (i % 251) as u8).collect();
+        let matrix = MatrixConfig::default();
+        let region = FrameRegion::new(0, 0, 128, 128, &matrix).unwrap();
+        let mut processor = UdpFrameProcessor::with_region(Some(region), matrix.width);
+        let packets = processor.prepare_packets(&frame, &FrameMips::default(), &[], 0, false);
+
+        let fragments: Vec<_> = packets
+            .iter()
+            .filter(|p| p.packet_type == PacketType::FrameData)
+            .map(|p| p.to_bytes().unwrap())
+            .collect();
+        assert_eq!(fragments.len(), 34);
+        assert!(fragments.iter().all(|bytes| bytes.len() <= MAX_PACKET_SIZE));
+
+        // Sent last to first, as a congested link may deliver them
+        let mut reassembly = FrameReassembly::new(Duration::from_millis(500));
+        let mut complete = None;
+        for bytes in fragments.iter().rev() {
+            let (header, payload) = PacketHeader::parse(bytes).unwrap();
+            complete = reassembly.push(&header, payload);
+        }
 
-        assert_eq!(processor.frame_buffer.len(), 64 * 64 * 3);
-        assert!(processor.frame_buffer.iter().all(|&x| x == 255));
+        let data = FrameData::from_payload(&complete.unwrap()).unwrap();
+        assert_eq!((data.width, data.height), (128, 128));
+        assert_eq!(data.data, frame);
     }
 
     #[test]
@@ -218,9 +355,24 @@ mod tests {
         assert_eq!(reduced[1], 5.5);
     }
 
+    #[test]
+    fn test_encode_and_stamp_sequence() {
+        let mut processor = UdpFrameProcessor::with_region(None, 128);
+        let frame = vec![10u8; 128 * 128 * 3];
+        let spectrum = vec![0.5f32; 64];
+
+        let mut packet =
+            processor.encode_packets(&frame, &FrameMips::new(&frame, 128, 128), &spectrum, false)
+                [0]
+            .clone();
+        stamp_sequence(&mut packet, 42);
+
+        assert_eq!(UdpPacket::from_bytes(&packet).unwrap().sequence, 42);
+    }
+
     #[test]
     fn test_compression() {
-        let mut processor = UdpFrameProcessor::new();
+        let mut processor = UdpFrameProcessor::with_region(None, 128);
         let data = vec![0u8; 1024];
 
         let compressed = processor.compress_data(&data);
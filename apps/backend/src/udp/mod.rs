@@ -1,21 +1,75 @@
+use crate::accent::AccentKind;
+use crate::calibration;
+use crate::commissioning::{self, Commissioning};
+use crate::config::{
+    AvDelayConfig, ColorCorrectionConfig, Config, HighPassConfig, NoiseGateConfig, OutputProfile,
+    OutputProfileConfig, ScheduleConfig, SmoothingConfig, SpectrumConfig, StreamConfig,
+    MAX_AV_DELAY_MS,
+};
+use crate::crash;
+use crate::crowd_noise::CrowdEnvelope;
+use crate::decibels;
+use crate::diagnostics;
+use crate::effects::EffectTag;
+use crate::genre::{self, GenrePreset, GENRE_PRESETS};
+use crate::handover::{self, HandoverState};
+use crate::led::Identify;
+use crate::messages::{self, Message};
+use crate::midi::{self, MidiRouter};
+use crate::presets::Preset;
+use crate::priority::is_backpressure;
+use crate::render::LedFrame;
+use crate::schedule::local_time;
+use crate::show_report;
 use crate::AppState;
 use anyhow::Result;
+use dj4led_protocol::{now_micros, TimeSample};
 use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::net::{SocketAddr, UdpSocket};
+use std::path::Path;
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+mod bandwidth;
+mod capture;
+mod command_log;
+mod config_transfer;
 mod frame_processor;
 mod protocol;
+mod schema;
+mod tape;
 
-pub use frame_processor::UdpFrameProcessor;
+use bandwidth::{ClientBandwidth, StreamMode};
+use capture::CaptureSocket;
+pub use capture::{print_capture, PacketCapture};
+use command_log::{CommandLog, CommandStatus};
+use config_transfer::{ConfigBundle, ConfigTransfers};
+pub use dj4led_protocol::stamp_sequence;
+pub use frame_processor::{FrameRegion, UdpFrameProcessor};
 pub use protocol::*;
+pub use schema::encode_command;
+use tape::{is_recordable, ControlTape};
 
 pub struct UdpServer {
     state: Arc<AppState>,
-    socket: UdpSocket,
+    socket: CaptureSocket,
     clients: Arc<Mutex<Vec<ClientInfo>>>,
+    stream_config: StreamConfig,
+    command_log: Arc<Mutex<CommandLog>>,
+    tape: Mutex<ControlTape>,
+    config_transfers: Mutex<ConfigTransfers>,
+    accept_config_push: bool,
+}
+
+pub const COMMAND_PORT: u16 = 8081;
+const PING_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_MISSED_PINGS: u32 = 3;
+
+fn is_spectrum(packet: &[u8]) -> bool {
+    packet.first() == Some(&(PacketType::SpectrumData as u8))
 }
 
 #[derive(Clone)]
@@ -24,11 +78,126 @@ struct ClientInfo {
     last_seen: Instant,
     packet_counter: u32,
     compression_enabled: bool,
+    role: ClientRole,
+    connected_at: Instant,
+    max_duration: Option<Duration>,
+    pending_ping: Option<(u32, Instant)>,
+    rtt_ms: Option<f32>,
+    missed_pings: u32,
+    region: Option<FrameRegion>,
+    bandwidth: ClientBandwidth,
+}
+
+impl ClientInfo {
+    fn new(
+        addr: SocketAddr,
+        compression_enabled: bool,
+        role: ClientRole,
+        max_duration: Option<Duration>,
+    ) -> Self {
+        Self {
+            addr,
+            last_seen: Instant::now(),
+            packet_counter: 0,
+            compression_enabled,
+            role,
+            connected_at: Instant::now(),
+            max_duration,
+            pending_ping: None,
+            rtt_ms: None,
+            missed_pings: 0,
+            region: None,
+            bandwidth: ClientBandwidth::new(0),
+        }
+    }
+
+    fn start_ping(&mut self, sequence: u32) {
+        if self.pending_ping.is_some() {
+            self.missed_pings += 1;
+        }
+        self.pending_ping = Some((sequence, Instant::now()));
+    }
+
+    fn record_pong(&mut self, sequence: u32) -> bool {
+        match self.pending_ping {
+            Some((pending, sent)) if pending == sequence => {
+                self.rtt_ms = Some(sent.elapsed().as_secs_f32() * 1000.0);
+                self.pending_ping = None;
+                self.missed_pings = 0;
+                self.last_seen = Instant::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn session_expired(&self) -> bool {
+        self.max_duration
+            .is_some_and(|max| self.connected_at.elapsed() > max)
+    }
+
+    fn is_alive(&self) -> bool {
+        self.missed_pings < MAX_MISSED_PINGS && self.last_seen.elapsed() < Duration::from_secs(60)
+    }
+
+    fn summary(&self) -> ClientSummary {
+        ClientSummary {
+            addr: self.addr.to_string(),
+            compression: self.compression_enabled,
+            role: self.role,
+            session_secs: self.connected_at.elapsed().as_secs(),
+            max_duration_secs: self.max_duration.map(|d| d.as_secs()),
+            rtt_ms: self.rtt_ms,
+            missed_pings: self.missed_pings,
+            region: self.region,
+            last_seen_ms: self.last_seen.elapsed().as_millis() as u64,
+            bytes_sent: self.bandwidth.total_bytes(),
+            kbps: self.bandwidth.kbps(),
+            max_kbps: self.bandwidth.cap_kbps(),
+            stream_mode: self.bandwidth.mode(),
+            frames_dropped: self.bandwidth.frames_dropped(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientSummary {
+    pub addr: String,
+    pub compression: bool,
+    pub role: ClientRole,
+    pub session_secs: u64,
+    pub max_duration_secs: Option<u64>,
+    pub rtt_ms: Option<f32>,
+    pub missed_pings: u32,
+    pub region: Option<FrameRegion>,
+    pub last_seen_ms: u64,
+    pub bytes_sent: u64,
+    pub kbps: f32,
+    pub max_kbps: Option<u32>,
+    pub stream_mode: StreamMode,
+    pub frames_dropped: u64,
+}
+
+// Read-only view of the connected clients for local UIs, usable after the server is moved
+#[cfg(feature = "tui")]
+#[derive(Clone)]
+pub struct ClientList(Arc<Mutex<Vec<ClientInfo>>>);
+
+#[cfg(feature = "tui")]
+impl ClientList {
+    pub fn summaries(&self) -> Vec<ClientSummary> {
+        self.0.lock().iter().map(ClientInfo::summary).collect()
+    }
 }
 
 impl UdpServer {
-    pub fn new(state: Arc<AppState>) -> Result<Self> {
-        let socket = match UdpSocket::bind("0.0.0.0:8081") {
+    pub fn new(
+        state: Arc<AppState>,
+        stream_config: StreamConfig,
+        accept_config_push: bool,
+        capture: Option<PacketCapture>,
+    ) -> Result<Self> {
+        let socket = match UdpSocket::bind(("0.0.0.0", COMMAND_PORT)) {
             Ok(s) => s,
             Err(e) => {
                 return Err(e.into());
@@ -67,11 +236,21 @@ impl UdpServer {
 
         Ok(Self {
             state,
-            socket,
+            socket: CaptureSocket::new(socket, capture),
             clients: Arc::new(Mutex::new(Vec::new())),
+            stream_config,
+            command_log: Arc::new(Mutex::new(CommandLog::new())),
+            tape: Mutex::new(ControlTape::new()),
+            config_transfers: Mutex::new(ConfigTransfers::new()),
+            accept_config_push,
         })
     }
 
+    #[cfg(feature = "tui")]
+    pub fn client_list(&self) -> ClientList {
+        ClientList(self.clients.clone())
+    }
+
     pub fn run(self) -> Result<()> {
         let state = self.state.clone();
         let clients = self.clients.clone();
@@ -87,42 +266,134 @@ impl UdpServer {
     }
 
     fn sender_loop(
-        socket: UdpSocket,
+        socket: CaptureSocket,
         state: Arc<AppState>,
         clients: Arc<Mutex<Vec<ClientInfo>>>,
     ) -> Result<()> {
-        let mut processor = UdpFrameProcessor::new();
+        // One processor per encoding group so each keeps its own change detection and buffers
+        let mut processors: HashMap<(bool, Option<FrameRegion>), UdpFrameProcessor> =
+            HashMap::new();
+        let mut send_buffer = Vec::with_capacity(MAX_PACKET_SIZE);
         let mut last_cleanup = Instant::now();
+        let mut last_ping = Instant::now();
+        let mut ping_sequence = 0u32;
         let mut stats = TransmissionStats::new();
+        let mut streaming = false;
 
         loop {
+            if last_ping.elapsed() > PING_INTERVAL {
+                ping_sequence = ping_sequence.wrapping_add(1);
+                Self::expire_sessions(&socket, &clients);
+                Self::send_heartbeats(&socket, &clients, ping_sequence);
+                last_ping = Instant::now();
+            }
+
             if last_cleanup.elapsed() > Duration::from_secs(30) {
                 let mut clients_list = clients.lock();
                 clients_list.retain(|c| c.last_seen.elapsed() < Duration::from_secs(60));
                 last_cleanup = Instant::now();
             }
 
-            let frame = state.led_frame.lock().clone();
+            let alerts: Vec<_> = state.output_alerts.lock().drain(..).collect();
+            for alert in alerts {
+                Self::broadcast_json(&socket, &clients, PacketType::OutputAlert, &alert);
+            }
+
+            let reloads: Vec<_> = state.reload_events.lock().drain(..).collect();
+            for reload in reloads {
+                if reload.applied.iter().any(|section| section == "palettes") {
+                    let color_modes = state.palettes.lock().to_json();
+                    Self::broadcast_json(&socket, &clients, PacketType::ColorModes, &color_modes);
+                }
+                Self::broadcast_json(&socket, &clients, PacketType::ConfigReload, &reload);
+            }
+
+            let events: Vec<_> = state.alert_events.lock().drain(..).collect();
+            for event in events {
+                Self::broadcast_json(&socket, &clients, PacketType::AlertEvent, &event);
+            }
+
+            let beats: Vec<_> = state.beat_events.lock().drain(..).collect();
+            for beat in beats {
+                Self::broadcast_json(&socket, &clients, PacketType::BeatEvent, &beat);
+            }
+
+            let LedFrame {
+                id: frame_id,
+                pixels: frame,
+            } = state.led_frame.lock().clone();
+            let mips = state.frame_mips.lock().clone();
             let spectrum = state.spectrum.lock().clone();
 
             let clients_snapshot = clients.lock().clone();
+            let client_count = clients_snapshot.len();
+            let mut queued_bytes = 0;
+            let mut packets_sent = 0;
+            let mut bytes_sent = 0;
 
-            for mut client in clients_snapshot {
-                let packets = processor.prepare_packets(
-                    &frame,
-                    &spectrum,
-                    client.packet_counter,
-                    client.compression_enabled,
-                );
+            // The last client leaving ends the show
+            if streaming && client_count == 0 {
+                show_report::finish(&state, "stream_stopped");
+            }
+            streaming = client_count > 0;
+            let send_start = Instant::now();
+
+            let mut groups: HashMap<(bool, Option<FrameRegion>), Vec<ClientInfo>> = HashMap::new();
+            for client in clients_snapshot {
+                groups
+                    .entry((client.compression_enabled, client.region))
+                    .or_default()
+                    .push(client);
+            }
+            processors.retain(|key, _| groups.contains_key(key));
+            // The LED output wins a saturated NIC: no preview while Art-Net sends meet backpressure
+            if client_count > 0 && state.output_priority.lock().preview_yields(send_start) {
+                groups.clear();
+            }
+
+            let mut counters = Vec::with_capacity(client_count);
+            for ((compressed, region), group) in groups {
+                let packets = processors
+                    .entry((compressed, region))
+                    .or_insert_with(|| UdpFrameProcessor::with_region(region, state.matrix.width))
+                    .encode_packets(&frame, &mips, &spectrum, compressed);
+
+                let group_bytes: usize = packets.iter().map(|p| p.len()).sum();
+                if !state.resource_budget.lock().allow_client_bytes(group_bytes) {
+                    continue;
+                }
+
+                // Frames are held back for clients over their cap, the spectrum always goes out
+                let frame_bytes: usize = packets
+                    .iter()
+                    .filter(|p| !is_spectrum(p))
+                    .map(|p| p.len())
+                    .sum();
+
+                for mut client in group {
+                    queued_bytes += group_bytes;
+                    let now = Instant::now();
+                    let send_frame =
+                        frame_bytes == 0 || client.bandwidth.allow_frame(frame_bytes, now);
 
-                for packet in packets {
-                    if let Ok(packet_data) = packet.to_bytes() {
-                        match socket.send_to(&packet_data, client.addr) {
-                            Ok(bytes_sent) => {
-                                stats.add_packet(bytes_sent);
+                    for packet in packets {
+                        if !send_frame && !is_spectrum(packet) {
+                            continue;
+                        }
+                        send_buffer.clear();
+                        send_buffer.extend_from_slice(packet);
+                        stamp_sequence(&mut send_buffer, client.packet_counter);
+
+                        match socket.send_to(&send_buffer, client.addr) {
+                            Ok(sent) => {
+                                stats.add_packet(sent);
+                                packets_sent += 1;
+                                bytes_sent += sent;
+                                client.bandwidth.record(sent, now);
                                 client.packet_counter = client.packet_counter.wrapping_add(1);
                             }
-                            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            Err(e) if is_backpressure(&e) => {
+                                state.output_priority.lock().record_preview_blocked();
                                 break;
                             }
                             Err(_) => {
@@ -130,9 +401,33 @@ impl UdpServer {
                             }
                         }
                     }
+
+                    counters.push((client.addr, client.packet_counter, client.bandwidth));
+                }
+            }
+
+            {
+                let mut clients_list = clients.lock();
+                for (addr, packet_counter, bandwidth) in counters {
+                    if let Some(client) = clients_list.iter_mut().find(|c| c.addr == addr) {
+                        client.packet_counter = packet_counter;
+                        client.bandwidth = bandwidth;
+                    }
                 }
             }
 
+            state
+                .resource_budget
+                .lock()
+                .record_clients(clients.lock().len(), queued_bytes);
+            state.frame_tracer.lock().record_network(
+                frame_id,
+                client_count,
+                packets_sent,
+                send_start.elapsed(),
+            );
+            state.show.lock().record_stream(packets_sent, bytes_sent);
+
             if stats.should_print() {
                 stats.print_and_reset();
             }
@@ -141,12 +436,77 @@ impl UdpServer {
         }
     }
 
+    // Zero on both sides means unlimited; otherwise the stricter of the role cap and the client request wins
+    fn session_limit(&self, request: &ConnectRequest) -> Option<Duration> {
+        let role_cap = match request.role {
+            ClientRole::Monitor => self.stream_config.monitor_max_secs,
+            ClientRole::Display => self.stream_config.display_max_secs,
+        };
+        let requested = request.max_duration_secs as u64;
+
+        let secs = match (role_cap, requested) {
+            (0, 0) => return None,
+            (0, requested) => requested,
+            (role_cap, 0) => role_cap,
+            (role_cap, requested) => role_cap.min(requested),
+        };
+        Some(Duration::from_secs(secs))
+    }
+
+    fn expire_sessions(socket: &CaptureSocket, clients: &Mutex<Vec<ClientInfo>>) {
+        let mut clients_list = clients.lock();
+        let Ok(disconnect) = UdpPacket::new(PacketType::Disconnect, 0, vec![]).to_bytes() else {
+            return;
+        };
+
+        clients_list.retain(|c| {
+            if c.session_expired() {
+                let _ = socket.send_to(&disconnect, c.addr);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn send_heartbeats(socket: &CaptureSocket, clients: &Mutex<Vec<ClientInfo>>, sequence: u32) {
+        let mut clients_list = clients.lock();
+        clients_list.retain(|c| c.is_alive());
+
+        let Ok(ping) = UdpPacket::new_ping(sequence).to_bytes() else {
+            return;
+        };
+        for client in clients_list.iter_mut() {
+            client.start_ping(sequence);
+            let _ = socket.send_to(&ping, client.addr);
+        }
+    }
+
+    fn broadcast_json(
+        socket: &CaptureSocket,
+        clients: &Mutex<Vec<ClientInfo>>,
+        packet_type: PacketType,
+        value: &impl Serialize,
+    ) {
+        let payload = serde_json::to_vec(value).unwrap_or_default();
+        let Ok(data) = UdpPacket::new(packet_type, 0, payload).to_bytes() else {
+            return;
+        };
+        for client in clients.lock().iter() {
+            let _ = socket.send_to(&data, client.addr);
+        }
+    }
+
     fn receiver_loop(&self) -> Result<()> {
         let mut buf = [0u8; 1024];
         let mut packets_received = 0u64;
         let mut last_log = Instant::now();
 
         loop {
+            self.replay_tape();
+            self.run_schedules();
+            self.run_triggers();
+
             match self.socket.recv_from(&mut buf) {
                 Ok((len, addr)) => {
                     packets_received += 1;
@@ -167,19 +527,71 @@ impl UdpServer {
         }
     }
 
+    fn replay_tape(&self) {
+        let Some((reply_to, commands)) = self.tape.lock().due() else {
+            return;
+        };
+        for command in commands {
+            self.process_command(command, reply_to, 0);
+        }
+        if !self.tape.lock().is_replaying() {
+            crash::log("📼 Tape replay finished");
+        }
+    }
+
+    fn run_schedules(&self) {
+        let due = self.state.scheduler.lock().due(local_time());
+        for (rule, command) in due {
+            crash::log(format!("⏰ Schedule: {}", rule));
+            // Nobody to reply to
+            self.process_command(command, SocketAddr::from(([0, 0, 0, 0], 0)), 0);
+        }
+    }
+
+    fn run_triggers(&self) {
+        let triggered = std::mem::take(&mut *self.state.triggered.lock());
+        for command in triggered {
+            self.process_command(command, SocketAddr::from(([0, 0, 0, 0], 0)), 0);
+        }
+    }
+
+    fn save_schedules(&self) {
+        let config = ScheduleConfig {
+            rules: self.state.scheduler.lock().rules(),
+        };
+        if let Err(e) = Config::save_section("schedule", &config) {
+            crash::log(format!("⚠️ Failed to save schedules: {}", e));
+        }
+    }
+
     fn handle_packet(&self, packet: UdpPacket, addr: SocketAddr) {
         match packet.packet_type {
             PacketType::Connect => {
                 let mut clients = self.clients.lock();
                 if let Some(client) = clients.iter_mut().find(|c| c.addr == addr) {
                     client.last_seen = Instant::now();
+                } else if !self
+                    .state
+                    .resource_budget
+                    .lock()
+                    .allow_client(clients.len())
+                {
+                    self.send_nack(addr, packet.sequence, &Message::new("server_full"));
+                    return;
                 } else {
-                    clients.push(ClientInfo {
+                    self.state.energy_saver.lock().record_activity();
+                    let request = ConnectRequest::from_payload(&packet.payload);
+                    let mut client = ClientInfo::new(
                         addr,
-                        last_seen: Instant::now(),
-                        packet_counter: 0,
-                        compression_enabled: packet.flags.contains(PacketFlags::COMPRESSED),
+                        packet.flags.contains(PacketFlags::COMPRESSED),
+                        request.role,
+                        self.session_limit(&request),
+                    );
+                    client.bandwidth = ClientBandwidth::new(match request.role {
+                        ClientRole::Monitor => self.stream_config.monitor_max_kbps,
+                        ClientRole::Display => self.stream_config.display_max_kbps,
                     });
+                    clients.push(client);
                 }
 
                 let ack = UdpPacket::new_ack(packet.sequence);
@@ -196,8 +608,35 @@ impl UdpServer {
                     }
                 }
 
+                // Retransmitted commands are answered from the cache instead of being applied twice
+                let status = self.command_log.lock().check(addr, packet.sequence);
+                match status {
+                    CommandStatus::Duplicate(Some(reply)) => {
+                        let _ = self.socket.send_to(&reply, addr);
+                        return;
+                    }
+                    CommandStatus::Duplicate(None) => {
+                        if packet.flags.contains(PacketFlags::REQUIRES_ACK) {
+                            self.send_reply(
+                                addr,
+                                packet.sequence,
+                                &UdpPacket::new_ack(packet.sequence),
+                            );
+                        }
+                        return;
+                    }
+                    CommandStatus::New => {}
+                }
+
                 if let Some(command) = UdpCommand::from_payload(&packet.payload) {
-                    self.process_command(command);
+                    self.tape.lock().record(&command);
+                    self.process_command(command, addr, packet.sequence);
+                }
+
+                if packet.flags.contains(PacketFlags::REQUIRES_ACK)
+                    && !self.command_log.lock().has_reply(addr, packet.sequence)
+                {
+                    self.send_reply(addr, packet.sequence, &UdpPacket::new_ack(packet.sequence));
                 }
             }
 
@@ -208,19 +647,188 @@ impl UdpServer {
                 }
             }
 
+            PacketType::Pong => {
+                let mut clients = self.clients.lock();
+                if let Some(client) = clients.iter_mut().find(|c| c.addr == addr) {
+                    client.record_pong(packet.sequence);
+                }
+            }
+
+            PacketType::Ack => {
+                self.state.frame_tracer.lock().record_ack();
+            }
+
+            // Stamped on both sides so the client can put its stream stats on our clock
+            PacketType::TimeSync => {
+                let received = now_micros();
+                if let Some(reply) = TimeSample::reply(&packet.payload, received, now_micros()) {
+                    let reply = UdpPacket::new(PacketType::TimeSync, packet.sequence, reply);
+                    if let Ok(data) = reply.to_bytes() {
+                        let _ = self.socket.send_to(&data, addr);
+                    }
+                }
+            }
+
             PacketType::Disconnect => {
                 let mut clients = self.clients.lock();
                 clients.retain(|c| c.addr != addr);
             }
 
+            PacketType::ConfigTransfer => self.receive_config(&packet, addr),
+
             _ => {}
         }
     }
 
-    fn process_command(&self, command: UdpCommand) {
+    // Pushed configs are only taken by instances started with --accept-config-push
+    fn receive_config(&self, packet: &UdpPacket, addr: SocketAddr) {
+        let reply = if !self.accept_config_push {
+            (packet.fragment_id == 0).then(|| Err(anyhow::anyhow!("Config push not accepted")))
+        } else {
+            self.config_transfers.lock().receive(addr, packet)
+        };
+
+        let reply = match reply.map(|bundle| bundle.and_then(|bundle| self.apply_config(bundle))) {
+            None => return,
+            Some(Ok(())) => {
+                crash::log(format!(
+                    "📥 Config received from {}, restart to apply it",
+                    addr
+                ));
+                UdpPacket::new_ack(packet.sequence)
+            }
+            Some(Err(e)) => {
+                crash::log(format!("⚠️ Config push from {} refused: {}", addr, e));
+                let message = Message::from_error(&e);
+                let payload = serde_json::to_vec(&message).unwrap_or_default();
+                UdpPacket::new(PacketType::Nack, packet.sequence, payload)
+            }
+        };
+        if let Ok(data) = reply.to_bytes() {
+            let _ = self.socket.send_to(&data, addr);
+        }
+    }
+
+    fn apply_config(&self, bundle: ConfigBundle) -> Result<()> {
+        bundle.install_config()?;
+        let color_modes = {
+            let mut palettes = self.state.palettes.lock();
+            palettes.replace(bundle.palettes)?;
+            palettes.to_json()
+        };
+        self.broadcast_color_modes(&color_modes);
+        Ok(())
+    }
+
+    fn set_client_region(&self, addr: SocketAddr, region: Option<FrameRegion>) -> bool {
+        match self.clients.lock().iter_mut().find(|c| c.addr == addr) {
+            Some(client) => {
+                client.region = region;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn send_reply(&self, addr: SocketAddr, sequence: u32, packet: &UdpPacket) {
+        Self::reply_from(&self.socket, &self.command_log, addr, sequence, packet);
+    }
+
+    // Also for replies sent from worker threads, so retransmits still get the cached answer
+    fn reply_from(
+        socket: &CaptureSocket,
+        command_log: &Mutex<CommandLog>,
+        addr: SocketAddr,
+        sequence: u32,
+        packet: &UdpPacket,
+    ) {
+        if let Ok(data) = packet.to_bytes() {
+            let _ = socket.send_to(&data, addr);
+            command_log.lock().record_reply(addr, sequence, &data);
+        }
+    }
+
+    fn send_response(&self, addr: SocketAddr, sequence: u32, value: &serde_json::Value) {
+        let payload = serde_json::to_vec(value).unwrap_or_default();
+        self.send_reply(addr, sequence, &UdpPacket::new_response(sequence, payload));
+    }
+
+    fn broadcast(&self, packet: &UdpPacket) {
+        let Ok(data) = packet.to_bytes() else {
+            return;
+        };
+        for client in self.clients.lock().iter() {
+            let _ = self.socket.send_to(&data, client.addr);
+        }
+    }
+
+    fn broadcast_color_modes(&self, color_modes: &serde_json::Value) {
+        let payload = serde_json::to_vec(color_modes).unwrap_or_default();
+        self.broadcast(&UdpPacket::new(PacketType::ColorModes, 0, payload));
+    }
+
+    fn room_calibration(&self) -> serde_json::Value {
+        let calibration = self.state.calibration.lock();
+        serde_json::json!({
+            "capturing": calibration.is_capturing(),
+            "progress": calibration.progress(),
+            "curve": calibration.curve(),
+        })
+    }
+
+    fn decibel_scale(&self) -> serde_json::Value {
+        let decibels = self.state.decibels.lock();
+        serde_json::json!({
+            "config": decibels.config(),
+            "floor_db": decibels.floor_db(),
+            "capturing": decibels.is_capturing(),
+        })
+    }
+
+    fn save_smoothing(&self, config: &SmoothingConfig) {
+        if let Err(e) = Config::save_section("smoothing", config) {
+            crash::log(format!("⚠️ Failed to save smoothing: {}", e));
+        }
+    }
+
+    // Saved once accepted, so the balance survives a restart
+    fn send_color_correction(
+        &self,
+        addr: SocketAddr,
+        sequence: u32,
+        result: Result<ColorCorrectionConfig>,
+    ) {
+        match result {
+            Ok(correction) => {
+                if let Err(e) = Config::save_section("color_correction", &correction) {
+                    crash::log(format!("⚠️ Failed to save color correction: {}", e));
+                }
+                self.send_response(addr, sequence, &serde_json::json!(correction));
+            }
+            Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+        }
+    }
+
+    // JSON code, text and arguments, so clients can translate the reason and branch on it
+    fn send_nack(&self, addr: SocketAddr, sequence: u32, message: &Message) {
+        let payload = serde_json::to_vec(message).unwrap_or_default();
+        let nack = UdpPacket::new(PacketType::Nack, sequence, payload);
+        self.send_reply(addr, sequence, &nack);
+    }
+
+    fn process_command(&self, command: UdpCommand, addr: SocketAddr, sequence: u32) {
+        // Queries from dashboards don't keep the wall awake, operator moves do
+        if is_recordable(&command) {
+            self.state.energy_saver.lock().record_activity();
+        }
+
         match command {
             UdpCommand::SetEffect(effect_id) => {
-                self.state.effect_engine.lock().set_effect(effect_id);
+                let mut engine = self.state.effect_engine.lock();
+                match engine.check_effect(effect_id) {
+                    Ok(()) => engine.set_effect(effect_id),
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
             }
 
             UdpCommand::SetColorMode(mode) => {
@@ -231,7 +839,960 @@ impl UdpServer {
                 self.state.effect_engine.lock().set_custom_color(r, g, b);
             }
 
-            UdpCommand::SetParameter(name, value) => {}
+            UdpCommand::SetParameter(name, value) => {
+                let Ok(value) = value.trim().parse::<f32>() else {
+                    let message = Message::new("invalid_value").arg("name", &name);
+                    self.send_nack(addr, sequence, &message);
+                    return;
+                };
+
+                let result = {
+                    let mut engine = self.state.effect_engine.lock();
+                    let result = match name.as_str() {
+                        "input_trim" => {
+                            engine.set_input_trim(value);
+                            Ok(())
+                        }
+                        "sensitivity" => engine.set_effect_sensitivity(value),
+                        // Anything else is a knob of the current effect
+                        _ => engine.set_effect_parameter(&name, value).map(|_| ()),
+                    };
+                    result.map(|_| {
+                        (
+                            engine.sensitivity_config().clone(),
+                            engine.effect_parameters().clone(),
+                            engine.parameters(),
+                        )
+                    })
+                };
+                match result {
+                    Ok((sensitivity, effect_parameters, parameters)) => {
+                        let saved = match name.as_str() {
+                            "input_trim" | "sensitivity" => {
+                                Config::save_section("sensitivity", &sensitivity)
+                            }
+                            _ => Config::save_section("effect_parameters", &effect_parameters),
+                        };
+                        if let Err(e) = saved {
+                            crash::log(format!("⚠️ Failed to save {}: {}", name, e));
+                        }
+                        self.send_response(addr, sequence, &parameters);
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::GetParameters => {
+                let parameters = self.state.effect_engine.lock().parameters();
+                self.send_response(addr, sequence, &parameters);
+            }
+
+            UdpCommand::LearnRoom(seconds) => {
+                let duration = match seconds {
+                    0 => calibration::DEFAULT_CAPTURE,
+                    seconds => Duration::from_secs(seconds as u64).min(calibration::MAX_CAPTURE),
+                };
+                self.state.calibration.lock().start_capture(duration);
+                crash::log(format!("🎧 Learning the room for {} s", duration.as_secs()));
+                self.send_response(addr, sequence, &self.room_calibration());
+            }
+
+            UdpCommand::GetRoomCalibration => {
+                self.send_response(addr, sequence, &self.room_calibration());
+            }
+
+            UdpCommand::ClearRoomCalibration => {
+                self.state.calibration.lock().clear();
+                if let Err(e) = Config::save_section("spectrum", &SpectrumConfig::default()) {
+                    crash::log(format!("⚠️ Failed to save room calibration: {}", e));
+                }
+                self.send_response(addr, sequence, &self.room_calibration());
+            }
+
+            UdpCommand::SetBlackout(enabled) => {
+                self.state.output.lock().set_blackout(enabled);
+            }
+
+            UdpCommand::SetMetronome(enabled) => {
+                self.state.effect_engine.lock().set_metronome(enabled);
+            }
+
+            UdpCommand::SetAccent(kind) => {
+                let mut engine = self.state.effect_engine.lock();
+                let kind = AccentKind::from_u8(kind);
+                match kind.map_or(Ok(()), |kind| engine.check_accent(kind)) {
+                    Ok(()) => engine.set_accent(kind),
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::GetAudioFeatures => {
+                let features = {
+                    let engine = self.state.effect_engine.lock();
+                    serde_json::json!({
+                        "bpm": engine.bpm(),
+                        "section": engine.section(),
+                        "tonalness": engine.tonalness(),
+                    })
+                };
+                self.send_response(addr, sequence, &features);
+            }
+
+            UdpCommand::Handover(seconds) => {
+                // Fading out and exiting is for a new instance on this machine to ask
+                if !addr.ip().is_loopback() {
+                    self.send_nack(addr, sequence, &Message::new("handover_localhost_only"));
+                    return;
+                }
+                let handover_state = HandoverState::capture(&self.state);
+                self.send_response(addr, sequence, &serde_json::json!(handover_state));
+                handover::release(&self.state, seconds);
+            }
+
+            UdpCommand::GetProtocolSchema => {
+                self.send_response(addr, sequence, &schema::protocol_schema());
+            }
+
+            UdpCommand::SetPlaylist(playlist) => {
+                let result = self.state.effect_engine.lock().set_playlist(&playlist);
+                match result {
+                    Ok(()) => {
+                        crash::log(format!(
+                            "🔁 Playlist set: {} entries",
+                            playlist.entries.len()
+                        ));
+                        if let Err(e) = Config::save_section("playlist", &playlist) {
+                            crash::log(format!("⚠️ Failed to save the playlist: {}", e));
+                        }
+                        let status = self.state.effect_engine.lock().playlist_status();
+                        self.send_response(addr, sequence, &status);
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::StartPlaylist => {
+                let result = {
+                    let mut engine = self.state.effect_engine.lock();
+                    engine.start_playlist().map(|()| engine.playlist_status())
+                };
+                match result {
+                    Ok(status) => {
+                        crash::log("🔁 Playlist started");
+                        self.send_response(addr, sequence, &status);
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::StopPlaylist => {
+                let status = {
+                    let mut engine = self.state.effect_engine.lock();
+                    engine.stop_playlist();
+                    engine.playlist_status()
+                };
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::StartCrowdNoise(peak, attack_secs, hold_secs, release_secs) => {
+                let envelope = CrowdEnvelope {
+                    peak,
+                    attack_secs,
+                    hold_secs,
+                    release_secs,
+                };
+                let result = {
+                    let mut crowd = self.state.crowd_noise.lock();
+                    crowd.start(envelope).map(|()| crowd.status())
+                };
+                match result {
+                    Ok(status) => {
+                        crash::log(format!(
+                            "👏 Simulated crowd: peak {:.2}, {:.1} s",
+                            peak,
+                            attack_secs + hold_secs + release_secs
+                        ));
+                        self.send_response(addr, sequence, &status);
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::StopCrowdNoise => {
+                let status = {
+                    let mut crowd = self.state.crowd_noise.lock();
+                    crowd.stop();
+                    crowd.status()
+                };
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::GetBpm => {
+                let beat = {
+                    let engine = self.state.effect_engine.lock();
+                    serde_json::json!({
+                        "bpm": engine.bpm(),
+                        "phase": engine.beat_phase(),
+                    })
+                };
+                self.send_response(addr, sequence, &beat);
+            }
+
+            UdpCommand::GetEffects(tag) => {
+                // An empty tag lists every effect
+                let tag = match tag.trim() {
+                    "" => None,
+                    name => match EffectTag::from_name(name) {
+                        Some(tag) => Some(tag),
+                        None => {
+                            let tags: Vec<_> = EffectTag::ALL.iter().map(|t| t.name()).collect();
+                            let message = Message::new("unknown_effect_tag")
+                                .arg("name", name)
+                                .arg("options", tags.join(", "));
+                            self.send_nack(addr, sequence, &message);
+                            return;
+                        }
+                    },
+                };
+                let effects = self.state.effect_engine.lock().effect_list(tag);
+                self.send_response(addr, sequence, &effects);
+            }
+
+            UdpCommand::SavePreset(name) => {
+                let preset = Preset::capture(
+                    &name,
+                    &self.state.effect_engine.lock(),
+                    &self.state.output.lock(),
+                );
+                let result = {
+                    let mut presets = self.state.presets.lock();
+                    presets.upsert(preset).map(|_| presets.to_json())
+                };
+                match result {
+                    Ok(presets) => self.send_response(addr, sequence, &presets),
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::LoadPreset(name) => {
+                let Some(preset) = self.state.presets.lock().get(&name).cloned() else {
+                    let message = Message::new("unknown_preset").arg("name", &name);
+                    self.send_nack(addr, sequence, &message);
+                    return;
+                };
+                let result = preset.apply(
+                    &mut self.state.effect_engine.lock(),
+                    &mut self.state.output.lock(),
+                );
+                match result {
+                    Ok(()) => {
+                        // The knobs outlive the preset, like a SetParameter would
+                        let saved =
+                            Config::save_section("effect_parameters", &preset.effect_parameters);
+                        if let Err(e) = saved {
+                            crash::log(format!("⚠️ Failed to save effect_parameters: {}", e));
+                        }
+                        crash::log(format!("🎛️ Loaded preset {}", preset.name));
+                        self.send_response(addr, sequence, &serde_json::json!(preset));
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::ListPresets => {
+                let presets = self.state.presets.lock().to_json();
+                self.send_response(addr, sequence, &presets);
+            }
+
+            UdpCommand::DeletePreset(name) => {
+                let result = {
+                    let mut presets = self.state.presets.lock();
+                    presets
+                        .remove(&name)
+                        .map(|removed| (removed, presets.to_json()))
+                };
+                match result {
+                    Ok((true, presets)) => self.send_response(addr, sequence, &presets),
+                    Ok((false, _)) => {
+                        let message = Message::new("unknown_preset").arg("name", &name);
+                        self.send_nack(addr, sequence, &message)
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::SetHud(enabled) => {
+                self.state.output.lock().hud = enabled;
+            }
+
+            UdpCommand::SetSmoothingProfile(profile) => {
+                let config = {
+                    let mut smoothing = self.state.smoothing.lock();
+                    smoothing.set_profile(profile);
+                    smoothing.config().clone()
+                };
+                self.save_smoothing(&config);
+                self.send_response(addr, sequence, &serde_json::json!(config));
+            }
+
+            UdpCommand::SetBandSmoothing(group, attack, release) => {
+                let config = {
+                    let mut smoothing = self.state.smoothing.lock();
+                    smoothing.set_band(group, attack, release);
+                    smoothing.config().clone()
+                };
+                self.save_smoothing(&config);
+                self.send_response(addr, sequence, &serde_json::json!(config));
+            }
+
+            UdpCommand::GetSmoothing => {
+                let config = self.state.smoothing.lock().config().clone();
+                self.send_response(addr, sequence, &serde_json::json!(config));
+            }
+
+            UdpCommand::SetDecibelScale(enabled, min_db, max_db) => {
+                let config = {
+                    let mut decibels = self.state.decibels.lock();
+                    decibels.set_window(enabled, min_db, max_db);
+                    decibels.config().clone()
+                };
+                if let Err(e) = Config::save_section("decibels", &config) {
+                    crash::log(format!("⚠️ Failed to save dB scale: {}", e));
+                }
+                self.send_response(addr, sequence, &self.decibel_scale());
+            }
+
+            UdpCommand::LearnNoiseFloor(seconds) => {
+                let duration = match seconds {
+                    0 => decibels::DEFAULT_CAPTURE,
+                    seconds => Duration::from_secs(seconds as u64).min(decibels::MAX_CAPTURE),
+                };
+                self.state.decibels.lock().start_noise_capture(duration);
+                crash::log(format!(
+                    "🔇 Measuring the noise floor for {} s",
+                    duration.as_secs()
+                ));
+                self.send_response(addr, sequence, &self.decibel_scale());
+            }
+
+            UdpCommand::GetDecibelScale => {
+                self.send_response(addr, sequence, &self.decibel_scale());
+            }
+
+            UdpCommand::Identify(controller, strip, pixel) => {
+                match Identify::new(
+                    &self.state.matrix,
+                    controller as usize,
+                    strip as usize,
+                    pixel.map(usize::from),
+                ) {
+                    Ok(identify) => {
+                        self.state.output.lock().identify = Some(identify);
+                        crash::log(format!(
+                            "🔦 Identifying controller {} strip {} {}",
+                            controller,
+                            strip,
+                            pixel.map_or("(whole strip)".to_string(), |p| format!("pixel {}", p))
+                        ));
+                        self.send_response(addr, sequence, &serde_json::json!(identify));
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::ClearIdentify => {
+                self.state.output.lock().identify = None;
+            }
+
+            // Applies to the streaming client sending it, so it must come from the stream socket
+            UdpCommand::SetFrameRegion(x, y, width, height) => {
+                match FrameRegion::new(x, y, width, height, &self.state.matrix) {
+                    Ok(region) => {
+                        if self.set_client_region(addr, Some(region)) {
+                            self.send_response(addr, sequence, &serde_json::json!(region));
+                        } else {
+                            self.send_nack(addr, sequence, &Message::new("not_streaming_client"));
+                        }
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::ClearFrameRegion => {
+                self.set_client_region(addr, None);
+            }
+
+            // Size 0 answers colors only; 16 or 32 adds a thumbnail from the shared mip chain
+            UdpCommand::GetFrameSummary(size) => {
+                let mips = self.state.frame_mips.lock().clone();
+                let thumbnail = match size {
+                    0 => None,
+                    16 | 32 => mips.level(size as usize),
+                    _ => {
+                        self.send_nack(addr, sequence, &Message::new("invalid_thumbnail_size"));
+                        return;
+                    }
+                };
+                let summary = serde_json::json!({
+                    "average": mips.average,
+                    "dominant": mips.dominant,
+                    "size": size,
+                    "thumbnail": thumbnail,
+                });
+                self.send_response(addr, sequence, &summary);
+            }
+
+            // The path is resolved on the backend machine
+            UdpCommand::ExportSpectrogram(path) => {
+                if path.trim().is_empty() {
+                    self.send_nack(addr, sequence, &Message::new("missing_png_path"));
+                    return;
+                }
+                let spectrogram = self.state.spectrogram.lock();
+                match spectrogram.export_png(Path::new(&path)) {
+                    Ok(()) => {
+                        crash::log(format!(
+                            "🎼 Spectrogram ({:.0} s) exported to {}",
+                            spectrogram.recorded_secs(),
+                            path
+                        ));
+                        let response = serde_json::json!({
+                            "path": path,
+                            "recorded_secs": spectrogram.recorded_secs(),
+                        });
+                        self.send_response(addr, sequence, &response);
+                    }
+                    Err(e) => {
+                        let message = Message::new("spectrogram_export_failed").arg("detail", e);
+                        self.send_nack(addr, sequence, &message)
+                    }
+                }
+            }
+
+            UdpCommand::GetSpectrogram(seconds, columns) => {
+                let spectrogram = self
+                    .state
+                    .spectrogram
+                    .lock()
+                    .query(seconds as u32, columns as usize);
+                self.send_response(addr, sequence, &spectrogram);
+            }
+
+            UdpCommand::StartCommissioning => {
+                let status = self
+                    .state
+                    .commissioning
+                    .lock()
+                    .insert(Commissioning::new(&self.state.matrix))
+                    .status();
+                crash::log("🧪 Commissioning started");
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::MarkCommissioning(passed, note) => {
+                let status = self.state.commissioning.lock().as_mut().map(|session| {
+                    session.mark(passed, note);
+                    session.status()
+                });
+                match status {
+                    Some(status) => self.send_response(addr, sequence, &status),
+                    None => self.send_nack(addr, sequence, &Message::new("no_commissioning")),
+                }
+            }
+
+            UdpCommand::CommissioningBack => {
+                let status = self.state.commissioning.lock().as_mut().map(|session| {
+                    session.back();
+                    session.status()
+                });
+                match status {
+                    Some(status) => self.send_response(addr, sequence, &status),
+                    None => self.send_nack(addr, sequence, &Message::new("no_commissioning")),
+                }
+            }
+
+            UdpCommand::GetCommissioning => {
+                let status = self
+                    .state
+                    .commissioning
+                    .lock()
+                    .as_ref()
+                    .map(Commissioning::status)
+                    .unwrap_or_else(|| serde_json::json!({ "active": false }));
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::FinishCommissioning => {
+                let Some(session) = self.state.commissioning.lock().take() else {
+                    self.send_nack(addr, sequence, &Message::new("no_commissioning"));
+                    return;
+                };
+                match session.write_report(Path::new(commissioning::REPORT_DIR)) {
+                    Ok(path) => {
+                        crash::log(format!(
+                            "🧪 Commissioning report written to {}",
+                            path.display()
+                        ));
+                        let mut status = session.status();
+                        status["active"] = false.into();
+                        status["report"] = path.display().to_string().into();
+                        self.send_response(addr, sequence, &status);
+                    }
+                    Err(e) => {
+                        // Keep the marks so the report can be retried once the disk is fixed
+                        *self.state.commissioning.lock() = Some(session);
+                        let message = Message::new("report_write_failed").arg("detail", e);
+                        self.send_nack(addr, sequence, &message);
+                    }
+                }
+            }
+
+            UdpCommand::SetStrobe(enabled) => {
+                let mut output = self.state.output.lock();
+                if enabled && output.strobe_blocked {
+                    let message = Message::new("blacklisted").arg("name", "strobe");
+                    self.send_nack(addr, sequence, &message);
+                    return;
+                }
+                output.strobe = enabled;
+            }
+
+            UdpCommand::NextEffect => {
+                self.state.effect_engine.lock().next_effect();
+            }
+
+            UdpCommand::PreviousEffect => {
+                self.state.effect_engine.lock().previous_effect();
+            }
+
+            UdpCommand::SetBrightness(brightness) => {
+                self.state.output.lock().set_brightness(brightness);
+            }
+
+            UdpCommand::SetXy(x, y) => {
+                let mut pad = self.state.xy_pad.lock();
+                pad.apply(
+                    x,
+                    y,
+                    &mut self.state.effect_engine.lock(),
+                    &mut self.state.output.lock(),
+                );
+            }
+
+            UdpCommand::SetXyRouting(x_target, y_target) => {
+                self.state.xy_pad.lock().set_routing(x_target, y_target);
+            }
+
+            // ArtPoll waits up to half a second: answered from its own thread so pings and
+            // commands keep flowing meanwhile
+            UdpCommand::GetDiagnostics => {
+                let Ok(socket) = self.socket.try_clone() else {
+                    return;
+                };
+                let state = self.state.clone();
+                let command_log = self.command_log.clone();
+                thread::spawn(move || {
+                    let report = diagnostics::run(&state, socket.local_addr().ok());
+                    let payload = serde_json::to_vec(&report).unwrap_or_default();
+                    let response = UdpPacket::new_response(sequence, payload);
+                    Self::reply_from(&socket, &command_log, addr, sequence, &response);
+                });
+            }
+
+            UdpCommand::GetLastReport => match show_report::last_report() {
+                Some((report, path)) => {
+                    let response = serde_json::json!({
+                        "report": report,
+                        "json": path.display().to_string(),
+                        "html": path.with_extension("html").display().to_string(),
+                    });
+                    self.send_response(addr, sequence, &response);
+                }
+                None => self.send_nack(addr, sequence, &Message::new("no_show_report")),
+            },
+
+            UdpCommand::RecordTape(name) => {
+                let result = self.tape.lock().start_recording(&name);
+                match result {
+                    Ok(()) => {
+                        crash::log(format!("📼 Recording control tape {}", name));
+                        self.send_response(addr, sequence, &self.tape.lock().status());
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::StopTape => {
+                let result = self.tape.lock().stop();
+                match result {
+                    Ok(saved) => {
+                        if let Some(tape) = saved {
+                            crash::log(format!(
+                                "📼 Control tape {} saved ({} commands, {} s)",
+                                tape.name,
+                                tape.entries.len(),
+                                tape.duration_ms / 1000
+                            ));
+                        }
+                        self.send_response(addr, sequence, &self.tape.lock().status());
+                    }
+                    Err(e) => {
+                        let message = Message::new("tape_save_failed").arg("detail", e);
+                        self.send_nack(addr, sequence, &message)
+                    }
+                }
+            }
+
+            UdpCommand::ReplayTape(name) => {
+                let result = self
+                    .tape
+                    .lock()
+                    .start_replay(&name, addr)
+                    .map(|tape| (tape.entries.len(), tape.duration_ms));
+                match result {
+                    Ok((commands, duration_ms)) => {
+                        crash::log(format!(
+                            "📼 Replaying control tape {} ({} commands, {} s)",
+                            name,
+                            commands,
+                            duration_ms / 1000
+                        ));
+                        self.send_response(addr, sequence, &self.tape.lock().status());
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::GetTape => {
+                self.send_response(addr, sequence, &self.tape.lock().status());
+            }
+
+            UdpCommand::SwitchTargetSet(name) => {
+                let mut targets = self.state.output_targets.lock();
+                match targets.switch(&name) {
+                    Ok(()) => self.send_response(addr, sequence, &targets.status()),
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::GetTargetSets => {
+                let status = self.state.output_targets.lock().status();
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::SetAvDelay(ms) => {
+                let ms = ms as u32;
+                if ms > MAX_AV_DELAY_MS {
+                    let message = Message::new("av_delay_out_of_range").arg("max", MAX_AV_DELAY_MS);
+                    self.send_nack(addr, sequence, &message);
+                } else {
+                    let config = AvDelayConfig { ms };
+                    *self.state.av_delay.lock() = config;
+                    if let Err(e) = Config::save_section("av_delay", &config) {
+                        crash::log(format!("⚠️ Failed to save AV delay: {}", e));
+                    }
+                    self.send_response(addr, sequence, &serde_json::json!({ "av_delay_ms": ms }));
+                }
+            }
+
+            UdpCommand::SetOutputProfile(name) => {
+                let Some(profile) = OutputProfile::from_name(&name) else {
+                    let profiles: Vec<_> = OutputProfile::ALL.iter().map(|p| p.name()).collect();
+                    let message = Message::new("unknown_output_profile")
+                        .arg("name", &name)
+                        .arg("options", profiles.join(", "));
+                    self.send_nack(addr, sequence, &message);
+                    return;
+                };
+                self.state.output.lock().profile = profile;
+                if let Err(e) =
+                    Config::save_section("output_profile", &OutputProfileConfig { profile })
+                {
+                    crash::log(format!("⚠️ Failed to save output profile: {}", e));
+                }
+                crash::log(format!("🎨 Output profile: {}", profile.name()));
+                self.send_response(
+                    addr,
+                    sequence,
+                    &serde_json::json!({ "output_profile": profile.name() }),
+                );
+            }
+
+            UdpCommand::QueryStats(from, to, points) => {
+                let rows = match self.state.stats_db.lock().as_ref() {
+                    Some(db) => db.query(from, to, points),
+                    None => Err(anyhow::anyhow!("Statistics database disabled")),
+                };
+                match rows {
+                    Ok(rows) => self.send_response(addr, sequence, &serde_json::json!(rows)),
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::AddSchedule(rule) => {
+                let result = self.state.scheduler.lock().add(&rule);
+                match result {
+                    Ok(()) => {
+                        crash::log(format!("⏰ Schedule added: {}", rule.trim()));
+                        self.save_schedules();
+                        self.send_response(addr, sequence, &self.state.scheduler.lock().status());
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::ListSchedules => {
+                self.send_response(addr, sequence, &self.state.scheduler.lock().status());
+            }
+
+            UdpCommand::RemoveSchedule(index) => {
+                let result = self.state.scheduler.lock().remove(index as usize);
+                match result {
+                    Ok(schedule) => {
+                        crash::log(format!("⏰ Schedule removed: {}", schedule.rule));
+                        self.save_schedules();
+                        self.send_response(addr, sequence, &self.state.scheduler.lock().status());
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::SetNoiseGate(enabled, threshold_db, hold_ms, release_ms) => {
+                let (config, status) = {
+                    let mut filter = self.state.input_filter.lock();
+                    filter.set_gate(NoiseGateConfig {
+                        enabled,
+                        threshold_db,
+                        hold_ms,
+                        release_ms,
+                    });
+                    (filter.gate().clone(), filter.status())
+                };
+                if let Err(e) = Config::save_section("noise_gate", &config) {
+                    crash::log(format!("⚠️ Failed to save noise gate: {}", e));
+                }
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::SetHighPass(enabled, cutoff_hz) => {
+                let (config, status) = {
+                    let mut filter = self.state.input_filter.lock();
+                    filter.set_high_pass(HighPassConfig { enabled, cutoff_hz });
+                    (filter.high_pass().clone(), filter.status())
+                };
+                if let Err(e) = Config::save_section("high_pass", &config) {
+                    crash::log(format!("⚠️ Failed to save high-pass filter: {}", e));
+                }
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::GetInputFilter => {
+                let status = self.state.input_filter.lock().status();
+                self.send_response(addr, sequence, &status);
+            }
+
+            UdpCommand::SetGenrePreset(name) => match GenrePreset::from_name(&name) {
+                Some(preset) => {
+                    let status = genre::apply(&self.state, preset);
+                    self.send_response(addr, sequence, &status);
+                }
+                None => {
+                    let names: Vec<&str> = GENRE_PRESETS.iter().map(|p| p.name()).collect();
+                    let message = Message::new("unknown_genre_preset")
+                        .arg("name", &name)
+                        .arg("options", names.join(", "));
+                    self.send_nack(addr, sequence, &message);
+                }
+            },
+
+            UdpCommand::PushConfig(target) => {
+                let pushed = ConfigBundle::export(self.state.palettes.lock().palettes())
+                    .and_then(|bundle| config_transfer::push(&bundle, &target));
+                match pushed {
+                    Ok(peer) => {
+                        crash::log(format!("📤 Config pushed to {}", peer));
+                        self.send_response(
+                            addr,
+                            sequence,
+                            &serde_json::json!({ "target": peer.to_string(), "pushed": true }),
+                        );
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::GetCrashReports => {
+                let reports = crash::pending_reports();
+                self.send_response(addr, sequence, &serde_json::json!(reports));
+            }
+
+            UdpCommand::DismissCrashReports => {
+                let response = match crash::dismiss_reports() {
+                    Ok(count) => serde_json::json!({ "dismissed": count }),
+                    Err(e) => serde_json::json!({ "error": e.to_string() }),
+                };
+                self.send_response(addr, sequence, &response);
+            }
+
+            UdpCommand::SetFrameTrace(every_n) => {
+                self.state.frame_tracer.lock().set_interval(every_n as u64);
+            }
+
+            UdpCommand::SetEffectByName(name) => {
+                let selected = self.state.effect_engine.lock().set_effect_by_name(&name);
+                match selected {
+                    Ok(effect) => {
+                        self.send_response(addr, sequence, &serde_json::json!({ "effect": effect }))
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::GetColorModes => {
+                let color_modes = self.state.palettes.lock().to_json();
+                self.send_response(addr, sequence, &color_modes);
+            }
+
+            UdpCommand::SavePalette(palette) => {
+                let result = {
+                    let mut palettes = self.state.palettes.lock();
+                    palettes.upsert(palette).map(|_| palettes.to_json())
+                };
+                match result {
+                    Ok(color_modes) => {
+                        self.send_response(addr, sequence, &color_modes);
+                        self.broadcast_color_modes(&color_modes);
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::DeletePalette(name) => {
+                let result = {
+                    let mut palettes = self.state.palettes.lock();
+                    palettes
+                        .remove(&name)
+                        .map(|removed| (removed, palettes.to_json()))
+                };
+                match result {
+                    Ok((true, color_modes)) => {
+                        self.send_response(addr, sequence, &color_modes);
+                        self.broadcast_color_modes(&color_modes);
+                    }
+                    Ok((false, _)) => {
+                        let message = Message::new("unknown_palette").arg("name", &name);
+                        self.send_nack(addr, sequence, &message)
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::FadeBrightness(target, seconds) => {
+                self.state.output.lock().fade_brightness(target, seconds);
+            }
+
+            UdpCommand::FadeToBlack(seconds) => {
+                self.state.output.lock().fade_to_black(seconds);
+            }
+
+            UdpCommand::SetHouseLight(level, color_temp) => {
+                self.state.output.lock().set_house_light(level, color_temp);
+            }
+
+            UdpCommand::SetRegionTrim(region, index, trim) => {
+                let result = {
+                    let mut output = self.state.output.lock();
+                    output
+                        .set_region_trim(region, index as usize, trim)
+                        .map(|_| output.trim.clone())
+                };
+                match result {
+                    Ok(trim) => {
+                        if let Err(e) = Config::save_section("trim", &trim) {
+                            crash::log(format!("⚠️ Failed to save trim map: {}", e));
+                        }
+                        self.send_response(addr, sequence, &serde_json::json!(trim));
+                    }
+                    Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+                }
+            }
+
+            UdpCommand::SetGamma(controller, gamma) => {
+                let result = {
+                    let mut output = self.state.output.lock();
+                    output
+                        .set_gamma(controller.map(usize::from), gamma)
+                        .map(|()| output.color_correction.clone())
+                };
+                self.send_color_correction(addr, sequence, result);
+            }
+
+            UdpCommand::SetColorBalance(controller, white_point, gains) => {
+                let result = {
+                    let mut output = self.state.output.lock();
+                    output
+                        .set_color_balance(controller.map(usize::from), white_point, gains)
+                        .map(|()| output.color_correction.clone())
+                };
+                self.send_color_correction(addr, sequence, result);
+            }
+
+            UdpCommand::ListMidiDevices => {
+                let response = serde_json::json!({
+                    "devices": midi::list_devices(),
+                    "mapping": self.state.midi.lock().mapping(),
+                });
+                self.send_response(addr, sequence, &response);
+            }
+
+            UdpCommand::SetMidiMapping(mapping) => match MidiRouter::new(mapping) {
+                Ok(router) => {
+                    crash::log(format!(
+                        "🎹 MIDI mapping set: {} bindings",
+                        router.mapping().bindings.len()
+                    ));
+                    if let Err(e) = router.save() {
+                        crash::log(format!("⚠️ Failed to save the MIDI mapping: {}", e));
+                    }
+                    let mapping = serde_json::json!(router.mapping());
+                    *self.state.midi.lock() = router;
+                    self.send_response(addr, sequence, &mapping);
+                }
+                Err(e) => self.send_nack(addr, sequence, &Message::from_error(&e)),
+            },
+
+            UdpCommand::GetMessages => {
+                self.send_response(addr, sequence, &messages::catalog());
+            }
+
+            UdpCommand::GetClients => {
+                let clients: Vec<ClientSummary> = self
+                    .clients
+                    .lock()
+                    .iter()
+                    .map(ClientInfo::summary)
+                    .collect();
+                self.send_response(addr, sequence, &serde_json::json!(clients));
+            }
+
+            UdpCommand::GetPerformance => {
+                let fps = self.state.render_health.lock().average_fps();
+                let resources = self.state.resource_budget.lock().report();
+                let duplicate_commands = self.command_log.lock().duplicates();
+                let energy_saver = self.state.energy_saver.lock().status();
+                let output_priority = self.state.output_priority.lock().report();
+                self.send_response(
+                    addr,
+                    sequence,
+                    &serde_json::json!({
+                        "render_fps": fps,
+                        "resources": resources,
+                        "duplicate_commands": duplicate_commands,
+                        "energy_saver": energy_saver,
+                        "output_priority": output_priority,
+                    }),
+                );
+            }
         }
     }
 }
@@ -280,14 +1841,48 @@ mod tests {
 
     #[test]
     fn test_client_info() {
-        let client = ClientInfo {
-            addr: "127.0.0.1:1234".parse().unwrap(),
-            last_seen: Instant::now(),
-            packet_counter: 0,
-            compression_enabled: false,
-        };
+        let client = ClientInfo::new(
+            "127.0.0.1:1234".parse().unwrap(),
+            false,
+            ClientRole::Monitor,
+            None,
+        );
 
         assert_eq!(client.packet_counter, 0);
         assert!(!client.compression_enabled);
     }
+
+    #[test]
+    fn test_client_heartbeat() {
+        let mut client = ClientInfo::new(
+            "127.0.0.1:1234".parse().unwrap(),
+            false,
+            ClientRole::Monitor,
+            None,
+        );
+
+        client.start_ping(1);
+        assert!(!client.record_pong(2));
+        assert!(client.record_pong(1));
+        assert!(client.rtt_ms.is_some());
+
+        for sequence in 2..=(MAX_MISSED_PINGS + 2) {
+            client.start_ping(sequence);
+        }
+        assert!(!client.is_alive());
+    }
+
+    #[test]
+    fn test_session_expiry() {
+        let mut client = ClientInfo::new(
+            "127.0.0.1:1234".parse().unwrap(),
+            false,
+            ClientRole::Display,
+            Some(Duration::from_secs(60)),
+        );
+        assert!(!client.session_expired());
+
+        client.connected_at = Instant::now() - Duration::from_secs(61);
+        assert!(client.session_expired());
+    }
 }
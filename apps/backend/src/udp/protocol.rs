@@ -1,7 +1,19 @@
+use crate::config::{PlaylistConfig, SmoothingProfile};
+use crate::led::TrimRegion;
+use crate::midi::MidiMapping;
+use crate::palettes::ColorPalette;
+use crate::smoothing::BandGroup;
 use anyhow::Result;
-use std::io::{Cursor, Read, Write};
+use dj4led_protocol::{
+    append_checksum, strip_checksum, DecodeError, PacketHeader, Reader, Writer, HEADER_SIZE,
+};
+use serde::Serialize;
 
 pub const MAX_PACKET_SIZE: usize = 1472;
+// Identify pixel index meaning "light the whole strip"
+const WHOLE_STRIP: u16 = 0xFFFF;
+// Color correction controller index meaning "every controller"
+const ALL_CONTROLLERS: u8 = 0xFF;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -11,10 +23,19 @@ pub enum PacketType {
     Ping = 0x03,
     Pong = 0x04,
     Ack = 0x05,
+    Nack = 0x06,
     Command = 0x10,
+    Response = 0x11,
     FrameData = 0x20,
     FrameDataCompressed = 0x21,
     SpectrumData = 0x30,
+    ColorModes = 0x31,
+    OutputAlert = 0x32,
+    ConfigReload = 0x33,
+    AlertEvent = 0x34,
+    BeatEvent = 0x35,
+    TimeSync = 0x36,
+    ConfigTransfer = 0x40,
 }
 
 impl PacketType {
@@ -25,10 +46,19 @@ impl PacketType {
             0x03 => Some(Self::Ping),
             0x04 => Some(Self::Pong),
             0x05 => Some(Self::Ack),
+            0x06 => Some(Self::Nack),
             0x10 => Some(Self::Command),
+            0x11 => Some(Self::Response),
             0x20 => Some(Self::FrameData),
             0x21 => Some(Self::FrameDataCompressed),
             0x30 => Some(Self::SpectrumData),
+            0x31 => Some(Self::ColorModes),
+            0x32 => Some(Self::OutputAlert),
+            0x33 => Some(Self::ConfigReload),
+            0x34 => Some(Self::AlertEvent),
+            0x35 => Some(Self::BeatEvent),
+            0x36 => Some(Self::TimeSync),
+            0x40 => Some(Self::ConfigTransfer),
             _ => None,
         }
     }
@@ -94,6 +124,17 @@ impl UdpPacket {
         }
     }
 
+    pub fn new_ping(sequence: u32) -> Self {
+        Self {
+            packet_type: PacketType::Ping,
+            flags: PacketFlags::NONE,
+            sequence,
+            fragment_id: 0,
+            fragment_count: 1,
+            payload: vec![],
+        }
+    }
+
     pub fn new_pong(sequence: u32) -> Self {
         Self {
             packet_type: PacketType::Pong,
@@ -105,65 +146,137 @@ impl UdpPacket {
         }
     }
 
-    pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(16 + self.payload.len());
-        let mut cursor = Cursor::new(&mut buffer);
-
-        cursor.write_all(&[self.packet_type as u8])?;
-        cursor.write_all(&[self.flags.bits()])?;
-        cursor.write_all(&self.sequence.to_le_bytes())?;
-        cursor.write_all(&self.fragment_id.to_le_bytes())?;
-        cursor.write_all(&self.fragment_count.to_le_bytes())?;
-        cursor.write_all(&(self.payload.len() as u16).to_le_bytes())?;
+    pub fn new_response(sequence: u32, payload: Vec<u8>) -> Self {
+        Self {
+            packet_type: PacketType::Response,
+            flags: PacketFlags::NONE,
+            sequence,
+            fragment_id: 0,
+            fragment_count: 1,
+            payload,
+        }
+    }
 
-        cursor.write_all(&self.payload)?;
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let header = PacketHeader {
+            packet_type: self.packet_type as u8,
+            flags: self.flags.bits(),
+            sequence: self.sequence,
+            fragment_id: self.fragment_id,
+            fragment_count: self.fragment_count,
+            payload_size: self.payload.len() as u16,
+        };
 
-        Ok(buffer)
+        Ok(header
+            .encode(Writer::with_capacity(HEADER_SIZE + self.payload.len()))
+            .bytes(&self.payload)
+            .finish())
     }
 
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        if data.len() < 12 {
-            anyhow::bail!("Packet too small");
-        }
+        let (header, payload) = PacketHeader::parse(data)?;
+        let packet_type = PacketType::from_u8(header.packet_type)
+            .ok_or_else(|| anyhow::anyhow!("Invalid packet type"))?;
 
-        let mut cursor = Cursor::new(data);
+        Ok(Self {
+            packet_type,
+            flags: PacketFlags::from_bits_truncate(header.flags),
+            sequence: header.sequence,
+            fragment_id: header.fragment_id,
+            fragment_count: header.fragment_count,
+            payload: payload.to_vec(),
+        })
+    }
+}
 
-        let mut type_byte = [0u8; 1];
-        cursor.read_exact(&mut type_byte)?;
-        let packet_type = PacketType::from_u8(type_byte[0])
-            .ok_or_else(|| anyhow::anyhow!("Invalid packet type"))?;
+#[derive(Debug, Clone, PartialEq)]
+pub enum XyTarget {
+    None,
+    Hue,
+    Saturation,
+    Brightness,
+    // Intensity macro knob of the current effect
+    Intensity,
+    // Audio sensitivity of the current effect
+    Sensitivity,
+    // Any knob of the current effect, swept across its range
+    Parameter(String),
+}
 
-        let mut flags_byte = [0u8; 1];
-        cursor.read_exact(&mut flags_byte)?;
-        let flags = PacketFlags::from_bits_truncate(flags_byte[0]);
+impl XyTarget {
+    fn to_u8(&self) -> u8 {
+        match self {
+            Self::None => 0x00,
+            Self::Hue => 0x01,
+            Self::Saturation => 0x02,
+            Self::Brightness => 0x03,
+            Self::Intensity => 0x04,
+            Self::Sensitivity => 0x05,
+            Self::Parameter(_) => 0x06,
+        }
+    }
 
-        let mut sequence_bytes = [0u8; 4];
-        cursor.read_exact(&mut sequence_bytes)?;
-        let sequence = u32::from_le_bytes(sequence_bytes);
+    // `parameter` is the knob name, only read for 0x06
+    fn from_u8(value: u8, parameter: String) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::None),
+            0x01 => Some(Self::Hue),
+            0x02 => Some(Self::Saturation),
+            0x03 => Some(Self::Brightness),
+            0x04 => Some(Self::Intensity),
+            0x05 => Some(Self::Sensitivity),
+            0x06 if !parameter.is_empty() => Some(Self::Parameter(parameter)),
+            _ => None,
+        }
+    }
 
-        let mut fragment_id_bytes = [0u8; 2];
-        cursor.read_exact(&mut fragment_id_bytes)?;
-        let fragment_id = u16::from_le_bytes(fragment_id_bytes);
+    fn parameter(&self) -> &str {
+        match self {
+            Self::Parameter(name) => name,
+            _ => "",
+        }
+    }
+}
 
-        let mut fragment_count_bytes = [0u8; 2];
-        cursor.read_exact(&mut fragment_count_bytes)?;
-        let fragment_count = u16::from_le_bytes(fragment_count_bytes);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum ClientRole {
+    Monitor = 0x00,
+    Display = 0x01,
+}
+
+impl ClientRole {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::Monitor),
+            0x01 => Some(Self::Display),
+            _ => None,
+        }
+    }
+}
 
-        let mut payload_len_bytes = [0u8; 2];
-        cursor.read_exact(&mut payload_len_bytes)?;
-        let payload_len = u16::from_le_bytes(payload_len_bytes) as usize;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectRequest {
+    pub role: ClientRole,
+    pub max_duration_secs: u32,
+}
 
-        let mut payload = vec![0u8; payload_len];
-        cursor.read_exact(&mut payload)?;
+impl ConnectRequest {
+    pub fn from_payload(data: &[u8]) -> Self {
+        // Older clients send an empty payload: an unlimited monitor
+        let mut reader = Reader::new(data);
+        let role = reader
+            .u8()
+            .ok()
+            .and_then(ClientRole::from_u8)
+            .unwrap_or(ClientRole::Monitor);
+        let max_duration_secs = reader.u32().unwrap_or(0);
 
-        Ok(Self {
-            packet_type,
-            flags,
-            sequence,
-            fragment_id,
-            fragment_count,
-            payload,
-        })
+        Self {
+            role,
+            max_duration_secs,
+        }
     }
 }
 
@@ -173,92 +286,381 @@ pub enum UdpCommand {
     SetColorMode(String),
     SetCustomColor(f32, f32, f32),
     SetParameter(String, String),
+    SetBlackout(bool),
+    SetStrobe(bool),
+    NextEffect,
+    PreviousEffect,
+    SetBrightness(f32),
+    SetXy(f32, f32),
+    SetXyRouting(XyTarget, XyTarget),
+    GetDiagnostics,
+    GetCrashReports,
+    DismissCrashReports,
+    GetPerformance,
+    SetFrameTrace(u32),
+    GetClients,
+    SetEffectByName(String),
+    GetColorModes,
+    SavePalette(ColorPalette),
+    DeletePalette(String),
+    FadeBrightness(f32, f32),
+    FadeToBlack(f32),
+    SetHouseLight(f32, f32),
+    SetRegionTrim(TrimRegion, u8, f32),
+    GetParameters,
+    LearnRoom(u32),
+    GetRoomCalibration,
+    ClearRoomCalibration,
+    SetMetronome(bool),
+    SetHud(bool),
+    SetSmoothingProfile(SmoothingProfile),
+    SetBandSmoothing(BandGroup, f32, f32),
+    GetSmoothing,
+    SetDecibelScale(bool, f32, f32),
+    LearnNoiseFloor(u32),
+    GetDecibelScale,
+    StartCommissioning,
+    MarkCommissioning(bool, String),
+    CommissioningBack,
+    GetCommissioning,
+    FinishCommissioning,
+    Identify(u8, u8, Option<u16>),
+    ClearIdentify,
+    SetFrameRegion(u8, u8, u8, u8),
+    ClearFrameRegion,
+    GetFrameSummary(u8),
+    ExportSpectrogram(String),
+    GetSpectrogram(u16, u8),
+    GetLastReport,
+    RecordTape(String),
+    StopTape,
+    ReplayTape(String),
+    GetTape,
+    PushConfig(String),
+    SwitchTargetSet(String),
+    GetTargetSets,
+    SetNoiseGate(bool, f32, f32, f32),
+    SetHighPass(bool, f32),
+    GetInputFilter,
+    SetGenrePreset(String),
+    SetAccent(u8),
+    GetAudioFeatures,
+    SetAvDelay(u16),
+    QueryStats(u64, u64, u16),
+    AddSchedule(String),
+    ListSchedules,
+    RemoveSchedule(u8),
+    GetBpm,
+    SavePreset(String),
+    LoadPreset(String),
+    ListPresets,
+    DeletePreset(String),
+    GetEffects(String),
+    SetOutputProfile(String),
+    // Fade seconds; only accepted from the same machine
+    Handover(f32),
+    GetProtocolSchema,
+    SetPlaylist(PlaylistConfig),
+    StartPlaylist,
+    StopPlaylist,
+    // Peak, attack, hold and release seconds
+    StartCrowdNoise(f32, f32, f32, f32),
+    StopCrowdNoise,
+    // Controller (None for all) and gamma
+    SetGamma(Option<u8>, f32),
+    // Controller (None for all), white point in kelvin and red, green, blue gains
+    SetColorBalance(Option<u8>, f32, [f32; 3]),
+    ListMidiDevices,
+    SetMidiMapping(MidiMapping),
+    // Texts of every status and error code, in every language
+    GetMessages,
 }
 
 impl UdpCommand {
     pub fn to_payload(&self) -> Vec<u8> {
+        let writer = Writer::new();
         match self {
-            Self::SetEffect(id) => {
-                let mut data = vec![0x01];
-                data.extend_from_slice(&(*id as u32).to_le_bytes());
-                data
+            Self::SetEffect(id) => writer.u8(0x01).u32(*id as u32),
+            Self::SetColorMode(mode) => writer.u8(0x02).str(mode),
+            Self::SetCustomColor(r, g, b) => writer.u8(0x03).f32(*r).f32(*g).f32(*b),
+            Self::SetParameter(name, value) => writer.u8(0x04).str16(name).str16(value),
+            Self::SetBlackout(enabled) => writer.u8(0x05).bool(*enabled),
+            Self::SetStrobe(enabled) => writer.u8(0x06).bool(*enabled),
+            Self::NextEffect => writer.u8(0x07),
+            Self::PreviousEffect => writer.u8(0x08),
+            Self::SetBrightness(brightness) => writer.u8(0x09).f32(*brightness),
+            Self::SetXy(x, y) => writer.u8(0x0A).f32(*x).f32(*y),
+            Self::SetXyRouting(x_target, y_target) => writer
+                .u8(0x0B)
+                .u8(x_target.to_u8())
+                .u8(y_target.to_u8())
+                .str16(x_target.parameter())
+                .str16(y_target.parameter()),
+            Self::GetDiagnostics => writer.u8(0x0C),
+            Self::GetCrashReports => writer.u8(0x0D),
+            Self::DismissCrashReports => writer.u8(0x0E),
+            Self::GetPerformance => writer.u8(0x0F),
+            Self::SetFrameTrace(every_n) => writer.u8(0x10).u32(*every_n),
+            Self::GetClients => writer.u8(0x11),
+            Self::SetEffectByName(name) => writer.u8(0x12).str(name),
+            Self::GetColorModes => writer.u8(0x13),
+            Self::SavePalette(palette) => writer
+                .u8(0x14)
+                .bytes(&serde_json::to_vec(palette).unwrap_or_default()),
+            Self::DeletePalette(name) => writer.u8(0x15).str(name),
+            Self::FadeBrightness(target, seconds) => writer.u8(0x16).f32(*target).f32(*seconds),
+            Self::FadeToBlack(seconds) => writer.u8(0x17).f32(*seconds),
+            Self::SetHouseLight(level, color_temp) => writer.u8(0x18).f32(*level).f32(*color_temp),
+            Self::SetRegionTrim(region, index, trim) => {
+                writer.u8(0x19).u8(*region as u8).u8(*index).f32(*trim)
             }
-            Self::SetColorMode(mode) => {
-                let mut data = vec![0x02];
-                data.extend_from_slice(mode.as_bytes());
-                data
+            Self::GetParameters => writer.u8(0x1A),
+            Self::LearnRoom(seconds) => writer.u8(0x1B).u32(*seconds),
+            Self::GetRoomCalibration => writer.u8(0x1C),
+            Self::ClearRoomCalibration => writer.u8(0x1D),
+            Self::SetMetronome(enabled) => writer.u8(0x1E).bool(*enabled),
+            Self::SetHud(enabled) => writer.u8(0x1F).bool(*enabled),
+            Self::SetSmoothingProfile(profile) => writer.u8(0x20).u8(*profile as u8),
+            Self::SetBandSmoothing(group, attack, release) => {
+                writer.u8(0x21).u8(*group as u8).f32(*attack).f32(*release)
             }
-            Self::SetCustomColor(r, g, b) => {
-                let mut data = vec![0x03];
-                data.extend_from_slice(&r.to_le_bytes());
-                data.extend_from_slice(&g.to_le_bytes());
-                data.extend_from_slice(&b.to_le_bytes());
-                data
+            Self::GetSmoothing => writer.u8(0x22),
+            Self::SetDecibelScale(enabled, min_db, max_db) => {
+                writer.u8(0x23).bool(*enabled).f32(*min_db).f32(*max_db)
             }
-            Self::SetParameter(name, value) => {
-                let mut data = vec![0x04];
-                data.extend_from_slice(&(name.len() as u16).to_le_bytes());
-                data.extend_from_slice(name.as_bytes());
-                data.extend_from_slice(&(value.len() as u16).to_le_bytes());
-                data.extend_from_slice(value.as_bytes());
-                data
+            Self::LearnNoiseFloor(seconds) => writer.u8(0x24).u32(*seconds),
+            Self::GetDecibelScale => writer.u8(0x25),
+            Self::StartCommissioning => writer.u8(0x26),
+            Self::MarkCommissioning(passed, note) => writer.u8(0x27).bool(*passed).str(note),
+            Self::CommissioningBack => writer.u8(0x28),
+            Self::GetCommissioning => writer.u8(0x29),
+            Self::FinishCommissioning => writer.u8(0x2A),
+            Self::Identify(controller, strip, pixel) => writer
+                .u8(0x2B)
+                .u8(*controller)
+                .u8(*strip)
+                .u16(pixel.unwrap_or(WHOLE_STRIP)),
+            Self::ClearIdentify => writer.u8(0x2C),
+            Self::SetFrameRegion(x, y, width, height) => {
+                writer.u8(0x2D).u8(*x).u8(*y).u8(*width).u8(*height)
             }
+            Self::ClearFrameRegion => writer.u8(0x2E),
+            Self::GetFrameSummary(size) => writer.u8(0x2F).u8(*size),
+            Self::ExportSpectrogram(path) => writer.u8(0x30).str(path),
+            Self::GetSpectrogram(seconds, columns) => writer.u8(0x31).u16(*seconds).u8(*columns),
+            Self::GetLastReport => writer.u8(0x32),
+            Self::RecordTape(name) => writer.u8(0x33).str(name),
+            Self::StopTape => writer.u8(0x34),
+            Self::ReplayTape(name) => writer.u8(0x35).str(name),
+            Self::GetTape => writer.u8(0x36),
+            Self::PushConfig(target) => writer.u8(0x37).str(target),
+            Self::SwitchTargetSet(name) => writer.u8(0x38).str(name),
+            Self::GetTargetSets => writer.u8(0x39),
+            Self::SetNoiseGate(enabled, threshold_db, hold_ms, release_ms) => writer
+                .u8(0x3A)
+                .bool(*enabled)
+                .f32(*threshold_db)
+                .f32(*hold_ms)
+                .f32(*release_ms),
+            Self::SetHighPass(enabled, cutoff_hz) => writer.u8(0x3B).bool(*enabled).f32(*cutoff_hz),
+            Self::GetInputFilter => writer.u8(0x3C),
+            Self::SetGenrePreset(name) => writer.u8(0x3D).str(name),
+            Self::SetAccent(kind) => writer.u8(0x3E).u8(*kind),
+            Self::GetAudioFeatures => writer.u8(0x3F),
+            Self::SetAvDelay(ms) => writer.u8(0x40).u16(*ms),
+            Self::QueryStats(from, to, points) => writer.u8(0x41).u64(*from).u64(*to).u16(*points),
+            Self::AddSchedule(rule) => writer.u8(0x42).str(rule),
+            Self::ListSchedules => writer.u8(0x43),
+            Self::RemoveSchedule(index) => writer.u8(0x44).u8(*index),
+            Self::GetBpm => writer.u8(0x45),
+            Self::SavePreset(name) => writer.u8(0x46).str(name),
+            Self::LoadPreset(name) => writer.u8(0x47).str(name),
+            Self::ListPresets => writer.u8(0x48),
+            Self::DeletePreset(name) => writer.u8(0x49).str(name),
+            Self::GetEffects(tag) => writer.u8(0x4A).str(tag),
+            Self::SetOutputProfile(name) => writer.u8(0x4B).str(name),
+            Self::Handover(seconds) => writer.u8(0x4C).f32(*seconds),
+            Self::GetProtocolSchema => writer.u8(0x4D),
+            Self::SetPlaylist(playlist) => writer
+                .u8(0x4E)
+                .bytes(&serde_json::to_vec(playlist).unwrap_or_default()),
+            Self::StartPlaylist => writer.u8(0x4F),
+            Self::StopPlaylist => writer.u8(0x50),
+            Self::StartCrowdNoise(peak, attack, hold, release) => writer
+                .u8(0x51)
+                .f32(*peak)
+                .f32(*attack)
+                .f32(*hold)
+                .f32(*release),
+            Self::StopCrowdNoise => writer.u8(0x52),
+            Self::SetGamma(controller, gamma) => writer
+                .u8(0x53)
+                .u8(controller.unwrap_or(ALL_CONTROLLERS))
+                .f32(*gamma),
+            Self::SetColorBalance(controller, white_point, [r, g, b]) => writer
+                .u8(0x54)
+                .u8(controller.unwrap_or(ALL_CONTROLLERS))
+                .f32(*white_point)
+                .f32(*r)
+                .f32(*g)
+                .f32(*b),
+            Self::ListMidiDevices => writer.u8(0x55),
+            Self::SetMidiMapping(mapping) => writer
+                .u8(0x56)
+                .bytes(&serde_json::to_vec(mapping).unwrap_or_default()),
+            Self::GetMessages => writer.u8(0x57),
         }
+        .finish()
     }
 
     pub fn from_payload(data: &[u8]) -> Option<Self> {
-        if data.is_empty() {
-            return None;
-        }
-
-        let mut cursor = Cursor::new(data);
-        let mut cmd_id = [0u8; 1];
-        cursor.read_exact(&mut cmd_id).ok()?;
+        Self::decode(&mut Reader::new(data)).ok()
+    }
 
-        match cmd_id[0] {
-            0x01 => {
-                let mut id_bytes = [0u8; 4];
-                cursor.read_exact(&mut id_bytes).ok()?;
-                Some(Self::SetEffect(u32::from_le_bytes(id_bytes) as usize))
+    // Fields are read in the order `to_payload` writes them
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(match reader.u8()? {
+            0x01 => Self::SetEffect(reader.u32()? as usize),
+            0x02 => Self::SetColorMode(reader.str()?),
+            0x03 => Self::SetCustomColor(reader.f32()?, reader.f32()?, reader.f32()?),
+            0x04 => Self::SetParameter(reader.str16()?, reader.str16()?),
+            0x05 => Self::SetBlackout(reader.bool()?),
+            0x06 => Self::SetStrobe(reader.bool()?),
+            0x07 => Self::NextEffect,
+            0x08 => Self::PreviousEffect,
+            0x09 => Self::SetBrightness(reader.f32()?),
+            0x0A => Self::SetXy(reader.f32()?, reader.f32()?),
+            0x0B => {
+                let (x_target, y_target) = (reader.u8()?, reader.u8()?);
+                // Clients from before parameter targets stop after the two targets
+                let (x_parameter, y_parameter) = if reader.remaining() == 0 {
+                    (String::new(), String::new())
+                } else {
+                    (reader.str16()?, reader.str16()?)
+                };
+                Self::SetXyRouting(
+                    XyTarget::from_u8(x_target, x_parameter)
+                        .ok_or(DecodeError::Invalid("XY target"))?,
+                    XyTarget::from_u8(y_target, y_parameter)
+                        .ok_or(DecodeError::Invalid("XY target"))?,
+                )
             }
-            0x02 => {
-                let mode = String::from_utf8(data[1..].to_vec()).ok()?;
-                Some(Self::SetColorMode(mode))
+            0x0C => Self::GetDiagnostics,
+            0x0D => Self::GetCrashReports,
+            0x0E => Self::DismissCrashReports,
+            0x0F => Self::GetPerformance,
+            0x10 => Self::SetFrameTrace(reader.u32()?),
+            0x11 => Self::GetClients,
+            0x12 => Self::SetEffectByName(reader.str()?),
+            0x13 => Self::GetColorModes,
+            0x14 => Self::SavePalette(
+                serde_json::from_slice(reader.rest())
+                    .map_err(|_| DecodeError::Invalid("palette"))?,
+            ),
+            0x15 => Self::DeletePalette(reader.str()?),
+            0x16 => Self::FadeBrightness(reader.f32()?, reader.f32()?),
+            0x17 => Self::FadeToBlack(reader.f32()?),
+            0x18 => Self::SetHouseLight(reader.f32()?, reader.f32()?),
+            0x19 => Self::SetRegionTrim(
+                TrimRegion::from_u8(reader.u8()?).ok_or(DecodeError::Invalid("trim region"))?,
+                reader.u8()?,
+                reader.f32()?,
+            ),
+            0x1A => Self::GetParameters,
+            0x1B => Self::LearnRoom(reader.u32()?),
+            0x1C => Self::GetRoomCalibration,
+            0x1D => Self::ClearRoomCalibration,
+            0x1E => Self::SetMetronome(reader.bool()?),
+            0x1F => Self::SetHud(reader.bool()?),
+            0x20 => Self::SetSmoothingProfile(
+                SmoothingProfile::from_u8(reader.u8()?)
+                    .ok_or(DecodeError::Invalid("smoothing profile"))?,
+            ),
+            0x21 => Self::SetBandSmoothing(
+                BandGroup::from_u8(reader.u8()?).ok_or(DecodeError::Invalid("band group"))?,
+                reader.f32()?,
+                reader.f32()?,
+            ),
+            0x22 => Self::GetSmoothing,
+            0x23 => Self::SetDecibelScale(reader.bool()?, reader.f32()?, reader.f32()?),
+            0x24 => Self::LearnNoiseFloor(reader.u32()?),
+            0x25 => Self::GetDecibelScale,
+            0x26 => Self::StartCommissioning,
+            0x27 => Self::MarkCommissioning(reader.bool()?, reader.str()?),
+            0x28 => Self::CommissioningBack,
+            0x29 => Self::GetCommissioning,
+            0x2A => Self::FinishCommissioning,
+            0x2B => {
+                let (controller, strip, pixel) = (reader.u8()?, reader.u8()?, reader.u16()?);
+                Self::Identify(controller, strip, (pixel != WHOLE_STRIP).then_some(pixel))
             }
-            0x03 => {
-                let mut r_bytes = [0u8; 4];
-                let mut g_bytes = [0u8; 4];
-                let mut b_bytes = [0u8; 4];
-                cursor.read_exact(&mut r_bytes).ok()?;
-                cursor.read_exact(&mut g_bytes).ok()?;
-                cursor.read_exact(&mut b_bytes).ok()?;
-                Some(Self::SetCustomColor(
-                    f32::from_le_bytes(r_bytes),
-                    f32::from_le_bytes(g_bytes),
-                    f32::from_le_bytes(b_bytes),
-                ))
+            0x2C => Self::ClearIdentify,
+            0x2D => Self::SetFrameRegion(reader.u8()?, reader.u8()?, reader.u8()?, reader.u8()?),
+            0x2E => Self::ClearFrameRegion,
+            0x2F => Self::GetFrameSummary(reader.u8()?),
+            0x30 => Self::ExportSpectrogram(reader.str()?),
+            0x31 => Self::GetSpectrogram(reader.u16()?, reader.u8()?),
+            0x32 => Self::GetLastReport,
+            0x33 => Self::RecordTape(reader.str()?),
+            0x34 => Self::StopTape,
+            0x35 => Self::ReplayTape(reader.str()?),
+            0x36 => Self::GetTape,
+            0x37 => Self::PushConfig(reader.str()?),
+            0x38 => Self::SwitchTargetSet(reader.str()?),
+            0x39 => Self::GetTargetSets,
+            0x3A => Self::SetNoiseGate(reader.bool()?, reader.f32()?, reader.f32()?, reader.f32()?),
+            0x3B => Self::SetHighPass(reader.bool()?, reader.f32()?),
+            0x3C => Self::GetInputFilter,
+            0x3D => Self::SetGenrePreset(reader.str()?),
+            0x3E => Self::SetAccent(reader.u8()?),
+            0x3F => Self::GetAudioFeatures,
+            0x40 => Self::SetAvDelay(reader.u16()?),
+            0x41 => Self::QueryStats(reader.u64()?, reader.u64()?, reader.u16()?),
+            0x42 => Self::AddSchedule(reader.str()?),
+            0x43 => Self::ListSchedules,
+            0x44 => Self::RemoveSchedule(reader.u8()?),
+            0x45 => Self::GetBpm,
+            0x46 => Self::SavePreset(reader.str()?),
+            0x47 => Self::LoadPreset(reader.str()?),
+            0x48 => Self::ListPresets,
+            0x49 => Self::DeletePreset(reader.str()?),
+            0x4A => Self::GetEffects(reader.str()?),
+            0x4B => Self::SetOutputProfile(reader.str()?),
+            0x4C => Self::Handover(reader.f32()?),
+            0x4D => Self::GetProtocolSchema,
+            0x4E => Self::SetPlaylist(
+                serde_json::from_slice(reader.rest())
+                    .map_err(|_| DecodeError::Invalid("playlist"))?,
+            ),
+            0x4F => Self::StartPlaylist,
+            0x50 => Self::StopPlaylist,
+            0x51 => {
+                Self::StartCrowdNoise(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?)
             }
-            0x04 => {
-                let mut name_len_bytes = [0u8; 2];
-                cursor.read_exact(&mut name_len_bytes).ok()?;
-                let name_len = u16::from_le_bytes(name_len_bytes) as usize;
-
-                let mut name_bytes = vec![0u8; name_len];
-                cursor.read_exact(&mut name_bytes).ok()?;
-                let name = String::from_utf8(name_bytes).ok()?;
-
-                let mut value_len_bytes = [0u8; 2];
-                cursor.read_exact(&mut value_len_bytes).ok()?;
-                let value_len = u16::from_le_bytes(value_len_bytes) as usize;
-
-                let mut value_bytes = vec![0u8; value_len];
-                cursor.read_exact(&mut value_bytes).ok()?;
-                let value = String::from_utf8(value_bytes).ok()?;
-
-                Some(Self::SetParameter(name, value))
+            0x52 => Self::StopCrowdNoise,
+            0x53 => {
+                let controller = reader.u8()?;
+                Self::SetGamma(
+                    (controller != ALL_CONTROLLERS).then_some(controller),
+                    reader.f32()?,
+                )
             }
-            _ => None,
-        }
+            0x54 => {
+                let controller = reader.u8()?;
+                Self::SetColorBalance(
+                    (controller != ALL_CONTROLLERS).then_some(controller),
+                    reader.f32()?,
+                    [reader.f32()?, reader.f32()?, reader.f32()?],
+                )
+            }
+            0x55 => Self::ListMidiDevices,
+            0x56 => Self::SetMidiMapping(
+                serde_json::from_slice(reader.rest())
+                    .map_err(|_| DecodeError::Invalid("midi mapping"))?,
+            ),
+            0x57 => Self::GetMessages,
+            _ => return Err(DecodeError::Invalid("command")),
+        })
     }
 }
 
@@ -280,23 +682,24 @@ pub enum FrameFormat {
 }
 
 impl FrameData {
+    // Ends with a CRC32 of everything before it, so clients can tell a corrupted frame from a lost one
     pub fn to_payload(&self) -> Vec<u8> {
-        let mut payload = Vec::with_capacity(5 + self.data.len());
-        payload.extend_from_slice(&self.width.to_le_bytes());
-        payload.extend_from_slice(&self.height.to_le_bytes());
-        payload.push(self.format as u8);
-        payload.extend_from_slice(&self.data);
+        let mut payload = Writer::with_capacity(5 + self.data.len() + 4)
+            .u16(self.width)
+            .u16(self.height)
+            .u8(self.format as u8)
+            .bytes(&self.data)
+            .finish();
+        append_checksum(&mut payload);
         payload
     }
 
-    pub fn from_payload(data: &[u8]) -> Option<Self> {
-        if data.len() < 5 {
-            return None;
-        }
-
-        let width = u16::from_le_bytes([data[0], data[1]]);
-        let height = u16::from_le_bytes([data[2], data[3]]);
-        let format = match data[4] {
+    // None when the payload is malformed or fails its checksum
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(strip_checksum(payload)?);
+        let width = reader.u16().ok()?;
+        let height = reader.u16().ok()?;
+        let format = match reader.u8().ok()? {
             0x01 => FrameFormat::RGB,
             0x02 => FrameFormat::RGBA,
             0x03 => FrameFormat::BGR,
@@ -308,7 +711,7 @@ impl FrameData {
             width,
             height,
             format,
-            data: data[5..].to_vec(),
+            data: reader.rest().to_vec(),
         })
     }
 }
@@ -320,40 +723,26 @@ pub struct SpectrumData {
 
 impl SpectrumData {
     pub fn to_payload(&self) -> Vec<u8> {
-        let mut payload = Vec::with_capacity(2 + self.bands.len() * 4);
-        payload.extend_from_slice(&(self.bands.len() as u16).to_le_bytes());
-
-        for &value in &self.bands {
-            payload.extend_from_slice(&value.to_le_bytes());
-        }
-
-        payload
+        self.bands
+            .iter()
+            .fold(
+                Writer::with_capacity(2 + self.bands.len() * 4).u16(self.bands.len() as u16),
+                |writer, &value| writer.f32(value),
+            )
+            .finish()
     }
 
     pub fn from_payload(data: &[u8]) -> Option<Self> {
-        if data.len() < 2 {
-            return None;
-        }
-
-        let band_count = u16::from_le_bytes([data[0], data[1]]) as usize;
-        let expected_size = 2 + band_count * 4;
-
-        if data.len() != expected_size {
+        let mut reader = Reader::new(data);
+        let band_count = reader.u16().ok()? as usize;
+        if reader.remaining() != band_count * 4 {
             return None;
         }
 
-        let mut bands = Vec::with_capacity(band_count);
-        for i in 0..band_count {
-            let offset = 2 + i * 4;
-            let value = f32::from_le_bytes([
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3],
-            ]);
-            bands.push(value);
-        }
-
+        let bands = (0..band_count)
+            .map(|_| reader.f32())
+            .collect::<Result<_, _>>()
+            .ok()?;
         Some(Self { bands })
     }
 }
@@ -361,6 +750,8 @@ impl SpectrumData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{PlaylistEntry, TransitionStyle};
+    use crate::midi::{MidiBinding, MidiControl};
 
     #[test]
     fn test_packet_serialization() {
@@ -386,6 +777,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_output_command_serialization() {
+        let payload = UdpCommand::SetBlackout(true).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::SetBlackout(enabled) => assert!(enabled),
+            _ => panic!("Wrong command type"),
+        }
+
+        let payload = UdpCommand::PreviousEffect.to_payload();
+        assert!(matches!(
+            UdpCommand::from_payload(&payload),
+            Some(UdpCommand::PreviousEffect)
+        ));
+
+        assert!(UdpCommand::from_payload(&[0x06]).is_none());
+    }
+
+    #[test]
+    fn test_xy_routing_serialization() {
+        let payload = UdpCommand::SetXyRouting(XyTarget::Saturation, XyTarget::Hue).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::SetXyRouting(x, y) => {
+                assert_eq!(x, XyTarget::Saturation);
+                assert_eq!(y, XyTarget::Hue);
+            }
+            _ => panic!("Wrong command type"),
+        }
+
+        assert!(UdpCommand::from_payload(&[0x0B, 0x01, 0x7F]).is_none());
+
+        let routing = UdpCommand::SetXyRouting(
+            XyTarget::Parameter("cooling".to_string()),
+            XyTarget::Intensity,
+        );
+        assert!(matches!(
+            UdpCommand::from_payload(&routing.to_payload()),
+            Some(UdpCommand::SetXyRouting(XyTarget::Parameter(name), XyTarget::Intensity))
+                if name == "cooling"
+        ));
+        // Older clients send only the two targets; a parameter target needs its name
+        assert!(matches!(
+            UdpCommand::from_payload(&[0x0B, 0x04, 0x05]),
+            Some(UdpCommand::SetXyRouting(
+                XyTarget::Intensity,
+                XyTarget::Sensitivity
+            ))
+        ));
+        assert!(UdpCommand::from_payload(&[0x0B, 0x06, 0x00]).is_none());
+    }
+
     #[test]
     fn test_frame_data_serialization() {
         let frame = FrameData {
@@ -395,11 +836,376 @@ mod tests {
             data: vec![255; 64 * 64 * 3],
         };
 
-        let payload = frame.to_payload();
+        let mut payload = frame.to_payload();
         let decoded = FrameData::from_payload(&payload).unwrap();
 
         assert_eq!(frame.width, decoded.width);
         assert_eq!(frame.height, decoded.height);
-        assert_eq!(frame.data.len(), decoded.data.len());
+        assert_eq!(frame.data, decoded.data);
+
+        // One flipped bit fails the checksum
+        payload[100] ^= 0x08;
+        assert!(FrameData::from_payload(&payload).is_none());
+    }
+
+    #[test]
+    fn test_connect_request_parsing() {
+        let request = ConnectRequest::from_payload(&[]);
+        assert_eq!(request.role, ClientRole::Monitor);
+        assert_eq!(request.max_duration_secs, 0);
+
+        let mut payload = vec![ClientRole::Display as u8];
+        payload.extend_from_slice(&3600u32.to_le_bytes());
+        let request = ConnectRequest::from_payload(&payload);
+        assert_eq!(request.role, ClientRole::Display);
+        assert_eq!(request.max_duration_secs, 3600);
+    }
+
+    #[test]
+    fn test_commissioning_mark_serialization() {
+        let payload = UdpCommand::MarkCommissioning(false, "no blue".to_string()).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::MarkCommissioning(passed, note) => {
+                assert!(!passed);
+                assert_eq!(note, "no blue");
+            }
+            _ => panic!("Wrong command type"),
+        }
+        assert!(UdpCommand::from_payload(&[0x27]).is_none());
+    }
+
+    #[test]
+    fn test_identify_serialization() {
+        for pixel in [Some(87), None] {
+            let payload = UdpCommand::Identify(2, 5, pixel).to_payload();
+            match UdpCommand::from_payload(&payload).unwrap() {
+                UdpCommand::Identify(controller, strip, decoded) => {
+                    assert_eq!((controller, strip, decoded), (2, 5, pixel));
+                }
+                _ => panic!("Wrong command type"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_noise_gate_serialization() {
+        let payload = UdpCommand::SetNoiseGate(true, -48.5, 300.0, 120.0).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::SetNoiseGate(enabled, threshold_db, hold_ms, release_ms) => {
+                assert_eq!(
+                    (enabled, threshold_db, hold_ms, release_ms),
+                    (true, -48.5, 300.0, 120.0)
+                );
+            }
+            _ => panic!("Wrong command type"),
+        }
+        assert!(UdpCommand::from_payload(&payload[..9]).is_none());
+    }
+
+    #[test]
+    fn test_frame_region_serialization() {
+        let payload = UdpCommand::SetFrameRegion(64, 0, 32, 128).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::SetFrameRegion(x, y, width, height) => {
+                assert_eq!((x, y, width, height), (64, 0, 32, 128));
+            }
+            _ => panic!("Wrong command type"),
+        }
+        assert!(UdpCommand::from_payload(&payload[..4]).is_none());
+    }
+
+    #[test]
+    fn test_frame_summary_serialization() {
+        let payload = UdpCommand::GetFrameSummary(32).to_payload();
+        assert!(matches!(
+            UdpCommand::from_payload(&payload),
+            Some(UdpCommand::GetFrameSummary(32))
+        ));
+        assert!(UdpCommand::from_payload(&payload[..1]).is_none());
+    }
+
+    #[test]
+    fn test_spectrogram_serialization() {
+        let payload = UdpCommand::GetSpectrogram(300, 96).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::GetSpectrogram(seconds, columns) => {
+                assert_eq!((seconds, columns), (300, 96))
+            }
+            _ => panic!("Wrong command type"),
+        }
+        assert!(UdpCommand::from_payload(&payload[..3]).is_none());
+
+        let payload = UdpCommand::ExportSpectrogram("show.png".to_string()).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::ExportSpectrogram(path) => assert_eq!(path, "show.png"),
+            _ => panic!("Wrong command type"),
+        }
+    }
+
+    #[test]
+    fn test_av_delay_serialization() {
+        let payload = UdpCommand::SetAvDelay(480).to_payload();
+        assert_eq!(payload, vec![0x40, 0xE0, 0x01]);
+        assert!(matches!(
+            UdpCommand::from_payload(&payload),
+            Some(UdpCommand::SetAvDelay(480))
+        ));
+        assert!(UdpCommand::from_payload(&payload[..2]).is_none());
+    }
+
+    #[test]
+    fn test_query_stats_serialization() {
+        let payload = UdpCommand::QueryStats(1_700_000_000, 1_700_086_400, 96).to_payload();
+        assert_eq!(payload.len(), 19);
+        assert!(matches!(
+            UdpCommand::from_payload(&payload),
+            Some(UdpCommand::QueryStats(1_700_000_000, 1_700_086_400, 96))
+        ));
+        assert!(UdpCommand::from_payload(&payload[..18]).is_none());
+    }
+
+    #[test]
+    fn test_schedule_commands_serialization() {
+        let payload = UdpCommand::AddSchedule("daily 04:00 blackout".to_string()).to_payload();
+        assert_eq!(payload[0], 0x42);
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::AddSchedule(rule) => assert_eq!(rule, "daily 04:00 blackout"),
+            _ => panic!("Wrong command type"),
+        }
+        assert!(matches!(
+            UdpCommand::from_payload(&UdpCommand::RemoveSchedule(3).to_payload()),
+            Some(UdpCommand::RemoveSchedule(3))
+        ));
+        assert!(UdpCommand::from_payload(&[0x44]).is_none());
+    }
+
+    #[test]
+    fn test_effect_by_name_serialization() {
+        let payload = UdpCommand::SetEffectByName("starfall".to_string()).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::SetEffectByName(name) => assert_eq!(name, "starfall"),
+            _ => panic!("Wrong command type"),
+        }
+    }
+
+    #[test]
+    fn test_palette_command_serialization() {
+        let palette = ColorPalette {
+            name: "club".to_string(),
+            colors: vec![(1.0, 0.0, 0.5)],
+        };
+        let payload = UdpCommand::SavePalette(palette).to_payload();
+        match UdpCommand::from_payload(&payload).unwrap() {
+            UdpCommand::SavePalette(palette) => {
+                assert_eq!(palette.name, "club");
+                assert_eq!(palette.colors, vec![(1.0, 0.0, 0.5)]);
+            }
+            _ => panic!("Wrong command type"),
+        }
+
+        assert!(UdpCommand::from_payload(&[0x14, b'{']).is_none());
+    }
+
+    // One of each command, so a field written in one order and read in another shows up here
+    fn every_command(n: u8, x: f32, text: &str) -> Vec<UdpCommand> {
+        let wide = n as u32 * 0x0101_0101;
+        let flag = n % 2 == 1;
+        vec![
+            UdpCommand::SetEffect(wide as usize),
+            UdpCommand::SetColorMode(text.to_string()),
+            UdpCommand::SetCustomColor(x, -x, x / 3.0),
+            UdpCommand::SetParameter(text.to_string(), format!("{}{}", x, text)),
+            UdpCommand::SetBlackout(flag),
+            UdpCommand::SetStrobe(!flag),
+            UdpCommand::NextEffect,
+            UdpCommand::PreviousEffect,
+            UdpCommand::SetBrightness(x),
+            UdpCommand::SetXy(x, -x),
+            UdpCommand::SetXyRouting(XyTarget::Brightness, XyTarget::None),
+            UdpCommand::GetDiagnostics,
+            UdpCommand::GetCrashReports,
+            UdpCommand::DismissCrashReports,
+            UdpCommand::GetPerformance,
+            UdpCommand::SetFrameTrace(wide),
+            UdpCommand::GetClients,
+            UdpCommand::SetEffectByName(text.to_string()),
+            UdpCommand::GetColorModes,
+            UdpCommand::SavePalette(ColorPalette {
+                name: text.to_string(),
+                colors: vec![(x, 0.5, -x)],
+            }),
+            UdpCommand::DeletePalette(text.to_string()),
+            UdpCommand::FadeBrightness(x, -x),
+            UdpCommand::FadeToBlack(x),
+            UdpCommand::SetHouseLight(-x, x),
+            UdpCommand::SetRegionTrim(TrimRegion::Strip, n, x),
+            UdpCommand::GetParameters,
+            UdpCommand::LearnRoom(wide),
+            UdpCommand::GetRoomCalibration,
+            UdpCommand::ClearRoomCalibration,
+            UdpCommand::SetMetronome(flag),
+            UdpCommand::SetHud(!flag),
+            UdpCommand::SetSmoothingProfile(SmoothingProfile::Broadcast),
+            UdpCommand::SetBandSmoothing(BandGroup::High, x, -x),
+            UdpCommand::GetSmoothing,
+            UdpCommand::SetDecibelScale(flag, -x, x),
+            UdpCommand::LearnNoiseFloor(wide),
+            UdpCommand::GetDecibelScale,
+            UdpCommand::StartCommissioning,
+            UdpCommand::MarkCommissioning(flag, text.to_string()),
+            UdpCommand::CommissioningBack,
+            UdpCommand::GetCommissioning,
+            UdpCommand::FinishCommissioning,
+            UdpCommand::Identify(n, !n, (n != 0).then_some(n as u16 * 3)),
+            UdpCommand::ClearIdentify,
+            UdpCommand::SetFrameRegion(n, !n, n / 2, n ^ 0x55),
+            UdpCommand::ClearFrameRegion,
+            UdpCommand::GetFrameSummary(n),
+            UdpCommand::ExportSpectrogram(text.to_string()),
+            UdpCommand::GetSpectrogram(wide as u16, !n),
+            UdpCommand::GetLastReport,
+            UdpCommand::RecordTape(text.to_string()),
+            UdpCommand::StopTape,
+            UdpCommand::ReplayTape(text.to_string()),
+            UdpCommand::GetTape,
+            UdpCommand::PushConfig(text.to_string()),
+            UdpCommand::SwitchTargetSet(text.to_string()),
+            UdpCommand::GetTargetSets,
+            UdpCommand::SetNoiseGate(flag, -x, x, x * 2.0),
+            UdpCommand::SetHighPass(!flag, x),
+            UdpCommand::GetInputFilter,
+            UdpCommand::SetGenrePreset(text.to_string()),
+            UdpCommand::SetAccent(n),
+            UdpCommand::GetAudioFeatures,
+            UdpCommand::SetAvDelay(wide as u16),
+            UdpCommand::QueryStats(wide as u64, u64::MAX - n as u64, !n as u16),
+            UdpCommand::AddSchedule(text.to_string()),
+            UdpCommand::ListSchedules,
+            UdpCommand::RemoveSchedule(n),
+            UdpCommand::GetBpm,
+            UdpCommand::SavePreset(text.to_string()),
+            UdpCommand::LoadPreset(text.to_string()),
+            UdpCommand::ListPresets,
+            UdpCommand::DeletePreset(text.to_string()),
+            UdpCommand::GetEffects(text.to_string()),
+            UdpCommand::SetOutputProfile(text.to_string()),
+            UdpCommand::Handover(x),
+            UdpCommand::GetProtocolSchema,
+            UdpCommand::SetPlaylist(PlaylistConfig {
+                entries: vec![PlaylistEntry {
+                    effect: text.to_string(),
+                    duration_secs: x,
+                    transition: TransitionStyle::FadeThroughBlack,
+                    transition_secs: -x,
+                }],
+                advance_on_drop: flag,
+            }),
+            UdpCommand::StartPlaylist,
+            UdpCommand::StopPlaylist,
+            UdpCommand::StartCrowdNoise(x, x / 2.0, -x, x * 3.0),
+            UdpCommand::StopCrowdNoise,
+            UdpCommand::SetGamma((n != 0xFF).then_some(n), x),
+            UdpCommand::SetColorBalance(None, x, [x, -x, x / 2.0]),
+            UdpCommand::ListMidiDevices,
+            UdpCommand::SetMidiMapping(MidiMapping {
+                device: flag.then(|| text.to_string()),
+                bindings: vec![MidiBinding {
+                    control: MidiControl::Note,
+                    channel: Some(n),
+                    number: n,
+                    action: text.to_string(),
+                    min: -x,
+                    max: x,
+                }],
+            }),
+            UdpCommand::GetMessages,
+        ]
+    }
+
+    #[test]
+    fn test_every_command_round_trips() {
+        for (n, x, text) in [(0, 0.0, ""), (1, -1.5, "dj é🎛"), (0xFF, f32::MAX, "x")] {
+            let commands = every_command(n, x, text);
+            let mut opcodes: Vec<u8> = commands.iter().map(|c| c.to_payload()[0]).collect();
+
+            for command in &commands {
+                let payload = command.to_payload();
+                let decoded = UdpCommand::from_payload(&payload)
+                    .unwrap_or_else(|| panic!("{:?} did not decode", command));
+                assert_eq!(format!("{:?}", decoded), format!("{:?}", command));
+            }
+
+            opcodes.sort_unstable();
+            opcodes.dedup();
+            assert_eq!(opcodes.len(), commands.len());
+        }
+        assert!(UdpCommand::from_payload(&[]).is_none());
+        assert!(UdpCommand::from_payload(&[0xFF]).is_none());
+    }
+
+    #[test]
+    fn test_json_commands_encode_like_binary_ones() {
+        use crate::udp::schema::encode_command;
+        use serde_json::json;
+
+        let cases = [
+            (
+                "set_region_trim",
+                json!({ "region": "strip", "index": 3, "trim": 0.5 }),
+                UdpCommand::SetRegionTrim(TrimRegion::Strip, 3, 0.5),
+            ),
+            (
+                "set_parameter",
+                json!({ "name": "speed", "value": "0.4" }),
+                UdpCommand::SetParameter("speed".to_string(), "0.4".to_string()),
+            ),
+            (
+                "set_blackout",
+                json!({ "enabled": true }),
+                UdpCommand::SetBlackout(true),
+            ),
+            ("next_effect", json!(null), UdpCommand::NextEffect),
+        ];
+        for (name, fields, command) in cases {
+            assert_eq!(encode_command(name, &fields).unwrap(), command.to_payload());
+        }
+
+        assert!(encode_command("set_brightness", &json!({})).is_err());
+        assert!(encode_command("set_accent", &json!({ "kind": "sparkle" })).is_err());
+        assert!(encode_command("set_frame_trace", &json!({ "every_n": -1 })).is_err());
+        assert!(encode_command("launch_rockets", &json!(null)).is_err());
+    }
+
+    #[test]
+    fn test_schema_matches_the_encoding() {
+        use crate::udp::schema::COMMANDS;
+
+        // Strings and JSON run to the end of the payload, so only give a lower bound
+        let size = |kind: &str| match kind {
+            "u16" | "str16" => 2,
+            "u32" | "f32" => 4,
+            "u64" => 8,
+            "str" | "json" => 0,
+            _ => 1,
+        };
+        let commands = every_command(1, 2.5, "name");
+        assert_eq!(COMMANDS.len(), commands.len());
+
+        for command in &commands {
+            let payload = command.to_payload();
+            let (_, _, fields) = COMMANDS
+                .iter()
+                .find(|(opcode, _, _)| *opcode == payload[0])
+                .unwrap_or_else(|| panic!("{:?} is missing from the schema", command));
+            let fixed = 1 + fields.iter().map(|(_, kind)| size(kind)).sum::<usize>();
+            let variable = fields
+                .iter()
+                .any(|(_, kind)| size(kind) == 0 || *kind == "str16");
+            if variable {
+                assert!(payload.len() >= fixed, "{:?}", command);
+            } else {
+                assert_eq!(payload.len(), fixed, "{:?}", command);
+            }
+        }
     }
 }
@@ -0,0 +1,449 @@
+use super::protocol::{PacketFlags, PacketType, MAX_PACKET_SIZE};
+use anyhow::{anyhow, bail, Result};
+use dj4led_protocol::{Writer, HEADER_SIZE};
+use serde_json::{json, Value};
+
+// Field name and type; a type is a primitive from PRIMITIVES or an enumeration from ENUMS
+type Fields = &'static [(&'static str, &'static str)];
+
+const PRIMITIVES: &[(&str, &str)] = &[
+    ("u8", "1 byte"),
+    ("bool", "1 byte, 0 is false"),
+    ("u16", "2 bytes, little-endian"),
+    ("u32", "4 bytes, little-endian"),
+    ("u64", "8 bytes, little-endian"),
+    ("f32", "4 bytes, IEEE 754, little-endian"),
+    ("str16", "u16 byte length, then UTF-8"),
+    ("str", "UTF-8 up to the end of the payload, so only last"),
+    ("json", "JSON up to the end of the payload, so only last"),
+];
+
+// Encoded as a u8, the position in the list being the value
+const ENUMS: &[(&str, &[&str])] = &[
+    ("client_role", &["monitor", "display"]),
+    (
+        "xy_target",
+        &[
+            "none",
+            "hue",
+            "saturation",
+            "brightness",
+            "intensity",
+            "sensitivity",
+            "parameter",
+        ],
+    ),
+    ("trim_region", &["quarter", "strip"]),
+    (
+        "smoothing_profile",
+        &["punchy", "smooth", "broadcast", "custom"],
+    ),
+    ("band_group", &["bass", "mid", "high"]),
+    ("accent", &["off", "flash", "shockwave", "glitch"]),
+];
+
+const HEADER: Fields = &[
+    ("packet_type", "u8"),
+    ("flags", "u8"),
+    ("sequence", "u32"),
+    ("fragment_id", "u16"),
+    ("fragment_count", "u16"),
+    ("payload_size", "u16"),
+];
+
+// In the order `UdpCommand::to_payload` writes them, after the opcode
+pub(super) const COMMANDS: &[(u8, &str, Fields)] = &[
+    (0x01, "set_effect", &[("effect_id", "u32")]),
+    (0x02, "set_color_mode", &[("mode", "str")]),
+    (
+        0x03,
+        "set_custom_color",
+        &[("r", "f32"), ("g", "f32"), ("b", "f32")],
+    ),
+    (
+        0x04,
+        "set_parameter",
+        &[("name", "str16"), ("value", "str16")],
+    ),
+    (0x05, "set_blackout", &[("enabled", "bool")]),
+    (0x06, "set_strobe", &[("enabled", "bool")]),
+    (0x07, "next_effect", &[]),
+    (0x08, "previous_effect", &[]),
+    (0x09, "set_brightness", &[("brightness", "f32")]),
+    (0x0A, "set_xy", &[("x", "f32"), ("y", "f32")]),
+    (
+        0x0B,
+        "set_xy_routing",
+        &[
+            ("x_target", "xy_target"),
+            ("y_target", "xy_target"),
+            // Knob names for a "parameter" target, empty otherwise
+            ("x_parameter", "str16"),
+            ("y_parameter", "str16"),
+        ],
+    ),
+    (0x0C, "get_diagnostics", &[]),
+    (0x0D, "get_crash_reports", &[]),
+    (0x0E, "dismiss_crash_reports", &[]),
+    (0x0F, "get_performance", &[]),
+    (0x10, "set_frame_trace", &[("every_n", "u32")]),
+    (0x11, "get_clients", &[]),
+    (0x12, "set_effect_by_name", &[("name", "str")]),
+    (0x13, "get_color_modes", &[]),
+    (0x14, "save_palette", &[("palette", "json")]),
+    (0x15, "delete_palette", &[("name", "str")]),
+    (
+        0x16,
+        "fade_brightness",
+        &[("target", "f32"), ("seconds", "f32")],
+    ),
+    (0x17, "fade_to_black", &[("seconds", "f32")]),
+    (
+        0x18,
+        "set_house_light",
+        &[("level", "f32"), ("color_temp", "f32")],
+    ),
+    (
+        0x19,
+        "set_region_trim",
+        &[("region", "trim_region"), ("index", "u8"), ("trim", "f32")],
+    ),
+    (0x1A, "get_parameters", &[]),
+    (0x1B, "learn_room", &[("seconds", "u32")]),
+    (0x1C, "get_room_calibration", &[]),
+    (0x1D, "clear_room_calibration", &[]),
+    (0x1E, "set_metronome", &[("enabled", "bool")]),
+    (0x1F, "set_hud", &[("enabled", "bool")]),
+    (
+        0x20,
+        "set_smoothing_profile",
+        &[("profile", "smoothing_profile")],
+    ),
+    (
+        0x21,
+        "set_band_smoothing",
+        &[
+            ("group", "band_group"),
+            ("attack", "f32"),
+            ("release", "f32"),
+        ],
+    ),
+    (0x22, "get_smoothing", &[]),
+    (
+        0x23,
+        "set_decibel_scale",
+        &[("enabled", "bool"), ("min_db", "f32"), ("max_db", "f32")],
+    ),
+    (0x24, "learn_noise_floor", &[("seconds", "u32")]),
+    (0x25, "get_decibel_scale", &[]),
+    (0x26, "start_commissioning", &[]),
+    (
+        0x27,
+        "mark_commissioning",
+        &[("passed", "bool"), ("note", "str")],
+    ),
+    (0x28, "commissioning_back", &[]),
+    (0x29, "get_commissioning", &[]),
+    (0x2A, "finish_commissioning", &[]),
+    // A pixel of 0xFFFF lights the whole strip
+    (
+        0x2B,
+        "identify",
+        &[("controller", "u8"), ("strip", "u8"), ("pixel", "u16")],
+    ),
+    (0x2C, "clear_identify", &[]),
+    (
+        0x2D,
+        "set_frame_region",
+        &[("x", "u8"), ("y", "u8"), ("width", "u8"), ("height", "u8")],
+    ),
+    (0x2E, "clear_frame_region", &[]),
+    (0x2F, "get_frame_summary", &[("size", "u8")]),
+    (0x30, "export_spectrogram", &[("path", "str")]),
+    (
+        0x31,
+        "get_spectrogram",
+        &[("seconds", "u16"), ("columns", "u8")],
+    ),
+    (0x32, "get_last_report", &[]),
+    (0x33, "record_tape", &[("name", "str")]),
+    (0x34, "stop_tape", &[]),
+    (0x35, "replay_tape", &[("name", "str")]),
+    (0x36, "get_tape", &[]),
+    (0x37, "push_config", &[("target", "str")]),
+    (0x38, "switch_target_set", &[("name", "str")]),
+    (0x39, "get_target_sets", &[]),
+    (
+        0x3A,
+        "set_noise_gate",
+        &[
+            ("enabled", "bool"),
+            ("threshold_db", "f32"),
+            ("hold_ms", "f32"),
+            ("release_ms", "f32"),
+        ],
+    ),
+    (
+        0x3B,
+        "set_high_pass",
+        &[("enabled", "bool"), ("cutoff_hz", "f32")],
+    ),
+    (0x3C, "get_input_filter", &[]),
+    (0x3D, "set_genre_preset", &[("name", "str")]),
+    (0x3E, "set_accent", &[("kind", "accent")]),
+    (0x3F, "get_audio_features", &[]),
+    (0x40, "set_av_delay", &[("ms", "u16")]),
+    (
+        0x41,
+        "query_stats",
+        &[("from", "u64"), ("to", "u64"), ("points", "u16")],
+    ),
+    (0x42, "add_schedule", &[("rule", "str")]),
+    (0x43, "list_schedules", &[]),
+    (0x44, "remove_schedule", &[("index", "u8")]),
+    (0x45, "get_bpm", &[]),
+    (0x46, "save_preset", &[("name", "str")]),
+    (0x47, "load_preset", &[("name", "str")]),
+    (0x48, "list_presets", &[]),
+    (0x49, "delete_preset", &[("name", "str")]),
+    (0x4A, "get_effects", &[("tag", "str")]),
+    (0x4B, "set_output_profile", &[("name", "str")]),
+    (0x4C, "handover", &[("seconds", "f32")]),
+    (0x4D, "get_protocol_schema", &[]),
+    (0x4E, "set_playlist", &[("playlist", "json")]),
+    (0x4F, "start_playlist", &[]),
+    (0x50, "stop_playlist", &[]),
+    (
+        0x51,
+        "start_crowd_noise",
+        &[
+            ("peak", "f32"),
+            ("attack_secs", "f32"),
+            ("hold_secs", "f32"),
+            ("release_secs", "f32"),
+        ],
+    ),
+    (0x52, "stop_crowd_noise", &[]),
+    // A controller of 0xFF sets every controller
+    (0x53, "set_gamma", &[("controller", "u8"), ("gamma", "f32")]),
+    (
+        0x54,
+        "set_color_balance",
+        &[
+            ("controller", "u8"),
+            ("white_point", "f32"),
+            ("r", "f32"),
+            ("g", "f32"),
+            ("b", "f32"),
+        ],
+    ),
+    (0x55, "list_midi_devices", &[]),
+    (0x56, "set_midi_mapping", &[("mapping", "json")]),
+    (0x57, "get_messages", &[]),
+];
+
+fn packet_types() -> Value {
+    let packet_types = [
+        (
+            PacketType::Connect,
+            "connect",
+            "to server",
+            "role client_role, max_duration_secs u32; both optional",
+        ),
+        (PacketType::Disconnect, "disconnect", "both", "empty"),
+        (
+            PacketType::Ping,
+            "ping",
+            "both",
+            "empty, answered by a pong with the same sequence",
+        ),
+        (PacketType::Pong, "pong", "both", "empty"),
+        (
+            PacketType::Ack,
+            "ack",
+            "both",
+            "empty, acknowledges the packet with the same sequence",
+        ),
+        (
+            PacketType::Nack,
+            "nack",
+            "to client",
+            "JSON: code, message and args",
+        ),
+        (
+            PacketType::Command,
+            "command",
+            "to server",
+            "command opcode u8, then its fields",
+        ),
+        (
+            PacketType::Response,
+            "response",
+            "to client",
+            "JSON, same sequence as the command",
+        ),
+        (
+            PacketType::FrameData,
+            "frame_data",
+            "to client",
+            "width u16, height u16, format u8 (1 RGB, 2 RGBA, 3 BGR, 4 BGRA), pixels, CRC32 u32",
+        ),
+        (
+            PacketType::FrameDataCompressed,
+            "frame_data_compressed",
+            "to client",
+            "gzip of a frame_data payload",
+        ),
+        (
+            PacketType::SpectrumData,
+            "spectrum_data",
+            "to client",
+            "band count u16, then one f32 per band",
+        ),
+        (PacketType::ColorModes, "color_modes", "to client", "JSON"),
+        (PacketType::OutputAlert, "output_alert", "to client", "JSON"),
+        (
+            PacketType::ConfigReload,
+            "config_reload",
+            "to client",
+            "JSON",
+        ),
+        (PacketType::AlertEvent, "alert_event", "to client", "JSON"),
+        (PacketType::BeatEvent, "beat_event", "to client", "JSON"),
+        (
+            PacketType::TimeSync,
+            "time_sync",
+            "both",
+            "client_sent u64 from the client; the server answers with client_sent, \
+             server_received and server_sent u64, all microseconds since the Unix epoch",
+        ),
+        (
+            PacketType::ConfigTransfer,
+            "config_transfer",
+            "between servers",
+            "zlib-compressed JSON config bundle, fragmented",
+        ),
+    ];
+    packet_types
+        .iter()
+        .map(|(packet_type, name, direction, payload)| {
+            json!({
+                "code": *packet_type as u8,
+                "name": name,
+                "direction": direction,
+                "payload": payload,
+            })
+        })
+        .collect()
+}
+
+fn fields(fields: Fields) -> Value {
+    fields
+        .iter()
+        .map(|(name, kind)| json!({ "name": name, "type": kind }))
+        .collect()
+}
+
+fn encode_field(writer: Writer, name: &str, kind: &str, value: &Value) -> Result<Writer> {
+    let number = || {
+        value
+            .as_u64()
+            .ok_or_else(|| anyhow!("{} must be a positive integer", name))
+    };
+    let text = || {
+        value
+            .as_str()
+            .ok_or_else(|| anyhow!("{} must be a string", name))
+    };
+    Ok(match kind {
+        "u8" => writer.u8(u8::try_from(number()?)?),
+        "u16" => writer.u16(u16::try_from(number()?)?),
+        "u32" => writer.u32(u32::try_from(number()?)?),
+        "u64" => writer.u64(number()?),
+        "bool" => writer.bool(
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("{} must be true or false", name))?,
+        ),
+        "f32" => writer.f32(
+            value
+                .as_f64()
+                .ok_or_else(|| anyhow!("{} must be a number", name))? as f32,
+        ),
+        "str16" => writer.str16(text()?),
+        "str" => writer.str(text()?),
+        "json" => writer.bytes(&serde_json::to_vec(value)?),
+        _ => {
+            let (_, values) = ENUMS
+                .iter()
+                .find(|(enum_name, _)| *enum_name == kind)
+                .ok_or_else(|| anyhow!("Unknown field type {}", kind))?;
+            let text = text()?;
+            let index = values
+                .iter()
+                .position(|v| *v == text)
+                .ok_or_else(|| anyhow!("{} must be one of {}", name, values.join(", ")))?;
+            writer.u8(index as u8)
+        }
+    })
+}
+
+// A command payload from its schema name and a JSON object of its fields, for clients that
+// don't speak the binary format
+pub fn encode_command(name: &str, fields: &Value) -> Result<Vec<u8>> {
+    let (opcode, _, command_fields) = COMMANDS
+        .iter()
+        .find(|(_, command, _)| *command == name)
+        .ok_or_else(|| anyhow!("Unknown command {}", name))?;
+    if !fields.is_null() && !fields.is_object() {
+        bail!("Fields of {} must be an object", name);
+    }
+
+    let mut writer = Writer::new().u8(*opcode);
+    for (field, kind) in command_fields.iter() {
+        let value = fields
+            .get(field)
+            .ok_or_else(|| anyhow!("{} needs {}", name, field))?;
+        writer = encode_field(writer, field, kind, value)?;
+    }
+    Ok(writer.finish())
+}
+
+// Everything a client needs to talk to this server, from the code that speaks the protocol
+pub fn protocol_schema() -> Value {
+    let flags = [
+        ("compressed", PacketFlags::COMPRESSED),
+        ("fragmented", PacketFlags::FRAGMENTED),
+        ("last_fragment", PacketFlags::LAST_FRAGMENT),
+        ("requires_ack", PacketFlags::REQUIRES_ACK),
+    ];
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "byte_order": "little-endian",
+        "header_size": HEADER_SIZE,
+        "max_packet_size": MAX_PACKET_SIZE,
+        "header": fields(HEADER),
+        "flags": flags
+            .iter()
+            .map(|(name, flag)| json!({ "name": name, "bit": flag.bits() }))
+            .collect::<Vec<_>>(),
+        "fragments": "frames larger than max_packet_size are split; fragments take consecutive \
+                      sequences and the last one carries last_fragment",
+        "packet_types": packet_types(),
+        "types": PRIMITIVES
+            .iter()
+            .map(|(name, encoding)| json!({ "name": name, "encoding": encoding }))
+            .collect::<Vec<_>>(),
+        "enums": ENUMS
+            .iter()
+            .map(|(name, values)| json!({ "name": name, "values": values }))
+            .collect::<Vec<_>>(),
+        "commands": COMMANDS
+            .iter()
+            .map(|(opcode, name, command_fields)| {
+                json!({ "opcode": opcode, "name": name, "fields": fields(command_fields) })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
@@ -0,0 +1,365 @@
+use super::protocol::UdpCommand;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const TAPE_DIR: &str = "control_tapes";
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TapeEntry {
+    pub at_ms: u64,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tape {
+    pub name: String,
+    pub recorded_at: u64,
+    pub duration_ms: u64,
+    pub entries: Vec<TapeEntry>,
+}
+
+enum Mode {
+    Idle,
+    Recording {
+        name: String,
+        started: Instant,
+        entries: Vec<TapeEntry>,
+    },
+    Replaying {
+        tape: Tape,
+        started: Instant,
+        next: usize,
+        // Replies of replayed commands go back to whoever started the replay
+        reply_to: SocketAddr,
+    },
+}
+
+// Only the moves an operator makes during a set; queries, calibration, commissioning and
+// per-client stream settings would not mean the same thing on another night
+pub fn is_recordable(command: &UdpCommand) -> bool {
+    matches!(
+        command,
+        UdpCommand::SetEffect(_)
+            | UdpCommand::SetEffectByName(_)
+            | UdpCommand::NextEffect
+            | UdpCommand::PreviousEffect
+            | UdpCommand::SetColorMode(_)
+            | UdpCommand::SetCustomColor(..)
+            | UdpCommand::SetParameter(..)
+            | UdpCommand::SetBlackout(_)
+            | UdpCommand::SetStrobe(_)
+            | UdpCommand::SetBrightness(_)
+            | UdpCommand::FadeBrightness(..)
+            | UdpCommand::FadeToBlack(_)
+            | UdpCommand::SetHouseLight(..)
+            | UdpCommand::SetRegionTrim(..)
+            | UdpCommand::SetXy(..)
+            | UdpCommand::SetXyRouting(..)
+            | UdpCommand::SetMetronome(_)
+            | UdpCommand::SetAccent(_)
+            | UdpCommand::SetHud(_)
+            | UdpCommand::SetSmoothingProfile(_)
+            | UdpCommand::SetBandSmoothing(..)
+            | UdpCommand::SetDecibelScale(..)
+            | UdpCommand::SetNoiseGate(..)
+            | UdpCommand::SetHighPass(..)
+            | UdpCommand::SetGenrePreset(_)
+            | UdpCommand::LoadPreset(_)
+            | UdpCommand::StartPlaylist
+            | UdpCommand::StopPlaylist
+    )
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Control tape: records operator commands with their timing and plays them back
+// against the live engine, one tape at a time
+pub struct ControlTape {
+    dir: PathBuf,
+    mode: Mode,
+}
+
+impl ControlTape {
+    pub fn new() -> Self {
+        Self::with_dir(PathBuf::from(TAPE_DIR))
+    }
+
+    fn with_dir(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            mode: Mode::Idle,
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    pub fn start_recording(&mut self, name: &str) -> Result<()> {
+        if !valid_name(name) {
+            return Err(anyhow!("Invalid tape name: {}", name));
+        }
+        if matches!(self.mode, Mode::Replaying { .. }) {
+            return Err(anyhow!("Stop the replay before recording"));
+        }
+
+        self.mode = Mode::Recording {
+            name: name.to_string(),
+            started: Instant::now(),
+            entries: Vec::new(),
+        };
+        Ok(())
+    }
+
+    pub fn record(&mut self, command: &UdpCommand) {
+        if let Mode::Recording {
+            started, entries, ..
+        } = &mut self.mode
+        {
+            if is_recordable(command) {
+                entries.push(TapeEntry {
+                    at_ms: started.elapsed().as_millis() as u64,
+                    payload: command.to_payload(),
+                });
+            }
+        }
+    }
+
+    // Saves a recording in progress, or cancels a replay; returns the saved tape
+    pub fn stop(&mut self) -> Result<Option<Tape>> {
+        match std::mem::replace(&mut self.mode, Mode::Idle) {
+            Mode::Recording {
+                name,
+                started,
+                entries,
+            } => {
+                let tape = Tape {
+                    name,
+                    recorded_at: timestamp(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    entries,
+                };
+                self.save(&tape)?;
+                Ok(Some(tape))
+            }
+            Mode::Replaying { .. } | Mode::Idle => Ok(None),
+        }
+    }
+
+    fn save(&self, tape: &Tape) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(&tape.name), serde_json::to_string_pretty(tape)?)?;
+        Ok(())
+    }
+
+    fn load(&self, name: &str) -> Result<Tape> {
+        if !valid_name(name) {
+            return Err(anyhow!("Invalid tape name: {}", name));
+        }
+        let content =
+            fs::read_to_string(self.path(name)).map_err(|_| anyhow!("Unknown tape: {}", name))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn start_replay(&mut self, name: &str, reply_to: SocketAddr) -> Result<&Tape> {
+        if matches!(self.mode, Mode::Recording { .. }) {
+            return Err(anyhow!("Stop the recording before replaying"));
+        }
+
+        self.mode = Mode::Replaying {
+            tape: self.load(name)?,
+            started: Instant::now(),
+            next: 0,
+            reply_to,
+        };
+        match &self.mode {
+            Mode::Replaying { tape, .. } => Ok(tape),
+            _ => unreachable!(),
+        }
+    }
+
+    // Commands whose time has come; the tape returns to idle after its last entry
+    pub fn due(&mut self) -> Option<(SocketAddr, Vec<UdpCommand>)> {
+        self.due_at(Instant::now())
+    }
+
+    fn due_at(&mut self, now: Instant) -> Option<(SocketAddr, Vec<UdpCommand>)> {
+        let Mode::Replaying {
+            tape,
+            started,
+            next,
+            reply_to,
+        } = &mut self.mode
+        else {
+            return None;
+        };
+
+        let elapsed = now.saturating_duration_since(*started).as_millis() as u64;
+        let count = tape.entries[*next..]
+            .iter()
+            .take_while(|entry| entry.at_ms <= elapsed)
+            .count();
+        let due: Vec<UdpCommand> = tape.entries[*next..*next + count]
+            .iter()
+            .filter_map(|entry| UdpCommand::from_payload(&entry.payload))
+            .collect();
+        *next += count;
+
+        let reply_to = *reply_to;
+        if *next == tape.entries.len() {
+            self.mode = Mode::Idle;
+        }
+        (!due.is_empty()).then_some((reply_to, due))
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replaying { .. })
+    }
+
+    fn list(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| path.file_stem()?.to_str().map(String::from))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        let (mode, name, position, total, elapsed_ms) = match &self.mode {
+            Mode::Idle => ("idle", None, 0, 0, 0),
+            Mode::Recording {
+                name,
+                started,
+                entries,
+            } => (
+                "recording",
+                Some(name.as_str()),
+                entries.len(),
+                entries.len(),
+                started.elapsed().as_millis() as u64,
+            ),
+            Mode::Replaying {
+                tape,
+                started,
+                next,
+                ..
+            } => (
+                "replaying",
+                Some(tape.name.as_str()),
+                *next,
+                tape.entries.len(),
+                started.elapsed().as_millis() as u64,
+            ),
+        };
+
+        serde_json::json!({
+            "mode": mode,
+            "name": name,
+            "position": position,
+            "commands": total,
+            "elapsed_ms": elapsed_ms,
+            "tapes": self.list(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn tape_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tape_{}_{}", label, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_records_only_control_moves() {
+        let dir = tape_dir("record");
+        let mut tape = ControlTape::with_dir(dir.clone());
+
+        tape.record(&UdpCommand::SetBrightness(0.2));
+        tape.start_recording("friday-set").unwrap();
+        tape.record(&UdpCommand::SetEffectByName("starfall".to_string()));
+        tape.record(&UdpCommand::GetParameters);
+        tape.record(&UdpCommand::FadeToBlack(4.0));
+
+        let saved = tape.stop().unwrap().unwrap();
+        assert_eq!(saved.entries.len(), 2);
+        assert!(matches!(
+            UdpCommand::from_payload(&saved.entries[1].payload),
+            Some(UdpCommand::FadeToBlack(_))
+        ));
+        assert_eq!(tape.status()["tapes"], serde_json::json!(["friday-set"]));
+        assert!(tape.start_recording("../etc").is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_replay_follows_timing() {
+        let dir = tape_dir("replay");
+        let mut tape = ControlTape::with_dir(dir.clone());
+        tape.save(&Tape {
+            name: "intro".to_string(),
+            recorded_at: 0,
+            duration_ms: 60_000,
+            entries: vec![
+                TapeEntry {
+                    at_ms: 0,
+                    payload: UdpCommand::NextEffect.to_payload(),
+                },
+                TapeEntry {
+                    at_ms: 60_000,
+                    payload: UdpCommand::SetBlackout(true).to_payload(),
+                },
+            ],
+        })
+        .unwrap();
+
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        assert!(tape.start_replay("missing", addr).is_err());
+        assert_eq!(tape.start_replay("intro", addr).unwrap().entries.len(), 2);
+        assert!(tape.start_recording("other").is_err());
+
+        let (reply_to, due) = tape.due().unwrap();
+        assert_eq!(reply_to, addr);
+        assert!(matches!(due.as_slice(), [UdpCommand::NextEffect]));
+        assert!(tape.due().is_none());
+        assert_eq!(tape.status()["position"], 1);
+
+        let later = Instant::now() + Duration::from_secs(61);
+        assert!(matches!(
+            tape.due_at(later).unwrap().1.as_slice(),
+            [UdpCommand::SetBlackout(true)]
+        ));
+        assert!(!tape.is_replaying());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::config::Config;
+use crate::crash;
+use crate::decibels::SpectrumScale;
+use crate::messages;
+use crate::palettes::PALETTES_PATH;
+use crate::smoothing::SpectrumSmoother;
+use crate::AppState;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CONFIG_PATH: &str = "config.toml";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Sections the running server can swap without a restart; everything else (audio
+// device, LED controllers, eHuB mapping...) is only read at startup
+const LIVE_SECTIONS: &[&str] = &[
+    "sensitivity",
+    "smoothing",
+    "band_weights",
+    "decibels",
+    "noise_gate",
+    "high_pass",
+    "trim",
+    "color_correction",
+    "content_policy",
+    "messages",
+    "av_delay",
+    "effect_parameters",
+];
+
+// What a reload did, broadcast to clients so a refused edit doesn't go unnoticed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReloadEvent {
+    pub file: String,
+    pub applied: Vec<String>,
+    // "section: reason" for every change left out
+    pub rejected: Vec<String>,
+}
+
+// Compared by content, so saves that swap the file and saves that rewrite it in place
+// both count, and rewriting identical contents does not
+struct WatchedFile {
+    path: PathBuf,
+    contents: Option<String>,
+}
+
+impl WatchedFile {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let contents = fs::read_to_string(&path).ok();
+        Self { path, contents }
+    }
+
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    // New contents when the file changed since the last poll; a vanished file is ignored
+    fn poll(&mut self) -> Option<String> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        if self.contents.as_ref() == Some(&contents) {
+            return None;
+        }
+        self.contents = Some(contents.clone());
+        Some(contents)
+    }
+}
+
+pub struct Watcher {
+    config_file: WatchedFile,
+    palettes_file: WatchedFile,
+    // Last config seen on disk, restart-only changes are reported once rather than every poll
+    config: Config,
+}
+
+impl Watcher {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config_file: WatchedFile::new(CONFIG_PATH),
+            palettes_file: WatchedFile::new(PALETTES_PATH),
+            config,
+        }
+    }
+
+    pub fn poll(&mut self, state: &AppState) -> Vec<ReloadEvent> {
+        let mut events = Vec::new();
+
+        if let Some(contents) = self.config_file.poll() {
+            let mut event = ReloadEvent {
+                file: self.config_file.name(),
+                applied: Vec::new(),
+                rejected: Vec::new(),
+            };
+            match self.reload_config(&contents) {
+                Ok((live, restart)) => {
+                    for section in live {
+                        apply_section(state, &section, &self.config);
+                        event.applied.push(section);
+                    }
+                    event.rejected.extend(
+                        restart
+                            .into_iter()
+                            .map(|section| format!("{}: restart required", section)),
+                    );
+                }
+                Err(reason) => event.rejected.push(reason),
+            }
+            if !event.applied.is_empty() || !event.rejected.is_empty() {
+                events.push(event);
+            }
+        }
+
+        if self.palettes_file.poll().is_some() {
+            let mut event = ReloadEvent {
+                file: self.palettes_file.name(),
+                applied: Vec::new(),
+                rejected: Vec::new(),
+            };
+            match state.palettes.lock().reload() {
+                Ok(true) => event.applied.push("palettes".to_string()),
+                Ok(false) => {}
+                Err(e) => event.rejected.push(format!("palettes: {}", e)),
+            }
+            if !event.applied.is_empty() || !event.rejected.is_empty() {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    // Splits the changed sections into live ones and restart-only ones. A config that
+    // doesn't parse or validate is refused whole, nothing of it gets applied.
+    fn reload_config(&mut self, contents: &str) -> Result<(Vec<String>, Vec<String>), String> {
+        let config: Config =
+            toml::from_str(contents).map_err(|e| format!("config: {}", e.message()))?;
+        let issues = config.validate();
+        if !issues.is_empty() {
+            return Err(format!("config: {}", issues.join(", ")));
+        }
+
+        let changed = changed_sections(&self.config, &config);
+        self.config = config;
+        Ok(changed
+            .into_iter()
+            .partition(|section| LIVE_SECTIONS.contains(&section.as_str())))
+    }
+}
+
+fn changed_sections(old: &Config, new: &Config) -> Vec<String> {
+    let (Ok(toml::Value::Table(old)), Ok(toml::Value::Table(new))) =
+        (toml::Value::try_from(old), toml::Value::try_from(new))
+    else {
+        return Vec::new();
+    };
+    let mut changed: Vec<String> = new
+        .iter()
+        .filter(|(key, value)| old.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .chain(old.keys().filter(|key| !new.contains_key(*key)).cloned())
+        .collect();
+    changed.sort();
+    changed
+}
+
+fn apply_section(state: &AppState, section: &str, config: &Config) {
+    match section {
+        "sensitivity" => state
+            .effect_engine
+            .lock()
+            .set_sensitivity_config(config.sensitivity.clone()),
+        "smoothing" => *state.smoothing.lock() = SpectrumSmoother::new(config.smoothing.clone()),
+        "band_weights" => *state.band_weights.lock() = config.band_weights,
+        "decibels" => *state.decibels.lock() = SpectrumScale::new(config.decibels.clone()),
+        "noise_gate" => state
+            .input_filter
+            .lock()
+            .set_gate(config.noise_gate.clone()),
+        "high_pass" => state
+            .input_filter
+            .lock()
+            .set_high_pass(config.high_pass.clone()),
+        "trim" => state.output.lock().trim = config.trim.clone(),
+        "color_correction" => {
+            state.output.lock().color_correction = config.color_correction.clone().normalized()
+        }
+        "content_policy" => {
+            state
+                .effect_engine
+                .lock()
+                .set_content_policy(config.content_policy.clone());
+            state
+                .output
+                .lock()
+                .set_content_policy(&config.content_policy);
+        }
+        "messages" => messages::set_language(config.messages.language),
+        "av_delay" => *state.av_delay.lock() = config.av_delay,
+        "effect_parameters" => state
+            .effect_engine
+            .lock()
+            .set_effect_parameters(config.effect_parameters.clone()),
+        _ => {}
+    }
+}
+
+// Watch mode (--watch): polls the config and palette files and applies edits live
+pub fn run(state: Arc<AppState>, config: Config) {
+    let mut watcher = Watcher::new(config);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        for event in watcher.poll(&state) {
+            if !event.applied.is_empty() {
+                crash::log(format!(
+                    "♻️ {} reloaded: {}",
+                    event.file,
+                    event.applied.join(", ")
+                ));
+            }
+            for rejected in &event.rejected {
+                crash::log(format!("⚠️ {} not reloaded: {}", event.file, rejected));
+            }
+            state.reload_events.lock().push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_changed_sections_are_reported() {
+        let old = Config::default();
+        let mut new = old.clone();
+        assert!(changed_sections(&old, &new).is_empty());
+
+        new.smoothing.bass.attack = 0.9;
+        new.led.brightness = 0.1;
+        assert_eq!(changed_sections(&old, &new), vec!["led", "smoothing"]);
+
+        let (live, restart): (Vec<_>, Vec<_>) = changed_sections(&old, &new)
+            .into_iter()
+            .partition(|section| LIVE_SECTIONS.contains(&section.as_str()));
+        assert_eq!(live, vec!["smoothing"]);
+        assert_eq!(restart, vec!["led"]);
+    }
+
+    #[test]
+    fn test_watched_file_reports_each_edit_once() {
+        let path = std::env::temp_dir().join(format!("watch_test_{}.toml", std::process::id()));
+        fs::write(&path, "a = 1").unwrap();
+        let mut file = WatchedFile::new(&path);
+        assert_eq!(file.poll(), None);
+
+        fs::write(&path, "a = 2").unwrap();
+        assert_eq!(file.poll().as_deref(), Some("a = 2"));
+        assert_eq!(file.poll(), None);
+
+        fs::write(&path, "a = 2").unwrap();
+        assert_eq!(file.poll(), None);
+
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,488 @@
+use crate::config::WebSocketConfig;
+use crate::crash;
+use crate::messages;
+use crate::udp::{
+    encode_command, FrameData, FrameFormat, PacketFlags, PacketType, SpectrumData, UdpCommand,
+    UdpPacket,
+};
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dj4led_protocol::{FrameReassembly, PacketHeader, Writer};
+use flate2::read::GzDecoder;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+// The UDP server of this same process; each WebSocket client is one of its clients
+const UDP_SERVER: &str = "127.0.0.1:8081";
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+// How long the client's socket is read before queued messages go out to it
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+// A client that stops reading is dropped rather than holding up its stream
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const FRAGMENT_TIMEOUT: Duration = Duration::from_millis(500);
+// Commands are small JSON objects; anything bigger is a misbehaving client
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+// Requests the server never answered (it was down) are forgotten past this many
+const MAX_PENDING: usize = 256;
+// After a handover the old instance holds the port until its fade is over
+const BIND_ATTEMPTS: u32 = 30;
+
+// {"id": 1, "command": "set_effect_by_name", "fields": {"name": "rain"}}: a command from
+// get_protocol_schema with its fields by name, or "subscribe" / "unsubscribe" for the stream
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    command: String,
+    #[serde(default)]
+    fields: Value,
+}
+
+struct Connection {
+    // Connected to the UDP server
+    socket: UdpSocket,
+    // To the thread that owns the WebSocket
+    outgoing: Sender<Value>,
+    // Request ids by the sequence their packet went out with
+    pending: Mutex<HashMap<u32, Value>>,
+    sequence: AtomicU32,
+    closed: AtomicBool,
+}
+
+impl Connection {
+    fn send_json(&self, message: Value) -> Result<()> {
+        self.outgoing
+            .send(message)
+            .map_err(|_| anyhow!("WebSocket client gone"))
+    }
+
+    fn send_packet(&self, packet: &UdpPacket) -> Result<()> {
+        self.socket.send(&packet.to_bytes()?)?;
+        Ok(())
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+
+    fn request(&self, request: Request) -> Result<()> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let packet = match request.command.as_str() {
+            "subscribe" => {
+                let role = match request.fields.get("role").and_then(Value::as_str) {
+                    None | Some("monitor") => 0x00,
+                    Some("display") => 0x01,
+                    Some(role) => bail!("Unknown role {}", role),
+                };
+                let max_duration_secs = request
+                    .fields
+                    .get("max_duration_secs")
+                    .and_then(Value::as_u64)
+                    .map_or(0, |secs| secs.min(u32::MAX as u64) as u32);
+                let payload = Writer::new().u8(role).u32(max_duration_secs).finish();
+                UdpPacket::new(PacketType::Connect, sequence, payload)
+            }
+            "unsubscribe" => {
+                // The server doesn't answer a disconnect
+                self.send_packet(&UdpPacket::new(PacketType::Disconnect, sequence, vec![]))?;
+                return self.send_json(json!({ "type": "ack", "id": request.id }));
+            }
+            name => {
+                let payload = encode_command(name, &request.fields)?;
+                match UdpCommand::from_payload(&payload) {
+                    Some(UdpCommand::Handover(_)) => {
+                        bail!("handover is only for a new backend on the same machine")
+                    }
+                    Some(_) => {}
+                    None => bail!("Invalid fields for {}", name),
+                }
+                let mut packet = UdpPacket::new(PacketType::Command, sequence, payload);
+                // Commands without a response are acknowledged, so every request gets an answer
+                packet.flags |= PacketFlags::REQUIRES_ACK;
+                packet
+            }
+        };
+
+        {
+            let mut pending = self.pending.lock();
+            if pending.len() >= MAX_PENDING {
+                pending.clear();
+            }
+            pending.insert(sequence, request.id);
+        }
+        self.send_packet(&packet)
+    }
+
+    // The WebSocket is only touched from here: reads time out so the messages queued by the
+    // forwarder go out in between. Pings and the close handshake are answered by tungstenite.
+    fn serve_client(
+        &self,
+        mut websocket: WebSocket<TcpStream>,
+        outgoing: Receiver<Value>,
+    ) -> Result<()> {
+        loop {
+            match websocket.read() {
+                Ok(Message::Text(text)) => {
+                    let request = serde_json::from_str::<Request>(&text)
+                        .map_err(|e| anyhow!("Invalid request: {}", e));
+                    let id = request.as_ref().map_or(Value::Null, |r| r.id.clone());
+                    if let Err(e) = request.and_then(|request| self.request(request)) {
+                        let error = json!({ "type": "error", "id": id, "error": e.to_string() });
+                        self.send_json(error)?;
+                    }
+                }
+                Ok(Message::Binary(_)) => {
+                    let error =
+                        json!({ "type": "error", "error": "Requests are JSON text messages" });
+                    self.send_json(error)?;
+                }
+                Ok(Message::Close(_)) => {
+                    // Sends tungstenite's reply to the close
+                    let _ = websocket.flush();
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+
+            for message in outgoing.try_iter() {
+                websocket.write(Message::text(message.to_string()))?;
+            }
+            websocket.flush()?;
+        }
+    }
+
+    fn forward_from_server(&self) {
+        let mut fragments = FrameReassembly::new(FRAGMENT_TIMEOUT);
+        let mut buf = vec![0u8; 65536];
+        while !self.closed.load(Ordering::Relaxed) {
+            // Timeouts let the loop notice the client leaving; a refused send while the
+            // server is down comes back here too
+            let Ok(len) = self.socket.recv(&mut buf) else {
+                continue;
+            };
+            let Ok(packet) = UdpPacket::from_bytes(&buf[..len]) else {
+                continue;
+            };
+
+            if packet.packet_type == PacketType::Ping {
+                let _ = self.send_packet(&UdpPacket::new_pong(packet.sequence));
+                continue;
+            }
+            let payload = if packet.flags.contains(PacketFlags::FRAGMENTED) {
+                let Ok((header, _)) = PacketHeader::parse(&buf[..len]) else {
+                    continue;
+                };
+                match fragments.push(&header, &packet.payload) {
+                    Some(payload) => payload,
+                    None => continue,
+                }
+            } else {
+                packet.payload
+            };
+
+            let message = to_message(
+                packet.packet_type,
+                packet.sequence,
+                &payload,
+                &mut self.pending.lock(),
+            );
+            if let Some(message) = message {
+                if self.send_json(message).is_err() {
+                    self.close();
+                }
+            }
+        }
+    }
+}
+
+fn frame_message(payload: &[u8]) -> Option<Value> {
+    let frame = FrameData::from_payload(payload)?;
+    let format = match frame.format {
+        FrameFormat::RGB => "rgb",
+        FrameFormat::RGBA => "rgba",
+        FrameFormat::BGR => "bgr",
+        FrameFormat::BGRA => "bgra",
+    };
+    Some(json!({
+        "type": "frame",
+        "width": frame.width,
+        "height": frame.height,
+        "format": format,
+        "pixels": BASE64.encode(&frame.data),
+    }))
+}
+
+// What a packet from the server becomes for the WebSocket client; None for what it doesn't need
+fn to_message(
+    packet_type: PacketType,
+    sequence: u32,
+    payload: &[u8],
+    pending: &mut HashMap<u32, Value>,
+) -> Option<Value> {
+    let event = |name: &str| {
+        let data: Value = serde_json::from_slice(payload).ok()?;
+        Some(json!({ "type": name, "data": data }))
+    };
+
+    match packet_type {
+        PacketType::Ack => {
+            let id = pending.remove(&sequence)?;
+            Some(json!({ "type": "ack", "id": id }))
+        }
+        PacketType::Response => {
+            let id = pending.remove(&sequence)?;
+            let result: Value = serde_json::from_slice(payload).unwrap_or(Value::Null);
+            Some(json!({ "type": "response", "id": id, "result": result }))
+        }
+        PacketType::Nack => {
+            let id = pending.remove(&sequence)?;
+            let (code, error) = messages::parse_nack(payload);
+            Some(json!({ "type": "error", "id": id, "code": code, "error": error }))
+        }
+        PacketType::FrameData => frame_message(payload),
+        PacketType::FrameDataCompressed => {
+            let mut frame = Vec::new();
+            GzDecoder::new(payload).read_to_end(&mut frame).ok()?;
+            frame_message(&frame)
+        }
+        PacketType::SpectrumData => {
+            let spectrum = SpectrumData::from_payload(payload)?;
+            Some(json!({ "type": "spectrum", "bands": spectrum.bands }))
+        }
+        PacketType::ColorModes => event("color_modes"),
+        PacketType::OutputAlert => event("output_alert"),
+        PacketType::ConfigReload => event("config_reload"),
+        PacketType::AlertEvent => event("alert_event"),
+        PacketType::BeatEvent => event("beat_event"),
+        PacketType::Disconnect => Some(json!({ "type": "disconnected" })),
+        _ => None,
+    }
+}
+
+// The HTTP upgrade; anything else gets a 400 explaining what the endpoint is
+fn accept(stream: TcpStream) -> Result<WebSocket<TcpStream>> {
+    let mut reply = stream.try_clone()?;
+    let config = tungstenite::protocol::WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_SIZE))
+        .max_frame_size(Some(MAX_MESSAGE_SIZE));
+    tungstenite::accept_with_config(stream, Some(config)).map_err(|e| {
+        let body = "WebSocket endpoint: connect with a WebSocket client\n";
+        let _ = write!(
+            reply,
+            "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        anyhow!("Not a WebSocket upgrade request: {}", e)
+    })
+}
+
+fn serve(stream: TcpStream) -> Result<()> {
+    let peer = stream.peer_addr()?;
+    let websocket = accept(stream)?;
+    websocket.get_ref().set_write_timeout(Some(WRITE_TIMEOUT))?;
+    websocket
+        .get_ref()
+        .set_read_timeout(Some(CLIENT_POLL_INTERVAL))?;
+
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    socket.connect(UDP_SERVER)?;
+    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+    let (outgoing, queued) = mpsc::channel();
+    let connection = Arc::new(Connection {
+        socket,
+        outgoing,
+        pending: Mutex::new(HashMap::new()),
+        sequence: AtomicU32::new(1),
+        closed: AtomicBool::new(false),
+    });
+    crash::log(format!("🌐 WebSocket client {} connected", peer));
+
+    let forwarder = {
+        let connection = connection.clone();
+        thread::spawn(move || connection.forward_from_server())
+    };
+    let result = connection.serve_client(websocket, queued);
+    connection.close();
+    let _ = connection.send_packet(&UdpPacket::new(PacketType::Disconnect, 0, vec![]));
+    let _ = forwarder.join();
+
+    match result {
+        Err(e) if !is_disconnect(&e) => {
+            crash::log(format!("🌐 WebSocket client {} dropped: {}", peer, e))
+        }
+        _ => crash::log(format!("🌐 WebSocket client {} disconnected", peer)),
+    }
+    Ok(())
+}
+
+fn is_disconnect(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<tungstenite::Error>() {
+        Some(tungstenite::Error::Protocol(
+            tungstenite::error::ProtocolError::ResetWithoutClosingHandshake,
+        )) => true,
+        Some(tungstenite::Error::Io(e)) => matches!(
+            e.kind(),
+            io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::NotConnected
+        ),
+        _ => false,
+    }
+}
+
+// WebSocket control API: the UDP commands as JSON, plus the preview stream, for web
+// dashboards and tablets that can't speak the binary protocol
+pub fn run(config: WebSocketConfig) {
+    let Some(address) = config.address else {
+        return;
+    };
+    let mut attempts = 0;
+    let listener = loop {
+        match TcpListener::bind(&address) {
+            Ok(listener) => break listener,
+            Err(e) if attempts < BIND_ATTEMPTS => {
+                attempts += 1;
+                if attempts == 1 {
+                    crash::log(format!(
+                        "⏳ WebSocket address {} busy ({}), retrying",
+                        address, e
+                    ));
+                }
+                thread::sleep(Duration::from_secs(1));
+            }
+            Err(e) => {
+                crash::log(format!(
+                    "❌ WebSocket API disabled, can't listen on {}: {}",
+                    address, e
+                ));
+                return;
+            }
+        }
+    };
+    crash::log(format!("🌐 WebSocket control API on ws://{}", address));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(e) = serve(stream) {
+                        crash::log(format!("⚠️ WebSocket connection refused: {}", e));
+                    }
+                });
+            }
+            Err(e) => crash::log(format!("⚠️ WebSocket accept failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_packets_become_json_messages() {
+        let mut pending = HashMap::from([(7, json!("trim")), (8, json!(2)), (9, json!(null))]);
+
+        let response = to_message(
+            PacketType::Response,
+            7,
+            br#"{"quarters":[1.0]}"#,
+            &mut pending,
+        );
+        assert_eq!(
+            response,
+            Some(json!({ "type": "response", "id": "trim", "result": { "quarters": [1.0] } }))
+        );
+        let nack = br#"{"code":"unknown_preset","message":"Unknown preset: drop","args":{}}"#;
+        let error = to_message(PacketType::Nack, 8, nack, &mut pending).unwrap();
+        assert_eq!(error["id"], 2);
+        assert_eq!(error["code"], "unknown_preset");
+        assert_eq!(error["error"], "Unknown preset: drop");
+        assert!(to_message(PacketType::Ack, 9, &[], &mut pending).is_some());
+        // Answered once, and frames acknowledged by the stream aren't requests
+        assert!(to_message(PacketType::Ack, 9, &[], &mut pending).is_none());
+
+        let frame = FrameData {
+            width: 2,
+            height: 1,
+            format: FrameFormat::RGB,
+            data: vec![255, 0, 0, 0, 0, 255],
+        };
+        let message = to_message(PacketType::FrameData, 40, &frame.to_payload(), &mut pending);
+        assert_eq!(
+            message,
+            Some(json!({
+                "type": "frame",
+                "width": 2,
+                "height": 1,
+                "format": "rgb",
+                "pixels": "/wAAAAD/",
+            }))
+        );
+
+        let spectrum = SpectrumData {
+            bands: vec![0.5, 0.25],
+        };
+        let message = to_message(
+            PacketType::SpectrumData,
+            41,
+            &spectrum.to_payload(),
+            &mut pending,
+        );
+        assert_eq!(message.unwrap()["bands"], json!([0.5, 0.25]));
+        let beat = to_message(PacketType::BeatEvent, 42, br#"{"bpm":128}"#, &mut pending);
+        assert_eq!(beat.unwrap()["data"]["bpm"], 128);
+    }
+
+    #[test]
+    fn test_only_upgrades_are_accepted() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let results: Vec<_> = (0..2)
+                .map(|_| accept(listener.accept().unwrap().0).map(|_| ()))
+                .collect();
+            results
+        });
+
+        let (_websocket, response) = tungstenite::client(
+            format!("ws://{}/control", address),
+            TcpStream::connect(address).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(response.status(), 101);
+
+        let mut plain = TcpStream::connect(address).unwrap();
+        plain
+            .write_all(b"GET / HTTP/1.1\r\nHost: wall.local\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        plain.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        let results = server.join().unwrap();
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::config::MAX_SENSITIVITY;
+use crate::effects::{hsv_to_rgb, EffectEngine, INTENSITY};
+use crate::led::OutputControl;
+use crate::udp::XyTarget;
+
+pub struct XyPad {
+    x_target: XyTarget,
+    y_target: XyTarget,
+    hue: f32,
+    saturation: f32,
+}
+
+impl XyPad {
+    pub fn new() -> Self {
+        Self {
+            x_target: XyTarget::Hue,
+            y_target: XyTarget::Brightness,
+            hue: 0.0,
+            saturation: 1.0,
+        }
+    }
+
+    pub fn set_routing(&mut self, x_target: XyTarget, y_target: XyTarget) {
+        self.x_target = x_target;
+        self.y_target = y_target;
+    }
+
+    pub fn apply(&mut self, x: f32, y: f32, engine: &mut EffectEngine, output: &mut OutputControl) {
+        let mut color_changed = false;
+
+        for (target, value) in [(&self.x_target, x), (&self.y_target, y)] {
+            let value = value.clamp(0.0, 1.0);
+
+            // Knobs the current effect doesn't have are skipped: the pad keeps streaming
+            // positions across effect changes
+            match target {
+                XyTarget::None => {}
+                XyTarget::Hue => {
+                    self.hue = value;
+                    color_changed = true;
+                }
+                XyTarget::Saturation => {
+                    self.saturation = value;
+                    color_changed = true;
+                }
+                XyTarget::Brightness => output.set_brightness(value),
+                XyTarget::Intensity => {
+                    let _ = engine.set_effect_parameter_fraction(INTENSITY, value);
+                }
+                XyTarget::Sensitivity => {
+                    let _ = engine.set_effect_sensitivity(value * MAX_SENSITIVITY);
+                }
+                XyTarget::Parameter(name) => {
+                    let _ = engine.set_effect_parameter_fraction(name, value);
+                }
+            }
+        }
+
+        if color_changed {
+            let (r, g, b) = hsv_to_rgb(self.hue.min(0.999), self.saturation, 1.0);
+            engine.set_color_mode("custom");
+            engine.set_custom_color(r, g, b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xy_routing() {
+        let mut pad = XyPad::new();
+        let mut engine = EffectEngine::new();
+        let mut output = OutputControl::default();
+
+        pad.apply(0.5, 0.25, &mut engine, &mut output);
+        assert_eq!(output.brightness, 0.25);
+        assert_eq!(pad.hue, 0.5);
+
+        pad.set_routing(XyTarget::Saturation, XyTarget::None);
+        pad.apply(0.1, 2.0, &mut engine, &mut output);
+        assert_eq!(output.brightness, 0.25);
+        assert_eq!(pad.saturation, 0.1);
+        assert_eq!(pad.hue, 0.5);
+
+        pad.set_routing(XyTarget::Intensity, XyTarget::Parameter("nope".to_string()));
+        pad.apply(0.8, 0.3, &mut engine, &mut output);
+        let parameters = engine.parameters();
+        let intensity = parameters["effect_parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == INTENSITY)
+            .unwrap();
+        assert!((intensity["value"].as_f64().unwrap() - 0.8).abs() < 1e-6);
+    }
+}
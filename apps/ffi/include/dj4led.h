@@ -0,0 +1,111 @@
+/* Generated by cbindgen from apps/ffi/src/lib.rs, do not edit. */
+
+#ifndef DJ4LED_H
+#define DJ4LED_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define DJ4LED_OK 0
+
+#define DJ4LED_ERR_NULL -1
+
+#define DJ4LED_ERR_INVALID -2
+
+#define DJ4LED_ERR_PANIC -3
+
+#define DJ4LED_FRAME_WIDTH 128
+
+#define DJ4LED_FRAME_HEIGHT 128
+
+#define DJ4LED_SPECTRUM_BANDS 64
+
+#define DJ4LED_SAMPLE_RATE 48000
+
+/**
+ * Opaque handle; create with `dj4led_renderer_new`, release with `dj4led_renderer_free`.
+ */
+typedef struct Dj4ledRenderer Dj4ledRenderer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a renderer starting on `effect` (loosely matched), or the default effect when
+ * `effect` is NULL. Returns NULL if the effect is unknown.
+ *
+ * # Safety
+ * `effect` must be NULL or a valid NUL-terminated string.
+ */
+struct Dj4ledRenderer *dj4led_renderer_new(const char *effect);
+
+/**
+ * # Safety
+ * `renderer` must be NULL or a handle from `dj4led_renderer_new` not yet freed.
+ */
+void dj4led_renderer_free(struct Dj4ledRenderer *renderer);
+
+/**
+ * Feeds `len` band values in 0..1, bass first (`DJ4LED_SPECTRUM_BANDS` expected).
+ *
+ * # Safety
+ * `renderer` must be a live handle and `spectrum` must point to `len` floats.
+ */
+int32_t dj4led_push_spectrum(struct Dj4ledRenderer *renderer, const float *spectrum, size_t len);
+
+/**
+ * Feeds mono float PCM at `DJ4LED_SAMPLE_RATE`, in chunks of any size.
+ *
+ * # Safety
+ * `renderer` must be a live handle and `samples` must point to `len` floats.
+ */
+int32_t dj4led_push_pcm(struct Dj4ledRenderer *renderer, const float *samples, size_t len);
+
+/**
+ * Renders the next frame: row-major RGB, `DJ4LED_FRAME_WIDTH * DJ4LED_FRAME_HEIGHT * 3`
+ * bytes, also written to `len` when it is not NULL. The pointer stays valid until the
+ * next call on this renderer or until it is freed. Returns NULL on error.
+ *
+ * # Safety
+ * `renderer` must be a live handle and `len` NULL or writable.
+ */
+const uint8_t *dj4led_next_frame(struct Dj4ledRenderer *renderer, size_t *len);
+
+/**
+ * # Safety
+ * `renderer` must be a live handle and `name` a valid NUL-terminated string.
+ */
+int32_t dj4led_set_effect(struct Dj4ledRenderer *renderer, const char *name);
+
+/**
+ * Color modes ("rainbow", "fire", "ocean", "sunset", "custom") are shared by every
+ * renderer in the process.
+ *
+ * # Safety
+ * `renderer` must be a live handle and `mode` a valid NUL-terminated string.
+ */
+int32_t dj4led_set_color_mode(struct Dj4ledRenderer *renderer, const char *mode);
+
+/**
+ * # Safety
+ * `renderer` must be NULL or a live handle.
+ */
+size_t dj4led_effect_count(const struct Dj4ledRenderer *renderer);
+
+/**
+ * Registered effect name at `index`, owned by the renderer; NULL when out of range.
+ *
+ * # Safety
+ * `renderer` must be NULL or a live handle.
+ */
+const char *dj4led_effect_name(const struct Dj4ledRenderer *renderer, size_t index);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DJ4LED_H */
@@ -0,0 +1,270 @@
+//! C ABI over [`led_visualizer::Renderer`]. The header lives in `include/dj4led.h` and is
+//! regenerated by `cargo build`. With the `python` feature the same library is also the
+//! `dj4led` Python module.
+
+use led_visualizer::{Renderer, RendererConfig};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+#[cfg(feature = "python")]
+mod python;
+
+pub const DJ4LED_OK: i32 = 0;
+pub const DJ4LED_ERR_NULL: i32 = -1;
+pub const DJ4LED_ERR_INVALID: i32 = -2;
+pub const DJ4LED_ERR_PANIC: i32 = -3;
+
+pub const DJ4LED_FRAME_WIDTH: usize = 128;
+pub const DJ4LED_FRAME_HEIGHT: usize = 128;
+pub const DJ4LED_SPECTRUM_BANDS: usize = 64;
+pub const DJ4LED_SAMPLE_RATE: u32 = 48_000;
+
+const _: () = assert!(DJ4LED_FRAME_WIDTH == led_visualizer::FRAME_WIDTH);
+const _: () = assert!(DJ4LED_FRAME_HEIGHT == led_visualizer::FRAME_HEIGHT);
+const _: () = assert!(DJ4LED_SPECTRUM_BANDS == led_visualizer::SPECTRUM_BANDS);
+const _: () = assert!(DJ4LED_SAMPLE_RATE == led_visualizer::renderer::SAMPLE_RATE);
+
+/// Opaque handle; create with `dj4led_renderer_new`, release with `dj4led_renderer_free`.
+pub struct Dj4ledRenderer {
+    renderer: Renderer,
+    effect_names: Vec<CString>,
+}
+
+// A panic must never unwind into C, so every entry point reports it as an error instead
+fn guard<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+unsafe fn str_arg<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+unsafe fn slice_arg<'a, T>(data: *const T, len: usize) -> Option<&'a [T]> {
+    if data.is_null() && len > 0 {
+        return None;
+    }
+    Some(if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    })
+}
+
+/// Creates a renderer starting on `effect` (loosely matched), or the default effect when
+/// `effect` is NULL. Returns NULL if the effect is unknown.
+///
+/// # Safety
+/// `effect` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_renderer_new(effect: *const c_char) -> *mut Dj4ledRenderer {
+    let effect = if effect.is_null() {
+        None
+    } else {
+        match str_arg(effect) {
+            Some(name) => Some(name.to_string()),
+            None => return ptr::null_mut(),
+        }
+    };
+
+    guard(ptr::null_mut(), || {
+        let Ok(renderer) = Renderer::new(RendererConfig {
+            effect,
+            ..RendererConfig::default()
+        }) else {
+            return ptr::null_mut();
+        };
+        let effect_names = renderer
+            .effect_names()
+            .into_iter()
+            .filter_map(|name| CString::new(name).ok())
+            .collect();
+        Box::into_raw(Box::new(Dj4ledRenderer {
+            renderer,
+            effect_names,
+        }))
+    })
+}
+
+/// # Safety
+/// `renderer` must be NULL or a handle from `dj4led_renderer_new` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_renderer_free(renderer: *mut Dj4ledRenderer) {
+    if !renderer.is_null() {
+        drop(Box::from_raw(renderer));
+    }
+}
+
+/// Feeds `len` band values in 0..1, bass first (`DJ4LED_SPECTRUM_BANDS` expected).
+///
+/// # Safety
+/// `renderer` must be a live handle and `spectrum` must point to `len` floats.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_push_spectrum(
+    renderer: *mut Dj4ledRenderer,
+    spectrum: *const f32,
+    len: usize,
+) -> i32 {
+    let (Some(handle), Some(spectrum)) = (renderer.as_mut(), slice_arg(spectrum, len)) else {
+        return DJ4LED_ERR_NULL;
+    };
+    guard(DJ4LED_ERR_PANIC, || {
+        handle.renderer.push_spectrum(spectrum);
+        DJ4LED_OK
+    })
+}
+
+/// Feeds mono float PCM at `DJ4LED_SAMPLE_RATE`, in chunks of any size.
+///
+/// # Safety
+/// `renderer` must be a live handle and `samples` must point to `len` floats.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_push_pcm(
+    renderer: *mut Dj4ledRenderer,
+    samples: *const f32,
+    len: usize,
+) -> i32 {
+    let (Some(handle), Some(samples)) = (renderer.as_mut(), slice_arg(samples, len)) else {
+        return DJ4LED_ERR_NULL;
+    };
+    guard(DJ4LED_ERR_PANIC, || {
+        handle.renderer.push_samples(samples);
+        DJ4LED_OK
+    })
+}
+
+/// Renders the next frame: row-major RGB, `DJ4LED_FRAME_WIDTH * DJ4LED_FRAME_HEIGHT * 3`
+/// bytes, also written to `len` when it is not NULL. The pointer stays valid until the
+/// next call on this renderer or until it is freed. Returns NULL on error.
+///
+/// # Safety
+/// `renderer` must be a live handle and `len` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_next_frame(
+    renderer: *mut Dj4ledRenderer,
+    len: *mut usize,
+) -> *const u8 {
+    let Some(handle) = renderer.as_mut() else {
+        return ptr::null();
+    };
+    let frame = guard(None, || {
+        let frame = handle.renderer.next_frame();
+        Some((frame.as_ptr(), frame.len()))
+    });
+    match frame {
+        Some((data, frame_len)) => {
+            if let Some(len) = len.as_mut() {
+                *len = frame_len;
+            }
+            data
+        }
+        None => ptr::null(),
+    }
+}
+
+/// # Safety
+/// `renderer` must be a live handle and `name` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_set_effect(
+    renderer: *mut Dj4ledRenderer,
+    name: *const c_char,
+) -> i32 {
+    let (Some(handle), Some(name)) = (renderer.as_mut(), str_arg(name)) else {
+        return DJ4LED_ERR_NULL;
+    };
+    guard(DJ4LED_ERR_PANIC, || match handle.renderer.set_effect(name) {
+        Ok(_) => DJ4LED_OK,
+        Err(_) => DJ4LED_ERR_INVALID,
+    })
+}
+
+/// Color modes ("rainbow", "fire", "ocean", "sunset", "custom") are shared by every
+/// renderer in the process.
+///
+/// # Safety
+/// `renderer` must be a live handle and `mode` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_set_color_mode(
+    renderer: *mut Dj4ledRenderer,
+    mode: *const c_char,
+) -> i32 {
+    let (Some(handle), Some(mode)) = (renderer.as_mut(), str_arg(mode)) else {
+        return DJ4LED_ERR_NULL;
+    };
+    guard(DJ4LED_ERR_PANIC, || match handle.renderer.set_color_mode(mode) {
+        Ok(()) => DJ4LED_OK,
+        Err(_) => DJ4LED_ERR_INVALID,
+    })
+}
+
+/// # Safety
+/// `renderer` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_effect_count(renderer: *const Dj4ledRenderer) -> usize {
+    renderer.as_ref().map_or(0, |handle| handle.effect_names.len())
+}
+
+/// Registered effect name at `index`, owned by the renderer; NULL when out of range.
+///
+/// # Safety
+/// `renderer` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn dj4led_effect_name(
+    renderer: *const Dj4ledRenderer,
+    index: usize,
+) -> *const c_char {
+    renderer
+        .as_ref()
+        .and_then(|handle| handle.effect_names.get(index))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_through_c_abi() {
+        unsafe {
+            let renderer = dj4led_renderer_new(c"spectrum bars".as_ptr());
+            assert!(!renderer.is_null());
+            assert!(dj4led_effect_count(renderer) > 0);
+            assert!(!dj4led_effect_name(renderer, 0).is_null());
+            assert!(dj4led_effect_name(renderer, usize::MAX).is_null());
+
+            let spectrum = [0.8f32; DJ4LED_SPECTRUM_BANDS];
+            assert_eq!(
+                dj4led_push_spectrum(renderer, spectrum.as_ptr(), spectrum.len()),
+                DJ4LED_OK
+            );
+            assert_eq!(
+                dj4led_set_effect(renderer, c"nothing like it".as_ptr()),
+                DJ4LED_ERR_INVALID
+            );
+
+            let mut len = 0;
+            let frame = dj4led_next_frame(renderer, &mut len);
+            assert!(!frame.is_null());
+            assert_eq!(len, DJ4LED_FRAME_WIDTH * DJ4LED_FRAME_HEIGHT * 3);
+            assert!(std::slice::from_raw_parts(frame, len).iter().any(|&c| c > 0));
+
+            dj4led_renderer_free(renderer);
+        }
+    }
+
+    #[test]
+    fn test_null_arguments_rejected() {
+        unsafe {
+            assert_eq!(
+                dj4led_push_pcm(ptr::null_mut(), ptr::null(), 0),
+                DJ4LED_ERR_NULL
+            );
+            assert!(dj4led_next_frame(ptr::null_mut(), ptr::null_mut()).is_null());
+            assert!(dj4led_renderer_new(c"nothing like it".as_ptr()).is_null());
+            dj4led_renderer_free(ptr::null_mut());
+        }
+    }
+}
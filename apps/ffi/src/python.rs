@@ -0,0 +1,274 @@
+use dj4led_protocol::{PacketHeader, Writer};
+use led_visualizer::messages;
+use led_visualizer::renderer::SAMPLE_RATE;
+use led_visualizer::{Renderer, RendererConfig, FRAME_HEIGHT, FRAME_WIDTH, SPECTRUM_BANDS};
+use pyo3::exceptions::{PyOSError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+const COMMAND: u8 = 0x10;
+const RESPONSE: u8 = 0x11;
+const NACK: u8 = 0x06;
+
+const SET_COLOR_MODE: u8 = 0x02;
+const SET_PARAMETER: u8 = 0x04;
+const SET_BLACKOUT: u8 = 0x05;
+const NEXT_EFFECT: u8 = 0x07;
+const PREVIOUS_EFFECT: u8 = 0x08;
+const SET_BRIGHTNESS: u8 = 0x09;
+const SET_EFFECT_BY_NAME: u8 = 0x12;
+const GET_PARAMETERS: u8 = 0x1A;
+
+fn value_error(e: anyhow::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    let json = py.import("json")?;
+    Ok(json.call_method1("loads", (value.to_string(),))?.unbind())
+}
+
+/// Offline render pipeline, same effects as the wall: push a spectrum or PCM, pull frames.
+#[pyclass(name = "Renderer")]
+struct PyRenderer {
+    renderer: Renderer,
+}
+
+#[pymethods]
+impl PyRenderer {
+    #[new]
+    #[pyo3(signature = (effect=None, color_mode=None))]
+    fn new(effect: Option<String>, color_mode: Option<String>) -> PyResult<Self> {
+        let renderer = Renderer::new(RendererConfig {
+            effect,
+            color_mode,
+            ..RendererConfig::default()
+        })
+        .map_err(value_error)?;
+        Ok(Self { renderer })
+    }
+
+    /// 64 band values in 0..1, bass first.
+    fn push_spectrum(&mut self, spectrum: Vec<f32>) {
+        self.renderer.push_spectrum(&spectrum);
+    }
+
+    /// Mono float PCM at 48 kHz, any chunk size.
+    fn push_samples(&mut self, samples: Vec<f32>) {
+        self.renderer.push_samples(&samples);
+    }
+
+    /// Row-major RGB bytes, 128 × 128 × 3.
+    fn next_frame<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.renderer.next_frame())
+    }
+
+    #[getter]
+    fn spectrum(&self) -> Vec<f32> {
+        self.renderer.spectrum().to_vec()
+    }
+
+    #[getter]
+    fn effect(&self) -> &'static str {
+        self.renderer.current_effect()
+    }
+
+    fn effect_names(&self) -> Vec<&'static str> {
+        self.renderer.effect_names()
+    }
+
+    /// Loosely matched like the server does; returns the registered name.
+    fn set_effect(&mut self, name: &str) -> PyResult<&'static str> {
+        self.renderer.set_effect(name).map_err(value_error)
+    }
+
+    fn set_color_mode(&mut self, mode: &str) -> PyResult<()> {
+        self.renderer.set_color_mode(mode).map_err(value_error)
+    }
+
+    /// Same names as the server's SetParameter: "input_trim" or "sensitivity" (current effect).
+    fn set_parameter(&mut self, name: &str, value: f32) -> PyResult<()> {
+        let engine = self.renderer.engine_mut();
+        match name {
+            "input_trim" => {
+                engine.set_input_trim(value);
+                Ok(())
+            }
+            "sensitivity" => engine.set_effect_sensitivity(value).map_err(value_error),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown parameter: {}",
+                name
+            ))),
+        }
+    }
+
+    fn set_metronome(&mut self, enabled: bool) {
+        self.renderer.engine_mut().set_metronome(enabled);
+    }
+
+    fn parameters(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+        let parameters = self.renderer.engine_mut().parameters();
+        json_to_py(py, &parameters)
+    }
+}
+
+/// Remote control of a running backend over its UDP command protocol.
+#[pyclass(name = "Client")]
+struct PyClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    sequence: u32,
+}
+
+impl PyClient {
+    fn packet(&mut self, payload: &[u8]) -> (u32, Vec<u8>) {
+        self.sequence = self.sequence.wrapping_add(1);
+        let packet = PacketHeader::packet(COMMAND, 0x00, self.sequence, payload);
+        (self.sequence, packet)
+    }
+
+    fn command(&mut self, payload: &[u8]) -> PyResult<()> {
+        let (_, packet) = self.packet(payload);
+        self.socket
+            .send_to(&packet, self.server)
+            .map_err(|e| PyOSError::new_err(e.to_string()))?;
+        Ok(())
+    }
+
+    // Waiting on the server must not hold the GIL, other notebook threads keep running
+    fn request_json(&mut self, py: Python<'_>, payload: &[u8]) -> PyResult<serde_json::Value> {
+        py.allow_threads(|| self.exchange(payload))
+    }
+
+    fn exchange(&mut self, payload: &[u8]) -> PyResult<serde_json::Value> {
+        let (sequence, packet) = self.packet(payload);
+        self.socket
+            .send_to(&packet, self.server)
+            .map_err(|e| PyOSError::new_err(e.to_string()))?;
+
+        let mut buf = vec![0u8; 65536];
+        loop {
+            let len = match self.socket.recv(&mut buf) {
+                Ok(len) => len,
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                    return Err(PyTimeoutError::new_err("Server didn't answer the request"));
+                }
+                Err(e) => return Err(PyOSError::new_err(e.to_string())),
+            };
+            let Ok((header, body)) = PacketHeader::parse(&buf[..len]) else {
+                continue;
+            };
+            if header.sequence != sequence {
+                continue;
+            }
+            match header.packet_type {
+                NACK => {
+                    // Text first, then the stable code to branch on
+                    let (code, reason) = messages::parse_nack(body);
+                    return Err(PyValueError::new_err((reason, code)));
+                }
+                RESPONSE => {
+                    return serde_json::from_slice(body).map_err(|e| {
+                        PyValueError::new_err(format!("Invalid response payload: {}", e))
+                    })
+                }
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (host="127.0.0.1", port=8081, timeout=1.0))]
+    fn new(host: &str, port: u16, timeout: f64) -> PyResult<Self> {
+        let server = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| PyOSError::new_err(e.to_string()))?
+            .next()
+            .ok_or_else(|| PyValueError::new_err(format!("Cannot resolve {}", host)))?;
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| PyOSError::new_err(e.to_string()))?;
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        socket
+            .set_read_timeout(Some(timeout))
+            .map_err(|e| PyOSError::new_err(e.to_string()))?;
+        Ok(Self {
+            socket,
+            server,
+            sequence: 0,
+        })
+    }
+
+    /// Raw command (opcode followed by its arguments) whose JSON reply is returned.
+    fn request(&mut self, py: Python<'_>, payload: &[u8]) -> PyResult<PyObject> {
+        let response = self.request_json(py, payload)?;
+        json_to_py(py, &response)
+    }
+
+    /// Raw fire-and-forget command.
+    fn send(&mut self, payload: &[u8]) -> PyResult<()> {
+        self.command(payload)
+    }
+
+    fn set_effect(&mut self, py: Python<'_>, name: &str) -> PyResult<String> {
+        let payload = Writer::new().u8(SET_EFFECT_BY_NAME).str(name).finish();
+        let response = self.request_json(py, &payload)?;
+        Ok(response["effect"].as_str().unwrap_or(name).to_string())
+    }
+
+    fn next_effect(&mut self) -> PyResult<()> {
+        self.command(&[NEXT_EFFECT])
+    }
+
+    fn previous_effect(&mut self) -> PyResult<()> {
+        self.command(&[PREVIOUS_EFFECT])
+    }
+
+    fn set_color_mode(&mut self, mode: &str) -> PyResult<()> {
+        let payload = Writer::new().u8(SET_COLOR_MODE).str(mode).finish();
+        self.command(&payload)
+    }
+
+    fn set_brightness(&mut self, level: f32) -> PyResult<()> {
+        let payload = Writer::new()
+            .u8(SET_BRIGHTNESS)
+            .f32(level.clamp(0.0, 1.0))
+            .finish();
+        self.command(&payload)
+    }
+
+    fn set_blackout(&mut self, enabled: bool) -> PyResult<()> {
+        self.command(&[SET_BLACKOUT, enabled as u8])
+    }
+
+    fn set_parameter(&mut self, py: Python<'_>, name: &str, value: f32) -> PyResult<PyObject> {
+        let payload = Writer::new()
+            .u8(SET_PARAMETER)
+            .str16(name)
+            .str16(&value.to_string())
+            .finish();
+        let response = self.request_json(py, &payload)?;
+        json_to_py(py, &response)
+    }
+
+    fn get_parameters(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+        let response = self.request_json(py, &[GET_PARAMETERS])?;
+        json_to_py(py, &response)
+    }
+}
+
+#[pymodule]
+fn dj4led(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRenderer>()?;
+    m.add_class::<PyClient>()?;
+    m.add("FRAME_WIDTH", FRAME_WIDTH)?;
+    m.add("FRAME_HEIGHT", FRAME_HEIGHT)?;
+    m.add("SPECTRUM_BANDS", SPECTRUM_BANDS)?;
+    m.add("SAMPLE_RATE", SAMPLE_RATE)?;
+    Ok(())
+}
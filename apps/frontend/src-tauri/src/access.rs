@@ -0,0 +1,82 @@
+// src-tauri/src/access.rs
+use std::env;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+// Launch level override ("viewer" or "operator") and the PIN that unlocks operator mode
+const ACCESS_ENV: &str = "DJ4LED_ACCESS";
+const PIN_ENV: &str = "DJ4LED_OPERATOR_PIN";
+
+pub type AccessState = Arc<Mutex<AccessLevel>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLevel {
+    Viewer,
+    Operator,
+}
+
+impl AccessLevel {
+    // Viewer builds (`--features viewer`) start read-only, the env var can override either way
+    pub fn at_launch() -> Self {
+        match env::var(ACCESS_ENV).as_deref() {
+            Ok("viewer") => AccessLevel::Viewer,
+            Ok("operator") => AccessLevel::Operator,
+            _ if cfg!(feature = "viewer") => AccessLevel::Viewer,
+            _ => AccessLevel::Operator,
+        }
+    }
+}
+
+// Every command that changes what the room sees goes through this
+pub fn require_operator(access: &AccessState) -> Result<(), String> {
+    match *access.lock().map_err(|_| "Failed to access permissions".to_string())? {
+        AccessLevel::Operator => Ok(()),
+        AccessLevel::Viewer => Err("🔒 Viewer mode: log in as operator to change the show".to_string()),
+    }
+}
+
+// Same check for physical inputs (hotkeys, gamepad) that only hold an app handle
+pub fn is_operator<R: Runtime>(app: &AppHandle<R>) -> bool {
+    require_operator(&app.state::<AccessState>()).is_ok()
+}
+
+fn set_level<R: Runtime>(app: &AppHandle<R>, access: &AccessState, level: AccessLevel) -> Result<(), String> {
+    *access.lock().map_err(|_| "Failed to access permissions".to_string())? = level;
+    let _ = app.emit("access_changed", json!({ "level": level }));
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn dj_get_access(access: State<'_, AccessState>) -> Result<AccessLevel, String> {
+    access
+        .lock()
+        .map(|level| *level)
+        .map_err(|_| "Failed to access permissions".to_string())
+}
+
+#[tauri::command]
+pub async fn dj_login(app: AppHandle, access: State<'_, AccessState>, pin: String) -> Result<String, String> {
+    let expected = env::var(PIN_ENV)
+        .ok()
+        .filter(|pin| !pin.is_empty())
+        .ok_or_else(|| format!("Operator login disabled: {} is not set", PIN_ENV))?;
+
+    if pin != expected {
+        println!("🔒 access: Rejected operator login");
+        return Err("Wrong PIN".to_string());
+    }
+
+    set_level(&app, &access, AccessLevel::Operator)?;
+    println!("🔓 access: Operator logged in");
+    Ok("🔓 Operator mode".to_string())
+}
+
+#[tauri::command]
+pub async fn dj_logout(app: AppHandle, access: State<'_, AccessState>) -> Result<String, String> {
+    set_level(&app, &access, AccessLevel::Viewer)?;
+    println!("🔒 access: Back to viewer mode");
+    Ok("🔒 Viewer mode".to_string())
+}
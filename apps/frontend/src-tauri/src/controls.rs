@@ -0,0 +1,109 @@
+// src-tauri/src/controls.rs
+use std::sync::{Arc, Mutex};
+use dj4led_protocol::Writer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::access::{require_operator, AccessState};
+use crate::{
+    send_command, NEXT_EFFECT, PREVIOUS_EFFECT, SET_BLACKOUT, SET_BRIGHTNESS, SET_COLOR_MODE,
+    SET_CUSTOM_COLOR, SET_EFFECT, SET_STROBE,
+};
+
+// Shared toggle state so every physical input (hotkeys, gamepad...) agrees on it
+pub type ControlState = Arc<Mutex<ControlContext>>;
+
+#[derive(Debug, Default)]
+pub struct ControlContext {
+    blackout: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ControlAction {
+    EffectSlot(u32),
+    NextEffect,
+    PreviousEffect,
+    Blackout,
+    Strobe,
+}
+
+impl ControlAction {
+    // Momentary actions follow press/release, the others only fire on press
+    pub fn is_momentary(&self) -> bool {
+        matches!(self, ControlAction::Strobe)
+    }
+}
+
+pub fn trigger_action<R: Runtime>(
+    app: &AppHandle<R>,
+    source: &str,
+    action: &ControlAction,
+    pressed: bool,
+) -> Result<(), String> {
+    if !pressed && !action.is_momentary() {
+        return Ok(());
+    }
+    require_operator(&app.state::<AccessState>())?;
+
+    let state = app.state::<ControlState>();
+    let blackout = {
+        let mut ctx = state.lock().map_err(|_| "Failed to access control state".to_string())?;
+        if *action == ControlAction::Blackout {
+            ctx.blackout = !ctx.blackout;
+        }
+        ctx.blackout
+    };
+
+    let payload = match action {
+        ControlAction::EffectSlot(slot) => Writer::new().u8(SET_EFFECT).u32(*slot).finish(),
+        ControlAction::NextEffect => vec![NEXT_EFFECT],
+        ControlAction::PreviousEffect => vec![PREVIOUS_EFFECT],
+        ControlAction::Blackout => vec![SET_BLACKOUT, blackout as u8],
+        ControlAction::Strobe => vec![SET_STROBE, pressed as u8],
+    };
+
+    send_command(payload)?;
+
+    let _ = app.emit("control_triggered", json!({
+        "source": source,
+        "action": action,
+        "pressed": pressed,
+        "blackout": blackout
+    }));
+
+    Ok(())
+}
+
+pub fn send_brightness(brightness: f32) -> Result<(), String> {
+    let payload = Writer::new().u8(SET_BRIGHTNESS).f32(brightness.clamp(0.0, 1.0)).finish();
+    send_command(payload)
+}
+
+pub fn send_hue(hue: f32) -> Result<(), String> {
+    let (r, g, b) = hsv_to_rgb(hue.rem_euclid(1.0), 1.0, 1.0);
+
+    let mode_payload = Writer::new().u8(SET_COLOR_MODE).str("custom").finish();
+    send_command(mode_payload)?;
+
+    let payload = Writer::new().u8(SET_CUSTOM_COLOR).f32(r).f32(g).f32(b).finish();
+    send_command(payload)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let c = v * s;
+    let x = c * (1.0 - ((h * 6.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+
+    let (r, g, b) = match (h * 6.0) as i32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    (r + m, g + m, b + m)
+}
@@ -0,0 +1,73 @@
+// src-tauri/src/frame_history.rs
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde_json::json;
+use tauri::State;
+
+use crate::{parse_frame_data, FRAME_DATA};
+
+// Enough to scrub back to a glitch the operator has just seen on the wall
+const HISTORY_WINDOW: Duration = Duration::from_secs(10);
+
+pub type FrameHistoryState = Arc<Mutex<FrameHistory>>;
+
+// A preview frame as it arrived: a FRAME_DATA payload, or a FRAME_DATA_COMPRESSED one left
+// compressed until it is asked for
+struct RecordedFrame {
+    received_ms: u64,
+    packet_type: u8,
+    payload: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct FrameHistory {
+    frames: VecDeque<RecordedFrame>,
+}
+
+impl FrameHistory {
+    pub fn record(&mut self, received_ms: u64, packet_type: u8, payload: &[u8]) {
+        let cutoff = received_ms.saturating_sub(HISTORY_WINDOW.as_millis() as u64);
+        while self.frames.front().is_some_and(|frame| frame.received_ms < cutoff) {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(RecordedFrame {
+            received_ms,
+            packet_type,
+            payload: payload.to_vec(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    // The frame on the preview at `timestamp`: the last one received by then, or the oldest
+    // kept for a time before the window
+    fn at(&self, timestamp: u64) -> Option<&RecordedFrame> {
+        let after = self.frames.partition_point(|frame| frame.received_ms <= timestamp);
+        self.frames.get(after.saturating_sub(1))
+    }
+}
+
+// Frame shown at `timestamp` (Unix ms) within the last 10 s of the stream, as the matching frame_data
+// or frame_data_compressed event would have carried it, with the span that can be scrubbed
+#[tauri::command]
+pub fn dj_get_frame_at(history_state: State<'_, FrameHistoryState>, timestamp: u64) -> Result<serde_json::Value, String> {
+    let history = history_state.lock().map_err(|_| "Failed to access frame history".to_string())?;
+    let frame = history.at(timestamp).ok_or_else(|| "No frame recorded yet".to_string())?;
+
+    let (kind, data) = if frame.packet_type == FRAME_DATA {
+        ("frame_data", parse_frame_data(&frame.payload)?)
+    } else {
+        ("frame_data_compressed", json!(frame.payload))
+    };
+
+    Ok(json!({
+        "timestamp": frame.received_ms,
+        "kind": kind,
+        "data": data,
+        "oldest": history.frames.front().map(|f| f.received_ms),
+        "newest": history.frames.back().map(|f| f.received_ms)
+    }))
+}
@@ -0,0 +1,360 @@
+// src-tauri/src/gamepad.rs
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use gilrs::{Axis, Event, EventType, Gilrs};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::access;
+use crate::controls::{self, trigger_action, ControlAction};
+
+const GAMEPAD_FILE: &str = "gamepad.json";
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+// Sticks fire hundreds of events per second, the server only needs ~30 updates/s
+const AXIS_SEND_INTERVAL: Duration = Duration::from_millis(33);
+const AXIS_MIN_DELTA: f32 = 0.01;
+
+pub type GamepadState = Arc<Mutex<GamepadContext>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StickSide {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadButtonBinding {
+    pub button: String,
+    pub action: ControlAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadMapping {
+    pub buttons: Vec<GamepadButtonBinding>,
+    pub hue_stick: Option<StickSide>,
+    pub brightness_axis: Option<String>,
+    pub deadzone: f32,
+}
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        let slot_buttons = ["South", "East", "West", "North", "LeftTrigger", "RightTrigger", "Select", "Start"];
+        let mut buttons: Vec<GamepadButtonBinding> = slot_buttons
+            .iter()
+            .enumerate()
+            .map(|(slot, button)| GamepadButtonBinding {
+                button: button.to_string(),
+                action: ControlAction::EffectSlot(slot as u32),
+            })
+            .collect();
+
+        buttons.push(GamepadButtonBinding {
+            button: "DPadRight".to_string(),
+            action: ControlAction::NextEffect,
+        });
+        buttons.push(GamepadButtonBinding {
+            button: "DPadLeft".to_string(),
+            action: ControlAction::PreviousEffect,
+        });
+        buttons.push(GamepadButtonBinding {
+            button: "DPadDown".to_string(),
+            action: ControlAction::Blackout,
+        });
+        buttons.push(GamepadButtonBinding {
+            button: "RightTrigger2".to_string(),
+            action: ControlAction::Strobe,
+        });
+
+        Self {
+            buttons,
+            hue_stick: Some(StickSide::Left),
+            brightness_axis: Some("RightStickY".to_string()),
+            deadzone: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GamepadInfo {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Default)]
+pub struct GamepadContext {
+    mapping: GamepadMapping,
+    connected: Vec<GamepadInfo>,
+}
+
+struct AxisOutput {
+    last_sent: Instant,
+    last_value: f32,
+    // Latest value held back by the rate limit, sent once the interval is over
+    pending: Option<f32>,
+}
+
+impl AxisOutput {
+    fn new() -> Self {
+        Self {
+            last_sent: Instant::now(),
+            last_value: f32::NAN,
+            pending: None,
+        }
+    }
+
+    fn should_send(&mut self, value: f32) -> bool {
+        if (value - self.last_value).abs() < AXIS_MIN_DELTA {
+            self.pending = None;
+            return false;
+        }
+        if self.last_sent.elapsed() < AXIS_SEND_INTERVAL {
+            self.pending = Some(value);
+            return false;
+        }
+        self.mark_sent(value);
+        true
+    }
+
+    fn take_pending(&mut self) -> Option<f32> {
+        if self.last_sent.elapsed() < AXIS_SEND_INTERVAL {
+            return None;
+        }
+        let value = self.pending.take()?;
+        self.mark_sent(value);
+        Some(value)
+    }
+
+    fn mark_sent(&mut self, value: f32) {
+        self.last_sent = Instant::now();
+        self.last_value = value;
+    }
+}
+
+fn gamepad_path<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(GAMEPAD_FILE))
+}
+
+fn load_mapping<R: Runtime>(app: &AppHandle<R>) -> GamepadMapping {
+    gamepad_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_mapping<R: Runtime>(app: &AppHandle<R>, mapping: &GamepadMapping) -> Result<(), String> {
+    let path = gamepad_path(app).ok_or("No config directory available")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Config directory error: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(mapping)
+        .map_err(|e| format!("Gamepad mapping serialization error: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Gamepad mapping save error: {}", e))
+}
+
+fn stick_axes(side: StickSide) -> (Axis, Axis) {
+    match side {
+        StickSide::Left => (Axis::LeftStickX, Axis::LeftStickY),
+        StickSide::Right => (Axis::RightStickX, Axis::RightStickY),
+    }
+}
+
+fn refresh_connected(gilrs: &Gilrs, state: &GamepadState) {
+    let connected = gilrs
+        .gamepads()
+        .map(|(id, gamepad)| GamepadInfo {
+            id: id.into(),
+            name: gamepad.name().to_string(),
+        })
+        .collect();
+
+    if let Ok(mut ctx) = state.lock() {
+        ctx.connected = connected;
+    }
+}
+
+fn handle_axis<R: Runtime>(
+    app: &AppHandle<R>,
+    mapping: &GamepadMapping,
+    axes: &mut HashMap<Axis, f32>,
+    hue_output: &mut AxisOutput,
+    brightness_output: &mut AxisOutput,
+    axis: Axis,
+    value: f32,
+) {
+    axes.insert(axis, value);
+    let operator = access::is_operator(app);
+
+    if let Some(side) = mapping.hue_stick {
+        let (x_axis, y_axis) = stick_axes(side);
+        if axis == x_axis || axis == y_axis {
+            let x = axes.get(&x_axis).copied().unwrap_or(0.0);
+            let y = axes.get(&y_axis).copied().unwrap_or(0.0);
+
+            if (x * x + y * y).sqrt() > mapping.deadzone {
+                let hue = y.atan2(x) / std::f32::consts::TAU;
+                if operator && hue_output.should_send(hue) {
+                    if let Err(e) = controls::send_hue(hue) {
+                        println!("❌ gamepad: Hue update failed: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    if mapping.brightness_axis.as_deref() == Some(format!("{:?}", axis).as_str()) {
+        let brightness = ((value + 1.0) * 0.5).clamp(0.0, 1.0);
+        if operator && brightness_output.should_send(brightness) {
+            if let Err(e) = controls::send_brightness(brightness) {
+                println!("❌ gamepad: Brightness update failed: {}", e);
+            }
+        }
+    }
+
+    if value.abs() > mapping.deadzone {
+        let _ = app.emit("gamepad_input", json!({
+            "kind": "axis",
+            "name": format!("{:?}", axis),
+            "value": value
+        }));
+    }
+}
+
+// A flick or a released stick ends on a value the rate limit held back: sent once no more
+// events are coming
+fn flush_axes<R: Runtime>(
+    app: &AppHandle<R>,
+    hue_output: &mut AxisOutput,
+    brightness_output: &mut AxisOutput,
+) {
+    if !access::is_operator(app) {
+        return;
+    }
+    if let Some(hue) = hue_output.take_pending() {
+        if let Err(e) = controls::send_hue(hue) {
+            println!("❌ gamepad: Hue update failed: {}", e);
+        }
+    }
+    if let Some(brightness) = brightness_output.take_pending() {
+        if let Err(e) = controls::send_brightness(brightness) {
+            println!("❌ gamepad: Brightness update failed: {}", e);
+        }
+    }
+}
+
+pub fn start_gamepad_thread<R: Runtime>(app: AppHandle<R>) {
+    let mapping = load_mapping(&app);
+    if let Ok(mut ctx) = app.state::<GamepadState>().lock() {
+        ctx.mapping = mapping;
+    }
+
+    thread::spawn(move || {
+        let mut gilrs = match Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(e) => {
+                println!("⚠️ gamepad: Gamepad support unavailable: {}", e);
+                return;
+            }
+        };
+
+        let state = app.state::<GamepadState>().inner().clone();
+        refresh_connected(&gilrs, &state);
+        println!("🎮 gamepad: Listening for controller input");
+
+        let mut axes: HashMap<Axis, f32> = HashMap::new();
+        let mut hue_output = AxisOutput::new();
+        let mut brightness_output = AxisOutput::new();
+
+        loop {
+            let Some(Event { event, .. }) = gilrs.next_event_blocking(Some(POLL_TIMEOUT)) else {
+                flush_axes(&app, &mut hue_output, &mut brightness_output);
+                continue;
+            };
+
+            // Re-read each event so mapping edits apply immediately
+            let mapping = match state.lock() {
+                Ok(ctx) => ctx.mapping.clone(),
+                Err(_) => continue,
+            };
+
+            match event {
+                EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                    let pressed = matches!(event, EventType::ButtonPressed(..));
+                    let name = format!("{:?}", button);
+
+                    if pressed {
+                        let _ = app.emit("gamepad_input", json!({
+                            "kind": "button",
+                            "name": name
+                        }));
+                    }
+
+                    if let Some(binding) = mapping.buttons.iter().find(|b| b.button == name) {
+                        if let Err(e) = trigger_action(&app, "gamepad", &binding.action, pressed) {
+                            println!("❌ gamepad: {:?} failed: {}", binding.action, e);
+                        }
+                    }
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    handle_axis(
+                        &app,
+                        &mapping,
+                        &mut axes,
+                        &mut hue_output,
+                        &mut brightness_output,
+                        axis,
+                        value,
+                    );
+                }
+                EventType::Connected | EventType::Disconnected => {
+                    refresh_connected(&gilrs, &state);
+                    let _ = app.emit("gamepad_status", json!({
+                        "connected": state.lock().map(|ctx| ctx.connected.len()).unwrap_or(0)
+                    }));
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn dj_list_gamepads(gamepad_state: State<'_, GamepadState>) -> Result<Vec<GamepadInfo>, String> {
+    gamepad_state
+        .lock()
+        .map(|ctx| ctx.connected.clone())
+        .map_err(|_| "Failed to access gamepad state".to_string())
+}
+
+#[tauri::command]
+pub async fn dj_get_gamepad_mapping(gamepad_state: State<'_, GamepadState>) -> Result<GamepadMapping, String> {
+    gamepad_state
+        .lock()
+        .map(|ctx| ctx.mapping.clone())
+        .map_err(|_| "Failed to access gamepad state".to_string())
+}
+
+#[tauri::command]
+pub async fn dj_set_gamepad_mapping(
+    app: AppHandle,
+    gamepad_state: State<'_, GamepadState>,
+    mapping: GamepadMapping,
+) -> Result<String, String> {
+    println!("🎮 dj_set_gamepad_mapping: Applying {} button bindings", mapping.buttons.len());
+
+    save_mapping(&app, &mapping)?;
+    let count = mapping.buttons.len();
+
+    gamepad_state
+        .lock()
+        .map(|mut ctx| ctx.mapping = mapping)
+        .map_err(|_| "Failed to access gamepad state".to_string())?;
+
+    Ok(format!("✅ Gamepad mapping updated ({} buttons)", count))
+}
@@ -0,0 +1,160 @@
+// src-tauri/src/hotkeys.rs
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::controls::{trigger_action, ControlAction};
+
+const HOTKEYS_FILE: &str = "hotkeys.json";
+
+pub type HotkeyState = Arc<Mutex<HotkeyContext>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotkeyBinding {
+    pub accelerator: String,
+    pub action: ControlAction,
+}
+
+#[derive(Default)]
+pub struct HotkeyContext {
+    bindings: Vec<HotkeyBinding>,
+    registered: Vec<(Shortcut, ControlAction)>,
+}
+
+pub fn default_bindings() -> Vec<HotkeyBinding> {
+    let mut bindings: Vec<HotkeyBinding> = (0..8)
+        .map(|slot| HotkeyBinding {
+            accelerator: format!("CmdOrCtrl+Alt+{}", slot + 1),
+            action: ControlAction::EffectSlot(slot),
+        })
+        .collect();
+
+    bindings.push(HotkeyBinding {
+        accelerator: "CmdOrCtrl+Alt+Right".to_string(),
+        action: ControlAction::NextEffect,
+    });
+    bindings.push(HotkeyBinding {
+        accelerator: "CmdOrCtrl+Alt+Left".to_string(),
+        action: ControlAction::PreviousEffect,
+    });
+    bindings.push(HotkeyBinding {
+        accelerator: "CmdOrCtrl+Alt+B".to_string(),
+        action: ControlAction::Blackout,
+    });
+    bindings.push(HotkeyBinding {
+        accelerator: "CmdOrCtrl+Alt+S".to_string(),
+        action: ControlAction::Strobe,
+    });
+
+    bindings
+}
+
+fn hotkeys_path<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(HOTKEYS_FILE))
+}
+
+fn load_bindings<R: Runtime>(app: &AppHandle<R>) -> Vec<HotkeyBinding> {
+    hotkeys_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(default_bindings)
+}
+
+fn save_bindings<R: Runtime>(app: &AppHandle<R>, bindings: &[HotkeyBinding]) -> Result<(), String> {
+    let path = hotkeys_path(app).ok_or("No config directory available")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Config directory error: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(bindings)
+        .map_err(|e| format!("Hotkey serialization error: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Hotkey save error: {}", e))
+}
+
+// Replaces every registered shortcut; invalid accelerators are reported but don't block the others
+fn apply_bindings<R: Runtime>(app: &AppHandle<R>, bindings: Vec<HotkeyBinding>) -> Vec<String> {
+    let global_shortcut = app.global_shortcut();
+    if let Err(e) = global_shortcut.unregister_all() {
+        println!("⚠️ hotkeys: Failed to clear shortcuts: {}", e);
+    }
+
+    let mut registered = Vec::with_capacity(bindings.len());
+    let mut errors = Vec::new();
+
+    for binding in &bindings {
+        match binding.accelerator.parse::<Shortcut>() {
+            Ok(shortcut) => match global_shortcut.register(shortcut) {
+                Ok(_) => registered.push((shortcut, binding.action.clone())),
+                Err(e) => errors.push(format!("{}: {}", binding.accelerator, e)),
+            },
+            Err(e) => errors.push(format!("{}: {}", binding.accelerator, e)),
+        }
+    }
+
+    let state = app.state::<HotkeyState>();
+    if let Ok(mut ctx) = state.lock() {
+        ctx.bindings = bindings;
+        ctx.registered = registered;
+    }
+
+    for error in &errors {
+        println!("⚠️ hotkeys: Could not register {}", error);
+    }
+
+    errors
+}
+
+pub fn register_saved_hotkeys<R: Runtime>(app: &AppHandle<R>) {
+    let bindings = load_bindings(app);
+    let count = bindings.len();
+    let errors = apply_bindings(app, bindings);
+    println!("⌨️ hotkeys: {} global shortcuts registered", count - errors.len());
+}
+
+pub fn handle_shortcut<R: Runtime>(app: &AppHandle<R>, shortcut: &Shortcut, event: ShortcutEvent) {
+    let action = {
+        let state = app.state::<HotkeyState>();
+        let Ok(ctx) = state.lock() else {
+            return;
+        };
+        let action = ctx
+            .registered
+            .iter()
+            .find(|(registered, _)| registered == shortcut)
+            .map(|(_, action)| action.clone());
+        action
+    };
+
+    if let Some(action) = action {
+        let pressed = event.state() == ShortcutState::Pressed;
+        if let Err(e) = trigger_action(app, "hotkey", &action, pressed) {
+            println!("❌ hotkeys: {:?} failed: {}", action, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn dj_get_hotkeys(hotkey_state: State<'_, HotkeyState>) -> Result<Vec<HotkeyBinding>, String> {
+    hotkey_state
+        .lock()
+        .map(|ctx| ctx.bindings.clone())
+        .map_err(|_| "Failed to access hotkey state".to_string())
+}
+
+#[tauri::command]
+pub async fn dj_set_hotkeys(app: AppHandle, bindings: Vec<HotkeyBinding>) -> Result<String, String> {
+    println!("⌨️ dj_set_hotkeys: Applying {} bindings", bindings.len());
+
+    save_bindings(&app, &bindings)?;
+    let count = bindings.len();
+    let errors = apply_bindings(&app, bindings);
+
+    if errors.is_empty() {
+        Ok(format!("✅ {} hotkeys registered", count))
+    } else {
+        Err(format!("⚠️ Some hotkeys could not be registered: {}", errors.join(", ")))
+    }
+}
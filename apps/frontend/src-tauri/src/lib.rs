@@ -5,6 +5,21 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use tauri::{State, Window, Emitter};
 use serde_json::json;
+use dj4led_protocol::{now_micros, strip_checksum, ClockSync, FrameReassembly, PacketHeader, Reader, TimeSample, Writer};
+
+mod access;
+mod controls;
+mod frame_history;
+mod gamepad;
+mod hotkeys;
+mod xy_pad;
+
+use access::{require_operator, AccessLevel, AccessState};
+use controls::ControlState;
+use frame_history::FrameHistoryState;
+use gamepad::GamepadState;
+use hotkeys::HotkeyState;
+use xy_pad::XyPadState;
 
 // Enhanced packet types selon la doc DJ-4LED
 const CONNECT: u8 = 0x01;
@@ -14,21 +29,122 @@ const PONG: u8 = 0x04;
 const ACK: u8 = 0x05;
 const NACK: u8 = 0x06;
 const COMMAND: u8 = 0x10;
+const RESPONSE: u8 = 0x11;
 const FRAME_DATA: u8 = 0x20;
 const FRAME_DATA_COMPRESSED: u8 = 0x21;
 const SPECTRUM_DATA: u8 = 0x30;
+const COLOR_MODES: u8 = 0x31;
+const OUTPUT_ALERT: u8 = 0x32;
+const CONFIG_RELOAD: u8 = 0x33;
+const ALERT_EVENT: u8 = 0x34;
+const BEAT_EVENT: u8 = 0x35;
+const TIME_SYNC: u8 = 0x36;
+
+const FLAG_FRAGMENTED: u8 = 0x02;
 
 // Command IDs
 const SET_EFFECT: u8 = 0x01;
 const SET_COLOR_MODE: u8 = 0x02;
 const SET_CUSTOM_COLOR: u8 = 0x03;
+const SET_PARAMETER: u8 = 0x04;
+const SET_BLACKOUT: u8 = 0x05;
+const SET_STROBE: u8 = 0x06;
+const NEXT_EFFECT: u8 = 0x07;
+const PREVIOUS_EFFECT: u8 = 0x08;
+const SET_BRIGHTNESS: u8 = 0x09;
+const SET_XY: u8 = 0x0A;
+const SET_XY_ROUTING: u8 = 0x0B;
+const GET_DIAGNOSTICS: u8 = 0x0C;
+const GET_CRASH_REPORTS: u8 = 0x0D;
+const DISMISS_CRASH_REPORTS: u8 = 0x0E;
+const GET_PERFORMANCE: u8 = 0x0F;
+const SET_FRAME_TRACE: u8 = 0x10;
+const GET_CLIENTS: u8 = 0x11;
+const SET_EFFECT_BY_NAME: u8 = 0x12;
+const GET_COLOR_MODES: u8 = 0x13;
+const SAVE_PALETTE: u8 = 0x14;
+const DELETE_PALETTE: u8 = 0x15;
+const FADE_BRIGHTNESS: u8 = 0x16;
+const FADE_TO_BLACK: u8 = 0x17;
+const SET_HOUSE_LIGHT: u8 = 0x18;
+const SET_REGION_TRIM: u8 = 0x19;
+const GET_PARAMETERS: u8 = 0x1A;
+const LEARN_ROOM: u8 = 0x1B;
+const GET_ROOM_CALIBRATION: u8 = 0x1C;
+const CLEAR_ROOM_CALIBRATION: u8 = 0x1D;
+const SET_METRONOME: u8 = 0x1E;
+const SET_HUD: u8 = 0x1F;
+const SET_SMOOTHING_PROFILE: u8 = 0x20;
+const SET_BAND_SMOOTHING: u8 = 0x21;
+const GET_SMOOTHING: u8 = 0x22;
+const SET_DB_SCALE: u8 = 0x23;
+const LEARN_NOISE_FLOOR: u8 = 0x24;
+const GET_DB_SCALE: u8 = 0x25;
+const START_COMMISSIONING: u8 = 0x26;
+const MARK_COMMISSIONING: u8 = 0x27;
+const COMMISSIONING_BACK: u8 = 0x28;
+const GET_COMMISSIONING: u8 = 0x29;
+const FINISH_COMMISSIONING: u8 = 0x2A;
+const IDENTIFY: u8 = 0x2B;
+const CLEAR_IDENTIFY: u8 = 0x2C;
+const SET_FRAME_REGION: u8 = 0x2D;
+const CLEAR_FRAME_REGION: u8 = 0x2E;
+const GET_FRAME_SUMMARY: u8 = 0x2F;
+const EXPORT_SPECTROGRAM: u8 = 0x30;
+const GET_SPECTROGRAM: u8 = 0x31;
+const GET_LAST_REPORT: u8 = 0x32;
+const RECORD_TAPE: u8 = 0x33;
+const STOP_TAPE: u8 = 0x34;
+const REPLAY_TAPE: u8 = 0x35;
+const GET_TAPE: u8 = 0x36;
+const PUSH_CONFIG: u8 = 0x37;
+const SWITCH_TARGET_SET: u8 = 0x38;
+const GET_TARGET_SETS: u8 = 0x39;
+const SET_NOISE_GATE: u8 = 0x3A;
+const SET_HIGH_PASS: u8 = 0x3B;
+const GET_INPUT_FILTER: u8 = 0x3C;
+const SET_GENRE_PRESET: u8 = 0x3D;
+const SET_ACCENT: u8 = 0x3E;
+const GET_AUDIO_FEATURES: u8 = 0x3F;
+const SET_AV_DELAY: u8 = 0x40;
+const QUERY_STATS: u8 = 0x41;
+const ADD_SCHEDULE: u8 = 0x42;
+const LIST_SCHEDULES: u8 = 0x43;
+const REMOVE_SCHEDULE: u8 = 0x44;
+const GET_BPM: u8 = 0x45;
+const SAVE_PRESET: u8 = 0x46;
+const LOAD_PRESET: u8 = 0x47;
+const LIST_PRESETS: u8 = 0x48;
+const DELETE_PRESET: u8 = 0x49;
+const GET_EFFECTS: u8 = 0x4A;
+const SET_OUTPUT_PROFILE: u8 = 0x4B;
+const GET_PROTOCOL_SCHEMA: u8 = 0x4D;
+const SET_PLAYLIST: u8 = 0x4E;
+const START_PLAYLIST: u8 = 0x4F;
+const STOP_PLAYLIST: u8 = 0x50;
+const START_CROWD_NOISE: u8 = 0x51;
+const STOP_CROWD_NOISE: u8 = 0x52;
+const SET_GAMMA: u8 = 0x53;
+const SET_COLOR_BALANCE: u8 = 0x54;
+const LIST_MIDI_DEVICES: u8 = 0x55;
+const SET_MIDI_MAPPING: u8 = 0x56;
+const GET_MESSAGES: u8 = 0x57;
+// Color correction controller meaning every controller
+const ALL_CONTROLLERS: u8 = 0xFF;
 
 // Enhanced server configuration
 const SERVER_ADDRESS: &str = "127.0.0.1:8081";
 const SOCKET_TIMEOUT_SECS: u64 = 1;
 const MAX_PACKET_SIZE: usize = 4096;
+// A frame's fragments leave the server back to back; one still incomplete after this lost a piece
+const FRAGMENT_TIMEOUT: Duration = Duration::from_millis(500);
 const STREAM_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
-const MAX_STREAM_DURATION: Duration = Duration::from_secs(120); // 2 minutes
+// The server pings every 2 s and frames flow at up to 60 fps: this much silence means it is gone
+const SERVER_SILENCE_TIMEOUT: Duration = Duration::from_secs(6);
+const STREAM_PING_INTERVAL: Duration = Duration::from_secs(2);
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+const STREAM_ROLE_MONITOR: u8 = 0x00;
 
 // Enhanced global state
 type ConnectionState = Arc<Mutex<Option<UdpSocket>>>;
@@ -43,7 +159,19 @@ struct StreamContext {
     spectrum_received: u32,
     bytes_received: u64,
     packets_lost: u32,
+    // Arrived but failed the frame checksum: Wi-Fi corruption rather than congestion
+    packets_corrupt: u32,
+    // Fragmented frames a fragment never arrived for, given up after FRAGMENT_TIMEOUT
+    frames_incomplete: u32,
+    // Sessions re-established after the server went silent
+    reconnects: u32,
+    // Server clock estimated from TIME_SYNC exchanges, for one-way delays and its stats' timestamps
+    clock: ClockSync,
     last_sequence: u32,
+    // Region asked with dj_set_frame_region, sent again to a reconnected session
+    frame_region: Option<[u8; 4]>,
+    // Stream socket, so per-client commands reach the server from the streaming address
+    socket: Option<Arc<UdpSocket>>,
 }
 
 impl Default for StreamContext {
@@ -56,61 +184,135 @@ impl Default for StreamContext {
             spectrum_received: 0,
             bytes_received: 0,
             packets_lost: 0,
+            packets_corrupt: 0,
+            frames_incomplete: 0,
+            reconnects: 0,
+            clock: ClockSync::new(),
             last_sequence: 0,
+            frame_region: None,
+            socket: None,
         }
     }
 }
 
-// Enhanced packet structure with validation
-#[derive(Debug)]
-struct PacketHeader {
-    packet_type: u8,
-    flags: u8,
-    sequence: u32,
-    fragment_id: u16,
-    fragment_count: u16,
-    payload_size: u16,
+fn create_packet(packet_type: u8, flags: u8, sequence: u32, payload: Vec<u8>) -> Vec<u8> {
+    PacketHeader::packet(packet_type, flags, sequence, &payload)
+}
+
+fn stream_active(stream_state: &StreamState) -> bool {
+    stream_state.lock().map(|ctx| ctx.is_active).unwrap_or(false)
 }
 
-impl PacketHeader {
-    fn parse(data: &[u8]) -> Result<Self, String> {
-        if data.len() < 12 {
-            return Err("Packet too short for header".to_string());
+// Keeps the stream up across backend restarts: pings the server, notices when it goes
+// silent and connects again with exponential backoff
+struct StreamSession {
+    socket: Arc<UdpSocket>,
+    // Session limit asked of the server, 0 for none
+    max_duration_secs: u32,
+    last_heard: Instant,
+    last_ping: Instant,
+    ping_sequence: u32,
+}
+
+impl StreamSession {
+    fn new(socket: Arc<UdpSocket>, max_duration_secs: u32) -> Self {
+        Self {
+            socket,
+            max_duration_secs,
+            last_heard: Instant::now(),
+            last_ping: Instant::now(),
+            ping_sequence: 0,
         }
+    }
 
-        Ok(PacketHeader {
-            packet_type: data[0],
-            flags: data[1],
-            sequence: u32::from_le_bytes([data[2], data[3], data[4], data[5]]),
-            fragment_id: u16::from_le_bytes([data[6], data[7]]),
-            fragment_count: u16::from_le_bytes([data[8], data[9]]),
-            payload_size: u16::from_le_bytes([data[10], data[11]]),
-        })
+    // CONNECT, then wait for the ACK past any stream packets still in flight
+    fn connect(&mut self) -> Result<(), String> {
+        let payload = Writer::new().u8(STREAM_ROLE_MONITOR).u32(self.max_duration_secs).finish();
+        let packet = create_packet(CONNECT, 0x01, get_timestamp(), payload);
+        self.socket.send_to(&packet, SERVER_ADDRESS)
+            .map_err(|e| format!("Stream connection failed: {}", e))?;
+
+        let deadline = Instant::now() + Duration::from_secs(SOCKET_TIMEOUT_SECS);
+        let mut buf = [0; MAX_PACKET_SIZE];
+        while Instant::now() < deadline {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, _)) if len > 0 && buf[0] == ACK => {
+                    self.heard();
+                    return Ok(());
+                }
+                Ok(_) => continue,
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+                Err(e) => return Err(format!("Stream connection failed: {}", e)),
+            }
+        }
+        Err("No ACK received for stream connection".to_string())
+    }
+
+    fn heard(&mut self) {
+        self.last_heard = Instant::now();
+    }
+
+    fn is_silent(&self) -> bool {
+        self.last_heard.elapsed() > SERVER_SILENCE_TIMEOUT
     }
 
-    fn validate(&self, packet_len: usize) -> Result<(), String> {
-        if packet_len < 12 + self.payload_size as usize {
-            return Err(format!(
-                "Packet length mismatch: expected {}, got {}",
-                12 + self.payload_size,
-                packet_len
-            ));
+    // Sent from the stream socket, so the PONG proves the path the frames take. A clock
+    // exchange goes with it, timed over that same path.
+    fn ping_if_due(&mut self) {
+        if self.last_ping.elapsed() < STREAM_PING_INTERVAL {
+            return;
         }
-        Ok(())
+        self.last_ping = Instant::now();
+        self.ping_sequence = self.ping_sequence.wrapping_add(1);
+        let ping = create_packet(PING, 0x00, self.ping_sequence, vec![]);
+        let _ = self.socket.send_to(&ping, SERVER_ADDRESS);
+        let sync = create_packet(TIME_SYNC, 0x00, self.ping_sequence, TimeSample::request(now_micros()));
+        let _ = self.socket.send_to(&sync, SERVER_ADDRESS);
     }
-}
 
-// Enhanced packet creation with better error handling
-fn create_packet(packet_type: u8, flags: u8, sequence: u32, payload: Vec<u8>) -> Vec<u8> {
-    let mut packet = Vec::with_capacity(12 + payload.len());
-    packet.push(packet_type);
-    packet.push(flags);
-    packet.extend_from_slice(&sequence.to_le_bytes());
-    packet.extend_from_slice(&0u16.to_le_bytes()); // fragment_id
-    packet.extend_from_slice(&1u16.to_le_bytes()); // fragment_count
-    packet.extend_from_slice(&(payload.len() as u16).to_le_bytes());
-    packet.extend_from_slice(&payload);
-    packet
+    // Tries until the server answers or the stream is stopped; false when stopped
+    fn reconnect(&mut self, stream_state: &StreamState, window: &Window) -> bool {
+        let lost_at = self.last_heard;
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        let mut attempt = 0u32;
+        let mut reason = format!("No packet from the server for {}s", SERVER_SILENCE_TIMEOUT.as_secs());
+
+        loop {
+            if !stream_active(stream_state) {
+                return false;
+            }
+            attempt += 1;
+            let _ = window.emit("stream_reconnecting", json!({
+                "attempt": attempt,
+                "reason": reason
+            }));
+            match self.connect() {
+                Ok(()) => {
+                    let downtime = lost_at.elapsed().as_secs_f32();
+                    println!("🔗 Stream thread: Reconnected after {} attempt(s), {:.1}s without server", attempt, downtime);
+                    let _ = window.emit("stream_reconnected", json!({
+                        "attempts": attempt,
+                        "downtime_secs": downtime
+                    }));
+                    return true;
+                }
+                Err(e) => {
+                    println!("🔌 Stream thread: Reconnect attempt {} failed ({}), retrying in {:?}", attempt, e, delay);
+                    reason = e;
+                }
+            }
+
+            // Waited in short steps so dj_stop_stream takes effect right away
+            let retry_at = Instant::now() + delay;
+            while Instant::now() < retry_at {
+                if !stream_active(stream_state) {
+                    return false;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+        }
+    }
 }
 
 fn create_socket_with_timeout(timeout_secs: u64) -> Result<UdpSocket, String> {
@@ -126,6 +328,59 @@ fn create_socket_with_timeout(timeout_secs: u64) -> Result<UdpSocket, String> {
     Ok(socket)
 }
 
+// Fire-and-forget command used by non-UI triggers (hotkeys, controllers)
+fn send_command(payload: Vec<u8>) -> Result<(), String> {
+    let socket = create_socket_with_timeout(2)?;
+    let packet = create_packet(COMMAND, 0x00, get_timestamp(), payload);
+
+    socket.send_to(&packet, SERVER_ADDRESS)
+        .map_err(|e| format!("Command failed: {}", e))?;
+
+    Ok(())
+}
+
+// Command that expects a JSON RESPONSE packet carrying the same sequence number
+fn send_request(payload: Vec<u8>, timeout_secs: u64) -> Result<serde_json::Value, String> {
+    let socket = create_socket_with_timeout(timeout_secs)?;
+    let sequence = get_timestamp();
+    let packet = create_packet(COMMAND, 0x00, sequence, payload);
+
+    socket.send_to(&packet, SERVER_ADDRESS)
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let mut buf = vec![0u8; 65536];
+    loop {
+        let (len, _) = socket.recv_from(&mut buf).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::TimedOut || e.kind() == std::io::ErrorKind::WouldBlock {
+                "⏰ Timeout - server didn't answer the request".to_string()
+            } else {
+                format!("Reception error: {}", e)
+            }
+        })?;
+
+        let (header, payload) = PacketHeader::parse(&buf[..len])
+            .map_err(|e| format!("Invalid packet: {}", e))?;
+
+        // JSON code and text; older backends sent the bare text
+        if header.packet_type == NACK && header.sequence == sequence {
+            let reason = String::from_utf8_lossy(payload).to_string();
+            return match serde_json::from_slice::<serde_json::Value>(payload) {
+                Ok(nack) if nack["code"].is_string() => {
+                    println!("❌ Request refused ({}): {}", nack["code"], nack["message"]);
+                    Err(nack["message"].as_str().map(str::to_string).unwrap_or(reason))
+                }
+                _ => Err(reason),
+            };
+        }
+        if header.packet_type != RESPONSE || header.sequence != sequence {
+            continue;
+        }
+
+        return serde_json::from_slice(payload)
+            .map_err(|e| format!("Invalid response payload: {}", e));
+    }
+}
+
 fn get_timestamp() -> u32 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -134,14 +389,17 @@ fn get_timestamp() -> u32 {
 }
 
 // Enhanced frame data parsing with validation
-fn parse_frame_data(data: &[u8]) -> Result<serde_json::Value, String> {
-    if data.len() < 5 {
-        return Err("Frame data too short for header".to_string());
-    }
+// FRAME_DATA payloads end with a CRC32 of everything before it
+fn frame_checksum_ok(payload: &[u8]) -> bool {
+    strip_checksum(payload).is_some()
+}
 
-    let width = u16::from_le_bytes([data[0], data[1]]);
-    let height = u16::from_le_bytes([data[2], data[3]]);
-    let format = data[4];
+fn parse_frame_data(data: &[u8]) -> Result<serde_json::Value, String> {
+    let mut reader = Reader::new(data);
+    let (width, height, format) = match (reader.u16(), reader.u16(), reader.u8()) {
+        (Ok(width), Ok(height), Ok(format)) => (width, height, format),
+        _ => return Err("Frame data too short for header".to_string()),
+    };
 
     // Validate dimensions
     if width == 0 || height == 0 || width > 1024 || height > 1024 {
@@ -154,15 +412,13 @@ fn parse_frame_data(data: &[u8]) -> Result<serde_json::Value, String> {
         _ => return Err(format!("Unsupported format: {}", format)),
     };
 
-    if data.len() < 5 + expected_size {
-        return Err(format!(
+    let rgb_data: Vec<u8> = reader.bytes(expected_size)
+        .map_err(|_| format!(
             "Insufficient frame data: expected {}, got {}",
             5 + expected_size,
             data.len()
-        ));
-    }
-
-    let rgb_data: Vec<u8> = data[5..5 + expected_size].to_vec();
+        ))?
+        .to_vec();
 
     Ok(json!({
         "width": width,
@@ -175,11 +431,9 @@ fn parse_frame_data(data: &[u8]) -> Result<serde_json::Value, String> {
 
 // Enhanced spectrum data parsing with normalization
 fn parse_spectrum_data(data: &[u8]) -> Result<Vec<f32>, String> {
-    if data.len() < 2 {
-        return Err("Spectrum data too short for header".to_string());
-    }
-
-    let band_count = u16::from_le_bytes([data[0], data[1]]);
+    let mut reader = Reader::new(data);
+    let band_count = reader.u16()
+        .map_err(|_| "Spectrum data too short for header".to_string())?;
 
     // Validate band count
     if band_count == 0 || band_count > 1024 {
@@ -197,12 +451,8 @@ fn parse_spectrum_data(data: &[u8]) -> Result<Vec<f32>, String> {
     }
 
     let mut spectrum_values = Vec::with_capacity(band_count as usize);
-    for i in 0..band_count {
-        let offset = 2 + (i as usize * 4);
-        let value = f32::from_le_bytes([
-            data[offset], data[offset + 1],
-            data[offset + 2], data[offset + 3]
-        ]);
+    for _ in 0..band_count {
+        let value = reader.f32().map_err(|e| e.to_string())?;
 
         // Clamp and normalize values
         let normalized_value = value.clamp(0.0, 1.0);
@@ -337,12 +587,12 @@ async fn dj_ping() -> Result<String, String> {
 
 // Enhanced command functions
 #[tauri::command]
-async fn dj_set_effect(effect_id: u32) -> Result<String, String> {
+async fn dj_set_effect(access: State<'_, AccessState>, effect_id: u32) -> Result<String, String> {
+    require_operator(&access)?;
     println!("🎇 dj_set_effect: Setting effect {}", effect_id);
 
     let socket = create_socket_with_timeout(2)?;
-    let mut payload = vec![SET_EFFECT];
-    payload.extend_from_slice(&effect_id.to_le_bytes());
+    let payload = Writer::new().u8(SET_EFFECT).u32(effect_id).finish();
     let packet = create_packet(COMMAND, 0x00, get_timestamp(), payload);
 
     socket.send_to(&packet, SERVER_ADDRESS)
@@ -352,13 +602,87 @@ async fn dj_set_effect(effect_id: u32) -> Result<String, String> {
     Ok(format!("✅ Effect {} applied", effect_id))
 }
 
+// Built-in color modes plus user palettes, with a revision bumped on every palette change
+#[tauri::command]
+async fn dj_get_color_modes() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_COLOR_MODES], SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_save_palette(access: State<'_, AccessState>, name: String, colors: Vec<(f32, f32, f32)>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎨 dj_save_palette: Saving palette '{}' ({} colors)", name, colors.len());
+
+    let palette = json!({ "name": name, "colors": colors });
+    let payload = Writer::new().u8(SAVE_PALETTE).str(&palette.to_string()).finish();
+
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_delete_palette(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎨 dj_delete_palette: Deleting palette '{}'", name);
+
+    let payload = Writer::new().u8(DELETE_PALETTE).str(&name).finish();
+
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Saves the current look (effect, color mode, custom color, brightness, effect knobs) under `name`
+#[tauri::command]
+async fn preset_save(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("💾 preset_save: Saving preset '{}'", name);
+
+    let payload = Writer::new().u8(SAVE_PRESET).str(name.trim()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
 #[tauri::command]
-async fn dj_set_color_mode(mode: String) -> Result<String, String> {
+async fn preset_load(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎛️ preset_load: Loading preset '{}'", name);
+
+    let payload = Writer::new().u8(LOAD_PRESET).str(&name).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn preset_list() -> Result<serde_json::Value, String> {
+    send_request(vec![LIST_PRESETS], SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn preset_delete(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🗑️ preset_delete: Deleting preset '{}'", name);
+
+    let payload = Writer::new().u8(DELETE_PRESET).str(&name).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Name lookup is fuzzy on the server and stays valid when effects are added or reordered
+#[tauri::command]
+async fn dj_set_effect_by_name(access: State<'_, AccessState>, name: String) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("🎇 dj_set_effect_by_name: Setting effect '{}'", name);
+
+    let payload = Writer::new().u8(SET_EFFECT_BY_NAME).str(&name).finish();
+    let response = send_request(payload, SOCKET_TIMEOUT_SECS)?;
+
+    let effect = response["effect"].as_str().unwrap_or(&name).to_string();
+    println!("✅ dj_set_effect_by_name: Effect '{}' applied", effect);
+    Ok(format!("✅ Effect {} applied", effect))
+}
+
+#[tauri::command]
+async fn dj_set_color_mode(access: State<'_, AccessState>, mode: String) -> Result<String, String> {
+    require_operator(&access)?;
     println!("🌈 dj_set_color_mode: Setting mode '{}'", mode);
 
     let socket = create_socket_with_timeout(2)?;
-    let mut payload = vec![SET_COLOR_MODE];
-    payload.extend_from_slice(mode.as_bytes());
+    let payload = Writer::new().u8(SET_COLOR_MODE).str(&mode).finish();
     let packet = create_packet(COMMAND, 0x00, get_timestamp(), payload);
 
     socket.send_to(&packet, SERVER_ADDRESS)
@@ -369,14 +693,12 @@ async fn dj_set_color_mode(mode: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn dj_set_custom_color(r: f32, g: f32, b: f32) -> Result<String, String> {
+async fn dj_set_custom_color(access: State<'_, AccessState>, r: f32, g: f32, b: f32) -> Result<String, String> {
+    require_operator(&access)?;
     println!("🎨 dj_set_custom_color: Setting RGB({:.3}, {:.3}, {:.3})", r, g, b);
 
     let socket = create_socket_with_timeout(2)?;
-    let mut payload = vec![SET_CUSTOM_COLOR];
-    payload.extend_from_slice(&r.to_le_bytes());
-    payload.extend_from_slice(&g.to_le_bytes());
-    payload.extend_from_slice(&b.to_le_bytes());
+    let payload = Writer::new().u8(SET_CUSTOM_COLOR).f32(r).f32(g).f32(b).finish();
     let packet = create_packet(COMMAND, 0x00, get_timestamp(), payload);
 
     socket.send_to(&packet, SERVER_ADDRESS)
@@ -386,11 +708,498 @@ async fn dj_set_custom_color(r: f32, g: f32, b: f32) -> Result<String, String> {
     Ok(format!("✅ Color RGB({:.3}, {:.3}, {:.3}) applied", r, g, b))
 }
 
+#[tauri::command]
+async fn dj_set_brightness(access: State<'_, AccessState>, brightness: f32) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("💡 dj_set_brightness: Setting brightness {:.2}", brightness);
+
+    controls::send_brightness(brightness)
+        .map_err(|e| format!("Brightness command failed: {}", e))?;
+
+    println!("✅ dj_set_brightness: Brightness applied");
+    Ok(format!("✅ Brightness {:.0}% applied", brightness.clamp(0.0, 1.0) * 100.0))
+}
+
+// Fades run on the server output, independently of the current effect
+#[tauri::command]
+async fn led_fade_brightness(access: State<'_, AccessState>, target: f32, seconds: f32) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("🌗 led_fade_brightness: Fading to {:.2} over {:.1}s", target, seconds);
+
+    let payload = Writer::new().u8(FADE_BRIGHTNESS).f32(target.clamp(0.0, 1.0)).f32(seconds.max(0.0)).finish();
+    send_command(payload).map_err(|e| format!("Fade command failed: {}", e))?;
+
+    println!("✅ led_fade_brightness: Fade started");
+    Ok(format!("✅ Fading to {:.0}% over {:.1}s", target.clamp(0.0, 1.0) * 100.0, seconds.max(0.0)))
+}
+
+#[tauri::command]
+async fn led_fade_to_black(access: State<'_, AccessState>, seconds: f32) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("🌑 led_fade_to_black: Fading out over {:.1}s", seconds);
+
+    let payload = Writer::new().u8(FADE_TO_BLACK).f32(seconds.max(0.0)).finish();
+    send_command(payload).map_err(|e| format!("Fade command failed: {}", e))?;
+
+    println!("✅ led_fade_to_black: Fade started");
+    Ok(format!("✅ Fading to black over {:.1}s", seconds.max(0.0)))
+}
+
+// Uniform white on the whole wall for setup and changeovers, level 0 returns to the effects
+#[tauri::command]
+async fn led_house_light(access: State<'_, AccessState>, level: f32, color_temp: f32) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("💡 led_house_light: Level {:.2} at {:.0}K", level, color_temp);
+
+    let payload = Writer::new().u8(SET_HOUSE_LIGHT).f32(level.clamp(0.0, 1.0)).f32(color_temp).finish();
+    send_command(payload).map_err(|e| format!("House light command failed: {}", e))?;
+
+    if level <= 0.0 {
+        println!("✅ led_house_light: Back to effects");
+        Ok("✅ House light off".to_string())
+    } else {
+        println!("✅ led_house_light: House light on");
+        Ok(format!("✅ House light {:.0}% at {:.0}K", level.clamp(0.0, 1.0) * 100.0, color_temp))
+    }
+}
+
+// Region is "quarter" (0-3, one per controller) or "strip" (0-63), the server persists the map
+#[tauri::command]
+async fn led_set_region_trim(access: State<'_, AccessState>, region: String, index: u8, trim: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎚️ led_set_region_trim: {} {} -> {:.2}", region, index, trim);
+
+    let region_id: u8 = match region.as_str() {
+        "quarter" => 0,
+        "strip" => 1,
+        _ => return Err(format!("Unknown trim region: {}", region)),
+    };
+
+    let payload = Writer::new().u8(SET_REGION_TRIM).u8(region_id).u8(index).f32(trim.clamp(0.0, 1.0)).finish();
+    let trims = send_request(payload, SOCKET_TIMEOUT_SECS)?;
+
+    println!("✅ led_set_region_trim: Trim applied");
+    Ok(trims)
+}
+
+#[tauri::command]
+async fn dj_set_smoothing_profile(access: State<'_, AccessState>, profile: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🌊 dj_set_smoothing_profile: {}", profile);
+
+    let profile_id: u8 = match profile.as_str() {
+        "punchy" => 0,
+        "smooth" => 1,
+        "broadcast" => 2,
+        "custom" => 3,
+        _ => return Err(format!("Unknown smoothing profile: {}", profile)),
+    };
+
+    let smoothing = send_request(vec![SET_SMOOTHING_PROFILE, profile_id], SOCKET_TIMEOUT_SECS)?;
+
+    println!("✅ dj_set_smoothing_profile: Profile applied");
+    Ok(smoothing)
+}
+
+// Attack and release are the share of the gap closed per frame (0.01..1); switches to the custom profile
+#[tauri::command]
+async fn dj_set_band_smoothing(access: State<'_, AccessState>, group: String, attack: f32, release: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🌊 dj_set_band_smoothing: {} attack {:.2} release {:.2}", group, attack, release);
+
+    let group_id: u8 = match group.as_str() {
+        "bass" => 0,
+        "mid" => 1,
+        "high" => 2,
+        _ => return Err(format!("Unknown band group: {}", group)),
+    };
+
+    let payload = Writer::new().u8(SET_BAND_SMOOTHING).u8(group_id).f32(attack).f32(release).finish();
+    let smoothing = send_request(payload, SOCKET_TIMEOUT_SECS)?;
+
+    println!("✅ dj_set_band_smoothing: Smoothing applied");
+    Ok(smoothing)
+}
+
+#[tauri::command]
+async fn dj_get_smoothing() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_SMOOTHING], SOCKET_TIMEOUT_SECS)
+}
+
+// Maps min_db..max_db (dBFS) onto 0..1 instead of the adaptive linear scale
+#[tauri::command]
+async fn dj_set_db_scale(access: State<'_, AccessState>, enabled: bool, min_db: f32, max_db: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("📏 dj_set_db_scale: {} ({:.0}..{:.0} dB)", if enabled { "on" } else { "off" }, min_db, max_db);
+
+    let payload = Writer::new().u8(SET_DB_SCALE).bool(enabled).f32(min_db).f32(max_db).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Measures the room with the music stopped (5 s by default); the window floor then sits just above it
+#[tauri::command]
+async fn dj_learn_noise_floor(access: State<'_, AccessState>, seconds: Option<u32>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🔇 dj_learn_noise_floor: Measuring {} s of room noise", seconds.unwrap_or(5));
+
+    let payload = Writer::new().u8(LEARN_NOISE_FLOOR).u32(seconds.unwrap_or(0)).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_get_db_scale() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_DB_SCALE], SOCKET_TIMEOUT_SECS)
+}
+
+// Silences the analysis below threshold_db (dBFS), held open hold_ms after the music drops; saved in config.toml
+#[tauri::command]
+async fn audio_set_noise_gate(access: State<'_, AccessState>, enabled: bool, threshold_db: f32, hold_ms: f32, release_ms: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🚪 audio_set_noise_gate: {} ({:.0} dB, hold {:.0} ms, release {:.0} ms)", if enabled { "on" } else { "off" }, threshold_db, hold_ms, release_ms);
+
+    let payload = Writer::new().u8(SET_NOISE_GATE).bool(enabled).f32(threshold_db).f32(hold_ms).f32(release_ms).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Cuts rumble below cutoff_hz before the FFT; saved in config.toml
+#[tauri::command]
+async fn audio_set_high_pass(access: State<'_, AccessState>, enabled: bool, cutoff_hz: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎚️ audio_set_high_pass: {} ({:.0} Hz)", if enabled { "on" } else { "off" }, cutoff_hz);
+
+    let payload = Writer::new().u8(SET_HIGH_PASS).bool(enabled).f32(cutoff_hz).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn audio_get_input_filter() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_INPUT_FILTER], SOCKET_TIMEOUT_SECS)
+}
+
+// "techno", "rock", "hip-hop" or "live band": band weighting, gain mode and smoothing in one action
+// Tempo, detected song section (intro, build, drop, breakdown) with its confidence, and tonalness (0 = percussion, 1 = melody)
+#[tauri::command]
+async fn audio_get_features() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_AUDIO_FEATURES], SOCKET_TIMEOUT_SECS)
+}
+
+// Detected tempo and where the music is in the bar (phase 0-1 through the beat, beat_in_bar 0 = downbeat)
+#[tauri::command]
+async fn audio_get_bpm() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_BPM], SOCKET_TIMEOUT_SECS)
+}
+
+// Simulated applause mixed into the capture to rehearse the applaudimetre: rises to peak (0-1) over attack_secs,
+// holds, then dies away over release_secs (0.8, 1.5 s, 4 s and 3 s by default)
+#[tauri::command]
+async fn audio_crowd_noise_start(access: State<'_, AccessState>, peak: Option<f32>, attack_secs: Option<f32>, hold_secs: Option<f32>, release_secs: Option<f32>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    let (peak, attack, hold, release) = (peak.unwrap_or(0.8), attack_secs.unwrap_or(1.5), hold_secs.unwrap_or(4.0), release_secs.unwrap_or(3.0));
+    println!("👏 audio_crowd_noise_start: peak {:.2} ({:.1} s up, {:.1} s held, {:.1} s down)", peak, attack, hold, release);
+
+    let payload = Writer::new().u8(START_CROWD_NOISE).f32(peak).f32(attack).f32(hold).f32(release).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn audio_crowd_noise_stop(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("👏 audio_crowd_noise_stop");
+
+    send_request(vec![STOP_CROWD_NOISE], SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn audio_set_genre_preset(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎛️ audio_set_genre_preset: {}", name);
+
+    let payload = Writer::new().u8(SET_GENRE_PRESET).str(name.trim()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// "sensitivity" targets the current effect, "input_trim" scales every effect; both are saved in config.toml
+#[tauri::command]
+async fn dj_set_parameter(access: State<'_, AccessState>, name: String, value: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎛️ dj_set_parameter: {} = {:.2}", name, value);
+
+    let parameters = send_request(set_parameter_payload(&name, value), SOCKET_TIMEOUT_SECS)?;
+
+    println!("✅ dj_set_parameter: {} applied", name);
+    Ok(parameters)
+}
+
+fn set_parameter_payload(name: &str, value: f32) -> Vec<u8> {
+    Writer::new().u8(SET_PARAMETER).str16(name).str16(&value.to_string()).finish()
+}
+
+#[tauri::command]
+async fn dj_get_parameters() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_PARAMETERS], SOCKET_TIMEOUT_SECS)
+}
+
+// Knobs of the current effect (name, value, min, max, default)
+fn effect_knobs(parameters: serde_json::Value) -> serde_json::Value {
+    json!({
+        "effect": parameters["effect"],
+        "parameters": parameters["effect_parameters"],
+    })
+}
+
+#[tauri::command]
+async fn effects_get_parameters() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_PARAMETERS], SOCKET_TIMEOUT_SECS).map(effect_knobs)
+}
+
+// Tunes a knob of the current effect; the backend clamps the value and keeps it in config.toml
+#[tauri::command]
+async fn effects_set_parameter(access: State<'_, AccessState>, name: String, value: f32) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎛️ effects_set_parameter: {} = {}", name, value);
+
+    send_request(set_parameter_payload(&name, value), SOCKET_TIMEOUT_SECS).map(effect_knobs)
+}
+
+// Every effect with its index, tags (ambient, reactive, party, utility) and whether it is playing
+#[tauri::command]
+async fn effects_get_list() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_EFFECTS], SOCKET_TIMEOUT_SECS)
+}
+
+// Only the effects carrying `tag`, e.g. "party" for peak-time rotations
+#[tauri::command]
+async fn effects_list_by_tag(tag: String) -> Result<serde_json::Value, String> {
+    let payload = Writer::new().u8(GET_EFFECTS).str(tag.trim()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Effects played in turn: each entry is { effect, duration_secs, transition, transition_secs } with
+// transition "cut", "crossfade" or "fade_through_black"; saved in config.toml and stopped until started
+#[tauri::command]
+async fn effects_playlist_set(access: State<'_, AccessState>, entries: Vec<serde_json::Value>, advance_on_drop: bool) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🔁 effects_playlist_set: {} entries{}", entries.len(), if advance_on_drop { ", advancing on drops" } else { "" });
+
+    let playlist = json!({ "entries": entries, "advance_on_drop": advance_on_drop });
+    let payload = Writer::new().u8(SET_PLAYLIST).str(&playlist.to_string()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn effects_playlist_start(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🔁 effects_playlist_start");
+
+    send_request(vec![START_PLAYLIST], SOCKET_TIMEOUT_SECS)
+}
+
+// The effect playing stays on the wall
+#[tauri::command]
+async fn effects_playlist_stop(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🔁 effects_playlist_stop");
+
+    send_request(vec![STOP_PLAYLIST], SOCKET_TIMEOUT_SECS)
+}
+
+// Records program audio (10 s by default) and derives a per-band normalization curve saved in config.toml
+#[tauri::command]
+async fn dj_learn_room(access: State<'_, AccessState>, seconds: Option<u32>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎧 dj_learn_room: Capturing {} s of program audio", seconds.unwrap_or(10));
+
+    let payload = Writer::new().u8(LEARN_ROOM).u32(seconds.unwrap_or(0)).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_get_room_calibration() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_ROOM_CALIBRATION], SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_clear_room_calibration(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎧 dj_clear_room_calibration: Back to a flat response");
+    send_request(vec![CLEAR_ROOM_CALIBRATION], SOCKET_TIMEOUT_SECS)
+}
+
+// Corner marker flashing on detected beats (brighter on downbeats) to check beat alignment at soundcheck
+#[tauri::command]
+async fn effects_set_metronome(access: State<'_, AccessState>, enabled: bool) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("🥁 effects_set_metronome: {}", if enabled { "on" } else { "off" });
+
+    send_command(vec![SET_METRONOME, enabled as u8])
+        .map_err(|e| format!("Metronome command failed: {}", e))?;
+
+    Ok(format!("✅ Metronome {}", if enabled { "enabled" } else { "disabled" }))
+}
+
+// One-shot overlay on strong onsets over the current effect: "flash", "shockwave", "glitch" or "off"
+#[tauri::command]
+async fn effects_set_accent(access: State<'_, AccessState>, kind: String) -> Result<String, String> {
+    require_operator(&access)?;
+    let code = match kind.trim().to_lowercase().as_str() {
+        "off" | "none" => 0u8,
+        "flash" => 1,
+        "shockwave" => 2,
+        "glitch" => 3,
+        other => return Err(format!("Unknown accent: {} (flash, shockwave, glitch or off)", other)),
+    };
+    println!("💥 effects_set_accent: {}", kind);
+
+    send_command(vec![SET_ACCENT, code])
+        .map_err(|e| format!("Accent command failed: {}", e))?;
+
+    Ok(format!("✅ Accent {}", kind))
+}
+
+#[tauri::command]
+async fn dj_set_hud(access: State<'_, AccessState>, enabled: bool) -> Result<String, String> {
+    require_operator(&access)?;
+    println!("📟 dj_set_hud: {}", if enabled { "on" } else { "off" });
+
+    send_command(vec![SET_HUD, enabled as u8])
+        .map_err(|e| format!("HUD command failed: {}", e))?;
+
+    Ok(format!("✅ Debug HUD {}", if enabled { "enabled" } else { "disabled" }))
+}
+
+// Lights one LED (or the whole strip when pixel is omitted) in white and dims the rest of the wall
+#[tauri::command]
+async fn led_identify(access: State<'_, AccessState>, controller: u8, strip: u8, pixel: Option<u16>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    match pixel {
+        Some(pixel) => println!("🔦 led_identify: Controller {} strip {} pixel {}", controller, strip, pixel),
+        None => println!("🔦 led_identify: Controller {} strip {}", controller, strip),
+    }
+
+    let payload = Writer::new().u8(IDENTIFY).u8(controller).u8(strip).u16(pixel.unwrap_or(0xFFFF)).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_clear_identify(access: State<'_, AccessState>) -> Result<String, String> {
+    require_operator(&access)?;
+    send_command(vec![CLEAR_IDENTIFY])
+        .map_err(|e| format!("Clear identify command failed: {}", e))?;
+
+    Ok("✅ Identify cleared".to_string())
+}
+
+// Guided installer check: each controller in red/green/blue/white, then every strip alone
+#[tauri::command]
+async fn led_run_commissioning(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🧪 led_run_commissioning: Starting the commissioning sequence");
+    send_request(vec![START_COMMISSIONING], SOCKET_TIMEOUT_SECS)
+}
+
+// Marks the pattern on the wall and moves on to the next check
+#[tauri::command]
+async fn dj_mark_commissioning(access: State<'_, AccessState>, passed: bool, note: Option<String>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🧪 dj_mark_commissioning: {}", if passed { "pass" } else { "fail" });
+
+    let payload = Writer::new().u8(MARK_COMMISSIONING).bool(passed).str(&note.unwrap_or_default()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_commissioning_back(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    send_request(vec![COMMISSIONING_BACK], SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_get_commissioning() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_COMMISSIONING], SOCKET_TIMEOUT_SECS)
+}
+
+// Ends the sequence and writes the report under commissioning_reports/ on the backend host
+#[tauri::command]
+async fn dj_finish_commissioning(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    let status = send_request(vec![FINISH_COMMISSIONING], SOCKET_TIMEOUT_SECS)?;
+    println!("✅ dj_finish_commissioning: Report written to {}", status["report"].as_str().unwrap_or("?"));
+    Ok(status)
+}
+
+#[tauri::command]
+async fn system_get_diagnostics() -> Result<serde_json::Value, String> {
+    println!("🩺 system_get_diagnostics: Running backend self-test...");
+
+    let report = send_request(vec![GET_DIAGNOSTICS], 5)?;
+
+    println!("✅ system_get_diagnostics: Overall status {}", report["status"]);
+    Ok(report)
+}
+
+// Crash reports left by previous backend runs, offered to the UI at startup
+#[tauri::command]
+async fn dj_get_crash_reports() -> Result<serde_json::Value, String> {
+    println!("💥 dj_get_crash_reports: Checking for previous crashes...");
+
+    let reports = send_request(vec![GET_CRASH_REPORTS], SOCKET_TIMEOUT_SECS)?;
+
+    println!("✅ dj_get_crash_reports: {} report(s) pending", reports.as_array().map(|r| r.len()).unwrap_or(0));
+    Ok(reports)
+}
+
+#[tauri::command]
+async fn dj_dismiss_crash_reports() -> Result<String, String> {
+    let response = send_request(vec![DISMISS_CRASH_REPORTS], SOCKET_TIMEOUT_SECS)?;
+
+    if let Some(error) = response["error"].as_str() {
+        return Err(format!("Failed to archive crash reports: {}", error));
+    }
+
+    Ok(format!("✅ {} crash report(s) archived", response["dismissed"]))
+}
+
+// Render rate plus particle, trail and client queue usage against their caps
+#[tauri::command]
+async fn dj_get_performance() -> Result<serde_json::Value, String> {
+    let performance = send_request(vec![GET_PERFORMANCE], SOCKET_TIMEOUT_SECS)?;
+
+    println!("📊 dj_get_performance: {:.1} FPS, {} bytes tracked",
+             performance["render_fps"].as_f64().unwrap_or(0.0),
+             performance["resources"]["total_bytes"]);
+    Ok(performance)
+}
+
+#[tauri::command]
+async fn dj_get_clients() -> Result<serde_json::Value, String> {
+    let clients = send_request(vec![GET_CLIENTS], SOCKET_TIMEOUT_SECS)?;
+
+    println!("👥 dj_get_clients: {} client(s) connected", clients.as_array().map(|c| c.len()).unwrap_or(0));
+    Ok(clients)
+}
+
+// Tags every Nth frame and logs its path through render, output and network stages (0 disables)
+#[tauri::command]
+async fn dj_set_frame_trace(every_n: u32) -> Result<String, String> {
+    println!("🔎 dj_set_frame_trace: Tracing every {} frames", every_n);
+
+    let payload = Writer::new().u8(SET_FRAME_TRACE).u32(every_n).finish();
+    send_command(payload).map_err(|e| format!("Frame trace command failed: {}", e))?;
+
+    if every_n == 0 {
+        Ok("✅ Frame tracing disabled".to_string())
+    } else {
+        Ok(format!("✅ Tracing one frame every {}", every_n))
+    }
+}
+
 // Enhanced streaming with better error handling and monitoring
 #[tauri::command]
 async fn dj_start_stream(
     window: Window,
-    stream_state: State<'_, StreamState>
+    stream_state: State<'_, StreamState>,
+    history_state: State<'_, FrameHistoryState>,
+    max_duration_secs: Option<u32>
 ) -> Result<String, String> {
     println!("🚀 dj_start_stream: Starting enhanced stream...");
 
@@ -403,46 +1212,33 @@ async fn dj_start_stream(
     }
 
     println!("🔌 dj_start_stream: Creating socket...");
-    let socket = create_socket_with_timeout(SOCKET_TIMEOUT_SECS)?;
+    let socket = Arc::new(create_socket_with_timeout(SOCKET_TIMEOUT_SECS)?);
 
-    // Enhanced connect packet with compression support
-    println!("📡 dj_start_stream: Sending connect packet to {}", SERVER_ADDRESS);
-    let connect_packet = create_packet(CONNECT, 0x01, get_timestamp(), vec![]);
-    socket.send_to(&connect_packet, SERVER_ADDRESS)
-        .map_err(|e| {
-            println!("❌ dj_start_stream: Connection failed: {}", e);
-            format!("Stream connection failed: {}", e)
-        })?;
-
-    // Wait for ACK with timeout
-    println!("⏳ dj_start_stream: Waiting for ACK...");
-    let mut buf = [0; MAX_PACKET_SIZE];
-    match socket.recv_from(&mut buf) {
-        Ok((len, addr)) => {
-            println!("📥 dj_start_stream: Received {} bytes from {}", len, addr);
-            if len < 1 || buf[0] != ACK {
-                println!("❌ dj_start_stream: No ACK received, got packet type: {:#04x}", buf[0]);
-                return Err("No ACK received for stream connection".to_string());
-            }
-            println!("✅ dj_start_stream: ACK received successfully");
-        }
-        Err(e) => {
-            println!("❌ dj_start_stream: Timeout waiting for ACK: {}", e);
-            return Err("Timeout waiting for stream ACK".to_string());
-        }
-    }
+    // Session limit is enforced by the server; 0 asks for an unlimited session
+    println!("📡 dj_start_stream: Connecting to {}", SERVER_ADDRESS);
+    let mut session = StreamSession::new(socket.clone(), max_duration_secs.unwrap_or(0));
+    session.connect().map_err(|e| {
+        println!("❌ dj_start_stream: {}", e);
+        e
+    })?;
+    println!("✅ dj_start_stream: ACK received successfully");
 
     // Initialize stream context
     if let Ok(mut stream_ctx) = stream_state.lock() {
         *stream_ctx = StreamContext {
             is_active: true,
             start_time: Some(Instant::now()),
+            socket: Some(socket.clone()),
             ..Default::default()
         };
         println!("🎯 dj_start_stream: Stream context initialized");
     }
+    if let Ok(mut history) = history_state.lock() {
+        history.clear();
+    }
 
     let stream_state_clone = stream_state.inner().clone();
+    let history_clone = history_state.inner().clone();
     let window_clone = window.clone();
 
     println!("🧵 dj_start_stream: Starting enhanced streaming thread...");
@@ -455,6 +1251,9 @@ async fn dj_start_stream(
 
         // Déclarer stream_ctx en dehors de la boucle pour qu'elle soit accessible après
         let mut stream_ctx = StreamContext::default();
+        // Kept out of stream_ctx, which is cloned on every packet
+        let mut fragments = FrameReassembly::new(FRAGMENT_TIMEOUT);
+        let mut buf = [0; MAX_PACKET_SIZE];
 
         loop {
             // Check if we should continue streaming et récupérer stream_ctx
@@ -475,41 +1274,89 @@ async fn dj_start_stream(
                 break;
             }
 
-            // Auto-stop after maximum duration
-            if let Some(start_time) = stream_ctx.start_time {
-                if start_time.elapsed() > MAX_STREAM_DURATION {
-                    println!("⏰ Stream thread: Auto-stopping after maximum duration");
-                    if let Ok(mut ctx) = stream_state_clone.lock() {
-                        ctx.is_active = false;
-                    }
-                    let _ = window_clone.emit("stream_status", json!({
-                        "status": "auto_stopped",
-                        "message": "Stream auto-stopped after maximum duration",
-                        "stats": {
-                            "packets": stream_ctx.packets_received,
-                            "frames": stream_ctx.frames_received,
-                            "spectrum": stream_ctx.spectrum_received,
-                            "bytes": stream_ctx.bytes_received,
-                            "lost": stream_ctx.packets_lost,
-                            "duration": start_time.elapsed().as_secs()
-                        }
-                    }));
+            session.ping_if_due();
+            if session.is_silent() {
+                println!("📴 Stream thread: No packet from the server for {:?}, reconnecting", SERVER_SILENCE_TIMEOUT);
+                if !session.reconnect(&stream_state_clone, &window_clone) {
                     break;
                 }
+
+                // A new session: the server numbers packets from scratch, forgot the region and
+                // may be another machine with its own clock
+                stream_ctx.reconnects += 1;
+                stream_ctx.last_sequence = 0;
+                stream_ctx.clock = ClockSync::new();
+                fragments = FrameReassembly::new(FRAGMENT_TIMEOUT);
+                if let Some([x, y, width, height]) = stream_ctx.frame_region {
+                    let packet = create_packet(COMMAND, 0x00, get_timestamp(), vec![SET_FRAME_REGION, x, y, width, height]);
+                    let _ = socket.send_to(&packet, SERVER_ADDRESS);
+                }
+                if let Ok(mut ctx) = stream_state_clone.lock() {
+                    ctx.reconnects = stream_ctx.reconnects;
+                    ctx.last_sequence = 0;
+                    ctx.clock = ClockSync::new();
+                }
+                continue;
             }
 
             // Receive data with enhanced error handling
             match socket.recv_from(&mut buf) {
-                Ok((len, _addr)) => {
+                Ok((len, addr)) => {
+                    session.heard();
                     stream_ctx.packets_received += 1;
                     stream_ctx.bytes_received += len as u64;
 
                     // Parse packet header
                     match PacketHeader::parse(&buf[..len]) {
-                        Ok(header) => {
-                            if let Err(e) = header.validate(len) {
-                                println!("⚠️ Stream thread: Invalid packet: {}", e);
-                                stream_ctx.packets_lost += 1;
+                        Ok((header, payload)) => {
+                            // Server ended the session (duration limit reached)
+                            if header.packet_type == DISCONNECT {
+                                println!("⏰ Stream thread: Session ended by server");
+                                if let Ok(mut ctx) = stream_state_clone.lock() {
+                                    ctx.is_active = false;
+                                }
+                                let _ = window_clone.emit("stream_status", json!({
+                                    "status": "auto_stopped",
+                                    "message": "Stream session ended by server",
+                                    "stats": {
+                                        "packets": stream_ctx.packets_received,
+                                        "frames": stream_ctx.frames_received,
+                                        "spectrum": stream_ctx.spectrum_received,
+                                        "bytes": stream_ctx.bytes_received,
+                                        "lost": stream_ctx.packets_lost,
+                                        "corrupt": stream_ctx.packets_corrupt,
+                                        "duration": stream_ctx.start_time.map(|t| t.elapsed().as_secs()).unwrap_or(0)
+                                    }
+                                }));
+                                break;
+                            }
+
+                            // Server heartbeat: answer before the sequence check, pings use their own counter
+                            if header.packet_type == PING {
+                                let pong = create_packet(PONG, 0x00, header.sequence, vec![]);
+                                if let Err(e) = socket.send_to(&pong, addr) {
+                                    println!("⚠️ Stream thread: Failed to answer server ping: {}", e);
+                                }
+                                continue;
+                            }
+
+                            // Answer to our own ping: its sequence is ours, not the stream's
+                            if header.packet_type == PONG {
+                                if let Ok(mut ctx) = stream_state_clone.lock() {
+                                    *ctx = stream_ctx.clone();
+                                }
+                                continue;
+                            }
+
+                            // Answer to our clock exchange, numbered like the pings
+                            if header.packet_type == TIME_SYNC {
+                                match TimeSample::decode(payload, now_micros()) {
+                                    Ok(sample) => stream_ctx.clock.push(sample),
+                                    Err(e) => println!("❌ Stream thread: Invalid time sync payload: {}", e),
+                                }
+                                if let Ok(mut ctx) = stream_state_clone.lock() {
+                                    *ctx = stream_ctx.clone();
+                                }
                                 continue;
                             }
 
@@ -521,9 +1368,30 @@ async fn dj_start_stream(
                             }
                             stream_ctx.last_sequence = header.sequence;
 
-                            let payload = &buf[12..12 + header.payload_size as usize];
+                            // Large frames arrive in pieces, handled as one packet once the last piece is in
+                            let reassembled;
+                            let payload = if header.flags & FLAG_FRAGMENTED != 0 {
+                                match fragments.push(&header, payload) {
+                                    Some(frame) => {
+                                        reassembled = frame;
+                                        &reassembled[..]
+                                    }
+                                    None => {
+                                        if let Ok(mut ctx) = stream_state_clone.lock() {
+                                            *ctx = stream_ctx.clone();
+                                        }
+                                        continue;
+                                    }
+                                }
+                            } else {
+                                payload
+                            };
 
                             match header.packet_type {
+                                FRAME_DATA if !frame_checksum_ok(payload) => {
+                                    stream_ctx.packets_corrupt += 1;
+                                    println!("⚠️ Stream thread: Corrupt frame dropped (checksum mismatch, {} so far)", stream_ctx.packets_corrupt);
+                                }
                                 FRAME_DATA => {
                                     stream_ctx.frames_received += 1;
                                     if stream_ctx.frames_received % 30 == 0 { // Log every 30th frame
@@ -531,6 +1399,9 @@ async fn dj_start_stream(
                                     }
                                     match parse_frame_data(payload) {
                                         Ok(frame_data) => {
+                                            if let Ok(mut history) = history_clone.lock() {
+                                                history.record(now_micros() / 1000, FRAME_DATA, payload);
+                                            }
                                             if let Err(e) = window_clone.emit("frame_data", frame_data) {
                                                 println!("❌ Stream thread: Failed to emit frame_data: {}", e);
                                             }
@@ -546,6 +1417,9 @@ async fn dj_start_stream(
                                     if stream_ctx.frames_received % 30 == 0 {
                                         println!("🗜️ Stream thread: Processing FRAME_DATA_COMPRESSED #{}", stream_ctx.frames_received);
                                     }
+                                    if let Ok(mut history) = history_clone.lock() {
+                                        history.record(now_micros() / 1000, FRAME_DATA_COMPRESSED, payload);
+                                    }
                                     let compressed_data: Vec<u8> = payload.to_vec();
                                     if let Err(e) = window_clone.emit("frame_data_compressed", compressed_data) {
                                         println!("❌ Stream thread: Failed to emit frame_data_compressed: {}", e);
@@ -568,6 +1442,48 @@ async fn dj_start_stream(
                                         }
                                     }
                                 }
+                                COLOR_MODES => {
+                                    match serde_json::from_slice::<serde_json::Value>(payload) {
+                                        Ok(color_modes) => {
+                                            println!("🎨 Stream thread: Color modes updated (revision {})", color_modes["revision"]);
+                                            let _ = window_clone.emit("color_modes", color_modes);
+                                        }
+                                        Err(e) => println!("❌ Stream thread: Invalid color modes payload: {}", e),
+                                    }
+                                }
+                                OUTPUT_ALERT => {
+                                    match serde_json::from_slice::<serde_json::Value>(payload) {
+                                        Ok(alert) => {
+                                            println!("🚨 Stream thread: Output alert: {}", alert["message"]);
+                                            let _ = window_clone.emit("output_alert", alert);
+                                        }
+                                        Err(e) => println!("❌ Stream thread: Invalid output alert payload: {}", e),
+                                    }
+                                }
+                                CONFIG_RELOAD => {
+                                    match serde_json::from_slice::<serde_json::Value>(payload) {
+                                        Ok(reload) => {
+                                            println!("♻️ Stream thread: {} reloaded", reload["file"]);
+                                            let _ = window_clone.emit("config_reload", reload);
+                                        }
+                                        Err(e) => println!("❌ Stream thread: Invalid config reload payload: {}", e),
+                                    }
+                                }
+                                ALERT_EVENT => {
+                                    match serde_json::from_slice::<serde_json::Value>(payload) {
+                                        Ok(alert) => {
+                                            println!("🔔 Stream thread: {}", alert["message"]);
+                                            let _ = window_clone.emit("alert", alert);
+                                        }
+                                        Err(e) => println!("❌ Stream thread: Invalid alert payload: {}", e),
+                                    }
+                                }
+                                BEAT_EVENT => {
+                                    // Every beat: not logged
+                                    if let Ok(beat) = serde_json::from_slice::<serde_json::Value>(payload) {
+                                        let _ = window_clone.emit("beat", beat);
+                                    }
+                                }
                                 _ => {
                                     if stream_ctx.packets_received % 100 == 0 { // Log unknown packets occasionally
                                         println!("❓ Stream thread: Unknown packet type: {:#04x}", header.packet_type);
@@ -587,29 +1503,24 @@ async fn dj_start_stream(
                     }
                 }
                 Err(e) => {
-                    if e.kind() == std::io::ErrorKind::TimedOut {
-                        // Timeout is normal, continue listening
-                        continue;
-                    } else {
+                    // Read timeouts are normal (WouldBlock on Unix); anything else, like the
+                    // port unreachable errors of a restarting backend, is left to the silence check
+                    if !matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut | std::io::ErrorKind::ConnectionRefused) {
                         println!("❌ Stream thread: Receive error: {}", e);
-                        // Increment error counter but don't break immediately
-                        stream_ctx.packets_lost += 1;
-                        if let Ok(mut ctx) = stream_state_clone.lock() {
-                            *ctx = stream_ctx.clone();
-                        }
-
-                        // Break only on persistent errors
-                        if stream_ctx.packets_lost > 100 {
-                            println!("❌ Stream thread: Too many errors, stopping stream");
-                            let _ = window_clone.emit("stream_status", json!({
-                                "status": "error",
-                                "message": "Stream stopped due to persistent errors",
-                                "error": format!("Receive error: {}", e)
-                            }));
-                            break;
-                        }
-                        continue;
                     }
+                    if e.kind() != std::io::ErrorKind::WouldBlock && e.kind() != std::io::ErrorKind::TimedOut {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    continue;
+                }
+            }
+
+            let expired = fragments.expire();
+            if expired > 0 {
+                stream_ctx.frames_incomplete += expired as u32;
+                println!("⚠️ Stream thread: {} incomplete frame(s) dropped (missing fragments)", expired);
+                if let Ok(mut ctx) = stream_state_clone.lock() {
+                    ctx.frames_incomplete = stream_ctx.frames_incomplete;
                 }
             }
 
@@ -625,11 +1536,13 @@ async fn dj_start_stream(
                     0.0
                 };
 
-                println!("📊 Stream health: {} packets, {} frames, {} spectrum, {:.1}% loss",
+                println!("📊 Stream health: {} packets, {} frames, {} spectrum, {:.1}% loss, {} corrupt, {} incomplete",
                     stream_ctx.packets_received,
                     stream_ctx.frames_received,
                     stream_ctx.spectrum_received,
-                    packet_loss_rate
+                    packet_loss_rate,
+                    stream_ctx.packets_corrupt,
+                    stream_ctx.frames_incomplete
                 );
 
                 // Emit health status if loss rate is concerning
@@ -643,6 +1556,7 @@ async fn dj_start_stream(
                             "spectrum": stream_ctx.spectrum_received,
                             "bytes": stream_ctx.bytes_received,
                             "lost": stream_ctx.packets_lost,
+                            "corrupt": stream_ctx.packets_corrupt,
                             "loss_rate": packet_loss_rate
                         }
                     }));
@@ -661,7 +1575,9 @@ async fn dj_start_stream(
                         "spectrum": stream_ctx.spectrum_received,
                         "bytes": stream_ctx.bytes_received,
                         "lost": stream_ctx.packets_lost,
-                        "duration": stream_ctx.start_time.map(|t| t.elapsed().as_secs()).unwrap_or(0)
+                        "corrupt": stream_ctx.packets_corrupt,
+                        "duration": stream_ctx.start_time.map(|t| t.elapsed().as_secs()).unwrap_or(0),
+                        "clock": clock_stats(&stream_ctx.clock)
                     }
                 }));
             }
@@ -695,6 +1611,7 @@ async fn dj_start_stream(
                 "spectrum": stream_ctx.spectrum_received,
                 "bytes": stream_ctx.bytes_received,
                 "lost": stream_ctx.packets_lost,
+                "corrupt": stream_ctx.packets_corrupt,
                 "loss_rate": final_loss_rate,
                 "duration": final_duration,
                 "avg_fps": if final_duration > 0 { stream_ctx.frames_received as f32 / final_duration as f32 } else { 0.0 },
@@ -728,11 +1645,272 @@ async fn dj_stop_stream(stream_state: State<'_, StreamState>) -> Result<String,
     }
 }
 
+// Magnifier: the stream carries this rectangle of the 128×128 frame at full resolution
+#[tauri::command]
+async fn dj_set_frame_region(stream_state: State<'_, StreamState>, x: u8, y: u8, width: u8, height: u8) -> Result<String, String> {
+    println!("🔍 dj_set_frame_region: {}×{} at ({}, {})", width, height, x, y);
+
+    if width == 0 || height == 0 || x as u16 + width as u16 > 128 || y as u16 + height as u16 > 128 {
+        return Err(format!("Region {}×{} at ({}, {}) is outside the 128×128 frame", width, height, x, y));
+    }
+
+    send_stream_command(&stream_state, vec![SET_FRAME_REGION, x, y, width, height])?;
+    if let Ok(mut stream_ctx) = stream_state.lock() {
+        stream_ctx.frame_region = Some([x, y, width, height]);
+    }
+    Ok(format!("🔍 Streaming region {}×{} at ({}, {})", width, height, x, y))
+}
+
+#[tauri::command]
+async fn dj_clear_frame_region(stream_state: State<'_, StreamState>) -> Result<String, String> {
+    send_stream_command(&stream_state, vec![CLEAR_FRAME_REGION])?;
+    if let Ok(mut stream_ctx) = stream_state.lock() {
+        stream_ctx.frame_region = None;
+    }
+    Ok("✅ Streaming the whole frame".to_string())
+}
+
+// Average and dominant colors of the current frame, plus a 16² or 32² thumbnail when asked
+#[tauri::command]
+async fn dj_get_frame_summary(thumbnail_size: Option<u8>) -> Result<serde_json::Value, String> {
+    let size = thumbnail_size.unwrap_or(0);
+    if !matches!(size, 0 | 16 | 32) {
+        return Err(format!("Thumbnail size {} not available (0, 16 or 32)", size));
+    }
+
+    send_request(vec![GET_FRAME_SUMMARY, size], SOCKET_TIMEOUT_SECS)
+}
+
+// Writes the rolling spectrogram as a PNG on the backend machine (time left to right, bass at the bottom)
+#[tauri::command]
+async fn system_export_spectrogram(png_path: String) -> Result<serde_json::Value, String> {
+    println!("🎼 system_export_spectrogram: {}", png_path);
+
+    if png_path.trim().is_empty() {
+        return Err("PNG path must not be empty".to_string());
+    }
+
+    let payload = Writer::new().u8(EXPORT_SPECTROGRAM).str(&png_path).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Last `seconds` of band history (whole buffer by default) pooled to at most `columns` columns
+#[tauri::command]
+async fn system_get_spectrogram(seconds: Option<u16>, columns: Option<u8>) -> Result<serde_json::Value, String> {
+    let payload = Writer::new().u8(GET_SPECTROGRAM).u16(seconds.unwrap_or(0)).u8(columns.unwrap_or(128)).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Per-minute FPS, packet loss, brightness, audio level and errors between two Unix times (seconds),
+// merged into at most `points` buckets (300 max) to compare events over months
+#[tauri::command]
+async fn system_query_stats(from: u64, to: u64, points: Option<u16>) -> Result<serde_json::Value, String> {
+    let payload = Writer::new().u8(QUERY_STATS).u64(from).u64(to).u16(points.unwrap_or(120)).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Unattended rules run by the backend, e.g. "daily 04:00 blackout" or "FRI 23:00 set profile=smooth"
+#[tauri::command]
+async fn system_add_schedule(access: State<'_, AccessState>, rule: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("⏰ system_add_schedule: {}", rule);
+
+    let payload = Writer::new().u8(ADD_SCHEDULE).str(&rule).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn system_list_schedules() -> Result<serde_json::Value, String> {
+    send_request(vec![LIST_SCHEDULES], SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn system_remove_schedule(access: State<'_, AccessState>, index: u8) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("⏰ system_remove_schedule: {}", index);
+    send_request(vec![REMOVE_SCHEDULE, index], SOCKET_TIMEOUT_SECS)
+}
+
+// Report of the last show, written by the backend when streaming stops or on shutdown
+#[tauri::command]
+async fn system_get_last_report() -> Result<serde_json::Value, String> {
+    let report = send_request(vec![GET_LAST_REPORT], SOCKET_TIMEOUT_SECS)?;
+
+    println!("📝 system_get_last_report: {}", report["html"].as_str().unwrap_or("?"));
+    Ok(report)
+}
+
+// Packet types, command opcodes and payload layouts as the running backend speaks them
+#[tauri::command]
+async fn system_get_protocol_schema() -> Result<serde_json::Value, String> {
+    let schema = send_request(vec![GET_PROTOCOL_SCHEMA], SOCKET_TIMEOUT_SECS)?;
+
+    println!(
+        "📚 system_get_protocol_schema: v{}, {} commands",
+        schema["version"].as_str().unwrap_or("?"),
+        schema["commands"].as_array().map_or(0, |commands| commands.len())
+    );
+    Ok(schema)
+}
+
+// Text of every status and error code in every language, for translating `code` fields of errors and alerts
+#[tauri::command]
+async fn system_get_messages() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_MESSAGES], SOCKET_TIMEOUT_SECS)
+}
+
+// Control tape: the backend records every control move with its timing under `name`
+#[tauri::command]
+async fn dj_record_tape(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("📼 dj_record_tape: {}", name);
+
+    let payload = Writer::new().u8(RECORD_TAPE).str(&name).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Saves the tape being recorded, or stops a replay
+#[tauri::command]
+async fn dj_stop_tape(access: State<'_, AccessState>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    send_request(vec![STOP_TAPE], SOCKET_TIMEOUT_SECS)
+}
+
+// Re-executes a saved tape against the live engine with its original timing
+#[tauri::command]
+async fn dj_replay_tape(access: State<'_, AccessState>, name: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("📼 dj_replay_tape: {}", name);
+
+    let payload = Writer::new().u8(REPLAY_TAPE).str(&name).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn dj_get_tape() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_TAPE], SOCKET_TIMEOUT_SECS)
+}
+
+// Sends this backend's config.toml and palettes to another instance (host or host:port),
+// which must run with --accept-config-push; the backend waits for its answer
+#[tauri::command]
+async fn system_push_config(access: State<'_, AccessState>, addr: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("📤 system_push_config: Pushing config to {}", addr);
+
+    let payload = Writer::new().u8(PUSH_CONFIG).str(addr.trim()).finish();
+    send_request(payload, 6)
+}
+
+// Retargets every universe to a named controller set from config (None = built-in controllers)
+#[tauri::command]
+async fn led_switch_target_set(access: State<'_, AccessState>, name: Option<String>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    let name = name.unwrap_or_default();
+    println!("🔀 led_switch_target_set: {}", if name.is_empty() { "default controllers" } else { name.as_str() });
+
+    let payload = Writer::new().u8(SWITCH_TARGET_SET).str(&name).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+#[tauri::command]
+async fn led_get_target_sets() -> Result<serde_json::Value, String> {
+    send_request(vec![GET_TARGET_SETS], SOCKET_TIMEOUT_SECS)
+}
+
+// Holds the LED output back by 0-500 ms so it lands with the PA instead of the booth feed
+#[tauri::command]
+async fn led_set_av_delay(access: State<'_, AccessState>, ms: u16) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("⏱️ led_set_av_delay: {} ms", ms);
+
+    let payload = Writer::new().u8(SET_AV_DELAY).u16(ms).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// Palette constraint on everything sent to the LEDs: standard, camera_safe, deuteranopia, protanopia or tritanopia
+#[tauri::command]
+async fn led_set_output_profile(access: State<'_, AccessState>, profile: String) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎨 led_set_output_profile: {}", profile);
+
+    let payload = Writer::new().u8(SET_OUTPUT_PROFILE).str(profile.trim()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// LED response curve of one controller (0-3), or of all of them without one: 1.0 is linear, 0.5 to 4 accepted
+#[tauri::command]
+async fn led_set_gamma(access: State<'_, AccessState>, gamma: f32, controller: Option<u8>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎛️ led_set_gamma: {:?} -> {:.2}", controller, gamma);
+
+    let payload = Writer::new().u8(SET_GAMMA).u8(controller.unwrap_or(ALL_CONTROLLERS)).f32(gamma).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// White point in kelvin (6500 neutral) and red, green, blue gains (0-1) of one controller, or of all of them
+#[tauri::command]
+async fn led_set_color_balance(access: State<'_, AccessState>, white_point: f32, red: f32, green: f32, blue: f32, controller: Option<u8>) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎛️ led_set_color_balance: {:?} -> {:.0} K ({:.2}, {:.2}, {:.2})", controller, white_point, red, green, blue);
+
+    let payload = Writer::new().u8(SET_COLOR_BALANCE).u8(controller.unwrap_or(ALL_CONTROLLERS)).f32(white_point).f32(red).f32(green).f32(blue).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+// MIDI ports seen by the backend, with the mapping in use
+#[tauri::command]
+async fn midi_list_devices() -> Result<serde_json::Value, String> {
+    send_request(vec![LIST_MIDI_DEVICES], SOCKET_TIMEOUT_SECS)
+}
+
+// Mapping is { device, bindings: [{ control: "cc" | "note", channel, number, action, min, max }] }, saved in midi.json
+#[tauri::command]
+async fn midi_set_mapping(access: State<'_, AccessState>, mapping: serde_json::Value) -> Result<serde_json::Value, String> {
+    require_operator(&access)?;
+    println!("🎹 midi_set_mapping");
+
+    let payload = Writer::new().u8(SET_MIDI_MAPPING).str(&mapping.to_string()).finish();
+    send_request(payload, SOCKET_TIMEOUT_SECS)
+}
+
+fn send_stream_command(stream_state: &StreamState, payload: Vec<u8>) -> Result<(), String> {
+    let socket = {
+        let stream_ctx = stream_state.lock()
+            .map_err(|_| "Failed to access stream state".to_string())?;
+        match &stream_ctx.socket {
+            Some(socket) if stream_ctx.is_active => socket.clone(),
+            _ => return Err("Stream is not active".to_string()),
+        }
+    };
+
+    let packet = create_packet(COMMAND, 0x00, get_timestamp(), payload);
+    socket.send_to(&packet, SERVER_ADDRESS)
+        .map_err(|e| format!("Stream command failed: {}", e))?;
+    Ok(())
+}
+
 #[tauri::command]
 async fn dj_get_server_info() -> Result<String, String> {
     Ok(format!("🖥️ DJ-4LED Server: {} (Enhanced Protocol)", SERVER_ADDRESS))
 }
 
+// Where the server's clock stands against ours and the one-way delays of the latest exchange, in ms;
+// null until the first exchange. server_time_ms lines client stats up with the server's own (Unix ms).
+fn clock_stats(clock: &ClockSync) -> serde_json::Value {
+    let ms = |micros: i64| micros as f64 / 1000.0;
+    match (clock.offset_micros(), clock.round_trip_micros(), clock.one_way_micros()) {
+        (Some(offset), Some(round_trip), Some((to_server, from_server))) => json!({
+            "offset_ms": ms(offset),
+            "round_trip_ms": ms(round_trip as i64),
+            "to_server_ms": ms(to_server),
+            "from_server_ms": ms(from_server),
+            "server_time_ms": clock.server_now(now_micros()).map(|micros| micros / 1000)
+        }),
+        _ => serde_json::Value::Null,
+    }
+}
+
 #[tauri::command]
 async fn dj_get_stream_stats(stream_state: State<'_, StreamState>) -> Result<serde_json::Value, String> {
     if let Ok(stream_ctx) = stream_state.lock() {
@@ -750,6 +1928,10 @@ async fn dj_get_stream_stats(stream_state: State<'_, StreamState>) -> Result<ser
             "spectrum_received": stream_ctx.spectrum_received,
             "bytes_received": stream_ctx.bytes_received,
             "packets_lost": stream_ctx.packets_lost,
+            "packets_corrupt": stream_ctx.packets_corrupt,
+            "frames_incomplete": stream_ctx.frames_incomplete,
+            "reconnects": stream_ctx.reconnects,
+            "clock": clock_stats(&stream_ctx.clock),
             "loss_rate": loss_rate,
             "duration": duration,
             "avg_fps": if duration > 0 { stream_ctx.frames_received as f32 / duration as f32 } else { 0.0 },
@@ -769,25 +1951,140 @@ fn greet(name: &str) -> String {
 pub fn run() {
     println!("🚀 Starting enhanced DJ-4LED application...");
 
+    let access_state: AccessState = Arc::new(Mutex::new(AccessLevel::at_launch()));
     let connection_state: ConnectionState = Arc::new(Mutex::new(None));
     let stream_state: StreamState = Arc::new(Mutex::new(StreamContext::default()));
+    let control_state: ControlState = Arc::new(Mutex::new(controls::ControlContext::default()));
+    let history_state: FrameHistoryState = Arc::new(Mutex::new(frame_history::FrameHistory::default()));
+    let hotkey_state: HotkeyState = Arc::new(Mutex::new(hotkeys::HotkeyContext::default()));
+    let gamepad_state: GamepadState = Arc::new(Mutex::new(gamepad::GamepadContext::default()));
+    let xy_pad_state: XyPadState = Arc::new(Mutex::new(xy_pad::XyPadContext::default()));
+    xy_pad::start_xy_flush_thread(xy_pad_state.clone());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(hotkeys::handle_shortcut)
+                .build(),
+        )
+        .manage(access_state)
         .manage(connection_state)
         .manage(stream_state)
+        .manage(control_state)
+        .manage(history_state)
+        .manage(hotkey_state)
+        .manage(gamepad_state)
+        .manage(xy_pad_state)
+        .setup(|app| {
+            hotkeys::register_saved_hotkeys(app.handle());
+            gamepad::start_gamepad_thread(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
+            access::dj_get_access,
+            access::dj_login,
+            access::dj_logout,
             dj_connect,
             dj_disconnect,
             dj_ping,
             dj_set_effect,
             dj_set_color_mode,
             dj_set_custom_color,
+            dj_set_brightness,
+            led_fade_brightness,
+            led_fade_to_black,
+            led_house_light,
+            led_set_region_trim,
+            dj_set_parameter,
+            dj_get_parameters,
+            dj_learn_room,
+            dj_get_room_calibration,
+            dj_clear_room_calibration,
+            effects_set_metronome,
+            dj_set_hud,
+            dj_set_smoothing_profile,
+            dj_set_band_smoothing,
+            dj_get_smoothing,
+            dj_set_db_scale,
+            dj_learn_noise_floor,
+            dj_get_db_scale,
+            audio_set_noise_gate,
+            audio_set_high_pass,
+            audio_get_input_filter,
+            audio_set_genre_preset,
+            audio_get_features,
+            audio_get_bpm,
+            audio_crowd_noise_start,
+            audio_crowd_noise_stop,
+            effects_set_accent,
+            effects_get_parameters,
+            effects_set_parameter,
+            effects_get_list,
+            effects_list_by_tag,
+            effects_playlist_set,
+            effects_playlist_start,
+            effects_playlist_stop,
+            led_identify,
+            dj_clear_identify,
+            led_run_commissioning,
+            dj_mark_commissioning,
+            dj_commissioning_back,
+            dj_get_commissioning,
+            dj_finish_commissioning,
             dj_start_stream,
             dj_stop_stream,
+            dj_set_frame_region,
+            dj_clear_frame_region,
+            dj_get_frame_summary,
+            system_export_spectrogram,
+            system_get_spectrogram,
+            system_get_last_report,
+            system_get_protocol_schema,
+            system_get_messages,
+            system_query_stats,
+            system_add_schedule,
+            system_list_schedules,
+            system_remove_schedule,
+            dj_record_tape,
+            dj_stop_tape,
+            dj_replay_tape,
+            dj_get_tape,
+            system_push_config,
+            led_switch_target_set,
+            led_get_target_sets,
+            led_set_av_delay,
+            led_set_output_profile,
+            led_set_gamma,
+            led_set_color_balance,
+            midi_list_devices,
+            midi_set_mapping,
             dj_get_server_info,
-            dj_get_stream_stats
+            dj_get_stream_stats,
+            system_get_diagnostics,
+            dj_get_crash_reports,
+            dj_dismiss_crash_reports,
+            dj_get_performance,
+            dj_set_frame_trace,
+            dj_get_clients,
+            dj_set_effect_by_name,
+            dj_get_color_modes,
+            dj_save_palette,
+            dj_delete_palette,
+            preset_save,
+            preset_load,
+            preset_list,
+            preset_delete,
+            hotkeys::dj_get_hotkeys,
+            hotkeys::dj_set_hotkeys,
+            gamepad::dj_list_gamepads,
+            gamepad::dj_get_gamepad_mapping,
+            gamepad::dj_set_gamepad_mapping,
+            xy_pad::effects_set_xy,
+            xy_pad::dj_set_xy_routing,
+            xy_pad::dj_get_xy_stats,
+            frame_history::dj_get_frame_at
         ])
         .run(tauri::generate_context!())
         .expect("error while running enhanced tauri application");
@@ -0,0 +1,92 @@
+// src-tauri/src/xy_pad.rs
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use dj4led_protocol::Writer;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::access::{require_operator, AccessState};
+use crate::{send_command, SET_XY, SET_XY_ROUTING};
+
+// The pad can emit hundreds of moves per second; only the latest position is sent at ~60 Hz
+const XY_FLUSH_INTERVAL: Duration = Duration::from_millis(16);
+
+pub type XyPadState = Arc<Mutex<XyPadContext>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum XyTarget {
+    None = 0x00,
+    Hue = 0x01,
+    Saturation = 0x02,
+    Brightness = 0x03,
+    Intensity = 0x04,
+    Sensitivity = 0x05,
+    // A knob of the current effect, named by x_parameter / y_parameter
+    Parameter = 0x06,
+}
+
+#[derive(Debug, Default)]
+pub struct XyPadContext {
+    pending: Option<(f32, f32)>,
+    coalesced: u64,
+}
+
+pub fn start_xy_flush_thread(state: XyPadState) {
+    thread::spawn(move || loop {
+        thread::sleep(XY_FLUSH_INTERVAL);
+
+        let pending = match state.lock() {
+            Ok(mut ctx) => ctx.pending.take(),
+            Err(_) => continue,
+        };
+
+        if let Some((x, y)) = pending {
+            let payload = Writer::new().u8(SET_XY).f32(x).f32(y).finish();
+
+            if let Err(e) = send_command(payload) {
+                println!("❌ xy_pad: Failed to send position: {}", e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn effects_set_xy(access: State<'_, AccessState>, xy_state: State<'_, XyPadState>, x: f32, y: f32) -> Result<(), String> {
+    require_operator(&access)?;
+    let mut ctx = xy_state.lock().map_err(|_| "Failed to access XY pad state".to_string())?;
+
+    if ctx.pending.is_some() {
+        ctx.coalesced += 1;
+    }
+    ctx.pending = Some((x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)));
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn dj_set_xy_routing(access: State<'_, AccessState>, x_target: XyTarget, y_target: XyTarget, x_parameter: Option<String>, y_parameter: Option<String>) -> Result<String, String> {
+    require_operator(&access)?;
+
+    let payload = Writer::new()
+        .u8(SET_XY_ROUTING)
+        .u8(x_target as u8)
+        .u8(y_target as u8)
+        .str16(x_parameter.as_deref().unwrap_or_default())
+        .str16(y_parameter.as_deref().unwrap_or_default())
+        .finish();
+    send_command(payload)
+        .map_err(|e| format!("XY routing command failed: {}", e))?;
+
+    Ok(format!("✅ XY pad routed: X -> {:?}, Y -> {:?}", x_target, y_target))
+}
+
+#[tauri::command]
+pub async fn dj_get_xy_stats(xy_state: State<'_, XyPadState>) -> Result<serde_json::Value, String> {
+    let ctx = xy_state.lock().map_err(|_| "Failed to access XY pad state".to_string())?;
+    Ok(serde_json::json!({
+        "coalesced_updates": ctx.coalesced,
+        "pending": ctx.pending.is_some()
+    }))
+}
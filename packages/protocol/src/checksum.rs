@@ -0,0 +1,35 @@
+// FRAME_DATA payloads end with a CRC32 of everything before it, so a client can tell a
+// frame corrupted on the way (Wi-Fi) from one that never arrived (congestion)
+pub fn append_checksum(payload: &mut Vec<u8>) {
+    let crc = crc32fast::hash(payload);
+    payload.extend_from_slice(&crc.to_le_bytes());
+}
+
+// The payload without its checksum, None when it doesn't match
+pub fn strip_checksum(payload: &[u8]) -> Option<&[u8]> {
+    let split = payload.len().checked_sub(4)?;
+    let (data, crc) = payload.split_at(split);
+    (crc32fast::hash(data).to_le_bytes() == crc).then_some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Values;
+
+    #[test]
+    fn test_any_flipped_bit_fails_the_checksum() {
+        let mut values = Values::new(7);
+        for _ in 0..200 {
+            let data = values.bytes(200);
+            let mut payload = data.clone();
+            append_checksum(&mut payload);
+            assert_eq!(strip_checksum(&payload), Some(&data[..]));
+
+            let bit = values.below(payload.len() * 8);
+            payload[bit / 8] ^= 1 << (bit % 8);
+            assert_eq!(strip_checksum(&payload), None);
+        }
+        assert_eq!(strip_checksum(&[1, 2, 3]), None);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::codec::{DecodeError, Reader, Writer};
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Exchanges kept to pick the best from; a few seconds' worth at one exchange per ping
+const MAX_SAMPLES: usize = 8;
+
+// Microseconds since the Unix epoch, the timebase both ends stamp TIME_SYNC packets with
+pub fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+// One NTP-style exchange. The client sends its clock, the server answers with it, when the
+// request arrived and when the answer left, and the client notes when the answer arrived.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSample {
+    pub client_sent: u64,
+    pub server_received: u64,
+    pub server_sent: u64,
+    pub client_received: u64,
+}
+
+impl TimeSample {
+    // Client's request: its own clock
+    pub fn request(client_sent: u64) -> Vec<u8> {
+        Writer::new().u64(client_sent).finish()
+    }
+
+    // Server's answer to a request payload, None for a malformed one
+    pub fn reply(request: &[u8], server_received: u64, server_sent: u64) -> Option<Vec<u8>> {
+        let client_sent = Reader::new(request).u64().ok()?;
+        Some(
+            Writer::new()
+                .u64(client_sent)
+                .u64(server_received)
+                .u64(server_sent)
+                .finish(),
+        )
+    }
+
+    pub fn decode(reply: &[u8], client_received: u64) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(reply);
+        Ok(Self {
+            client_sent: reader.u64()?,
+            server_received: reader.u64()?,
+            server_sent: reader.u64()?,
+            client_received,
+        })
+    }
+
+    // Server clock minus client clock, assuming the way out takes as long as the way back
+    pub fn offset_micros(&self) -> i64 {
+        let out = self.server_received as i64 - self.client_sent as i64;
+        let back = self.server_sent as i64 - self.client_received as i64;
+        (out + back) / 2
+    }
+
+    // Time on the network, without the server's own processing
+    pub fn round_trip_micros(&self) -> u64 {
+        let total = self.client_received.saturating_sub(self.client_sent);
+        total.saturating_sub(self.server_sent.saturating_sub(self.server_received))
+    }
+}
+
+// Client-side estimate of the server's clock. The exchange with the shortest round trip was
+// the least delayed by queues either way, so its offset is the one trusted, like NTP's clock
+// filter; one-way figures then come from the latest exchange measured against that offset.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    samples: VecDeque<TimeSample>,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, sample: TimeSample) {
+        // A reply older than its request is a stale or forged packet
+        if sample.client_received < sample.client_sent {
+            return;
+        }
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn is_synced(&self) -> bool {
+        !self.samples.is_empty()
+    }
+
+    fn best(&self) -> Option<&TimeSample> {
+        self.samples.iter().min_by_key(|s| s.round_trip_micros())
+    }
+
+    pub fn offset_micros(&self) -> Option<i64> {
+        self.best().map(TimeSample::offset_micros)
+    }
+
+    pub fn round_trip_micros(&self) -> Option<u64> {
+        self.samples.back().map(TimeSample::round_trip_micros)
+    }
+
+    // A server timestamp on the client's clock
+    pub fn to_local(&self, server_micros: u64) -> Option<u64> {
+        let offset = self.offset_micros()?;
+        Some(server_micros.saturating_add_signed(-offset))
+    }
+
+    // The client's clock on the server's timebase
+    pub fn server_now(&self, client_micros: u64) -> Option<u64> {
+        let offset = self.offset_micros()?;
+        Some(client_micros.saturating_add_signed(offset))
+    }
+
+    // (to the server, from the server) of the latest exchange; negative values mean the
+    // estimate is off by more than the delay itself
+    pub fn one_way_micros(&self) -> Option<(i64, i64)> {
+        let offset = self.offset_micros()?;
+        let latest = self.samples.back()?;
+        let out = latest.server_received as i64 - offset - latest.client_sent as i64;
+        let back = latest.client_received as i64 - (latest.server_sent as i64 - offset);
+        Some((out, back))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Server clock 5 s ahead; request takes `out` µs, the answer `back` µs, 100 µs in between
+    fn exchange(at: u64, out: u64, back: u64) -> TimeSample {
+        let ahead = 5_000_000;
+        let request = TimeSample::request(at);
+        let server_received = at + out + ahead;
+        let reply = TimeSample::reply(&request, server_received, server_received + 100).unwrap();
+        TimeSample::decode(&reply, at + out + 100 + back).unwrap()
+    }
+
+    #[test]
+    fn test_exchange_finds_the_offset() {
+        let sample = exchange(1_000, 2_000, 2_000);
+        assert_eq!(sample.offset_micros(), 5_000_000);
+        assert_eq!(sample.round_trip_micros(), 4_000);
+        assert!(TimeSample::reply(&[1, 2], 0, 0).is_none());
+        assert!(TimeSample::decode(&[0; 16], 0).is_err());
+    }
+
+    #[test]
+    fn test_sync_trusts_the_quickest_exchange() {
+        let mut clock = ClockSync::new();
+        assert_eq!(clock.offset_micros(), None);
+
+        // Queued on the way back: alone it would put the offset 10 ms off
+        clock.push(exchange(0, 1_000, 21_000));
+        clock.push(exchange(100_000, 1_000, 1_000));
+        clock.push(exchange(200_000, 1_000, 9_000));
+        assert_eq!(clock.offset_micros(), Some(5_000_000));
+        assert_eq!(clock.round_trip_micros(), Some(10_000));
+        assert_eq!(clock.one_way_micros(), Some((1_000, 9_000)));
+        assert_eq!(clock.to_local(5_300_000), Some(300_000));
+        assert_eq!(clock.server_now(300_000), Some(5_300_000));
+
+        for at in 0..MAX_SAMPLES as u64 {
+            clock.push(exchange(300_000 + at * 100_000, 3_000, 3_000));
+        }
+        assert_eq!(clock.offset_micros(), Some(5_000_000));
+        assert_eq!(clock.round_trip_micros(), Some(6_000));
+    }
+}
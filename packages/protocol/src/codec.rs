@@ -0,0 +1,303 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    Truncated { needed: usize, remaining: usize },
+    InvalidUtf8,
+    // A field held a value outside its enumeration, e.g. an unknown smoothing profile
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { needed, remaining } => write!(
+                f,
+                "Truncated payload: {} more bytes needed, {} left",
+                needed, remaining
+            ),
+            Self::InvalidUtf8 => write!(f, "Invalid UTF-8 string"),
+            Self::Invalid(field) => write!(f, "Invalid {}", field),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// Builds a payload field by field: `Writer::new().u8(0x04).str16(name).finish()`
+#[derive(Debug, Clone, Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn u8(mut self, value: u8) -> Self {
+        self.buf.push(value);
+        self
+    }
+
+    pub fn bool(self, value: bool) -> Self {
+        self.u8(value as u8)
+    }
+
+    pub fn u16(mut self, value: u16) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, value: u32) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn f32(mut self, value: f32) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn bytes(mut self, value: &[u8]) -> Self {
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    // Unprefixed, so only as the last field: the reader takes the rest of the payload
+    pub fn str(self, value: &str) -> Self {
+        self.bytes(value.as_bytes())
+    }
+
+    // u16 byte length then the bytes; anything past 64 KiB is cut at a char boundary
+    pub fn str16(self, value: &str) -> Self {
+        let mut len = value.len().min(u16::MAX as usize);
+        while !value.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.u16(len as u16).bytes(&value.as_bytes()[..len])
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+// Reads a payload back in the order it was written
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if len > self.remaining() {
+            return Err(DecodeError::Truncated {
+                needed: len,
+                remaining: self.remaining(),
+            });
+        }
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    // Any non-zero byte is true
+    pub fn bool(&mut self) -> Result<bool, DecodeError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+
+    // The rest of the payload as a string, the counterpart of `Writer::str`
+    pub fn str(&mut self) -> Result<String, DecodeError> {
+        Self::utf8(self.rest())
+    }
+
+    pub fn str16(&mut self) -> Result<String, DecodeError> {
+        let len = self.u16()? as usize;
+        Self::utf8(self.bytes(len)?)
+    }
+
+    fn utf8(bytes: &[u8]) -> Result<String, DecodeError> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Values;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Field {
+        U8(u8),
+        Bool(bool),
+        U16(u16),
+        U32(u32),
+        U64(u64),
+        // Compared bit for bit so NaN payloads count too
+        F32(u32),
+        Str16(String),
+    }
+
+    fn random_field(values: &mut Values) -> Field {
+        match values.below(7) {
+            0 => Field::U8(values.u64() as u8),
+            1 => Field::Bool(values.u64() & 1 == 1),
+            2 => Field::U16(values.u64() as u16),
+            3 => Field::U32(values.u64() as u32),
+            4 => Field::U64(values.u64()),
+            5 => Field::F32(values.u64() as u32),
+            _ => Field::Str16(values.string(40)),
+        }
+    }
+
+    fn write(writer: Writer, field: &Field) -> Writer {
+        match field {
+            Field::U8(v) => writer.u8(*v),
+            Field::Bool(v) => writer.bool(*v),
+            Field::U16(v) => writer.u16(*v),
+            Field::U32(v) => writer.u32(*v),
+            Field::U64(v) => writer.u64(*v),
+            Field::F32(bits) => writer.f32(f32::from_bits(*bits)),
+            Field::Str16(v) => writer.str16(v),
+        }
+    }
+
+    fn read(reader: &mut Reader, like: &Field) -> Result<Field, DecodeError> {
+        Ok(match like {
+            Field::U8(_) => Field::U8(reader.u8()?),
+            Field::Bool(_) => Field::Bool(reader.bool()?),
+            Field::U16(_) => Field::U16(reader.u16()?),
+            Field::U32(_) => Field::U32(reader.u32()?),
+            Field::U64(_) => Field::U64(reader.u64()?),
+            Field::F32(_) => Field::F32(reader.f32()?.to_bits()),
+            Field::Str16(_) => Field::Str16(reader.str16()?),
+        })
+    }
+
+    #[test]
+    fn test_random_fields_round_trip() {
+        let mut values = Values::new(1753);
+        for _ in 0..500 {
+            let fields: Vec<Field> = (0..values.below(12))
+                .map(|_| random_field(&mut values))
+                .collect();
+            let tail = values.string(20);
+            let payload = fields.iter().fold(Writer::new(), write).str(&tail).finish();
+
+            let mut reader = Reader::new(&payload);
+            for field in &fields {
+                assert_eq!(read(&mut reader, field).unwrap(), *field);
+            }
+            assert_eq!(reader.str().unwrap(), tail);
+            assert_eq!(reader.remaining(), 0);
+
+            // Any cut inside a fixed-size field is reported, never misread
+            if let Some(cut) = payload.len().checked_sub(tail.len() + 1) {
+                let mut reader = Reader::new(&payload[..cut]);
+                let decoded: Result<Vec<_>, _> = fields
+                    .iter()
+                    .map(|field| read(&mut reader, field))
+                    .collect();
+                assert!(decoded.is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_fields_are_little_endian() {
+        let payload = Writer::new()
+            .u16(0x0102)
+            .u32(0x0304_0506)
+            .f32(1.0)
+            .str16("é")
+            .finish();
+        assert_eq!(
+            payload,
+            [0x02, 0x01, 0x06, 0x05, 0x04, 0x03, 0x00, 0x00, 0x80, 0x3F, 0x02, 0x00, 0xC3, 0xA9]
+        );
+
+        let mut reader = Reader::new(&payload[..5]);
+        assert_eq!(reader.u16(), Ok(0x0102));
+        assert_eq!(
+            reader.u32(),
+            Err(DecodeError::Truncated {
+                needed: 4,
+                remaining: 3
+            })
+        );
+        assert_eq!(
+            Reader::new(&[0x01, 0x00, 0xFF]).str16(),
+            Err(DecodeError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_long_strings_are_cut_on_a_char_boundary() {
+        let long = "é".repeat(40_000);
+        let payload = Writer::new().str16(&long).finish();
+        let decoded = Reader::new(&payload).str16().unwrap();
+        assert_eq!(decoded.len(), 65_534);
+        assert!(long.starts_with(&decoded));
+    }
+}
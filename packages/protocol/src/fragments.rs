@@ -0,0 +1,153 @@
+use crate::header::PacketHeader;
+use std::time::{Duration, Instant};
+
+// A 255×255 RGB region over a 1500 byte MTU needs 134; anything past this is garbage
+pub const MAX_FRAGMENTS: u16 = 256;
+// Frames still being assembled; an older one is given up when a newer one needs the room
+const MAX_PENDING: usize = 4;
+
+struct PartialFrame {
+    packet_type: u8,
+    first_sequence: u32,
+    started: Instant,
+    fragments: Vec<Option<Vec<u8>>>,
+    missing: usize,
+}
+
+// Frames too large for one datagram are sent as fragments with consecutive sequences, so
+// `sequence - fragment_id` is the same for every fragment of a frame and names it
+pub struct FrameReassembly {
+    pending: Vec<PartialFrame>,
+    timeout: Duration,
+}
+
+impl FrameReassembly {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: Vec::new(),
+            timeout,
+        }
+    }
+
+    // The frame's whole payload once its last missing fragment arrives
+    pub fn push(&mut self, header: &PacketHeader, payload: &[u8]) -> Option<Vec<u8>> {
+        let count = header.fragment_count;
+        if count == 0 || count > MAX_FRAGMENTS || header.fragment_id >= count {
+            return None;
+        }
+        let first_sequence = header.sequence.wrapping_sub(header.fragment_id as u32);
+
+        let index = match self.pending.iter().position(|frame| {
+            frame.packet_type == header.packet_type
+                && frame.first_sequence == first_sequence
+                && frame.fragments.len() == count as usize
+        }) {
+            Some(index) => index,
+            None => {
+                if self.pending.len() == MAX_PENDING {
+                    self.pending.remove(0);
+                }
+                self.pending.push(PartialFrame {
+                    packet_type: header.packet_type,
+                    first_sequence,
+                    started: Instant::now(),
+                    fragments: vec![None; count as usize],
+                    missing: count as usize,
+                });
+                self.pending.len() - 1
+            }
+        };
+
+        let frame = &mut self.pending[index];
+        let slot = &mut frame.fragments[header.fragment_id as usize];
+        if slot.is_none() {
+            *slot = Some(payload.to_vec());
+            frame.missing -= 1;
+        }
+        if frame.missing > 0 {
+            return None;
+        }
+
+        let frame = self.pending.remove(index);
+        Some(frame.fragments.into_iter().flatten().flatten().collect())
+    }
+
+    // Drops frames that stayed incomplete past the timeout, returning how many
+    pub fn expire(&mut self) -> usize {
+        let before = self.pending.len();
+        self.pending
+            .retain(|frame| frame.started.elapsed() < self.timeout);
+        before - self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Values;
+
+    fn fragments(payload: &[u8], size: usize, sequence: u32) -> Vec<(PacketHeader, Vec<u8>)> {
+        let chunks: Vec<_> = payload.chunks(size).collect();
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut header = PacketHeader::new(
+                    0x20,
+                    0x02,
+                    sequence.wrapping_add(i as u32),
+                    chunk.len() as u16,
+                );
+                header.fragment_id = i as u16;
+                header.fragment_count = chunks.len() as u16;
+                (header, chunk.to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shuffled_fragments_reassemble() {
+        let mut values = Values::new(3);
+        let payload: Vec<u8> = (0..49_161).map(|_| values.u64() as u8).collect();
+        let mut parts = fragments(&payload, 1460, u32::MAX - 5);
+        assert_eq!(parts.len(), 34);
+        for i in (1..parts.len()).rev() {
+            parts.swap(i, values.below(i + 1));
+        }
+        // A duplicate doesn't count twice
+        parts.insert(3, parts[0].clone());
+
+        let mut reassembly = FrameReassembly::new(Duration::from_millis(500));
+        let (last, rest) = parts.split_last().unwrap();
+        for (header, chunk) in rest {
+            assert_eq!(reassembly.push(header, chunk), None);
+        }
+        assert_eq!(reassembly.push(&last.0, &last.1), Some(payload));
+        assert_eq!(reassembly.expire(), 0);
+    }
+
+    #[test]
+    fn test_incomplete_frames_are_given_up() {
+        let payload = vec![7u8; 5000];
+        let mut reassembly = FrameReassembly::new(Duration::ZERO);
+
+        // Interleaved frames don't mix, and a lost fragment leaves its frame pending
+        let first = fragments(&payload, 1000, 10);
+        let second = fragments(&payload, 1000, 15);
+        for (header, chunk) in first.iter().skip(1) {
+            assert_eq!(reassembly.push(header, chunk), None);
+        }
+        for (i, (header, chunk)) in second.iter().enumerate() {
+            let complete = reassembly.push(header, chunk);
+            assert_eq!(complete.is_some(), i == second.len() - 1);
+        }
+        assert_eq!(reassembly.expire(), 1);
+
+        let mut bogus = first[0].0;
+        bogus.fragment_id = bogus.fragment_count;
+        assert_eq!(reassembly.push(&bogus, &first[0].1), None);
+        bogus.fragment_count = MAX_FRAGMENTS + 1;
+        assert_eq!(reassembly.push(&bogus, &first[0].1), None);
+        assert_eq!(reassembly.expire(), 0);
+    }
+}
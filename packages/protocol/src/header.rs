@@ -0,0 +1,122 @@
+use crate::codec::{DecodeError, Reader, Writer};
+
+pub const HEADER_SIZE: usize = 12;
+
+// type u8, flags u8, sequence u32, fragment id u16, fragment count u16, payload size u16
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub packet_type: u8,
+    pub flags: u8,
+    pub sequence: u32,
+    pub fragment_id: u16,
+    pub fragment_count: u16,
+    pub payload_size: u16,
+}
+
+impl PacketHeader {
+    // A packet sent in one piece
+    pub fn new(packet_type: u8, flags: u8, sequence: u32, payload_size: u16) -> Self {
+        Self {
+            packet_type,
+            flags,
+            sequence,
+            fragment_id: 0,
+            fragment_count: 1,
+            payload_size,
+        }
+    }
+
+    pub fn encode(&self, writer: Writer) -> Writer {
+        writer
+            .u8(self.packet_type)
+            .u8(self.flags)
+            .u32(self.sequence)
+            .u16(self.fragment_id)
+            .u16(self.fragment_count)
+            .u16(self.payload_size)
+    }
+
+    pub fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Self {
+            packet_type: reader.u8()?,
+            flags: reader.u8()?,
+            sequence: reader.u32()?,
+            fragment_id: reader.u16()?,
+            fragment_count: reader.u16()?,
+            payload_size: reader.u16()?,
+        })
+    }
+
+    // Header then payload, the size field taken from the payload
+    pub fn packet(packet_type: u8, flags: u8, sequence: u32, payload: &[u8]) -> Vec<u8> {
+        let header = Self::new(packet_type, flags, sequence, payload.len() as u16);
+        header
+            .encode(Writer::with_capacity(HEADER_SIZE + payload.len()))
+            .bytes(payload)
+            .finish()
+    }
+
+    // The header and the payload it announces; bytes past it are ignored
+    pub fn parse(packet: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        let mut reader = Reader::new(packet);
+        let header = Self::decode(&mut reader)?;
+        let payload = reader.bytes(header.payload_size as usize)?;
+        Ok((header, payload))
+    }
+}
+
+// Rewrites the sequence of an encoded packet, for payloads serialized once and sent to many clients
+pub fn stamp_sequence(packet: &mut [u8], sequence: u32) {
+    packet[2..6].copy_from_slice(&sequence.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Values;
+
+    #[test]
+    fn test_random_headers_round_trip() {
+        let mut values = Values::new(42);
+        for _ in 0..500 {
+            let payload = values.bytes(300);
+            let header = PacketHeader {
+                packet_type: values.u64() as u8,
+                flags: values.u64() as u8,
+                sequence: values.u64() as u32,
+                fragment_id: values.u64() as u16,
+                fragment_count: values.u64() as u16,
+                payload_size: payload.len() as u16,
+            };
+            let mut packet = header.encode(Writer::new()).bytes(&payload).finish();
+            packet.extend_from_slice(b"trailing");
+
+            let (decoded, decoded_payload) = PacketHeader::parse(&packet).unwrap();
+            assert_eq!(decoded, header);
+            assert_eq!(decoded_payload, payload);
+
+            let sequence = values.u64() as u32;
+            stamp_sequence(&mut packet, sequence);
+            assert_eq!(PacketHeader::parse(&packet).unwrap().0.sequence, sequence);
+        }
+    }
+
+    #[test]
+    fn test_short_packets_are_rejected() {
+        let packet = PacketHeader::packet(0x10, 0, 7, &[1, 2, 3]);
+        assert_eq!(packet.len(), HEADER_SIZE + 3);
+        assert_eq!(
+            &packet[..HEADER_SIZE],
+            &[0x10, 0, 7, 0, 0, 0, 0, 0, 1, 0, 3, 0]
+        );
+
+        assert!(PacketHeader::parse(&packet[..HEADER_SIZE - 1]).is_err());
+        assert_eq!(
+            PacketHeader::parse(&packet[..HEADER_SIZE + 2]),
+            Err(DecodeError::Truncated {
+                needed: 3,
+                remaining: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Wire format shared by the DJ-4LED server and its clients (the Tauri app, the Python
+//! bindings). Every multi-byte field is little-endian whatever the host; packets and
+//! payloads are only ever read and written through [`Reader`], [`Writer`] and
+//! [`PacketHeader`] so the implementations can't drift apart field by field.
+
+mod checksum;
+mod clock;
+mod codec;
+mod fragments;
+mod header;
+
+pub use checksum::{append_checksum, strip_checksum};
+pub use clock::{now_micros, ClockSync, TimeSample};
+pub use codec::{DecodeError, Reader, Writer};
+pub use fragments::{FrameReassembly, MAX_FRAGMENTS};
+pub use header::{stamp_sequence, PacketHeader, HEADER_SIZE};
+
+#[cfg(test)]
+pub(crate) mod testing {
+    // xorshift64*, enough to throw varied values at the codecs without a property testing crate
+    pub struct Values(u64);
+
+    impl Values {
+        pub fn new(seed: u64) -> Self {
+            Self(seed.max(1))
+        }
+
+        pub fn u64(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        pub fn below(&mut self, max: usize) -> usize {
+            (self.u64() % max as u64) as usize
+        }
+
+        pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+            let len = self.below(max_len + 1);
+            (0..len).map(|_| self.u64() as u8).collect()
+        }
+
+        pub fn string(&mut self, max_len: usize) -> String {
+            let len = self.below(max_len + 1);
+            (0..len)
+                .map(|_| ['a', 'Z', '0', ' ', 'é', '🎛'][self.below(6)])
+                .collect()
+        }
+    }
+}